@@ -7,4 +7,102 @@ fn main() {
     flags.toggle(vergen::ConstantsFlags::SEMVER_FROM_CARGO_PKG);
     vergen::generate_cargo_keys(vergen::ConstantsFlags::all())
         .expect("Unable to generate the cargo keys!");
+
+    if std::env::var_os("CARGO_FEATURE_BPF_CORE").is_some() {
+        bpf_core::build();
+    }
+}
+
+/// Compiles the CO-RE BPF programs, `src/samplers/<sampler>/bpf.core.c`, into
+/// objects in `OUT_DIR` which are embedded into the binary.
+mod bpf_core {
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const SAMPLERS: &[&str] = &["ext4", "interrupt", "network", "scheduler", "tcp", "xfs"];
+
+    pub fn build() {
+        let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+        // samplers without a CO-RE program are rejected when their BPF
+        // telemetry is enabled
+        println!("cargo:rustc-env=BPF_CORE_SAMPLERS={}", SAMPLERS.join(","));
+
+        println!("cargo:rerun-if-env-changed=VMLINUX_H");
+        println!("cargo:rerun-if-env-changed=LIBBPF_INCLUDE");
+        println!("cargo:rerun-if-changed=src/common/bpf/histogram.h");
+
+        // vmlinux.h may be provided when building for a different kernel than
+        // the one on the build host
+        match env::var("VMLINUX_H") {
+            Ok(path) => {
+                std::fs::copy(&path, out.join("vmlinux.h"))
+                    .unwrap_or_else(|e| panic!("failed to copy {}: {}", path, e));
+            }
+            Err(_) => {
+                let output = Command::new("bpftool")
+                    .args(&[
+                        "btf",
+                        "dump",
+                        "file",
+                        "/sys/kernel/btf/vmlinux",
+                        "format",
+                        "c",
+                    ])
+                    .output()
+                    .expect("failed to run bpftool, which is needed to generate vmlinux.h");
+                if !output.status.success() {
+                    panic!(
+                        "failed to generate vmlinux.h: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                }
+                std::fs::write(out.join("vmlinux.h"), output.stdout)
+                    .expect("failed to write vmlinux.h");
+            }
+        }
+
+        let arch = match env::var("CARGO_CFG_TARGET_ARCH").unwrap().as_str() {
+            "x86_64" => "x86",
+            "aarch64" => "arm64",
+            arch => panic!("unsupported architecture for bpf_core: {}", arch),
+        };
+
+        for sampler in SAMPLERS {
+            let source = format!("src/samplers/{}/bpf.core.c", sampler);
+            println!("cargo:rerun-if-changed={}", source);
+            compile(
+                Path::new(&source),
+                &out.join(format!("{}.bpf.o", sampler)),
+                &out,
+                arch,
+            );
+        }
+    }
+
+    fn compile(source: &Path, object: &Path, out: &Path, arch: &str) {
+        let mut command = Command::new(env::var("CLANG").unwrap_or_else(|_| "clang".to_string()));
+        command
+            .arg("-g")
+            .arg("-O2")
+            .arg("-target")
+            .arg("bpf")
+            .arg(format!("-D__TARGET_ARCH_{}", arch))
+            .arg(format!("-I{}", out.display()))
+            .arg("-Isrc/common/bpf");
+        if let Ok(include) = env::var("LIBBPF_INCLUDE") {
+            command.arg(format!("-I{}", include));
+        }
+        let status = command
+            .arg("-c")
+            .arg(source)
+            .arg("-o")
+            .arg(object)
+            .status()
+            .expect("failed to run clang, which is needed to compile bpf programs");
+        if !status.success() {
+            panic!("failed to compile {}", source.display());
+        }
+    }
 }
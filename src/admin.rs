@@ -0,0 +1,229 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::os::unix::net::UnixListener;
+use std::time::Duration;
+
+use log::LevelFilter;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::common::http::{authorized, bind_unix, parse_request, read_head, respond};
+use crate::common::logger::LogLevels;
+use crate::config::{ConfigHandle, Listen};
+use crate::samplers::*;
+
+/// Timeout for reading a request from, or writing a response to, a client of
+/// the unix domain socket
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Listener {
+    Tcp(Server),
+    Unix(UnixListener),
+}
+
+/// Admin API for controlling the agent at runtime. Samplers may be enabled or
+/// disabled, asked to sample immediately, and the log level may be changed,
+/// overall or for individual modules. Changes to samplers are applied on top
+/// of the config file and persist when it is reloaded. Requests must present
+/// the same bearer token as the HTTP exposition, if one is configured.
+pub struct Admin {
+    common: Common,
+    config: ConfigHandle,
+    listener: Listener,
+    log_levels: LogLevels,
+    registry: Registry,
+    token: Option<String>,
+}
+
+impl Admin {
+    pub fn new(
+        address: Listen,
+        mode: Option<u32>,
+        config: ConfigHandle,
+        common: Common,
+        registry: Registry,
+        log_levels: LogLevels,
+    ) -> Self {
+        let listener = match address {
+            Listen::Tcp(address) => match Server::http(address) {
+                Ok(server) => Listener::Tcp(server),
+                Err(_) => {
+                    fatal!("Failed to open {} for admin listener", address);
+                }
+            },
+            Listen::Unix(path) => match bind_unix(&path, mode) {
+                Ok(listener) => Listener::Unix(listener),
+                Err(e) => {
+                    fatal!(
+                        "Failed to open {} for admin listener: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            },
+        };
+        let token = match config.current().exposition().http().token() {
+            Ok(token) => token,
+            Err(e) => {
+                fatal!("Failed to read HTTP bearer token: {}", e);
+            }
+        };
+        Self {
+            common,
+            config,
+            listener,
+            log_levels,
+            registry,
+            token,
+        }
+    }
+
+    pub fn run(&mut self) {
+        match self.listener {
+            Listener::Tcp(ref server) => {
+                if let Ok(request) = server.recv() {
+                    let url = request.url().split('?').next().unwrap_or("").to_string();
+                    let authorization = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Authorization"))
+                        .map(|h| h.value.as_str().to_string());
+                    let (status, body) = self.handle(request.method(), &url, authorization);
+                    let mut response = Response::from_string(body).with_status_code(status);
+                    if status == 401 {
+                        response.add_header(
+                            Header::from_bytes(&b"WWW-Authenticate"[..], &b"Bearer"[..]).unwrap(),
+                        );
+                    }
+                    let _ = request.respond(response);
+                }
+            }
+            Listener::Unix(ref listener) => {
+                if let Ok((mut stream, _)) = listener.accept() {
+                    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+                    let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+                    let request = read_head(&mut stream).and_then(|head| parse_request(&head));
+                    let (status, body) = match request {
+                        Some((method, url, authorization)) => match method.parse::<Method>() {
+                            Ok(method) => self.handle(
+                                &method,
+                                url.split('?').next().unwrap_or(""),
+                                authorization,
+                            ),
+                            Err(_) => (404, String::new()),
+                        },
+                        None => (400, String::new()),
+                    };
+                    let headers = if status == 401 {
+                        vec![("WWW-Authenticate", "Bearer")]
+                    } else {
+                        Vec::new()
+                    };
+                    respond(&mut stream, status, &headers, body.as_bytes());
+                }
+            }
+        }
+    }
+
+    /// Rejects the request if it doesn't present the bearer token, otherwise
+    /// routes it
+    fn handle(&self, method: &Method, url: &str, authorization: Option<String>) -> (u16, String) {
+        if let Some(ref token) = self.token {
+            if !authorized(token, authorization.as_deref()) {
+                debug!("rejecting unauthorized admin request for: {}", url);
+                return (401, String::new());
+            }
+        }
+        self.route(method, url)
+    }
+
+    /// Returns the status code and body of the response for a request
+    fn route(&self, method: &Method, url: &str) -> (u16, String) {
+        let parts: Vec<&str> = url.trim_matches('/').split('/').collect();
+        match (method, parts.as_slice()) {
+            (Method::Get, ["samplers"]) => (200, self.samplers()),
+            (Method::Post, ["samplers", name, "enable"]) => self.set_enabled(name, true),
+            (Method::Post, ["samplers", name, "disable"]) => self.set_enabled(name, false),
+            (Method::Post, ["samplers", name, "sample"]) => {
+                if self.registry.trigger(name) {
+                    (200, format!("sampling {}\n", name))
+                } else {
+                    (404, format!("{} sampler is not running\n", name))
+                }
+            }
+            (Method::Get, ["config"]) => (200, format!("{:#?}\n", self.config.current())),
+            (Method::Get, ["logging"]) => (200, self.logging()),
+            (Method::Put, ["logging", level]) | (Method::Post, ["logging", level]) => {
+                match level.parse::<LevelFilter>() {
+                    Ok(level) => {
+                        self.log_levels.set_level(level);
+                        info!("log level set to {}", level);
+                        (200, format!("{}\n", level.to_string().to_lowercase()))
+                    }
+                    Err(_) => (400, format!("unknown log level: {}\n", level)),
+                }
+            }
+            (Method::Put, ["logging", module, level])
+            | (Method::Post, ["logging", module, level]) => match level.parse::<LevelFilter>() {
+                Ok(level) => {
+                    self.log_levels.set_module(module, Some(level));
+                    info!("log level of {} set to {}", module, level);
+                    (
+                        200,
+                        format!("{}={}\n", module, level.to_string().to_lowercase()),
+                    )
+                }
+                Err(_) => (400, format!("unknown log level: {}\n", level)),
+            },
+            (Method::Delete, ["logging", module]) => {
+                self.log_levels.set_module(module, None);
+                info!("log level of {} reset", module);
+                (200, format!("{} uses the log level\n", module))
+            }
+            _ => (404, String::new()),
+        }
+    }
+
+    /// The log level, followed by `<module>=<level>` for each module which has
+    /// its own level
+    fn logging(&self) -> String {
+        let mut body = format!("{}\n", self.log_levels.level().to_string().to_lowercase());
+        for (module, level) in self.log_levels.modules() {
+            body += &format!("{}={}\n", module, level.to_string().to_lowercase());
+        }
+        body
+    }
+
+    /// Lists each sampler, whether it is enabled, and whether it has been
+    /// spawned
+    fn samplers(&self) -> String {
+        let config = self.config.current();
+        let mut samplers = json::JsonValue::new_object();
+        for name in sampler_names() {
+            let mut sampler = json::JsonValue::new_object();
+            sampler["enabled"] = sampler_enabled(&config, name).unwrap_or(false).into();
+            sampler["spawned"] = self.registry.is_spawned(name).into();
+            samplers[name] = sampler;
+        }
+        samplers.pretty(2) + "\n"
+    }
+
+    fn set_enabled(&self, name: &str, enabled: bool) -> (u16, String) {
+        if sampler_enabled(&self.config.current(), name).is_none() {
+            return (404, format!("unknown sampler: {}\n", name));
+        }
+        if let Err(e) = self.config.set_sampler_enabled(name, enabled) {
+            error!("failed to update config: {}", e);
+            return (500, format!("failed to update config: {}\n", e));
+        }
+        let state = if enabled { "enabled" } else { "disabled" };
+        info!("{} sampler {}", name, state);
+        // samplers which are disabled keep running, but skip sampling until
+        // they are enabled again
+        let mut common = self.common.clone();
+        common.refresh_config();
+        spawn_samplers(&common, &self.registry);
+        (200, format!("{} sampler {}\n", name, state))
+    }
+}
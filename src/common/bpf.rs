@@ -7,10 +7,106 @@ pub struct BPF {
     pub inner: bcc::BPF,
 }
 
-#[cfg(not(feature = "bpf"))]
+/// BPF programs compiled ahead of time against BTF type information and loaded
+/// with libbpf. The object is embedded in the binary so no compiler or kernel
+/// headers are needed at runtime.
+#[cfg(feature = "bpf_core")]
+pub struct BPF {
+    object: libbpf_rs::Object,
+    _links: Vec<libbpf_rs::Link>,
+}
+
+// the object is only accessed while holding the sampler's mutex
+#[cfg(feature = "bpf_core")]
+unsafe impl Send for BPF {}
+
+#[cfg(not(any(feature = "bpf", feature = "bpf_core")))]
 pub struct BPF {}
 
 #[cfg(feature = "bpf")]
+impl BPF {
+    /// Returns the contents of a histogram table, keyed by bucket value, and
+    /// clears it
+    pub fn histogram(&self, table: &str) -> Option<std::collections::HashMap<u64, u32>> {
+        let mut table = self.inner.table(table).ok()?;
+        Some(map_from_table(&mut table))
+    }
+
+    /// Returns the contents of an array of counters, keyed by index
+    pub fn counters(&self, table: &str) -> Option<std::collections::HashMap<u32, u64>> {
+        let table = self.inner.table(table).ok()?;
+        Some(perf_table_to_map(&table))
+    }
+}
+
+#[cfg(feature = "bpf_core")]
+impl BPF {
+    /// Opens and loads a CO-RE object, then attaches each of its programs.
+    /// Programs named in `skip` are not loaded, which allows an object to
+    /// carry alternatives for probes which only exist on some kernels.
+    pub fn load(name: &str, object: &[u8], skip: &[&str]) -> Result<Self, anyhow::Error> {
+        let mut open = libbpf_rs::ObjectBuilder::default().open_memory(name, object)?;
+        for program in skip {
+            if let Some(program) = open.prog_mut(program)? {
+                program.set_autoload(false)?;
+            }
+        }
+        let mut object = open.load()?;
+        let mut links = Vec::new();
+        for program in object.progs_iter_mut() {
+            if skip.contains(&program.name()) {
+                continue;
+            }
+            links.push(program.attach()?);
+        }
+        Ok(Self {
+            object,
+            _links: links,
+        })
+    }
+
+    /// Returns the contents of a histogram table, keyed by bucket value, and
+    /// clears it
+    pub fn histogram(&self, table: &str) -> Option<std::collections::HashMap<u64, u32>> {
+        use libbpf_rs::MapFlags;
+
+        let map = self.object.map(table).ok()??;
+        let mut current = std::collections::HashMap::new();
+
+        trace!("transferring data to userspace");
+        for index in 0..=460_u32 {
+            let key = index.to_ne_bytes();
+            if let Ok(Some(value)) = map.lookup(&key, MapFlags::ANY) {
+                let value = parse_u64(value);
+                if value == 0 {
+                    continue;
+                }
+                if let Some(key) = key_to_value(index as u64) {
+                    current.insert(key, value as u32);
+                }
+                // clear the source counter
+                let _ = map.update(&key, &[0_u8; 8], MapFlags::ANY);
+            }
+        }
+        Some(current)
+    }
+
+    /// Returns the contents of an array of counters, keyed by index
+    pub fn counters(&self, table: &str) -> Option<std::collections::HashMap<u32, u64>> {
+        use libbpf_rs::MapFlags;
+
+        let map = self.object.map(table).ok()??;
+        let mut current = std::collections::HashMap::new();
+        for key in map.keys() {
+            if let Ok(Some(value)) = map.lookup(&key, MapFlags::ANY) {
+                current.insert(parse_u32(key), parse_u64(value));
+            }
+        }
+        Some(current)
+    }
+}
+
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
 pub fn key_to_value(index: u64) -> Option<u64> {
     let index = index;
     if index < 100 {
@@ -29,7 +125,7 @@ pub fn key_to_value(index: u64) -> Option<u64> {
 }
 
 // TODO: a result is probably more appropriate
-#[cfg(feature = "bpf")]
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
 pub fn symbol_lookup(name: &str) -> Option<String> {
     use std::fs::File;
     use std::io::prelude::*;
@@ -124,7 +220,7 @@ pub fn bpf_hash_char_to_map(table: &bcc::table::Table) -> std::collections::Hash
     map
 }
 
-#[cfg(feature = "bpf")]
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
 pub fn parse_u32(x: Vec<u8>) -> u32 {
     let mut v = [0_u8; 4];
     for (i, byte) in v.iter_mut().enumerate() {
@@ -134,7 +230,7 @@ pub fn parse_u32(x: Vec<u8>) -> u32 {
     u32::from_ne_bytes(v)
 }
 
-#[cfg(feature = "bpf")]
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
 pub fn parse_u64(x: Vec<u8>) -> u64 {
     let mut v = [0_u8; 8];
     for (i, byte) in v.iter_mut().enumerate() {
@@ -151,3 +247,14 @@ pub fn parse_string(x: &[u8]) -> String {
         None => String::from_utf8_lossy(x).to_string(),
     }
 }
+
+/// Checks whether a tracepoint exists on the running kernel
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
+pub fn tracepoint_exists(category: &str, name: &str) -> bool {
+    [
+        "/sys/kernel/tracing/events",
+        "/sys/kernel/debug/tracing/events",
+    ]
+    .iter()
+    .any(|root| std::path::Path::new(&format!("{}/{}/{}", root, category, name)).exists())
+}
@@ -0,0 +1,81 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Maps and helpers shared by the CO-RE BPF programs. Histograms have the same
+// layout as BPF_HISTOGRAM in the BCC programs, an array of 461 buckets indexed
+// by value_to_index2(), so that userspace can read either of them the same way.
+
+#ifndef REZOLUS_HISTOGRAM_H
+#define REZOLUS_HISTOGRAM_H
+
+#define HISTOGRAM_BUCKETS 461
+
+#define HISTOGRAM(name)                                 \
+    struct {                                            \
+        __uint(type, BPF_MAP_TYPE_ARRAY);               \
+        __uint(max_entries, HISTOGRAM_BUCKETS);         \
+        __type(key, u32);                               \
+        __type(value, u64);                             \
+    } name SEC(".maps")
+
+#define COUNTER(name)                                   \
+    struct {                                            \
+        __uint(type, BPF_MAP_TYPE_ARRAY);               \
+        __uint(max_entries, 1);                         \
+        __type(key, u32);                               \
+        __type(value, u64);                             \
+    } name SEC(".maps")
+
+#define COUNTERS(name, entries)                         \
+    struct {                                            \
+        __uint(type, BPF_MAP_TYPE_ARRAY);               \
+        __uint(max_entries, entries);                   \
+        __type(key, u32);                               \
+        __type(value, u64);                             \
+    } name SEC(".maps")
+
+#define HASH(name, key_type, value_type)                \
+    struct {                                            \
+        __uint(type, BPF_MAP_TYPE_HASH);                \
+        __uint(max_entries, 65536);                     \
+        __type(key, key_type);                          \
+        __type(value, value_type);                      \
+    } name SEC(".maps")
+
+// histogram indexing
+static __always_inline u32 value_to_index2(u64 value)
+{
+    if (value < 100) {
+        // 0-99 => [0..100)
+        return value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        return 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        return 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        return 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        return 360 + value / 10000;
+    }
+    return 460;
+}
+
+static __always_inline void increment(void *map, u32 index)
+{
+    u64 *count = bpf_map_lookup_elem(map, &index);
+    if (count) {
+        __sync_fetch_and_add(count, 1);
+    }
+}
+
+static __always_inline void histogram_add(void *map, u64 value)
+{
+    increment(map, value_to_index2(value));
+}
+
+#endif
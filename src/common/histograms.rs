@@ -0,0 +1,175 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Cumulative histograms of selected distributions, which are exported as
+//! Prometheus histograms alongside the percentiles. Unlike percentiles, the
+//! buckets of histograms from many hosts can be summed, so that latency can be
+//! aggregated correctly. Each histogram is exported under the name of its
+//! distribution with a `_histogram` suffix, as the percentiles are already
+//! exported under the name itself.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::common::labels;
+use crate::config::wildcard;
+
+// bucket `i` counts values of at most 2^i, with a final bucket for the rest
+const BUCKETS: usize = 65;
+
+/// Keeps a histogram for each distribution whose name matches one of the
+/// patterns. Nothing is recorded if there are no patterns.
+pub struct Histograms {
+    patterns: Vec<String>,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// whether each name which has been recorded matches the patterns
+    matched: HashMap<String, bool>,
+    histograms: BTreeMap<String, Histogram>,
+}
+
+struct Histogram {
+    buckets: [u64; BUCKETS],
+    sum: u64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            sum: 0,
+            count: 0,
+        }
+    }
+}
+
+impl Histograms {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            patterns,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Records `count` occurrences of a value of the named distribution
+    pub fn record(&self, name: &str, value: u64, count: u32) {
+        if self.patterns.is_empty() || count == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let patterns = &self.patterns;
+        let matched = *inner
+            .matched
+            .entry(name.to_string())
+            .or_insert_with(|| patterns.iter().any(|pattern| wildcard(pattern, name)));
+        if !matched {
+            return;
+        }
+        let histogram = inner.histograms.entry(name.to_string()).or_default();
+        histogram.buckets[bucket(value)] += count as u64;
+        histogram.sum = histogram.sum.wrapping_add(value.wrapping_mul(count as u64));
+        histogram.count += count as u64;
+    }
+
+    /// Returns the histograms in the Prometheus text format. Every histogram
+    /// has the same buckets, each power of two up to 2^63, whether or not
+    /// they have been used, so that the buckets of many hosts line up when
+    /// they are summed. Unless `flat_names` is set, the dimensions of a
+    /// distribution are exported as labels.
+    pub fn prometheus(&self, flat_names: bool) -> String {
+        let inner = self.inner.lock().unwrap();
+        let host = labels::prometheus(&labels::host());
+        let mut families: BTreeMap<String, String> = BTreeMap::new();
+        for (name, histogram) in &inner.histograms {
+            let mut labels = host.clone();
+            let name = match labels::labels(name).filter(|_| !flat_names) {
+                Some(statistic) => {
+                    labels.extend(statistic.prometheus());
+                    format!("{}_histogram", statistic.name.replace('/', "_"))
+                }
+                None => format!("{}_histogram", name.replace('/', "_")),
+            };
+            let series = |extra: Option<String>| {
+                let labels: Vec<String> = labels.iter().cloned().chain(extra).collect();
+                if labels.is_empty() {
+                    String::new()
+                } else {
+                    format!("{{{}}}", labels.join(","))
+                }
+            };
+            let content = families.entry(name.clone()).or_default();
+            let mut cumulative = 0;
+            for (i, count) in histogram.buckets[..BUCKETS - 1].iter().enumerate() {
+                cumulative += count;
+                let le = format!("le=\"{}\"", 1u64 << i);
+                *content += &format!("{}_bucket{} {}\n", name, series(Some(le)), cumulative);
+            }
+            let le = "le=\"+Inf\"".to_string();
+            *content += &format!("{}_bucket{} {}\n", name, series(Some(le)), histogram.count);
+            *content += &format!("{}_sum{} {}\n", name, series(None), histogram.sum);
+            *content += &format!("{}_count{} {}\n", name, series(None), histogram.count);
+        }
+        let mut content = String::new();
+        for (name, samples) in families {
+            content += &format!("# TYPE {} histogram\n", name);
+            content += &samples;
+        }
+        content
+    }
+}
+
+/// The index of the smallest power of two bucket which the value fits in
+fn bucket(value: u64) -> usize {
+    if value <= 1 {
+        0
+    } else {
+        64 - (value - 1).leading_zeros() as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 0);
+        assert_eq!(bucket(2), 1);
+        assert_eq!(bucket(3), 2);
+        assert_eq!(bucket(4), 2);
+        assert_eq!(bucket(1025), 11);
+        assert_eq!(bucket(u64::MAX), 64);
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let histograms = Histograms::new(vec!["disk/*/latency".to_string()]);
+        histograms.record("disk/read/latency", 3, 2);
+        histograms.record("disk/read/latency", 1, 1);
+        histograms.record("disk/read/bytes", 3, 1);
+        let content = histograms.prometheus(false);
+        assert!(content.starts_with(
+            "# TYPE disk_read_latency_histogram histogram\n\
+             disk_read_latency_histogram_bucket{le=\"1\"} 1\n\
+             disk_read_latency_histogram_bucket{le=\"2\"} 1\n\
+             disk_read_latency_histogram_bucket{le=\"4\"} 3\n\
+             disk_read_latency_histogram_bucket{le=\"8\"} 3\n"
+        ));
+        assert!(content.ends_with(
+            "disk_read_latency_histogram_bucket{le=\"9223372036854775808\"} 3\n\
+             disk_read_latency_histogram_bucket{le=\"+Inf\"} 3\n\
+             disk_read_latency_histogram_sum 7\n\
+             disk_read_latency_histogram_count 3\n"
+        ));
+        // the buckets don't depend on the values which have been recorded
+        assert_eq!(content.matches("_bucket{").count(), BUCKETS);
+
+        assert_eq!(Histograms::new(Vec::new()).prometheus(false), "");
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! A minimal HTTP/1.x server for connections which are not handled by
+//! `tiny_http`, such as TLS connections and unix domain sockets. A single
+//! request is served on each connection, which is then closed.
+
+use std::io::{Read, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+
+/// Upper bound on the size of the request line and headers of a request
+const MAX_REQUEST_HEAD: usize = 8192;
+
+/// Binds a unix domain socket, replacing a socket left at the path by a
+/// previous run, and sets the permissions of the socket if a mode is given
+pub fn bind_unix(path: &Path, mode: Option<u32>) -> Result<UnixListener, std::io::Error> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// Reads the request line and headers of a request, returning `None` if the
+/// connection is closed or they are too large
+pub fn read_head<S: Read>(stream: &mut S) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return None,
+            Ok(len) => head.extend_from_slice(&buf[..len]),
+        }
+        if head.len() > MAX_REQUEST_HEAD {
+            return None;
+        }
+    }
+    Some(String::from_utf8_lossy(&head).to_string())
+}
+
+/// Writes a response with the given status, additional headers, and body
+pub fn respond<S: Write>(stream: &mut S, status: u16, headers: &[(&str, &str)], body: &[u8]) {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        reason(status),
+        body.len()
+    );
+    for (name, value) in headers {
+        response += &format!("{}: {}\r\n", name, value);
+    }
+    response += "\r\n";
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.write_all(body);
+}
+
+/// Parses the request line and headers of an HTTP/1.x request, returning the
+/// method, url, and `Authorization` header if present
+pub fn parse_request(head: &str) -> Option<(String, String, Option<String>)> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let url = request_line.next()?.to_string();
+    let mut authorization = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+    Some((method, url, authorization))
+}
+
+/// Returns the value of a header of an HTTP/1.x request, if present
+pub fn header(head: &str, name: &str) -> Option<String> {
+    head.split("\r\n").skip(1).find_map(|line| {
+        let (field, value) = line.split_once(':')?;
+        if field.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Checks the value of an `Authorization` header against the bearer token,
+/// taking the same time regardless of where they differ
+pub fn authorized(token: &str, authorization: Option<&str>) -> bool {
+    let provided = match authorization.and_then(|v| v.strip_prefix("Bearer ")) {
+        Some(provided) => provided.trim().as_bytes(),
+        None => return false,
+    };
+    let expected = token.as_bytes();
+    if provided.len() != expected.len() {
+        return false;
+    }
+    provided
+        .iter()
+        .zip(expected)
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Decodes a query parameter, where `%XX` is an escaped byte and `+` is a
+/// space. Escapes which are not valid are kept as they are.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = value
+                    .get((i + 1)..(i + 3))
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = byte {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+                decoded.push(b'%');
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The reason phrase of a status code
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            parse_request("GET /metrics HTTP/1.1\r\nHost: a\r\nauthorization: Bearer x\r\n\r\n"),
+            Some((
+                "GET".to_string(),
+                "/metrics".to_string(),
+                Some("Bearer x".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_request("GET /vars HTTP/1.1\r\n\r\n"),
+            Some(("GET".to_string(), "/vars".to_string(), None))
+        );
+        assert_eq!(parse_request(""), None);
+    }
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized("secret", Some("Bearer secret")));
+        assert!(!authorized("secret", Some("Bearer secreT")));
+        assert!(!authorized("secret", Some("Basic secret")));
+        assert!(!authorized("secret", None));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("cpu%2Fusage%2F*"), "cpu/usage/*");
+        assert_eq!(percent_decode("a+b%20c"), "a b c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%+1%2"), "%zz% 1%2");
+    }
+
+    #[test]
+    fn test_header() {
+        let head = "GET /metrics HTTP/1.1\r\nHost: a\r\naccept-encoding: gzip, br\r\n\r\n";
+        assert_eq!(
+            header(head, "Accept-Encoding"),
+            Some("gzip, br".to_string())
+        );
+        assert_eq!(header(head, "Authorization"), None);
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Labels of statistics whose names include a dimension, such as a device, a
+//! CPU, or a process. The statistics are registered as they are created, so
+//! that exporters which support labels can export them under a common name
+//! with the dimensions as labels, such as `disk/latency{device="sda"}` rather
+//! than `disk/sda/read/latency`. The labels which describe the host are also
+//! kept here, as they are attached to every exported metric.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static REGISTRY: Mutex<BTreeMap<String, Labels>> = Mutex::new(BTreeMap::new());
+static HOST: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// The name of a statistic without its dimensions, and the dimensions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Labels {
+    pub name: String,
+    pub labels: Vec<(&'static str, String)>,
+}
+
+impl Labels {
+    /// The labels in the Prometheus text format, such as `device="sda"`
+    pub fn prometheus(&self) -> Vec<String> {
+        prometheus(&self.labels)
+    }
+}
+
+/// Registers the labels of a statistic, where `base` is its name without the
+/// labels, and returns the name of the statistic
+pub fn labeled(name: String, base: String, labels: &[(&'static str, &str)]) -> String {
+    let mut registry = REGISTRY.lock().unwrap();
+    if !registry.contains_key(&name) {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+        registry.insert(name.clone(), Labels { name: base, labels });
+    }
+    name
+}
+
+/// Returns the labels of the named statistic, if it has any
+pub fn labels(name: &str) -> Option<Labels> {
+    REGISTRY.lock().unwrap().get(name).cloned()
+}
+
+/// Forgets the labels of a statistic which is no longer exported
+pub fn remove(name: &str) {
+    REGISTRY.lock().unwrap().remove(name);
+}
+
+/// Sets the labels which describe the host, before the exporters start
+pub fn set_host(labels: Vec<(String, String)>) {
+    *HOST.lock().unwrap() = labels;
+}
+
+/// Returns the labels which describe the host
+pub fn host() -> Vec<(String, String)> {
+    HOST.lock().unwrap().clone()
+}
+
+/// Formats labels in the Prometheus text format, such as `device="sda"`
+pub fn prometheus<K: AsRef<str>>(labels: &[(K, String)]) -> Vec<String> {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key.as_ref(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_labeled() {
+        let name = labeled(
+            "test/sda/read/latency".to_string(),
+            "test/latency".to_string(),
+            &[("device", "sda"), ("operation", "read")],
+        );
+        assert_eq!(name, "test/sda/read/latency");
+        assert_eq!(
+            labels(&name),
+            Some(Labels {
+                name: "test/latency".to_string(),
+                labels: vec![
+                    ("device", "sda".to_string()),
+                    ("operation", "read".to_string())
+                ],
+            })
+        );
+        assert_eq!(
+            labels(&name).unwrap().prometheus(),
+            vec!["device=\"sda\"", "operation=\"read\""]
+        );
+        assert_eq!(labels("test/read/latency"), None);
+    }
+}
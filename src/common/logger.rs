@@ -0,0 +1,318 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Log levels which may be changed at runtime, for all modules or for
+//! individual modules, and a logger which writes each record as a line of
+//! JSON, so that the logs can be ingested and queried alongside the metrics.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// module paths within rezolus are given without the crate name
+const CRATE: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+/// Install a logger which filters records by the given levels before passing
+/// them on to `inner`
+pub fn init(inner: Box<dyn Log>, levels: LogLevels) -> Result<(), log::SetLoggerError> {
+    log::set_boxed_logger(Box::new(Filter {
+        inner,
+        levels: levels.clone(),
+    }))?;
+    levels.update_max_level();
+    Ok(())
+}
+
+struct Filter {
+    inner: Box<dyn Log>,
+    levels: LogLevels,
+}
+
+impl Log for Filter {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.levels.enabled(metadata.target(), metadata.level()) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// The log level, and the levels of any modules which override it. Clones
+/// share the same levels.
+#[derive(Clone)]
+pub struct LogLevels {
+    inner: Arc<RwLock<Levels>>,
+}
+
+struct Levels {
+    level: LevelFilter,
+    modules: BTreeMap<String, LevelFilter>,
+}
+
+impl LogLevels {
+    pub fn new(level: LevelFilter) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Levels {
+                level,
+                modules: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// The level of modules which have not been given their own
+    pub fn level(&self) -> LevelFilter {
+        self.inner.read().unwrap().level
+    }
+
+    pub fn set_level(&self, level: LevelFilter) {
+        self.inner.write().unwrap().level = level;
+        self.update_max_level();
+    }
+
+    /// Log one level more verbosely, returning the new level
+    pub fn raise(&self) -> LevelFilter {
+        let level = match self.level() {
+            LevelFilter::Off => LevelFilter::Error,
+            LevelFilter::Error => LevelFilter::Warn,
+            LevelFilter::Warn => LevelFilter::Info,
+            LevelFilter::Info => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        };
+        self.set_level(level);
+        level
+    }
+
+    /// Log one level less verbosely, returning the new level. Errors are
+    /// always logged.
+    pub fn lower(&self) -> LevelFilter {
+        let level = match self.level() {
+            LevelFilter::Trace => LevelFilter::Debug,
+            LevelFilter::Debug => LevelFilter::Info,
+            LevelFilter::Info => LevelFilter::Warn,
+            _ => LevelFilter::Error,
+        };
+        self.set_level(level);
+        level
+    }
+
+    /// The modules which have their own level
+    pub fn modules(&self) -> Vec<(String, LevelFilter)> {
+        let levels = self.inner.read().unwrap();
+        levels
+            .modules
+            .iter()
+            .map(|(module, level)| (module.clone(), *level))
+            .collect()
+    }
+
+    /// Set the level of a module and its submodules, such as
+    /// `samplers::disk`, or remove it so they use the overall level
+    pub fn set_module(&self, module: &str, level: Option<LevelFilter>) {
+        let module = module.strip_prefix(CRATE).unwrap_or(module).to_string();
+        {
+            let mut levels = self.inner.write().unwrap();
+            match level {
+                Some(level) => levels.modules.insert(module, level),
+                None => levels.modules.remove(&module),
+            };
+        }
+        self.update_max_level();
+    }
+
+    /// Returns whether a record from the module at the level is logged. The
+    /// most specific module with its own level applies.
+    fn enabled(&self, path: &str, level: Level) -> bool {
+        let path = path.strip_prefix(CRATE).unwrap_or(path);
+        let levels = self.inner.read().unwrap();
+        let module_level = levels
+            .modules
+            .iter()
+            .filter(|(module, _)| {
+                path == module.as_str()
+                    || (path.starts_with(module.as_str()) && path[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level);
+        level <= module_level.unwrap_or(levels.level)
+    }
+
+    /// Records are only passed to the logger up to the most verbose level
+    fn update_max_level(&self) {
+        let levels = self.inner.read().unwrap();
+        let max = levels
+            .modules
+            .values()
+            .fold(levels.level, |max, level| std::cmp::max(max, *level));
+        log::set_max_level(max);
+    }
+}
+
+/// Writes each record as a line of JSON. Records are filtered by level before
+/// they reach the logger.
+pub struct JsonLogger;
+
+impl Log for JsonLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let line = json_line(
+            SystemTime::now(),
+            record.level(),
+            record.module_path(),
+            &record.args().to_string(),
+        );
+        let _ = writeln!(std::io::stdout().lock(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+fn json_line(time: SystemTime, level: Level, module: Option<&str>, message: &str) -> String {
+    let mut line = json::object! {
+        timestamp: rfc3339(time),
+        level: level.to_string().to_lowercase(),
+    };
+    if let Some(sampler) = module.and_then(sampler) {
+        line["sampler"] = sampler.into();
+    }
+    // warnings and errors are usually logged as `<what failed>: <error>`
+    match message.split_once(": ") {
+        Some((message, error)) if level <= Level::Warn => {
+            line["message"] = message.into();
+            line["error"] = error.into();
+        }
+        _ => {
+            line["message"] = message.into();
+        }
+    }
+    line.dump()
+}
+
+/// Returns the name of the sampler a module belongs to, from a module path of
+/// the form `rezolus::samplers::<sampler>::...`
+fn sampler(module: &str) -> Option<&str> {
+    let mut parts = module.split("::");
+    parts.find(|part| *part == "samplers")?;
+    parts.next()
+}
+
+/// Formats the time in UTC with millisecond precision, such as
+/// `2021-10-15T12:13:20.000Z`
+fn rfc3339(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since.as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    let seconds = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        since.subsec_millis()
+    )
+}
+
+/// Converts days since the unix epoch into a date in the proleptic Gregorian
+/// calendar, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // shift the epoch to 0000-03-01, so that leap days end each 400 year era
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rfc3339() {
+        let time = |millis| UNIX_EPOCH + Duration::from_millis(millis);
+        assert_eq!(rfc3339(time(0)), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(time(1_634_300_000_000)), "2021-10-15T12:13:20.000Z");
+        assert_eq!(rfc3339(time(951_782_400_000)), "2000-02-29T00:00:00.000Z");
+        assert_eq!(rfc3339(time(1_709_164_800_500)), "2024-02-29T00:00:00.500Z");
+    }
+
+    #[test]
+    fn test_log_levels() {
+        let levels = LogLevels::new(LevelFilter::Info);
+        assert!(levels.enabled("rezolus::samplers::disk", Level::Info));
+        assert!(!levels.enabled("rezolus::samplers::disk", Level::Debug));
+
+        levels.set_module("samplers::disk", Some(LevelFilter::Trace));
+        levels.set_module("rezolus::samplers::disk::stat", Some(LevelFilter::Warn));
+        assert!(levels.enabled("rezolus::samplers::disk", Level::Trace));
+        assert!(!levels.enabled("rezolus::samplers::diskstats", Level::Debug));
+        assert!(!levels.enabled("rezolus::samplers::disk::stat", Level::Info));
+        assert_eq!(log::max_level(), LevelFilter::Trace);
+
+        levels.set_module("samplers::disk", None);
+        assert!(!levels.enabled("rezolus::samplers::disk", Level::Debug));
+        assert_eq!(
+            levels.modules(),
+            vec![("samplers::disk::stat".to_string(), LevelFilter::Warn)]
+        );
+
+        assert_eq!(levels.raise(), LevelFilter::Debug);
+        assert_eq!(levels.lower(), LevelFilter::Info);
+        levels.set_level(LevelFilter::Warn);
+        assert_eq!(levels.lower(), LevelFilter::Error);
+        assert_eq!(levels.lower(), LevelFilter::Error);
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = json_line(
+            UNIX_EPOCH,
+            Level::Error,
+            Some("rezolus::samplers::disk"),
+            "failed to open /proc/diskstats: No such file or directory (os error 2)",
+        );
+        let line = json::parse(&line).unwrap();
+        assert_eq!(line["timestamp"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(line["level"], "error");
+        assert_eq!(line["sampler"], "disk");
+        assert_eq!(line["message"], "failed to open /proc/diskstats");
+        assert_eq!(line["error"], "No such file or directory (os error 2)");
+
+        // informational messages are not split, and only samplers are named
+        let line = json_line(
+            UNIX_EPOCH,
+            Level::Info,
+            Some("rezolus"),
+            "reloading: config",
+        );
+        let line = json::parse(&line).unwrap();
+        assert!(line["sampler"].is_null());
+        assert_eq!(line["message"], "reloading: config");
+        assert!(line["error"].is_null());
+    }
+}
@@ -5,12 +5,20 @@
 use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::SeekFrom;
+use std::process::Output;
+use std::time::Duration;
 
 use dashmap::DashMap;
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
 
 pub mod bpf;
+pub mod histograms;
+pub mod http;
+pub mod labels;
+pub mod logger;
+pub mod perf;
+pub mod systemd;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const NAME: &str = env!("CARGO_PKG_NAME");
@@ -20,6 +28,11 @@ pub const MILLISECOND: u64 = 1_000 * MICROSECOND;
 pub const MICROSECOND: u64 = 1_000 * NANOSECOND;
 pub const NANOSECOND: u64 = 1;
 
+/// Time allowed for the command line tools which some samplers read from, so
+/// that a tool which hangs, such as on a failing device, doesn't stall the
+/// sampler until the watchdog restarts it
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct HardwareInfo {
     numa_mapping: DashMap<u64, u64>,
 }
@@ -84,6 +97,21 @@ pub fn hardware_threads() -> Result<u64, ()> {
         .map(|i| i + 1)
 }
 
+/// helper function to run a command and collect its output, killing it if it
+/// doesn't exit within the `COMMAND_TIMEOUT`
+pub async fn command_output(
+    command: &mut tokio::process::Command,
+) -> Result<Output, std::io::Error> {
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+    match tokio::time::timeout(COMMAND_TIMEOUT, command.kill_on_drop(true).output()).await {
+        Ok(output) => output,
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("{} did not exit within {:?}", program, COMMAND_TIMEOUT),
+        )),
+    }
+}
+
 /// helper function to create a nested map from files with the form of
 /// pkey1 lkey1 lkey2 ... lkeyN
 /// pkey1 value1 value2 ... valueN
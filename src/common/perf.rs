@@ -0,0 +1,175 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Opens counters of PMUs which count events for a whole device or socket,
+//! such as the uncore and GPU PMUs, with the `perf_event_open` syscall, see
+//! `linux/perf_event.h`
+
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+
+/// Mirrors the first version of `struct perf_event_attr`, which the kernel
+/// accepts when `size` is set to its length
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// An open counter of an uncore PMU
+pub struct Counter {
+    file: File,
+    /// multiplier to convert the count to the unit of the event
+    scale: f64,
+}
+
+impl Counter {
+    /// Opens a named event of a PMU in `/sys/bus/event_source/devices` on the
+    /// given cpu, which should be one listed in the `cpumask` of the PMU
+    pub fn open(pmu: &Path, event: &str, cpu: u64) -> Result<Self, std::io::Error> {
+        let kind: u32 = parse(&std::fs::read_to_string(pmu.join("type"))?)?;
+        let encoding = std::fs::read_to_string(pmu.join("events").join(event))?;
+        let mut attr = PerfEventAttr {
+            kind,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            ..Default::default()
+        };
+        for term in encoding.trim().split(',') {
+            let (name, value) = term.split_once('=').unwrap_or((term, "1"));
+            let value = u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map_err(|_| invalid(term))?;
+            let format = std::fs::read_to_string(pmu.join("format").join(name))?;
+            let (field, shift) = parse_format(&format).ok_or_else(|| invalid(&format))?;
+            match field {
+                "config" => attr.config |= value << shift,
+                "config1" => attr.config1 |= value << shift,
+                _ => return Err(invalid(&format)),
+            }
+        }
+        let scale = scale(
+            std::fs::read_to_string(pmu.join("events").join(format!("{}.scale", event))).ok(),
+            std::fs::read_to_string(pmu.join("events").join(format!("{}.unit", event))).ok(),
+        );
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                -1,
+                cpu as libc::c_int,
+                -1,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let file = unsafe { File::from_raw_fd(fd as libc::c_int) };
+        Ok(Self { file, scale })
+    }
+
+    /// Returns the count since the counter was opened, multiplied by the scale
+    /// of the event, such as bytes for memory bandwidth events
+    pub fn read(&mut self) -> Result<u64, std::io::Error> {
+        let mut buffer = [0_u8; 8];
+        self.file.read_exact(&mut buffer)?;
+        Ok((u64::from_ne_bytes(buffer) as f64 * self.scale) as u64)
+    }
+}
+
+/// Returns the cpus which counters of a PMU should be opened on, which are
+/// listed in its `cpumask`, such as one cpu for each socket for uncore PMUs
+pub fn cpus(pmu: &Path) -> Result<Vec<u64>, std::io::Error> {
+    let content = std::fs::read_to_string(pmu.join("cpumask"))?;
+    Ok(parse_cpumask(&content))
+}
+
+fn invalid(content: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("unexpected perf event format: {}", content.trim()),
+    )
+}
+
+fn parse<T: std::str::FromStr>(content: &str) -> Result<T, std::io::Error> {
+    content.trim().parse().map_err(|_| invalid(content))
+}
+
+/// Parses a cpu list, such as `0,28` or `0-3`, into the cpu ids
+fn parse_cpumask(content: &str) -> Vec<u64> {
+    let mut cpus = Vec::new();
+    for range in content.trim().split(',').filter(|r| !r.is_empty()) {
+        let mut bounds = range.splitn(2, '-').filter_map(|b| b.parse::<u64>().ok());
+        if let Some(low) = bounds.next() {
+            let high = bounds.next().unwrap_or(low);
+            cpus.extend(low..=high);
+        }
+    }
+    cpus
+}
+
+/// Parses a PMU format file, such as `config:8-15`, into the field of
+/// `perf_event_attr` and the shift of the value within it
+fn parse_format(content: &str) -> Option<(&str, u64)> {
+    let (field, bits) = content.trim().split_once(':')?;
+    let low = bits.split('-').next()?.parse().ok()?;
+    Some((field, low))
+}
+
+/// Returns the multiplier which converts a count to bytes from the scale and
+/// unit of an event, such as `6.103515625e-5` and `MiB` for a count of 64 byte
+/// cache lines
+fn scale(scale: Option<String>, unit: Option<String>) -> f64 {
+    let scale = scale.and_then(|s| s.trim().parse().ok()).unwrap_or(1.0_f64);
+    let unit = match unit.as_deref().map(|u| u.trim()) {
+        Some("KiB") => 1024.0,
+        Some("MiB") => 1024.0 * 1024.0,
+        Some("GiB") => 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    scale * unit
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpumask() {
+        assert_eq!(parse_cpumask("0,28\n"), vec![0, 28]);
+        assert_eq!(parse_cpumask("0-2,8\n"), vec![0, 1, 2, 8]);
+        assert!(parse_cpumask("\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format("config:0-7\n"), Some(("config", 0)));
+        assert_eq!(parse_format("config:8-15\n"), Some(("config", 8)));
+        assert_eq!(parse_format("config1:0\n"), Some(("config1", 0)));
+        assert_eq!(parse_format("config"), None);
+    }
+
+    #[test]
+    fn test_scale() {
+        let scale = scale(
+            Some("6.103515625e-5\n".to_string()),
+            Some("MiB\n".to_string()),
+        );
+        assert!((scale - 64.0).abs() < f64::EPSILON);
+        assert!((super::scale(None, None) - 1.0).abs() < f64::EPSILON);
+    }
+}
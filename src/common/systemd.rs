@@ -0,0 +1,128 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Notifies systemd of the state of the agent when it is run as a service
+//! with `Type=notify`. Nothing is sent unless systemd has set `NOTIFY_SOCKET`.
+
+use std::io::{Error, ErrorKind};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+/// Tells systemd that the agent has started
+pub fn ready() {
+    notify("READY=1");
+}
+
+/// Tells systemd that the agent is stopping
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Tells systemd that the agent is still healthy
+pub fn watchdog() {
+    notify("WATCHDOG=1");
+}
+
+/// Returns how often systemd expects watchdog notifications, if the service
+/// has `WatchdogSec=` set
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+fn notify(state: &str) {
+    if let Ok(path) = std::env::var("NOTIFY_SOCKET") {
+        if let Err(e) = send(&path, state) {
+            debug!("failed to notify systemd of {}: {}", state, e);
+        }
+    }
+}
+
+fn send(path: &str, state: &str) -> Result<(), Error> {
+    let socket = UnixDatagram::unbound()?;
+    let name = match path.strip_prefix('@') {
+        Some(name) => name.as_bytes(),
+        None => {
+            socket.send_to(state.as_bytes(), path)?;
+            return Ok(());
+        }
+    };
+
+    // the socket is in the abstract namespace, which can't be addressed with
+    // std, its address is the name following a leading nul byte
+    let mut address: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    if name.len() >= address.sun_path.len() {
+        return Err(Error::new(ErrorKind::InvalidInput, "socket name too long"));
+    }
+    address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (i, byte) in name.iter().enumerate() {
+        address.sun_path[i + 1] = *byte as libc::c_char;
+    }
+    let length = std::mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+    let ret = unsafe {
+        libc::sendto(
+            socket.as_raw_fd(),
+            state.as_ptr() as *const libc::c_void,
+            state.len(),
+            0,
+            &address as *const libc::sockaddr_un as *const libc::sockaddr,
+            length as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// The watchdog applies to the process named by `WATCHDOG_PID`, or to the main
+/// process of the service if it is unset
+fn parse_watchdog(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok()? != own_pid {
+            return None;
+        }
+    }
+    match usec?.parse().ok()? {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_watchdog() {
+        assert_eq!(
+            parse_watchdog(Some("30000000"), None, 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_watchdog(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_watchdog(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(parse_watchdog(Some("0"), None, 42), None);
+        assert_eq!(parse_watchdog(None, None, 42), None);
+    }
+
+    #[test]
+    fn test_send() {
+        let path = std::env::temp_dir().join(format!("rezolus-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).unwrap();
+        send(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buffer = [0; 16];
+        let length = listener.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..length], b"READY=1");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Admin {
+    #[serde(default)]
+    listen: Option<String>,
+    #[serde(default)]
+    listen_mode: Option<String>,
+}
+
+impl Admin {
+    /// address for the admin API to listen on, either a socket address or a
+    /// `unix:<path>` unix domain socket. The admin API is disabled unless this
+    /// is set.
+    pub fn listen(&self) -> Option<Listen> {
+        self.listen.as_ref().map(|v| Listen::parse(v).unwrap())
+    }
+
+    /// the listen address as configured, before it is resolved
+    pub(super) fn listen_address(&self) -> Option<String> {
+        self.listen.clone()
+    }
+
+    /// permissions of the unix domain socket which is listened on
+    pub fn listen_mode(&self) -> Option<u32> {
+        self.listen_mode.as_ref().map(|v| parse_mode(v).unwrap())
+    }
+
+    /// the socket permissions as configured, before they are parsed
+    pub(super) fn listen_mode_setting(&self) -> Option<String> {
+        self.listen_mode.clone()
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+use rustcommon_atomics::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Graphite {
+    #[serde(default = "default_enabled")]
+    enabled: AtomicBool,
+    #[serde(default = "default_address")]
+    address: String,
+    #[serde(default = "default_interval")]
+    interval: AtomicUsize,
+    #[serde(default = "default_prefix")]
+    prefix: String,
+}
+
+impl Default for Graphite {
+    fn default() -> Graphite {
+        Graphite {
+            enabled: default_enabled(),
+            address: default_address(),
+            interval: default_interval(),
+            prefix: default_prefix(),
+        }
+    }
+}
+
+fn default_enabled() -> AtomicBool {
+    AtomicBool::new(false)
+}
+
+fn default_address() -> String {
+    "127.0.0.1:2003".to_string()
+}
+
+fn default_interval() -> AtomicUsize {
+    AtomicUsize::new(10_000)
+}
+
+fn default_prefix() -> String {
+    "rezolus".to_string()
+}
+
+impl Graphite {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// TCP address of the carbon server or relay
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// interval in ms between flushes
+    pub fn interval(&self) -> usize {
+        self.interval.load(Ordering::Relaxed)
+    }
+
+    /// prefix prepended to every metric path, separated by a `.`
+    pub fn prefix(&self) -> String {
+        self.prefix.clone()
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+
+/// Recent values of selected statistics, which are kept in memory and served
+/// on `/history`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct History {
+    #[serde(default)]
+    statistics: Vec<String>,
+    #[serde(default = "default_window")]
+    window: usize,
+    #[serde(default = "default_resolution")]
+    resolution: usize,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History {
+            statistics: Default::default(),
+            window: default_window(),
+            resolution: default_resolution(),
+        }
+    }
+}
+
+fn default_window() -> usize {
+    900
+}
+
+fn default_resolution() -> usize {
+    1000
+}
+
+impl History {
+    /// statistics, or patterns matching them, whose history is kept. No
+    /// history is kept unless this is set.
+    pub fn statistics(&self) -> Vec<String> {
+        self.statistics.clone()
+    }
+
+    /// seconds of history which are kept
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// interval in ms between the values which are kept
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Http {
+    #[serde(default)]
+    certificate: Option<String>,
+    #[serde(default)]
+    private_key: Option<String>,
+    #[serde(default)]
+    client_ca: Option<String>,
+    #[serde(default)]
+    compression: Option<bool>,
+    #[serde(default)]
+    histograms: Vec<String>,
+    #[serde(default)]
+    token: Option<Secret<String>>,
+    #[serde(default)]
+    token_file: Option<String>,
+}
+
+impl Http {
+    /// PEM certificate chain served by the listener. TLS is enabled when both
+    /// the certificate and private key are set.
+    pub fn certificate(&self) -> Option<String> {
+        self.certificate.clone()
+    }
+
+    /// PEM private key for the certificate
+    pub fn private_key(&self) -> Option<String> {
+        self.private_key.clone()
+    }
+
+    /// PEM bundle of CAs used to verify client certificates. When set, clients
+    /// must present a certificate signed by one of these CAs.
+    pub fn client_ca(&self) -> Option<String> {
+        self.client_ca.clone()
+    }
+
+    /// Whether responses are compressed for clients which accept gzip or
+    /// deflate encoding, which is the default
+    pub fn compression(&self) -> bool {
+        self.compression.unwrap_or(true)
+    }
+
+    /// Statistics, or patterns matching them, which are also exported as
+    /// Prometheus histograms
+    pub fn histograms(&self) -> Vec<String> {
+        self.histograms.clone()
+    }
+
+    /// Bearer token which requests must present in their `Authorization`
+    /// header. Read from `token_file` if not set directly.
+    pub fn token(&self) -> Result<Option<String>, std::io::Error> {
+        if let Some(ref token) = self.token {
+            return Ok(Some(token.get().clone()));
+        }
+        match self.token_file {
+            Some(ref file) => Ok(Some(std::fs::read_to_string(file)?.trim().to_string())),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+
+use crate::config::*;
+use rustcommon_atomics::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Influxdb {
+    #[serde(default = "default_enabled")]
+    enabled: AtomicBool,
+    #[serde(default = "default_url")]
+    url: String,
+    #[serde(default)]
+    org: Option<String>,
+    #[serde(default)]
+    bucket: Option<String>,
+    #[serde(default)]
+    token: Option<Secret<String>>,
+    #[serde(default)]
+    token_file: Option<String>,
+    #[serde(default = "default_interval")]
+    interval: AtomicUsize,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+impl Default for Influxdb {
+    fn default() -> Influxdb {
+        Influxdb {
+            enabled: default_enabled(),
+            url: default_url(),
+            org: Default::default(),
+            bucket: Default::default(),
+            token: Default::default(),
+            token_file: Default::default(),
+            interval: default_interval(),
+            tags: Default::default(),
+        }
+    }
+}
+
+fn default_enabled() -> AtomicBool {
+    AtomicBool::new(false)
+}
+
+fn default_url() -> String {
+    "http://localhost:8086".to_string()
+}
+
+fn default_interval() -> AtomicUsize {
+    AtomicUsize::new(10_000)
+}
+
+impl Influxdb {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// base URL of the InfluxDB server, which the v2 write API is under
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// organization which owns the bucket
+    pub fn org(&self) -> Option<String> {
+        self.org.clone()
+    }
+
+    /// bucket which metrics are written to
+    pub fn bucket(&self) -> Option<String> {
+        self.bucket.clone()
+    }
+
+    /// API token sent in the `Authorization` header of each write. Read from
+    /// `token_file` if not set directly.
+    pub fn token(&self) -> Result<Option<String>, std::io::Error> {
+        if let Some(ref token) = self.token {
+            return Ok(Some(token.get().clone()));
+        }
+        match self.token_file {
+            Some(ref file) => Ok(Some(std::fs::read_to_string(file)?.trim().to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// interval in ms between writes
+    pub fn interval(&self) -> usize {
+        self.interval.load(Ordering::Relaxed)
+    }
+
+    /// tags attached to every point, which take precedence over the labels
+    /// of the host
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+}
@@ -5,6 +5,35 @@
 use crate::config::*;
 use rustcommon_atomics::*;
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KafkaFormat {
+    /// the JSON format, in the configured version
+    Json,
+    /// the `Snapshot` message of `proto/rezolus.proto`
+    Protobuf,
+}
+
+impl Default for KafkaFormat {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KafkaCompression {
+    None,
+    Gzip,
+    Snappy,
+}
+
+impl Default for KafkaCompression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Kafka {
@@ -14,6 +43,14 @@ pub struct Kafka {
     interval: AtomicUsize,
     hosts: Vec<String>,
     topic: Option<String>,
+    #[serde(default = "default_version")]
+    version: u64,
+    #[serde(default)]
+    format: KafkaFormat,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+    #[serde(default)]
+    compression: KafkaCompression,
 }
 
 impl Default for Kafka {
@@ -23,6 +60,10 @@ impl Default for Kafka {
             interval: default_interval(),
             hosts: Default::default(),
             topic: Default::default(),
+            version: default_version(),
+            format: Default::default(),
+            batch_size: default_batch_size(),
+            compression: Default::default(),
         }
     }
 }
@@ -35,6 +76,14 @@ fn default_interval() -> AtomicUsize {
     AtomicUsize::new(500)
 }
 
+fn default_version() -> u64 {
+    1
+}
+
+fn default_batch_size() -> usize {
+    1
+}
+
 #[cfg(feature = "push_kafka")]
 impl Kafka {
     pub fn enabled(&self) -> bool {
@@ -52,4 +101,24 @@ impl Kafka {
     pub fn topic(&self) -> Option<String> {
         self.topic.clone()
     }
+
+    /// version of the JSON format of the records
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// whether records are JSON or protobuf
+    pub fn format(&self) -> KafkaFormat {
+        self.format
+    }
+
+    /// number of snapshots which are buffered and produced together
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// compression of the message sets sent to the brokers
+    pub fn compression(&self) -> KafkaCompression {
+        self.compression
+    }
 }
@@ -0,0 +1,58 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+
+use crate::config::*;
+
+/// Labels which describe this host and are attached to every exported metric
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Labels {
+    #[serde(default)]
+    hostname: bool,
+    #[serde(default)]
+    cloud: bool,
+    #[serde(default)]
+    datacenter: Option<String>,
+    #[serde(default)]
+    rack: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    instance_id: Option<String>,
+    #[serde(default)]
+    custom: HashMap<String, String>,
+}
+
+impl Labels {
+    /// whether to add a `hostname` label with the name of the host
+    pub fn hostname(&self) -> bool {
+        self.hostname
+    }
+
+    /// whether to add labels from the instance metadata service when running
+    /// in AWS, GCP, or Azure
+    pub fn cloud(&self) -> bool {
+        self.cloud
+    }
+
+    /// the labels which are configured, other than those which are detected,
+    /// with the custom labels taking precedence
+    pub fn configured(&self) -> Vec<(String, String)> {
+        let mut labels: Vec<(String, String)> = vec![
+            ("datacenter", &self.datacenter),
+            ("rack", &self.rack),
+            ("role", &self.role),
+            ("instance_id", &self.instance_id),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.clone().map(|value| (key.to_string(), value)))
+        .filter(|(key, _)| !self.custom.contains_key(key))
+        .collect();
+        labels.extend(self.custom.iter().map(|(k, v)| (k.clone(), v.clone())));
+        labels.sort();
+        labels
+    }
+}
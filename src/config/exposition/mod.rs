@@ -4,20 +4,97 @@
 
 use serde_derive::*;
 
+mod graphite;
+mod history;
+mod http;
+mod influxdb;
 mod kafka;
+mod labels;
+mod otlp;
+mod recorder;
+mod statsd;
 
+use self::graphite::*;
+use self::history::*;
+use self::http::*;
+use self::influxdb::*;
 use self::kafka::*;
+use self::otlp::*;
+use self::recorder::*;
+use self::statsd::*;
+
+#[cfg(feature = "push_kafka")]
+pub use self::kafka::{KafkaCompression, KafkaFormat};
+pub use self::labels::Labels;
+pub use self::statsd::StatsdFormat;
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Exposition {
+    #[serde(default)]
+    flat_names: bool,
+    #[serde(default)]
+    graphite: Graphite,
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    http: Http,
+    #[serde(default)]
+    influxdb: Influxdb,
     #[serde(default)]
     kafka: Kafka,
+    #[serde(default)]
+    labels: Labels,
+    #[serde(default)]
+    otlp: Otlp,
+    #[serde(default)]
+    recorder: Recorder,
+    #[serde(default)]
+    statsd: Statsd,
 }
 
 impl Exposition {
+    /// Whether statistics with dimensions, such as a device, are exported
+    /// with the dimensions in their names rather than as labels
+    pub fn flat_names(&self) -> bool {
+        self.flat_names
+    }
+
+    pub fn graphite(&self) -> &Graphite {
+        &self.graphite
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn http(&self) -> &Http {
+        &self.http
+    }
+
+    pub fn influxdb(&self) -> &Influxdb {
+        &self.influxdb
+    }
+
     #[cfg(feature = "push_kafka")]
     pub fn kafka(&self) -> &Kafka {
         &self.kafka
     }
+
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    #[cfg(feature = "push_otlp")]
+    pub fn otlp(&self) -> &Otlp {
+        &self.otlp
+    }
+
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    pub fn statsd(&self) -> &Statsd {
+        &self.statsd
+    }
 }
@@ -0,0 +1,75 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+
+use crate::config::*;
+use rustcommon_atomics::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Otlp {
+    #[serde(default = "default_enabled")]
+    enabled: AtomicBool,
+    #[serde(default = "default_endpoint")]
+    endpoint: String,
+    #[serde(default = "default_interval")]
+    interval: AtomicUsize,
+    #[serde(default)]
+    headers: Secret<HashMap<String, String>>,
+    #[serde(default)]
+    resource_attributes: HashMap<String, String>,
+}
+
+impl Default for Otlp {
+    fn default() -> Otlp {
+        Otlp {
+            enabled: default_enabled(),
+            endpoint: default_endpoint(),
+            interval: default_interval(),
+            headers: Default::default(),
+            resource_attributes: Default::default(),
+        }
+    }
+}
+
+fn default_enabled() -> AtomicBool {
+    AtomicBool::new(false)
+}
+
+fn default_endpoint() -> String {
+    "http://localhost:4317".to_string()
+}
+
+fn default_interval() -> AtomicUsize {
+    AtomicUsize::new(60_000)
+}
+
+#[cfg(feature = "push_otlp")]
+impl Otlp {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// gRPC endpoint of the OpenTelemetry collector
+    pub fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    /// interval in ms between exports
+    pub fn interval(&self) -> usize {
+        self.interval.load(Ordering::Relaxed)
+    }
+
+    /// additional gRPC metadata sent with each export request, typically
+    /// used for authentication
+    pub fn headers(&self) -> &HashMap<String, String> {
+        self.headers.get()
+    }
+
+    /// attributes attached to the resource describing this host
+    pub fn resource_attributes(&self) -> &HashMap<String, String> {
+        &self.resource_attributes
+    }
+}
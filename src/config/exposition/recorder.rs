@@ -0,0 +1,81 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use crate::config::*;
+use rustcommon_atomics::*;
+
+/// Continuous recording of snapshots to a bounded ring of files on disk
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recorder {
+    #[serde(default = "default_enabled")]
+    enabled: AtomicBool,
+    #[serde(default = "default_directory")]
+    directory: String,
+    #[serde(default = "default_interval")]
+    interval: AtomicUsize,
+    #[serde(default = "default_segment_size")]
+    segment_size: u64,
+    #[serde(default = "default_segments")]
+    segments: usize,
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder {
+            enabled: default_enabled(),
+            directory: default_directory(),
+            interval: default_interval(),
+            segment_size: default_segment_size(),
+            segments: default_segments(),
+        }
+    }
+}
+
+fn default_enabled() -> AtomicBool {
+    AtomicBool::new(false)
+}
+
+fn default_directory() -> String {
+    "/var/lib/rezolus/recording".to_string()
+}
+
+fn default_interval() -> AtomicUsize {
+    AtomicUsize::new(1_000)
+}
+
+fn default_segment_size() -> u64 {
+    16 * 1024 * 1024
+}
+
+fn default_segments() -> usize {
+    8
+}
+
+impl Recorder {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// directory which the segments of the recording are written to
+    pub fn directory(&self) -> String {
+        self.directory.clone()
+    }
+
+    /// interval in ms between snapshots
+    pub fn interval(&self) -> usize {
+        self.interval.load(Ordering::Relaxed)
+    }
+
+    /// size in bytes at which a segment is closed and the next is started
+    pub fn segment_size(&self) -> u64 {
+        self.segment_size
+    }
+
+    /// number of segments which are kept, the oldest being removed as new
+    /// ones are started
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+
+use crate::config::*;
+use rustcommon_atomics::*;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsdFormat {
+    /// plain StatsD, which has no support for tags
+    Statsd,
+    /// DogStatsD, which extends StatsD with tags
+    Dogstatsd,
+}
+
+impl Default for StatsdFormat {
+    fn default() -> Self {
+        Self::Statsd
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Statsd {
+    #[serde(default = "default_enabled")]
+    enabled: AtomicBool,
+    #[serde(default = "default_address")]
+    address: String,
+    #[serde(default)]
+    socket: Option<String>,
+    #[serde(default = "default_interval")]
+    interval: AtomicUsize,
+    #[serde(default)]
+    format: StatsdFormat,
+    #[serde(default = "default_prefix")]
+    prefix: String,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default = "default_max_packet_size")]
+    max_packet_size: usize,
+}
+
+impl Default for Statsd {
+    fn default() -> Statsd {
+        Statsd {
+            enabled: default_enabled(),
+            address: default_address(),
+            socket: Default::default(),
+            interval: default_interval(),
+            format: Default::default(),
+            prefix: default_prefix(),
+            tags: Default::default(),
+            max_packet_size: default_max_packet_size(),
+        }
+    }
+}
+
+fn default_enabled() -> AtomicBool {
+    AtomicBool::new(false)
+}
+
+fn default_address() -> String {
+    "127.0.0.1:8125".to_string()
+}
+
+fn default_interval() -> AtomicUsize {
+    AtomicUsize::new(10_000)
+}
+
+fn default_prefix() -> String {
+    "rezolus".to_string()
+}
+
+fn default_max_packet_size() -> usize {
+    1432
+}
+
+impl Statsd {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// UDP address of the StatsD server
+    pub fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    /// path of a unix datagram socket, which is used instead of the UDP
+    /// address when set
+    pub fn socket(&self) -> Option<String> {
+        self.socket.clone()
+    }
+
+    /// interval in ms between flushes
+    pub fn interval(&self) -> usize {
+        self.interval.load(Ordering::Relaxed)
+    }
+
+    pub fn format(&self) -> StatsdFormat {
+        self.format
+    }
+
+    /// prefix prepended to every metric name, separated by a `.`
+    pub fn prefix(&self) -> String {
+        self.prefix.clone()
+    }
+
+    /// tags attached to every metric, only used with the DogStatsD format
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    /// upper bound on the size of each datagram, in bytes
+    pub fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+}
@@ -6,15 +6,38 @@ use rustcommon_atomics::*;
 
 use crate::config::*;
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// plain text lines
+    Text,
+    /// a JSON object per line
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct General {
     listen: Option<String>,
+    #[serde(default)]
+    listen_mode: Option<String>,
     #[serde(with = "LevelDef")]
     #[serde(default = "default_logging_level")]
     logging: Level,
+    #[serde(default)]
+    log_format: LogFormat,
     #[serde(default = "default_interval")]
     interval: AtomicUsize,
+    #[serde(default)]
+    jitter: usize,
+    #[serde(default)]
+    percentiles: Option<Vec<f64>>,
     #[serde(default = "default_threads")]
     threads: usize,
     #[serde(default = "default_window")]
@@ -23,6 +46,10 @@ pub struct General {
     fault_tolerant: AtomicBool,
     #[serde(default = "default_reading_suffix")]
     reading_suffix: String,
+    #[serde(default = "default_watchdog")]
+    watchdog: usize,
+    #[serde(default = "default_shutdown_timeout")]
+    shutdown_timeout: usize,
 }
 
 impl General {
@@ -30,6 +57,12 @@ impl General {
         self.listen.clone()
     }
 
+    /// permissions, in octal, of the unix domain socket which is listened on
+    /// when `listen` is a `unix:<path>` address
+    pub fn listen_mode(&self) -> Option<String> {
+        self.listen_mode.clone()
+    }
+
     pub fn logging(&self) -> Level {
         self.logging
     }
@@ -38,11 +71,26 @@ impl General {
         self.logging = level;
     }
 
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
     /// interval in ms between samples if no sampler specific interval
     pub fn interval(&self) -> usize {
         self.interval.load(Ordering::Relaxed)
     }
 
+    /// percentage of the interval by which each sample may be delayed, so
+    /// that samplers don't all sample at once, if no sampler specific jitter
+    pub fn jitter(&self) -> usize {
+        self.jitter
+    }
+
+    /// percentiles exported by samplers which don't set their own
+    pub fn percentiles(&self) -> Option<&[f64]> {
+        self.percentiles.as_deref()
+    }
+
     pub fn threads(&self) -> usize {
         self.threads
     }
@@ -63,18 +111,36 @@ impl General {
             Some(&self.reading_suffix)
         }
     }
+
+    /// seconds a sampler may go beyond its interval without sampling before
+    /// it is restarted, or 0 to never restart samplers
+    pub fn watchdog(&self) -> usize {
+        self.watchdog
+    }
+
+    /// seconds to wait for samplers to stop and exporters to flush when
+    /// stopping
+    pub fn shutdown_timeout(&self) -> usize {
+        self.shutdown_timeout
+    }
 }
 
 impl Default for General {
     fn default() -> General {
         General {
             listen: None,
+            listen_mode: None,
             logging: default_logging_level(),
+            log_format: Default::default(),
             interval: default_interval(),
+            jitter: Default::default(),
+            percentiles: Default::default(),
             threads: default_threads(),
             window: default_window(),
             fault_tolerant: default_fault_tolerant(),
             reading_suffix: default_reading_suffix(),
+            watchdog: default_watchdog(),
+            shutdown_timeout: default_shutdown_timeout(),
         }
     }
 }
@@ -99,6 +165,14 @@ fn default_reading_suffix() -> String {
     "count".to_string()
 }
 
+fn default_watchdog() -> usize {
+    60
+}
+
+fn default_shutdown_timeout() -> usize {
+    10
+}
+
 #[derive(Clone, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 #[serde(remote = "Level")]
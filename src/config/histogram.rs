@@ -0,0 +1,36 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+
+/// Parameters of the histograms a sampler keeps for its distributions. Any
+/// which are not set use the sampler's own defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Histogram {
+    #[serde(default)]
+    max: Option<u64>,
+    #[serde(default)]
+    precision: Option<u8>,
+    #[serde(default)]
+    window: Option<usize>,
+}
+
+impl Histogram {
+    /// largest value which can be recorded, larger values are clamped to it
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    /// significant figures kept for each value
+    pub fn precision(&self) -> Option<u8> {
+        self.precision
+    }
+
+    /// seconds of samples which percentiles are calculated over, if not the
+    /// general window
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+}
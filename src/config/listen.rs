@@ -0,0 +1,48 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+
+/// An address for a listener, either a TCP socket address or the path of a
+/// unix domain socket, which is given as `unix:<path>`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Listen {
+    pub fn parse(address: &str) -> Result<Self, String> {
+        if let Some(path) = address.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(format!("{} has no socket path", address));
+            }
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        match address.to_socket_addrs().map(|mut v| v.next()) {
+            Ok(Some(address)) => Ok(Self::Tcp(address)),
+            Ok(None) => Err(format!("{} does not resolve to an address", address)),
+            Err(e) => Err(format!("{} is not a valid address: {}", address, e)),
+        }
+    }
+}
+
+impl std::fmt::Display for Listen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{}", address),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Parses the permissions of a unix domain socket, given in octal such as
+/// `"0660"`
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => Err(format!("{} is not an octal file mode", mode)),
+    }
+}
@@ -2,12 +2,18 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+mod admin;
 mod exposition;
 mod general;
+mod histogram;
+mod listen;
 mod samplers;
+mod secret;
+mod statistics;
 
-use std::io::Read;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use clap::{App, Arg};
 use rustcommon_logger::Level;
@@ -15,9 +21,18 @@ use serde_derive::*;
 
 use crate::*;
 
+use config::admin::Admin;
 use config::exposition::*;
-pub use config::general::General;
+#[cfg(feature = "push_kafka")]
+pub use config::exposition::{KafkaCompression, KafkaFormat};
+pub use config::exposition::{Labels, StatsdFormat};
+pub use config::general::{General, LogFormat};
+pub use config::histogram::Histogram;
+use config::listen::parse_mode;
+pub use config::listen::Listen;
 use config::samplers::*;
+pub use config::secret::Secret;
+pub use config::statistics::deserialize_statistics;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const NAME: &str = env!("CARGO_PKG_NAME");
@@ -25,12 +40,21 @@ pub const NAME: &str = env!("CARGO_PKG_NAME");
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
+    #[serde(default)]
+    admin: Admin,
     #[serde(default)]
     exposition: Exposition,
     #[serde(default)]
     general: General,
     #[serde(default)]
     samplers: Samplers,
+    #[serde(skip)]
+    file: Option<String>,
+    #[serde(skip)]
+    verbose: u64,
+    /// samplers which have been enabled or disabled through the admin API
+    #[serde(skip)]
+    overrides: BTreeMap<String, bool>,
 }
 
 impl Config {
@@ -47,6 +71,32 @@ impl Config {
                     .help("TOML config file")
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("check-config")
+                    .long("check-config")
+                    .value_name("FILE")
+                    .help("Validate the TOML config file and exit without sampling")
+                    .takes_value(true)
+                    .conflicts_with("config"),
+            )
+            .arg(
+                Arg::with_name("dump-recording")
+                    .long("dump-recording")
+                    .value_name("DIRECTORY")
+                    .help("Write the snapshots of a flight recording to stdout and exit")
+                    .takes_value(true)
+                    .conflicts_with_all(&["config", "check-config"]),
+            )
+            .arg(
+                Arg::with_name("dump-format")
+                    .long("dump-format")
+                    .value_name("FORMAT")
+                    .help("Format of the dumped snapshots")
+                    .takes_value(true)
+                    .possible_values(&["json", "csv"])
+                    .default_value("json")
+                    .requires("dump-recording"),
+            )
             .arg(
                 Arg::with_name("verbose")
                     .short("v")
@@ -57,31 +107,87 @@ impl Config {
 
         let matches = app.get_matches();
 
-        let mut config = if let Some(file) = matches.value_of("config") {
+        if let Some(file) = matches.value_of("check-config") {
+            std::process::exit(Config::check_file(file));
+        }
+
+        if let Some(directory) = matches.value_of("dump-recording") {
+            let format = matches.value_of("dump-format").unwrap_or("json");
+            std::process::exit(crate::exposition::dump_recording(directory, format));
+        }
+
+        let mut config: Config = if let Some(file) = matches.value_of("config") {
             Config::load_from_file(file)
         } else {
             println!("NOTE: using builtin base configuration");
-            Default::default()
+            match Config::parse(empty(), BTreeMap::new()) {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("Failed to apply config from environment");
+                    println!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        };
+
+        config.set_verbosity(matches.occurrences_of("verbose"));
+
+        config
+    }
+
+    /// re-read the config file this `Config` was loaded from, keeping any
+    /// options which were provided on the command line or through the admin
+    /// API
+    pub fn reload(&self) -> Result<Config, anyhow::Error> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| format_err!("no config file to reload"))?;
+        self.rebuild(Some(file), self.overrides.clone())
+    }
+
+    /// returns a copy of this `Config` with the named sampler enabled or
+    /// disabled. The config file, if any, is re-read.
+    pub fn with_sampler_enabled(&self, name: &str, enabled: bool) -> Result<Config, anyhow::Error> {
+        let mut overrides = self.overrides.clone();
+        overrides.insert(name.to_string(), enabled);
+        self.rebuild(self.file.as_deref(), overrides)
+    }
+
+    fn rebuild(
+        &self,
+        file: Option<&str>,
+        overrides: BTreeMap<String, bool>,
+    ) -> Result<Config, anyhow::Error> {
+        let mut config = match file {
+            Some(file) => Config::from_file_with_overrides(file, overrides)?,
+            None => Config::parse(empty(), overrides)?,
         };
+        config.set_verbosity(self.verbose);
+        Ok(config)
+    }
 
-        match matches.occurrences_of("verbose") {
+    fn set_verbosity(&mut self, verbose: u64) {
+        self.verbose = verbose;
+        match verbose {
             0 => {} // don't do anything, default is Info
             1 => {
-                if config.general.logging() == Level::Info {
-                    config.general.set_logging(Level::Debug);
+                if self.general.logging() == Level::Info {
+                    self.general.set_logging(Level::Debug);
                 }
             }
-            _ => config.general.set_logging(Level::Trace),
+            _ => self.general.set_logging(Level::Trace),
         }
-
-        config
     }
 
     /// get listen address
-    pub fn listen(&self) -> Option<SocketAddr> {
-        self.general
-            .listen()
-            .map(|v| v.to_socket_addrs().unwrap().next().unwrap())
+    pub fn listen(&self) -> Option<Listen> {
+        self.general.listen().map(|v| Listen::parse(&v).unwrap())
+    }
+
+    /// get the permissions of the listener's unix domain socket, if set
+    pub fn listen_mode(&self) -> Option<u32> {
+        self.general.listen_mode().map(|v| parse_mode(&v).unwrap())
     }
 
     /// get logging level
@@ -89,6 +195,10 @@ impl Config {
         self.general.logging()
     }
 
+    pub fn admin(&self) -> &Admin {
+        &self.admin
+    }
+
     #[allow(dead_code)]
     pub fn exposition(&self) -> &Exposition {
         &self.exposition
@@ -107,12 +217,8 @@ impl Config {
     }
 
     fn load_from_file(filename: &str) -> Config {
-        let mut file = std::fs::File::open(filename).expect("failed to open workload file");
-        let mut content = String::new();
-        file.read_to_string(&mut content).expect("failed to read");
-        let toml = toml::from_str(&content);
-        match toml {
-            Ok(toml) => toml,
+        match Config::from_file(filename) {
+            Ok(config) => config,
             Err(e) => {
                 println!("Failed to parse TOML config: {}", filename);
                 println!("{}", e);
@@ -120,6 +226,533 @@ impl Config {
             }
         }
     }
+
+    /// check the config file, printing each problem found, and return the
+    /// exit code
+    fn check_file(filename: &str) -> i32 {
+        let result = Config::read(filename)
+            .map_err(|e| vec![e.to_string()])
+            .and_then(Config::check);
+        match result {
+            Ok(()) => {
+                println!("{}: config ok", filename);
+                0
+            }
+            Err(errors) => {
+                for error in errors {
+                    println!("{}: {}", filename, error);
+                }
+                1
+            }
+        }
+    }
+
+    /// check TOML config, returning every problem found rather than only the
+    /// first
+    fn check(mut value: toml::Value) -> Result<(), Vec<String>> {
+        apply_env(&mut value, env_vars()).map_err(|e| vec![e.to_string()])?;
+        apply_percentiles(&mut value).map_err(|e| vec![e.to_string()])?;
+
+        // deserializing stops at the first error, so each section, and each
+        // sampler, is checked on its own
+        let mut errors = Vec::new();
+        for (key, section) in value.as_table().into_iter().flatten() {
+            match (key.as_str(), section.as_table()) {
+                ("samplers", Some(samplers)) => {
+                    for (name, sampler) in samplers {
+                        errors.extend(check_sampler(name, sampler));
+                    }
+                }
+                _ => {
+                    if let Err(e) = nested(&[key.as_str()], section.clone()).try_into::<Config>() {
+                        errors.push(format!("{}: {}", key, e));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            match value.try_into::<Config>() {
+                Ok(config) => errors.extend(config.validate()),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// returns the problems with a config which deserializing it does not
+    /// catch
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let general = &self.general;
+        for (key, value) in &[
+            ("interval", general.interval()),
+            ("threads", general.threads()),
+            ("window", general.window()),
+        ] {
+            if *value == 0 {
+                errors.push(format!("general.{}: must be greater than 0", key));
+            }
+        }
+        for percentile in general.percentiles().unwrap_or_default() {
+            if !(0.0..=100.0).contains(percentile) {
+                errors.push(format!(
+                    "general.percentiles: {} is not between 0 and 100",
+                    percentile
+                ));
+            }
+        }
+        if general.jitter() > 100 {
+            errors.push("general.jitter: must be at most 100 percent".to_string());
+        }
+
+        let listen = listen_address("general.listen", general.listen(), &mut errors);
+        let admin = listen_address("admin.listen", self.admin.listen_address(), &mut errors);
+        match (&listen, &admin) {
+            (Some(Listen::Tcp(listen)), Some(Listen::Tcp(admin))) => {
+                let shared = listen.ip() == admin.ip()
+                    || listen.ip().is_unspecified()
+                    || admin.ip().is_unspecified();
+                if listen.port() == admin.port() && shared {
+                    errors.push(format!(
+                        "admin.listen: {} conflicts with general.listen {}, use another port",
+                        admin, listen
+                    ));
+                }
+            }
+            (Some(Listen::Unix(listen)), Some(Listen::Unix(admin))) if listen == admin => {
+                errors.push(format!(
+                    "admin.listen: unix:{} conflicts with general.listen, use another path",
+                    admin.display()
+                ));
+            }
+            _ => {}
+        }
+        for (key, mode, listen) in vec![
+            ("general.listen_mode", general.listen_mode(), &listen),
+            (
+                "admin.listen_mode",
+                self.admin.listen_mode_setting(),
+                &admin,
+            ),
+        ] {
+            if let Some(mode) = mode {
+                if let Err(e) = parse_mode(&mode) {
+                    errors.push(format!("{}: {}", key, e));
+                } else if !matches!(listen, Some(Listen::Unix(_))) {
+                    errors.push(format!("{}: only applies to a unix socket", key));
+                }
+            }
+        }
+
+        let http = self.exposition.http();
+        match (http.certificate(), http.private_key()) {
+            (Some(_), None) => {
+                errors.push("exposition.http: certificate is set without a private_key".to_string())
+            }
+            (None, Some(_)) => {
+                errors.push("exposition.http: private_key is set without a certificate".to_string())
+            }
+            _ => {}
+        }
+        if let Err(e) = http.token() {
+            errors.push(format!("exposition.http.token_file: {}", e));
+        }
+
+        let recorder = self.exposition.recorder();
+        if recorder.segment_size() == 0 {
+            errors.push("exposition.recorder.segment_size: must be greater than 0".to_string());
+        }
+        if recorder.segments() == 0 {
+            errors.push("exposition.recorder.segments: must be greater than 0".to_string());
+        }
+
+        let history = self.exposition.history();
+        for (key, value) in &[
+            ("window", history.window()),
+            ("resolution", history.resolution()),
+        ] {
+            if *value == 0 {
+                errors.push(format!(
+                    "exposition.history.{}: must be greater than 0",
+                    key
+                ));
+            }
+        }
+
+        let influxdb = self.exposition.influxdb();
+        if influxdb.enabled() {
+            if influxdb.org().is_none() {
+                errors.push("exposition.influxdb: org is required".to_string());
+            }
+            if influxdb.bucket().is_none() {
+                errors.push("exposition.influxdb: bucket is required".to_string());
+            }
+        }
+        if let Err(e) = influxdb.token() {
+            errors.push(format!("exposition.influxdb.token_file: {}", e));
+        }
+
+        #[cfg(feature = "push_kafka")]
+        {
+            let version = self.exposition.kafka().version();
+            if !crate::exposition::JSON_VERSIONS.contains(&version) {
+                errors.push(format!(
+                    "exposition.kafka.version: unsupported version {}",
+                    version
+                ));
+            }
+            if self.exposition.kafka().batch_size() == 0 {
+                errors.push("exposition.kafka.batch_size: must be greater than 0".to_string());
+            }
+        }
+
+        for (key, _) in self.exposition.labels().configured() {
+            let valid = key
+                .chars()
+                .enumerate()
+                .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+            if key.is_empty() || !valid {
+                errors.push(format!(
+                    "exposition.labels.custom: \"{}\" is not a valid label name",
+                    key
+                ));
+            }
+        }
+
+        errors.extend(crate::samplers::check_samplers(self));
+        errors
+    }
+
+    fn from_file(filename: &str) -> Result<Config, anyhow::Error> {
+        Config::from_file_with_overrides(filename, BTreeMap::new())
+    }
+
+    fn from_file_with_overrides(
+        filename: &str,
+        overrides: BTreeMap<String, bool>,
+    ) -> Result<Config, anyhow::Error> {
+        let value = Config::read(filename)?;
+        let mut config = Config::parse(value, overrides)?;
+        config.file = Some(filename.to_string());
+        Ok(config)
+    }
+
+    /// read a TOML config file, merging in the files it includes in order.
+    /// Relative paths are relative to the directory of the config file, and
+    /// the file names may contain `*` and `?` wildcards, such as
+    /// `include = ["conf.d/*.toml"]`. Files matching a wildcard are included in
+    /// the order of their names.
+    fn read(filename: &str) -> Result<toml::Value, anyhow::Error> {
+        let path = Path::new(filename);
+        let mut value = read_toml(path)?;
+        let include = match value.as_table_mut().and_then(|v| v.remove("include")) {
+            Some(include) => include,
+            None => return Ok(value),
+        };
+        let patterns = match include {
+            toml::Value::Array(patterns) => patterns,
+            _ => return Err(format_err!("include: expected an array of paths")),
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for pattern in patterns {
+            let pattern = pattern
+                .as_str()
+                .ok_or_else(|| format_err!("include: expected an array of paths"))?;
+            for path in expand(&dir.join(pattern))? {
+                let fragment = read_toml(&path)?;
+                if fragment.get("include").is_some() {
+                    return Err(format_err!(
+                        "{}: included files may not include others",
+                        path.display()
+                    ));
+                }
+                merge(&mut value, fragment);
+            }
+        }
+        Ok(value)
+    }
+
+    /// parse TOML config, applying any overrides from the environment and
+    /// then setting `enabled` for each overridden sampler. The config is
+    /// rejected if it is invalid, so that it is never made active.
+    fn parse(
+        mut value: toml::Value,
+        overrides: BTreeMap<String, bool>,
+    ) -> Result<Config, anyhow::Error> {
+        apply_env(&mut value, env_vars())?;
+        apply_percentiles(&mut value)?;
+        for (sampler, enabled) in &overrides {
+            let samplers = value
+                .as_table_mut()
+                .and_then(|v| {
+                    v.entry("samplers")
+                        .or_insert_with(|| toml::Value::Table(Default::default()))
+                        .as_table_mut()
+                })
+                .ok_or_else(|| format_err!("samplers is not a table"))?;
+            let sampler = samplers
+                .entry(sampler.clone())
+                .or_insert_with(|| toml::Value::Table(Default::default()))
+                .as_table_mut()
+                .ok_or_else(|| format_err!("samplers.{} is not a table", sampler))?;
+            sampler.insert("enabled".to_string(), toml::Value::Boolean(*enabled));
+        }
+        let mut config: Config = value.try_into()?;
+        config.overrides = overrides;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            return Err(format_err!("{}", errors.join("\n")));
+        }
+        Ok(config)
+    }
+}
+
+fn empty() -> toml::Value {
+    toml::Value::Table(Default::default())
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value, anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format_err!("failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format_err!("{}: {}", path.display(), e))
+}
+
+/// expand a path whose file name may contain wildcards into the files which
+/// match it, sorted by name. Paths without wildcards are returned as is, so
+/// that they fail to be read if they don't exist.
+fn expand(pattern: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let name = pattern.file_name().and_then(|v| v.to_str()).unwrap_or("");
+    if !name.contains(|c| c == '*' || c == '?') {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format_err!("failed to read {}: {}", dir.display(), e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let matches = match entry.file_name().to_str() {
+            // hidden files are only matched by patterns which start with a dot
+            Some(file) => wildcard(name, file) && (!file.starts_with('.') || name.starts_with('.')),
+            None => false,
+        };
+        if matches && entry.path().is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// match a name against a pattern, where `*` matches any number of characters
+/// and `?` matches exactly one
+pub fn wildcard(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => name.is_empty(),
+        Some('*') => name
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(name.len()))
+            .any(|i| wildcard(chars.as_str(), &name[i..])),
+        Some('?') => {
+            let mut name = name.chars();
+            name.next().is_some() && wildcard(chars.as_str(), name.as_str())
+        }
+        Some(c) => name
+            .strip_prefix(c)
+            .map(|name| wildcard(chars.as_str(), name))
+            .unwrap_or(false),
+    }
+}
+
+/// merge `overlay` into `base`. Tables are merged key by key, while any other
+/// value, including an array, replaces the value in `base`.
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// environment variables with this prefix override config keys
+const ENV_PREFIX: &str = "REZOLUS_";
+
+/// environment variables which are valid unicode, others can't be overrides
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// override config keys from environment variables of the form
+/// `REZOLUS_<SECTION>__<KEY>`, with `__` separating each level of the key, such
+/// as `REZOLUS_SAMPLERS__CPU__ENABLED=false`. Values are parsed as TOML, and
+/// taken as a string if they are not valid TOML.
+fn apply_env(
+    value: &mut toml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<(), anyhow::Error> {
+    for (var, setting) in vars {
+        let path = match var.strip_prefix(ENV_PREFIX) {
+            Some(path) if path.contains("__") => path.to_lowercase(),
+            _ => continue,
+        };
+        let mut keys: Vec<&str> = path.split("__").collect();
+        let last = keys.pop().unwrap();
+
+        let mut table = value
+            .as_table_mut()
+            .ok_or_else(|| format_err!("{}: config is not a table", var))?;
+        for key in keys {
+            table = table
+                .entry(key.to_string())
+                .or_insert_with(|| toml::Value::Table(Default::default()))
+                .as_table_mut()
+                .ok_or_else(|| format_err!("{}: {} is not a table", var, key))?;
+        }
+        let setting = toml::from_str::<toml::value::Table>(&format!("v = {}", setting))
+            .ok()
+            .and_then(|mut v| v.remove("v"))
+            .unwrap_or(toml::Value::String(setting));
+        table.insert(last.to_string(), setting);
+    }
+    Ok(())
+}
+
+/// samplers which don't set their own percentiles use those set in
+/// `[general]`, if any
+fn apply_percentiles(value: &mut toml::Value) -> Result<(), anyhow::Error> {
+    let percentiles = match value.get("general").and_then(|v| v.get("percentiles")) {
+        Some(percentiles) if percentiles.is_array() => percentiles.clone(),
+        _ => return Ok(()),
+    };
+    let samplers = value
+        .as_table_mut()
+        .and_then(|v| v.entry("samplers").or_insert_with(empty).as_table_mut())
+        .ok_or_else(|| format_err!("samplers is not a table"))?;
+    for name in crate::samplers::sampler_names() {
+        samplers
+            .entry(name)
+            .or_insert_with(empty)
+            .as_table_mut()
+            .ok_or_else(|| format_err!("samplers.{} is not a table", name))?
+            .entry("percentiles")
+            .or_insert_with(|| percentiles.clone());
+    }
+    Ok(())
+}
+
+/// check the config of a single sampler. Statistics are checked one at a time
+/// so that every unknown statistic is reported.
+fn check_sampler(name: &str, sampler: &toml::Value) -> Vec<String> {
+    if !crate::samplers::sampler_names().contains(&name) {
+        return vec![format!("samplers.{}: unknown sampler", name)];
+    }
+    let parse = |value: toml::Value| nested(&["samplers", name], value).try_into::<Config>();
+
+    let mut errors = Vec::new();
+    let mut sampler = sampler.clone();
+    if let Some(statistics) = sampler.as_table_mut().and_then(|v| v.remove("statistics")) {
+        match statistics {
+            toml::Value::Array(statistics) => {
+                for statistic in statistics {
+                    let mut table = toml::value::Table::new();
+                    let value = toml::Value::Array(vec![statistic.clone()]);
+                    table.insert("statistics".to_string(), value);
+                    if parse(toml::Value::Table(table)).is_err() {
+                        errors.push(format!(
+                            "samplers.{}.statistics: unknown statistic {}, see docs/METRICS.md",
+                            name, statistic
+                        ));
+                    }
+                }
+            }
+            _ => errors.push(format!(
+                "samplers.{}.statistics: expected an array of statistic names",
+                name
+            )),
+        }
+    }
+    if let Err(e) = parse(sampler) {
+        errors.push(format!("samplers.{}: {}", name, e));
+    }
+    errors
+}
+
+/// wraps the value in a table for each key in the path
+fn nested(path: &[&str], value: toml::Value) -> toml::Value {
+    path.iter().rev().fold(value, |value, key| {
+        let mut table = toml::value::Table::new();
+        table.insert(key.to_string(), value);
+        toml::Value::Table(table)
+    })
+}
+
+/// parse a listen address, recording an error if it is invalid
+fn listen_address(key: &str, address: Option<String>, errors: &mut Vec<String>) -> Option<Listen> {
+    match Listen::parse(&address?) {
+        Ok(listen) => Some(listen),
+        Err(e) => {
+            errors.push(format!("{}: {}", key, e));
+            None
+        }
+    }
+}
+
+/// A shared handle to the active `Config`. Reloading replaces the `Config`
+/// held by the handle, and samplers pick up the new one on their next sample.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    inner: Arc<RwLock<Arc<Config>>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// get the active `Config`
+    pub fn current(&self) -> Arc<Config> {
+        self.inner.read().unwrap().clone()
+    }
+
+    /// reload the config file and make it the active `Config`
+    pub fn reload(&self) -> Result<Arc<Config>, anyhow::Error> {
+        let config = Arc::new(self.current().reload()?);
+        *self.inner.write().unwrap() = config.clone();
+        Ok(config)
+    }
+
+    /// enable or disable a sampler, making the resulting `Config` active
+    pub fn set_sampler_enabled(
+        &self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<Arc<Config>, anyhow::Error> {
+        let config = Arc::new(self.current().with_sampler_enabled(name, enabled)?);
+        *self.inner.write().unwrap() = config.clone();
+        Ok(config)
+    }
 }
 
 pub trait SamplerConfig {
@@ -130,10 +763,228 @@ pub trait SamplerConfig {
     fn enabled(&self) -> bool {
         false
     }
+    fn histogram(&self) -> &Histogram;
     fn interval(&self) -> Option<usize>;
+    fn jitter(&self) -> Option<usize>;
     fn percentiles(&self) -> &[f64];
     fn perf_events(&self) -> bool {
         false
     }
     fn statistics(&self) -> Vec<<Self as config::SamplerConfig>::Statistic>;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(config: &str) -> Result<(), Vec<String>> {
+        Config::check(toml::from_str(config).unwrap())
+    }
+
+    #[test]
+    fn test_check() {
+        let config = r#"
+            [general]
+            listen = "0.0.0.0:4242"
+
+            [samplers.cpu]
+            enabled = true
+            statistics = ["cpu/usage/user"]
+        "#;
+        assert_eq!(check(config), Ok(()));
+
+        let config = r#"
+            [admin]
+            listen = "127.0.0.1:4242"
+
+            [general]
+            listen = "0.0.0.0:4242"
+            interval = 0
+        "#;
+        assert_eq!(
+            check(config),
+            Err(vec![
+                "general.interval: must be greater than 0".to_string(),
+                "admin.listen: 127.0.0.1:4242 conflicts with general.listen 0.0.0.0:4242, \
+                 use another port"
+                    .to_string(),
+            ])
+        );
+
+        // every problem is reported, not only the first
+        let config = r#"
+            [general]
+            unknown = true
+
+            [samplers.cpu]
+            statistics = ["cpu/usage/user", "cpu/usage/bogus", "cpu/bogus"]
+
+            [samplers.bogus]
+            enabled = true
+        "#;
+        let errors = check(config).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("general: unknown field `unknown`"));
+        assert_eq!(errors[1], "samplers.bogus: unknown sampler");
+        assert!(errors[2].contains("unknown statistic \"cpu/usage/bogus\""));
+        assert!(errors[3].contains("unknown statistic \"cpu/bogus\""));
+
+        let config = r#"
+            [exposition.labels]
+            datacenter = "dc1"
+
+            [exposition.labels.custom]
+            team = "storage"
+            "1st" = "bad"
+        "#;
+        assert_eq!(
+            check(config),
+            Err(vec![
+                "exposition.labels.custom: \"1st\" is not a valid label name".to_string()
+            ])
+        );
+
+        let config = r#"
+            [admin]
+            listen = "unix:/run/rezolus/admin.sock"
+            listen_mode = "0600"
+
+            [general]
+            listen = "unix:/run/rezolus/admin.sock"
+            listen_mode = "0999"
+        "#;
+        assert_eq!(
+            check(config),
+            Err(vec![
+                "admin.listen: unix:/run/rezolus/admin.sock conflicts with general.listen, \
+                 use another path"
+                    .to_string(),
+                "general.listen_mode: 0999 is not an octal file mode".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [general]
+            listen = "0.0.0.0:4242"
+
+            [samplers.cpu]
+            enabled = true
+        "#,
+        )
+        .unwrap();
+        let vars = vec![
+            ("REZOLUS_SAMPLERS__CPU__ENABLED", "false"),
+            ("REZOLUS_SAMPLERS__IO_URING__PERCENTILES", "[50.0, 99.0]"),
+            ("REZOLUS_GENERAL__LISTEN", "127.0.0.1:4243"),
+            ("REZOLUS_GENERAL__INTERVAL", "500"),
+            ("REZOLUS_LOG", "debug"),
+            ("HOME", "/root"),
+        ];
+        let vars = vars
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+        apply_env(&mut value, vars).unwrap();
+
+        let config: Config = value.try_into().unwrap();
+        assert!(!config.samplers().cpu().enabled());
+        assert_eq!(config.samplers().io_uring().percentiles(), &[50.0, 99.0]);
+        assert_eq!(
+            config.general().listen(),
+            Some("127.0.0.1:4243".to_string())
+        );
+        assert_eq!(config.general().interval(), 500);
+
+        let mut value: toml::Value = toml::from_str("[general]\nlisten = \"\"").unwrap();
+        let vars = vec![("REZOLUS_GENERAL__LISTEN__PORT".to_string(), "1".to_string())];
+        assert!(apply_env(&mut value, vars.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let config = r#"
+            [general]
+            percentiles = [25.0, 50.0, 99.0, 99.9, 99.99]
+
+            [samplers.disk]
+            percentiles = [50.0, 99.9]
+        "#;
+        let config = Config::parse(toml::from_str(config).unwrap(), BTreeMap::new()).unwrap();
+        assert_eq!(
+            config.general().percentiles(),
+            Some(&[25.0, 50.0, 99.0, 99.9, 99.99][..])
+        );
+        assert_eq!(
+            config.samplers().cpu().percentiles(),
+            &[25.0, 50.0, 99.0, 99.9, 99.99]
+        );
+        assert_eq!(config.samplers().disk().percentiles(), &[50.0, 99.9]);
+
+        let config = Config::parse(empty(), BTreeMap::new()).unwrap();
+        assert_eq!(
+            config.samplers().cpu().percentiles(),
+            &crate::common::default_percentiles()[..]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let config = "[general]\ninterval = 0";
+        assert!(Config::parse(toml::from_str(config).unwrap(), BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_wildcard() {
+        assert!(wildcard("*.toml", "cpu.toml"));
+        assert!(wildcard("*.toml", ".toml"));
+        assert!(!wildcard("*.toml", "cpu.toml.bak"));
+        assert!(wildcard("??-*", "10-base"));
+        assert!(!wildcard("??-*", "1-base"));
+        assert!(wildcard("base.toml", "base.toml"));
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("rezolus-include-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        let write = |name: &str, content: &str| std::fs::write(dir.join(name), content).unwrap();
+        write(
+            "rezolus.toml",
+            r#"
+            include = ["conf.d/*.toml"]
+
+            [general]
+            listen = "0.0.0.0:4242"
+            interval = 1000
+
+            [samplers.cpu]
+            enabled = true
+            percentiles = [50.0, 99.0]
+        "#,
+        );
+        write(
+            "conf.d/20-role.toml",
+            "[samplers.cpu]\nenabled = false\n[samplers.disk]\nenabled = true",
+        );
+        write(
+            "conf.d/10-fleet.toml",
+            "[general]\ninterval = 500\n[samplers.cpu]\npercentiles = [99.9]",
+        );
+        write("conf.d/.hidden.toml", "[general]\ninterval = 1");
+
+        let config = Config::from_file(dir.join("rezolus.toml").to_str().unwrap()).unwrap();
+        assert_eq!(config.general().listen(), Some("0.0.0.0:4242".to_string()));
+        assert_eq!(config.general().interval(), 500);
+        assert!(!config.samplers().cpu().enabled());
+        assert_eq!(config.samplers().cpu().percentiles(), &[99.9]);
+        assert!(config.samplers().disk().enabled());
+
+        write("conf.d/30-nested.toml", "include = [\"other.toml\"]");
+        assert!(Config::from_file(dir.join("rezolus.toml").to_str().unwrap()).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -4,94 +4,337 @@
 
 use crate::config::*;
 
+use samplers::aer::AerConfig;
+use samplers::amdgpu::AmdgpuConfig;
+use samplers::bonding::BondingConfig;
+use samplers::btrfs::BtrfsConfig;
+use samplers::ceph::CephConfig;
+use samplers::cgroups::CgroupsConfig;
+use samplers::conntrack::ConntrackConfig;
 use samplers::cpu::CpuConfig;
+use samplers::cpufreq::CpufreqConfig;
+use samplers::cpuidle::CpuidleConfig;
 use samplers::disk::DiskConfig;
+use samplers::dm::DmConfig;
+use samplers::dns::DnsConfig;
+use samplers::ebpf::EbpfConfig;
+use samplers::edac::EdacConfig;
+use samplers::entropy::EntropyConfig;
 use samplers::ext4::Ext4Config;
+use samplers::fd::FdConfig;
+use samplers::filesystem::FilesystemConfig;
+use samplers::fsync::FsyncConfig;
+use samplers::futex::FutexConfig;
 use samplers::http::HttpConfig;
+use samplers::hugepages::HugepagesConfig;
+use samplers::intel_gpu::IntelGpuConfig;
 use samplers::interrupt::InterruptConfig;
+use samplers::io_uring::IoUringConfig;
+use samplers::ipmi::IpmiConfig;
 use samplers::krb5kdc::Krb5kdcConfig;
+use samplers::mce::MceConfig;
+use samplers::mdraid::MdraidConfig;
 use samplers::memcache::MemcacheConfig;
 use samplers::memory::MemoryConfig;
 use samplers::network::NetworkConfig;
+use samplers::nfs::NfsConfig;
 use samplers::ntp::NtpConfig;
+use samplers::numa::NumaConfig;
 use samplers::nvidia::NvidiaConfig;
+use samplers::nvme::NvmeConfig;
+use samplers::oom::OomConfig;
 use samplers::page_cache::PageCacheConfig;
+use samplers::power::PowerConfig;
+use samplers::process::ProcessConfig;
+use samplers::psi::PsiConfig;
+use samplers::quic::QuicConfig;
+use samplers::rdma::RdmaConfig;
+use samplers::reclaim::ReclaimConfig;
+use samplers::resctrl::ResctrlConfig;
 use samplers::rezolus::RezolusConfig;
 use samplers::scheduler::SchedulerConfig;
+use samplers::slab::SlabConfig;
+use samplers::smart::SmartConfig;
+use samplers::softirq::SoftirqConfig;
 use samplers::softnet::SoftnetConfig;
+use samplers::sriov::SriovConfig;
+use samplers::swap::SwapConfig;
+use samplers::syscall::SyscallConfig;
 use samplers::tcp::TcpConfig;
+use samplers::thermal::ThermalConfig;
+use samplers::tls::TlsConfig;
 use samplers::udp::UdpConfig;
+use samplers::uncore::UncoreConfig;
+use samplers::uprobe::UprobeConfig;
+use samplers::usdt::UsdtConfig;
 use samplers::usercall::UsercallConfig;
+use samplers::vfs::VfsConfig;
+use samplers::writeback::WritebackConfig;
 use samplers::xfs::XfsConfig;
+use samplers::zfs::ZfsConfig;
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Samplers {
+    #[serde(default)]
+    aer: AerConfig,
+    #[serde(default)]
+    amdgpu: AmdgpuConfig,
+    #[serde(default)]
+    bonding: BondingConfig,
+    #[serde(default)]
+    btrfs: BtrfsConfig,
+    #[serde(default)]
+    ceph: CephConfig,
+    #[serde(default)]
+    cgroups: CgroupsConfig,
+    #[serde(default)]
+    conntrack: ConntrackConfig,
     #[serde(default)]
     cpu: CpuConfig,
     #[serde(default)]
+    cpufreq: CpufreqConfig,
+    #[serde(default)]
+    cpuidle: CpuidleConfig,
+    #[serde(default)]
     disk: DiskConfig,
     #[serde(default)]
+    dm: DmConfig,
+    #[serde(default)]
+    dns: DnsConfig,
+    #[serde(default)]
+    ebpf: EbpfConfig,
+    #[serde(default)]
+    edac: EdacConfig,
+    #[serde(default)]
+    entropy: EntropyConfig,
+    #[serde(default)]
     ext4: Ext4Config,
     #[serde(default)]
+    fd: FdConfig,
+    #[serde(default)]
+    filesystem: FilesystemConfig,
+    #[serde(default)]
+    fsync: FsyncConfig,
+    #[serde(default)]
+    futex: FutexConfig,
+    #[serde(default)]
     http: HttpConfig,
     #[serde(default)]
+    hugepages: HugepagesConfig,
+    #[serde(default)]
+    intel_gpu: IntelGpuConfig,
+    #[serde(default)]
     interrupt: InterruptConfig,
     #[serde(default)]
+    io_uring: IoUringConfig,
+    #[serde(default)]
+    ipmi: IpmiConfig,
+    #[serde(default)]
     krb5kdc: Krb5kdcConfig,
     #[serde(default)]
+    mce: MceConfig,
+    #[serde(default)]
+    mdraid: MdraidConfig,
+    #[serde(default)]
     memcache: MemcacheConfig,
     #[serde(default)]
     memory: MemoryConfig,
     #[serde(default)]
     network: NetworkConfig,
     #[serde(default)]
+    nfs: NfsConfig,
+    #[serde(default)]
     ntp: NtpConfig,
     #[serde(default)]
+    numa: NumaConfig,
+    #[serde(default)]
     nvidia: NvidiaConfig,
     #[serde(default)]
+    nvme: NvmeConfig,
+    #[serde(default)]
+    oom: OomConfig,
+    #[serde(default)]
     page_cache: PageCacheConfig,
     #[serde(default)]
+    power: PowerConfig,
+    #[serde(default)]
+    process: ProcessConfig,
+    #[serde(default)]
+    psi: PsiConfig,
+    #[serde(default)]
+    quic: QuicConfig,
+    #[serde(default)]
+    rdma: RdmaConfig,
+    #[serde(default)]
+    reclaim: ReclaimConfig,
+    #[serde(default)]
+    resctrl: ResctrlConfig,
+    #[serde(default)]
     rezolus: RezolusConfig,
     #[serde(default)]
     scheduler: SchedulerConfig,
     #[serde(default)]
+    slab: SlabConfig,
+    #[serde(default)]
+    smart: SmartConfig,
+    #[serde(default)]
+    softirq: SoftirqConfig,
+    #[serde(default)]
     softnet: SoftnetConfig,
     #[serde(default)]
+    sriov: SriovConfig,
+    #[serde(default)]
+    swap: SwapConfig,
+    #[serde(default)]
+    syscall: SyscallConfig,
+    #[serde(default)]
     tcp: TcpConfig,
     #[serde(default)]
+    thermal: ThermalConfig,
+    #[serde(default)]
+    tls: TlsConfig,
+    #[serde(default)]
     udp: UdpConfig,
     #[serde(default)]
+    uncore: UncoreConfig,
+    #[serde(default)]
+    uprobe: UprobeConfig,
+    #[serde(default)]
+    usdt: UsdtConfig,
+    #[serde(default)]
     usercall: UsercallConfig,
     #[serde(default)]
+    vfs: VfsConfig,
+    #[serde(default)]
+    writeback: WritebackConfig,
+    #[serde(default)]
     xfs: XfsConfig,
+    #[serde(default)]
+    zfs: ZfsConfig,
 }
 
 impl Samplers {
+    pub fn aer(&self) -> &AerConfig {
+        &self.aer
+    }
+
+    pub fn amdgpu(&self) -> &AmdgpuConfig {
+        &self.amdgpu
+    }
+
+    pub fn bonding(&self) -> &BondingConfig {
+        &self.bonding
+    }
+
+    pub fn btrfs(&self) -> &BtrfsConfig {
+        &self.btrfs
+    }
+
+    pub fn ceph(&self) -> &CephConfig {
+        &self.ceph
+    }
+
+    pub fn cgroups(&self) -> &CgroupsConfig {
+        &self.cgroups
+    }
+
+    pub fn conntrack(&self) -> &ConntrackConfig {
+        &self.conntrack
+    }
+
     pub fn cpu(&self) -> &CpuConfig {
         &self.cpu
     }
 
+    pub fn cpufreq(&self) -> &CpufreqConfig {
+        &self.cpufreq
+    }
+
+    pub fn cpuidle(&self) -> &CpuidleConfig {
+        &self.cpuidle
+    }
+
     pub fn disk(&self) -> &DiskConfig {
         &self.disk
     }
 
+    pub fn dm(&self) -> &DmConfig {
+        &self.dm
+    }
+
+    pub fn dns(&self) -> &DnsConfig {
+        &self.dns
+    }
+
+    pub fn ebpf(&self) -> &EbpfConfig {
+        &self.ebpf
+    }
+
+    pub fn edac(&self) -> &EdacConfig {
+        &self.edac
+    }
+
+    pub fn entropy(&self) -> &EntropyConfig {
+        &self.entropy
+    }
+
     pub fn ext4(&self) -> &Ext4Config {
         &self.ext4
     }
 
+    pub fn fd(&self) -> &FdConfig {
+        &self.fd
+    }
+
+    pub fn filesystem(&self) -> &FilesystemConfig {
+        &self.filesystem
+    }
+
+    pub fn fsync(&self) -> &FsyncConfig {
+        &self.fsync
+    }
+
+    pub fn futex(&self) -> &FutexConfig {
+        &self.futex
+    }
+
     pub fn http(&self) -> &HttpConfig {
         &self.http
     }
 
+    pub fn hugepages(&self) -> &HugepagesConfig {
+        &self.hugepages
+    }
+
+    pub fn intel_gpu(&self) -> &IntelGpuConfig {
+        &self.intel_gpu
+    }
+
     pub fn interrupt(&self) -> &InterruptConfig {
         &self.interrupt
     }
 
+    pub fn io_uring(&self) -> &IoUringConfig {
+        &self.io_uring
+    }
+
+    pub fn ipmi(&self) -> &IpmiConfig {
+        &self.ipmi
+    }
+
     pub fn krb5kdc(&self) -> &Krb5kdcConfig {
         &self.krb5kdc
     }
 
+    pub fn mce(&self) -> &MceConfig {
+        &self.mce
+    }
+
+    pub fn mdraid(&self) -> &MdraidConfig {
+        &self.mdraid
+    }
+
     pub fn memcache(&self) -> &MemcacheConfig {
         &self.memcache
     }
@@ -104,18 +347,62 @@ impl Samplers {
         &self.network
     }
 
+    pub fn nfs(&self) -> &NfsConfig {
+        &self.nfs
+    }
+
     pub fn ntp(&self) -> &NtpConfig {
         &self.ntp
     }
 
+    pub fn numa(&self) -> &NumaConfig {
+        &self.numa
+    }
+
     pub fn nvidia(&self) -> &NvidiaConfig {
         &self.nvidia
     }
 
+    pub fn nvme(&self) -> &NvmeConfig {
+        &self.nvme
+    }
+
+    pub fn oom(&self) -> &OomConfig {
+        &self.oom
+    }
+
     pub fn page_cache(&self) -> &PageCacheConfig {
         &self.page_cache
     }
 
+    pub fn power(&self) -> &PowerConfig {
+        &self.power
+    }
+
+    pub fn process(&self) -> &ProcessConfig {
+        &self.process
+    }
+
+    pub fn psi(&self) -> &PsiConfig {
+        &self.psi
+    }
+
+    pub fn quic(&self) -> &QuicConfig {
+        &self.quic
+    }
+
+    pub fn rdma(&self) -> &RdmaConfig {
+        &self.rdma
+    }
+
+    pub fn reclaim(&self) -> &ReclaimConfig {
+        &self.reclaim
+    }
+
+    pub fn resctrl(&self) -> &ResctrlConfig {
+        &self.resctrl
+    }
+
     pub fn rezolus(&self) -> &RezolusConfig {
         &self.rezolus
     }
@@ -124,23 +411,79 @@ impl Samplers {
         &self.scheduler
     }
 
+    pub fn slab(&self) -> &SlabConfig {
+        &self.slab
+    }
+
+    pub fn smart(&self) -> &SmartConfig {
+        &self.smart
+    }
+
+    pub fn softirq(&self) -> &SoftirqConfig {
+        &self.softirq
+    }
+
     pub fn softnet(&self) -> &SoftnetConfig {
         &self.softnet
     }
 
+    pub fn sriov(&self) -> &SriovConfig {
+        &self.sriov
+    }
+
+    pub fn swap(&self) -> &SwapConfig {
+        &self.swap
+    }
+
+    pub fn syscall(&self) -> &SyscallConfig {
+        &self.syscall
+    }
+
     pub fn tcp(&self) -> &TcpConfig {
         &self.tcp
     }
 
+    pub fn thermal(&self) -> &ThermalConfig {
+        &self.thermal
+    }
+
+    pub fn tls(&self) -> &TlsConfig {
+        &self.tls
+    }
+
     pub fn udp(&self) -> &UdpConfig {
         &self.udp
     }
 
+    pub fn uncore(&self) -> &UncoreConfig {
+        &self.uncore
+    }
+
+    pub fn uprobe(&self) -> &UprobeConfig {
+        &self.uprobe
+    }
+
+    pub fn usdt(&self) -> &UsdtConfig {
+        &self.usdt
+    }
+
     pub fn usercall(&self) -> &UsercallConfig {
         &self.usercall
     }
 
+    pub fn vfs(&self) -> &VfsConfig {
+        &self.vfs
+    }
+
+    pub fn writeback(&self) -> &WritebackConfig {
+        &self.writeback
+    }
+
     pub fn xfs(&self) -> &XfsConfig {
         &self.xfs
     }
+
+    pub fn zfs(&self) -> &ZfsConfig {
+        &self.zfs
+    }
 }
@@ -0,0 +1,35 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+
+/// A value which is hidden when the config is formatted, such as when it is
+/// served by the admin API, so that credentials are not exposed
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_debug() {
+        let secret = Secret("hunter2".to_string());
+        assert_eq!(secret.get(), "hunter2");
+        assert_eq!(format!("{:?}", Some(secret)), "Some(<redacted>)");
+    }
+}
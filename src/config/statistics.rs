@@ -0,0 +1,89 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde::de::{Deserialize, Deserializer, Error};
+use strum::IntoEnumIterator;
+
+use super::wildcard;
+
+/// Deserializes the list of statistics for a sampler. Each entry is the name
+/// of a statistic or a pattern, where `*` matches any number of characters and
+/// `?` matches exactly one, such as `"cpu/usage/*"`. Entries prefixed with `!`
+/// exclude the statistics they match from those selected by earlier entries,
+/// and a list which starts with an exclusion selects every other statistic.
+pub fn deserialize_statistics<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: IntoEnumIterator + Copy + PartialEq + Into<&'static str>,
+{
+    let entries: Vec<String> = Vec::deserialize(deserializer)?;
+    select(&entries).map_err(D::Error::custom)
+}
+
+fn select<T>(entries: &[String]) -> Result<Vec<T>, String>
+where
+    T: IntoEnumIterator + Copy + PartialEq + Into<&'static str>,
+{
+    let mut selected: Vec<T> = match entries.first() {
+        Some(entry) if entry.starts_with('!') => T::iter().collect(),
+        _ => Vec::new(),
+    };
+    for entry in entries {
+        let (exclude, pattern) = match entry.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, entry.as_str()),
+        };
+        let matching: Vec<T> = T::iter()
+            .filter(|statistic| wildcard(pattern, (*statistic).into()))
+            .collect();
+        if matching.is_empty() {
+            return Err(format!("unknown statistic \"{}\"", pattern));
+        }
+        if exclude {
+            selected.retain(|statistic| !matching.contains(statistic));
+        } else {
+            for statistic in matching {
+                if !selected.contains(&statistic) {
+                    selected.push(statistic);
+                }
+            }
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::samplers::cpu::CpuStatistic;
+
+    fn names(entries: &[&str]) -> Result<Vec<&'static str>, String> {
+        let entries: Vec<String> = entries.iter().map(|v| v.to_string()).collect();
+        let selected: Vec<CpuStatistic> = select(&entries)?;
+        Ok(selected.into_iter().map(|v| v.into()).collect())
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(
+            names(&["cpu/usage/user", "cpu/usage/system"]),
+            Ok(vec!["cpu/usage/user", "cpu/usage/system"])
+        );
+
+        let usage = names(&["cpu/usage/*", "!cpu/usage/guest*"]).unwrap();
+        assert!(usage.contains(&"cpu/usage/user"));
+        assert!(usage.iter().all(|v| v.starts_with("cpu/usage/")));
+        assert!(!usage.iter().any(|v| v.starts_with("cpu/usage/guest")));
+
+        let all = names(&["!cpu/usage/*"]).unwrap();
+        assert!(!all.is_empty());
+        assert!(!all.iter().any(|v| v.starts_with("cpu/usage/")));
+
+        assert_eq!(
+            names(&["cpu/usage/bogus"]),
+            Err("unknown statistic \"cpu/usage/bogus\"".to_string())
+        );
+        assert!(names(&["cpu/usage/*", "!cpu/bogus/*"]).is_err());
+    }
+}
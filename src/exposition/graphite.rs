@@ -0,0 +1,158 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::convert::TryInto;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use rustcommon_atomics::AtomicBool;
+use rustcommon_metrics::*;
+
+use crate::config::Config;
+use crate::exposition::{pause, MetricsSnapshot};
+
+/// Timeout for connecting to, and writing to, the carbon server
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct GraphiteExporter {
+    address: String,
+    /// cleared when the exporter should make its final push
+    exporting: Arc<AtomicBool>,
+    interval: Duration,
+    prefix: String,
+    snapshot: MetricsSnapshot,
+    stream: Option<TcpStream>,
+}
+
+impl GraphiteExporter {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let graphite = config.exposition().graphite();
+        Self {
+            address: graphite.address(),
+            exporting,
+            interval: Duration::from_millis(graphite.interval().try_into().unwrap()),
+            prefix: graphite.prefix(),
+            // the dimensions of statistics are kept in their names, as the
+            // plaintext protocol has no labels
+            snapshot: MetricsSnapshot::new(metrics, None, true),
+            stream: None,
+        }
+    }
+
+    pub fn run(&mut self) {
+        let start = Instant::now();
+        if let Err(e) = self.flush() {
+            error!("failed to send metrics to graphite: {}", e);
+            // force the connection to be reopened on the next attempt
+            self.stream = None;
+        }
+        let stop = Instant::now();
+        if start + self.interval > stop {
+            pause(self.interval - (stop - start), &self.exporting);
+        }
+    }
+
+    /// Sends the metrics one last time when stopping
+    pub fn finish(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("failed to send final metrics to graphite: {}", e);
+        }
+    }
+
+    fn connect(&self) -> Result<TcpStream, std::io::Error> {
+        debug!("connecting to graphite address: {}", self.address);
+        let address = self.address.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "bad graphite address")
+        })?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(stream)
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        if self.stream.is_none() {
+            self.stream = Some(self.connect()?);
+        }
+
+        self.snapshot.refresh();
+        let mut content = String::new();
+        for line in self.lines() {
+            content += &line;
+            content.push('\n');
+        }
+
+        if let Some(ref mut stream) = self.stream {
+            stream.write_all(content.as_bytes())?;
+            stream.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Converts the current snapshot into plaintext protocol lines. Counters
+    /// are sent as their cumulative value, and percentiles are distinguished
+    /// by the name.
+    fn lines(&self) -> Vec<String> {
+        let timestamp = self
+            .snapshot
+            .refreshed
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_secs())
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for (metric, value) in &self.snapshot.snapshot {
+            let name = match metric.output() {
+                Output::Reading => metric.statistic().name().to_string(),
+                Output::Percentile(percentile) => {
+                    format!("{}/histogram/p{:02}", metric.statistic().name(), percentile)
+                }
+            };
+            lines.push(format_line(&self.prefix, &name, *value, timestamp));
+        }
+        lines.sort();
+        lines
+    }
+}
+
+/// Formats a single line of the form `prefix.name value timestamp`, where the
+/// slashes of the name become dots and characters which the protocol reserves
+/// are replaced
+fn format_line(prefix: &str, name: &str, value: u64, timestamp: u64) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' => '.',
+            ';' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect();
+    if prefix.is_empty() {
+        format!("{} {} {}", name, value, timestamp)
+    } else {
+        format!("{}.{} {} {}", prefix, name, value, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line("rezolus", "cpu/usage/user", 42, 1_600_000_000),
+            "rezolus.cpu.usage.user 42 1600000000"
+        );
+        assert_eq!(
+            format_line("", "process/my app;1/cpu", 1, 1),
+            "process.my_app_1.cpu 1 1"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Recent values of selected statistics, kept in memory so that they can be
+//! inspected on the host without a remote time series database, such as
+//! during an incident which has cut the host off from it.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::wildcard;
+
+/// A ring of values for each statistic whose name matches one of the
+/// patterns, covering the most recent window at the given resolution.
+/// Nothing is recorded if there are no patterns.
+pub struct History {
+    patterns: Vec<String>,
+    resolution: Duration,
+    /// number of values kept for each statistic
+    capacity: usize,
+    recorded: Option<Instant>,
+    /// whether each name which has been recorded matches the patterns
+    matched: HashMap<String, bool>,
+    /// the time in milliseconds since the epoch, and the value
+    series: BTreeMap<String, VecDeque<(u64, u64)>>,
+}
+
+impl History {
+    pub fn new(patterns: Vec<String>, window: Duration, resolution: Duration) -> Self {
+        let capacity = (window.as_millis() / resolution.as_millis().max(1)).max(1) as usize;
+        Self {
+            patterns,
+            resolution,
+            capacity,
+            recorded: None,
+            matched: HashMap::new(),
+            series: BTreeMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Whether the resolution has passed since values were last recorded
+    pub fn due(&self) -> bool {
+        self.enabled()
+            && self
+                .recorded
+                .map_or(true, |recorded| recorded.elapsed() >= self.resolution)
+    }
+
+    /// Records the values which match the patterns, at a time in milliseconds
+    /// since the epoch
+    pub fn record<I: IntoIterator<Item = (String, u64)>>(&mut self, timestamp: u64, values: I) {
+        self.recorded = Some(Instant::now());
+        for (name, value) in values {
+            let patterns = &self.patterns;
+            let matched = *self
+                .matched
+                .entry(name.clone())
+                .or_insert_with(|| patterns.iter().any(|pattern| wildcard(pattern, &name)));
+            if !matched {
+                continue;
+            }
+            let series = self.series.entry(name).or_default();
+            if series.len() == self.capacity {
+                series.pop_front();
+            }
+            series.push_back((timestamp, value));
+        }
+    }
+
+    /// Returns the values of the statistics matching the pattern as JSON,
+    /// limited to those within `range` of the latest if it is given, or
+    /// `None` if there are no such statistics
+    pub fn query(&self, pattern: &str, range: Option<Duration>) -> Option<String> {
+        let mut series = json::JsonValue::new_object();
+        let mut found = false;
+        for (name, values) in &self.series {
+            if !wildcard(pattern, name) {
+                continue;
+            }
+            found = true;
+            let start = match (range, values.back()) {
+                (Some(range), Some((latest, _))) => latest.saturating_sub(range.as_millis() as u64),
+                _ => 0,
+            };
+            let points: Vec<json::JsonValue> = values
+                .iter()
+                .filter(|(timestamp, _)| *timestamp >= start)
+                .map(|(timestamp, value)| json::array![*timestamp, *value])
+                .collect();
+            let _ = series.insert(name, json::JsonValue::Array(points));
+        }
+        if !found {
+            return None;
+        }
+        let mut content = json::JsonValue::new_object();
+        let _ = content.insert("resolution", self.resolution.as_millis() as u64);
+        let _ = content.insert("series", series);
+        Some(content.dump())
+    }
+}
+
+/// Parses a range such as `90`, `90s`, `15m`, or `1h`, where a number without
+/// a unit is in seconds. Ranges too large to represent are rejected.
+pub fn parse_range(range: &str) -> Option<Duration> {
+    let (number, multiplier) = match range.chars().last()? {
+        's' => (&range[..range.len() - 1], 1),
+        'm' => (&range[..range.len() - 1], 60),
+        'h' => (&range[..range.len() - 1], 3600),
+        _ => (range, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_range("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_range("15m"), Some(Duration::from_secs(900)));
+        assert_eq!(parse_range("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_range("m"), None);
+        assert_eq!(parse_range("99999999999999999h"), None);
+        assert_eq!(parse_range(""), None);
+    }
+
+    #[test]
+    fn test_query() {
+        let mut history = History::new(
+            vec!["cpu/*".to_string()],
+            Duration::from_secs(3),
+            Duration::from_secs(1),
+        );
+        for i in 0..4 {
+            history.record(
+                i * 1000,
+                vec![
+                    ("cpu/usage/user".to_string(), i),
+                    ("memory/free".to_string(), i),
+                ],
+            );
+        }
+        // only the most recent window is kept
+        assert_eq!(
+            history.query("cpu/usage/user", None),
+            Some(
+                "{\"resolution\":1000,\"series\":{\"cpu/usage/user\":\
+                 [[1000,1],[2000,2],[3000,3]]}}"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            history.query("cpu/*", Some(Duration::from_secs(1))),
+            Some(
+                "{\"resolution\":1000,\"series\":{\"cpu/usage/user\":[[2000,2],[3000,3]]}}"
+                    .to_string()
+            )
+        );
+        assert_eq!(history.query("memory/free", None), None);
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Labels which describe this host, which are attached to every exported
+//! metric. They are taken from the config, and may also be detected from the
+//! hostname and the instance metadata service of the cloud provider.
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use crate::config::Labels;
+
+// the address of the instance metadata service in AWS, GCP, and Azure
+const METADATA: &str = "http://169.254.169.254";
+
+// a metadata service on the link-local network responds quickly if there is
+// one at all
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Returns the labels of this host, sorted by name. Configured labels take
+/// precedence over those which are detected.
+pub fn host_labels(config: &Labels) -> Vec<(String, String)> {
+    let mut labels = config.configured();
+    let mut detected = Vec::new();
+    if config.hostname() {
+        match std::fs::read_to_string("/proc/sys/kernel/hostname") {
+            Ok(hostname) => detected.push(("hostname".to_string(), hostname.trim().to_string())),
+            Err(e) => warn!("failed to read hostname: {}", e),
+        }
+    }
+    if config.cloud() {
+        match cloud() {
+            Some(cloud) => detected.extend(cloud),
+            None => warn!("no cloud instance metadata service found"),
+        }
+    }
+    for (key, value) in detected {
+        if !labels.iter().any(|(k, _)| *k == key) {
+            labels.push((key, value));
+        }
+    }
+    labels.sort();
+    labels
+}
+
+/// Labels from the instance metadata service of AWS, GCP, or Azure
+fn cloud() -> Option<Vec<(String, String)>> {
+    let client = Client::builder()
+        .connect_timeout(TIMEOUT)
+        .timeout(TIMEOUT)
+        .build()
+        .ok()?;
+    aws(&client)
+        .or_else(|| gcp(&client))
+        .or_else(|| azure(&client))
+        .map(|labels| {
+            labels
+                .into_iter()
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(key, value)| (key.to_string(), value))
+                .collect()
+        })
+}
+
+fn aws(client: &Client) -> Option<Vec<(&'static str, String)>> {
+    // IMDSv2 requires a session token for each request
+    let token = client
+        .put(&format!("{}/latest/api/token", METADATA))
+        .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .text()
+        .ok()?;
+    let get = |path: &str| {
+        client
+            .get(&format!("{}/latest/meta-data/{}", METADATA, path))
+            .header("X-aws-ec2-metadata-token", &token)
+            .send()
+            .ok()?
+            .error_for_status()
+            .ok()?
+            .text()
+            .ok()
+    };
+    let instance_id = get("instance-id")?;
+    Some(vec![
+        ("cloud_provider", "aws".to_string()),
+        ("instance_id", instance_id),
+        ("instance_type", get("instance-type").unwrap_or_default()),
+        ("region", get("placement/region").unwrap_or_default()),
+        (
+            "zone",
+            get("placement/availability-zone").unwrap_or_default(),
+        ),
+    ])
+}
+
+fn gcp(client: &Client) -> Option<Vec<(&'static str, String)>> {
+    let get = |path: &str| {
+        client
+            .get(&format!(
+                "{}/computeMetadata/v1/instance/{}",
+                METADATA, path
+            ))
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .ok()?
+            .error_for_status()
+            .ok()?
+            .text()
+            .ok()
+    };
+    let instance_id = get("id")?;
+    // the zone and machine type are paths, such as
+    // `projects/123/zones/us-central1-a`
+    let last = |value: Option<String>| {
+        value
+            .and_then(|v| v.rsplit('/').next().map(|v| v.to_string()))
+            .unwrap_or_default()
+    };
+    let zone = last(get("zone"));
+    let region = match zone.rsplit_once('-') {
+        Some((region, _)) => region.to_string(),
+        None => String::new(),
+    };
+    Some(vec![
+        ("cloud_provider", "gcp".to_string()),
+        ("instance_id", instance_id),
+        ("instance_type", last(get("machine-type"))),
+        ("region", region),
+        ("zone", zone),
+    ])
+}
+
+fn azure(client: &Client) -> Option<Vec<(&'static str, String)>> {
+    let body = client
+        .get(&format!(
+            "{}/metadata/instance/compute?api-version=2021-02-01",
+            METADATA
+        ))
+        .header("Metadata", "true")
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .text()
+        .ok()?;
+    let compute = json::parse(&body).ok()?;
+    let field = |key: &str| compute[key].as_str().unwrap_or_default().to_string();
+    Some(vec![
+        ("cloud_provider", "azure".to_string()),
+        ("instance_id", compute["vmId"].as_str()?.to_string()),
+        ("instance_type", field("vmSize")),
+        ("region", field("location")),
+        ("zone", field("zone")),
+    ])
+}
@@ -2,82 +2,597 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::net::SocketAddr;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::net::UnixListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
 use rustcommon_logger::*;
 use rustcommon_metrics::*;
-use tiny_http::{Method, Response, Server};
+use tiny_http::{Header, Response, Server};
 
-use super::MetricsSnapshot;
+use super::history::{parse_range, History};
+use super::{MetricsSnapshot, JSON_VERSIONS};
+use crate::common::histograms::Histograms;
+use crate::common::http::{
+    authorized, bind_unix, header, parse_request, percent_decode, read_head, respond,
+};
+use crate::config::{Config, Listen};
+
+/// Timeout for reading a request from, or writing a response to, a client
+/// which is not served by `tiny_http`
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on the number of TLS or unix socket connections which are
+/// being served at once, beyond which new connections are closed
+const MAX_CONNECTIONS: usize = 16;
+
+/// Responses smaller than this are not worth compressing
+const MIN_COMPRESSED_SIZE: usize = 1024;
+
+/// A content encoding which responses may be compressed with
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        }
+    }
+
+    fn encode(self, body: &[u8]) -> Vec<u8> {
+        // compressing into memory can't fail
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+                let _ = encoder.write_all(body);
+                encoder.finish().unwrap_or_default()
+            }
+            Self::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+                let _ = encoder.write_all(body);
+                encoder.finish().unwrap_or_default()
+            }
+        }
+    }
+}
+
+enum Listener {
+    Plain(Server),
+    Tls(TcpListener, SslAcceptor),
+    Unix(UnixListener),
+}
+
+/// The status, headers, and body of a response
+type Reply = (u16, Vec<(&'static str, &'static str)>, Vec<u8>);
+
+/// The head of a request read from a TLS or unix socket connection, which is
+/// answered by the main loop through `reply`
+struct Request {
+    head: String,
+    reply: Sender<Reply>,
+}
+
+/// Counts a connection as being served until it is dropped
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
 
 pub struct Http {
+    compression: bool,
+    /// number of TLS or unix socket connections being served
+    connections: Arc<AtomicUsize>,
+    histograms: Arc<Histograms>,
+    history: History,
+    listener: Listener,
+    /// requests read by the threads serving TLS and unix socket connections,
+    /// which are routed on the main loop
+    requests: (Sender<Request>, Receiver<Request>),
     snapshot: MetricsSnapshot,
-    server: Server,
+    token: Option<String>,
     updated: Instant,
 }
 
 impl Http {
     pub fn new(
-        address: SocketAddr,
+        config: Arc<Config>,
         metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
-        count_label: Option<&str>,
+        histograms: Arc<Histograms>,
     ) -> Self {
-        let server = tiny_http::Server::http(address);
-        if server.is_err() {
-            fatal!("Failed to open {} for HTTP Stats listener", address);
-        }
+        let address = config.listen().expect("no listen address");
+        let http = config.exposition().http();
+        let listener = match (address, http.certificate(), http.private_key()) {
+            (Listen::Unix(path), _, _) => {
+                match bind_unix(&path, config.listen_mode()).and_then(|listener| {
+                    // the listener is polled along with the rest of the main loop
+                    listener.set_nonblocking(true)?;
+                    Ok(listener)
+                }) {
+                    Ok(listener) => Listener::Unix(listener),
+                    Err(e) => {
+                        fatal!(
+                            "Failed to open {} for HTTP Stats listener: {}",
+                            path.display(),
+                            e
+                        );
+                    }
+                }
+            }
+            (Listen::Tcp(address), Some(certificate), Some(private_key)) => {
+                match tls_listener(address, &certificate, &private_key, http.client_ca()) {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        fatal!("Failed to open {} for HTTPS Stats listener: {}", address, e);
+                    }
+                }
+            }
+            (Listen::Tcp(address), _, _) => match Server::http(address) {
+                Ok(server) => Listener::Plain(server),
+                Err(_) => {
+                    fatal!("Failed to open {} for HTTP Stats listener", address);
+                }
+            },
+        };
+        let token = match http.token() {
+            Ok(token) => token,
+            Err(e) => {
+                fatal!("Failed to read HTTP bearer token: {}", e);
+            }
+        };
+        let history = config.exposition().history();
         Self {
-            snapshot: MetricsSnapshot::new(metrics, count_label),
-            server: server.unwrap(),
+            compression: http.compression(),
+            connections: Arc::new(AtomicUsize::new(0)),
+            histograms,
+            history: History::new(
+                history.statistics(),
+                Duration::from_secs(history.window() as u64),
+                Duration::from_millis(history.resolution() as u64),
+            ),
+            listener,
+            requests: channel(),
+            snapshot: MetricsSnapshot::new(
+                metrics,
+                config.general().reading_suffix(),
+                config.exposition().flat_names(),
+            ),
+            token,
             updated: Instant::now(),
         }
     }
 
     pub fn run(&mut self) {
-        if let Ok(Some(request)) = self.server.try_recv() {
-            if self.updated.elapsed() >= Duration::from_millis(500) {
-                self.snapshot.refresh();
-                self.updated = Instant::now();
+        if self.history.due() {
+            self.record_history();
+        }
+        match self.listener {
+            Listener::Plain(ref server) => {
+                if let Ok(Some(request)) = server.try_recv() {
+                    let header = |name: &str| {
+                        request
+                            .headers()
+                            .iter()
+                            .find(|h| h.field.equiv(name))
+                            .map(|h| h.value.as_str().to_string())
+                    };
+                    let authorization = header("Authorization");
+                    let accept_encoding = header("Accept-Encoding");
+                    let (status, body) = self.route(
+                        &request.method().to_string(),
+                        request.url(),
+                        authorization.as_deref(),
+                    );
+                    let mut response = match self.encoding(accept_encoding.as_deref(), &body) {
+                        Some(encoding) => {
+                            let mut response =
+                                Response::from_data(encoding.encode(body.as_bytes()))
+                                    .with_status_code(status);
+                            response.add_header(
+                                Header::from_bytes(
+                                    &b"Content-Type"[..],
+                                    &b"text/plain; charset=UTF-8"[..],
+                                )
+                                .unwrap(),
+                            );
+                            response.add_header(
+                                Header::from_bytes(&b"Content-Encoding"[..], encoding.name())
+                                    .unwrap(),
+                            );
+                            response
+                        }
+                        None => Response::from_string(body).with_status_code(status),
+                    };
+                    if status == 401 {
+                        response.add_header(
+                            Header::from_bytes(&b"WWW-Authenticate"[..], &b"Bearer"[..]).unwrap(),
+                        );
+                    }
+                    let _ = request.respond(response);
+                }
+            }
+            Listener::Tls(ref listener, ref acceptor) => {
+                if let Ok((stream, peer)) = listener.accept() {
+                    let acceptor = acceptor.clone();
+                    let requests = self.requests.0.clone();
+                    self.spawn_connection(move || {
+                        let _ = stream.set_nonblocking(false);
+                        let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+                        let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+                        // client certificates, if required, are verified
+                        // during the handshake
+                        match acceptor.accept(stream) {
+                            Ok(mut stream) => {
+                                serve(&mut stream, &requests);
+                                let _ = stream.shutdown();
+                            }
+                            Err(e) => debug!("TLS handshake with {} failed: {}", peer, e),
+                        }
+                    });
+                }
+            }
+            Listener::Unix(ref listener) => {
+                if let Ok((mut stream, _)) = listener.accept() {
+                    let requests = self.requests.0.clone();
+                    self.spawn_connection(move || {
+                        let _ = stream.set_nonblocking(false);
+                        let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+                        let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+                        serve(&mut stream, &requests);
+                    });
+                }
+            }
+        }
+        while let Ok(request) = self.requests.1.try_recv() {
+            let reply = self.handle(&request.head);
+            let _ = request.reply.send(reply);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    /// Serves a TLS or unix socket connection on its own thread, so that a
+    /// slow client doesn't hold up the main loop. The connection is closed
+    /// if too many are already being served.
+    fn spawn_connection<F: FnOnce() + Send + 'static>(&self, serve: F) {
+        if self.connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+            self.connections.fetch_sub(1, Ordering::Relaxed);
+            debug!("too many connections, closing new connection");
+            return;
+        }
+        let connection = Connection(self.connections.clone());
+        let spawned = std::thread::Builder::new()
+            .name("http-connection".to_string())
+            .spawn(move || {
+                let _connection = connection;
+                serve();
+            });
+        if let Err(e) = spawned {
+            error!("failed to spawn thread for connection: {}", e);
+        }
+    }
+
+    /// Routes the request read from a TLS or unix socket connection
+    fn handle(&mut self, head: &str) -> Reply {
+        let (status, body) = match parse_request(head) {
+            Some((method, url, authorization)) => {
+                self.route(&method, &url, authorization.as_deref())
+            }
+            None => (400, String::new()),
+        };
+        let encoding = self.encoding(header(head, "Accept-Encoding").as_deref(), &body);
+        let body = match encoding {
+            Some(encoding) => encoding.encode(body.as_bytes()),
+            None => body.into_bytes(),
+        };
+        let mut headers = Vec::new();
+        if let Some(encoding) = encoding {
+            headers.push(("Content-Encoding", encoding.name()));
+        }
+        if status == 401 {
+            headers.push(("WWW-Authenticate", "Bearer"));
+        }
+        (status, headers, body)
+    }
+
+    /// Records the current value of each reading and percentile in the history
+    fn record_history(&mut self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let values = self
+            .snapshot
+            .metrics
+            .snapshot()
+            .into_iter()
+            .map(|(metric, value)| {
+                let name = match metric.output() {
+                    Output::Reading => metric.statistic().name().to_string(),
+                    Output::Percentile(percentile) => {
+                        format!("{}/histogram/p{:02}", metric.statistic().name(), percentile)
+                    }
+                };
+                (name, value)
+            });
+        self.history.record(timestamp, values);
+    }
+
+    /// Serves the recent values of the statistics matching the `metric` query
+    /// parameter, over the last `range` if it is given
+    fn history(&self, query: &str) -> (u16, String) {
+        if !self.history.enabled() {
+            return (404, "history is not enabled\n".to_string());
+        }
+        let param = |key: &str| {
+            query
+                .split('&')
+                .find_map(|param| param.strip_prefix(key)?.strip_prefix('='))
+        };
+        let metric = match param("metric") {
+            Some(metric) => percent_decode(metric),
+            None => return (400, "metric is required\n".to_string()),
+        };
+        let range = match param("range") {
+            Some(range) => match parse_range(range) {
+                Some(range) => Some(range),
+                None => return (400, format!("invalid range: {}\n", range)),
+            },
+            None => None,
+        };
+        match self.history.query(&metric, range) {
+            Some(content) => (200, content),
+            None => (404, format!("no history for: {}\n", metric)),
+        }
+    }
+
+    /// The encoding to compress a response with, if compression is enabled,
+    /// the client accepts it, and the response is large enough to benefit
+    fn encoding(&self, accept_encoding: Option<&str>, body: &str) -> Option<Encoding> {
+        if !self.compression || body.len() < MIN_COMPRESSED_SIZE {
+            return None;
+        }
+        accept_encoding.and_then(preferred_encoding)
+    }
+
+    /// Serves the JSON format in the version given by the `version` query
+    /// parameter, which defaults to 1
+    fn json(&self, query: &str) -> (u16, String) {
+        let version = query
+            .split('&')
+            .find_map(|param| param.strip_prefix("version="))
+            .unwrap_or("1");
+        match version
+            .parse()
+            .ok()
+            .and_then(|version| self.snapshot.json_version(version))
+        {
+            Some(content) => (200, content),
+            None => {
+                debug!("unsupported json version: {}", version);
+                (
+                    400,
+                    format!(
+                        "unsupported version: {}, supported versions: {:?}\n",
+                        version, JSON_VERSIONS
+                    ),
+                )
+            }
+        }
+    }
+
+    /// Returns the status code and body of the response for a request
+    fn route(&mut self, method: &str, url: &str, authorization: Option<&str>) -> (u16, String) {
+        if let Some(ref token) = self.token {
+            if !authorized(token, authorization) {
+                debug!("rejecting unauthorized request for: {}", url);
+                return (401, String::new());
             }
-            let url = request.url();
-            let parts: Vec<&str> = url.split('?').collect();
-            let url = parts[0];
-            match request.method() {
-                Method::Get => match url {
-                    "/" => {
-                        debug!("Serving GET on index");
-                        let _ = request.respond(Response::from_string(format!(
+        }
+        if self.updated.elapsed() >= Duration::from_millis(500) {
+            self.snapshot.refresh();
+            self.updated = Instant::now();
+        }
+        let parts: Vec<&str> = url.split('?').collect();
+        let url = parts[0];
+        let query = parts.get(1).copied().unwrap_or_default();
+        match method {
+            "GET" => match url {
+                "/" => {
+                    debug!("Serving GET on index");
+                    (
+                        200,
+                        format!(
                             "Welcome to {}\nVersion: {}\n",
                             crate::config::NAME,
                             crate::config::VERSION,
-                        )));
-                    }
-                    "/metrics" => {
-                        debug!("Serving Prometheus compatible stats");
-                        let _ = request.respond(Response::from_string(self.snapshot.prometheus()));
-                    }
-                    "/metrics.json" | "/vars.json" | "/admin/metrics.json" => {
-                        debug!("Serving machine readable stats");
-                        let _ = request.respond(Response::from_string(self.snapshot.json(false)));
-                    }
-                    "/vars" => {
-                        debug!("Serving human readable stats");
-                        let _ = request.respond(Response::from_string(self.snapshot.human()));
-                    }
-                    url => {
-                        debug!("GET on non-existent url: {}", url);
-                        debug!("Serving machine readable stats");
-                        let _ = request.respond(Response::from_string(self.snapshot.json(false)));
-                    }
-                },
-                method => {
-                    debug!("unsupported request method: {}", method);
-                    let _ = request.respond(Response::empty(404));
+                        ),
+                    )
+                }
+                "/metrics" => {
+                    debug!("Serving Prometheus compatible stats");
+                    (200, prometheus(&self.snapshot, &self.histograms))
+                }
+                "/metrics.json" | "/vars.json" | "/admin/metrics.json" => {
+                    debug!("Serving machine readable stats");
+                    self.json(query)
+                }
+                "/history" => {
+                    debug!("Serving history");
+                    self.history(query)
+                }
+                "/metadata.json" => {
+                    debug!("Serving metric metadata");
+                    (200, self.snapshot.metadata())
+                }
+                "/vars" => {
+                    debug!("Serving human readable stats");
+                    (200, self.snapshot.human())
+                }
+                url => {
+                    debug!("GET on non-existent url: {}", url);
+                    debug!("Serving machine readable stats");
+                    (200, self.snapshot.json(false))
                 }
+            },
+            method => {
+                debug!("unsupported request method: {}", method);
+                (404, String::new())
             }
         }
-        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+/// Reads a request from a TLS or unix socket connection, and writes the
+/// response once the main loop has routed it
+fn serve<S: Read + Write>(stream: &mut S, requests: &Sender<Request>) {
+    let head = match read_head(stream) {
+        Some(head) => head,
+        None => return,
+    };
+    let (reply, response) = channel();
+    if requests.send(Request { head, reply }).is_err() {
+        return;
+    }
+    if let Ok((status, headers, body)) = response.recv() {
+        respond(stream, status, &headers, &body);
+    }
+}
+
+fn tls_listener(
+    address: SocketAddr,
+    certificate: &str,
+    private_key: &str,
+    client_ca: Option<String>,
+) -> Result<Listener, anyhow::Error> {
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+    acceptor.set_certificate_chain_file(certificate)?;
+    acceptor.set_private_key_file(private_key, SslFiletype::PEM)?;
+    acceptor.check_private_key()?;
+    if let Some(client_ca) = client_ca {
+        acceptor.set_ca_file(client_ca)?;
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    }
+    let listener = TcpListener::bind(address)?;
+    // the listener is polled along with the rest of the main loop
+    listener.set_nonblocking(true)?;
+    Ok(Listener::Tls(listener, acceptor.build()))
+}
+
+/// Chooses the supported encoding with the highest quality from an
+/// `Accept-Encoding` header, preferring gzip when they are equal
+/// The content of `/metrics`, which is the metrics followed by the histograms
+/// of the selected distributions, each of which are a separate family
+fn prometheus(snapshot: &MetricsSnapshot, histograms: &Histograms) -> String {
+    let mut content = snapshot.prometheus();
+    content += &histograms.prometheus(snapshot.flat_names);
+    content
+}
+
+fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut preferred: Option<(Encoding, f64)> = None;
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_lowercase();
+        let quality = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|q| q.trim().parse().ok())
+            .unwrap_or(1.0);
+        let encoding = match name.as_str() {
+            "gzip" | "x-gzip" | "*" => Encoding::Gzip,
+            "deflate" => Encoding::Deflate,
+            _ => continue,
+        };
+        let better = preferred.map_or(true, |(_, q)| {
+            quality > q || (encoding == Encoding::Gzip && quality >= q)
+        });
+        if quality > 0.0 && better {
+            preferred = Some((encoding, quality));
+        }
+    }
+    preferred.map(|(encoding, _)| encoding)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preferred_encoding() {
+        assert_eq!(preferred_encoding("gzip"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("deflate, gzip"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("br, deflate"), Some(Encoding::Deflate));
+        assert_eq!(
+            preferred_encoding("gzip;q=0.5, deflate;q=0.8"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(preferred_encoding("gzip;q=0, identity"), None);
+        assert_eq!(preferred_encoding("*"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("br"), None);
+    }
+
+    #[test]
+    fn test_prometheus() {
+        use crate::samplers::disk::DiskStatistic;
+
+        let metrics = Arc::new(Metrics::<AtomicU64, AtomicU32>::new());
+        let statistic = DiskStatistic::LatencyRead;
+        metrics.register(&statistic);
+        metrics.add_output(&statistic, Output::Percentile(50.0));
+        metrics.add_summary(
+            &statistic,
+            Summary::heatmap(1_000_000_000, 2, Duration::new(60, 0), Duration::new(1, 0)),
+        );
+        let histograms = Histograms::new(vec!["disk/read/latency".to_string()]);
+        let _ = metrics.record_bucket(&statistic, Instant::now(), 3, 1);
+        histograms.record(statistic.name(), 3, 1);
+        let mut snapshot = MetricsSnapshot::new(metrics, None, false);
+        snapshot.refresh();
+
+        let content = prometheus(&snapshot, &histograms);
+        assert!(content.contains("# TYPE disk_read_latency gauge\n"));
+        assert!(content.contains("# TYPE disk_read_latency_histogram histogram\n"));
+
+        // Prometheus rejects a scrape which types a family more than once
+        let mut families: Vec<&str> = content
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+            .filter_map(|line| line.split(' ').next())
+            .collect();
+        let count = families.len();
+        families.sort_unstable();
+        families.dedup();
+        assert_eq!(families.len(), count);
+    }
+
+    #[test]
+    fn test_encode() {
+        use flate2::read::{GzDecoder, ZlibDecoder};
+
+        let body = "cpu_usage_user 1\n".repeat(100);
+        let mut decoded = String::new();
+        GzDecoder::new(&Encoding::Gzip.encode(body.as_bytes())[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        let mut decoded = String::new();
+        ZlibDecoder::new(&Encoding::Deflate.encode(body.as_bytes())[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
     }
 }
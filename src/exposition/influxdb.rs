@@ -0,0 +1,222 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use reqwest::blocking::Client;
+use rustcommon_atomics::AtomicBool;
+use rustcommon_metrics::*;
+
+use crate::common::labels;
+use crate::config::Config;
+use crate::exposition::{pause, MetricsSnapshot};
+
+/// Timeout for each write to the server
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct InfluxdbExporter {
+    bucket: String,
+    client: Client,
+    /// cleared when the exporter should make its final push
+    exporting: Arc<AtomicBool>,
+    interval: Duration,
+    org: String,
+    snapshot: MetricsSnapshot,
+    /// tags attached to every point, sorted by key
+    tags: Vec<(String, String)>,
+    token: Option<String>,
+    url: String,
+}
+
+impl InfluxdbExporter {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let influxdb = config.exposition().influxdb();
+        let token = match influxdb.token() {
+            Ok(token) => token,
+            Err(e) => {
+                fatal!("Failed to read InfluxDB token: {}", e);
+            }
+        };
+        let client = match Client::builder().timeout(TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                fatal!("Failed to create InfluxDB client: {}", e);
+            }
+        };
+        // the labels of the host are sent as tags, unless a tag of the same
+        // name is configured
+        let mut tags: Vec<(String, String)> = labels::host()
+            .into_iter()
+            .filter(|(k, _)| !influxdb.tags().contains_key(k))
+            .chain(influxdb.tags().iter().map(|(k, v)| (k.clone(), v.clone())))
+            .collect();
+        tags.sort();
+        Self {
+            bucket: influxdb.bucket().unwrap_or_default(),
+            client,
+            exporting,
+            interval: Duration::from_millis(influxdb.interval().try_into().unwrap()),
+            org: influxdb.org().unwrap_or_default(),
+            snapshot: MetricsSnapshot::new(metrics, None, config.exposition().flat_names()),
+            tags,
+            token,
+            url: format!("{}/api/v2/write", influxdb.url().trim_end_matches('/')),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let start = Instant::now();
+        if let Err(e) = self.flush() {
+            error!("failed to write metrics to influxdb: {}", e);
+        }
+        let stop = Instant::now();
+        if start + self.interval > stop {
+            pause(self.interval - (stop - start), &self.exporting);
+        }
+    }
+
+    /// Sends the metrics one last time when stopping
+    pub fn finish(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("failed to write final metrics to influxdb: {}", e);
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), reqwest::Error> {
+        self.snapshot.refresh();
+        let body = self.lines().join("\n");
+        let mut request = self
+            .client
+            .post(&self.url)
+            .query(&[
+                ("org", self.org.as_str()),
+                ("bucket", self.bucket.as_str()),
+                ("precision", "ms"),
+            ])
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(body);
+        if let Some(ref token) = self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        request.send()?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Converts the current snapshot into line protocol. Each statistic is a
+    /// measurement with its dimensions as tags, and its reading and
+    /// percentiles as the `value` and `pXX` fields of a single point.
+    fn lines(&self) -> Vec<String> {
+        let timestamp = self
+            .snapshot
+            .refreshed
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_millis())
+            .unwrap_or(0);
+        let mut points: BTreeMap<(String, Vec<(String, String)>), Vec<(String, u64)>> =
+            BTreeMap::new();
+        for (metric, value) in &self.snapshot.snapshot {
+            let statistic = metric.statistic().name();
+            let mut tags = self.tags.clone();
+            let measurement = match self.snapshot.labels(statistic) {
+                Some(labels) => {
+                    tags.extend(
+                        labels
+                            .labels
+                            .into_iter()
+                            .map(|(key, value)| (key.to_string(), value)),
+                    );
+                    labels.name
+                }
+                None => statistic.to_string(),
+            };
+            tags.sort();
+            let field = match metric.output() {
+                Output::Reading => "value".to_string(),
+                Output::Percentile(percentile) => format!("p{:02}", percentile),
+            };
+            points
+                .entry((measurement, tags))
+                .or_default()
+                .push((field, *value));
+        }
+        points
+            .into_iter()
+            .map(|((measurement, tags), mut fields)| {
+                fields.sort();
+                format_line(&measurement, &tags, &fields, timestamp)
+            })
+            .collect()
+    }
+}
+
+/// Formats a point of the form `measurement,tag=value field=valuei timestamp`
+fn format_line(
+    measurement: &str,
+    tags: &[(String, String)],
+    fields: &[(String, u64)],
+    timestamp: u128,
+) -> String {
+    let mut line = escape(measurement, &[',', ' ']);
+    for (key, value) in tags {
+        // empty tag values are not allowed
+        if value.is_empty() {
+            continue;
+        }
+        line += &format!(
+            ",{}={}",
+            escape(key, &[',', '=', ' ']),
+            escape(value, &[',', '=', ' '])
+        );
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}i", escape(key, &[',', '=', ' ']), value))
+        .collect();
+    format!("{} {} {}", line, fields.join(","), timestamp)
+}
+
+/// Escapes the given characters, and backslashes, with a backslash
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::new();
+    // newlines end a point, so they are replaced
+    for c in value.chars().map(|c| if c == '\n' { ' ' } else { c }) {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line(
+                "disk/latency",
+                &[
+                    ("device".to_string(), "sda".to_string()),
+                    ("role".to_string(), "cache, primary".to_string()),
+                ],
+                &[("p50".to_string(), 1), ("p99".to_string(), 7)],
+                1_600_000_000_000,
+            ),
+            "disk/latency,device=sda,role=cache\\,\\ primary p50=1i,p99=7i 1600000000000"
+        );
+        assert_eq!(
+            format_line("cpu/usage/user", &[], &[("value".to_string(), 42)], 1),
+            "cpu/usage/user value=42i 1"
+        );
+    }
+}
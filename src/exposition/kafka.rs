@@ -6,42 +6,115 @@ use std::convert::TryInto;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use kafka::client::Compression;
 use kafka::producer::{Producer, Record};
-use rustcommon_metrics_legacy::*;
+use rustcommon_atomics::AtomicBool;
+use rustcommon_metrics::*;
 
-use crate::config::Config;
-use crate::exposition::MetricsSnapshot;
+use crate::config::{Config, KafkaCompression, KafkaFormat};
+use crate::exposition::{pause, MetricsSnapshot, JSON_VERSIONS};
 
 pub struct KafkaProducer {
+    batch_size: usize,
+    /// cleared when the producer should make its final push
+    exporting: Arc<AtomicBool>,
+    format: KafkaFormat,
+    /// snapshots which have not yet been produced
+    pending: Vec<Vec<u8>>,
     snapshot: MetricsSnapshot,
     producer: Producer,
     topic: String,
     interval: Duration,
+    version: u64,
 }
 
 impl KafkaProducer {
-    pub fn new(config: Arc<Config>, metrics: Arc<Metrics<AtomicU32>>) -> Self {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let kafka = config.exposition().kafka();
+        let version = kafka.version();
+        if !JSON_VERSIONS.contains(&version) {
+            fatal!("unsupported json version for kafka: {}", version);
+        }
+        let compression = match kafka.compression() {
+            KafkaCompression::None => Compression::NONE,
+            KafkaCompression::Gzip => Compression::GZIP,
+            KafkaCompression::Snappy => Compression::SNAPPY,
+        };
+        let producer = match Producer::from_hosts(kafka.hosts())
+            .with_compression(compression)
+            .create()
+        {
+            Ok(producer) => producer,
+            Err(e) => {
+                fatal!("Failed to create kafka producer: {}", e);
+            }
+        };
         Self {
-            snapshot: MetricsSnapshot::new(metrics, config.general().reading_suffix()),
-            producer: Producer::from_hosts(config.exposition().kafka().hosts())
-                .create()
-                .unwrap(),
-            topic: config.exposition().kafka().topic().unwrap(),
-            interval: Duration::from_millis(
-                config.exposition().kafka().interval().try_into().unwrap(),
+            batch_size: kafka.batch_size(),
+            exporting,
+            format: kafka.format(),
+            pending: Vec::new(),
+            snapshot: MetricsSnapshot::new(
+                metrics,
+                config.general().reading_suffix(),
+                config.exposition().flat_names(),
             ),
+            producer,
+            topic: kafka.topic().unwrap(),
+            interval: Duration::from_millis(kafka.interval().try_into().unwrap()),
+            version,
         }
     }
 
     pub fn run(&mut self) {
         let start = Instant::now();
-        self.snapshot.refresh();
-        let _ = self
-            .producer
-            .send(&Record::from_value(&self.topic, self.snapshot.json(false)));
+        self.record();
+        if self.pending.len() >= self.batch_size {
+            self.send();
+        }
         let stop = Instant::now();
         if start + self.interval > stop {
-            std::thread::sleep(self.interval - (stop - start));
+            pause(self.interval - (stop - start), &self.exporting);
+        }
+    }
+
+    /// Sends the metrics one last time when stopping, along with any which
+    /// are waiting to fill a batch
+    pub fn finish(&mut self) {
+        self.record();
+        self.send();
+    }
+
+    /// Adds a snapshot of the metrics to the pending batch
+    fn record(&mut self) {
+        self.snapshot.refresh();
+        let value = match self.format {
+            KafkaFormat::Json => self
+                .snapshot
+                .json_version(self.version)
+                .unwrap_or_default()
+                .into_bytes(),
+            KafkaFormat::Protobuf => self.snapshot.protobuf(),
+        };
+        self.pending.push(value);
+    }
+
+    /// Produces the pending snapshots as a single request. They are dropped
+    /// if it fails, rather than growing without bound while the brokers are
+    /// unavailable.
+    fn send(&mut self) {
+        let records: Vec<Record<(), &[u8]>> = self
+            .pending
+            .iter()
+            .map(|value| Record::from_value(&self.topic, value.as_slice()))
+            .collect();
+        if let Err(e) = self.producer.send_all(&records) {
+            error!("failed to send {} records to kafka: {}", records.len(), e);
         }
+        self.pending.clear();
     }
 }
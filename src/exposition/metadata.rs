@@ -0,0 +1,231 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Help text and units for each statistic, taken from the descriptions in
+//! `docs/METRICS.md` so that they are documented in only one place.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::wildcard;
+
+const METRICS: &str = include_str!("../../docs/METRICS.md");
+
+// units which may be named in a description, in order of preference
+const UNITS: &[(&str, &str)] = &[
+    ("nanoseconds", "nanoseconds"),
+    ("microseconds", "microseconds"),
+    ("milliseconds", "milliseconds"),
+    ("seconds", "seconds"),
+    ("bytes", "bytes"),
+    ("kilobytes", "kilobytes"),
+    ("pages", "pages"),
+    ("°c", "celsius"),
+    ("watts", "watts"),
+    ("microjoules", "microjoules"),
+    ("joules", "joules"),
+    ("mhz", "megahertz"),
+    ("hz", "hertz"),
+    ("percentage", "percent"),
+    ("percent", "percent"),
+];
+
+/// The documented help text and unit of a statistic
+#[derive(Clone, Debug, PartialEq)]
+pub struct Description {
+    pub help: String,
+    pub unit: Option<&'static str>,
+}
+
+struct Entry {
+    /// the documented name, with placeholders replaced by `*`
+    pattern: String,
+    /// the namespace which short names in the section are exported under
+    namespace: Option<String>,
+    description: Description,
+}
+
+/// Looks up the descriptions of statistics by name
+pub struct Metadata {
+    entries: Vec<Entry>,
+    cache: Mutex<HashMap<String, Option<usize>>>,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new(METRICS)
+    }
+}
+
+impl Metadata {
+    /// Parses the bulleted `` * `name` - description `` entries of the
+    /// document. Short names are matched within the namespace of their section,
+    /// which is given by its first `` `namespace/...` `` form.
+    pub fn new(document: &str) -> Self {
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut namespace = None;
+        let mut bullet = false;
+        for line in document.lines() {
+            if line.starts_with("## ") {
+                namespace = None;
+                bullet = false;
+            } else if let Some(entry) = line.strip_prefix("* `") {
+                bullet = false;
+                if let Some((name, help)) = entry.split_once("` - ") {
+                    entries.push(Entry {
+                        pattern: placeholders(name),
+                        namespace: namespace.clone(),
+                        description: Description {
+                            help: help.trim().to_string(),
+                            unit: None,
+                        },
+                    });
+                    bullet = true;
+                }
+            } else if bullet && line.starts_with("  ") {
+                let help = &mut entries.last_mut().unwrap().description.help;
+                help.push(' ');
+                help.push_str(line.trim());
+            } else {
+                bullet = false;
+                if namespace.is_none() {
+                    namespace = line
+                        .split('`')
+                        .skip(1)
+                        .step_by(2)
+                        .find(|form| form.ends_with("/..."))
+                        .and_then(|form| form.split('/').next())
+                        .map(|v| v.to_string());
+                }
+            }
+        }
+        for entry in &mut entries {
+            entry.description.unit = unit(&entry.description.help);
+        }
+        Self {
+            entries,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the description of the named statistic, if it is documented
+    pub fn describe(&self, name: &str) -> Option<Description> {
+        let mut cache = self.cache.lock().unwrap();
+        let index = *cache
+            .entry(name.to_string())
+            .or_insert_with(|| self.find(name));
+        index.map(|index| self.entries[index].description.clone())
+    }
+
+    // an exact match is preferred, and otherwise the longest pattern
+    fn find(&self, name: &str) -> Option<usize> {
+        let mut best: Option<((bool, usize), usize)> = None;
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (exact, matched) = match entry.namespace {
+                Some(ref namespace) if !entry.pattern.starts_with(&format!("{}/", namespace)) => (
+                    false,
+                    wildcard(&format!("{}/*/{}", namespace, entry.pattern), name),
+                ),
+                _ => (entry.pattern == name, wildcard(&entry.pattern, name)),
+            };
+            if !matched {
+                continue;
+            }
+            let rank = (exact, entry.pattern.len());
+            if best.map_or(true, |(best, _)| rank > best) {
+                best = Some((rank, index));
+            }
+        }
+        best.map(|(_, index)| index)
+    }
+}
+
+/// Replaces placeholders such as `[device]` and `{DEVICE}` with `*`
+fn placeholders(name: &str) -> String {
+    let mut pattern = String::new();
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '[' | '{' | '<' => {
+                if depth == 0 {
+                    pattern.push('*');
+                }
+                depth += 1;
+            }
+            ']' | '}' | '>' if depth > 0 => depth -= 1,
+            c if depth == 0 => pattern.push(c),
+            _ => {}
+        }
+    }
+    pattern
+}
+
+/// The first unit from `UNITS` which is named in the description
+fn unit(help: &str) -> Option<&'static str> {
+    let help = help.to_lowercase();
+    let words: Vec<&str> = help
+        .split(|c: char| !(c.is_alphanumeric() || c == '°'))
+        .collect();
+    UNITS
+        .iter()
+        .find(|(word, _)| words.contains(word))
+        .map(|(_, unit)| *unit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DOCUMENT: &str = "\
+## Disk
+
+### Basic
+
+* `disk/read/bytes` - bytes read from all disks
+* `disk/read/latency` - distribution of read latency in
+  nanoseconds
+* `disk/[device]/read/bytes` - bytes read from the device
+
+## Thermal
+
+Statistics are exported with the form `thermal/{ZONE}/...`.
+
+* `temperature` - current temperature in °C
+";
+
+    #[test]
+    fn test_describe() {
+        let metadata = Metadata::new(DOCUMENT);
+        assert_eq!(
+            metadata.describe("disk/read/latency"),
+            Some(Description {
+                help: "distribution of read latency in nanoseconds".to_string(),
+                unit: Some("nanoseconds"),
+            })
+        );
+        assert_eq!(
+            metadata.describe("disk/read/bytes").unwrap().help,
+            "bytes read from all disks"
+        );
+        assert_eq!(
+            metadata.describe("disk/sda/read/bytes").unwrap().help,
+            "bytes read from the device"
+        );
+        assert_eq!(
+            metadata.describe("thermal/zone0/temperature").unwrap().unit,
+            Some("celsius")
+        );
+        assert_eq!(metadata.describe("temperature"), None);
+        assert_eq!(metadata.describe("disk/write/bytes"), None);
+    }
+
+    #[test]
+    fn test_documented() {
+        let metadata = Metadata::default();
+        assert_eq!(
+            metadata.describe("cpu/usage/user").unwrap().unit,
+            Some("nanoseconds")
+        );
+    }
+}
@@ -2,64 +2,189 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use rustcommon_atomics::{Atomic, AtomicBool, Ordering};
 use rustcommon_metrics::*;
 
+use crate::common::labels::{self, Labels};
+
+mod graphite;
+mod history;
+mod host;
 mod http;
+mod influxdb;
 #[cfg(feature = "push_kafka")]
 mod kafka;
+mod metadata;
+#[cfg(feature = "push_otlp")]
+mod otlp;
+#[cfg(feature = "push_kafka")]
+mod protobuf;
+mod recorder;
+mod statsd;
 
+pub use self::graphite::GraphiteExporter;
+pub use self::host::host_labels;
 pub use self::http::Http;
+pub use self::influxdb::InfluxdbExporter;
 #[cfg(feature = "push_kafka")]
 pub use self::kafka::KafkaProducer;
+#[cfg(feature = "push_otlp")]
+pub use self::otlp::OtlpExporter;
+pub use self::recorder::{dump as dump_recording, Recorder};
+pub use self::statsd::StatsdExporter;
+
+use self::metadata::Metadata;
+
+/// The versions of the JSON format which may be requested. Version 1 is a flat
+/// map of names to values, and version 2 describes each value.
+pub const JSON_VERSIONS: &[u64] = &[1, 2];
+
+// how often a paused exporter checks whether it should stop
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// Sleeps for the duration, returning early once `exporting` is cleared so that
+/// an exporter may make its final push promptly when stopping
+fn pause(duration: Duration, exporting: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while exporting.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        std::thread::sleep(std::cmp::min(deadline - now, PAUSE_POLL));
+    }
+}
 
 pub struct MetricsSnapshot {
     metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
     snapshot: HashMap<Metric<AtomicU64, AtomicU32>, u64>,
-    refreshed: Instant,
+    refreshed: SystemTime,
     count_label: Option<String>,
+    flat_names: bool,
+    /// the labels of the host in the Prometheus text format
+    host: Vec<String>,
+    metadata: Metadata,
+}
+
+/// The samples of a metric in the Prometheus text format
+struct Family {
+    /// the name of the first statistic of the metric, for its description
+    statistic: String,
+    /// whether the metric may be typed as a counter
+    counter: bool,
+    samples: Vec<String>,
 }
 
 impl MetricsSnapshot {
-    pub fn new(metrics: Arc<Metrics<AtomicU64, AtomicU32>>, count_label: Option<&str>) -> Self {
+    pub fn new(
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        count_label: Option<&str>,
+        flat_names: bool,
+    ) -> Self {
         Self {
             metrics,
             snapshot: HashMap::new(),
-            refreshed: Instant::now(),
+            refreshed: SystemTime::now(),
             count_label: count_label.map(std::string::ToString::to_string),
+            flat_names,
+            host: labels::prometheus(&labels::host()),
+            metadata: Metadata::default(),
         }
     }
 
     pub fn refresh(&mut self) {
         self.snapshot = self.metrics.snapshot();
-        self.refreshed = Instant::now();
+        self.refreshed = SystemTime::now();
+    }
+
+    /// Returns the labels of the named statistic, unless statistics are
+    /// exported with flat names
+    pub fn labels(&self, name: &str) -> Option<Labels> {
+        if self.flat_names {
+            None
+        } else {
+            labels::labels(name)
+        }
     }
 
+    /// Returns the metrics in the Prometheus text format, where each metric is
+    /// preceded by its help text, unit, and type
     pub fn prometheus(&self) -> String {
-        let mut data = Vec::new();
+        let mut families: BTreeMap<String, Family> = BTreeMap::new();
         for (metric, value) in &self.snapshot {
-            let label = metric.statistic().name();
-            let output = metric.output();
-            match output {
-                Output::Reading => {
-                    data.push(format!("# TYPE {} gauge\n{} {}", label, label, value));
+            let statistic = metric.statistic().name();
+            let mut labels = self.host.clone();
+            let name = match self.labels(statistic) {
+                Some(statistic) => {
+                    labels.extend(statistic.prometheus());
+                    statistic.name.replace('/', "_")
                 }
-                Output::Percentile(percentile) => {
-                    data.push(format!(
-                        "# TYPE {} gauge\n{}{{percentile=\"{:02}\"}} {}",
-                        label, label, percentile, value
-                    ));
+                None => statistic.replace('/', "_"),
+            };
+            let family = families.entry(name.clone()).or_insert_with(|| Family {
+                statistic: statistic.to_string(),
+                counter: true,
+                samples: Vec::new(),
+            });
+            family.counter &= metric.statistic().source() == Source::Counter;
+            if let Output::Percentile(percentile) = metric.output() {
+                // percentiles of a counter are of its rate
+                family.counter = false;
+                labels.push(format!("percentile=\"{:02}\"", percentile));
+            }
+            if labels.is_empty() {
+                family.samples.push(format!("{} {}", name, value));
+            } else {
+                family
+                    .samples
+                    .push(format!("{}{{{}}} {}", name, labels.join(","), value));
+            }
+        }
+        let mut content = String::new();
+        for (name, mut family) in families {
+            if let Some(description) = self.metadata.describe(&family.statistic) {
+                content += &format!("# HELP {} {}\n", name, escape_help(&description.help));
+                if let Some(unit) = description.unit {
+                    content += &format!("# UNIT {} {}\n", name, unit);
                 }
             }
+            let kind = if family.counter { "counter" } else { "gauge" };
+            content += &format!("# TYPE {} {}\n", name, kind);
+            family.samples.sort();
+            for sample in family.samples {
+                content += &sample;
+                content += "\n";
+            }
         }
-        data.sort();
-        let mut content = data.join("\n");
-        content += "\n";
-        let parts: Vec<&str> = content.split('/').collect();
-        parts.join("_")
+        content
+    }
+
+    /// Returns the type, unit, and help text of each metric as JSON
+    pub fn metadata(&self) -> String {
+        let mut names: Vec<(&str, Source)> = self
+            .snapshot
+            .keys()
+            .map(|metric| (metric.statistic().name(), metric.statistic().source()))
+            .collect();
+        names.sort_by(|a, b| a.0.cmp(b.0));
+        names.dedup_by(|a, b| a.0 == b.0);
+        let mut content = json::JsonValue::new_object();
+        for (name, source) in names {
+            let mut entry = json::JsonValue::new_object();
+            let _ = entry.insert("type", kind(source));
+            if let Some(description) = self.metadata.describe(name) {
+                if let Some(unit) = description.unit {
+                    let _ = entry.insert("unit", unit);
+                }
+                let _ = entry.insert("help", description.help);
+            }
+            let _ = content.insert(name, entry);
+        }
+        content.dump()
     }
 
     pub fn human(&self) -> String {
@@ -86,6 +211,137 @@ impl MetricsSnapshot {
         content
     }
 
+    /// Returns the metrics in the requested version of the JSON format, or
+    /// `None` if the version is not supported
+    pub fn json_version(&self, version: u64) -> Option<String> {
+        match version {
+            1 => Some(self.json(false)),
+            2 => Some(self.json_v2()),
+            _ => None,
+        }
+    }
+
+    /// Version 2 of the JSON format, which lists each reading and percentile
+    /// with its type, unit, labels, and the time of the snapshot in
+    /// milliseconds since the epoch. New fields may be added to the objects,
+    /// but the existing fields will not change within a version.
+    fn json_v2(&self) -> String {
+        let timestamp = self
+            .refreshed
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let mut host = json::JsonValue::new_object();
+        for (key, value) in labels::host() {
+            let _ = host.insert(&key, value);
+        }
+
+        let mut entries = Vec::new();
+        for (metric, value) in &self.snapshot {
+            let statistic = metric.statistic();
+            let mut entry = json::JsonValue::new_object();
+            let _ = entry.insert("name", statistic.name());
+            let _ = entry.insert("type", kind(statistic.source()));
+            let percentile = match metric.output() {
+                Output::Reading => None,
+                Output::Percentile(percentile) => Some(percentile),
+            };
+            if let Some(percentile) = percentile {
+                let _ = entry.insert("percentile", percentile);
+            }
+            if let Some(unit) = self
+                .metadata
+                .describe(statistic.name())
+                .and_then(|description| description.unit)
+            {
+                let _ = entry.insert("unit", unit);
+            }
+            if let Some(labels) = self.labels(statistic.name()) {
+                let mut object = json::JsonValue::new_object();
+                for (key, value) in labels.labels {
+                    let _ = object.insert(key, value);
+                }
+                let _ = entry.insert("metric", labels.name);
+                let _ = entry.insert("labels", object);
+            }
+            let _ = entry.insert("value", *value);
+            let _ = entry.insert("timestamp", timestamp);
+            entries.push((statistic.name(), percentile.unwrap_or(-1.0), entry));
+        }
+        entries.sort_by(|a, b| {
+            a.0.cmp(b.0)
+                .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        });
+
+        let mut content = json::JsonValue::new_object();
+        let _ = content.insert("version", 2);
+        let _ = content.insert("timestamp", timestamp);
+        let _ = content.insert("labels", host);
+        let _ = content.insert(
+            "metrics",
+            json::JsonValue::Array(entries.into_iter().map(|(_, _, entry)| entry).collect()),
+        );
+        content.dump()
+    }
+
+    /// The `Snapshot` message of `proto/rezolus.proto`, which has the same
+    /// fields as version 2 of the JSON format
+    #[cfg(feature = "push_kafka")]
+    pub fn protobuf(&self) -> Vec<u8> {
+        use self::protobuf::Message;
+
+        let timestamp = self
+            .refreshed
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let mut content = Message::new();
+        content.uint64(1, 2);
+        content.uint64(2, timestamp);
+        for (key, value) in labels::host() {
+            content.map_entry(3, &key, &value);
+        }
+
+        let mut entries = Vec::new();
+        for (metric, value) in &self.snapshot {
+            let statistic = metric.statistic();
+            let mut entry = Message::new();
+            entry.string(1, statistic.name());
+            entry.string(2, kind(statistic.source()));
+            let percentile = match metric.output() {
+                Output::Reading => None,
+                Output::Percentile(percentile) => Some(percentile),
+            };
+            if let Some(percentile) = percentile {
+                entry.double(3, percentile);
+            }
+            if let Some(unit) = self
+                .metadata
+                .describe(statistic.name())
+                .and_then(|description| description.unit)
+            {
+                entry.string(4, unit);
+            }
+            if let Some(labels) = self.labels(statistic.name()) {
+                entry.string(5, &labels.name);
+                for (key, value) in labels.labels {
+                    entry.map_entry(6, key, &value);
+                }
+            }
+            entry.uint64(7, *value);
+            entry.uint64(8, timestamp);
+            entries.push((statistic.name(), percentile.unwrap_or(-1.0), entry));
+        }
+        entries.sort_by(|a, b| {
+            a.0.cmp(b.0)
+                .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        for (_, _, entry) in entries {
+            content.message(4, &entry);
+        }
+        content.into_bytes()
+    }
+
     fn json(&self, pretty: bool) -> String {
         let mut head = "{".to_owned();
         if pretty {
@@ -126,3 +382,17 @@ impl MetricsSnapshot {
         content
     }
 }
+
+/// Escapes help text for the Prometheus text format
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// The name of the type of a statistic
+fn kind(source: Source) -> &'static str {
+    match source {
+        Source::Counter => "counter",
+        Source::Gauge => "gauge",
+        Source::Distribution => "distribution",
+    }
+}
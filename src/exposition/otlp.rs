@@ -0,0 +1,232 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric, number_data_point, AggregationTemporality, Gauge, InstrumentationLibraryMetrics,
+    Metric as OtlpMetric, NumberDataPoint, ResourceMetrics, Sum,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use rustcommon_atomics::{Atomic, AtomicBool, Ordering};
+use rustcommon_metrics::*;
+use tonic::metadata::{MetadataKey, MetadataValue};
+use tonic::transport::Channel;
+
+use crate::common::labels;
+use crate::config::Config;
+use crate::exposition::{MetricsSnapshot, PAUSE_POLL};
+
+pub struct OtlpExporter {
+    client: Option<MetricsServiceClient<Channel>>,
+    endpoint: String,
+    /// cleared when the exporter should make its final push
+    exporting: Arc<AtomicBool>,
+    headers: Vec<(String, String)>,
+    interval: Duration,
+    resource: Resource,
+    snapshot: MetricsSnapshot,
+    start: u64,
+}
+
+impl OtlpExporter {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let otlp = config.exposition().otlp();
+
+        let mut attributes = vec![
+            string_attribute("service.name", crate::config::NAME),
+            string_attribute("service.version", crate::config::VERSION),
+        ];
+        let host = labels::host();
+        for (key, value) in host
+            .iter()
+            .map(|(k, v)| (k, v))
+            .chain(otlp.resource_attributes())
+        {
+            attributes.retain(|a| &a.key != key);
+            attributes.push(string_attribute(key, value));
+        }
+
+        Self {
+            client: None,
+            endpoint: otlp.endpoint(),
+            exporting,
+            headers: otlp
+                .headers()
+                .iter()
+                .map(|(k, v)| (k.to_lowercase(), v.clone()))
+                .collect(),
+            interval: Duration::from_millis(otlp.interval().try_into().unwrap()),
+            resource: Resource {
+                attributes,
+                dropped_attributes_count: 0,
+            },
+            snapshot: MetricsSnapshot::new(metrics, None, config.exposition().flat_names()),
+            start: unix_nanos(),
+        }
+    }
+
+    pub async fn run(&mut self) {
+        let start = Instant::now();
+        if let Err(e) = self.export().await {
+            error!("failed to export metrics to {}: {}", self.endpoint, e);
+            // force a reconnect on the next attempt
+            self.client = None;
+        }
+        let stop = Instant::now();
+        if start + self.interval > stop {
+            self.pause(self.interval - (stop - start)).await;
+        }
+    }
+
+    /// Exports the metrics one last time when stopping
+    pub async fn finish(&mut self) {
+        if let Err(e) = self.export().await {
+            error!("failed to export final metrics to {}: {}", self.endpoint, e);
+        }
+    }
+
+    /// Sleeps for the duration, returning early once the exporter should
+    /// make its final push
+    async fn pause(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        while self.exporting.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            tokio::time::sleep(std::cmp::min(deadline - now, PAUSE_POLL)).await;
+        }
+    }
+
+    async fn export(&mut self) -> Result<(), anyhow::Error> {
+        if self.client.is_none() {
+            debug!("connecting to otlp endpoint: {}", self.endpoint);
+            self.client = Some(MetricsServiceClient::connect(self.endpoint.clone()).await?);
+        }
+
+        self.snapshot.refresh();
+
+        let mut request = tonic::Request::new(ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(self.resource.clone()),
+                instrumentation_library_metrics: vec![InstrumentationLibraryMetrics {
+                    instrumentation_library: None,
+                    metrics: self.metrics(),
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        });
+        for (key, value) in &self.headers {
+            request.metadata_mut().insert(
+                MetadataKey::from_bytes(key.as_bytes())?,
+                MetadataValue::from_str(value)?,
+            );
+        }
+
+        if let Some(client) = self.client.as_mut() {
+            client.export(request).await?;
+        }
+        Ok(())
+    }
+
+    /// Converts the current snapshot into OTLP metrics, with a single metric
+    /// for all of the data points with the same name. Counter readings are
+    /// exported as cumulative monotonic sums, and all other readings as
+    /// gauges. Percentiles are exported as gauges named `<name>/histogram`,
+    /// so that their type doesn't conflict with that of the reading.
+    fn metrics(&self) -> Vec<OtlpMetric> {
+        let now = unix_nanos();
+        // whether each metric is a sum, and its data points
+        let mut metrics: BTreeMap<String, (bool, Vec<NumberDataPoint>)> = BTreeMap::new();
+        for (metric, value) in &self.snapshot.snapshot {
+            // the dimensions of a statistic are exported as attributes
+            let (name, mut attributes) = match self.snapshot.labels(metric.statistic().name()) {
+                Some(labels) => {
+                    let attributes = labels
+                        .labels
+                        .iter()
+                        .map(|(key, value)| string_attribute(key, value))
+                        .collect();
+                    (labels.name, attributes)
+                }
+                None => (metric.statistic().name().to_string(), Vec::new()),
+            };
+            let (name, sum) = match metric.output() {
+                Output::Reading => (name, metric.statistic().source() == Source::Counter),
+                Output::Percentile(percentile) => {
+                    attributes.push(string_attribute(
+                        "percentile",
+                        &format!("{:02}", percentile),
+                    ));
+                    (format!("{}/histogram", name), false)
+                }
+            };
+            let point = self.data_point(now, *value, attributes);
+            metrics
+                .entry(name)
+                .or_insert_with(|| (sum, Vec::new()))
+                .1
+                .push(point);
+        }
+        metrics
+            .into_iter()
+            .map(|(name, (sum, data_points))| {
+                let data = if sum {
+                    metric::Data::Sum(Sum {
+                        data_points,
+                        aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                        is_monotonic: true,
+                    })
+                } else {
+                    metric::Data::Gauge(Gauge { data_points })
+                };
+                OtlpMetric {
+                    name,
+                    description: String::new(),
+                    unit: String::new(),
+                    data: Some(data),
+                }
+            })
+            .collect()
+    }
+
+    fn data_point(&self, now: u64, value: u64, attributes: Vec<KeyValue>) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            start_time_unix_nano: self.start,
+            time_unix_nano: now,
+            value: Some(number_data_point::Value::AsInt(value as i64)),
+            ..Default::default()
+        }
+    }
+}
+
+fn string_attribute(key: &str, value: &str) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value.to_string())),
+        }),
+    }
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,97 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! A minimal protocol buffers encoder for the snapshot messages described in
+//! `proto/rezolus.proto`, which avoids depending on a code generator for two
+//! small messages.
+
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LENGTH_DELIMITED: u32 = 2;
+
+/// An encoded message, which fields are appended to
+#[derive(Default)]
+pub struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn uint64(&mut self, field: u32, value: u64) {
+        self.key(field, VARINT);
+        self.varint(value);
+    }
+
+    pub fn double(&mut self, field: u32, value: f64) {
+        self.key(field, FIXED64);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    pub fn message(&mut self, field: u32, value: &Message) {
+        self.bytes(field, &value.buf);
+    }
+
+    /// Appends an entry of a `map<string, string>` field
+    pub fn map_entry(&mut self, field: u32, key: &str, value: &str) {
+        let mut entry = Message::new();
+        entry.string(1, key);
+        entry.string(2, value);
+        self.message(field, &entry);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, LENGTH_DELIMITED);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint(((field << 3) | wire_type) as u64);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let mut message = Message::new();
+        message.uint64(1, 150);
+        message.string(2, "testing");
+        assert_eq!(
+            message.into_bytes(),
+            vec![0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g']
+        );
+
+        let mut message = Message::new();
+        message.double(3, 1.0);
+        message.map_entry(6, "a", "b");
+        assert_eq!(
+            message.into_bytes(),
+            vec![
+                0x19, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, 0x32, 0x06, 0x0a, 0x01, b'a', 0x12, 0x01, b'b'
+            ]
+        );
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! A flight recorder, which continuously writes snapshots of the metrics to a
+//! bounded ring of segment files so that they survive the loss of network
+//! connectivity, and can be dumped for post-mortem analysis.
+//!
+//! Each segment is a sequence of frames, which are the length of the frame as
+//! a little endian `u32` followed by a gzip compressed snapshot in version 2
+//! of the JSON format. A frame which was cut short, such as by a crash, ends
+//! the segment.
+
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rustcommon_atomics::AtomicBool;
+use rustcommon_metrics::*;
+
+use crate::config::Config;
+use crate::exposition::{pause, MetricsSnapshot};
+
+const SEGMENT_PREFIX: &str = "rezolus-";
+const SEGMENT_SUFFIX: &str = ".rec";
+
+struct Segment {
+    file: File,
+    size: u64,
+}
+
+pub struct Recorder {
+    directory: PathBuf,
+    /// cleared when the recorder should make its final snapshot
+    exporting: Arc<AtomicBool>,
+    interval: Duration,
+    segment: Option<Segment>,
+    segment_size: u64,
+    segments: usize,
+    snapshot: MetricsSnapshot,
+}
+
+impl Recorder {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let recorder = config.exposition().recorder();
+        let directory = PathBuf::from(recorder.directory());
+        if let Err(e) = std::fs::create_dir_all(&directory) {
+            fatal!(
+                "Failed to create recording directory {}: {}",
+                directory.display(),
+                e
+            );
+        }
+        Self {
+            directory,
+            exporting,
+            interval: Duration::from_millis(recorder.interval().try_into().unwrap()),
+            segment: None,
+            segment_size: recorder.segment_size(),
+            segments: recorder.segments(),
+            snapshot: MetricsSnapshot::new(metrics, None, config.exposition().flat_names()),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let start = Instant::now();
+        if let Err(e) = self.record() {
+            error!("failed to record metrics: {}", e);
+            // start a new segment on the next attempt
+            self.segment = None;
+        }
+        let stop = Instant::now();
+        if start + self.interval > stop {
+            pause(self.interval - (stop - start), &self.exporting);
+        }
+    }
+
+    /// Records the metrics one last time when stopping
+    pub fn finish(&mut self) {
+        if let Err(e) = self.record() {
+            error!("failed to record final metrics: {}", e);
+        }
+    }
+
+    fn record(&mut self) -> Result<(), std::io::Error> {
+        self.snapshot.refresh();
+        let frame = frame(self.snapshot.json_version(2).unwrap_or_default().as_bytes())?;
+
+        let full = match self.segment {
+            Some(ref segment) => {
+                segment.size > 0 && segment.size + frame.len() as u64 > self.segment_size
+            }
+            None => true,
+        };
+        if full {
+            self.start_segment()?;
+        }
+        if let Some(ref mut segment) = self.segment {
+            segment.file.write_all(&frame)?;
+            segment.file.sync_data()?;
+            segment.size += frame.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Starts the segment after the latest one, removing the oldest segments
+    /// so that no more than the configured number are kept
+    fn start_segment(&mut self) -> Result<(), std::io::Error> {
+        let mut existing = segments(&self.directory)?;
+        let sequence = existing
+            .last()
+            .map(|(sequence, _)| sequence + 1)
+            .unwrap_or(0);
+        let path = self.directory.join(format!(
+            "{}{:010}{}",
+            SEGMENT_PREFIX, sequence, SEGMENT_SUFFIX
+        ));
+        debug!("starting recording segment: {}", path.display());
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)?;
+        existing.push((sequence, path));
+        while existing.len() > self.segments {
+            let (_, oldest) = existing.remove(0);
+            debug!("removing recording segment: {}", oldest.display());
+            std::fs::remove_file(oldest)?;
+        }
+        self.segment = Some(Segment { file, size: 0 });
+        Ok(())
+    }
+}
+
+/// Compresses a snapshot and prefixes it with its length
+fn frame(snapshot: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(snapshot)?;
+    let compressed = encoder.finish()?;
+    let mut frame = (compressed.len() as u32).to_le_bytes().to_vec();
+    frame.extend_from_slice(&compressed);
+    Ok(frame)
+}
+
+/// The segments in the directory, in the order they were written
+fn segments(directory: &Path) -> Result<Vec<(u64, PathBuf)>, std::io::Error> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let sequence = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(SEGMENT_PREFIX))
+            .and_then(|name| name.strip_suffix(SEGMENT_SUFFIX))
+            .and_then(|sequence| sequence.parse().ok());
+        if let Some(sequence) = sequence {
+            segments.push((sequence, path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// Reads the snapshots in a segment, stopping at the first frame which is
+/// incomplete or can't be decompressed, or whose length is more than `limit`
+/// bytes, as a corrupt length would otherwise allocate up to 4GiB
+fn read_segment<R: Read>(mut reader: R, limit: u64) -> Vec<String> {
+    let mut snapshots = Vec::new();
+    loop {
+        let mut length = [0; 4];
+        if reader.read_exact(&mut length).is_err() {
+            break;
+        }
+        let length = u32::from_le_bytes(length);
+        if length as u64 > limit {
+            break;
+        }
+        let mut compressed = vec![0; length as usize];
+        if reader.read_exact(&mut compressed).is_err() {
+            break;
+        }
+        let mut snapshot = String::new();
+        if GzDecoder::new(&compressed[..])
+            .read_to_string(&mut snapshot)
+            .is_err()
+        {
+            break;
+        }
+        snapshots.push(snapshot);
+    }
+    snapshots
+}
+
+/// Writes each snapshot of the recording in the directory to stdout, either
+/// as a line of JSON or as CSV rows, and returns the exit code
+pub fn dump(directory: &str, format: &str) -> i32 {
+    let segments = match segments(Path::new(directory)) {
+        Ok(segments) => segments,
+        Err(e) => {
+            eprintln!("{}: {}", directory, e);
+            return 1;
+        }
+    };
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    if format == "csv" {
+        let _ = writeln!(stdout, "timestamp,name,labels,percentile,value");
+    }
+    for (_, path) in segments {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                continue;
+            }
+        };
+        // the first frame of a segment may be larger than the segment size,
+        // so frames are limited to the length of the file they are read from
+        let limit = match file.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                continue;
+            }
+        };
+        for snapshot in read_segment(std::io::BufReader::new(file), limit) {
+            let content = match format {
+                "csv" => csv(&snapshot),
+                _ => snapshot,
+            };
+            if content.is_empty() {
+                continue;
+            }
+            // stop quietly when the output is closed, such as by `head`
+            if writeln!(stdout, "{}", content).is_err() {
+                return 0;
+            }
+        }
+    }
+    0
+}
+
+/// Converts a snapshot into CSV rows of its timestamp, statistic name, labels,
+/// percentile, and value. The labels are written as `key=value` pairs which
+/// are separated by `;`
+fn csv(snapshot: &str) -> String {
+    let snapshot = match json::parse(snapshot) {
+        Ok(snapshot) => snapshot,
+        Err(_) => return String::new(),
+    };
+    let rows: Vec<String> = snapshot["metrics"]
+        .members()
+        .map(|metric| {
+            let name = metric["name"].as_str().unwrap_or_default();
+            let labels: Vec<String> = metric["labels"]
+                .entries()
+                .map(|(key, value)| format!("{}={}", key, value.as_str().unwrap_or_default()))
+                .collect();
+            let percentile = metric["percentile"]
+                .as_f64()
+                .map(|p| p.to_string())
+                .unwrap_or_default();
+            format!(
+                "{},{},{},{},{}",
+                metric["timestamp"],
+                field(name),
+                field(&labels.join(";")),
+                percentile,
+                metric["value"]
+            )
+        })
+        .collect();
+    rows.join("\n")
+}
+
+/// Quotes a CSV field if it contains a separator or quote
+fn field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_segment() {
+        let mut segment = frame(b"{\"version\":2}").unwrap();
+        segment.extend(frame(b"{\"version\":2,\"metrics\":[]}").unwrap());
+        // a frame which was cut short is ignored
+        let partial = frame(b"{}").unwrap();
+        segment.extend_from_slice(&partial[..partial.len() - 1]);
+        assert_eq!(
+            read_segment(&segment[..], segment.len() as u64),
+            vec!["{\"version\":2}", "{\"version\":2,\"metrics\":[]}"]
+        );
+    }
+
+    #[test]
+    fn test_read_segment_limit() {
+        let mut segment = frame(b"{\"version\":2}").unwrap();
+        // a corrupt length ends the segment without allocating the frame
+        segment.extend_from_slice(&u32::MAX.to_le_bytes());
+        segment.extend(frame(b"{}").unwrap());
+        assert_eq!(
+            read_segment(&segment[..], segment.len() as u64),
+            vec!["{\"version\":2}"]
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let snapshot = "{\"version\":2,\"metrics\":[\
+            {\"name\":\"cpu/usage/user\",\"type\":\"counter\",\"value\":5,\"timestamp\":1000},\
+            {\"name\":\"disk/read/latency\",\"type\":\"distribution\",\"percentile\":99.9,\
+            \"value\":7,\"timestamp\":1000},\
+            {\"name\":\"cgroups/web/cpu/user\",\"type\":\"counter\",\"metric\":\"cgroups/cpu/user\",\
+            \"labels\":{\"cgroup\":\"web\",\"pod\":\"a,b\"},\"value\":3,\"timestamp\":1000}]}";
+        assert_eq!(
+            csv(snapshot),
+            "1000,cpu/usage/user,,,5\n\
+            1000,disk/read/latency,,99.9,7\n\
+            1000,cgroups/web/cpu/user,\"cgroup=web;pod=a,b\",,3"
+        );
+    }
+}
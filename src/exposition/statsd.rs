@@ -0,0 +1,281 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rustcommon_atomics::AtomicBool;
+use rustcommon_metrics::*;
+
+use crate::common::labels;
+use crate::config::{Config, StatsdFormat};
+use crate::exposition::{pause, MetricsSnapshot};
+
+enum Socket {
+    Udp(UdpSocket),
+    Unix(UnixDatagram),
+}
+
+impl Socket {
+    fn send(&self, buf: &[u8]) -> Result<usize, std::io::Error> {
+        match self {
+            Self::Udp(socket) => socket.send(buf),
+            Self::Unix(socket) => socket.send(buf),
+        }
+    }
+}
+
+pub struct StatsdExporter {
+    address: String,
+    /// previous value of each counter, used to send the change since the last
+    /// flush as StatsD counters are deltas
+    counters: HashMap<String, u64>,
+    /// cleared when the exporter should make its final push
+    exporting: Arc<AtomicBool>,
+    format: StatsdFormat,
+    interval: Duration,
+    max_packet_size: usize,
+    path: Option<String>,
+    prefix: String,
+    snapshot: MetricsSnapshot,
+    socket: Option<Socket>,
+    tags: Vec<String>,
+}
+
+impl StatsdExporter {
+    pub fn new(
+        config: Arc<Config>,
+        metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+        exporting: Arc<AtomicBool>,
+    ) -> Self {
+        let statsd = config.exposition().statsd();
+        // the labels of the host are sent as tags, unless a tag of the same
+        // name is configured
+        let mut tags: Vec<String> = labels::host()
+            .iter()
+            .filter(|(k, _)| !statsd.tags().contains_key(k))
+            .map(|(k, v)| (k, v))
+            .chain(statsd.tags().iter())
+            .map(|(k, v)| format!("{}:{}", k, v))
+            .collect();
+        tags.sort();
+        Self {
+            address: statsd.address(),
+            counters: HashMap::new(),
+            exporting,
+            format: statsd.format(),
+            interval: Duration::from_millis(statsd.interval().try_into().unwrap()),
+            max_packet_size: statsd.max_packet_size(),
+            path: statsd.socket(),
+            prefix: statsd.prefix(),
+            snapshot: MetricsSnapshot::new(metrics, None, config.exposition().flat_names()),
+            socket: None,
+            tags,
+        }
+    }
+
+    pub fn run(&mut self) {
+        let start = Instant::now();
+        if let Err(e) = self.flush() {
+            error!("failed to send metrics to statsd: {}", e);
+            // force the socket to be recreated on the next attempt
+            self.socket = None;
+        }
+        let stop = Instant::now();
+        if start + self.interval > stop {
+            pause(self.interval - (stop - start), &self.exporting);
+        }
+    }
+
+    /// Sends the metrics one last time when stopping
+    pub fn finish(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("failed to send final metrics to statsd: {}", e);
+        }
+    }
+
+    fn connect(&self) -> Result<Socket, std::io::Error> {
+        if let Some(ref path) = self.path {
+            debug!("connecting to statsd socket: {}", path);
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(path)?;
+            Ok(Socket::Unix(socket))
+        } else {
+            debug!("connecting to statsd address: {}", self.address);
+            let address = self.address.to_socket_addrs()?.next().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "bad statsd address")
+            })?;
+            let local = if address.is_ipv6() {
+                "[::]:0"
+            } else {
+                "0.0.0.0:0"
+            };
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(address)?;
+            Ok(Socket::Udp(socket))
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        if self.socket.is_none() {
+            self.socket = Some(self.connect()?);
+        }
+
+        self.snapshot.refresh();
+        let lines = self.lines();
+
+        if let Some(ref socket) = self.socket {
+            for packet in packets(&lines, self.max_packet_size) {
+                socket.send(packet.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the current snapshot into StatsD lines. Counter readings are
+    /// sent as the change since the previous flush, all other readings and
+    /// percentiles are sent as gauges. Percentiles and the dimensions of a
+    /// statistic are distinguished by tags in the DogStatsD format and by the
+    /// name otherwise.
+    fn lines(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (metric, value) in &self.snapshot.snapshot {
+            let statistic = metric.statistic().name();
+            let (name, mut tags) = match self.snapshot.labels(statistic) {
+                Some(labels) if self.format == StatsdFormat::Dogstatsd => {
+                    let tags = labels
+                        .labels
+                        .iter()
+                        .map(|(key, value)| format!("{}:{}", key, value.replace(',', "_")))
+                        .collect();
+                    (labels.name, tags)
+                }
+                _ => (statistic.to_string(), Vec::new()),
+            };
+            match metric.output() {
+                Output::Reading => {
+                    if metric.statistic().source() == Source::Counter {
+                        let previous = self.counters.insert(statistic.to_string(), *value);
+                        // counters are sent once there is a previous value,
+                        // and restart from zero if they have been reset
+                        if let Some(previous) = previous {
+                            let delta = value.checked_sub(previous).unwrap_or(*value);
+                            lines.push(self.line(&name, delta, "c", &tags));
+                        }
+                    } else {
+                        lines.push(self.line(&name, *value, "g", &tags));
+                    }
+                }
+                Output::Percentile(percentile) => match self.format {
+                    StatsdFormat::Dogstatsd => {
+                        tags.push(format!("percentile:{:02}", percentile));
+                        lines.push(self.line(&name, *value, "g", &tags));
+                    }
+                    StatsdFormat::Statsd => {
+                        let name = format!("{}/histogram/p{:02}", name, percentile);
+                        lines.push(self.line(&name, *value, "g", &tags));
+                    }
+                },
+            }
+        }
+        lines.sort();
+        lines
+    }
+
+    fn line(&self, name: &str, value: u64, kind: &str, tags: &[String]) -> String {
+        let mut all = Vec::new();
+        if self.format == StatsdFormat::Dogstatsd {
+            all.extend(self.tags.iter().map(|t| t.as_str()));
+            all.extend(tags.iter().map(|t| t.as_str()));
+        }
+        format_line(&self.prefix, name, value, kind, &all)
+    }
+}
+
+/// Formats a single StatsD line of the form `prefix.name:value|kind`, with
+/// DogStatsD tags of the form `|#key:value,...` appended if present
+fn format_line(prefix: &str, name: &str, value: u64, kind: &str, tags: &[&str]) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' => '.',
+            ':' | '|' | '@' | '#' | ',' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect();
+    let mut line = if prefix.is_empty() {
+        format!("{}:{}|{}", name, value, kind)
+    } else {
+        format!("{}.{}:{}|{}", prefix, name, value, kind)
+    };
+    if !tags.is_empty() {
+        line += "|#";
+        line += &tags.join(",");
+    }
+    line
+}
+
+/// Joins lines into newline separated packets which are no larger than the
+/// maximum size, unless a single line exceeds it
+fn packets(lines: &[String], max_size: usize) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > max_size {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet += line;
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line("rezolus", "cpu/usage/user", 42, "c", &[]),
+            "rezolus.cpu.usage.user:42|c"
+        );
+        assert_eq!(
+            format_line("", "cgroups/system.slice/a:b/memory/current", 1, "g", &[]),
+            "cgroups.system.slice.a_b.memory.current:1|g"
+        );
+        assert_eq!(
+            format_line(
+                "rezolus",
+                "cpu/usage",
+                7,
+                "g",
+                &["env:prod", "percentile:99"]
+            ),
+            "rezolus.cpu.usage:7|g|#env:prod,percentile:99"
+        );
+    }
+
+    #[test]
+    fn test_packets() {
+        let lines = vec![
+            "a:1|c".to_string(),
+            "b:2|c".to_string(),
+            "c:3|c".to_string(),
+        ];
+        assert_eq!(packets(&lines, 1432), vec!["a:1|c\nb:2|c\nc:3|c"]);
+        assert_eq!(packets(&lines, 11), vec!["a:1|c\nb:2|c", "c:3|c"]);
+        assert_eq!(packets(&lines, 2), vec!["a:1|c", "b:2|c", "c:3|c"]);
+    }
+}
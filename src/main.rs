@@ -8,33 +8,43 @@ extern crate rustcommon_logger;
 #[macro_use]
 extern crate anyhow;
 
-use rustcommon_atomics::{Atomic, Ordering};
+#[cfg(all(feature = "bpf", feature = "bpf_core"))]
+compile_error!("the bpf and bpf_core features are mutually exclusive");
+
+use std::sync::mpsc::channel;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use rustcommon_atomics::AtomicBool;
-use rustcommon_logger::Logger;
+use rustcommon_atomics::{Atomic, AtomicBool, Ordering};
+use rustcommon_logger::{Level, Logger};
 use rustcommon_metrics::*;
 use tokio::runtime::Builder;
+use tokio::signal::unix::{signal, SignalKind};
 
+mod admin;
 mod common;
 mod config;
 mod exposition;
 mod samplers;
 
+use common::histograms::Histograms;
+use common::logger::{JsonLogger, LogLevels};
 use common::*;
-use config::Config;
+use config::{Config, ConfigHandle, LogFormat};
 use samplers::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // get config
     let config = Arc::new(Config::new());
 
-    // initialize logging
-    Logger::new()
-        .label(common::NAME)
-        .level(config.logging())
-        .init()
-        .expect("Failed to initialize logger");
+    // initialize logging. The logger accepts every level so that the levels
+    // may be changed at runtime through the admin API or signals
+    let log_levels = LogLevels::new(config.logging().to_level_filter());
+    let logger: Box<dyn log::Log> = match config.general().log_format() {
+        LogFormat::Text => Box::new(Logger::new().label(common::NAME).level(Level::Trace)),
+        LogFormat::Json => Box::new(JsonLogger),
+    };
+    common::logger::init(logger, log_levels.clone()).expect("Failed to initialize logger");
 
     info!("----------");
     info!("{} {}", common::NAME, common::VERSION);
@@ -46,15 +56,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     debug!("host cores: {}", hardware_threads().unwrap_or(1));
 
-    let runnable = Arc::new(AtomicBool::new(true));
-    let r = runnable.clone();
+    // labels which describe the host are attached to every exported metric
+    let host_labels = exposition::host_labels(config.exposition().labels());
+    debug!("host labels: {:?}", host_labels);
+    common::labels::set_host(host_labels);
 
-    // initialize signal handler
-    debug!("initializing signal handler");
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::Relaxed);
-    })
-    .expect("Failed to set handler for SIGINT / SIGTERM");
+    let runnable = Arc::new(AtomicBool::new(true));
 
     // initialize metrics
     debug!("initializing metrics");
@@ -74,50 +81,282 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // spawn samplers
     debug!("spawning samplers");
-    let common = Common::new(config.clone(), metrics.clone(), runtime);
-    Cpu::spawn(common.clone());
-    Disk::spawn(common.clone());
-    Ext4::spawn(common.clone());
-    Http::spawn(common.clone());
-    Interrupt::spawn(common.clone());
-    Krb5kdc::spawn(common.clone());
-    Memcache::spawn(common.clone());
-    Memory::spawn(common.clone());
-    PageCache::spawn(common.clone());
-    Network::spawn(common.clone());
-    Ntp::spawn(common.clone());
-    Nvidia::spawn(common.clone());
-    Rezolus::spawn(common.clone());
-    Scheduler::spawn(common.clone());
-    Softnet::spawn(common.clone());
-    Tcp::spawn(common.clone());
-    Udp::spawn(common.clone());
-    Usercall::spawn(common.clone());
-    Xfs::spawn(common);
+    let config_handle = ConfigHandle::new(config.clone());
+    let histograms = Arc::new(Histograms::new(config.exposition().http().histograms()));
+    let common = Common::new(
+        config_handle.clone(),
+        metrics.clone(),
+        runtime.clone(),
+        histograms.clone(),
+    );
+    let registry = Registry::default();
+    spawn_samplers(&common, &registry);
+
+    // restart samplers which stop sampling
+    debug!("starting watchdog");
+    {
+        let common = common.clone();
+        let registry = registry.clone();
+        let _ = std::thread::Builder::new()
+            .name("watchdog".to_string())
+            .spawn(move || watchdog(common, registry));
+    }
+
+    // initialize signal handler
+    debug!("initializing signal handler");
+    runtime.spawn(handle_signals(
+        runnable.clone(),
+        config_handle.clone(),
+        common.clone(),
+        registry.clone(),
+        log_levels.clone(),
+    ));
+
+    if let Some(address) = config.admin().listen() {
+        debug!("starting admin listener");
+        let mut admin = admin::Admin::new(
+            address,
+            config.admin().listen_mode(),
+            config_handle.clone(),
+            common,
+            registry.clone(),
+            log_levels,
+        );
+        let _ = std::thread::Builder::new()
+            .name("admin".to_string())
+            .spawn(move || loop {
+                admin.run();
+            });
+    }
+
+    // push exporters run until the samplers have stopped, then make a final
+    // push and report that they are done
+    let exporting = Arc::new(AtomicBool::new(true));
+    let (exported, exporters_done) = channel();
+    let mut exporters = 0;
 
     #[cfg(feature = "push_kafka")]
     {
         if config.exposition().kafka().enabled() {
             let mut kafka_producer =
-                exposition::KafkaProducer::new(config.clone(), metrics.clone());
-            let _ = std::thread::Builder::new()
+                exposition::KafkaProducer::new(config.clone(), metrics.clone(), exporting.clone());
+            let exporting = exporting.clone();
+            let exported = exported.clone();
+            if std::thread::Builder::new()
                 .name("kafka".to_string())
-                .spawn(move || loop {
-                    kafka_producer.run();
-                });
+                .spawn(move || {
+                    while exporting.load(Ordering::Relaxed) {
+                        kafka_producer.run();
+                    }
+                    kafka_producer.finish();
+                    let _ = exported.send(());
+                })
+                .is_ok()
+            {
+                exporters += 1;
+            }
+        }
+    }
+
+    #[cfg(feature = "push_otlp")]
+    {
+        if config.exposition().otlp().enabled() {
+            let mut otlp_exporter =
+                exposition::OtlpExporter::new(config.clone(), metrics.clone(), exporting.clone());
+            let exporting = exporting.clone();
+            let exported = exported.clone();
+            runtime.spawn(async move {
+                while exporting.load(Ordering::Relaxed) {
+                    otlp_exporter.run().await;
+                }
+                otlp_exporter.finish().await;
+                let _ = exported.send(());
+            });
+            exporters += 1;
+        }
+    }
+
+    if config.exposition().statsd().enabled() {
+        let mut statsd_exporter =
+            exposition::StatsdExporter::new(config.clone(), metrics.clone(), exporting.clone());
+        let exporting = exporting.clone();
+        let exported = exported.clone();
+        if std::thread::Builder::new()
+            .name("statsd".to_string())
+            .spawn(move || {
+                while exporting.load(Ordering::Relaxed) {
+                    statsd_exporter.run();
+                }
+                statsd_exporter.finish();
+                let _ = exported.send(());
+            })
+            .is_ok()
+        {
+            exporters += 1;
+        }
+    }
+
+    if config.exposition().graphite().enabled() {
+        let mut graphite_exporter =
+            exposition::GraphiteExporter::new(config.clone(), metrics.clone(), exporting.clone());
+        let exporting = exporting.clone();
+        let exported = exported.clone();
+        if std::thread::Builder::new()
+            .name("graphite".to_string())
+            .spawn(move || {
+                while exporting.load(Ordering::Relaxed) {
+                    graphite_exporter.run();
+                }
+                graphite_exporter.finish();
+                let _ = exported.send(());
+            })
+            .is_ok()
+        {
+            exporters += 1;
+        }
+    }
+
+    if config.exposition().influxdb().enabled() {
+        let mut influxdb_exporter =
+            exposition::InfluxdbExporter::new(config.clone(), metrics.clone(), exporting.clone());
+        let exporting = exporting.clone();
+        let exported = exported.clone();
+        if std::thread::Builder::new()
+            .name("influxdb".to_string())
+            .spawn(move || {
+                while exporting.load(Ordering::Relaxed) {
+                    influxdb_exporter.run();
+                }
+                influxdb_exporter.finish();
+                let _ = exported.send(());
+            })
+            .is_ok()
+        {
+            exporters += 1;
+        }
+    }
+
+    if config.exposition().recorder().enabled() {
+        let mut recorder =
+            exposition::Recorder::new(config.clone(), metrics.clone(), exporting.clone());
+        let exporting = exporting.clone();
+        let exported = exported.clone();
+        if std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || {
+                while exporting.load(Ordering::Relaxed) {
+                    recorder.run();
+                }
+                recorder.finish();
+                let _ = exported.send(());
+            })
+            .is_ok()
+        {
+            exporters += 1;
         }
     }
+    drop(exported);
 
     debug!("beginning stats exposition");
-    let mut http = exposition::Http::new(
-        config.listen().expect("no listen address"),
-        metrics,
-        config.general().reading_suffix(),
-    );
+    let mut http = exposition::Http::new(config.clone(), metrics, histograms);
+
+    // every enabled sampler has been initialized
+    systemd::ready();
+    if let Some(interval) = systemd::watchdog_interval() {
+        debug!("notifying systemd watchdog every {:?}", interval / 2);
+        runtime.spawn(notify_watchdog(registry.clone(), interval));
+    }
 
     while runnable.load(Ordering::Relaxed) {
         http.run();
     }
 
+    // stop the samplers so that their BPF probes are detached and their final
+    // samples are included in the last push, waiting no longer than the
+    // shutdown timeout in total
+    info!("shutting down");
+    systemd::stopping();
+    let timeout = config_handle.current().general().shutdown_timeout();
+    let deadline = Instant::now() + Duration::from_secs(timeout as u64);
+    registry.stop();
+    let running = registry.wait(deadline);
+    if !running.is_empty() {
+        warn!("samplers did not stop in time: {}", running.join(", "));
+    }
+
+    exporting.store(false, Ordering::Relaxed);
+    for _ in 0..exporters {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if exporters_done.recv_timeout(remaining).is_err() {
+            warn!("exporters did not finish in time");
+            break;
+        }
+    }
+
     Ok(())
 }
+
+/// Notifies the systemd watchdog from the runtime which the samplers run on,
+/// so that notifications stop if the runtime is wedged. They also stop while a
+/// sampler has gone without sampling for the whole watchdog interval, as
+/// restarting the sampler has not recovered it, so systemd restarts the agent.
+async fn notify_watchdog(registry: Registry, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval / 2);
+    loop {
+        ticker.tick().await;
+        let stalled = registry.stalled(interval);
+        if stalled.is_empty() {
+            systemd::watchdog();
+        } else {
+            warn!("samplers have stopped sampling: {}", stalled.join(", "));
+        }
+    }
+}
+
+/// Stops the agent on SIGINT / SIGTERM and reloads the config file on SIGHUP.
+/// Statistics and their history are retained across a reload; samplers pick
+/// up the new config on their next sample, and samplers which have become
+/// enabled are spawned. SIGUSR1 and SIGUSR2 raise and lower the log level.
+async fn handle_signals(
+    runnable: Arc<AtomicBool>,
+    config: ConfigHandle,
+    mut common: Common,
+    registry: Registry,
+    log_levels: LogLevels,
+) {
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to set handler for SIGINT");
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to set handler for SIGTERM");
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to set handler for SIGHUP");
+    let mut user1 = signal(SignalKind::user_defined1()).expect("Failed to set handler for SIGUSR1");
+    let mut user2 = signal(SignalKind::user_defined2()).expect("Failed to set handler for SIGUSR2");
+
+    loop {
+        tokio::select! {
+            _ = interrupt.recv() => runnable.store(false, Ordering::Relaxed),
+            _ = terminate.recv() => runnable.store(false, Ordering::Relaxed),
+            _ = hangup.recv() => {
+                info!("reloading config");
+                match config.reload() {
+                    Ok(_) => {
+                        // samplers spawned after a reload share the metrics
+                        // and overhead of those spawned at startup
+                        common.refresh_config();
+                        // sampler initialization may block
+                        tokio::task::block_in_place(|| spawn_samplers(&common, &registry));
+                    }
+                    Err(e) => {
+                        error!("failed to reload config: {}", e);
+                    }
+                }
+            }
+            _ = user1.recv() => {
+                let level = log_levels.raise();
+                warn!("log level raised to {}", level.to_string().to_lowercase());
+            }
+            _ = user2.recv() => {
+                let level = log_levels.lower();
+                warn!("log level lowered to {}", level.to_string().to_lowercase());
+            }
+        }
+    }
+}
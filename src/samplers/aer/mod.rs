@@ -0,0 +1,174 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_PCI_DEVICES: &str = "/sys/bus/pci/devices";
+
+pub struct Aer {
+    common: Common,
+    /// statistics which have been registered, as pci devices are discovered at
+    /// runtime
+    registered: HashSet<AerStatistic>,
+    statistics: Vec<AerConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Aer {
+    type Statistic = AerStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().aer().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().aer().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize aer sampler {}", e);
+                    } else {
+                        error!("failed to initialize aer sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().aer()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().aer().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_devices().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Aer {
+    fn record(&mut self, statistic: AerStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    /// Samples the error counts of each pci device which supports Advanced
+    /// Error Reporting, devices without AER have no `aer_dev_*` files
+    async fn sample_devices(&mut self) -> Result<(), std::io::Error> {
+        let mut entries = tokio::fs::read_dir(SYS_PCI_DEVICES).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let device = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let path = entry.path().join(statistic.sysfs());
+                if let Ok(content) = tokio::fs::read_to_string(&path).await {
+                    if let Some(value) = parse_total(&content, statistic.total()) {
+                        self.record(AerStatistic::new(&device, statistic), time, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `aer_dev_*` file, which has a line with the count of each type
+/// of error followed by a line with the total, and returns the total
+fn parse_total(content: &str, total: &str) -> Option<u64> {
+    content.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next()? == total {
+            fields.next()?.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_total() {
+        let content = "RxErr 0\n\
+                       BadTLP 3\n\
+                       BadDLLP 1\n\
+                       Rollover 0\n\
+                       Timeout 0\n\
+                       NonFatalErr 0\n\
+                       CorrIntErr 0\n\
+                       HeaderOF 0\n\
+                       TOTAL_ERR_COR 4\n";
+        assert_eq!(parse_total(content, "TOTAL_ERR_COR"), Some(4));
+        assert_eq!(parse_total(content, "TOTAL_ERR_FATAL"), None);
+    }
+}
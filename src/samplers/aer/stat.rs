@@ -0,0 +1,103 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum AerConfigStatistic {
+    #[strum(serialize = "correctable")]
+    Correctable,
+    #[strum(serialize = "fatal")]
+    Fatal,
+    #[strum(serialize = "nonfatal")]
+    Nonfatal,
+}
+
+impl AerConfigStatistic {
+    /// The sysfs file of a pci device with the counts of this class of errors
+    pub fn sysfs(self) -> &'static str {
+        match self {
+            Self::Correctable => "aer_dev_correctable",
+            Self::Fatal => "aer_dev_fatal",
+            Self::Nonfatal => "aer_dev_nonfatal",
+        }
+    }
+
+    /// The line of the sysfs file with the total count of errors
+    pub fn total(self) -> &'static str {
+        match self {
+            Self::Correctable => "TOTAL_ERR_COR",
+            Self::Fatal => "TOTAL_ERR_FATAL",
+            Self::Nonfatal => "TOTAL_ERR_NONFATAL",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for AerConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        AerConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AerStatistic {
+    name: String,
+    statistic: AerConfigStatistic,
+}
+
+impl AerStatistic {
+    /// A statistic of a pci device, which is named `aer/<address>/...`
+    pub fn new(device: &str, statistic: AerConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("aer/{}/{}", device, suffix),
+                format!("aer/{}", suffix),
+                &[("device", device)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> AerConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for AerStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
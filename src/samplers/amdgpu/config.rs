@@ -0,0 +1,76 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AmdgpuConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<AmdgpuConfigStatistic>,
+}
+
+impl Default for AmdgpuConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_statistics() -> Vec<AmdgpuConfigStatistic> {
+    AmdgpuConfigStatistic::iter().collect()
+}
+
+impl SamplerConfig for AmdgpuConfig {
+    type Statistic = AmdgpuStatistic;
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // gpus are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
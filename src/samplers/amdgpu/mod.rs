@@ -0,0 +1,243 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_DRM: &str = "/sys/class/drm";
+
+pub struct Amdgpu {
+    common: Common,
+    /// statistics which have been registered, as gpus are discovered at runtime
+    registered: HashSet<AmdgpuStatistic>,
+    statistics: Vec<AmdgpuConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Amdgpu {
+    type Statistic = AmdgpuStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().amdgpu().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().amdgpu().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize amdgpu sampler {}", e);
+                    } else {
+                        error!("failed to initialize amdgpu sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().amdgpu()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().amdgpu().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_gpus().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Amdgpu {
+    fn record(&mut self, statistic: AmdgpuStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples each GPU driven by `amdgpu`, which are the `card<N>` devices of
+    /// the DRM subsystem. Temperature and power are read from the hwmon
+    /// device of the GPU, and ECC error counts from its RAS interface, which
+    /// is only present on GPUs with ECC memory.
+    async fn sample_gpus(&mut self) -> Result<(), std::io::Error> {
+        let mut entries = tokio::fs::read_dir(SYS_DRM).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let id: u64 = match entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("card"))
+                .and_then(|id| id.parse().ok())
+            {
+                Some(id) => id,
+                None => continue,
+            };
+            let device = entry.path().join("device");
+            match tokio::fs::read_link(device.join("driver")).await {
+                Ok(driver) if driver.ends_with("amdgpu") => {}
+                _ => continue,
+            }
+            let hwmon = hwmon(&device).await;
+            let ecc = match tokio::fs::read_to_string(device.join("ras/umc_err_count")).await {
+                Ok(content) => parse_err_count(&content),
+                Err(_) => None,
+            };
+
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    AmdgpuConfigStatistic::GpuTemperature => match hwmon {
+                        // millidegrees Celsius
+                        Some(ref hwmon) => read_value(hwmon.join("temp1_input"))
+                            .await
+                            .map(|v| v / 1000),
+                        None => None,
+                    },
+                    AmdgpuConfigStatistic::GpuUtilization => {
+                        read_value(device.join("gpu_busy_percent")).await
+                    }
+                    AmdgpuConfigStatistic::MemoryEccCorrectable => ecc.map(|(ce, _)| ce),
+                    AmdgpuConfigStatistic::MemoryEccUncorrectable => ecc.map(|(_, ue)| ue),
+                    AmdgpuConfigStatistic::MemoryVramTotal => {
+                        read_value(device.join("mem_info_vram_total")).await
+                    }
+                    AmdgpuConfigStatistic::MemoryVramUsed => {
+                        read_value(device.join("mem_info_vram_used")).await
+                    }
+                    AmdgpuConfigStatistic::PowerUsage => match hwmon {
+                        Some(ref hwmon) => read_power(hwmon).await,
+                        None => None,
+                    },
+                };
+                if let Some(value) = value {
+                    self.record(AmdgpuStatistic::new(id, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the hwmon device of a GPU, which is the single entry in its `hwmon`
+/// directory
+async fn hwmon(device: &Path) -> Option<PathBuf> {
+    let mut entries = tokio::fs::read_dir(device.join("hwmon")).await.ok()?;
+    let entry = entries.next_entry().await.ok()??;
+    Some(entry.path())
+}
+
+/// Reads the power usage of a GPU in Watts from its hwmon device, which
+/// reports microwatts. Newer GPUs only report instantaneous power rather than
+/// an average.
+async fn read_power(hwmon: &Path) -> Option<u64> {
+    let power = match read_value(hwmon.join("power1_average")).await {
+        Some(power) => power,
+        None => read_value(hwmon.join("power1_input")).await?,
+    };
+    Some((power as f64 / 1_000_000.0).round() as u64)
+}
+
+async fn read_value<P: AsRef<Path>>(path: P) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Parses a RAS error count file, which has the uncorrectable and correctable
+/// error counts as `ue: <count>` and `ce: <count>`. Returns the correctable and
+/// uncorrectable counts.
+fn parse_err_count(content: &str) -> Option<(u64, u64)> {
+    let mut ce = None;
+    let mut ue = None;
+    for line in content.lines() {
+        match line.split_once(':') {
+            Some(("ce", count)) => ce = count.trim().parse().ok(),
+            Some(("ue", count)) => ue = count.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    Some((ce?, ue?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_err_count() {
+        assert_eq!(parse_err_count("ue: 1\nce: 42\n"), Some((42, 1)));
+        assert_eq!(parse_err_count("ue: 0\n"), None);
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum AmdgpuConfigStatistic {
+    #[strum(serialize = "gpu/temperature")]
+    GpuTemperature,
+    #[strum(serialize = "gpu/utilization")]
+    GpuUtilization,
+    #[strum(serialize = "memory/ecc/correctable")]
+    MemoryEccCorrectable,
+    #[strum(serialize = "memory/ecc/uncorrectable")]
+    MemoryEccUncorrectable,
+    #[strum(serialize = "memory/vram/total")]
+    MemoryVramTotal,
+    #[strum(serialize = "memory/vram/used")]
+    MemoryVramUsed,
+    #[strum(serialize = "power/usage")]
+    PowerUsage,
+}
+
+impl AmdgpuConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::MemoryEccCorrectable | Self::MemoryEccUncorrectable => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for AmdgpuConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        AmdgpuConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AmdgpuStatistic {
+    name: String,
+    statistic: AmdgpuConfigStatistic,
+}
+
+impl AmdgpuStatistic {
+    /// A statistic of a GPU, which is named `amdgpu/gpu_<id>/...`
+    pub fn new(id: u64, statistic: AmdgpuConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("amdgpu/gpu_{}/{}", id, suffix),
+                format!("amdgpu/{}", suffix),
+                &[("gpu", &id.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> AmdgpuConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for AmdgpuStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_NET_BONDING: &str = "/proc/net/bonding";
+
+pub struct Bonding {
+    common: Common,
+    /// statistics which have been registered, as bonds and slaves are
+    /// discovered at runtime
+    registered: HashSet<BondingStatistic>,
+    statistics: Vec<BondingConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Bonding {
+    type Statistic = BondingStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().bonding().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().bonding().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize bonding sampler {}", e);
+                    } else {
+                        error!("failed to initialize bonding sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().bonding()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().bonding().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_bonds().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Bonding {
+    fn record(&mut self, statistic: BondingStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    async fn sample_bonds(&mut self) -> Result<(), std::io::Error> {
+        let mut entries = tokio::fs::read_dir(PROC_NET_BONDING).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let content = match tokio::fs::read_to_string(entry.path()).await {
+                Ok(content) => content,
+                // the bond may have been removed
+                Err(_) => continue,
+            };
+            let bond = parse_bond(&content);
+            let time = Instant::now();
+
+            for statistic in self.statistics.clone() {
+                if statistic.slave() {
+                    for slave in &bond.slaves {
+                        let value = match statistic {
+                            BondingConfigStatistic::SlaveActive => Some(bond.is_active(slave)),
+                            BondingConfigStatistic::SlaveChurnedActor => slave.actor_churned,
+                            BondingConfigStatistic::SlaveChurnedPartner => slave.partner_churned,
+                            BondingConfigStatistic::SlaveLinkFailures => slave.link_failures,
+                            BondingConfigStatistic::SlaveMiiStatus => Some(slave.mii_up as u64),
+                            _ => None,
+                        };
+                        if let Some(value) = value {
+                            let metric = BondingStatistic::new(&name, Some(&slave.name), statistic);
+                            self.record(metric, time, value);
+                        }
+                    }
+                } else {
+                    let value = match statistic {
+                        BondingConfigStatistic::MiiStatus => bond.mii_up as u64,
+                        BondingConfigStatistic::SlavesTotal => bond.slaves.len() as u64,
+                        BondingConfigStatistic::SlavesUp => {
+                            bond.slaves.iter().filter(|s| s.mii_up).count() as u64
+                        }
+                        _ => continue,
+                    };
+                    self.record(BondingStatistic::new(&name, None, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Bond {
+    mii_up: bool,
+    /// the currently active slave in active-backup mode
+    active_slave: Option<String>,
+    /// the active aggregator in 802.3ad mode
+    active_aggregator: Option<u64>,
+    slaves: Vec<Slave>,
+}
+
+impl Bond {
+    /// Whether a slave is carrying traffic. Slaves in modes without a notion
+    /// of an active slave or aggregator are active while their link is up.
+    fn is_active(&self, slave: &Slave) -> u64 {
+        let active = if let Some(ref active) = self.active_slave {
+            *active == slave.name
+        } else if let Some(aggregator) = self.active_aggregator {
+            slave.aggregator == Some(aggregator) && slave.mii_up
+        } else {
+            slave.mii_up
+        };
+        active as u64
+    }
+}
+
+#[derive(Debug, Default)]
+struct Slave {
+    name: String,
+    mii_up: bool,
+    link_failures: Option<u64>,
+    aggregator: Option<u64>,
+    actor_churned: Option<u64>,
+    partner_churned: Option<u64>,
+}
+
+/// Parses a bond status file from `/proc/net/bonding`. Lines before the first
+/// `Slave Interface` describe the bond and the lines after each describe that
+/// slave, with some keys, such as `MII Status`, used in both.
+fn parse_bond(content: &str) -> Bond {
+    let mut bond = Bond::default();
+    for line in content.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        if key == "Slave Interface" {
+            bond.slaves.push(Slave {
+                name: value.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        match bond.slaves.last_mut() {
+            None => match key {
+                "MII Status" => bond.mii_up = value == "up",
+                "Currently Active Slave" if value != "None" => {
+                    bond.active_slave = Some(value.to_string())
+                }
+                "Aggregator ID" => bond.active_aggregator = value.parse().ok(),
+                _ => {}
+            },
+            Some(slave) => match key {
+                "MII Status" => slave.mii_up = value == "up",
+                "Link Failure Count" => slave.link_failures = value.parse().ok(),
+                "Aggregator ID" => slave.aggregator = value.parse().ok(),
+                "Actor Churned Count" => slave.actor_churned = value.parse().ok(),
+                "Partner Churned Count" => slave.partner_churned = value.parse().ok(),
+                _ => {}
+            },
+        }
+    }
+    bond
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_active_backup() {
+        let bond = parse_bond(
+            "Ethernet Channel Bonding Driver: v5.4.0\n\
+             \n\
+             Bonding Mode: fault-tolerance (active-backup)\n\
+             Primary Slave: None\n\
+             Currently Active Slave: eth1\n\
+             MII Status: up\n\
+             MII Polling Interval (ms): 100\n\
+             \n\
+             Slave Interface: eth0\n\
+             MII Status: down\n\
+             Speed: Unknown\n\
+             Link Failure Count: 3\n\
+             Permanent HW addr: 0c:c4:7a:00:00:01\n\
+             Slave queue ID: 0\n\
+             \n\
+             Slave Interface: eth1\n\
+             MII Status: up\n\
+             Speed: 10000 Mbps\n\
+             Link Failure Count: 0\n\
+             Permanent HW addr: 0c:c4:7a:00:00:02\n\
+             Slave queue ID: 0\n",
+        );
+        assert!(bond.mii_up);
+        assert_eq!(bond.slaves.len(), 2);
+        assert!(!bond.slaves[0].mii_up);
+        assert_eq!(bond.slaves[0].link_failures, Some(3));
+        assert_eq!(bond.is_active(&bond.slaves[0]), 0);
+        assert_eq!(bond.is_active(&bond.slaves[1]), 1);
+    }
+
+    #[test]
+    fn test_parse_lacp() {
+        let bond = parse_bond(
+            "Ethernet Channel Bonding Driver: v5.4.0\n\
+             \n\
+             Bonding Mode: IEEE 802.3ad Dynamic link aggregation\n\
+             MII Status: up\n\
+             \n\
+             802.3ad info\n\
+             LACP rate: fast\n\
+             Active Aggregator Info:\n\
+             \tAggregator ID: 2\n\
+             \tNumber of ports: 1\n\
+             \n\
+             Slave Interface: eth0\n\
+             MII Status: up\n\
+             Link Failure Count: 1\n\
+             Aggregator ID: 1\n\
+             Actor Churn State: churned\n\
+             Partner Churn State: churned\n\
+             Actor Churned Count: 4\n\
+             Partner Churned Count: 5\n\
+             \n\
+             Slave Interface: eth1\n\
+             MII Status: up\n\
+             Link Failure Count: 0\n\
+             Aggregator ID: 2\n\
+             Actor Churned Count: 0\n\
+             Partner Churned Count: 0\n",
+        );
+        assert_eq!(bond.active_aggregator, Some(2));
+        assert_eq!(bond.slaves[0].actor_churned, Some(4));
+        assert_eq!(bond.slaves[0].partner_churned, Some(5));
+        assert_eq!(bond.is_active(&bond.slaves[0]), 0);
+        assert_eq!(bond.is_active(&bond.slaves[1]), 1);
+    }
+}
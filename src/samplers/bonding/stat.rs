@@ -0,0 +1,124 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum BondingConfigStatistic {
+    #[strum(serialize = "mii_status")]
+    MiiStatus,
+    #[strum(serialize = "slaves/total")]
+    SlavesTotal,
+    #[strum(serialize = "slaves/up")]
+    SlavesUp,
+    #[strum(serialize = "slave/active")]
+    SlaveActive,
+    #[strum(serialize = "slave/churned/actor")]
+    SlaveChurnedActor,
+    #[strum(serialize = "slave/churned/partner")]
+    SlaveChurnedPartner,
+    #[strum(serialize = "slave/link_failures")]
+    SlaveLinkFailures,
+    #[strum(serialize = "slave/mii_status")]
+    SlaveMiiStatus,
+}
+
+impl BondingConfigStatistic {
+    /// Whether this statistic is reported for each slave, rather than for each
+    /// bond
+    pub fn slave(self) -> bool {
+        matches!(
+            self,
+            Self::SlaveActive
+                | Self::SlaveChurnedActor
+                | Self::SlaveChurnedPartner
+                | Self::SlaveLinkFailures
+                | Self::SlaveMiiStatus
+        )
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::SlaveChurnedActor | Self::SlaveChurnedPartner | Self::SlaveLinkFailures => {
+                Source::Counter
+            }
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for BondingConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        BondingConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BondingStatistic {
+    name: String,
+    statistic: BondingConfigStatistic,
+}
+
+impl BondingStatistic {
+    /// A statistic of a bond, or of one of its slaves, which is named
+    /// `bonding/<bond>/slave/<slave>/...`
+    pub fn new(bond: &str, slave: Option<&str>, statistic: BondingConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let name = match slave {
+            Some(slave) => labeled(
+                format!(
+                    "bonding/{}/slave/{}/{}",
+                    bond,
+                    slave,
+                    suffix.trim_start_matches("slave/")
+                ),
+                format!("bonding/{}", suffix),
+                &[("bond", bond), ("slave", slave)],
+            ),
+            None => labeled(
+                format!("bonding/{}/{}", bond, suffix),
+                format!("bonding/{}", suffix),
+                &[("bond", bond)],
+            ),
+        };
+        Self { name, statistic }
+    }
+
+    pub fn statistic(&self) -> BondingConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for BondingStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
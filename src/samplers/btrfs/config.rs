@@ -0,0 +1,91 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BtrfsConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(default = "default_scrub_status")]
+    scrub_status: String,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<BtrfsConfigStatistic>,
+}
+
+impl Default for BtrfsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            scrub_status: default_scrub_status(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_scrub_status() -> String {
+    "/var/lib/btrfs".to_string()
+}
+
+fn default_statistics() -> Vec<BtrfsConfigStatistic> {
+    BtrfsConfigStatistic::iter().collect()
+}
+
+impl BtrfsConfig {
+    /// The directory which contains the scrub status files written by
+    /// btrfs-progs
+    pub fn scrub_status(&self) -> &str {
+        &self.scrub_status
+    }
+}
+
+impl SamplerConfig for BtrfsConfig {
+    type Statistic = BtrfsStatistic;
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // filesystems are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_FS_BTRFS: &str = "/sys/fs/btrfs";
+
+pub struct Btrfs {
+    common: Common,
+    /// statistics which have been registered, as filesystems are discovered at
+    /// runtime
+    registered: HashSet<BtrfsStatistic>,
+    statistics: Vec<BtrfsConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Btrfs {
+    type Statistic = BtrfsStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().btrfs().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().btrfs().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize btrfs sampler {}", e);
+                    } else {
+                        error!("failed to initialize btrfs sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().btrfs()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().btrfs().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_filesystems().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Btrfs {
+    fn record(&mut self, statistic: BtrfsStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples each mounted btrfs filesystem, which are named by their label,
+    /// or by their uuid if they have none
+    async fn sample_filesystems(&mut self) -> Result<(), std::io::Error> {
+        let scrub_status = self
+            .common
+            .config()
+            .samplers()
+            .btrfs()
+            .scrub_status()
+            .to_string();
+        let mut entries = tokio::fs::read_dir(SYS_FS_BTRFS).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let uuid = match entry.file_name().into_string() {
+                Ok(uuid) => uuid,
+                Err(_) => continue,
+            };
+            // the directory also contains `features`, which is not a
+            // filesystem
+            let path = entry.path();
+            if !path.join("allocation").exists() {
+                continue;
+            }
+            let name = match tokio::fs::read_to_string(path.join("label")).await {
+                Ok(label) if !label.trim().is_empty() => label.trim().to_string(),
+                _ => uuid.clone(),
+            };
+
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if let Some(file) = statistic.allocation() {
+                    if let Some(value) = read_u64(&path.join("allocation").join(file)).await {
+                        self.record(BtrfsStatistic::new(&name, None, statistic), time, value);
+                    }
+                }
+            }
+
+            self.sample_devices(&path, &name).await;
+
+            let scrub = Path::new(&scrub_status).join(format!("scrub.status.{}", uuid));
+            if let Ok(content) = tokio::fs::read_to_string(scrub).await {
+                self.sample_scrub(&name, &parse_scrub_status(&content));
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the error counters of each device of a filesystem, which are
+    /// available from Linux 5.14
+    async fn sample_devices(&mut self, path: &Path, name: &str) {
+        let mut devices = match tokio::fs::read_dir(path.join("devinfo")).await {
+            Ok(devices) => devices,
+            Err(_) => return,
+        };
+        while let Ok(Some(device)) = devices.next_entry().await {
+            let devid = match device.file_name().into_string() {
+                Ok(devid) => devid,
+                Err(_) => continue,
+            };
+            let errors = match tokio::fs::read_to_string(device.path().join("error_stats")).await {
+                Ok(content) => parse_error_stats(&content),
+                Err(_) => continue,
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if let Some(value) = statistic.device_error().and_then(|k| errors.get(k)) {
+                    let metric = BtrfsStatistic::new(name, Some(&devid), statistic);
+                    self.record(metric, time, *value);
+                }
+            }
+        }
+    }
+
+    fn sample_scrub(&mut self, name: &str, scrub: &Scrub) {
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            let value = match statistic {
+                BtrfsConfigStatistic::ScrubBytes => scrub.bytes,
+                BtrfsConfigStatistic::ScrubErrorsCorrected => scrub.corrected,
+                BtrfsConfigStatistic::ScrubErrorsUncorrectable => scrub.uncorrectable,
+                BtrfsConfigStatistic::ScrubRunning => scrub.running as u64,
+                BtrfsConfigStatistic::ScrubStarted => scrub.started,
+                _ => continue,
+            };
+            self.record(BtrfsStatistic::new(name, None, statistic), time, value);
+        }
+    }
+}
+
+async fn read_u64(path: &Path) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Parses the `error_stats` file of a device, which has a `name value` line
+/// for each counter
+fn parse_error_stats(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        if let Some((name, value)) = line.split_once(' ') {
+            if let Ok(value) = value.trim().parse() {
+                result.insert(name.to_string(), value);
+            }
+        }
+    }
+    result
+}
+
+/// The most recent scrub of a filesystem, summed across its devices
+#[derive(Debug, Default, PartialEq)]
+struct Scrub {
+    bytes: u64,
+    corrected: u64,
+    uncorrectable: u64,
+    running: bool,
+    /// when the scrub was started, in seconds since the epoch
+    started: u64,
+}
+
+/// Parses a scrub status file, which btrfs-progs keeps up to date while a
+/// scrub is running. It has a `<fsid>:<devid>|key:value|...` line for each
+/// device.
+fn parse_scrub_status(content: &str) -> Scrub {
+    let mut scrub = Scrub::default();
+    for line in content.lines() {
+        let fields = match line.split_once('|') {
+            Some((_, fields)) => fields,
+            None => continue,
+        };
+        let fields: HashMap<&str, u64> = fields
+            .split('|')
+            .filter_map(|f| f.split_once(':'))
+            .filter_map(|(k, v)| v.parse().ok().map(|v| (k, v)))
+            .collect();
+        let get = |key| fields.get(key).copied().unwrap_or(0);
+        scrub.bytes += get("data_bytes_scrubbed") + get("tree_bytes_scrubbed");
+        scrub.corrected += get("corrected_errors");
+        scrub.uncorrectable += get("uncorrectable_errors");
+        scrub.running |= get("finished") == 0 && get("canceled") == 0;
+        scrub.started = scrub.started.max(get("t_start"));
+    }
+    scrub
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_stats() {
+        let errors = parse_error_stats(
+            "write_errs 0\n\
+             read_errs 2\n\
+             flush_errs 0\n\
+             corruption_errs 5\n\
+             generation_errs 0\n",
+        );
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors.get("read_errs"), Some(&2));
+        assert_eq!(errors.get("corruption_errs"), Some(&5));
+    }
+
+    #[test]
+    fn test_parse_scrub_status() {
+        let scrub = parse_scrub_status(
+            "scrub status:1\n\
+             8a3e9c1a-5b4f-4c7e-9d2a-0f1e2d3c4b5a:1|data_extents_scrubbed:100|\
+             tree_extents_scrubbed:10|data_bytes_scrubbed:4096000|tree_bytes_scrubbed:163840|\
+             read_errors:0|csum_errors:1|verify_errors:0|corrected_errors:1|\
+             uncorrectable_errors:0|t_start:1630000000|t_resumed:0|duration:60|\
+             canceled:0|finished:1\n\
+             8a3e9c1a-5b4f-4c7e-9d2a-0f1e2d3c4b5a:2|data_bytes_scrubbed:1000|\
+             tree_bytes_scrubbed:0|corrected_errors:0|uncorrectable_errors:2|\
+             t_start:1630000001|canceled:0|finished:0\n",
+        );
+        assert_eq!(
+            scrub,
+            Scrub {
+                bytes: 4096000 + 163840 + 1000,
+                corrected: 1,
+                uncorrectable: 2,
+                running: true,
+                started: 1630000001,
+            }
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum BtrfsConfigStatistic {
+    #[strum(serialize = "allocation/data/total")]
+    AllocationDataTotal,
+    #[strum(serialize = "allocation/data/used")]
+    AllocationDataUsed,
+    #[strum(serialize = "allocation/metadata/total")]
+    AllocationMetadataTotal,
+    #[strum(serialize = "allocation/metadata/used")]
+    AllocationMetadataUsed,
+    #[strum(serialize = "allocation/system/total")]
+    AllocationSystemTotal,
+    #[strum(serialize = "allocation/system/used")]
+    AllocationSystemUsed,
+    #[strum(serialize = "device/errors/corruption")]
+    DeviceErrorsCorruption,
+    #[strum(serialize = "device/errors/flush")]
+    DeviceErrorsFlush,
+    #[strum(serialize = "device/errors/generation")]
+    DeviceErrorsGeneration,
+    #[strum(serialize = "device/errors/read")]
+    DeviceErrorsRead,
+    #[strum(serialize = "device/errors/write")]
+    DeviceErrorsWrite,
+    #[strum(serialize = "scrub/bytes")]
+    ScrubBytes,
+    #[strum(serialize = "scrub/errors/corrected")]
+    ScrubErrorsCorrected,
+    #[strum(serialize = "scrub/errors/uncorrectable")]
+    ScrubErrorsUncorrectable,
+    #[strum(serialize = "scrub/running")]
+    ScrubRunning,
+    #[strum(serialize = "scrub/started")]
+    ScrubStarted,
+}
+
+impl BtrfsConfigStatistic {
+    /// The path, relative to `/sys/fs/btrfs/<uuid>/allocation`, of the file
+    /// this statistic is read from
+    pub fn allocation(self) -> Option<&'static str> {
+        match self {
+            Self::AllocationDataTotal => Some("data/total_bytes"),
+            Self::AllocationDataUsed => Some("data/bytes_used"),
+            Self::AllocationMetadataTotal => Some("metadata/total_bytes"),
+            Self::AllocationMetadataUsed => Some("metadata/bytes_used"),
+            Self::AllocationSystemTotal => Some("system/total_bytes"),
+            Self::AllocationSystemUsed => Some("system/bytes_used"),
+            _ => None,
+        }
+    }
+
+    /// The key in the `error_stats` file of a device this statistic is read
+    /// from
+    pub fn device_error(self) -> Option<&'static str> {
+        match self {
+            Self::DeviceErrorsCorruption => Some("corruption_errs"),
+            Self::DeviceErrorsFlush => Some("flush_errs"),
+            Self::DeviceErrorsGeneration => Some("generation_errs"),
+            Self::DeviceErrorsRead => Some("read_errs"),
+            Self::DeviceErrorsWrite => Some("write_errs"),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        if self.device_error().is_some() {
+            Source::Counter
+        } else {
+            Source::Gauge
+        }
+    }
+}
+
+impl TryFrom<&str> for BtrfsConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        BtrfsConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BtrfsStatistic {
+    name: String,
+    statistic: BtrfsConfigStatistic,
+}
+
+impl BtrfsStatistic {
+    /// A statistic of a filesystem, or of one of its devices, which is named
+    /// `btrfs/<filesystem>/device/<devid>/...`
+    pub fn new(filesystem: &str, device: Option<&str>, statistic: BtrfsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let name = match device {
+            Some(device) => labeled(
+                format!(
+                    "btrfs/{}/device/{}/{}",
+                    filesystem,
+                    device,
+                    suffix.trim_start_matches("device/")
+                ),
+                format!("btrfs/{}", suffix),
+                &[("filesystem", filesystem), ("device", device)],
+            ),
+            None => labeled(
+                format!("btrfs/{}/{}", filesystem, suffix),
+                format!("btrfs/{}", suffix),
+                &[("filesystem", filesystem)],
+            ),
+        };
+        Self { name, statistic }
+    }
+
+    pub fn statistic(&self) -> BtrfsConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for BtrfsStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+/// The admin socket command which returns the perf counters as JSON
+const PERF_DUMP: &[u8] = b"{\"prefix\": \"perf dump\"}\0";
+
+/// How long to wait for a client to respond on its admin socket
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct Ceph {
+    common: Common,
+    /// counters of each (pool, image) from the previous sample, used to find
+    /// the mean latency of the operations completed since then
+    previous: HashMap<(String, String), Image>,
+    /// statistics which have been registered, as images are discovered at
+    /// runtime
+    registered: HashSet<CephStatistic>,
+    statistics: Vec<CephConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Ceph {
+    type Statistic = CephStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().ceph().statistics.clone();
+        Ok(Self {
+            common,
+            previous: HashMap::new(),
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().ceph().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize ceph sampler {}", e);
+                    } else {
+                        error!("failed to initialize ceph sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().ceph()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().ceph().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_images().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Ceph {
+    fn record(&mut self, statistic: CephStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Queries the admin socket of each ceph client for its librbd perf
+    /// counters. An image may be open in more than one client, in which case
+    /// the counters of each are summed.
+    async fn sample_images(&mut self) -> Result<(), std::io::Error> {
+        let path = self.common.config().samplers().ceph().path().to_string();
+        let mut images: HashMap<(String, String), Image> = HashMap::new();
+
+        let mut entries = tokio::fs::read_dir(&path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            // daemons such as osds also have admin sockets, but only clients
+            // have librbd counters
+            if !name.ends_with(".asok") || !name.contains("client.") {
+                continue;
+            }
+            let content = match perf_dump(&entry.path()).await {
+                Ok(content) => content,
+                Err(e) => {
+                    // sockets of clients which have exited are left behind
+                    debug!("failed to query ceph admin socket {}: {}", name, e);
+                    continue;
+                }
+            };
+            for (key, image) in parse_perf_dump(&content) {
+                images.entry(key).or_default().add(&image);
+            }
+        }
+
+        let time = Instant::now();
+        for (key, image) in &images {
+            let previous = self.previous.get(key).cloned();
+            for statistic in self.statistics.clone() {
+                let value = match statistic.source() {
+                    Source::Gauge => previous
+                        .as_ref()
+                        .and_then(|p| image.mean_latency(p, statistic.counter())),
+                    _ => image.counters.get(statistic.counter()).copied(),
+                };
+                if let Some(value) = value {
+                    self.record(CephStatistic::new(&key.0, &key.1, statistic), time, value);
+                }
+            }
+        }
+
+        // images which have been closed are dropped along with their counters
+        self.previous = images;
+        Ok(())
+    }
+}
+
+/// Sends the `perf dump` command to an admin socket and returns the response,
+/// which is prefixed with its length as a big-endian u32
+async fn perf_dump(path: &Path) -> Result<String, std::io::Error> {
+    let query = async {
+        let mut stream = tokio::net::UnixStream::connect(path).await?;
+        stream.write_all(PERF_DUMP).await?;
+        let length = stream.read_u32().await? as usize;
+        let mut buffer = vec![0; length];
+        stream.read_exact(&mut buffer).await?;
+        String::from_utf8(buffer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    };
+    tokio::time::timeout(SOCKET_TIMEOUT, query)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "admin socket timeout"))?
+}
+
+/// The librbd perf counters of an image
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Image {
+    counters: HashMap<String, u64>,
+    /// the operation count and cumulative latency, in nanoseconds, of each
+    /// latency counter
+    latencies: HashMap<String, (u64, u64)>,
+}
+
+impl Image {
+    fn add(&mut self, other: &Image) {
+        for (name, value) in &other.counters {
+            *self.counters.entry(name.clone()).or_default() += value;
+        }
+        for (name, (count, sum)) in &other.latencies {
+            let entry = self.latencies.entry(name.clone()).or_default();
+            entry.0 += count;
+            entry.1 += sum;
+        }
+    }
+
+    /// The mean latency, in nanoseconds, of the operations which completed
+    /// since the previous counters were read
+    fn mean_latency(&self, previous: &Image, name: &str) -> Option<u64> {
+        let (count, sum) = self.latencies.get(name)?;
+        let (previous_count, previous_sum) = previous.latencies.get(name)?;
+        let count = count.checked_sub(*previous_count)?;
+        if count == 0 {
+            return None;
+        }
+        Some(sum.checked_sub(*previous_sum)? / count)
+    }
+}
+
+/// Parses the librbd perf counters from the response to `perf dump`. The
+/// counters of each open image are in a section named
+/// `librbd-<image id>-<pool>-<image>`, and latencies are objects with the
+/// operation count as `avgcount` and the cumulative latency, in seconds, as
+/// `sum`. The image id does not contain `-`, but the pool and image may, in
+/// which case the pool is taken to end at the first `-`.
+fn parse_perf_dump(content: &str) -> HashMap<(String, String), Image> {
+    let mut images = HashMap::new();
+    let json = match json::parse(content) {
+        Ok(json) => json,
+        Err(_) => return images,
+    };
+    for (section, counters) in json.entries() {
+        let (pool, image) = match section
+            .strip_prefix("librbd-")
+            .and_then(|s| s.split_once('-'))
+            .and_then(|(_, s)| s.split_once('-'))
+        {
+            Some((pool, image)) => (pool.to_string(), image.to_string()),
+            None => continue,
+        };
+        let mut result = Image::default();
+        for (name, value) in counters.entries() {
+            if value.is_object() {
+                if let (Some(count), Some(sum)) =
+                    (value["avgcount"].as_u64(), value["sum"].as_f64())
+                {
+                    let sum = (sum * crate::SECOND as f64) as u64;
+                    result.latencies.insert(name.to_string(), (count, sum));
+                }
+            } else if let Some(value) = value.as_u64() {
+                result.counters.insert(name.to_string(), value);
+            }
+        }
+        images.insert((pool, image), result);
+    }
+    images
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_perf_dump() {
+        let images = parse_perf_dump(
+            r#"{
+                "AsyncMessenger::Worker-0": { "msgr_recv_messages": 10 },
+                "librbd-10226b8b4567-rbd-vm-disk-1": {
+                    "rd": 100,
+                    "rd_bytes": 409600,
+                    "rd_latency": { "avgcount": 100, "sum": 0.25, "avgtime": 0.0025 },
+                    "wr": 20,
+                    "wr_bytes": 81920,
+                    "wr_latency": { "avgcount": 20, "sum": 0.1, "avgtime": 0.005 },
+                    "flush": 3
+                }
+            }"#,
+        );
+        assert_eq!(images.len(), 1);
+        let image = &images[&("rbd".to_string(), "vm-disk-1".to_string())];
+        assert_eq!(image.counters["rd"], 100);
+        assert_eq!(image.counters["wr_bytes"], 81920);
+        assert_eq!(image.counters["flush"], 3);
+        assert_eq!(
+            image.latencies["rd_latency"],
+            (100, 250 * crate::MILLISECOND)
+        );
+
+        let mut previous = image.clone();
+        previous
+            .latencies
+            .insert("rd_latency".to_string(), (50, 50 * crate::MILLISECOND));
+        assert_eq!(
+            image.mean_latency(&previous, "rd_latency"),
+            Some(4 * crate::MILLISECOND)
+        );
+        assert_eq!(image.mean_latency(image, "rd_latency"), None);
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum CephConfigStatistic {
+    #[strum(serialize = "discard/operations")]
+    DiscardOperations,
+    #[strum(serialize = "flush/operations")]
+    FlushOperations,
+    #[strum(serialize = "read/bytes")]
+    ReadBytes,
+    #[strum(serialize = "read/latency")]
+    ReadLatency,
+    #[strum(serialize = "read/operations")]
+    ReadOperations,
+    #[strum(serialize = "write/bytes")]
+    WriteBytes,
+    #[strum(serialize = "write/latency")]
+    WriteLatency,
+    #[strum(serialize = "write/operations")]
+    WriteOperations,
+}
+
+impl CephConfigStatistic {
+    /// The name of the librbd perf counter this statistic is read from
+    pub fn counter(self) -> &'static str {
+        match self {
+            Self::DiscardOperations => "discard",
+            Self::FlushOperations => "flush",
+            Self::ReadBytes => "rd_bytes",
+            Self::ReadLatency => "rd_latency",
+            Self::ReadOperations => "rd",
+            Self::WriteBytes => "wr_bytes",
+            Self::WriteLatency => "wr_latency",
+            Self::WriteOperations => "wr",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::ReadLatency | Self::WriteLatency => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for CephConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        CephConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CephStatistic {
+    name: String,
+    statistic: CephConfigStatistic,
+}
+
+impl CephStatistic {
+    /// A statistic of an RBD image, which is named
+    /// `ceph/rbd/<pool>/<image>/...`
+    pub fn new(pool: &str, image: &str, statistic: CephConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("ceph/rbd/{}/{}/{}", pool, image, suffix),
+                format!("ceph/rbd/{}", suffix),
+                &[("pool", pool), ("image", image)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> CephConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for CephStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
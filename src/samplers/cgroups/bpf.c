@@ -0,0 +1,89 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <net/sock.h>
+
+// counters, which are kept for each cgroup
+#define TCP_TX_BYTES 0
+#define TCP_RX_BYTES 1
+#define TCP_CONNECT 2
+#define TCP_ACCEPT 3
+#define UDP_TX_BYTES 4
+#define UDP_RX_BYTES 5
+
+// the key of the per-cgroup counters, where the id is that of the cgroup v2
+// cgroup of the current task, which is the inode number of its directory
+struct cgroup_key_t {
+    u64 id;
+    u32 counter;
+    u32 pad;
+};
+
+BPF_HASH(cgroup_counters, struct cgroup_key_t, u64, 65536);
+
+// all of the functions traced run in the context of the task which owns the
+// socket, so it is attributed to the cgroup of the current task
+static void add(u32 counter, u64 value)
+{
+    struct cgroup_key_t key = {};
+    key.id = bpf_get_current_cgroup_id();
+    key.counter = counter;
+    u64 zero = 0;
+    u64 *count = cgroup_counters.lookup_or_try_init(&key, &zero);
+    if (count) lock_xadd(count, value);
+}
+
+int trace_tcp_sendmsg_return(struct pt_regs *ctx)
+{
+    int sent = PT_REGS_RC(ctx);
+    if (sent > 0) {
+        add(TCP_TX_BYTES, sent);
+    }
+    return 0;
+}
+
+// called when data has been copied from the receive queue to userspace
+int trace_tcp_cleanup_rbuf(struct pt_regs *ctx, struct sock *sk, int copied)
+{
+    if (copied > 0) {
+        add(TCP_RX_BYTES, copied);
+    }
+    return 0;
+}
+
+int trace_tcp_connect_return(struct pt_regs *ctx)
+{
+    if (PT_REGS_RC(ctx) == 0) {
+        add(TCP_CONNECT, 1);
+    }
+    return 0;
+}
+
+int trace_tcp_accept_return(struct pt_regs *ctx)
+{
+    struct sock *sk = (struct sock *)PT_REGS_RC(ctx);
+    if (sk != 0) {
+        add(TCP_ACCEPT, 1);
+    }
+    return 0;
+}
+
+int trace_udp_sendmsg_return(struct pt_regs *ctx)
+{
+    int sent = PT_REGS_RC(ctx);
+    if (sent > 0) {
+        add(UDP_TX_BYTES, sent);
+    }
+    return 0;
+}
+
+int trace_udp_recvmsg_return(struct pt_regs *ctx)
+{
+    int copied = PT_REGS_RC(ctx);
+    if (copied > 0) {
+        add(UDP_RX_BYTES, copied);
+    }
+    return 0;
+}
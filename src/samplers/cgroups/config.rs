@@ -0,0 +1,206 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupsConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<CgroupsConfigStatistic>,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default = "default_depth")]
+    depth: usize,
+    #[serde(default = "default_max_cgroups")]
+    max_cgroups: usize,
+    #[serde(default)]
+    kubernetes: KubernetesConfig,
+}
+
+impl Default for CgroupsConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+            path: default_path(),
+            depth: default_depth(),
+            max_cgroups: default_max_cgroups(),
+            kubernetes: Default::default(),
+        }
+    }
+}
+
+fn default_statistics() -> Vec<CgroupsConfigStatistic> {
+    CgroupsConfigStatistic::iter().collect()
+}
+
+fn default_path() -> String {
+    "/sys/fs/cgroup".to_string()
+}
+
+fn default_depth() -> usize {
+    1
+}
+
+fn default_max_cgroups() -> usize {
+    256
+}
+
+impl CgroupsConfig {
+    /// Mount point of the cgroup v2 unified hierarchy
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    /// How many levels below the root of the hierarchy to walk. A depth of 1
+    /// only reports the top-level cgroups.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Upper bound on the number of cgroups which will be tracked
+    pub fn max_cgroups(&self) -> usize {
+        self.max_cgroups
+    }
+
+    pub fn kubernetes(&self) -> &KubernetesConfig {
+        &self.kubernetes
+    }
+
+    /// The statistics which will be collected, those which require BPF are
+    /// only included if it is enabled
+    pub fn enabled_statistics(&self) -> Vec<CgroupsConfigStatistic> {
+        self.statistics
+            .iter()
+            .filter(|s| s.bpf_counter().is_none() || self.bpf)
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KubernetesConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_kubelet_url")]
+    url: String,
+    #[serde(default = "default_token_file")]
+    token_file: String,
+    #[serde(default = "default_ca_file")]
+    ca_file: String,
+    #[serde(default)]
+    insecure: bool,
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Default::default(),
+            url: default_kubelet_url(),
+            token_file: default_token_file(),
+            ca_file: default_ca_file(),
+            insecure: Default::default(),
+        }
+    }
+}
+
+fn default_kubelet_url() -> String {
+    "https://localhost:10250/pods".to_string()
+}
+
+fn default_token_file() -> String {
+    "/var/run/secrets/kubernetes.io/serviceaccount/token".to_string()
+}
+
+fn default_ca_file() -> String {
+    "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt".to_string()
+}
+
+impl KubernetesConfig {
+    /// Whether to name cgroups belonging to Kubernetes pods by their
+    /// namespace, pod, and container names
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// URL of the kubelet pod list
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// File containing the bearer token used to authenticate to the kubelet
+    pub fn token_file(&self) -> String {
+        self.token_file.clone()
+    }
+
+    /// File containing the CA certificate for verifying the kubelet
+    pub fn ca_file(&self) -> String {
+        self.ca_file.clone()
+    }
+
+    /// Skip verification of the kubelet certificate
+    pub fn insecure(&self) -> bool {
+        self.insecure
+    }
+}
+
+impl SamplerConfig for CgroupsConfig {
+    type Statistic = CgroupsStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // cgroups are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::config::KubernetesConfig;
+
+/// Identity of a pod, and the names of its containers keyed by container id
+#[derive(Debug, Default)]
+struct Pod {
+    namespace: String,
+    name: String,
+    containers: HashMap<String, String>,
+}
+
+/// Resolves cgroup paths to the Kubernetes pods and containers which they
+/// belong to, using the pod list from the local kubelet
+pub struct Kubelet {
+    client: reqwest::blocking::Client,
+    url: String,
+    token_file: String,
+    pods: HashMap<String, Pod>,
+}
+
+impl Kubelet {
+    pub fn new(config: &KubernetesConfig) -> Result<Self, anyhow::Error> {
+        let mut builder = reqwest::blocking::Client::builder().timeout(Duration::from_secs(5));
+        if config.insecure() {
+            builder = builder.danger_accept_invalid_certs(true);
+        } else if let Ok(pem) = std::fs::read(config.ca_file()) {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        Ok(Self {
+            client: builder.build()?,
+            url: config.url(),
+            token_file: config.token_file(),
+            pods: HashMap::new(),
+        })
+    }
+
+    /// Fetches the current pod list from the kubelet
+    pub fn refresh(&mut self) -> Result<(), anyhow::Error> {
+        let mut request = self.client.get(&self.url);
+        // the token is rotated periodically, so it is read for each request
+        if let Ok(token) = std::fs::read_to_string(&self.token_file) {
+            request = request.bearer_auth(token.trim());
+        }
+        let body = request.send()?.error_for_status()?.text()?;
+        self.pods = parse_pods(&body)?;
+        Ok(())
+    }
+
+    /// Returns whether the cgroup belongs to a pod which is not yet known
+    pub fn is_unknown(&self, cgroup: &str) -> bool {
+        match parse_kubepods(cgroup) {
+            Some((uid, _)) => !self.pods.contains_key(&uid),
+            None => false,
+        }
+    }
+
+    /// Returns the name to use for a cgroup, of the form `namespace/pod` for
+    /// pod cgroups or `namespace/pod/container` for container cgroups. Returns
+    /// `None` for cgroups which are not managed by the kubelet or which belong
+    /// to an unknown pod.
+    pub fn resolve(&self, cgroup: &str) -> Option<String> {
+        let (uid, container) = parse_kubepods(cgroup)?;
+        let pod = self.pods.get(&uid)?;
+        match container {
+            Some(id) => {
+                // containers which are not in the pod spec, such as the
+                // sandbox, are identified by a prefix of their id
+                let name = pod
+                    .containers
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| id.chars().take(12).collect());
+                Some(format!("{}/{}/{}", pod.namespace, pod.name, name))
+            }
+            None => Some(format!("{}/{}", pod.namespace, pod.name)),
+        }
+    }
+}
+
+/// Parses the kubelet `/pods` response into pods keyed by their uid
+fn parse_pods(body: &str) -> Result<HashMap<String, Pod>, anyhow::Error> {
+    let json = json::parse(body)?;
+    let mut pods = HashMap::new();
+    for item in json["items"].members() {
+        let metadata = &item["metadata"];
+        let uid = match metadata["uid"].as_str() {
+            Some(uid) => uid.to_string(),
+            None => continue,
+        };
+        let mut pod = Pod {
+            namespace: metadata["namespace"].as_str().unwrap_or("").to_string(),
+            name: metadata["name"].as_str().unwrap_or("").to_string(),
+            containers: HashMap::new(),
+        };
+        for status in ["containerStatuses", "initContainerStatuses"].iter() {
+            for container in item["status"][*status].members() {
+                // container ids have the form `containerd://<id>`
+                if let (Some(id), Some(name)) = (
+                    container["containerID"].as_str(),
+                    container["name"].as_str(),
+                ) {
+                    let id = id.rsplit("://").next().unwrap_or(id);
+                    pod.containers.insert(id.to_string(), name.to_string());
+                }
+            }
+        }
+        pods.insert(uid, pod);
+    }
+    Ok(pods)
+}
+
+/// Extracts the pod uid, and container id if present, from a cgroup path.
+/// Both the systemd cgroup driver, which uses paths such as
+/// `kubepods.slice/.../kubepods-burstable-pod<uid>.slice/cri-containerd-<id>.scope`,
+/// and the cgroupfs driver, which uses paths such as
+/// `kubepods/burstable/pod<uid>/<id>`, are supported.
+fn parse_kubepods(cgroup: &str) -> Option<(String, Option<String>)> {
+    let mut parts = cgroup.split('/').skip_while(|p| !p.starts_with("kubepods"));
+    let mut uid = None;
+    for part in &mut parts {
+        if let Some(slice) = part.strip_suffix(".slice") {
+            // the systemd driver escapes the dashes in the uid as underscores
+            if let Some(index) = slice.rfind("-pod") {
+                uid = Some(slice[(index + 4)..].replace('_', "-"));
+                break;
+            }
+        } else if let Some(pod) = part.strip_prefix("pod") {
+            uid = Some(pod.to_string());
+            break;
+        }
+    }
+    let uid = uid?;
+    let container = parts.next().map(|part| {
+        let id = part.strip_suffix(".scope").unwrap_or(part);
+        // runtime prefixes, such as `cri-containerd-`, `crio-`, or `docker-`
+        id.rsplit('-').next().unwrap_or(id).to_string()
+    });
+    Some((uid, container))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_kubepods_systemd() {
+        assert_eq!(
+            parse_kubepods(
+                "kubepods.slice/kubepods-burstable.slice/\
+                 kubepods-burstable-pod1a2b3c4d_0000_1111_2222_333344445555.slice/\
+                 cri-containerd-abcdef0123.scope"
+            ),
+            Some((
+                "1a2b3c4d-0000-1111-2222-333344445555".to_string(),
+                Some("abcdef0123".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_kubepods("kubepods.slice/kubepods-pod1a2b_3c4d.slice"),
+            Some(("1a2b-3c4d".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_parse_kubepods_cgroupfs() {
+        assert_eq!(
+            parse_kubepods("kubepods/besteffort/pod1a2b-3c4d/abcdef0123"),
+            Some(("1a2b-3c4d".to_string(), Some("abcdef0123".to_string())))
+        );
+        assert_eq!(parse_kubepods("kubepods/besteffort"), None);
+        assert_eq!(parse_kubepods("system.slice/sshd.service"), None);
+    }
+
+    #[test]
+    fn test_parse_pods() {
+        let pods = parse_pods(
+            r#"{"items": [{
+                "metadata": {"uid": "1a2b-3c4d", "namespace": "default", "name": "web-0"},
+                "status": {"containerStatuses": [
+                    {"name": "nginx", "containerID": "containerd://abcdef0123"}
+                ]}
+            }]}"#,
+        )
+        .unwrap();
+        let pod = pods.get("1a2b-3c4d").unwrap();
+        assert_eq!(pod.namespace, "default");
+        assert_eq!(pod.name, "web-0");
+        assert_eq!(pod.containers.get("abcdef0123"), Some(&"nginx".to_string()));
+    }
+}
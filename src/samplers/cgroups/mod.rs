@@ -0,0 +1,546 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod kubernetes;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+use kubernetes::Kubelet;
+
+/// Minimum time between refreshes of the kubelet pod list
+const KUBELET_REFRESH: Duration = Duration::from_secs(10);
+
+#[allow(dead_code)]
+pub struct Cgroups {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    /// paths of all cgroups in the hierarchy, keyed by their cgroup id
+    cgroup_ids: HashMap<u64, String>,
+    /// known cgroups, keyed by path, and the names used for their metrics
+    cgroups: HashMap<String, String>,
+    common: Common,
+    kubelet: Option<Kubelet>,
+    kubelet_last: Option<Instant>,
+    /// totals of the BPF counters for each known cgroup, including those of
+    /// its descendants
+    network: HashMap<(String, CgroupsConfigStatistic), u64>,
+    path: PathBuf,
+    statistics: Vec<CgroupsConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Cgroups {
+    type Statistic = CgroupsStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().cgroups().enabled_statistics();
+        let path = PathBuf::from(common.config().samplers().cgroups().path());
+        if !path.join("cgroup.controllers").exists() {
+            return Err(format_err!(
+                "{} is not a cgroup v2 hierarchy",
+                path.to_string_lossy()
+            ));
+        }
+        let kubernetes = common.config().samplers().cgroups().kubernetes();
+        let kubelet = if kubernetes.enabled() {
+            Some(Kubelet::new(kubernetes)?)
+        } else {
+            None
+        };
+        let mut sampler = Self {
+            bpf: None,
+            cgroup_ids: HashMap::new(),
+            cgroups: HashMap::new(),
+            common,
+            kubelet,
+            kubelet_last: None,
+            network: HashMap::new(),
+            path,
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().cgroups().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize cgroups sampler {}", e);
+                    } else {
+                        error!("failed to initialize cgroups sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().cgroups()
+    }
+
+    fn reconfigure(&mut self) {
+        // forget known cgroups so they are registered again with the new
+        // statistics on the next discovery
+        for (_, name) in std::mem::take(&mut self.cgroups) {
+            self.deregister_cgroup(&name);
+        }
+        self.statistics = self
+            .common
+            .config()
+            .samplers()
+            .cgroups()
+            .enabled_statistics();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        self.discover_cgroups();
+
+        let r = self.sample_cgroups().await;
+        self.map_result(r)?;
+
+        #[cfg(feature = "bpf")]
+        self.time_bpf(|s| s.sample_bpf());
+
+        Ok(())
+    }
+}
+
+impl Cgroups {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && self.statistics.iter().any(|s| s.bpf_counter().is_some())
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let mut bpf = bcc::BPF::new(include_str!("bpf.c"))?;
+
+                bcc::Kprobe::new()
+                    .handler("trace_tcp_cleanup_rbuf")
+                    .function("tcp_cleanup_rbuf")
+                    .attach(&mut bpf)?;
+                for (handler, function) in &[
+                    ("trace_tcp_sendmsg_return", "tcp_sendmsg"),
+                    ("trace_tcp_connect_return", "tcp_v4_connect"),
+                    ("trace_tcp_connect_return", "tcp_v6_connect"),
+                    ("trace_tcp_accept_return", "inet_csk_accept"),
+                    ("trace_udp_sendmsg_return", "udp_sendmsg"),
+                    ("trace_udp_sendmsg_return", "udpv6_sendmsg"),
+                    ("trace_udp_recvmsg_return", "udp_recvmsg"),
+                    ("trace_udp_recvmsg_return", "udpv6_recvmsg"),
+                ] {
+                    // ipv6 may be built as a module which is not loaded
+                    if let Ok(results) = bpf.get_kprobe_functions(function) {
+                        if !results.is_empty() {
+                            bcc::Kretprobe::new()
+                                .handler(handler)
+                                .function(function)
+                                .attach(&mut bpf)?;
+                        }
+                    }
+                }
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Walks the hierarchy up to the configured depth, registering statistics
+    /// for newly created cgroups and forgetting those which have been removed.
+    fn discover_cgroups(&mut self) {
+        let depth = self.common.config().samplers().cgroups().depth();
+        let max_cgroups = self.common.config().samplers().cgroups().max_cgroups();
+
+        let mut found = HashSet::new();
+        for entry in walkdir::WalkDir::new(&self.path)
+            .min_depth(1)
+            .max_depth(depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+        {
+            if let Ok(relative) = entry.path().strip_prefix(&self.path) {
+                found.insert(relative.to_string_lossy().to_string());
+            }
+        }
+
+        let removed: Vec<String> = self
+            .cgroups
+            .iter()
+            .filter(|(cgroup, _)| !found.contains(*cgroup))
+            .map(|(_, name)| name.clone())
+            .collect();
+        for name in removed {
+            self.deregister_cgroup(&name);
+        }
+        self.cgroups.retain(|cgroup, _| found.contains(cgroup));
+        let cgroups = &self.cgroups;
+        self.network
+            .retain(|(cgroup, _), _| cgroups.contains_key(cgroup));
+        self.refresh_kubelet(&found);
+
+        let mut added = Vec::new();
+        let mut skipped = 0;
+        for cgroup in found {
+            if let Some(name) = self.cgroups.get(&cgroup) {
+                // pods which were unknown when first discovered are renamed
+                // once the kubelet reports them
+                if *name != self.cgroup_name(&cgroup) {
+                    added.push(cgroup);
+                }
+                continue;
+            }
+            if self.cgroups.len() + added.len() >= max_cgroups {
+                skipped += 1;
+                continue;
+            }
+            added.push(cgroup);
+        }
+        if skipped > 0 {
+            debug!(
+                "not tracking {} cgroups, max_cgroups ({}) reached",
+                skipped, max_cgroups
+            );
+        }
+
+        for cgroup in added {
+            if let Some(previous) = self.cgroups.get(&cgroup) {
+                self.deregister_cgroup(previous);
+            }
+            let name = self.cgroup_name(&cgroup);
+            self.register_cgroup(&name);
+            self.cgroups.insert(cgroup, name);
+        }
+    }
+
+    /// Fetches the pod list from the kubelet if any of the cgroups belong to
+    /// pods which are not yet known
+    fn refresh_kubelet(&mut self, cgroups: &HashSet<String>) {
+        if let Some(ref mut kubelet) = self.kubelet {
+            if let Some(last) = self.kubelet_last {
+                if last.elapsed() < KUBELET_REFRESH {
+                    return;
+                }
+            }
+            if cgroups.iter().any(|cgroup| kubelet.is_unknown(cgroup)) {
+                self.kubelet_last = Some(Instant::now());
+                if let Err(e) = kubelet.refresh() {
+                    error!("failed to fetch pods from kubelet: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Returns the name used in metrics for a cgroup, which is its path unless
+    /// it can be resolved to a Kubernetes pod or container
+    fn cgroup_name(&self, cgroup: &str) -> String {
+        self.kubelet
+            .as_ref()
+            .and_then(|kubelet| kubelet.resolve(cgroup))
+            .unwrap_or_else(|| cgroup.to_string())
+    }
+
+    fn deregister_cgroup(&self, cgroup: &str) {
+        for statistic in &self.statistics {
+            self.common
+                .deregister(&CgroupsStatistic::new(cgroup, *statistic));
+        }
+    }
+
+    fn register_cgroup(&self, cgroup: &str) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in &self.statistics {
+            let statistic = CgroupsStatistic::new(cgroup, *statistic);
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+        }
+    }
+
+    async fn sample_cgroups(&self) -> Result<(), std::io::Error> {
+        let files: HashSet<&'static str> = self
+            .statistics
+            .iter()
+            .filter_map(|s| s.keys())
+            .map(|(file, _)| file)
+            .collect();
+
+        for (cgroup, name) in &self.cgroups {
+            let directory = self.path.join(cgroup);
+            let mut result = HashMap::<&'static str, HashMap<String, u64>>::new();
+            for file in &files {
+                // the cgroup may have been removed since discovery, or the
+                // controller may not be enabled for this cgroup
+                if let Ok(content) = read_interface_file(&directory, file).await {
+                    let parsed = match *file {
+                        "io.stat" => parse_nested_keyed(&content),
+                        "memory.current" => parse_single_value(&content),
+                        _ => parse_flat_keyed(&content),
+                    };
+                    result.insert(file, parsed);
+                }
+            }
+
+            let time = Instant::now();
+            for statistic in &self.statistics {
+                let (file, key) = match statistic.keys() {
+                    Some(keys) => keys,
+                    None => continue,
+                };
+                if let Some(value) = result.get(file).and_then(|v| v.get(key)) {
+                    let value = value * statistic.multiplier();
+                    let metric = CgroupsStatistic::new(name, *statistic);
+                    match statistic.source() {
+                        Source::Counter => {
+                            self.common().record_counter(&metric, time, value);
+                        }
+                        Source::Gauge => {
+                            let _ = self.metrics().record_gauge(&metric, time, value);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads and clears the per-cgroup BPF counters, adding them to the totals
+    /// of the known cgroups which contain the cgroup they were counted in
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&mut self) {
+        let mut counts = Vec::new();
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Ok(mut table) = (*bpf).inner.table("cgroup_counters") {
+                for mut entry in table.iter() {
+                    if entry.key.len() != 16 {
+                        continue;
+                    }
+                    let id = parse_u64(entry.key[0..8].to_vec());
+                    let counter = parse_u32(entry.key[8..12].to_vec());
+                    let value = parse_u64(entry.value.clone());
+                    let _ = table.delete(&mut entry.key);
+                    counts.push((id, counter, value));
+                }
+            }
+        }
+
+        // cgroups which were created since the hierarchy was last walked
+        if counts
+            .iter()
+            .any(|(id, _, _)| !self.cgroup_ids.contains_key(id))
+        {
+            self.discover_cgroup_ids();
+        }
+
+        for (id, counter, value) in counts {
+            let path = match self.cgroup_ids.get(&id) {
+                Some(path) => path,
+                None => continue,
+            };
+            let statistic = match self
+                .statistics
+                .iter()
+                .find(|s| s.bpf_counter() == Some(counter))
+            {
+                Some(statistic) => *statistic,
+                None => continue,
+            };
+            for cgroup in self.cgroups.keys() {
+                if is_within(cgroup, path) {
+                    *self.network.entry((cgroup.clone(), statistic)).or_insert(0) += value;
+                }
+            }
+        }
+
+        let time = Instant::now();
+        for (cgroup, name) in &self.cgroups {
+            for statistic in self.statistics.iter().filter(|s| s.bpf_counter().is_some()) {
+                let value = self
+                    .network
+                    .get(&(cgroup.clone(), *statistic))
+                    .copied()
+                    .unwrap_or(0);
+                let metric = CgroupsStatistic::new(name, *statistic);
+                self.common().record_counter(&metric, time, value);
+            }
+        }
+    }
+
+    /// Walks the entire hierarchy to map cgroup ids, which are the inode
+    /// numbers of their directories, to their paths
+    #[cfg(feature = "bpf")]
+    fn discover_cgroup_ids(&mut self) {
+        use std::os::unix::fs::MetadataExt;
+
+        self.cgroup_ids.clear();
+        for entry in walkdir::WalkDir::new(&self.path)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+        {
+            if let (Ok(metadata), Ok(relative)) =
+                (entry.metadata(), entry.path().strip_prefix(&self.path))
+            {
+                self.cgroup_ids
+                    .insert(metadata.ino(), relative.to_string_lossy().to_string());
+            }
+        }
+    }
+}
+
+/// Whether the cgroup at `path` is `cgroup` or one of its descendants
+#[allow(dead_code)]
+fn is_within(cgroup: &str, path: &str) -> bool {
+    path == cgroup || (path.starts_with(cgroup) && path[cgroup.len()..].starts_with('/'))
+}
+
+async fn read_interface_file(directory: &Path, file: &str) -> Result<String, std::io::Error> {
+    tokio::fs::read_to_string(directory.join(file)).await
+}
+
+/// Parses flat keyed files, such as `cpu.stat` and `memory.stat`, which have
+/// one `key value` pair per line
+fn parse_flat_keyed(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(key), Some(Ok(value))) = (parts.next(), parts.next().map(|v| v.parse())) {
+            result.insert(key.to_string(), value);
+        }
+    }
+    result
+}
+
+/// Parses nested keyed files, such as `io.stat`, which have one line per
+/// device of the form `major:minor key=value key=value ...`. Values are summed
+/// across all devices.
+fn parse_nested_keyed(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        for part in line.split_whitespace().skip(1) {
+            let mut kv = part.split('=');
+            if let (Some(key), Some(Ok(value))) = (kv.next(), kv.next().map(|v| v.parse::<u64>())) {
+                *result.entry(key.to_string()).or_insert(0) += value;
+            }
+        }
+    }
+    result
+}
+
+/// Parses single value files, such as `memory.current`. The value is stored
+/// with an empty key.
+fn parse_single_value(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    if let Ok(value) = content.trim().parse() {
+        result.insert("".to_string(), value);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_flat_keyed() {
+        let result = parse_flat_keyed(
+            "usage_usec 2118430\nuser_usec 1326232\nsystem_usec 792198\nnr_periods 0\n",
+        );
+        assert_eq!(result.len(), 4);
+        assert_eq!(result.get("usage_usec"), Some(&2118430));
+        assert_eq!(result.get("nr_periods"), Some(&0));
+    }
+
+    #[test]
+    fn test_parse_nested_keyed() {
+        let result = parse_nested_keyed(
+            "8:0 rbytes=1024 wbytes=2048 rios=1 wios=2 dbytes=0 dios=0\n\
+             259:0 rbytes=1024 wbytes=0 rios=3 wios=0 dbytes=0 dios=0\n",
+        );
+        assert_eq!(result.get("rbytes"), Some(&2048));
+        assert_eq!(result.get("wbytes"), Some(&2048));
+        assert_eq!(result.get("rios"), Some(&4));
+    }
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("system.slice", "system.slice"));
+        assert!(is_within("system.slice", "system.slice/sshd.service"));
+        assert!(!is_within("system.slice", "system.slice2"));
+        assert!(!is_within("system.slice/sshd.service", "system.slice"));
+    }
+
+    #[test]
+    fn test_parse_single_value() {
+        assert_eq!(parse_single_value("4096\n").get(""), Some(&4096));
+        assert!(parse_single_value("max\n").is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum CgroupsConfigStatistic {
+    #[strum(serialize = "cpu/usage")]
+    CpuUsage,
+    #[strum(serialize = "cpu/user")]
+    CpuUser,
+    #[strum(serialize = "cpu/system")]
+    CpuSystem,
+    #[strum(serialize = "cpu/periods")]
+    CpuPeriods,
+    #[strum(serialize = "cpu/throttled/periods")]
+    CpuThrottledPeriods,
+    #[strum(serialize = "cpu/throttled/time")]
+    CpuThrottledTime,
+    #[strum(serialize = "memory/current")]
+    MemoryCurrent,
+    #[strum(serialize = "memory/anon")]
+    MemoryAnon,
+    #[strum(serialize = "memory/file")]
+    MemoryFile,
+    #[strum(serialize = "memory/kernel_stack")]
+    MemoryKernelStack,
+    #[strum(serialize = "memory/slab")]
+    MemorySlab,
+    #[strum(serialize = "memory/sock")]
+    MemorySock,
+    #[strum(serialize = "memory/shmem")]
+    MemoryShmem,
+    #[strum(serialize = "memory/pgfault")]
+    MemoryPgfault,
+    #[strum(serialize = "memory/pgmajfault")]
+    MemoryPgmajfault,
+    #[strum(serialize = "io/read/bytes")]
+    IoReadBytes,
+    #[strum(serialize = "io/write/bytes")]
+    IoWriteBytes,
+    #[strum(serialize = "io/discard/bytes")]
+    IoDiscardBytes,
+    #[strum(serialize = "io/read/operations")]
+    IoReadOperations,
+    #[strum(serialize = "io/write/operations")]
+    IoWriteOperations,
+    #[strum(serialize = "io/discard/operations")]
+    IoDiscardOperations,
+    #[strum(serialize = "network/tcp/accept")]
+    NetworkTcpAccept,
+    #[strum(serialize = "network/tcp/connect")]
+    NetworkTcpConnect,
+    #[strum(serialize = "network/tcp/receive/bytes")]
+    NetworkTcpReceiveBytes,
+    #[strum(serialize = "network/tcp/transmit/bytes")]
+    NetworkTcpTransmitBytes,
+    #[strum(serialize = "network/udp/receive/bytes")]
+    NetworkUdpReceiveBytes,
+    #[strum(serialize = "network/udp/transmit/bytes")]
+    NetworkUdpTransmitBytes,
+}
+
+impl CgroupsConfigStatistic {
+    /// The cgroup interface file and key within that file which provide the
+    /// value for this statistic
+    pub fn keys(self) -> Option<(&'static str, &'static str)> {
+        let keys = match self {
+            Self::CpuUsage => ("cpu.stat", "usage_usec"),
+            Self::CpuUser => ("cpu.stat", "user_usec"),
+            Self::CpuSystem => ("cpu.stat", "system_usec"),
+            Self::CpuPeriods => ("cpu.stat", "nr_periods"),
+            Self::CpuThrottledPeriods => ("cpu.stat", "nr_throttled"),
+            Self::CpuThrottledTime => ("cpu.stat", "throttled_usec"),
+            Self::MemoryCurrent => ("memory.current", ""),
+            Self::MemoryAnon => ("memory.stat", "anon"),
+            Self::MemoryFile => ("memory.stat", "file"),
+            Self::MemoryKernelStack => ("memory.stat", "kernel_stack"),
+            Self::MemorySlab => ("memory.stat", "slab"),
+            Self::MemorySock => ("memory.stat", "sock"),
+            Self::MemoryShmem => ("memory.stat", "shmem"),
+            Self::MemoryPgfault => ("memory.stat", "pgfault"),
+            Self::MemoryPgmajfault => ("memory.stat", "pgmajfault"),
+            Self::IoReadBytes => ("io.stat", "rbytes"),
+            Self::IoWriteBytes => ("io.stat", "wbytes"),
+            Self::IoDiscardBytes => ("io.stat", "dbytes"),
+            Self::IoReadOperations => ("io.stat", "rios"),
+            Self::IoWriteOperations => ("io.stat", "wios"),
+            Self::IoDiscardOperations => ("io.stat", "dios"),
+            _ => return None,
+        };
+        Some(keys)
+    }
+
+    /// The counter kept for each cgroup by BPF which provides the value for
+    /// this statistic
+    pub fn bpf_counter(self) -> Option<u32> {
+        match self {
+            Self::NetworkTcpTransmitBytes => Some(0),
+            Self::NetworkTcpReceiveBytes => Some(1),
+            Self::NetworkTcpConnect => Some(2),
+            Self::NetworkTcpAccept => Some(3),
+            Self::NetworkUdpTransmitBytes => Some(4),
+            Self::NetworkUdpReceiveBytes => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Multiplier to convert the raw value into the exported unit
+    pub fn multiplier(self) -> u64 {
+        match self {
+            Self::CpuUsage | Self::CpuUser | Self::CpuSystem | Self::CpuThrottledTime => {
+                crate::common::MICROSECOND
+            }
+            _ => 1,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::MemoryCurrent
+            | Self::MemoryAnon
+            | Self::MemoryFile
+            | Self::MemoryKernelStack
+            | Self::MemorySlab
+            | Self::MemorySock
+            | Self::MemoryShmem => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for CgroupsConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        CgroupsConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CgroupsStatistic {
+    name: String,
+    statistic: CgroupsConfigStatistic,
+}
+
+impl CgroupsStatistic {
+    pub fn new(cgroup: &str, statistic: CgroupsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("cgroups/{}/{}", cgroup, suffix),
+                format!("cgroups/{}", suffix),
+                &[("cgroup", cgroup)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> CgroupsConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for CgroupsStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
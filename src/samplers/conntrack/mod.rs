@@ -0,0 +1,262 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::time::*;
+
+use async_trait::async_trait;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const CONNTRACK_COUNT: &str = "/proc/sys/net/netfilter/nf_conntrack_count";
+const CONNTRACK_MAX: &str = "/proc/sys/net/netfilter/nf_conntrack_max";
+const CONNTRACK_STAT: &str = "/proc/net/stat/nf_conntrack";
+const CONNTRACK_TABLE: &str = "/proc/net/nf_conntrack";
+
+pub struct Conntrack {
+    common: Common,
+    files: HashMap<&'static str, File>,
+    statistics: Vec<ConntrackStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Conntrack {
+    type Statistic = ConntrackStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().conntrack().statistics();
+        let sampler = Self {
+            common,
+            files: HashMap::new(),
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().conntrack().enabled() {
+            if let Ok(mut sampler) = Self::new(common.clone()) {
+                common.runtime().spawn(async move {
+                    while !sampler.retired() {
+                        let _ = sampler.sample().await;
+                    }
+                });
+            } else if !common.config.fault_tolerant() {
+                fatal!("failed to initialize conntrack sampler");
+            } else {
+                error!("failed to initialize conntrack sampler");
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().conntrack()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_utilization().await;
+        self.map_result(r)?;
+
+        if self.statistics.iter().any(|s| s.stat_field().is_some()) {
+            let r = self.sample_stat().await;
+            self.map_result(r)?;
+        }
+
+        // the table may be large, so it is only read when needed
+        if self.statistics.iter().any(|s| s.protocol().is_some()) {
+            let r = self.sample_protocols().await;
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Conntrack {
+    /// Reads the full content of a file, which is kept open between reads
+    async fn read(&mut self, path: &'static str) -> Result<String, std::io::Error> {
+        if !self.files.contains_key(path) {
+            let file = File::open(path).await?;
+            self.files.insert(path, file);
+        }
+        let mut content = String::new();
+        if let Some(file) = self.files.get_mut(path) {
+            file.seek(SeekFrom::Start(0)).await?;
+            file.read_to_string(&mut content).await?;
+        }
+        Ok(content)
+    }
+
+    async fn sample_utilization(&mut self) -> Result<(), std::io::Error> {
+        let count = self.read(CONNTRACK_COUNT).await?.trim().parse::<u64>().ok();
+        let max = self.read(CONNTRACK_MAX).await?.trim().parse::<u64>().ok();
+
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            let value = match statistic {
+                ConntrackStatistic::Entries => count,
+                ConntrackStatistic::Max => max,
+                // stored as hundredths of a percent
+                ConntrackStatistic::Utilization => match (count, max) {
+                    (Some(count), Some(max)) if max > 0 => Some(count * 10_000 / max),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(value) = value {
+                let _ = self.metrics().record_gauge(statistic, time, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sample_stat(&mut self) -> Result<(), std::io::Error> {
+        let content = self.read(CONNTRACK_STAT).await?;
+        let result = parse_stat(&content);
+
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            if let Some(value) = statistic.stat_field().and_then(|f| result.get(f)) {
+                self.common().record_counter(statistic, time, *value);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sample_protocols(&mut self) -> Result<(), std::io::Error> {
+        let content = self.read(CONNTRACK_TABLE).await?;
+        let result = parse_protocols(&content);
+
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            if let Some(protocol) = statistic.protocol() {
+                let value = result.get(protocol).copied().unwrap_or(0);
+                let _ = self.metrics().record_gauge(statistic, time, value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `/proc/net/stat/nf_conntrack`, which has a header row of field names
+/// followed by a row of hex values for each CPU, into the sum of each field
+/// across all CPUs
+fn parse_stat(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    let mut lines = content.lines();
+    let fields: Vec<&str> = match lines.next() {
+        Some(header) => header.split_whitespace().collect(),
+        None => return result,
+    };
+    for line in lines {
+        for (field, value) in fields.iter().zip(line.split_whitespace()) {
+            if let Ok(value) = u64::from_str_radix(value, 16) {
+                *result.entry(field.to_string()).or_insert(0) += value;
+            }
+        }
+    }
+    result
+}
+
+/// Counts the entries of `/proc/net/nf_conntrack` by layer 4 protocol, which
+/// is the third column of each entry
+fn parse_protocols(content: &str) -> HashMap<&'static str, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let protocol = match line.split_whitespace().nth(2) {
+            Some("tcp") => "tcp",
+            Some("udp") => "udp",
+            Some("icmp") | Some("icmpv6") => "icmp",
+            Some(_) => "other",
+            None => continue,
+        };
+        *result.entry(protocol).or_insert(0) += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let result = parse_stat(
+            "entries  searched found new invalid ignore delete delete_list insert \
+             insert_failed drop early_drop icmp_error  expect_new expect_create \
+             expect_delete search_restart\n\
+             0000002a  00000000 00000000 00000000 00000003 00000010 00000000 00000000 \
+             00000000 00000001 00000000 00000000 00000000  00000000 00000000 00000000 \
+             00000004\n\
+             0000002a  00000000 00000000 00000000 0000000a 00000020 00000000 00000000 \
+             00000000 00000002 00000001 00000000 00000000  00000000 00000000 00000000 \
+             00000000\n",
+        );
+        assert_eq!(result.get("invalid"), Some(&13));
+        assert_eq!(result.get("insert_failed"), Some(&3));
+        assert_eq!(result.get("drop"), Some(&1));
+        assert_eq!(result.get("search_restart"), Some(&4));
+    }
+
+    #[test]
+    fn test_parse_protocols() {
+        let result = parse_protocols(
+            "ipv4     2 tcp      6 431999 ESTABLISHED src=10.0.0.1 dst=10.0.0.2 sport=22 \
+             dport=52000 src=10.0.0.2 dst=10.0.0.1 sport=52000 dport=22 [ASSURED] mark=0 \
+             zone=0 use=2\n\
+             ipv4     2 udp      17 29 src=10.0.0.1 dst=10.0.0.53 sport=40000 dport=53 \
+             src=10.0.0.53 dst=10.0.0.1 sport=53 dport=40000 mark=0 zone=0 use=2\n\
+             ipv6     10 icmpv6   58 29 src=::1 dst=::1 type=128 code=0 id=1 src=::1 dst=::1 \
+             type=129 code=0 id=1 mark=0 zone=0 use=2\n\
+             ipv4     2 gre      47 179 timeout=180, stream_timeout=180 src=10.0.0.1 \
+             dst=10.0.0.3 srckey=0x0 dstkey=0x0 mark=0 zone=0 use=2\n",
+        );
+        assert_eq!(result.get("tcp"), Some(&1));
+        assert_eq!(result.get("udp"), Some(&1));
+        assert_eq!(result.get("icmp"), Some(&1));
+        assert_eq!(result.get("other"), Some(&1));
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ConntrackStatistic {
+    #[strum(serialize = "conntrack/drop")]
+    Drop,
+    #[strum(serialize = "conntrack/early_drop")]
+    EarlyDrop,
+    #[strum(serialize = "conntrack/entries")]
+    Entries,
+    #[strum(serialize = "conntrack/entries/icmp")]
+    EntriesIcmp,
+    #[strum(serialize = "conntrack/entries/other")]
+    EntriesOther,
+    #[strum(serialize = "conntrack/entries/tcp")]
+    EntriesTcp,
+    #[strum(serialize = "conntrack/entries/udp")]
+    EntriesUdp,
+    #[strum(serialize = "conntrack/insert_failed")]
+    InsertFailed,
+    #[strum(serialize = "conntrack/invalid")]
+    Invalid,
+    #[strum(serialize = "conntrack/max")]
+    Max,
+    #[strum(serialize = "conntrack/search_restart")]
+    SearchRestart,
+    #[strum(serialize = "conntrack/utilization")]
+    Utilization,
+}
+
+impl ConntrackStatistic {
+    /// The column of `/proc/net/stat/nf_conntrack` which holds this counter
+    pub fn stat_field(self) -> Option<&'static str> {
+        match self {
+            Self::Drop => Some("drop"),
+            Self::EarlyDrop => Some("early_drop"),
+            Self::InsertFailed => Some("insert_failed"),
+            Self::Invalid => Some("invalid"),
+            Self::SearchRestart => Some("search_restart"),
+            _ => None,
+        }
+    }
+
+    /// The layer 4 protocol of the entries counted by this statistic, where
+    /// `other` matches any protocol without its own statistic
+    pub fn protocol(self) -> Option<&'static str> {
+        match self {
+            Self::EntriesIcmp => Some("icmp"),
+            Self::EntriesOther => Some("other"),
+            Self::EntriesTcp => Some("tcp"),
+            Self::EntriesUdp => Some("udp"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ConntrackStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.stat_field().is_some() {
+            Source::Counter
+        } else {
+            Source::Gauge
+        }
+    }
+}
+
+impl TryFrom<&str> for ConntrackStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ConntrackStatistic::from_str(s)
+    }
+}
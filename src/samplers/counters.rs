@@ -0,0 +1,136 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Turns the raw readings of counters into totals which only ever increase,
+/// so that a counter which wraps or is reset doesn't appear as a huge jump in
+/// its rate. Readings are tracked by the name of the statistic.
+#[derive(Default)]
+pub struct Counters {
+    counters: Mutex<HashMap<String, Counter>>,
+}
+
+struct Counter {
+    /// the last raw reading
+    last: u64,
+    /// the total which is recorded in place of the raw reading
+    total: u64,
+    /// number of times the source of the counter has been reset
+    resets: u64,
+}
+
+impl Counters {
+    /// Takes a raw reading of the named counter and returns the total which
+    /// should be recorded. A reading which is lower than the last is treated
+    /// as a wrap if the counter was close to the maximum of 32 or 64 bits,
+    /// such as the 32-bit counters of some network drivers, or otherwise as
+    /// a reset of its source, such as a restart of a probed process.
+    pub fn record(&self, name: &str, value: u64) -> u64 {
+        let mut counters = self.counters.lock().unwrap();
+        let counter = match counters.get_mut(name) {
+            Some(counter) => counter,
+            None => {
+                counters.insert(
+                    name.to_string(),
+                    Counter {
+                        last: value,
+                        total: value,
+                        resets: 0,
+                    },
+                );
+                return value;
+            }
+        };
+        let delta = if value >= counter.last {
+            value - counter.last
+        } else if let Some(delta) = wrapped(counter.last, value) {
+            delta
+        } else {
+            counter.resets += 1;
+            value
+        };
+        counter.last = value;
+        counter.total = counter.total.wrapping_add(delta);
+        counter.total
+    }
+
+    /// Forgets the named counter, so that its next reading starts a new total
+    pub fn remove(&self, name: &str) {
+        self.counters.lock().unwrap().remove(name);
+    }
+
+    /// Returns the number of resets of each counter which has been reset
+    pub fn resets(&self) -> Vec<(String, u64)> {
+        self.counters
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, counter)| counter.resets > 0)
+            .map(|(name, counter)| (name.clone(), counter.resets))
+            .collect()
+    }
+}
+
+/// Returns the increase from `last` to `value` if it is explained by the
+/// counter wrapping at 32 or 64 bits. A wrap is only assumed if the counter
+/// was in the upper half of its range, and the increase is less than half of
+/// the range.
+fn wrapped(last: u64, value: u64) -> Option<u64> {
+    if last <= u32::MAX as u64 {
+        let delta = (u32::MAX as u64 - last) + value + 1;
+        if delta < 1 << 31 {
+            return Some(delta);
+        }
+    }
+    let delta = value.wrapping_sub(last);
+    if delta < 1 << 63 {
+        Some(delta)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = Counters::default();
+        assert_eq!(counters.record("network/receive/bytes", 100), 100);
+        assert_eq!(counters.record("network/receive/bytes", 150), 150);
+
+        // a 32-bit counter which wraps continues from the total
+        assert_eq!(
+            counters.record("network/receive/bytes", u32::MAX as u64 - 9),
+            u32::MAX as u64 - 9
+        );
+        assert_eq!(
+            counters.record("network/receive/bytes", 20),
+            u32::MAX as u64 + 21
+        );
+        assert!(counters.resets().is_empty());
+
+        // a counter which goes back is otherwise treated as a reset, and its
+        // new reading is added to the total
+        assert_eq!(counters.record("usercall/requests", 1000), 1000);
+        assert_eq!(counters.record("usercall/requests", 10), 1010);
+        assert_eq!(counters.record("usercall/requests", 15), 1015);
+        assert_eq!(
+            counters.resets(),
+            vec![("usercall/requests".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_wrapped() {
+        assert_eq!(wrapped(u32::MAX as u64, 0), Some(1));
+        assert_eq!(wrapped(u64::MAX - 4, 5), Some(10));
+        // a counter which was low in its range has been reset
+        assert_eq!(wrapped(1000, 10), None);
+        assert_eq!(wrapped(1 << 40, 10), None);
+    }
+}
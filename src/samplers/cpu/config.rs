@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -15,12 +15,19 @@ pub struct CpuConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
     #[serde(default)]
     perf_events: bool,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<CpuStatistic>,
 }
 
@@ -28,7 +35,9 @@ impl Default for CpuConfig {
     fn default() -> Self {
         Self {
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             perf_events: Default::default(),
             statistics: default_statistics(),
@@ -46,10 +55,18 @@ impl SamplerConfig for CpuConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
@@ -61,7 +78,7 @@ impl SamplerConfig for CpuConfig {
     fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
         let mut enabled = Vec::new();
         for statistic in self.statistics.iter() {
-            if statistic.table().is_some() {
+            if statistic.perf() {
                 if self.perf_events() {
                     enabled.push(*statistic);
                 }
@@ -36,6 +36,9 @@ pub struct Cpu {
     cstates: HashMap<String, String>,
     cstate_files: HashMap<String, HashMap<String, File>>,
     perf: Option<Arc<Mutex<BPF>>>,
+    /// instructions and cycles at the previous sample, to calculate the
+    /// instructions per cycle
+    perf_previous: Option<(u64, u64)>,
     tick_duration: u64,
     proc_cpuinfo: Option<File>,
     proc_stat: Option<File>,
@@ -61,6 +64,7 @@ impl Sampler for Cpu {
             cstates: HashMap::new(),
             cstate_files: HashMap::new(),
             perf: None,
+            perf_previous: None,
             tick_duration: nanos_per_tick(),
             proc_cpuinfo: None,
             proc_stat: None,
@@ -96,7 +100,7 @@ impl Sampler for Cpu {
         if common.config().samplers().cpu().enabled() {
             if let Ok(mut cpu) = Cpu::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !cpu.retired() {
                         let _ = cpu.sample().await;
                     }
                 });
@@ -120,6 +124,13 @@ impl Sampler for Cpu {
         self.common.config().samplers().cpu()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -135,7 +146,7 @@ impl Sampler for Cpu {
         // between underlying counter updates
         #[cfg(feature = "bpf")]
         {
-            let r = self.sample_bpf_perf_counters();
+            let r = self.time_bpf(|s| s.sample_bpf_perf_counters());
             self.map_result(r)?;
         }
 
@@ -232,8 +243,7 @@ impl Cpu {
             let time = Instant::now();
             for stat in self.sampler_config().statistics() {
                 if let Some(value) = result.get(&stat) {
-                    let _ = self
-                        .metrics()
+                    self.common()
                         .record_counter(&stat, time, value * self.tick_duration);
                 }
             }
@@ -272,10 +282,11 @@ impl Cpu {
     }
 
     #[cfg(feature = "bpf")]
-    fn sample_bpf_perf_counters(&self) -> Result<(), std::io::Error> {
+    fn sample_bpf_perf_counters(&mut self) -> Result<(), std::io::Error> {
+        let time = Instant::now();
+        let mut totals = HashMap::new();
         if let Some(ref bpf) = self.perf {
             let bpf = bpf.lock().unwrap();
-            let time = Instant::now();
             for stat in self.statistics.iter().filter(|s| s.table().is_some()) {
                 if let Ok(table) = &(*bpf).inner.table(stat.table().unwrap()) {
                     let map = crate::common::bpf::perf_table_to_map(table);
@@ -283,8 +294,26 @@ impl Cpu {
                     for (_cpu, count) in map.iter() {
                         total += count;
                     }
-                    let _ = self.metrics().record_counter(stat, time, total);
+                    self.common().record_counter(stat, time, total);
+                    totals.insert(*stat, total);
+                }
+            }
+        }
+
+        // instructions per cycle, in hundredths, since the previous sample
+        if self.statistics.contains(&CpuStatistic::Ipc) {
+            if let (Some(&instructions), Some(&cycles)) = (
+                totals.get(&CpuStatistic::Instructions),
+                totals.get(&CpuStatistic::Cycles),
+            ) {
+                if let Some((previous_instructions, previous_cycles)) = self.perf_previous {
+                    let cycles = cycles.wrapping_sub(previous_cycles);
+                    if cycles > 0 {
+                        let ipc = instructions.wrapping_sub(previous_instructions) * 100 / cycles;
+                        let _ = self.metrics().record_gauge(&CpuStatistic::Ipc, time, ipc);
+                    }
                 }
+                self.perf_previous = Some((instructions, cycles));
             }
         }
         Ok(())
@@ -379,7 +408,7 @@ impl Cpu {
         let time = Instant::now();
         for stat in &self.statistics {
             if let Some(value) = result.get(stat) {
-                let _ = self.metrics().record_counter(stat, time, *value);
+                self.common().record_counter(stat, time, *value);
             }
         }
 
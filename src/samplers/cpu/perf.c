@@ -17,6 +17,12 @@ BPF_PERF_ARRAY(dtlb_store_miss_array, NUM_CPU);
 BPF_PERF_ARRAY(dtlb_store_access_array, NUM_CPU);
 BPF_PERF_ARRAY(instructions_array, NUM_CPU);
 BPF_PERF_ARRAY(reference_cycles_array, NUM_CPU);
+BPF_PERF_ARRAY(l1d_load_access_array, NUM_CPU);
+BPF_PERF_ARRAY(l1d_load_miss_array, NUM_CPU);
+BPF_PERF_ARRAY(llc_load_access_array, NUM_CPU);
+BPF_PERF_ARRAY(llc_load_miss_array, NUM_CPU);
+BPF_PERF_ARRAY(stalled_cycles_backend_array, NUM_CPU);
+BPF_PERF_ARRAY(stalled_cycles_frontend_array, NUM_CPU);
 
 // Tables which are read in user space
 BPF_ARRAY(branch_instructions, u64, NUM_CPU);
@@ -30,6 +36,12 @@ BPF_ARRAY(dtlb_store_access, u64, NUM_CPU);
 BPF_ARRAY(dtlb_store_miss, u64, NUM_CPU);
 BPF_ARRAY(instructions, u64, NUM_CPU);
 BPF_ARRAY(reference_cycles, u64, NUM_CPU);
+BPF_ARRAY(l1d_load_access, u64, NUM_CPU);
+BPF_ARRAY(l1d_load_miss, u64, NUM_CPU);
+BPF_ARRAY(llc_load_access, u64, NUM_CPU);
+BPF_ARRAY(llc_load_miss, u64, NUM_CPU);
+BPF_ARRAY(stalled_cycles_backend, u64, NUM_CPU);
+BPF_ARRAY(stalled_cycles_frontend, u64, NUM_CPU);
 
 int do_count(struct bpf_perf_event_data *ctx) {
     u32 cpu = bpf_get_smp_processor_id();
@@ -90,5 +102,35 @@ int do_count(struct bpf_perf_event_data *ctx) {
         reference_cycles.update(&cpu, &count);
     }
 
+    count = l1d_load_access_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        l1d_load_access.update(&cpu, &count);
+    }
+
+    count = l1d_load_miss_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        l1d_load_miss.update(&cpu, &count);
+    }
+
+    count = llc_load_access_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        llc_load_access.update(&cpu, &count);
+    }
+
+    count = llc_load_miss_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        llc_load_miss.update(&cpu, &count);
+    }
+
+    count = stalled_cycles_backend_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        stalled_cycles_backend.update(&cpu, &count);
+    }
+
+    count = stalled_cycles_frontend_array.perf_read(CUR_CPU_IDENTIFIER);
+    if ((s64)count < -256 || (s64)count > 0) {
+        stalled_cycles_frontend.update(&cpu, &count);
+    }
+
     return 0;
 }
@@ -54,6 +54,14 @@ pub enum CpuStatistic {
     BpuBranches,
     #[strum(serialize = "cpu/bpu/miss")]
     BpuMiss,
+    #[strum(serialize = "cpu/cache/l1d/load/access")]
+    L1dLoadAccess,
+    #[strum(serialize = "cpu/cache/l1d/load/miss")]
+    L1dLoadMiss,
+    #[strum(serialize = "cpu/cache/llc/load/access")]
+    LlcLoadAccess,
+    #[strum(serialize = "cpu/cache/llc/load/miss")]
+    LlcLoadMiss,
     #[strum(serialize = "cpu/cycles")]
     Cycles,
     #[strum(serialize = "cpu/dtlb/load/miss")]
@@ -66,8 +74,14 @@ pub enum CpuStatistic {
     DtlbStoreMiss,
     #[strum(serialize = "cpu/instructions")]
     Instructions,
+    #[strum(serialize = "cpu/ipc")]
+    Ipc,
     #[strum(serialize = "cpu/reference_cycles")]
     ReferenceCycles,
+    #[strum(serialize = "cpu/stalled_cycles/backend")]
+    StalledCyclesBackend,
+    #[strum(serialize = "cpu/stalled_cycles/frontend")]
+    StalledCyclesFrontend,
     #[strum(serialize = "cpu/cstate/c0/time")]
     CstateC0Time,
     #[strum(serialize = "cpu/cstate/c1/time")]
@@ -103,7 +117,7 @@ impl Statistic<AtomicU64, AtomicU32> for CpuStatistic {
 
     fn source(&self) -> Source {
         match self {
-            Self::Frequency => Source::Gauge,
+            Self::Frequency | Self::Ipc => Source::Gauge,
             _ => Source::Counter,
         }
     }
@@ -139,7 +153,33 @@ impl CpuStatistic {
                 CacheResult::Access,
             )),
             Self::Instructions => Some(Event::Hardware(HardwareEvent::Instructions)),
+            Self::L1dLoadAccess => Some(Event::HardwareCache(
+                CacheId::L1D,
+                CacheOp::Read,
+                CacheResult::Access,
+            )),
+            Self::L1dLoadMiss => Some(Event::HardwareCache(
+                CacheId::L1D,
+                CacheOp::Read,
+                CacheResult::Miss,
+            )),
+            Self::LlcLoadAccess => Some(Event::HardwareCache(
+                CacheId::LL,
+                CacheOp::Read,
+                CacheResult::Access,
+            )),
+            Self::LlcLoadMiss => Some(Event::HardwareCache(
+                CacheId::LL,
+                CacheOp::Read,
+                CacheResult::Miss,
+            )),
             Self::ReferenceCycles => Some(Event::Hardware(HardwareEvent::RefCpuCycles)),
+            Self::StalledCyclesBackend => {
+                Some(Event::Hardware(HardwareEvent::StalledCyclesBackend))
+            }
+            Self::StalledCyclesFrontend => {
+                Some(Event::Hardware(HardwareEvent::StalledCyclesFrontend))
+            }
             _ => None,
         }
     }
@@ -156,10 +196,22 @@ impl CpuStatistic {
             Self::DtlbStoreMiss => Some("dtlb_store_miss"),
             Self::DtlbStoreAccess => Some("dtlb_store_access"),
             Self::Instructions => Some("instructions"),
+            Self::L1dLoadAccess => Some("l1d_load_access"),
+            Self::L1dLoadMiss => Some("l1d_load_miss"),
+            Self::LlcLoadAccess => Some("llc_load_access"),
+            Self::LlcLoadMiss => Some("llc_load_miss"),
             Self::ReferenceCycles => Some("reference_cycles"),
+            Self::StalledCyclesBackend => Some("stalled_cycles_backend"),
+            Self::StalledCyclesFrontend => Some("stalled_cycles_frontend"),
             _ => None,
         }
     }
+
+    /// Whether this statistic requires perf events. Instructions per cycle is
+    /// derived from the instructions and cycles counters.
+    pub fn perf(self) -> bool {
+        self.table().is_some() || self == Self::Ipc
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,269 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_CPU: &str = "/sys/devices/system/cpu";
+
+/// Counts cycles at the actual frequency while the core is not halted
+const MSR_APERF: u64 = 0xE8;
+/// Counts cycles at the nominal frequency while the core is not halted
+const MSR_MPERF: u64 = 0xE7;
+
+pub struct Cpufreq {
+    common: Common,
+    /// the msr device of each cpu, which is opened on first use
+    msr: HashMap<u64, File>,
+    /// the previous APERF and MPERF readings of each cpu
+    previous: HashMap<u64, (u64, u64)>,
+    /// statistics which have been registered, as cpus are discovered at
+    /// runtime
+    registered: HashSet<CpufreqStatistic>,
+    statistics: Vec<CpufreqConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Cpufreq {
+    type Statistic = CpufreqStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().cpufreq().statistics.clone();
+        Ok(Self {
+            common,
+            msr: HashMap::new(),
+            previous: HashMap::new(),
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().cpufreq().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize cpufreq sampler {}", e);
+                    } else {
+                        error!("failed to initialize cpufreq sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().cpufreq()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().cpufreq().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_cpus().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Cpufreq {
+    fn record(&mut self, statistic: CpufreqStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    async fn sample_cpus(&mut self) -> Result<(), std::io::Error> {
+        let msr = self.common.config().samplers().cpufreq().msr();
+        let mut entries = tokio::fs::read_dir(SYS_CPU).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let cpu: u64 = match entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .and_then(|id| id.parse().ok())
+            {
+                Some(cpu) => cpu,
+                None => continue,
+            };
+            let cpufreq = entry.path().join("cpufreq");
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                match statistic {
+                    CpufreqConfigStatistic::PstateTime => {
+                        // only provided by some drivers, such as acpi-cpufreq
+                        let path = cpufreq.join("stats/time_in_state");
+                        if let Ok(content) = tokio::fs::read_to_string(path).await {
+                            for (frequency, value) in parse_time_in_state(&content) {
+                                self.record(CpufreqStatistic::pstate(cpu, frequency), time, value);
+                            }
+                        }
+                    }
+                    CpufreqConfigStatistic::TurboRatio => {
+                        if msr {
+                            if let Some(ratio) = self.sample_msr(cpu) {
+                                self.record(CpufreqStatistic::new(cpu, statistic), time, ratio);
+                            }
+                        }
+                    }
+                    _ => {
+                        let path = cpufreq.join(statistic.sysfs().unwrap());
+                        if let Ok(content) = tokio::fs::read_to_string(path).await {
+                            if let Ok(khz) = content.trim().parse::<u64>() {
+                                let metric = CpufreqStatistic::new(cpu, statistic);
+                                self.record(metric, time, khz * 1000);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the APERF and MPERF MSRs of a cpu and returns the ratio of the
+    /// busy frequency to the nominal frequency since the previous reading, in
+    /// hundredths of a percent
+    fn sample_msr(&mut self, cpu: u64) -> Option<u64> {
+        if !self.msr.contains_key(&cpu) {
+            match File::open(format!("/dev/cpu/{}/msr", cpu)) {
+                Ok(file) => {
+                    self.msr.insert(cpu, file);
+                }
+                Err(e) => {
+                    debug!("failed to open msr for cpu{}: {}", cpu, e);
+                    return None;
+                }
+            }
+        }
+        let file = self.msr.get(&cpu)?;
+        let aperf = read_msr(file, MSR_APERF)?;
+        let mperf = read_msr(file, MSR_MPERF)?;
+        let previous = self.previous.insert(cpu, (aperf, mperf));
+        let (previous_aperf, previous_mperf) = previous?;
+        turbo_ratio(
+            aperf.wrapping_sub(previous_aperf),
+            mperf.wrapping_sub(previous_mperf),
+        )
+    }
+}
+
+fn read_msr(file: &File, register: u64) -> Option<u64> {
+    let mut buffer = [0_u8; 8];
+    file.read_exact_at(&mut buffer, register).ok()?;
+    Some(u64::from_ne_bytes(buffer))
+}
+
+/// The ratio of the APERF to MPERF deltas, in hundredths of a percent, which
+/// is above 10000 when the cpu ran above its nominal frequency
+fn turbo_ratio(aperf: u64, mperf: u64) -> Option<u64> {
+    if mperf == 0 {
+        None
+    } else {
+        Some((aperf as u128 * 10_000 / mperf as u128) as u64)
+    }
+}
+
+/// Parses `cpufreq/stats/time_in_state`, which has a line for each frequency,
+/// in kHz, with the time spent at it in units of 10ms. Returns the frequency
+/// in Hz and time in nanoseconds.
+fn parse_time_in_state(content: &str) -> Vec<(u64, u64)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let khz: u64 = fields.next()?.parse().ok()?;
+            let time: u64 = fields.next()?.parse().ok()?;
+            Some((khz * 1000, time * 10 * crate::MILLISECOND))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_in_state() {
+        assert_eq!(
+            parse_time_in_state("3400000 12\n2400000 345\n1200000 6789\n"),
+            vec![
+                (3_400_000_000, 120 * crate::MILLISECOND),
+                (2_400_000_000, 3450 * crate::MILLISECOND),
+                (1_200_000_000, 67890 * crate::MILLISECOND),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_turbo_ratio() {
+        assert_eq!(turbo_ratio(3_000, 2_000), Some(15_000));
+        assert_eq!(turbo_ratio(1_000, 2_000), Some(5_000));
+        assert_eq!(turbo_ratio(1_000, 0), None);
+    }
+}
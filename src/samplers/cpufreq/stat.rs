@@ -0,0 +1,119 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum CpufreqConfigStatistic {
+    #[strum(serialize = "frequency/current")]
+    FrequencyCurrent,
+    #[strum(serialize = "frequency/max")]
+    FrequencyMax,
+    #[strum(serialize = "frequency/min")]
+    FrequencyMin,
+    #[strum(serialize = "pstate/time")]
+    PstateTime,
+    #[strum(serialize = "turbo/ratio")]
+    TurboRatio,
+}
+
+impl CpufreqConfigStatistic {
+    /// The file in the cpufreq directory of a CPU, in kHz, for frequency
+    /// statistics
+    pub fn sysfs(self) -> Option<&'static str> {
+        match self {
+            Self::FrequencyCurrent => Some("scaling_cur_freq"),
+            Self::FrequencyMax => Some("scaling_max_freq"),
+            Self::FrequencyMin => Some("scaling_min_freq"),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::PstateTime => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for CpufreqConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        CpufreqConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CpufreqStatistic {
+    name: String,
+    statistic: CpufreqConfigStatistic,
+}
+
+impl CpufreqStatistic {
+    /// A statistic of a CPU, which is named `cpufreq/cpu<id>/...`
+    pub fn new(cpu: u64, statistic: CpufreqConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("cpufreq/cpu{}/{}", cpu, suffix),
+                format!("cpufreq/{}", suffix),
+                &[("cpu", &cpu.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    /// The time a CPU has spent in a P-state, which is named
+    /// `cpufreq/cpu<id>/pstate/<MHz>/time`
+    pub fn pstate(cpu: u64, frequency: u64) -> Self {
+        Self {
+            name: labeled(
+                format!("cpufreq/cpu{}/pstate/{}/time", cpu, frequency / 1_000_000),
+                "cpufreq/pstate/time".to_string(),
+                &[
+                    ("cpu", &cpu.to_string()),
+                    ("frequency", &(frequency / 1_000_000).to_string()),
+                ],
+            ),
+            statistic: CpufreqConfigStatistic::PstateTime,
+        }
+    }
+
+    pub fn statistic(&self) -> CpufreqConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for CpufreqStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
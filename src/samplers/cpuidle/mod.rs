@@ -0,0 +1,175 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_CPU: &str = "/sys/devices/system/cpu";
+
+pub struct Cpuidle {
+    common: Common,
+    /// statistics which have been registered, as cpus are discovered at
+    /// runtime
+    registered: HashSet<CpuidleStatistic>,
+    statistics: Vec<CpuidleConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Cpuidle {
+    type Statistic = CpuidleStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().cpuidle().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().cpuidle().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize cpuidle sampler {}", e);
+                    } else {
+                        error!("failed to initialize cpuidle sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().cpuidle()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().cpuidle().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_cpus().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Cpuidle {
+    fn record(&mut self, statistic: CpuidleStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    async fn sample_cpus(&mut self) -> Result<(), std::io::Error> {
+        let mut entries = tokio::fs::read_dir(SYS_CPU).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let cpu: u64 = match entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .and_then(|id| id.parse().ok())
+            {
+                Some(cpu) => cpu,
+                None => continue,
+            };
+            // cpus which are offline, or without a cpuidle driver, have no
+            // states
+            let mut states = match tokio::fs::read_dir(entry.path().join("cpuidle")).await {
+                Ok(states) => states,
+                Err(_) => continue,
+            };
+            while let Some(state) = states.next_entry().await? {
+                if !state.file_name().to_string_lossy().starts_with("state") {
+                    continue;
+                }
+                let name = match tokio::fs::read_to_string(state.path().join("name")).await {
+                    Ok(name) => name.trim().to_string(),
+                    Err(_) => continue,
+                };
+                let time = Instant::now();
+                for statistic in self.statistics.clone() {
+                    let value = match statistic {
+                        // reported in microseconds
+                        CpuidleConfigStatistic::Time => read_value(&state.path().join("time"))
+                            .await
+                            .map(|v| v * crate::MICROSECOND),
+                        CpuidleConfigStatistic::Usage => {
+                            read_value(&state.path().join("usage")).await
+                        }
+                    };
+                    if let Some(value) = value {
+                        self.record(CpuidleStatistic::new(cpu, &name, statistic), time, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read_value(path: &Path) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,84 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum CpuidleConfigStatistic {
+    #[strum(serialize = "time")]
+    Time,
+    #[strum(serialize = "usage")]
+    Usage,
+}
+
+impl CpuidleConfigStatistic {
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for CpuidleConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        CpuidleConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CpuidleStatistic {
+    name: String,
+    statistic: CpuidleConfigStatistic,
+}
+
+impl CpuidleStatistic {
+    /// A statistic of a C-state of a CPU, which is named
+    /// `cpuidle/cpu<id>/<state>/...`, with the state name in lowercase
+    pub fn new(cpu: u64, state: &str, statistic: CpuidleConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("cpuidle/cpu{}/{}/{}", cpu, state.to_lowercase(), suffix),
+                format!("cpuidle/{}", suffix),
+                &[("cpu", &cpu.to_string()), ("state", &state.to_lowercase())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> CpuidleConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for CpuidleStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -9,6 +9,19 @@ struct val_t {
     char name[TASK_COMM_LEN];
 };
 
+// operations, which are also the op of the per-disk histogram keys
+#define OP_READ 0
+#define OP_WRITE 1
+#define OP_FLUSH 2
+
+// the key of the per-disk latency and size histograms, with the device number encoded
+// as the major in the upper 12 bits and the minor in the lower 20 bits
+struct disk_key_t {
+    u32 dev;
+    u32 op;
+    u32 index;
+};
+
 // hashes to track request details
 BPF_HASH(queue_start, struct request *);
 BPF_HASH(request_start, struct request *);
@@ -23,6 +36,12 @@ BPF_HISTOGRAM(io_size_write, int, 461);
 BPF_HISTOGRAM(latency_write, int, 461);
 BPF_HISTOGRAM(device_latency_write, int, 461);
 BPF_HISTOGRAM(queue_latency_write, int, 461);
+BPF_HISTOGRAM(latency_flush, int, 461);
+#ifdef PER_DISK
+BPF_HASH(disk_latency, struct disk_key_t, u64);
+BPF_HASH(disk_io_size, struct disk_key_t, u64);
+#endif
+
 // histogram indexing
 static unsigned int value_to_index2(unsigned int value) {
     unsigned int index = 460;
@@ -57,6 +76,49 @@ static unsigned int value_to_index2(unsigned int value) {
     return index;
 }
 
+// requests which are neither writes nor flushes, such as discards, are
+// counted as reads
+static u32 req_op(struct request *req)
+{
+    #ifdef REQ_WRITE
+        if (req->cmd_flags & REQ_FLUSH) {
+            return OP_FLUSH;
+        }
+        return (req->cmd_flags & REQ_WRITE) ? OP_WRITE : OP_READ;
+    #else
+        #ifdef REQ_OP_SHIFT
+            u32 op = req->cmd_flags >> REQ_OP_SHIFT;
+        #else
+            u32 op = req->cmd_flags & REQ_OP_MASK;
+        #endif
+        if (op == REQ_OP_FLUSH) {
+            return OP_FLUSH;
+        }
+        return op == REQ_OP_WRITE ? OP_WRITE : OP_READ;
+    #endif
+}
+
+#ifdef PER_DISK
+// fills in the per-disk histogram key, returning non-zero if the request has
+// no disk
+static int disk_key(struct request *req, u32 op, unsigned int index, struct disk_key_t *key)
+{
+    // the disk of a request moved to its queue in Linux 5.17
+    #ifdef REQ_Q_DISK
+        struct gendisk *disk = req->q->disk;
+    #else
+        struct gendisk *disk = req->rq_disk;
+    #endif
+    if (disk == 0) {
+        return -1;
+    }
+    key->dev = disk->major << 20 | disk->first_minor;
+    key->op = op;
+    key->index = index;
+    return 0;
+}
+#endif
+
 int trace_pid_start(struct pt_regs *ctx, struct request *req)
 {
     struct val_t val = {};
@@ -72,22 +134,15 @@ int trace_req_start(struct pt_regs *ctx, struct request *req)
 {
     u64 now = bpf_ktime_get_ns();
 
-    u64 rwflag = 0;
-    #ifdef REQ_WRITE
-        rwflag = !!(req->cmd_flags & REQ_WRITE);
-    #elif defined(REQ_OP_SHIFT)
-        rwflag = !!((req->cmd_flags >> REQ_OP_SHIFT) == REQ_OP_WRITE);
-    #else
-        rwflag = !!((req->cmd_flags & REQ_OP_MASK) == REQ_OP_WRITE);
-    #endif
+    u32 op = req_op(req);
 
     u64 *enqueued;
     enqueued = queue_start.lookup(&req);
     if (enqueued != 0) {
         unsigned int index = value_to_index2((now - *enqueued) / 1000);
-        if (rwflag == 1) {
+        if (op == OP_WRITE) {
             queue_latency_write.increment(index);
-        } else {
+        } else if (op == OP_READ) {
             queue_latency_read.increment(index);
         }
     }
@@ -99,14 +154,7 @@ int do_count(struct pt_regs *ctx, struct request *req)
 {
     u64 now = bpf_ktime_get_ns();
 
-    u64 rwflag = 0;
-    #ifdef REQ_WRITE
-        rwflag = !!(req->cmd_flags & REQ_WRITE);
-    #elif defined(REQ_OP_SHIFT)
-        rwflag = !!((req->cmd_flags >> REQ_OP_SHIFT) == REQ_OP_WRITE);
-    #else
-        rwflag = !!((req->cmd_flags & REQ_OP_MASK) == REQ_OP_WRITE);
-    #endif
+    u32 op = req_op(req);
 
     // Size
     struct val_t *valp;
@@ -117,11 +165,17 @@ int do_count(struct pt_regs *ctx, struct request *req)
     u64 delta = req->__data_len / 1024;
     unsigned int index = value_to_index2(delta);
     if (req->__data_len > 0) {
-        if (rwflag == 1) {
+        if (op == OP_WRITE) {
             io_size_write.increment(index);
-        } else {
+        } else if (op == OP_READ) {
             io_size_read.increment(index);
         }
+        #ifdef PER_DISK
+            struct disk_key_t key = {};
+            if (op != OP_FLUSH && disk_key(req, op, index, &key) == 0) {
+                disk_io_size.increment(key);
+            }
+        #endif
     }
 
     // Latency
@@ -131,20 +185,28 @@ int do_count(struct pt_regs *ctx, struct request *req)
     enqueued = queue_start.lookup(&req);
     if (enqueued != 0) {
         unsigned int index = value_to_index2((now - *enqueued) / 1000);
-        if (rwflag == 1) {
+        if (op == OP_WRITE) {
             latency_write.increment(index);
+        } else if (op == OP_FLUSH) {
+            latency_flush.increment(index);
         } else {
             latency_read.increment(index);
         }
+        #ifdef PER_DISK
+            struct disk_key_t key = {};
+            if (disk_key(req, op, index, &key) == 0) {
+                disk_latency.increment(key);
+            }
+        #endif
     }
 
     // request latency not including queued time
     requested = request_start.lookup(&req);
     if (requested != 0) {
         unsigned int index = value_to_index2((now - *requested) / 1000);
-        if (rwflag == 1) {
+        if (op == OP_WRITE) {
             device_latency_write.increment(index);
-        } else {
+        } else if (op == OP_READ) {
             device_latency_read.increment(index);
         }
     }
@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -15,12 +15,21 @@ pub struct DiskConfig {
     #[serde(default)]
     bpf: bool,
     #[serde(default)]
+    devices: bool,
+    #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<DiskStatistic>,
 }
 
@@ -28,8 +37,11 @@ impl Default for DiskConfig {
     fn default() -> Self {
         Self {
             bpf: Default::default(),
+            devices: Default::default(),
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             statistics: default_statistics(),
         }
@@ -40,6 +52,14 @@ fn default_statistics() -> Vec<DiskStatistic> {
     DiskStatistic::iter().collect()
 }
 
+impl DiskConfig {
+    /// Whether statistics are also reported for each disk. Latency and IO
+    /// size distributions require BPF
+    pub fn devices(&self) -> bool {
+        self.devices
+    }
+}
+
 impl SamplerConfig for DiskConfig {
     type Statistic = DiskStatistic;
 
@@ -51,10 +71,18 @@ impl SamplerConfig for DiskConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
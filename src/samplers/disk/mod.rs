@@ -2,13 +2,14 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::SeekFrom;
 use std::sync::{Arc, Mutex};
 use std::time::*;
 
 use async_trait::async_trait;
 use regex::Regex;
+use rustcommon_metrics::{Output, Source, Statistic, Summary};
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
 
@@ -28,6 +29,9 @@ pub struct Disk {
     bpf: Option<Arc<Mutex<BPF>>>,
     bpf_last: Arc<Mutex<Instant>>,
     common: Common,
+    /// names of disks, by device number
+    device_names: HashMap<u32, String>,
+    device_statistics: HashSet<DeviceStatistic>,
     proc_diskstats: Option<File>,
     disk_regex: Option<Regex>,
     statistics: Vec<DiskStatistic>,
@@ -46,6 +50,8 @@ impl Sampler for Disk {
             bpf: None,
             bpf_last: Arc::new(Mutex::new(Instant::now())),
             common,
+            device_names: HashMap::new(),
+            device_statistics: HashSet::new(),
             proc_diskstats: None,
             disk_regex: None,
             statistics,
@@ -69,7 +75,7 @@ impl Sampler for Disk {
         if common.config().samplers().disk().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -93,6 +99,13 @@ impl Sampler for Disk {
         self.common.config().samplers().disk()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -106,8 +119,13 @@ impl Sampler for Disk {
 
         let r = self.sample_diskstats().await;
         self.map_result(r)?;
+        let r = self.sample_in_flight().await;
+        self.map_result(r)?;
         #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
@@ -118,6 +136,9 @@ impl Disk {
     #[cfg(feature = "bpf")]
     fn bpf_enabled(&self) -> bool {
         if self.sampler_config().bpf() {
+            if self.common.config().samplers().disk().devices() {
+                return true;
+            }
             for statistic in self.sampler_config().statistics() {
                 if statistic.bpf_table().is_some() {
                     return true;
@@ -133,8 +154,15 @@ impl Disk {
             if self.enabled() && self.bpf_enabled() {
                 debug!("initializing bpf");
                 // load the code and compile
-                let code = include_str!("bpf.c");
-                let mut bpf = bcc::BPF::new(code)?;
+                let mut code = String::new();
+                if self.common.config().samplers().disk().devices() {
+                    code.push_str("#define PER_DISK\n");
+                }
+                if kernel_version().map(|v| v >= (5, 17)).unwrap_or(false) {
+                    code.push_str("#define REQ_Q_DISK\n");
+                }
+                code.push_str(include_str!("bpf.c"));
+                let mut bpf = bcc::BPF::new(&code)?;
                 // load + attach kprobes!
                 bcc::Kprobe::new()
                     .handler("trace_pid_start")
@@ -220,20 +248,76 @@ impl Disk {
                             | DiskStatistic::BandwidthDiscard => value * 512,
                             _ => *value,
                         };
-                        let _ = self.metrics().record_counter(stat, time, value);
+                        self.common().record_counter(stat, time, value);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the number of reads and writes in flight for each disk from
+    /// `/sys/block/<disk>/inflight`
+    async fn sample_in_flight(&mut self) -> Result<(), std::io::Error> {
+        let devices = self.common.config().samplers().disk().devices();
+        let read = self.statistics.contains(&DiskStatistic::InFlightRead);
+        let write = self.statistics.contains(&DiskStatistic::InFlightWrite);
+        if !devices && !read && !write {
+            return Ok(());
+        }
+
+        let mut disks = Vec::new();
+        let mut entries = tokio::fs::read_dir("/sys/block").await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Ok(name) = entry.file_name().into_string() {
+                if let Some(re) = &self.disk_regex {
+                    if re.is_match(&name) {
+                        disks.push(name);
+                    }
+                }
+            }
+        }
+
+        let time = Instant::now();
+        let mut total = (0, 0);
+        for disk in disks {
+            let path = format!("/sys/block/{}/inflight", disk);
+            let in_flight = match tokio::fs::read_to_string(&path).await {
+                Ok(content) => parse_inflight(&content),
+                Err(_) => None,
+            };
+            if let Some((reads, writes)) = in_flight {
+                total.0 += reads;
+                total.1 += writes;
+                if devices {
+                    for (operation, value) in &[("read", reads), ("write", writes)] {
+                        let statistic = DeviceStatistic::in_flight(&disk, operation);
+                        self.register_device_statistic(&statistic);
+                        let _ = self.metrics().record_gauge(&statistic, time, *value);
                     }
                 }
             }
         }
+        if read {
+            let _ = self
+                .metrics()
+                .record_gauge(&DiskStatistic::InFlightRead, time, total.0);
+        }
+        if write {
+            let _ = self
+                .metrics()
+                .record_gauge(&DiskStatistic::InFlightWrite, time, total.1);
+        }
 
         Ok(())
     }
 
     #[cfg(feature = "bpf")]
-    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+    fn sample_bpf(&mut self) -> Result<(), std::io::Error> {
         use std::convert::TryInto;
         if self.bpf_last.lock().unwrap().elapsed()
-            >= Duration::new(self.general_config().window().try_into().unwrap(), 0)
+            >= Duration::new(self.window().try_into().unwrap(), 0)
         {
             let time = Instant::now();
             if let Some(ref bpf) = self.bpf {
@@ -242,7 +326,7 @@ impl Disk {
                     if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
                         for (&value, &count) in &map_from_table(&mut table) {
                             if count > 0 {
-                                let _ = self.metrics().record_bucket(
+                                self.common().record_bucket(
                                     statistic,
                                     time,
                                     value * crate::MICROSECOND,
@@ -253,8 +337,150 @@ impl Disk {
                     }
                 }
             }
+            if self.common.config().samplers().disk().devices() {
+                self.sample_bpf_devices();
+            }
             *self.bpf_last.lock().unwrap() = Instant::now();
         }
         Ok(())
     }
+
+    /// Transfers the latency and size histograms of each disk, which are each
+    /// held in a single table keyed by device, operation, and bucket
+    #[cfg(feature = "bpf")]
+    fn sample_bpf_devices(&mut self) {
+        let tables: [(&str, fn(&str, &str) -> DeviceStatistic); 2] = [
+            ("disk_latency", DeviceStatistic::latency),
+            ("disk_io_size", DeviceStatistic::io_size),
+        ];
+        let mut histograms = HashMap::<(usize, u32, u32), Vec<(u64, u32)>>::new();
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            for (id, (name, _)) in tables.iter().enumerate() {
+                let mut table = match (*bpf).inner.table(name) {
+                    Ok(table) => table,
+                    Err(_) => continue,
+                };
+                for mut entry in table.iter() {
+                    if entry.key.len() != 12 {
+                        continue;
+                    }
+                    let field = |i: usize| parse_u32(entry.key[(i * 4)..(i * 4 + 4)].to_vec());
+                    let (dev, op, index) = (field(0), field(1), field(2));
+                    let count = parse_u64(entry.value.clone());
+                    if count > 0 {
+                        if let Some(value) = key_to_value(index as u64) {
+                            histograms
+                                .entry((id, dev, op))
+                                .or_default()
+                                .push((value, count as u32));
+                        }
+                        // clear the source counter
+                        let _ = table.set(&mut entry.key, &mut [0_u8; 8]);
+                    }
+                }
+            }
+        }
+
+        let time = Instant::now();
+        for ((id, dev, op), buckets) in histograms {
+            let operation = match op {
+                0 => "read",
+                1 => "write",
+                2 => "flush",
+                _ => continue,
+            };
+            let device = self.device_name(dev);
+            let statistic = (tables[id].1)(&device, operation);
+            self.register_device_statistic(&statistic);
+            for (value, count) in buckets {
+                self.common()
+                    .record_bucket(&statistic, time, value * crate::MICROSECOND, count);
+            }
+        }
+    }
+
+    /// Returns the name of a disk by its device number, as encoded by the BPF
+    /// program, falling back to `major:minor` if it has no name in sysfs
+    #[cfg(feature = "bpf")]
+    fn device_name(&mut self, dev: u32) -> String {
+        if let Some(name) = self.device_names.get(&dev) {
+            return name.clone();
+        }
+        let device = format!("{}:{}", dev >> 20, dev & 0xFFFFF);
+        let name = std::fs::read_link(format!("/sys/dev/block/{}", device))
+            .ok()
+            .and_then(|path| path.file_name().map(|v| v.to_string_lossy().to_string()))
+            .unwrap_or(device);
+        self.device_names.insert(dev, name.clone());
+        name
+    }
+
+    fn register_device_statistic(&mut self, statistic: &DeviceStatistic) {
+        if self.device_statistics.contains(statistic) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            if statistic.source() == Source::Distribution {
+                self.metrics()
+                    .add_summary(statistic, self.heatmap(1_000_000_000));
+            } else {
+                self.metrics()
+                    .add_summary(statistic, Summary::stream(self.samples()));
+            }
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.device_statistics.insert(statistic.clone());
+    }
+}
+
+/// Parses the number of reads and writes in flight from the contents of
+/// `/sys/block/<disk>/inflight`
+fn parse_inflight(content: &str) -> Option<(u64, u64)> {
+    let mut parts = content.split_whitespace();
+    let reads = parts.next()?.parse().ok()?;
+    let writes = parts.next()?.parse().ok()?;
+    Some((reads, writes))
+}
+
+/// Returns the major and minor version of the running kernel
+#[allow(dead_code)]
+fn kernel_version() -> Option<(u32, u32)> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    parse_kernel_version(&release)
+}
+
+/// Parses the major and minor version from a kernel release, such as
+/// `5.15.0-86-generic`
+fn parse_kernel_version(release: &str) -> Option<(u32, u32)> {
+    let mut parts = release.trim().split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_inflight() {
+        assert_eq!(parse_inflight("       3       12\n"), Some((3, 12)));
+        assert_eq!(parse_inflight("0 0"), Some((0, 0)));
+        assert_eq!(parse_inflight("7"), None);
+    }
+
+    #[test]
+    fn test_parse_kernel_version() {
+        assert_eq!(parse_kernel_version("5.15.0-86-generic\n"), Some((5, 15)));
+        assert_eq!(parse_kernel_version("6.1.55"), Some((6, 1)));
+        assert_eq!(parse_kernel_version("4.19-rc1"), Some((4, 19)));
+        assert_eq!(parse_kernel_version("unknown"), None);
+    }
 }
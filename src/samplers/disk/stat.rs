@@ -10,6 +10,8 @@ use serde_derive::{Deserialize, Serialize};
 use strum::ParseError;
 use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 
+use crate::common::labels::labeled;
+
 #[derive(
     Clone,
     Copy,
@@ -41,6 +43,8 @@ pub enum DiskStatistic {
     LatencyRead,
     #[strum(serialize = "disk/write/latency")]
     LatencyWrite,
+    #[strum(serialize = "disk/flush/latency")]
+    LatencyFlush,
     #[strum(serialize = "disk/read/device_latency")]
     DeviceLatencyRead,
     #[strum(serialize = "disk/write/device_latency")]
@@ -53,6 +57,10 @@ pub enum DiskStatistic {
     IoSizeRead,
     #[strum(serialize = "disk/write/io_size")]
     IoSizeWrite,
+    #[strum(serialize = "disk/read/in_flight")]
+    InFlightRead,
+    #[strum(serialize = "disk/write/in_flight")]
+    InFlightWrite,
 }
 
 impl DiskStatistic {
@@ -60,6 +68,7 @@ impl DiskStatistic {
         match self {
             Self::LatencyRead => Some("latency_read"),
             Self::LatencyWrite => Some("latency_write"),
+            Self::LatencyFlush => Some("latency_flush"),
             Self::DeviceLatencyRead => Some("device_latency_read"),
             Self::DeviceLatencyWrite => Some("device_latency_write"),
             Self::QueueLatencyRead => Some("queue_latency_read"),
@@ -80,7 +89,10 @@ impl Statistic<AtomicU64, AtomicU32> for DiskStatistic {
         if self.bpf_table().is_some() {
             Source::Distribution
         } else {
-            Source::Counter
+            match self {
+                Self::InFlightRead | Self::InFlightWrite => Source::Gauge,
+                _ => Source::Counter,
+            }
         }
     }
 }
@@ -92,3 +104,64 @@ impl TryFrom<&str> for DiskStatistic {
         DiskStatistic::from_str(s)
     }
 }
+
+/// A statistic of a single disk, which are discovered at runtime
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DeviceStatistic {
+    name: String,
+    gauge: bool,
+}
+
+impl DeviceStatistic {
+    /// The end-to-end latency of an operation, named
+    /// `disk/<device>/<operation>/latency`
+    pub fn latency(device: &str, operation: &str) -> Self {
+        Self {
+            name: labeled(
+                format!("disk/{}/{}/latency", device, operation),
+                "disk/latency".to_string(),
+                &[("device", device), ("operation", operation)],
+            ),
+            gauge: false,
+        }
+    }
+
+    /// The size of an operation, named `disk/<device>/<operation>/io_size`
+    pub fn io_size(device: &str, operation: &str) -> Self {
+        Self {
+            name: labeled(
+                format!("disk/{}/{}/io_size", device, operation),
+                "disk/io_size".to_string(),
+                &[("device", device), ("operation", operation)],
+            ),
+            gauge: false,
+        }
+    }
+
+    /// The number of operations which have been issued to the device but not
+    /// yet completed, named `disk/<device>/<operation>/in_flight`
+    pub fn in_flight(device: &str, operation: &str) -> Self {
+        Self {
+            name: labeled(
+                format!("disk/{}/{}/in_flight", device, operation),
+                "disk/in_flight".to_string(),
+                &[("device", device), ("operation", operation)],
+            ),
+            gauge: true,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for DeviceStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        if self.gauge {
+            Source::Gauge
+        } else {
+            Source::Distribution
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::command_output;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_BLOCK: &str = "/sys/block";
+const SECTOR_SIZE: u64 = 512;
+
+pub struct Dm {
+    common: Common,
+    /// statistics which have been registered, as devices are discovered at
+    /// runtime
+    registered: HashSet<DmStatistic>,
+    statistics: Vec<DmConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Dm {
+    type Statistic = DmStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().dm().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().dm().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize dm sampler {}", e);
+                    } else {
+                        error!("failed to initialize dm sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().dm()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().dm().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_io().await;
+        self.map_result(r)?;
+
+        let r = self.sample_pools().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Dm {
+    fn record(&mut self, statistic: DmStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples the IO counters of each device-mapper device
+    async fn sample_io(&mut self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| s.stat_field().is_some()) {
+            return Ok(());
+        }
+        let mut entries = tokio::fs::read_dir(SYS_BLOCK).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = match tokio::fs::read_to_string(path.join("dm/name")).await {
+                Ok(name) => name.trim().to_string(),
+                // not a device-mapper device
+                Err(_) => continue,
+            };
+            let stat = match tokio::fs::read_to_string(path.join("stat")).await {
+                Ok(stat) => stat,
+                Err(_) => continue,
+            };
+            let fields: Vec<u64> = stat
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if let Some((index, sectors)) = statistic.stat_field() {
+                    // discard fields are only present from Linux 4.18
+                    if let Some(value) = fields.get(index) {
+                        let value = if sectors { value * SECTOR_SIZE } else { *value };
+                        self.record(DmStatistic::new(&name, statistic), time, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the data and metadata utilization of each thin pool and cache.
+    /// These are only reported through the status of the device-mapper table,
+    /// so they are read from the output of `dmsetup status`.
+    async fn sample_pools(&mut self) -> Result<(), std::io::Error> {
+        if self.statistics.iter().all(|s| s.stat_field().is_some()) {
+            return Ok(());
+        }
+        let output = command_output(tokio::process::Command::new("dmsetup").args(&[
+            "status",
+            "--target",
+            "thin-pool",
+        ]))
+        .await?;
+        let mut pools = parse_status(&String::from_utf8_lossy(&output.stdout));
+        let output = command_output(
+            tokio::process::Command::new("dmsetup").args(&["status", "--target", "cache"]),
+        )
+        .await?;
+        pools.extend(parse_status(&String::from_utf8_lossy(&output.stdout)));
+
+        let time = Instant::now();
+        for (name, pool) in pools {
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    DmConfigStatistic::DataUtilization => utilization(pool.data),
+                    DmConfigStatistic::MetadataUtilization => utilization(pool.metadata),
+                    _ => continue,
+                };
+                if let Some(value) = value {
+                    self.record(DmStatistic::new(&name, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Used and total blocks of the data and metadata of a thin pool or cache
+#[derive(Debug, Default, PartialEq)]
+struct Pool {
+    data: (u64, u64),
+    metadata: (u64, u64),
+}
+
+/// Utilization, in hundredths of a percent, of (used, total) blocks
+fn utilization((used, total): (u64, u64)) -> Option<u64> {
+    if total == 0 {
+        return None;
+    }
+    Some(used.saturating_mul(10_000) / total)
+}
+
+fn parse_blocks(value: &str) -> Option<(u64, u64)> {
+    let (used, total) = value.split_once('/')?;
+    Some((used.parse().ok()?, total.parse().ok()?))
+}
+
+/// Parses the output of `dmsetup status`, which has a line for each target of
+/// the form `<name>: <start> <length> <target> <status...>`. The status of a
+/// `thin-pool` begins with `<transaction id> <used>/<total metadata blocks>
+/// <used>/<total data blocks>`, and that of a `cache` with `<metadata block
+/// size> <used>/<total metadata blocks> <cache block size> <used>/<total cache
+/// blocks>`.
+fn parse_status(content: &str) -> HashMap<String, Pool> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let (name, status) = match line.split_once(": ") {
+            Some((name, status)) => (name, status),
+            None => continue,
+        };
+        let fields: Vec<&str> = status.split_whitespace().collect();
+        let (metadata, data) = match fields.get(2) {
+            Some(&"thin-pool") => (fields.get(4), fields.get(5)),
+            Some(&"cache") => (fields.get(4), fields.get(6)),
+            _ => continue,
+        };
+        if let (Some(metadata), Some(data)) = (
+            metadata.and_then(|v| parse_blocks(v)),
+            data.and_then(|v| parse_blocks(v)),
+        ) {
+            result.insert(name.to_string(), Pool { data, metadata });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let pools = parse_status(
+            "vg0-pool-tpool: 0 209715200 thin-pool 3 1234/262144 81920/102400 - rw \
+             no_discard_passdown queue_if_no_space - 1024\n\
+             vg0-root: 0 41943040 linear \n\
+             vg0-cached: 0 976773168 cache 8 2048/32768 128 4096/8192 100 50 20 10 0 0 3 \
+             1 writeback 2 migration_threshold 2048 smq 0 rw -\n\
+             vg0-failed-tpool: 0 10 thin-pool Fail\n",
+        );
+        assert_eq!(pools.len(), 2);
+        let pool = &pools["vg0-pool-tpool"];
+        assert_eq!(utilization(pool.data), Some(8000));
+        assert_eq!(utilization(pool.metadata), Some(47));
+        let cache = &pools["vg0-cached"];
+        assert_eq!(cache.data, (4096, 8192));
+        assert_eq!(cache.metadata, (2048, 32768));
+    }
+}
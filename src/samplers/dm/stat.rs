@@ -0,0 +1,113 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum DmConfigStatistic {
+    #[strum(serialize = "data/utilization")]
+    DataUtilization,
+    #[strum(serialize = "discard/bytes")]
+    DiscardBytes,
+    #[strum(serialize = "discard/operations")]
+    DiscardOperations,
+    #[strum(serialize = "metadata/utilization")]
+    MetadataUtilization,
+    #[strum(serialize = "read/bytes")]
+    ReadBytes,
+    #[strum(serialize = "read/operations")]
+    ReadOperations,
+    #[strum(serialize = "write/bytes")]
+    WriteBytes,
+    #[strum(serialize = "write/operations")]
+    WriteOperations,
+}
+
+impl DmConfigStatistic {
+    /// The index of the field in `/sys/block/<dev>/stat` this statistic is
+    /// read from, and whether the field is in sectors
+    pub fn stat_field(self) -> Option<(usize, bool)> {
+        match self {
+            Self::ReadOperations => Some((0, false)),
+            Self::ReadBytes => Some((2, true)),
+            Self::WriteOperations => Some((4, false)),
+            Self::WriteBytes => Some((6, true)),
+            Self::DiscardOperations => Some((11, false)),
+            Self::DiscardBytes => Some((13, true)),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::DataUtilization | Self::MetadataUtilization => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for DmConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        DmConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DmStatistic {
+    name: String,
+    statistic: DmConfigStatistic,
+}
+
+impl DmStatistic {
+    /// A statistic of a device-mapper device, which is named `dm/<name>/...`
+    /// using the name of the device rather than its `dm-N` kernel name
+    pub fn new(device: &str, statistic: DmConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("dm/{}/{}", device, suffix),
+                format!("dm/{}", suffix),
+                &[("device", device)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> DmConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for DmStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Based on: https://github.com/iovisor/bcc/blob/master/tools/gethostlatency.py
+
+#include <uapi/linux/ptrace.h>
+
+// results, which index the results array
+#define RESULT_TOTAL 0
+#define RESULT_NXDOMAIN 1
+#define RESULT_TIMEOUT 2
+#define RESULT_ERROR 3
+
+// return values of getaddrinfo() from netdb.h
+#define EAI_NONAME -2
+#define EAI_AGAIN -3
+
+BPF_HASH(start, u64, u64);
+BPF_ARRAY(results, u64, 4);
+BPF_HISTOGRAM(latency, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+int trace_lookup_entry(struct pt_regs *ctx)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    start.update(&id, &ts);
+    return 0;
+}
+
+// records the latency of a lookup, returning zero if the entry was missed
+static int record_latency(void)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    u64 *tsp = start.lookup(&id);
+    if (tsp == 0) {
+        return 0;   // missed entry
+    }
+    u64 delta_us = (bpf_ktime_get_ns() - *tsp) / 1000ul;
+    latency.increment(value_to_index2(delta_us));
+    start.delete(&id);
+
+    results.increment(RESULT_TOTAL);
+    return 1;
+}
+
+int trace_getaddrinfo_return(struct pt_regs *ctx)
+{
+    if (!record_latency()) {
+        return 0;
+    }
+    int ret = PT_REGS_RC(ctx);
+    if (ret == EAI_NONAME) {
+        results.increment(RESULT_NXDOMAIN);
+    } else if (ret == EAI_AGAIN) {
+        results.increment(RESULT_TIMEOUT);
+    } else if (ret != 0) {
+        results.increment(RESULT_ERROR);
+    }
+    return 0;
+}
+
+// gethostbyname() reports the reason for a failure in h_errno, which is thread
+// local and not readable here, so failures are only counted as errors
+int trace_gethostbyname_return(struct pt_regs *ctx)
+{
+    if (!record_latency()) {
+        return 0;
+    }
+    if (PT_REGS_RC(ctx) == 0) {
+        results.increment(RESULT_ERROR);
+    }
+    return 0;
+}
@@ -0,0 +1,97 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DnsConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "default_libc")]
+    libc: String,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    statistics: Vec<DnsStatistic>,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            libc: default_libc(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+// resolved by bcc through the linker cache
+fn default_libc() -> String {
+    "c".to_string()
+}
+
+fn default_statistics() -> Vec<DnsStatistic> {
+    DnsStatistic::iter().collect()
+}
+
+impl DnsConfig {
+    /// The path or name of the libc which provides the resolver functions
+    pub fn libc(&self) -> &str {
+        &self.libc
+    }
+}
+
+impl SamplerConfig for DnsConfig {
+    type Statistic = DnsStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        self.statistics.clone()
+    }
+}
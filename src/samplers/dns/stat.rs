@@ -0,0 +1,80 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum DnsStatistic {
+    #[strum(serialize = "dns/lookup/error")]
+    LookupError,
+    #[strum(serialize = "dns/lookup/latency")]
+    LookupLatency,
+    #[strum(serialize = "dns/lookup/nxdomain")]
+    LookupNxdomain,
+    #[strum(serialize = "dns/lookup/timeout")]
+    LookupTimeout,
+    #[strum(serialize = "dns/lookup/total")]
+    LookupTotal,
+}
+
+impl DnsStatistic {
+    /// The index of the counter in the `results` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::LookupTotal => Some(0),
+            Self::LookupNxdomain => Some(1),
+            Self::LookupTimeout => Some(2),
+            Self::LookupError => Some(3),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::LookupLatency => Some("latency"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for DnsStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.bpf_table().is_some() {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for DnsStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        DnsStatistic::from_str(s)
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+mod sys;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Ebpf {
+    common: Common,
+    /// statistics which have been registered, as programs are discovered at
+    /// runtime
+    registered: HashSet<EbpfStatistic>,
+    statistics: Vec<EbpfConfigStatistic>,
+}
+
+/// The totals of the programs which share a name
+#[derive(Default)]
+struct Programs {
+    map_memory: u64,
+    memory: u64,
+    run_count: u64,
+    run_time: u64,
+}
+
+#[async_trait]
+impl Sampler for Ebpf {
+    type Statistic = EbpfStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().ebpf().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().ebpf().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize ebpf sampler {}", e);
+                    } else {
+                        error!("failed to initialize ebpf sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().ebpf()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().ebpf().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_bpf_objects();
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Ebpf {
+    fn register(&mut self, statistic: &EbpfStatistic) {
+        if self.registered.contains(statistic) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            self.metrics()
+                .add_summary(statistic, Summary::stream(self.samples()));
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.registered.insert(statistic.clone());
+    }
+
+    fn record(&mut self, statistic: EbpfStatistic, time: Instant, value: u64) {
+        self.register(&statistic);
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Enumerates the loaded programs and maps, including those loaded by
+    /// rezolus itself. Programs which share a name, such as those attached to
+    /// several functions, are reported together.
+    fn sample_bpf_objects(&mut self) -> Result<(), std::io::Error> {
+        let mut maps = HashMap::new();
+        for id in sys::map_ids()? {
+            // maps may be released while they are being enumerated
+            if let Ok(memory) = sys::map_memory(id) {
+                maps.insert(id, memory);
+            }
+        }
+
+        let mut count = 0;
+        let mut programs = HashMap::<String, Programs>::new();
+        for id in sys::program_ids()? {
+            let program = match sys::program(id) {
+                Ok(program) => program,
+                Err(_) => continue,
+            };
+            count += 1;
+            let name = if program.name.is_empty() {
+                "unnamed".to_string()
+            } else {
+                program.name
+            };
+            let totals = programs.entry(name).or_default();
+            totals.map_memory += program
+                .map_ids
+                .iter()
+                .filter_map(|id| maps.get(id))
+                .sum::<u64>();
+            totals.memory += program.memory;
+            totals.run_count += program.run_count;
+            totals.run_time += program.run_time;
+        }
+
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            let value = match statistic {
+                EbpfConfigStatistic::Maps => maps.len() as u64,
+                EbpfConfigStatistic::MapsMemory => maps.values().sum(),
+                EbpfConfigStatistic::Programs => count,
+                _ => continue,
+            };
+            self.record(EbpfStatistic::host(statistic), time, value);
+        }
+        let statistics: Vec<EbpfConfigStatistic> = self
+            .statistics
+            .iter()
+            .filter(|s| s.program())
+            .copied()
+            .collect();
+        for (name, totals) in &programs {
+            for statistic in statistics.iter().copied() {
+                let value = match statistic {
+                    EbpfConfigStatistic::ProgramMapMemory => totals.map_memory,
+                    EbpfConfigStatistic::ProgramMemory => totals.memory,
+                    EbpfConfigStatistic::ProgramRunCount => totals.run_count,
+                    EbpfConfigStatistic::ProgramRunTime => totals.run_time,
+                    _ => continue,
+                };
+                self.record(EbpfStatistic::program(name, statistic), time, value);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum EbpfConfigStatistic {
+    #[strum(serialize = "maps")]
+    Maps,
+    #[strum(serialize = "maps/memory")]
+    MapsMemory,
+    #[strum(serialize = "programs")]
+    Programs,
+    #[strum(serialize = "program/map/memory")]
+    ProgramMapMemory,
+    #[strum(serialize = "program/memory")]
+    ProgramMemory,
+    #[strum(serialize = "program/run/count")]
+    ProgramRunCount,
+    #[strum(serialize = "program/run/time")]
+    ProgramRunTime,
+}
+
+impl EbpfConfigStatistic {
+    /// Whether this statistic is reported for each program
+    pub fn program(self) -> bool {
+        matches!(
+            self,
+            Self::ProgramMapMemory
+                | Self::ProgramMemory
+                | Self::ProgramRunCount
+                | Self::ProgramRunTime
+        )
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::ProgramRunCount | Self::ProgramRunTime => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for EbpfConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        EbpfConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EbpfStatistic {
+    name: String,
+    statistic: EbpfConfigStatistic,
+}
+
+impl EbpfStatistic {
+    /// A host-wide statistic, which is named `ebpf/...`
+    pub fn host(statistic: EbpfConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("ebpf/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of the programs with the given name, which is named
+    /// `ebpf/program/<name>/...`
+    pub fn program(program: &str, statistic: EbpfConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!(
+                    "ebpf/program/{}/{}",
+                    program,
+                    suffix.trim_start_matches("program/")
+                ),
+                format!("ebpf/{}", suffix),
+                &[("program", program)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> EbpfConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for EbpfStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Wrappers around the `bpf()` syscall to enumerate the BPF programs and maps
+//! which are loaded on the host and to read their info. These require
+//! `CAP_SYS_ADMIN`.
+
+use std::io::Error;
+use std::os::unix::io::RawFd;
+
+// bpf() commands
+const BPF_PROG_GET_NEXT_ID: libc::c_long = 11;
+const BPF_MAP_GET_NEXT_ID: libc::c_long = 12;
+const BPF_PROG_GET_FD_BY_ID: libc::c_long = 13;
+const BPF_MAP_GET_FD_BY_ID: libc::c_long = 14;
+const BPF_OBJ_GET_INFO_BY_FD: libc::c_long = 15;
+
+/// The attributes of the `*_GET_NEXT_ID` and `*_GET_FD_BY_ID` commands
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Default)]
+struct IdAttr {
+    start_id: u32,
+    next_id: u32,
+    open_flags: u32,
+}
+
+/// The attributes of the `BPF_OBJ_GET_INFO_BY_FD` command
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Default)]
+struct InfoAttr {
+    bpf_fd: u32,
+    info_len: u32,
+    info: u64,
+}
+
+/// A prefix of `struct bpf_prog_info`, the kernel fills in as much of it as
+/// it knows about
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Default)]
+struct ProgInfo {
+    prog_type: u32,
+    id: u32,
+    tag: [u8; 8],
+    jited_prog_len: u32,
+    xlated_prog_len: u32,
+    jited_prog_insns: u64,
+    xlated_prog_insns: u64,
+    load_time: u64,
+    created_by_uid: u32,
+    nr_map_ids: u32,
+    map_ids: u64,
+    name: [u8; 16],
+    ifindex: u32,
+    gpl_compatible: u32,
+    netns_dev: u64,
+    netns_ino: u64,
+    nr_jited_ksyms: u32,
+    nr_jited_func_lens: u32,
+    jited_ksyms: u64,
+    jited_func_lens: u64,
+    btf_id: u32,
+    func_info_rec_size: u32,
+    func_info: u64,
+    nr_func_info: u32,
+    nr_line_info: u32,
+    line_info: u64,
+    jited_line_info: u64,
+    nr_jited_line_info: u32,
+    line_info_rec_size: u32,
+    jited_line_info_rec_size: u32,
+    nr_prog_tags: u32,
+    prog_tags: u64,
+    run_time_ns: u64,
+    run_cnt: u64,
+}
+
+/// A loaded BPF program
+pub struct Program {
+    pub name: String,
+    /// ids of the maps used by the program
+    pub map_ids: Vec<u32>,
+    /// bytes of locked memory used by the program
+    pub memory: u64,
+    /// number of times the program has run, which is only counted while the
+    /// `kernel.bpf_stats_enabled` sysctl is set
+    pub run_count: u64,
+    /// total time, in nanoseconds, the program has run for, which is only
+    /// counted while the `kernel.bpf_stats_enabled` sysctl is set
+    pub run_time: u64,
+}
+
+/// An open file descriptor of a BPF object, which is closed on drop
+struct Fd(RawFd);
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+fn bpf<T>(cmd: libc::c_long, attr: &mut T) -> Result<libc::c_long, Error> {
+    let ret =
+        unsafe { libc::syscall(libc::SYS_bpf, cmd, attr as *mut T, std::mem::size_of::<T>()) };
+    if ret < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+fn ids(cmd: libc::c_long) -> Result<Vec<u32>, Error> {
+    let mut ids = Vec::new();
+    let mut attr = IdAttr::default();
+    loop {
+        match bpf(cmd, &mut attr) {
+            Ok(_) => {
+                ids.push(attr.next_id);
+                attr.start_id = attr.next_id;
+            }
+            // there are no more objects after the start id
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => return Ok(ids),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn fd_by_id(cmd: libc::c_long, id: u32) -> Result<Fd, Error> {
+    let mut attr = IdAttr {
+        start_id: id,
+        ..Default::default()
+    };
+    bpf(cmd, &mut attr).map(|fd| Fd(fd as RawFd))
+}
+
+/// Returns the ids of all loaded programs
+pub fn program_ids() -> Result<Vec<u32>, Error> {
+    ids(BPF_PROG_GET_NEXT_ID)
+}
+
+/// Returns the ids of all loaded maps
+pub fn map_ids() -> Result<Vec<u32>, Error> {
+    ids(BPF_MAP_GET_NEXT_ID)
+}
+
+/// Returns the program with the given id, which fails if it has since been
+/// unloaded
+pub fn program(id: u32) -> Result<Program, Error> {
+    let fd = fd_by_id(BPF_PROG_GET_FD_BY_ID, id)?;
+
+    // the first call returns the number of maps, which are then read into a
+    // buffer of that size by the second
+    let mut info = ProgInfo::default();
+    prog_info(&fd, &mut info)?;
+    let mut map_ids = vec![0_u32; info.nr_map_ids as usize];
+    if !map_ids.is_empty() {
+        info = ProgInfo {
+            nr_map_ids: map_ids.len() as u32,
+            map_ids: map_ids.as_mut_ptr() as u64,
+            ..Default::default()
+        };
+        prog_info(&fd, &mut info)?;
+        // maps may have been released in between the calls
+        map_ids.truncate(info.nr_map_ids as usize);
+    }
+
+    let len = info
+        .name
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(info.name.len());
+    Ok(Program {
+        name: String::from_utf8_lossy(&info.name[..len]).to_string(),
+        map_ids,
+        memory: memlock(&fd)?,
+        run_count: info.run_cnt,
+        run_time: info.run_time_ns,
+    })
+}
+
+fn prog_info(fd: &Fd, info: &mut ProgInfo) -> Result<(), Error> {
+    let mut attr = InfoAttr {
+        bpf_fd: fd.0 as u32,
+        info_len: std::mem::size_of::<ProgInfo>() as u32,
+        info: info as *mut ProgInfo as u64,
+    };
+    bpf(BPF_OBJ_GET_INFO_BY_FD, &mut attr).map(|_| ())
+}
+
+/// Returns the bytes of locked memory used by the map with the given id,
+/// which fails if it has since been released
+pub fn map_memory(id: u32) -> Result<u64, Error> {
+    let fd = fd_by_id(BPF_MAP_GET_FD_BY_ID, id)?;
+    memlock(&fd)
+}
+
+/// Reads the locked memory of a BPF object from the fdinfo of its descriptor
+fn memlock(fd: &Fd) -> Result<u64, Error> {
+    let content = std::fs::read_to_string(format!("/proc/self/fdinfo/{}", fd.0))?;
+    Ok(parse_memlock(&content).unwrap_or(0))
+}
+
+/// Parses the `memlock` field from the fdinfo of a BPF program or map
+fn parse_memlock(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("memlock:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_memlock() {
+        let content = "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\n\
+                       prog_type:\t1\nprog_jited:\t1\nprog_tag:\t3b185187f1855c4c\n\
+                       memlock:\t4096\nprog_id:\t33\n";
+        assert_eq!(parse_memlock(content), Some(4096));
+        assert_eq!(parse_memlock("pos:\t0\n"), None);
+    }
+
+    #[test]
+    fn test_prog_info_layout() {
+        // offsets of the fields in struct bpf_prog_info
+        assert_eq!(std::mem::size_of::<ProgInfo>(), 208);
+        let info = ProgInfo::default();
+        let base = &info as *const ProgInfo as usize;
+        assert_eq!(&info.name as *const _ as usize - base, 64);
+        assert_eq!(&info.run_time_ns as *const _ as usize - base, 192);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_EDAC_MC: &str = "/sys/devices/system/edac/mc";
+
+pub struct Edac {
+    common: Common,
+    /// statistics which have been registered, as memory controllers and
+    /// DIMMs are discovered at runtime
+    registered: HashSet<EdacStatistic>,
+    statistics: Vec<EdacConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Edac {
+    type Statistic = EdacStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().edac().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().edac().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize edac sampler {}", e);
+                    } else {
+                        error!("failed to initialize edac sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().edac()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().edac().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_controllers().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Edac {
+    fn record(&mut self, statistic: EdacStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    /// Samples the error counts of each memory controller, which are the
+    /// `mc<N>` directories, and of each of their DIMMs. Older kernels and some
+    /// drivers expose `rank<N>` directories instead of `dimm<N>`.
+    async fn sample_controllers(&mut self) -> Result<(), std::io::Error> {
+        let mut controllers = tokio::fs::read_dir(SYS_EDAC_MC).await?;
+        while let Some(controller) = controllers.next_entry().await? {
+            let name = match controller.file_name().into_string() {
+                Ok(name) if indexed(&name, "mc") => name,
+                _ => continue,
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let path = controller.path().join(statistic.controller());
+                if let Some(value) = read_value(&path).await {
+                    self.record(EdacStatistic::controller(&name, statistic), time, value);
+                }
+            }
+
+            let mut dimms = tokio::fs::read_dir(controller.path()).await?;
+            while let Some(dimm) = dimms.next_entry().await? {
+                let dimm_name = match dimm.file_name().into_string() {
+                    Ok(dimm) if indexed(&dimm, "dimm") || indexed(&dimm, "rank") => dimm,
+                    _ => continue,
+                };
+                for statistic in self.statistics.clone() {
+                    let path = dimm.path().join(statistic.dimm());
+                    if let Some(value) = read_value(&path).await {
+                        self.record(
+                            EdacStatistic::dimm(&name, &dimm_name, statistic),
+                            time,
+                            value,
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read_value<P: AsRef<std::path::Path>>(path: P) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Returns true if the name is the prefix followed by an index, such as `mc0`
+fn indexed(name: &str, prefix: &str) -> bool {
+    match name.strip_prefix(prefix) {
+        Some(index) => !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_indexed() {
+        assert!(indexed("mc0", "mc"));
+        assert!(indexed("dimm12", "dimm"));
+        assert!(!indexed("mc", "mc"));
+        assert!(!indexed("mc_name", "mc"));
+        assert!(!indexed("rank0", "dimm"));
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum EdacConfigStatistic {
+    #[strum(serialize = "correctable")]
+    Correctable,
+    #[strum(serialize = "uncorrectable")]
+    Uncorrectable,
+}
+
+impl EdacConfigStatistic {
+    /// The sysfs file of a memory controller with the count of errors
+    pub fn controller(self) -> &'static str {
+        match self {
+            Self::Correctable => "ce_count",
+            Self::Uncorrectable => "ue_count",
+        }
+    }
+
+    /// The sysfs file of a DIMM with the count of errors
+    pub fn dimm(self) -> &'static str {
+        match self {
+            Self::Correctable => "dimm_ce_count",
+            Self::Uncorrectable => "dimm_ue_count",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for EdacConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        EdacConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EdacStatistic {
+    name: String,
+    statistic: EdacConfigStatistic,
+}
+
+impl EdacStatistic {
+    /// A statistic of a memory controller, which is named `edac/<mc>/...`
+    pub fn controller(controller: &str, statistic: EdacConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("edac/{}/{}", controller, suffix),
+                format!("edac/{}", suffix),
+                &[("controller", controller)],
+            ),
+            statistic,
+        }
+    }
+
+    /// A statistic of a DIMM, which is named `edac/<mc>/<dimm>/...`
+    pub fn dimm(controller: &str, dimm: &str, statistic: EdacConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("edac/{}/{}/{}", controller, dimm, suffix),
+                format!("edac/{}", suffix),
+                &[("controller", controller), ("dimm", dimm)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> EdacConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for EdacStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
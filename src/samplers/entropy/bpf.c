@@ -0,0 +1,45 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+
+// events, which index the counters array
+#define GETRANDOM_BLOCKED 0
+
+// waits shorter than this returned without sleeping, as the crng was ready
+#define MIN_BLOCKED_NS 100000
+
+BPF_HASH(start, u32, u64);
+BPF_ARRAY(counters, u64, 1);
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+int trace_wait_entry(struct pt_regs *ctx)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    start.update(&pid, &ts);
+    return 0;
+}
+
+int trace_wait_return(struct pt_regs *ctx)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = start.lookup(&pid);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    if (bpf_ktime_get_ns() - *tsp >= MIN_BLOCKED_NS) {
+        add(GETRANDOM_BLOCKED, 1);
+    }
+    start.delete(&pid);
+    return 0;
+}
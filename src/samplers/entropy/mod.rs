@@ -0,0 +1,185 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_SYS_RANDOM: &str = "/proc/sys/kernel/random";
+
+#[allow(dead_code)]
+pub struct Entropy {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    common: Common,
+    statistics: Vec<EntropyStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Entropy {
+    type Statistic = EntropyStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().entropy().statistics();
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            common,
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().entropy().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize entropy sampler {}", e);
+                    } else {
+                        error!("failed to initialize entropy sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().entropy()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_procfs().await;
+        self.map_result(r)?;
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Entropy {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && self.statistics.iter().any(|s| s.index().is_some())
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let mut bpf = bcc::BPF::new(include_str!("bpf.c"))?;
+
+                // getrandom(2), and other callers which wait for the crng to
+                // be initialized, block in this function
+                bcc::Kprobe::new()
+                    .handler("trace_wait_entry")
+                    .function("wait_for_random_bytes")
+                    .attach(&mut bpf)?;
+                bcc::Kretprobe::new()
+                    .handler("trace_wait_return")
+                    .function("wait_for_random_bytes")
+                    .attach(&mut bpf)?;
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sample_procfs(&self) -> Result<(), std::io::Error> {
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            if let Some(file) = statistic.procfs() {
+                let path = format!("{}/{}", PROC_SYS_RANDOM, file);
+                let content = tokio::fs::read_to_string(&path).await?;
+                if let Ok(value) = content.trim().parse::<u64>() {
+                    let _ = self.metrics().record_gauge(statistic, time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Some(counters) = bpf.counters("counters") {
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    if let Some(index) = statistic.index() {
+                        let value = counters.get(&index).copied().unwrap_or(0);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
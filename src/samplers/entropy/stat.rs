@@ -0,0 +1,74 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum EntropyStatistic {
+    #[strum(serialize = "entropy/available")]
+    Available,
+    #[strum(serialize = "entropy/getrandom/blocked")]
+    GetrandomBlocked,
+    #[strum(serialize = "entropy/pool_size")]
+    PoolSize,
+}
+
+impl EntropyStatistic {
+    /// The file in `/proc/sys/kernel/random` this statistic is read from
+    pub fn procfs(self) -> Option<&'static str> {
+        match self {
+            Self::Available => Some("entropy_avail"),
+            Self::PoolSize => Some("poolsize"),
+            _ => None,
+        }
+    }
+
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::GetrandomBlocked => Some(0),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for EntropyStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.index().is_some() {
+            Source::Counter
+        } else {
+            Source::Gauge
+        }
+    }
+}
+
+impl TryFrom<&str> for EntropyStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        EntropyStatistic::from_str(s)
+    }
+}
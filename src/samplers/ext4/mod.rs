@@ -59,7 +59,7 @@ impl Sampler for Ext4 {
         if common.config().samplers().ext4().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -83,6 +83,13 @@ impl Sampler for Ext4 {
         self.common.config().samplers().ext4()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -95,8 +102,11 @@ impl Sampler for Ext4 {
         debug!("sampling");
 
         // sample bpf
-        #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
@@ -104,7 +114,7 @@ impl Sampler for Ext4 {
 
 impl Ext4 {
     // checks that bpf is enabled in config and one or more bpf stats enabled
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn bpf_enabled(&self) -> bool {
         if self.sampler_config().bpf() {
             for statistic in &self.statistics {
@@ -166,22 +176,30 @@ impl Ext4 {
             }
         }
 
+        #[cfg(feature = "bpf_core")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let object = include_bytes!(concat!(env!("OUT_DIR"), "/ext4.bpf.o"));
+                let bpf = BPF::load("ext4", object, &[])?;
+                self.bpf = Some(Arc::new(Mutex::new(bpf)));
+            }
+        }
+
         Ok(())
     }
 
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn sample_bpf(&self) -> Result<(), std::io::Error> {
-        if self.bpf_last.lock().unwrap().elapsed()
-            >= Duration::new(self.general_config().window() as u64, 0)
-        {
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
             if let Some(ref bpf) = self.bpf {
                 let bpf = bpf.lock().unwrap();
                 let time = Instant::now();
                 for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
-                    if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
-                        for (&value, &count) in &map_from_table(&mut table) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
                             if count > 0 {
-                                let _ = self.metrics().record_bucket(
+                                self.common().record_bucket(
                                     statistic,
                                     time,
                                     value * crate::MICROSECOND,
@@ -0,0 +1,246 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_FILE_NR: &str = "/proc/sys/fs/file-nr";
+
+pub struct Fd {
+    common: Common,
+    /// statistics which have been registered, as processes are discovered at
+    /// runtime
+    registered: HashSet<FdStatistic>,
+    statistics: Vec<FdConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Fd {
+    type Statistic = FdStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().fd().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().fd().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize fd sampler {}", e);
+                    } else {
+                        error!("failed to initialize fd sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().fd()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().fd().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_file_nr().await;
+        self.map_result(r)?;
+
+        if self.statistics.contains(&FdConfigStatistic::ProcessOpen) {
+            let r = self.sample_processes().await;
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Fd {
+    fn register(&mut self, statistic: &FdStatistic) {
+        if self.registered.contains(statistic) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            self.metrics()
+                .add_summary(statistic, Summary::stream(self.samples()));
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.registered.insert(statistic.clone());
+    }
+
+    fn record(&mut self, statistic: FdStatistic, time: Instant, value: u64) {
+        self.register(&statistic);
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Reads the system-wide number of allocated file handles and its limit
+    async fn sample_file_nr(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_FILE_NR).await?;
+        let (allocated, max) = match parse_file_nr(&content) {
+            Some(v) => v,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("failed to parse {}", PROC_FILE_NR),
+                ));
+            }
+        };
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            let value = match statistic {
+                FdConfigStatistic::Allocated => allocated,
+                FdConfigStatistic::Max => max,
+                FdConfigStatistic::ProcessOpen => continue,
+            };
+            self.record(FdStatistic::host(statistic), time, value);
+        }
+        Ok(())
+    }
+
+    /// Counts the open file descriptors of each process and reports the
+    /// processes with the most. Processes are grouped by name, so that a
+    /// daemon with many worker processes is reported as a single entry.
+    async fn sample_processes(&mut self) -> Result<(), std::io::Error> {
+        let mut processes = Vec::new();
+
+        let mut entries = tokio::fs::read_dir("/proc").await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let pid = match entry.file_name().to_str().map(|v| v.parse::<u32>()) {
+                Some(Ok(pid)) => pid,
+                _ => continue,
+            };
+            // the process may exit at any point while we read it
+            let name = match tokio::fs::read_to_string(format!("/proc/{}/comm", pid)).await {
+                Ok(name) => name.trim().to_string(),
+                Err(_) => continue,
+            };
+            let mut fds = match tokio::fs::read_dir(format!("/proc/{}/fd", pid)).await {
+                Ok(fds) => fds,
+                Err(_) => continue,
+            };
+            let mut open = 0;
+            while let Ok(Some(_)) = fds.next_entry().await {
+                open += 1;
+            }
+            processes.push((name, open));
+        }
+
+        let top_n = self.common.config().samplers().fd().top_n();
+        let time = Instant::now();
+        for (name, open) in rank_processes(processes, top_n) {
+            self.record(
+                FdStatistic::process(&name, FdConfigStatistic::ProcessOpen),
+                time,
+                open,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parses `/proc/sys/fs/file-nr`, which holds the number of allocated file
+/// handles, the number of allocated but unused handles, and the maximum,
+/// returning the number in use and the maximum
+fn parse_file_nr(content: &str) -> Option<(u64, u64)> {
+    let mut fields = content.split_whitespace().map(|v| v.parse::<u64>());
+    let allocated = fields.next()?.ok()?;
+    let unused = fields.next()?.ok()?;
+    let max = fields.next()?.ok()?;
+    Some((allocated.saturating_sub(unused), max))
+}
+
+/// Sums the open file descriptors of processes with the same name and returns
+/// the `top` names with the most, in descending order
+fn rank_processes(processes: Vec<(String, u64)>, top: usize) -> Vec<(String, u64)> {
+    let mut totals = HashMap::<String, u64>::new();
+    for (name, open) in processes {
+        *totals.entry(name).or_default() += open;
+    }
+    let mut ranked: Vec<(String, u64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top);
+    ranked
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_nr() {
+        assert_eq!(
+            parse_file_nr("9824\t0\t9223372036854775807\n"),
+            Some((9824, 9223372036854775807))
+        );
+        assert_eq!(parse_file_nr("1024\t24\t65536\n"), Some((1000, 65536)));
+        assert_eq!(parse_file_nr("1024\n"), None);
+    }
+
+    #[test]
+    fn test_rank_processes() {
+        let processes = vec![
+            ("nginx".to_string(), 100),
+            ("java".to_string(), 500),
+            ("nginx".to_string(), 450),
+            ("sshd".to_string(), 10),
+        ];
+        assert_eq!(
+            rank_processes(processes, 2),
+            vec![("nginx".to_string(), 550), ("java".to_string(), 500)]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum FdConfigStatistic {
+    #[strum(serialize = "allocated")]
+    Allocated,
+    #[strum(serialize = "max")]
+    Max,
+    #[strum(serialize = "process/open")]
+    ProcessOpen,
+}
+
+impl FdConfigStatistic {
+    pub fn source(self) -> Source {
+        Source::Gauge
+    }
+}
+
+impl TryFrom<&str> for FdConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        FdConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FdStatistic {
+    name: String,
+    statistic: FdConfigStatistic,
+}
+
+impl FdStatistic {
+    /// A host-wide statistic, which is named `fd/...`
+    pub fn host(statistic: FdConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("fd/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of the processes with the given name, which is named
+    /// `fd/process/<name>/...`
+    pub fn process(process: &str, statistic: FdConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        // process names may contain characters which would be ambiguous in
+        // the metric name, such as `kworker/0:1`
+        let process: String = process
+            .chars()
+            .map(|c| {
+                if c == '/' || c.is_whitespace() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        Self {
+            name: labeled(
+                format!(
+                    "fd/process/{}/{}",
+                    process,
+                    suffix.trim_start_matches("process/")
+                ),
+                format!("fd/{}", suffix),
+                &[("process", &process)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> FdConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for FdStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
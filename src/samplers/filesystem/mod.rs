@@ -0,0 +1,249 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_MOUNTS: &str = "/proc/self/mounts";
+
+pub struct Filesystem {
+    common: Common,
+    /// statistics which have been registered, as mounts are discovered at
+    /// runtime
+    registered: HashSet<FilesystemStatistic>,
+    statistics: Vec<FilesystemConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Filesystem {
+    type Statistic = FilesystemStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().filesystem().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().filesystem().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize filesystem sampler {}", e);
+                    } else {
+                        error!("failed to initialize filesystem sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().filesystem()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self
+            .common
+            .config()
+            .samplers()
+            .filesystem()
+            .statistics
+            .clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_filesystems().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Filesystem {
+    fn record(&mut self, statistic: FilesystemStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    async fn sample_filesystems(&mut self) -> Result<(), std::io::Error> {
+        let mut mountpoints = self
+            .common
+            .config()
+            .samplers()
+            .filesystem()
+            .mountpoints()
+            .to_vec();
+        if mountpoints.is_empty() {
+            let content = tokio::fs::read_to_string(PROC_MOUNTS).await?;
+            mountpoints = parse_mounts(&content);
+        }
+        for mountpoint in mountpoints {
+            let usage = match Usage::statvfs(&mountpoint) {
+                Ok(usage) => usage,
+                Err(e) => {
+                    // the filesystem may have been unmounted
+                    debug!("failed to statvfs {}: {}", mountpoint, e);
+                    continue;
+                }
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    FilesystemConfigStatistic::BytesAvailable => usage.bytes_available,
+                    FilesystemConfigStatistic::BytesFree => usage.bytes_free,
+                    FilesystemConfigStatistic::BytesTotal => usage.bytes_total,
+                    FilesystemConfigStatistic::BytesUsed => {
+                        usage.bytes_total.saturating_sub(usage.bytes_free)
+                    }
+                    FilesystemConfigStatistic::InodesFree => usage.inodes_free,
+                    FilesystemConfigStatistic::InodesTotal => usage.inodes_total,
+                    FilesystemConfigStatistic::InodesUsed => {
+                        usage.inodes_total.saturating_sub(usage.inodes_free)
+                    }
+                    FilesystemConfigStatistic::Readonly => usage.readonly as u64,
+                };
+                self.record(
+                    FilesystemStatistic::new(&mountpoint, statistic),
+                    time,
+                    value,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// bytes available to unprivileged users
+    bytes_available: u64,
+    /// bytes free, including those reserved for root
+    bytes_free: u64,
+    bytes_total: u64,
+    inodes_free: u64,
+    inodes_total: u64,
+    readonly: bool,
+}
+
+impl Usage {
+    fn statvfs(mountpoint: &str) -> Result<Self, std::io::Error> {
+        let path = CString::new(mountpoint)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let fragment = stat.f_frsize as u64;
+        Ok(Self {
+            bytes_available: stat.f_bavail as u64 * fragment,
+            bytes_free: stat.f_bfree as u64 * fragment,
+            bytes_total: stat.f_blocks as u64 * fragment,
+            inodes_free: stat.f_ffree as u64,
+            inodes_total: stat.f_files as u64,
+            readonly: stat.f_flag & libc::ST_RDONLY != 0,
+        })
+    }
+}
+
+/// Parses the mountpoints of filesystems which store data on the host from
+/// `/proc/self/mounts`. These are those backed by a device, and ZFS datasets,
+/// whose source is the dataset name. Pseudo filesystems are skipped, as are
+/// network filesystems, for which `statvfs` may block if the server is
+/// unavailable.
+fn parse_mounts(content: &str) -> Vec<String> {
+    let mut mountpoints = Vec::new();
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 3 {
+            continue;
+        }
+        let (source, mountpoint, fstype) = (parts[0], parts[1], parts[2]);
+        if !(source.starts_with('/') || fstype == "zfs") {
+            continue;
+        }
+        // the same filesystem may be mounted over an earlier one
+        if !mountpoints.iter().any(|m| m == mountpoint) {
+            mountpoints.push(mountpoint.to_string());
+        }
+    }
+    mountpoints
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let mountpoints = parse_mounts(
+            "sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0\n\
+             proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0\n\
+             /dev/nvme0n1p2 / ext4 rw,relatime 0 0\n\
+             tmpfs /run tmpfs rw,nosuid,nodev,mode=755 0 0\n\
+             /dev/nvme0n1p1 /boot/efi vfat rw,relatime 0 0\n\
+             tank/data /data zfs rw,xattr,noacl 0 0\n\
+             nfs01:/export /mnt/nfs nfs4 rw,relatime,vers=4.1 0 0\n\
+             /dev/sdb1 /data/logs xfs ro,relatime 0 0\n",
+        );
+        assert_eq!(mountpoints, vec!["/", "/boot/efi", "/data", "/data/logs"]);
+    }
+}
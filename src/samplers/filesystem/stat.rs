@@ -0,0 +1,95 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum FilesystemConfigStatistic {
+    #[strum(serialize = "bytes/available")]
+    BytesAvailable,
+    #[strum(serialize = "bytes/free")]
+    BytesFree,
+    #[strum(serialize = "bytes/total")]
+    BytesTotal,
+    #[strum(serialize = "bytes/used")]
+    BytesUsed,
+    #[strum(serialize = "inodes/free")]
+    InodesFree,
+    #[strum(serialize = "inodes/total")]
+    InodesTotal,
+    #[strum(serialize = "inodes/used")]
+    InodesUsed,
+    #[strum(serialize = "readonly")]
+    Readonly,
+}
+
+impl TryFrom<&str> for FilesystemConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        FilesystemConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FilesystemStatistic {
+    name: String,
+    statistic: FilesystemConfigStatistic,
+}
+
+impl FilesystemStatistic {
+    /// A statistic of a mounted filesystem, which is named
+    /// `filesystem/<mountpoint>/...` with the leading `/` of the mountpoint
+    /// removed, and with the root filesystem named `root`
+    pub fn new(mountpoint: &str, statistic: FilesystemConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let mountpoint = match mountpoint.trim_start_matches('/') {
+            "" => "root",
+            mountpoint => mountpoint,
+        };
+        Self {
+            name: labeled(
+                format!("filesystem/{}/{}", mountpoint, suffix),
+                format!("filesystem/{}", suffix),
+                &[("mountpoint", mountpoint)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> FilesystemConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for FilesystemStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Gauge
+    }
+}
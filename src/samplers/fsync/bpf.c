@@ -0,0 +1,163 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <linux/sched.h>
+
+// events, which index the counters array
+#define TOTAL 0
+
+// filesystems, which select the filesystem-specific histogram
+#define FS_EXT4 0
+#define FS_XFS 1
+#define FS_BTRFS 2
+
+struct comm_t {
+    char name[TASK_COMM_LEN];
+};
+
+// the filesystem-specific functions are called from vfs_fsync_range() and
+// have their own start times
+BPF_HASH(start, u32);
+BPF_HASH(fs_start, u32);
+BPF_ARRAY(counters, u64, 1);
+
+// names of the processes to trace, populated by userspace when FILTER is
+// defined
+BPF_HASH(names, struct comm_t, u8);
+
+// value_to_index2() gives us from 0-460 as the index
+BPF_HISTOGRAM(latency, int, 461);
+BPF_HISTOGRAM(latency_ext4, int, 461);
+BPF_HISTOGRAM(latency_xfs, int, 461);
+BPF_HISTOGRAM(latency_btrfs, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+// returns non-zero if the current process should be traced
+static int traced()
+{
+    #ifdef FILTER
+        struct comm_t comm = {};
+        bpf_get_current_comm(&comm.name, sizeof(comm.name));
+        return names.lookup(&comm) != 0;
+    #else
+        return 1;
+    #endif
+}
+
+int trace_entry(struct pt_regs *ctx)
+{
+    if (!traced()) {
+        return 0;
+    }
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    start.update(&pid, &ts);
+    return 0;
+}
+
+int trace_fs_entry(struct pt_regs *ctx)
+{
+    if (!traced()) {
+        return 0;
+    }
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    fs_start.update(&pid, &ts);
+    return 0;
+}
+
+int trace_return(struct pt_regs *ctx)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = start.lookup(&pid);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    latency.increment(value_to_index2(delta));
+    add(TOTAL, 1);
+    start.delete(&pid);
+    return 0;
+}
+
+static int trace_fs_return(struct pt_regs *ctx, int fs)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = fs_start.lookup(&pid);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    unsigned int index = value_to_index2(delta);
+    if (fs == FS_EXT4) {
+        latency_ext4.increment(index);
+    } else if (fs == FS_XFS) {
+        latency_xfs.increment(index);
+    } else if (fs == FS_BTRFS) {
+        latency_btrfs.increment(index);
+    }
+    fs_start.delete(&pid);
+    return 0;
+}
+
+int trace_ext4_return(struct pt_regs *ctx)
+{
+    return trace_fs_return(ctx, FS_EXT4);
+}
+
+int trace_xfs_return(struct pt_regs *ctx)
+{
+    return trace_fs_return(ctx, FS_XFS);
+}
+
+int trace_btrfs_return(struct pt_regs *ctx)
+{
+    return trace_fs_return(ctx, FS_BTRFS);
+}
@@ -0,0 +1,93 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FsyncConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    statistics: Vec<FsyncStatistic>,
+}
+
+impl Default for FsyncConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            names: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_statistics() -> Vec<FsyncStatistic> {
+    FsyncStatistic::iter().collect()
+}
+
+impl FsyncConfig {
+    /// Only trace fsyncs by processes with one of these names. Names longer
+    /// than 15 characters are truncated, as they are by the kernel
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+impl SamplerConfig for FsyncConfig {
+    type Statistic = FsyncStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        self.statistics.clone()
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+#[allow(dead_code)]
+pub struct Fsync {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    statistics: Vec<FsyncStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Fsync {
+    type Statistic = FsyncStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().fsync().statistics();
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().fsync().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize fsync sampler {}", e);
+                    } else {
+                        error!("failed to initialize fsync sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().fsync()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Fsync {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && !self.sampler_config().statistics().is_empty()
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let names = self.common.config().samplers().fsync().names();
+                let code = if names.is_empty() {
+                    include_str!("bpf.c").to_string()
+                } else {
+                    format!("#define FILTER\n{}", include_str!("bpf.c"))
+                };
+                let mut bpf = bcc::BPF::new(&code)?;
+
+                // vfs_fsync_range() is called by both fsync() and fdatasync()
+                bcc::Kprobe::new()
+                    .handler("trace_entry")
+                    .function("vfs_fsync_range")
+                    .attach(&mut bpf)?;
+                bcc::Kretprobe::new()
+                    .handler("trace_return")
+                    .function("vfs_fsync_range")
+                    .attach(&mut bpf)?;
+
+                // filesystems which are built as modules may not be loaded
+                for (handler, function) in &[
+                    ("trace_ext4_return", "ext4_sync_file"),
+                    ("trace_xfs_return", "xfs_file_fsync"),
+                    ("trace_btrfs_return", "btrfs_sync_file"),
+                ] {
+                    if let Ok(results) = bpf.get_kprobe_functions(function) {
+                        if !results.is_empty() {
+                            bcc::Kprobe::new()
+                                .handler("trace_fs_entry")
+                                .function(function)
+                                .attach(&mut bpf)?;
+                            bcc::Kretprobe::new()
+                                .handler(handler)
+                                .function(function)
+                                .attach(&mut bpf)?;
+                        }
+                    }
+                }
+
+                if !names.is_empty() {
+                    let mut table = bpf.table("names")?;
+                    for name in names {
+                        table.set(&mut comm(name), &mut [1])?;
+                    }
+                }
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Some(counters) = bpf.counters("counters") {
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    if let Some(index) = statistic.index() {
+                        let value = counters.get(&index).copied().unwrap_or(0);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+        }
+
+        // latency histograms are transferred once per window
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
+            if let Some(ref bpf) = self.bpf {
+                let bpf = bpf.lock().unwrap();
+                let time = Instant::now();
+                for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
+                            if count > 0 {
+                                self.common().record_bucket(
+                                    statistic,
+                                    time,
+                                    value * crate::MICROSECOND,
+                                    count,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            *self.bpf_last.lock().unwrap() = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of a process as the kernel stores it, truncated to fit in
+/// `TASK_COMM_LEN` bytes including the terminating null
+#[allow(dead_code)]
+fn comm(name: &str) -> [u8; 16] {
+    let mut comm = [0; 16];
+    for (i, byte) in name.bytes().take(15).enumerate() {
+        comm[i] = byte;
+    }
+    comm
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_comm() {
+        assert_eq!(&comm("mysqld")[..7], b"mysqld\0");
+        let long = comm("postgres: checkpointer");
+        assert_eq!(&long[..15], b"postgres: check");
+        assert_eq!(long[15], 0);
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum FsyncStatistic {
+    #[strum(serialize = "fsync/btrfs/latency")]
+    BtrfsLatency,
+    #[strum(serialize = "fsync/ext4/latency")]
+    Ext4Latency,
+    #[strum(serialize = "fsync/latency")]
+    Latency,
+    #[strum(serialize = "fsync/total")]
+    Total,
+    #[strum(serialize = "fsync/xfs/latency")]
+    XfsLatency,
+}
+
+impl FsyncStatistic {
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::Total => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::BtrfsLatency => Some("latency_btrfs"),
+            Self::Ext4Latency => Some("latency_ext4"),
+            Self::Latency => Some("latency"),
+            Self::XfsLatency => Some("latency_xfs"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for FsyncStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.bpf_table().is_some() {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for FsyncStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        FsyncStatistic::from_str(s)
+    }
+}
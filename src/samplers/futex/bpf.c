@@ -0,0 +1,119 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <linux/errno.h>
+#include <uapi/linux/futex.h>
+
+// events, which index the counters array
+#define WAIT_TOTAL 0
+#define WAIT_TIMEOUT 1
+#define WAKE_TOTAL 2
+#define WAKE_WOKEN 3
+
+// the kind of futex operation in progress on a thread
+#define OP_WAIT 1
+#define OP_WAKE 2
+
+struct entry_t {
+    u64 ts;
+    u32 op;
+};
+
+BPF_HASH(start, u64, struct entry_t);
+BPF_ARRAY(counters, u64, 4);
+BPF_HISTOGRAM(latency, int, 461);
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+int trace_futex_enter(struct tracepoint__syscalls__sys_enter_futex *args)
+{
+    struct entry_t entry = {};
+    switch (args->op & FUTEX_CMD_MASK) {
+    case FUTEX_WAIT:
+    case FUTEX_WAIT_BITSET:
+    case FUTEX_LOCK_PI:
+        entry.op = OP_WAIT;
+        break;
+    case FUTEX_WAKE:
+    case FUTEX_WAKE_BITSET:
+    case FUTEX_WAKE_OP:
+    case FUTEX_UNLOCK_PI:
+        entry.op = OP_WAKE;
+        break;
+    default:
+        return 0;
+    }
+    entry.ts = bpf_ktime_get_ns();
+
+    u64 id = bpf_get_current_pid_tgid();
+    start.update(&id, &entry);
+    return 0;
+}
+
+int trace_futex_exit(struct tracepoint__syscalls__sys_exit_futex *args)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    struct entry_t *entry = start.lookup(&id);
+    if (entry == 0) {
+        return 0;   // missed entry, or not a wait or wake
+    }
+
+    long ret = args->ret;
+    if (entry->op == OP_WAIT) {
+        add(WAIT_TOTAL, 1);
+        if (ret == -ETIMEDOUT) {
+            add(WAIT_TIMEOUT, 1);
+        }
+        u64 delta_us = (bpf_ktime_get_ns() - entry->ts) / 1000ul;
+        latency.increment(value_to_index2(delta_us));
+    } else {
+        add(WAKE_TOTAL, 1);
+        // a wake returns the number of waiters which were woken
+        if (ret > 0) {
+            add(WAKE_WOKEN, ret);
+        }
+    }
+
+    start.delete(&id);
+    return 0;
+}
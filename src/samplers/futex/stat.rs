@@ -0,0 +1,80 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum FutexStatistic {
+    #[strum(serialize = "futex/wait/latency")]
+    WaitLatency,
+    #[strum(serialize = "futex/wait/timeout")]
+    WaitTimeout,
+    #[strum(serialize = "futex/wait/total")]
+    WaitTotal,
+    #[strum(serialize = "futex/wake/total")]
+    WakeTotal,
+    #[strum(serialize = "futex/wake/woken")]
+    WakeWoken,
+}
+
+impl FutexStatistic {
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::WaitTotal => Some(0),
+            Self::WaitTimeout => Some(1),
+            Self::WakeTotal => Some(2),
+            Self::WakeWoken => Some(3),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::WaitLatency => Some("latency"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for FutexStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.bpf_table().is_some() {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for FutexStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        FutexStatistic::from_str(s)
+    }
+}
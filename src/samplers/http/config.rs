@@ -4,7 +4,7 @@
 
 use serde_derive::Deserialize;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -16,8 +16,12 @@ pub struct HttpConfig {
     enabled: bool,
     gauges: Vec<String>,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
     #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default)]
     passthrough: bool,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
@@ -30,7 +34,9 @@ impl Default for HttpConfig {
             counters: Vec::new(),
             enabled: Default::default(),
             gauges: Vec::new(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             passthrough: Default::default(),
             percentiles: crate::common::default_percentiles(),
             url: None,
@@ -70,10 +76,18 @@ impl SamplerConfig for HttpConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
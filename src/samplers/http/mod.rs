@@ -49,7 +49,7 @@ impl Sampler for Http {
         if common.config().samplers().http().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -125,10 +125,7 @@ impl Sampler for Http {
                                 }
                                 match statistic.source() {
                                     Source::Counter => {
-                                        let _ = self
-                                            .common()
-                                            .metrics()
-                                            .record_counter(statistic, time, value);
+                                        self.common().record_counter(statistic, time, value);
                                     }
                                     Source::Gauge => {
                                         let _ = self
@@ -0,0 +1,225 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_HUGEPAGES: &str = "/sys/kernel/mm/hugepages";
+const SYS_KHUGEPAGED: &str = "/sys/kernel/mm/transparent_hugepage/khugepaged";
+const PROC_VMSTAT: &str = "/proc/vmstat";
+
+pub struct Hugepages {
+    common: Common,
+    /// statistics which have been registered, as hugepage sizes are
+    /// discovered at runtime
+    registered: HashSet<HugepagesStatistic>,
+    statistics: Vec<HugepagesConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Hugepages {
+    type Statistic = HugepagesStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().hugepages().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().hugepages().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize hugepages sampler {}", e);
+                    } else {
+                        error!("failed to initialize hugepages sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().hugepages()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self
+            .common
+            .config()
+            .samplers()
+            .hugepages()
+            .statistics
+            .clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_pools().await;
+        self.map_result(r)?;
+
+        let r = self.sample_thp().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Hugepages {
+    fn record(&mut self, statistic: HugepagesStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples the static hugepage pool of each hugepage size, which are the
+    /// `hugepages-<size>` directories in sysfs
+    async fn sample_pools(&mut self) -> Result<(), std::io::Error> {
+        let mut entries = tokio::fs::read_dir(SYS_HUGEPAGES).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let size = match entry.file_name().into_string() {
+                Ok(name) => match name.strip_prefix("hugepages-") {
+                    Some(size) => size.to_string(),
+                    None => continue,
+                },
+                Err(_) => continue,
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if !statistic.pool() {
+                    continue;
+                }
+                let path = entry.path().join(statistic.sysfs().unwrap());
+                if let Some(value) = read_value(&path).await {
+                    self.record(HugepagesStatistic::pool(&size, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the transparent hugepage counters from `/proc/vmstat` and the
+    /// activity of khugepaged from sysfs
+    async fn sample_thp(&mut self) -> Result<(), std::io::Error> {
+        let vmstat = parse_vmstat(&tokio::fs::read_to_string(PROC_VMSTAT).await?);
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            if statistic.pool() {
+                continue;
+            }
+            let value = if let Some(name) = statistic.vmstat() {
+                vmstat.get(name).copied()
+            } else {
+                let path = format!("{}/{}", SYS_KHUGEPAGED, statistic.sysfs().unwrap());
+                read_value(&path).await
+            };
+            // counters which are missing on this kernel are skipped
+            if let Some(value) = value {
+                self.record(HugepagesStatistic::host(statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read_value<P: AsRef<std::path::Path>>(path: P) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn parse_vmstat(content: &str) -> HashMap<&str, u64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(' ')?;
+            Some((name, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_vmstat() {
+        let vmstat = parse_vmstat(
+            "nr_free_pages 1234\n\
+             thp_fault_alloc 42\n\
+             thp_fault_fallback 7\n\
+             thp_split_pmd 3\n",
+        );
+        assert_eq!(vmstat.get("thp_fault_alloc"), Some(&42));
+        assert_eq!(vmstat.get("thp_fault_fallback"), Some(&7));
+        assert_eq!(vmstat.get("thp_split_pmd"), Some(&3));
+        assert_eq!(vmstat.get("thp_file_alloc"), None);
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum HugepagesConfigStatistic {
+    #[strum(serialize = "free")]
+    Free,
+    #[strum(serialize = "reserved")]
+    Reserved,
+    #[strum(serialize = "surplus")]
+    Surplus,
+    #[strum(serialize = "total")]
+    Total,
+    #[strum(serialize = "khugepaged/full_scans")]
+    KhugepagedFullScans,
+    #[strum(serialize = "khugepaged/pages_collapsed")]
+    KhugepagedPagesCollapsed,
+    #[strum(serialize = "thp/collapse/alloc")]
+    ThpCollapseAlloc,
+    #[strum(serialize = "thp/collapse/failed")]
+    ThpCollapseFailed,
+    #[strum(serialize = "thp/fault/alloc")]
+    ThpFaultAlloc,
+    #[strum(serialize = "thp/fault/fallback")]
+    ThpFaultFallback,
+    #[strum(serialize = "thp/file/alloc")]
+    ThpFileAlloc,
+    #[strum(serialize = "thp/file/fallback")]
+    ThpFileFallback,
+    #[strum(serialize = "thp/split/deferred")]
+    ThpSplitDeferred,
+    #[strum(serialize = "thp/split/failed")]
+    ThpSplitFailed,
+    #[strum(serialize = "thp/split/page")]
+    ThpSplitPage,
+    #[strum(serialize = "thp/split/pmd")]
+    ThpSplitPmd,
+}
+
+impl HugepagesConfigStatistic {
+    /// Whether this statistic is reported for each hugepage size, rather than
+    /// for the host
+    pub fn pool(self) -> bool {
+        matches!(
+            self,
+            Self::Free | Self::Reserved | Self::Surplus | Self::Total
+        )
+    }
+
+    /// The file in the sysfs directory of a hugepage size for pool
+    /// statistics, or in the khugepaged directory for khugepaged statistics
+    pub fn sysfs(self) -> Option<&'static str> {
+        match self {
+            Self::Free => Some("free_hugepages"),
+            Self::Reserved => Some("resv_hugepages"),
+            Self::Surplus => Some("surplus_hugepages"),
+            Self::Total => Some("nr_hugepages"),
+            Self::KhugepagedFullScans => Some("full_scans"),
+            Self::KhugepagedPagesCollapsed => Some("pages_collapsed"),
+            _ => None,
+        }
+    }
+
+    /// The name of the counter in `/proc/vmstat`
+    pub fn vmstat(self) -> Option<&'static str> {
+        match self {
+            Self::ThpCollapseAlloc => Some("thp_collapse_alloc"),
+            Self::ThpCollapseFailed => Some("thp_collapse_alloc_failed"),
+            Self::ThpFaultAlloc => Some("thp_fault_alloc"),
+            Self::ThpFaultFallback => Some("thp_fault_fallback"),
+            Self::ThpFileAlloc => Some("thp_file_alloc"),
+            Self::ThpFileFallback => Some("thp_file_fallback"),
+            Self::ThpSplitDeferred => Some("thp_deferred_split_page"),
+            Self::ThpSplitFailed => Some("thp_split_page_failed"),
+            Self::ThpSplitPage => Some("thp_split_page"),
+            Self::ThpSplitPmd => Some("thp_split_pmd"),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        if self.pool() {
+            Source::Gauge
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for HugepagesConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        HugepagesConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HugepagesStatistic {
+    name: String,
+    statistic: HugepagesConfigStatistic,
+}
+
+impl HugepagesStatistic {
+    /// A host-wide statistic, which is named `hugepages/thp/...` or
+    /// `hugepages/khugepaged/...`
+    pub fn host(statistic: HugepagesConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("hugepages/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of the pool of a hugepage size, which is named
+    /// `hugepages/<size>/...`, such as `hugepages/2048kB/free`
+    pub fn pool(size: &str, statistic: HugepagesConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("hugepages/{}/{}", size, suffix),
+                format!("hugepages/{}", suffix),
+                &[("size", size)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> HugepagesConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for HugepagesStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::perf::{self, Counter};
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const EVENT_SOURCE_DEVICES: &str = "/sys/bus/event_source/devices";
+const SYS_DRM: &str = "/sys/class/drm";
+
+pub struct IntelGpu {
+    common: Common,
+    /// busy time counters of each engine, by GPU id and engine name, which
+    /// are opened on the first sample
+    counters: Option<Vec<(u64, String, Counter)>>,
+    /// statistics which have been registered, as gpus are discovered at runtime
+    registered: HashSet<IntelGpuStatistic>,
+    statistics: Vec<IntelGpuConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for IntelGpu {
+    type Statistic = IntelGpuStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().intel_gpu().statistics.clone();
+        Ok(Self {
+            common,
+            counters: None,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().intel_gpu().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize intel_gpu sampler {}", e);
+                    } else {
+                        error!("failed to initialize intel_gpu sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().intel_gpu()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self
+            .common
+            .config()
+            .samplers()
+            .intel_gpu()
+            .statistics
+            .clone();
+        // open counters and register the new set of statistics on the next
+        // sample
+        self.counters = None;
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_gpus().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl IntelGpu {
+    fn record(&mut self, statistic: IntelGpuStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples each GPU driven by `i915` or `xe`, which are the `card<N>`
+    /// devices of the DRM subsystem
+    async fn sample_gpus(&mut self) -> Result<(), std::io::Error> {
+        let gpus = discover_gpus().await?;
+        let memory = if self
+            .statistics
+            .contains(&IntelGpuConfigStatistic::MemoryUsed)
+        {
+            drm_memory().await
+        } else {
+            HashMap::new()
+        };
+
+        let time = Instant::now();
+        for gpu in &gpus {
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    IntelGpuConfigStatistic::FrequencyActual => {
+                        read_value(gpu.frequency("act")).await
+                    }
+                    IntelGpuConfigStatistic::FrequencyRequested => {
+                        read_value(gpu.frequency("cur")).await
+                    }
+                    IntelGpuConfigStatistic::MemoryUsed => memory.get(&gpu.pci).copied(),
+                    // engines are sampled from their perf counters below
+                    IntelGpuConfigStatistic::EngineBusy => None,
+                };
+                if let Some(value) = value {
+                    self.record(IntelGpuStatistic::new(gpu.id, statistic), time, value);
+                }
+            }
+        }
+
+        if self
+            .statistics
+            .contains(&IntelGpuConfigStatistic::EngineBusy)
+        {
+            if self.counters.is_none() {
+                self.counters = Some(open_engines(&gpus));
+            }
+            let mut readings = Vec::new();
+            for (id, engine, counter) in self.counters.as_mut().unwrap().iter_mut() {
+                readings.push((*id, engine.clone(), counter.read()?));
+            }
+            for (id, engine, value) in readings {
+                let statistic =
+                    IntelGpuStatistic::engine(id, &engine, IntelGpuConfigStatistic::EngineBusy);
+                self.record(statistic, time, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Gpu {
+    id: u64,
+    /// the `card<N>` directory of the GPU
+    card: PathBuf,
+    driver: String,
+    /// the PCI address of the GPU, such as `0000:00:02.0`
+    pci: String,
+}
+
+impl Gpu {
+    /// The path of a GPU frequency, in MHz, such as the actual (`act`) or
+    /// requested (`cur`) frequency. `i915` exposes the frequencies of the
+    /// primary GT in the card directory, while `xe` exposes them for each
+    /// tile and GT.
+    fn frequency(&self, name: &str) -> PathBuf {
+        if self.driver == "xe" {
+            self.card
+                .join(format!("device/tile0/gt0/freq0/{}_freq", name))
+        } else {
+            self.card.join(format!("gt_{}_freq_mhz", name))
+        }
+    }
+}
+
+async fn discover_gpus() -> Result<Vec<Gpu>, std::io::Error> {
+    let mut gpus = Vec::new();
+    let mut entries = tokio::fs::read_dir(SYS_DRM).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let id: u64 = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("card"))
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => continue,
+        };
+        let card = entry.path();
+        let driver = match tokio::fs::read_link(card.join("device/driver")).await {
+            Ok(driver) => match driver.file_name().and_then(|d| d.to_str()) {
+                Some(driver) if driver == "i915" || driver == "xe" => driver.to_string(),
+                _ => continue,
+            },
+            Err(_) => continue,
+        };
+        let pci = match tokio::fs::read_link(card.join("device")).await {
+            Ok(device) => match device.file_name().and_then(|d| d.to_str()) {
+                Some(pci) => pci.to_string(),
+                None => continue,
+            },
+            Err(_) => continue,
+        };
+        gpus.push(Gpu {
+            id,
+            card,
+            driver,
+            pci,
+        });
+    }
+    Ok(gpus)
+}
+
+/// Opens the busy time counters, in nanoseconds, of each engine of the GPUs
+/// driven by `i915`, such as `rcs0-busy` for the render engine or `vcs0-busy`
+/// for the first video decode engine. The PMU of an integrated GPU is named
+/// `i915` and those of discrete GPUs include their PCI address. Engine busy
+/// time is not yet available for `xe`.
+fn open_engines(gpus: &[Gpu]) -> Vec<(u64, String, Counter)> {
+    let mut counters = Vec::new();
+    for gpu in gpus.iter().filter(|gpu| gpu.driver == "i915") {
+        let discrete =
+            Path::new(EVENT_SOURCE_DEVICES).join(format!("i915_{}", gpu.pci.replace(':', "_")));
+        let pmu = if discrete.exists() {
+            discrete
+        } else {
+            Path::new(EVENT_SOURCE_DEVICES).join("i915")
+        };
+        let cpu = match perf::cpus(&pmu).ok().and_then(|cpus| cpus.first().copied()) {
+            Some(cpu) => cpu,
+            None => continue,
+        };
+        let entries = match std::fs::read_dir(pmu.join("events")) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let event = entry.file_name().to_string_lossy().to_string();
+            let engine = match event.strip_suffix("-busy") {
+                Some(engine) => engine.to_string(),
+                None => continue,
+            };
+            match Counter::open(&pmu, &event, cpu) {
+                Ok(counter) => counters.push((gpu.id, engine, counter)),
+                Err(e) => debug!("failed to open {} of {}: {}", event, pmu.display(), e),
+            }
+        }
+    }
+    counters
+}
+
+/// Sums the memory resident for the DRM clients of each GPU, by its PCI
+/// address. Memory is only reported per client, in the `fdinfo` of each open
+/// DRM file, which may be shared by several file descriptors or processes.
+async fn drm_memory() -> HashMap<String, u64> {
+    let mut clients = HashMap::new();
+    let mut processes = match tokio::fs::read_dir("/proc").await {
+        Ok(processes) => processes,
+        Err(_) => return HashMap::new(),
+    };
+    while let Ok(Some(process)) = processes.next_entry().await {
+        if process
+            .file_name()
+            .to_str()
+            .map(|p| p.parse::<u32>().is_ok())
+            != Some(true)
+        {
+            continue;
+        }
+        let mut fds = match tokio::fs::read_dir(process.path().join("fd")).await {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        while let Ok(Some(fd)) = fds.next_entry().await {
+            // only DRM files have the fdinfo which is parsed
+            match tokio::fs::read_link(fd.path()).await {
+                Ok(target) if target.starts_with("/dev/dri") => {}
+                _ => continue,
+            }
+            let path = process.path().join("fdinfo").join(fd.file_name());
+            if let Ok(content) = tokio::fs::read_to_string(path).await {
+                if let Some((pdev, client, resident)) = parse_fdinfo(&content) {
+                    clients.insert((pdev, client), resident);
+                }
+            }
+        }
+    }
+    let mut memory = HashMap::new();
+    for ((pdev, _), resident) in clients {
+        *memory.entry(pdev).or_insert(0) += resident;
+    }
+    memory
+}
+
+/// Parses the `fdinfo` of a DRM file of an Intel GPU, returning the PCI
+/// address of the GPU, the id of the client, and the bytes resident in all
+/// memory regions.
+fn parse_fdinfo(content: &str) -> Option<(String, u64, u64)> {
+    let mut driver = None;
+    let mut pdev = None;
+    let mut client = None;
+    let mut resident = 0;
+    for line in content.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "drm-driver" => driver = Some(value),
+            "drm-pdev" => pdev = Some(value.to_string()),
+            "drm-client-id" => client = value.parse().ok(),
+            _ if key.starts_with("drm-resident-") => {
+                let mut parts = value.split_whitespace();
+                let amount: u64 = match parts.next().and_then(|v| v.parse().ok()) {
+                    Some(amount) => amount,
+                    None => continue,
+                };
+                let multiplier = match parts.next() {
+                    Some("KiB") => 1024,
+                    Some("MiB") => 1024 * 1024,
+                    Some("GiB") => 1024 * 1024 * 1024,
+                    _ => 1,
+                };
+                resident += amount * multiplier;
+            }
+            _ => {}
+        }
+    }
+    match driver {
+        Some("i915") | Some("xe") => Some((pdev?, client?, resident)),
+        _ => None,
+    }
+}
+
+async fn read_value<P: AsRef<Path>>(path: P) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_fdinfo() {
+        let content = "pos:\t0\n\
+                       flags:\t02100002\n\
+                       drm-driver:\ti915\n\
+                       drm-client-id:\t7\n\
+                       drm-pdev:\t0000:00:02.0\n\
+                       drm-total-system0:\t12 MiB\n\
+                       drm-resident-system0:\t12 MiB\n\
+                       drm-resident-local0:\t512 KiB\n\
+                       drm-engine-render:\t1255744 ns\n";
+        assert_eq!(
+            parse_fdinfo(content),
+            Some(("0000:00:02.0".to_string(), 7, (12 << 20) + (512 << 10)))
+        );
+        assert_eq!(
+            parse_fdinfo("drm-driver:\tamdgpu\ndrm-client-id:\t1\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum IntelGpuConfigStatistic {
+    #[strum(serialize = "engine/busy")]
+    EngineBusy,
+    #[strum(serialize = "frequency/actual")]
+    FrequencyActual,
+    #[strum(serialize = "frequency/requested")]
+    FrequencyRequested,
+    #[strum(serialize = "memory/used")]
+    MemoryUsed,
+}
+
+impl IntelGpuConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::EngineBusy => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for IntelGpuConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        IntelGpuConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct IntelGpuStatistic {
+    name: String,
+    statistic: IntelGpuConfigStatistic,
+}
+
+impl IntelGpuStatistic {
+    /// A statistic of a GPU, which is named `intel_gpu/gpu_<id>/...`
+    pub fn new(id: u64, statistic: IntelGpuConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("intel_gpu/gpu_{}/{}", id, suffix),
+                format!("intel_gpu/{}", suffix),
+                &[("gpu", &id.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    /// A statistic of an engine of a GPU, such as `rcs0`, which is named
+    /// `intel_gpu/gpu_<id>/engine/<engine>/...`
+    pub fn engine(id: u64, engine: &str, statistic: IntelGpuConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!(
+                    "intel_gpu/gpu_{}/engine/{}/{}",
+                    id,
+                    engine,
+                    suffix.trim_start_matches("engine/")
+                ),
+                format!("intel_gpu/{}", suffix),
+                &[("gpu", &id.to_string()), ("engine", engine)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> IntelGpuConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for IntelGpuStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// CO-RE version of bpf.c, built with the bpf_core feature
+// Based on: https://github.com/iovisor/bcc/blob/master/libbpf-tools/hardirqs.bpf.c
+//           https://github.com/iovisor/bcc/blob/master/libbpf-tools/softirqs.bpf.c
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+#include "histogram.h"
+
+typedef struct account_val {
+    u64 ts;
+    u32 vec;
+} account_val_t;
+
+// Software IRQ
+HASH(soft_start, u32, account_val_t);
+HISTOGRAM(hi);
+HISTOGRAM(timer);
+HISTOGRAM(net_tx);
+HISTOGRAM(net_rx);
+HISTOGRAM(block);
+HISTOGRAM(irq_poll);
+HISTOGRAM(tasklet);
+HISTOGRAM(sched);
+HISTOGRAM(hr_timer);
+HISTOGRAM(rcu);
+HISTOGRAM(unknown);
+
+// Hardware IRQ
+HASH(hard_start, u32, u64);
+HISTOGRAM(hardirq_total);
+
+SEC("tracepoint/irq/softirq_entry")
+int softirq_entry(struct trace_event_raw_softirq *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    account_val_t val = {};
+    val.ts = bpf_ktime_get_ns();
+    val.vec = args->vec;
+    bpf_map_update_elem(&soft_start, &pid, &val, BPF_ANY);
+    return 0;
+}
+
+SEC("tracepoint/irq/softirq_exit")
+int softirq_exit(struct trace_event_raw_softirq *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+
+    // missed start
+    account_val_t *valp = bpf_map_lookup_elem(&soft_start, &pid);
+    if (!valp) {
+        return 0;
+    }
+    u64 delta_us = (bpf_ktime_get_ns() - valp->ts) / 1000;
+
+    // May need updates if more softirqs are added
+    void *histogram;
+    switch (valp->vec) {
+        case 0: histogram = &hi; break;
+        case 1: histogram = &timer; break;
+        case 2: histogram = &net_tx; break;
+        case 3: histogram = &net_rx; break;
+        case 4: histogram = &block; break;
+        case 5: histogram = &irq_poll; break;
+        case 6: histogram = &tasklet; break;
+        case 7: histogram = &sched; break;
+        case 8: histogram = &hr_timer; break;
+        case 9: histogram = &rcu; break;
+        default: histogram = &unknown; break;
+    }
+    histogram_add(histogram, delta_us);
+
+    bpf_map_delete_elem(&soft_start, &pid);
+    return 0;
+}
+
+SEC("kprobe/handle_irq_event_percpu")
+int BPF_KPROBE(hardirq_entry)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    bpf_map_update_elem(&hard_start, &pid, &ts, BPF_ANY);
+    return 0;
+}
+
+SEC("kretprobe/handle_irq_event_percpu")
+int BPF_KRETPROBE(hardirq_exit)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+
+    // missed start
+    u64 *tsp = bpf_map_lookup_elem(&hard_start, &pid);
+    if (!tsp) {
+        return 0;
+    }
+
+    histogram_add(&hardirq_total, (bpf_ktime_get_ns() - *tsp) / 1000);
+
+    bpf_map_delete_elem(&hard_start, &pid);
+    return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -17,10 +17,17 @@ pub struct InterruptConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<InterruptStatistic>,
 }
 
@@ -29,7 +36,9 @@ impl Default for InterruptConfig {
         Self {
             bpf: Default::default(),
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             statistics: default_statistics(),
         }
@@ -51,10 +60,18 @@ impl SamplerConfig for InterruptConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
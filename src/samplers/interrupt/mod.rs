@@ -66,7 +66,7 @@ impl Sampler for Interrupt {
         if common.config().samplers().interrupt().enabled() {
             if let Ok(mut interrupt) = Interrupt::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !interrupt.retired() {
                         let _ = interrupt.sample().await;
                     }
                 });
@@ -90,6 +90,13 @@ impl Sampler for Interrupt {
         self.common.config().samplers().interrupt()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -103,15 +110,18 @@ impl Sampler for Interrupt {
 
         self.sample_interrupt().await?;
 
-        #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
 }
 
 impl Interrupt {
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn bpf_enabled(&self) -> bool {
         if self.sampler_config().bpf() {
             for statistic in &self.statistics {
@@ -155,6 +165,16 @@ impl Interrupt {
             }
         }
 
+        #[cfg(feature = "bpf_core")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let object = include_bytes!(concat!(env!("OUT_DIR"), "/interrupt.bpf.o"));
+                let bpf = BPF::load("interrupt", object, &[])?;
+                self.bpf = Some(Arc::new(Mutex::new(bpf)));
+            }
+        }
+
         Ok(())
     }
 
@@ -296,26 +316,24 @@ impl Interrupt {
         let time = Instant::now();
         for stat in &self.statistics {
             if let Some(value) = result.get(stat) {
-                let _ = self.metrics().record_counter(stat, time, *value);
+                self.common().record_counter(stat, time, *value);
             }
         }
 
         Ok(())
     }
 
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn sample_bpf(&self) -> Result<(), std::io::Error> {
-        if self.bpf_last.lock().unwrap().elapsed()
-            >= Duration::new(self.general_config().window() as u64, 0)
-        {
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
             if let Some(ref bpf) = self.bpf {
                 let bpf = bpf.lock().unwrap();
                 let time = Instant::now();
                 for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
-                    if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
-                        for (&value, &count) in &map_from_table(&mut table) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
                             if count > 0 {
-                                let _ = self.metrics().record_bucket(
+                                self.common().record_bucket(
                                     statistic,
                                     time,
                                     value * crate::MICROSECOND,
@@ -0,0 +1,142 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+
+// identifies an in-flight request by its ring and the user supplied data
+typedef struct request_key {
+    u64 ctx;
+    u64 user_data;
+} request_key_t;
+
+BPF_HASH(enter_start, u32);
+BPF_HASH(request_start, request_key_t);
+
+BPF_ARRAY(enter, u64, 1);
+BPF_ARRAY(submit, u64, 1);
+BPF_ARRAY(sqpoll_submit, u64, 1);
+BPF_ARRAY(async_work, u64, 1);
+BPF_ARRAY(complete, u64, 1);
+BPF_ARRAY(complete_error, u64, 1);
+
+// value_to_index2() gives us from 0-460 as the index
+BPF_HISTOGRAM(enter_latency, int, 461);
+BPF_HISTOGRAM(request_latency, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+static void increment_counter(u64 *count)
+{
+    if (count) lock_xadd(count, 1);
+}
+
+int trace_enter(struct tracepoint__syscalls__sys_enter_io_uring_enter *args)
+{
+    int zero = 0;
+    increment_counter(enter.lookup(&zero));
+
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    enter_start.update(&pid, &ts);
+    return 0;
+}
+
+int trace_enter_return(struct tracepoint__syscalls__sys_exit_io_uring_enter *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = enter_start.lookup(&pid);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    enter_latency.increment(value_to_index2(delta));
+    enter_start.delete(&pid);
+    return 0;
+}
+
+// the submission tracepoint was renamed from io_uring_submit_sqe to
+// io_uring_submit_req in Linux 5.19, SUBMIT_TRACEPOINT is replaced with the
+// one available on the running kernel
+int trace_submit(struct tracepoint__io_uring__SUBMIT_TRACEPOINT *args)
+{
+    int zero = 0;
+    increment_counter(submit.lookup(&zero));
+    if (args->sq_thread) {
+        increment_counter(sqpoll_submit.lookup(&zero));
+    }
+
+    request_key_t key = {};
+    key.ctx = (u64)args->ctx;
+    key.user_data = args->user_data;
+    u64 ts = bpf_ktime_get_ns();
+    request_start.update(&key, &ts);
+    return 0;
+}
+
+int trace_queue_async_work(struct tracepoint__io_uring__io_uring_queue_async_work *args)
+{
+    int zero = 0;
+    increment_counter(async_work.lookup(&zero));
+    return 0;
+}
+
+int trace_complete(struct tracepoint__io_uring__io_uring_complete *args)
+{
+    int zero = 0;
+    increment_counter(complete.lookup(&zero));
+    if (args->res < 0) {
+        increment_counter(complete_error.lookup(&zero));
+    }
+
+    request_key_t key = {};
+    key.ctx = (u64)args->ctx;
+    key.user_data = args->user_data;
+    u64 *tsp = request_start.lookup(&key);
+
+    // skip requests submitted before the probes were attached, or with
+    // duplicate user data
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    request_latency.increment(value_to_index2(delta));
+    request_start.delete(&key);
+    return 0;
+}
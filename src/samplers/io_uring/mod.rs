@@ -0,0 +1,225 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+#[cfg(feature = "bpf")]
+use rustcommon_metrics::*;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+#[allow(dead_code)]
+pub struct IoUring {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    statistics: Vec<IoUringStatistic>,
+}
+
+#[async_trait]
+impl Sampler for IoUring {
+    type Statistic = IoUringStatistic;
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().io_uring().statistics();
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().io_uring().enabled() {
+            if let Ok(mut sampler) = Self::new(common.clone()) {
+                common.runtime().spawn(async move {
+                    while !sampler.retired() {
+                        let _ = sampler.sample().await;
+                    }
+                });
+            } else if !common.config.fault_tolerant() {
+                fatal!("failed to initialize io_uring sampler");
+            } else {
+                error!("failed to initialize io_uring sampler");
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().io_uring()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        // sample bpf
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl IoUring {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        if self.sampler_config().bpf() {
+            for statistic in &self.statistics {
+                if statistic.bpf_table().is_some() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                // the submission tracepoint was renamed in Linux 5.19
+                let submit = if tracepoint_exists("io_uring", "io_uring_submit_req") {
+                    "io_uring_submit_req"
+                } else {
+                    "io_uring_submit_sqe"
+                };
+
+                // load the code and compile
+                let code = include_str!("bpf.c").replace("SUBMIT_TRACEPOINT", submit);
+                let mut bpf = bcc::BPF::new(&code)?;
+
+                bcc::Tracepoint::new()
+                    .handler("trace_enter")
+                    .subsystem("syscalls")
+                    .tracepoint("sys_enter_io_uring_enter")
+                    .attach(&mut bpf)?;
+                bcc::Tracepoint::new()
+                    .handler("trace_enter_return")
+                    .subsystem("syscalls")
+                    .tracepoint("sys_exit_io_uring_enter")
+                    .attach(&mut bpf)?;
+                bcc::Tracepoint::new()
+                    .handler("trace_submit")
+                    .subsystem("io_uring")
+                    .tracepoint(submit)
+                    .attach(&mut bpf)?;
+                bcc::Tracepoint::new()
+                    .handler("trace_queue_async_work")
+                    .subsystem("io_uring")
+                    .tracepoint("io_uring_queue_async_work")
+                    .attach(&mut bpf)?;
+                bcc::Tracepoint::new()
+                    .handler("trace_complete")
+                    .subsystem("io_uring")
+                    .tracepoint("io_uring_complete")
+                    .attach(&mut bpf)?;
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let time = Instant::now();
+
+            // counters are cumulative and read every interval
+            for statistic in self
+                .statistics
+                .iter()
+                .filter(|s| s.source() == Source::Counter)
+            {
+                if let Ok(table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
+                    if let Some(entry) = table.iter().next() {
+                        let value = parse_u64(entry.value);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+
+            // histograms are drained once per window
+            if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
+                for statistic in self
+                    .statistics
+                    .iter()
+                    .filter(|s| s.source() == Source::Distribution)
+                {
+                    if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &map_from_table(&mut table) {
+                            if count > 0 {
+                                self.common().record_bucket(
+                                    statistic,
+                                    time,
+                                    value * crate::MICROSECOND,
+                                    count,
+                                );
+                            }
+                        }
+                    }
+                }
+                *self.bpf_last.lock().unwrap() = Instant::now();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum IoUringStatistic {
+    #[strum(serialize = "io_uring/enter")]
+    Enter,
+    #[strum(serialize = "io_uring/enter/latency")]
+    EnterLatency,
+    #[strum(serialize = "io_uring/submit")]
+    Submit,
+    #[strum(serialize = "io_uring/sqpoll/submit")]
+    SqpollSubmit,
+    #[strum(serialize = "io_uring/async")]
+    Async,
+    #[strum(serialize = "io_uring/complete")]
+    Complete,
+    #[strum(serialize = "io_uring/complete/error")]
+    CompleteError,
+    #[strum(serialize = "io_uring/request/latency")]
+    RequestLatency,
+}
+
+impl IoUringStatistic {
+    #[allow(dead_code)]
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::Enter => Some("enter"),
+            Self::EnterLatency => Some("enter_latency"),
+            Self::Submit => Some("submit"),
+            Self::SqpollSubmit => Some("sqpoll_submit"),
+            Self::Async => Some("async_work"),
+            Self::Complete => Some("complete"),
+            Self::CompleteError => Some("complete_error"),
+            Self::RequestLatency => Some("request_latency"),
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for IoUringStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        match self {
+            Self::EnterLatency | Self::RequestLatency => Source::Distribution,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for IoUringStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        IoUringStatistic::from_str(s)
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::command_output;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Ipmi {
+    common: Common,
+    /// statistics which have been registered, as sensors are discovered at
+    /// runtime
+    registered: HashSet<IpmiStatistic>,
+    statistics: Vec<IpmiConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Ipmi {
+    type Statistic = IpmiStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().ipmi().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().ipmi().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize ipmi sampler {}", e);
+                    } else {
+                        error!("failed to initialize ipmi sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().ipmi()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().ipmi().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_sensors().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Ipmi {
+    fn record(&mut self, statistic: IpmiStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Reads the sensors of the local BMC with `ipmitool`, which uses the
+    /// `/dev/ipmi0` device of the kernel IPMI driver to read each sensor data
+    /// record
+    async fn sample_sensors(&mut self) -> Result<(), std::io::Error> {
+        let output = command_output(
+            tokio::process::Command::new("ipmitool").args(&["-I", "open", "-c", "sdr", "list"]),
+        )
+        .await?;
+        if !output.status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("ipmitool sdr list failed: {}", output.status),
+            ));
+        }
+        let time = Instant::now();
+        for (sensor, statistic, value) in parse_sdr(&String::from_utf8_lossy(&output.stdout)) {
+            if self.statistics.contains(&statistic) {
+                self.record(IpmiStatistic::new(&sensor, statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the CSV output of `ipmitool sdr list`, which has the name, reading,
+/// unit, and status of each sensor. Returns the readings of the sensors which
+/// are reported, with voltages in millivolts and power supply status as `1`
+/// when ok and `0` otherwise. Sensors without a reading have a status of `ns`
+/// and are skipped.
+fn parse_sdr(content: &str) -> Vec<(String, IpmiConfigStatistic, u64)> {
+    let mut readings = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() < 4 || fields[3] == "ns" {
+            continue;
+        }
+        let statistic = match IpmiConfigStatistic::from_sensor(fields[0], fields[2]) {
+            Some(statistic) => statistic,
+            None => continue,
+        };
+        let value = if statistic == IpmiConfigStatistic::PowerSupply {
+            if fields[3] == "ok" {
+                1
+            } else {
+                0
+            }
+        } else {
+            let reading: f64 = match fields[1].parse() {
+                Ok(reading) if reading >= 0.0 => reading,
+                _ => continue,
+            };
+            if statistic == IpmiConfigStatistic::Voltage {
+                (reading * 1000.0).round() as u64
+            } else {
+                reading.round() as u64
+            }
+        };
+        readings.push((sensor_name(fields[0]), statistic, value));
+    }
+    readings
+}
+
+/// Converts a sensor name, such as `Inlet Temp`, into the form used in the
+/// statistic name, such as `inlet_temp`
+fn sensor_name(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_sdr() {
+        let readings = parse_sdr(
+            "Fan1A,5880,RPM,ok\n\
+             Fan1B,,RPM,ns\n\
+             Inlet Temp,23,degrees C,ok\n\
+             12V,12.10,Volts,ok\n\
+             Pwr Consumption,168,Watts,ok\n\
+             PS1 Status,0x01,discrete,ok\n\
+             PS2 Status,0x0b,discrete,cr\n\
+             Intrusion,0x00,discrete,ok\n",
+        );
+        assert_eq!(
+            readings,
+            vec![
+                ("fan1a".to_string(), IpmiConfigStatistic::Fan, 5880),
+                (
+                    "inlet_temp".to_string(),
+                    IpmiConfigStatistic::Temperature,
+                    23
+                ),
+                ("12v".to_string(), IpmiConfigStatistic::Voltage, 12100),
+                (
+                    "ps1_status".to_string(),
+                    IpmiConfigStatistic::PowerSupply,
+                    1
+                ),
+                (
+                    "ps2_status".to_string(),
+                    IpmiConfigStatistic::PowerSupply,
+                    0
+                ),
+            ]
+        );
+    }
+}
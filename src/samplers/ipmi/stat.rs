@@ -0,0 +1,103 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum IpmiConfigStatistic {
+    #[strum(serialize = "fan")]
+    Fan,
+    #[strum(serialize = "power_supply")]
+    PowerSupply,
+    #[strum(serialize = "temperature")]
+    Temperature,
+    #[strum(serialize = "voltage")]
+    Voltage,
+}
+
+impl IpmiConfigStatistic {
+    /// The statistic which a sensor is reported as, based on the unit of its
+    /// reading. Power supply status sensors have discrete readings, and are
+    /// recognized by their name.
+    pub fn from_sensor(name: &str, unit: &str) -> Option<Self> {
+        match unit {
+            "RPM" => Some(Self::Fan),
+            "degrees C" => Some(Self::Temperature),
+            "Volts" => Some(Self::Voltage),
+            "discrete" => {
+                let name = name.to_lowercase();
+                if name.starts_with("ps") || name.contains("power supply") {
+                    Some(Self::PowerSupply)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for IpmiConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        IpmiConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct IpmiStatistic {
+    name: String,
+    statistic: IpmiConfigStatistic,
+}
+
+impl IpmiStatistic {
+    /// A statistic of a sensor, which is named `ipmi/<statistic>/<sensor>`
+    pub fn new(sensor: &str, statistic: IpmiConfigStatistic) -> Self {
+        let prefix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("ipmi/{}/{}", prefix, sensor),
+                format!("ipmi/{}", prefix),
+                &[("sensor", sensor)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> IpmiConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for IpmiStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Gauge
+    }
+}
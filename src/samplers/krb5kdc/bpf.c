@@ -339,3 +339,93 @@ int count_process_tgs_req(struct pt_regs *ctx) {
 
   return 0;
 }
+
+// Section for latency of the ticket processing functions. Each krb5kdc worker
+// is single threaded, so requests are matched to their return by thread id.
+
+BPF_HASH(start_process_as_req, u32, u64);
+BPF_HASH(start_process_tgs_req, u32, u64);
+
+// value_to_index2() gives us from 0-460 as the index
+BPF_HISTOGRAM(process_as_req_latency, int, 461);
+BPF_HISTOGRAM(process_tgs_req_latency, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+int trace_process_as_req_entry(struct pt_regs *ctx) {
+  u32 pid = bpf_get_current_pid_tgid();
+  u64 ts = bpf_ktime_get_ns();
+  start_process_as_req.update(&pid, &ts);
+  return 0;
+}
+
+int trace_process_as_req_return(struct pt_regs *ctx) {
+  u32 pid = bpf_get_current_pid_tgid();
+  u64 *tsp = start_process_as_req.lookup(&pid);
+
+  // skip events with unknown start
+  if (tsp == 0) {
+    return 0;
+  }
+
+  u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+  process_as_req_latency.increment(value_to_index2(delta));
+  start_process_as_req.delete(&pid);
+
+  return 0;
+}
+
+int trace_process_tgs_req_entry(struct pt_regs *ctx) {
+  u32 pid = bpf_get_current_pid_tgid();
+  u64 ts = bpf_ktime_get_ns();
+  start_process_tgs_req.update(&pid, &ts);
+  return 0;
+}
+
+int trace_process_tgs_req_return(struct pt_regs *ctx) {
+  u32 pid = bpf_get_current_pid_tgid();
+  u64 *tsp = start_process_tgs_req.lookup(&pid);
+
+  // skip events with unknown start
+  if (tsp == 0) {
+    return 0;
+  }
+
+  u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+  process_tgs_req_latency.increment(value_to_index2(delta));
+  start_process_tgs_req.delete(&pid);
+
+  return 0;
+}
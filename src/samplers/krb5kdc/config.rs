@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -17,13 +17,50 @@ pub struct Krb5kdcConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
     #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
-    statistics: Vec<Krb5kdcStatistic>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    statistics: Vec<Krb5kdcConfigStatistic>,
     #[serde(default)]
-    path: String,
+    path: Paths,
+}
+
+/// One or more krb5kdc binaries to probe
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Paths {
+    One(String),
+    Many(Vec<Path>),
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self::One(Default::default())
+    }
+}
+
+/// A krb5kdc binary, which may be given a label to use in metric names
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Path {
+    Plain(String),
+    Labeled { path: String, label: String },
+}
+
+/// A krb5kdc binary to probe, with the label which distinguishes its metrics
+/// from those of other binaries
+#[derive(Clone, Debug, PartialEq)]
+pub struct Krb5kdcBinary {
+    pub path: String,
+    pub label: Option<String>,
 }
 
 impl Default for Krb5kdcConfig {
@@ -31,8 +68,10 @@ impl Default for Krb5kdcConfig {
         Self {
             bpf: Default::default(),
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
-            percentiles: crate::common::default_percentiles(),
+            jitter: Default::default(),
+            percentiles: default_percentiles(),
             statistics: default_statistics(),
             path: Default::default(),
         }
@@ -40,13 +79,56 @@ impl Default for Krb5kdcConfig {
 }
 
 impl Krb5kdcConfig {
-    pub fn path(&self) -> String {
-        self.path.clone()
+    /// The binaries to probe. A single binary without a label keeps the
+    /// unlabeled metric names, otherwise unlabeled binaries are labeled by
+    /// their path.
+    pub fn binaries(&self) -> Vec<Krb5kdcBinary> {
+        match &self.path {
+            Paths::One(path) => vec![Krb5kdcBinary {
+                path: path.clone(),
+                label: None,
+            }],
+            Paths::Many(paths) => {
+                let single = paths.len() == 1;
+                paths
+                    .iter()
+                    .map(|path| match path {
+                        Path::Plain(path) => Krb5kdcBinary {
+                            path: path.clone(),
+                            label: if single { None } else { Some(path_label(path)) },
+                        },
+                        Path::Labeled { path, label } => Krb5kdcBinary {
+                            path: path.clone(),
+                            label: Some(label.clone()),
+                        },
+                    })
+                    .collect()
+            }
+        }
     }
 }
 
-fn default_statistics() -> Vec<Krb5kdcStatistic> {
-    Krb5kdcStatistic::iter().collect()
+/// Derives a label from the path of a binary, for use in metric names
+fn path_label(path: &str) -> String {
+    path.trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// request latency is mostly of interest in the tail
+fn default_percentiles() -> Vec<f64> {
+    vec![50.0, 90.0, 99.0, 99.9]
+}
+
+fn default_statistics() -> Vec<Krb5kdcConfigStatistic> {
+    Krb5kdcConfigStatistic::iter().collect()
 }
 
 impl SamplerConfig for Krb5kdcConfig {
@@ -60,19 +142,84 @@ impl SamplerConfig for Krb5kdcConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
 
     fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
         let mut enabled = Vec::new();
-        for statistic in self.statistics.iter() {
-            enabled.push(*statistic);
+        for binary in self.binaries() {
+            for statistic in self.statistics.iter() {
+                enabled.push(Krb5kdcStatistic::new(binary.label.as_deref(), *statistic));
+            }
         }
         enabled
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(content: &str) -> Krb5kdcConfig {
+        toml::from_str(content).expect("failed to parse")
+    }
+
+    #[test]
+    fn binaries() {
+        assert_eq!(
+            config("path = \"/usr/sbin/krb5kdc\"").binaries(),
+            vec![Krb5kdcBinary {
+                path: "/usr/sbin/krb5kdc".to_string(),
+                label: None,
+            }]
+        );
+        assert_eq!(
+            config("path = [\"/srv/a.example/krb5kdc\", \"/srv/b/krb5kdc\"]").binaries(),
+            vec![
+                Krb5kdcBinary {
+                    path: "/srv/a.example/krb5kdc".to_string(),
+                    label: Some("srv_a_example_krb5kdc".to_string()),
+                },
+                Krb5kdcBinary {
+                    path: "/srv/b/krb5kdc".to_string(),
+                    label: Some("srv_b_krb5kdc".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            config("path = [{ path = \"/srv/b/krb5kdc\", label = \"b\" }]").binaries(),
+            vec![Krb5kdcBinary {
+                path: "/srv/b/krb5kdc".to_string(),
+                label: Some("b".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn statistic_names() {
+        use rustcommon_metrics::Statistic;
+
+        let statistic = Krb5kdcConfigStatistic::ProcessTgsReqNone;
+        assert_eq!(
+            Krb5kdcStatistic::new(None, statistic).name(),
+            "krb5kdc/process_tgs_req/none"
+        );
+        assert_eq!(
+            Krb5kdcStatistic::new(Some("b"), statistic).name(),
+            "krb5kdc/b/process_tgs_req/none"
+        );
+    }
+}
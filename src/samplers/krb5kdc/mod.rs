@@ -14,7 +14,11 @@ use crate::samplers::{Common, Sampler};
 #[cfg(feature = "bpf")]
 use crate::common::bpf::bpf_hash_char_to_map;
 #[cfg(feature = "bpf")]
+use rustcommon_metrics::{Source, Statistic};
+#[cfg(feature = "bpf")]
 use std::collections::HashMap;
+#[cfg(feature = "bpf")]
+use std::time::Duration;
 
 mod config;
 mod stat;
@@ -22,17 +26,37 @@ mod stat;
 pub use config::Krb5kdcConfig;
 pub use stat::Krb5kdcStatistic;
 
+/// A probed krb5kdc binary
+#[allow(dead_code)]
+struct Binary {
+    bpf: Arc<Mutex<BPF>>,
+    label: Option<String>,
+}
+
 #[allow(dead_code)]
 pub struct Krb5kdc {
-    bpf: Option<Arc<Mutex<BPF>>>,
+    binaries: Vec<Binary>,
     bpf_last: Arc<Mutex<Instant>>,
     common: Common,
     statistics: Vec<Krb5kdcStatistic>,
-    path: String,
 }
 
 impl Krb5kdc {
     fn init_bpf(&mut self) -> Result<(), anyhow::Error> {
+        for binary in self.common.config().samplers().krb5kdc().binaries() {
+            debug!("attaching krb5kdc probes to: {}", binary.path);
+            let bpf = self.probe(&binary.path)?;
+            self.binaries.push(Binary {
+                bpf: Arc::new(Mutex::new(bpf)),
+                label: binary.label,
+            });
+        }
+        Ok(())
+    }
+
+    /// Attaches the probes to a single krb5kdc binary
+    #[allow(unused_variables)]
+    fn probe(&self, path: &str) -> Result<BPF, anyhow::Error> {
         #[cfg(feature = "bpf")]
         {
             let code = include_str!("bpf.c");
@@ -40,7 +64,7 @@ impl Krb5kdc {
 
             if let Err(err) = bcc::Uprobe::new()
                 .handler("count_finish_process_as_req")
-                .binary(self.path.clone())
+                .binary(path)
                 .symbol("finish_process_as_req")
                 .attach(&mut bpf)
             {
@@ -53,7 +77,7 @@ impl Krb5kdc {
 
             if let Err(err) = bcc::Uprobe::new()
                 .handler("count_finish_dispatch_cache")
-                .binary(self.path.clone())
+                .binary(path)
                 .symbol("finish_dispatch_cache")
                 .attach(&mut bpf)
             {
@@ -66,7 +90,7 @@ impl Krb5kdc {
 
             if let Err(err) = bcc::Uretprobe::new()
                 .handler("count_process_tgs_req")
-                .binary(self.path.clone())
+                .binary(path)
                 .symbol("process_tgs_req")
                 .attach(&mut bpf)
             {
@@ -77,10 +101,107 @@ impl Krb5kdc {
                 }
             }
 
-            self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            // entry and return probes to measure the latency of each request
+            for symbol in &["process_as_req", "process_tgs_req"] {
+                if let Err(err) = bcc::Uprobe::new()
+                    .handler(&format!("trace_{}_entry", symbol))
+                    .binary(path)
+                    .symbol(symbol)
+                    .attach(&mut bpf)
+                {
+                    if self.common.config().fault_tolerant() {
+                        warn!("krb5kdc unable to attach probe to function {}", symbol);
+                    } else {
+                        Err(err)?;
+                    }
+                }
+
+                if let Err(err) = bcc::Uretprobe::new()
+                    .handler(&format!("trace_{}_return", symbol))
+                    .binary(path)
+                    .symbol(symbol)
+                    .attach(&mut bpf)
+                {
+                    if self.common.config().fault_tolerant() {
+                        warn!(
+                            "krb5kdc unable to attach return probe to function {}",
+                            symbol
+                        );
+                    } else {
+                        Err(err)?;
+                    }
+                }
+            }
+
+            Ok(BPF { inner: bpf })
+        }
+
+        #[cfg(not(feature = "bpf"))]
+        Ok(BPF {})
+    }
+
+    /// Records the counters of a single binary
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self, binary: &Binary) -> Result<(), std::io::Error> {
+        let bpf = binary.bpf.lock().unwrap();
+        let mut table_map = HashMap::new();
+
+        for table in &[
+            "counts_finish_process_as_req",
+            "counts_finish_dispatch_cache",
+            "counts_process_tgs_req",
+        ] {
+            table_map.insert(
+                *table,
+                bpf_hash_char_to_map(
+                    &(*bpf)
+                        .inner
+                        .table(table)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
+                ),
+            );
+        }
+
+        for stat in self
+            .statistics
+            .iter()
+            .filter(|s| s.label() == binary.label.as_deref())
+        {
+            if stat.source() == Source::Distribution {
+                continue;
+            }
+            let val = table_map
+                .get(stat.statistic().bpf_table())
+                .and_then(|entry_map| entry_map.get(stat.statistic().bpf_entry()))
+                .unwrap_or(&0);
+            self.common().record_counter(stat, Instant::now(), *val);
         }
         Ok(())
     }
+
+    /// Transfers the latency histograms of a single binary
+    #[cfg(feature = "bpf")]
+    fn sample_bpf_histograms(&self, binary: &Binary) {
+        let bpf = binary.bpf.lock().unwrap();
+        let time = Instant::now();
+        for stat in self
+            .statistics
+            .iter()
+            .filter(|s| s.label() == binary.label.as_deref())
+        {
+            if stat.source() != Source::Distribution {
+                continue;
+            }
+            if let Some(histogram) = bpf.histogram(stat.statistic().bpf_table()) {
+                for (&value, &count) in &histogram {
+                    if count > 0 {
+                        self.common()
+                            .record_bucket(stat, time, value * crate::MICROSECOND, count);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -90,13 +211,11 @@ impl Sampler for Krb5kdc {
     fn new(common: Common) -> Result<Self, anyhow::Error> {
         let fault_tolerant = common.config.general().fault_tolerant();
         let statistics = common.config().samplers().krb5kdc().statistics();
-        let path = common.config().samplers().krb5kdc().path();
         let mut sampler = Self {
-            bpf: None,
+            binaries: Vec::new(),
             bpf_last: Arc::new(Mutex::new(Instant::now())),
             common,
             statistics,
-            path,
         };
 
         if let Err(e) = sampler.init_bpf() {
@@ -118,7 +237,7 @@ impl Sampler for Krb5kdc {
             match Self::new(common.clone()) {
                 Ok(mut sampler) => {
                     common.runtime().spawn(async move {
-                        loop {
+                        while !sampler.retired() {
                             let _ = sampler.sample().await;
                         }
                     });
@@ -146,6 +265,13 @@ impl Sampler for Krb5kdc {
         self.common.config().samplers().krb5kdc()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -156,53 +282,23 @@ impl Sampler for Krb5kdc {
         }
 
         #[cfg(feature = "bpf")]
-        if let Some(ref bpf) = self.bpf {
-            let bpf = bpf.lock().unwrap();
-            let mut table_map = HashMap::new();
-
-            table_map.insert(
-                "counts_finish_process_as_req",
-                bpf_hash_char_to_map(
-                    &(*bpf)
-                        .inner
-                        .table("counts_finish_process_as_req")
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
-                ),
-            );
-
-            table_map.insert(
-                "counts_finish_dispatch_cache",
-                bpf_hash_char_to_map(
-                    &(*bpf)
-                        .inner
-                        .table("counts_finish_dispatch_cache")
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
-                ),
-            );
-
-            table_map.insert(
-                "counts_process_tgs_req",
-                bpf_hash_char_to_map(
-                    &(*bpf)
-                        .inner
-                        .table("counts_process_tgs_req")
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
-                ),
-            );
+        self.time_bpf(|s| {
+            s.binaries
+                .iter()
+                .try_for_each(|binary| s.sample_bpf(binary))
+        })?;
 
-            for stat in self.statistics.iter() {
-                if let Some(entry_map) = table_map.get(stat.bpf_table()) {
-                    let val = entry_map.get(stat.bpf_entry()).unwrap_or(&0);
-                    self.metrics()
-                        .record_counter(stat, Instant::now(), *val)
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-                } else {
-                    self.metrics()
-                        .record_counter(stat, Instant::now(), 0)
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        // latency histograms are transferred once per window
+        #[cfg(feature = "bpf")]
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
+            self.time_bpf(|s| {
+                for binary in &s.binaries {
+                    s.sample_bpf_histograms(binary);
                 }
-            }
+            });
+            *self.bpf_last.lock().unwrap() = Instant::now();
         }
+
         Ok(())
     }
 }
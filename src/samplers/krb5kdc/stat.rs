@@ -24,7 +24,7 @@ use strum_macros::{EnumIter, EnumString, IntoStaticStr};
     Serialize,
 )]
 #[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
-pub enum Krb5kdcStatistic {
+pub enum Krb5kdcConfigStatistic {
     #[strum(serialize = "krb5kdc/finish_process_as_req/unknown")]
     FinishProcessAsReqUnknown,
 
@@ -303,9 +303,15 @@ pub enum Krb5kdcStatistic {
 
     #[strum(serialize = "krb5kdc/process_tgs_req/svc_unavailable")]
     ProcessTgsReqSvcUnavailable,
+
+    #[strum(serialize = "krb5kdc/process_as_req/latency")]
+    ProcessAsReqLatency,
+
+    #[strum(serialize = "krb5kdc/process_tgs_req/latency")]
+    ProcessTgsReqLatency,
 }
 
-impl Krb5kdcStatistic {
+impl Krb5kdcConfigStatistic {
     pub fn bpf_table(self) -> &'static str {
         match self {
             Self::FinishProcessAsReqUnknown => "counts_finish_process_as_req",
@@ -403,6 +409,9 @@ impl Krb5kdcStatistic {
             Self::ProcessTgsReqMustUseUser2user => "counts_process_tgs_req",
             Self::ProcessTgsReqPathNotAccepted => "counts_process_tgs_req",
             Self::ProcessTgsReqSvcUnavailable => "counts_process_tgs_req",
+
+            Self::ProcessAsReqLatency => "process_as_req_latency",
+            Self::ProcessTgsReqLatency => "process_tgs_req_latency",
         }
     }
 
@@ -503,24 +512,67 @@ impl Krb5kdcStatistic {
             Self::ProcessTgsReqMustUseUser2user => "MUST_USE_USER2USER",
             Self::ProcessTgsReqPathNotAccepted => "PATH_NOT_ACCEPTED",
             Self::ProcessTgsReqSvcUnavailable => "SVC_UNAVAILABLE",
+
+            // histograms are not broken down by error code
+            Self::ProcessAsReqLatency | Self::ProcessTgsReqLatency => "",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::ProcessAsReqLatency | Self::ProcessTgsReqLatency => Source::Distribution,
+            _ => Source::Counter,
         }
     }
 }
 
-impl Statistic<AtomicU64, AtomicU32> for Krb5kdcStatistic {
-    fn name(&self) -> &str {
-        (*self).into()
+impl TryFrom<&str> for Krb5kdcConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Krb5kdcConfigStatistic::from_str(s)
+    }
+}
+
+/// A statistic for one of the probed krb5kdc binaries. When more than one
+/// binary is probed, the binary's label is included in the name, for example:
+/// `krb5kdc/realm_a/process_tgs_req/none`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Krb5kdcStatistic {
+    label: Option<String>,
+    name: String,
+    statistic: Krb5kdcConfigStatistic,
+}
+
+impl Krb5kdcStatistic {
+    pub fn new(label: Option<&str>, statistic: Krb5kdcConfigStatistic) -> Self {
+        let name: &str = statistic.into();
+        let name = match label {
+            Some(label) => name.replacen("krb5kdc/", &format!("krb5kdc/{}/", label), 1),
+            None => name.to_string(),
+        };
+        Self {
+            label: label.map(|l| l.to_string()),
+            name,
+            statistic,
+        }
     }
 
-    fn source(&self) -> Source {
-        Source::Counter
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn statistic(&self) -> Krb5kdcConfigStatistic {
+        self.statistic
     }
 }
 
-impl TryFrom<&str> for Krb5kdcStatistic {
-    type Error = ParseError;
+impl Statistic<AtomicU64, AtomicU32> for Krb5kdcStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
 
-    fn try_from(s: &str) -> Result<Self, Self::Error> {
-        Krb5kdcStatistic::from_str(s)
+    fn source(&self) -> Source {
+        self.statistic.source()
     }
 }
@@ -0,0 +1,32 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+
+// severities, which are the low byte of the events key
+#define CORRECTED 0
+#define UNCORRECTED 1
+#define FATAL 2
+
+// bits of the MCi_STATUS register of the bank which logged the error
+#define MCI_STATUS_UC (1ULL << 61)
+#define MCI_STATUS_PCC (1ULL << 57)
+
+// machine check events keyed by the bank in the upper bits and the severity
+// in the low byte
+BPF_HASH(events, u32, u64);
+
+int trace_mce_record(struct tracepoint__mce__mce_record *args)
+{
+    u32 severity = CORRECTED;
+    if (args->status & MCI_STATUS_UC) {
+        // uncorrected errors which corrupted the processor context are fatal
+        severity = (args->status & MCI_STATUS_PCC) ? FATAL : UNCORRECTED;
+    }
+    u32 key = ((u32)args->bank << 8) | severity;
+    u64 zero = 0;
+    u64 *count = events.lookup_or_try_init(&key, &zero);
+    if (count) lock_xadd(count, 1);
+    return 0;
+}
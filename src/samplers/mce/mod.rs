@@ -0,0 +1,197 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#[cfg(feature = "bpf")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "bpf")]
+use std::time::*;
+
+use async_trait::async_trait;
+#[cfg(feature = "bpf")]
+use rustcommon_metrics::*;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+#[allow(dead_code)]
+pub struct Mce {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    common: Common,
+    /// statistics which have been registered, as machine check banks are
+    /// discovered at runtime
+    registered: HashSet<MceStatistic>,
+    statistics: Vec<MceConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Mce {
+    type Statistic = MceStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().mce().statistics.clone();
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            common,
+            registered: HashSet::new(),
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().mce().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize mce sampler {}", e);
+                    } else {
+                        error!("failed to initialize mce sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().mce()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().mce().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Mce {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && !self.statistics.is_empty()
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let code = include_str!("bpf.c");
+                let mut bpf = bcc::BPF::new(code)?;
+
+                bcc::Tracepoint::new()
+                    .handler("trace_mce_record")
+                    .subsystem("mce")
+                    .tracepoint("mce_record")
+                    .attach(&mut bpf)?;
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn record(&mut self, statistic: MceStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    /// Records the count of machine check events of each severity, in total
+    /// and for each bank which has logged an event
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&mut self) -> Result<(), std::io::Error> {
+        let events = match self.bpf {
+            Some(ref bpf) => bpf.lock().unwrap().counters("events"),
+            None => None,
+        };
+        if let Some(events) = events {
+            let time = Instant::now();
+            let mut totals: HashMap<MceConfigStatistic, u64> = HashMap::new();
+            for (key, count) in events {
+                if let Some(statistic) = MceConfigStatistic::from_severity(key & 0xFF) {
+                    if self.statistics.contains(&statistic) {
+                        *totals.entry(statistic).or_insert(0) += count;
+                        self.record(MceStatistic::bank(key >> 8, statistic), time, count);
+                    }
+                }
+            }
+            // totals are reported even before the first event
+            for statistic in self.statistics.clone() {
+                let count = totals.get(&statistic).copied().unwrap_or(0);
+                self.record(MceStatistic::host(statistic), time, count);
+            }
+        }
+        Ok(())
+    }
+}
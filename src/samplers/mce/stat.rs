@@ -0,0 +1,105 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum MceConfigStatistic {
+    #[strum(serialize = "corrected")]
+    Corrected,
+    #[strum(serialize = "fatal")]
+    Fatal,
+    #[strum(serialize = "uncorrected")]
+    Uncorrected,
+}
+
+impl MceConfigStatistic {
+    /// The severity of the events, which is the low byte of the keys of the
+    /// BPF `events` table
+    pub fn from_severity(severity: u32) -> Option<Self> {
+        match severity {
+            0 => Some(Self::Corrected),
+            1 => Some(Self::Uncorrected),
+            2 => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for MceConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        MceConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MceStatistic {
+    name: String,
+    statistic: MceConfigStatistic,
+}
+
+impl MceStatistic {
+    /// A statistic of all banks, which is named `mce/...`
+    pub fn host(statistic: MceConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("mce/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of a machine check bank, which is named `mce/bank<id>/...`
+    pub fn bank(bank: u32, statistic: MceConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("mce/bank{}/{}", bank, suffix),
+                format!("mce/{}", suffix),
+                &[("bank", &bank.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> MceConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for MceStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
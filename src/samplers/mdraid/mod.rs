@@ -0,0 +1,298 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_MDSTAT: &str = "/proc/mdstat";
+
+/// Values of `array_state` for an array which is running
+const ACTIVE_STATES: &[&str] = &[
+    "active",
+    "active-idle",
+    "clean",
+    "read-auto",
+    "readonly",
+    "write-pending",
+];
+
+pub struct Mdraid {
+    common: Common,
+    /// statistics which have been registered, as arrays are discovered at
+    /// runtime
+    registered: HashSet<MdraidStatistic>,
+    statistics: Vec<MdraidConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Mdraid {
+    type Statistic = MdraidStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().mdraid().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().mdraid().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize mdraid sampler {}", e);
+                    } else {
+                        error!("failed to initialize mdraid sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().mdraid()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().mdraid().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_arrays().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Mdraid {
+    fn record(&mut self, statistic: MdraidStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Samples each array in `/proc/mdstat`, with the rest of its state read
+    /// from its attributes in sysfs
+    async fn sample_arrays(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_MDSTAT).await?;
+        for array in parse_mdstat(&content) {
+            let attributes = Attributes::read(&array.name).await;
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    MdraidConfigStatistic::Active => attributes
+                        .state
+                        .as_deref()
+                        .map(|s| ACTIVE_STATES.contains(&s) as u64),
+                    MdraidConfigStatistic::Degraded => attributes.degraded,
+                    MdraidConfigStatistic::DevicesFailed => Some(array.failed),
+                    MdraidConfigStatistic::DevicesSpare => Some(array.spare),
+                    MdraidConfigStatistic::DevicesTotal => attributes.raid_disks,
+                    MdraidConfigStatistic::Mismatches => attributes.mismatches,
+                    MdraidConfigStatistic::SyncActive => attributes
+                        .sync_action
+                        .as_deref()
+                        .map(|a| (a != "idle" && a != "frozen") as u64),
+                    // progress and speed are reported as `none` while idle,
+                    // which is treated as no progress
+                    MdraidConfigStatistic::SyncProgress => {
+                        Some(attributes.sync_progress.unwrap_or(0))
+                    }
+                    MdraidConfigStatistic::SyncSpeed => Some(attributes.sync_speed.unwrap_or(0)),
+                };
+                if let Some(value) = value {
+                    self.record(MdraidStatistic::new(&array.name, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The attributes of an array from `/sys/block/<array>/md`. Arrays which are
+/// not running, or are of a level without redundancy, lack some of them.
+#[derive(Debug, Default)]
+struct Attributes {
+    state: Option<String>,
+    degraded: Option<u64>,
+    raid_disks: Option<u64>,
+    mismatches: Option<u64>,
+    sync_action: Option<String>,
+    /// progress of the current sync, in hundredths of a percent
+    sync_progress: Option<u64>,
+    /// speed of the current sync, in bytes per second
+    sync_speed: Option<u64>,
+}
+
+impl Attributes {
+    async fn read(array: &str) -> Self {
+        let path = format!("/sys/block/{}/md", array);
+        let read = |name: &str| {
+            let file = format!("{}/{}", path, name);
+            async move {
+                tokio::fs::read_to_string(file)
+                    .await
+                    .ok()
+                    .map(|v| v.trim().to_string())
+            }
+        };
+        let parse = |v: Option<String>| v.and_then(|v| v.parse::<u64>().ok());
+        Self {
+            state: read("array_state").await,
+            degraded: parse(read("degraded").await),
+            raid_disks: parse(read("raid_disks").await),
+            mismatches: parse(read("mismatch_cnt").await),
+            sync_action: read("sync_action").await,
+            sync_progress: read("sync_completed")
+                .await
+                .and_then(|v| parse_sync_completed(&v)),
+            // reported in KiB/s
+            sync_speed: parse(read("sync_speed").await).map(|v| v * 1024),
+        }
+    }
+}
+
+/// Parses `sync_completed`, which is `<done> / <total>` in sectors, or `none`
+/// while idle, into hundredths of a percent
+fn parse_sync_completed(value: &str) -> Option<u64> {
+    let (done, total) = value.split_once('/')?;
+    let done: u64 = done.trim().parse().ok()?;
+    let total: u64 = total.trim().parse().ok()?;
+    if total == 0 {
+        return None;
+    }
+    Some(done.saturating_mul(10_000) / total)
+}
+
+#[derive(Debug, PartialEq)]
+struct Array {
+    name: String,
+    failed: u64,
+    spare: u64,
+}
+
+/// Parses the arrays from `/proc/mdstat`, in which each array begins with a
+/// line such as `md0 : active raid1 sdb1[1] sda1[0](F)`, where failed and
+/// spare devices are marked with `(F)` and `(S)`
+fn parse_mdstat(content: &str) -> Vec<Array> {
+    let mut arrays = Vec::new();
+    for line in content.lines() {
+        let (name, rest) = match line.split_once(" : ") {
+            Some((name, rest)) if name.starts_with("md") => (name.trim(), rest),
+            _ => continue,
+        };
+        let devices = rest.split_whitespace().filter(|d| d.contains('['));
+        let (mut failed, mut spare) = (0, 0);
+        for device in devices {
+            if device.ends_with("(F)") {
+                failed += 1;
+            } else if device.ends_with("(S)") {
+                spare += 1;
+            }
+        }
+        arrays.push(Array {
+            name: name.to_string(),
+            failed,
+            spare,
+        });
+    }
+    arrays
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_mdstat() {
+        let arrays = parse_mdstat(
+            "Personalities : [raid1] [raid6] [raid5] [raid4]\n\
+             md1 : active raid5 sde1[4] sdd1[2] sdc1[1] sdb1[0](F) sdf1[5](S)\n\
+             \x20     5860270080 blocks super 1.2 level 5, 512k chunk, algorithm 2 [4/3] [_UUU]\n\
+             \x20     [=>...................]  recovery =  8.1% (158465024/1953423360) \
+             finish=155.2min speed=192756K/sec\n\
+             \n\
+             md0 : active raid1 sdb2[1] sda2[0]\n\
+             \x20     1048512 blocks super 1.2 [2/2] [UU]\n\
+             \n\
+             unused devices: <none>\n",
+        );
+        assert_eq!(
+            arrays,
+            vec![
+                Array {
+                    name: "md1".to_string(),
+                    failed: 1,
+                    spare: 1,
+                },
+                Array {
+                    name: "md0".to_string(),
+                    failed: 0,
+                    spare: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sync_completed() {
+        assert_eq!(parse_sync_completed("158465024 / 1953423360"), Some(811));
+        assert_eq!(parse_sync_completed("none"), None);
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum MdraidConfigStatistic {
+    #[strum(serialize = "active")]
+    Active,
+    #[strum(serialize = "degraded")]
+    Degraded,
+    #[strum(serialize = "devices/failed")]
+    DevicesFailed,
+    #[strum(serialize = "devices/spare")]
+    DevicesSpare,
+    #[strum(serialize = "devices/total")]
+    DevicesTotal,
+    #[strum(serialize = "mismatches")]
+    Mismatches,
+    #[strum(serialize = "sync/active")]
+    SyncActive,
+    #[strum(serialize = "sync/progress")]
+    SyncProgress,
+    #[strum(serialize = "sync/speed")]
+    SyncSpeed,
+}
+
+impl TryFrom<&str> for MdraidConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        MdraidConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MdraidStatistic {
+    name: String,
+    statistic: MdraidConfigStatistic,
+}
+
+impl MdraidStatistic {
+    /// A statistic of an array, which is named `mdraid/<array>/...`
+    pub fn new(array: &str, statistic: MdraidConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("mdraid/{}/{}", array, suffix),
+                format!("mdraid/{}", suffix),
+                &[("array", array)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> MdraidConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for MdraidStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        // all statistics reflect the current state of the array
+        Source::Gauge
+    }
+}
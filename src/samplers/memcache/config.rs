@@ -4,7 +4,7 @@
 
 use serde_derive::Deserialize;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -14,7 +14,11 @@ pub struct MemcacheConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
     endpoint: Option<String>,
@@ -24,7 +28,9 @@ impl Default for MemcacheConfig {
     fn default() -> Self {
         Self {
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             endpoint: None,
         }
@@ -44,10 +50,18 @@ impl SamplerConfig for MemcacheConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
@@ -62,7 +62,7 @@ impl Sampler for Memcache {
         if common.config().samplers().memcache().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -138,10 +138,7 @@ impl Sampler for Memcache {
                                         self.common()
                                             .metrics()
                                             .add_output(&statistic, Output::Reading);
-                                        let _ = self
-                                            .common()
-                                            .metrics()
-                                            .record_counter(&statistic, time, value);
+                                        self.common().record_counter(&statistic, time, value);
                                         for percentile in self.sampler_config().percentiles() {
                                             self.common().metrics().add_output(
                                                 &statistic,
@@ -54,7 +54,7 @@ impl Sampler for Memory {
         if common.config().samplers().memory().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -78,6 +78,13 @@ impl Sampler for Memory {
         self.common.config().samplers().memory()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -181,7 +188,7 @@ impl Memory {
             if let Some(value) = result.get(statistic) {
                 match statistic.source() {
                     Source::Counter => {
-                        let _ = self.metrics().record_counter(statistic, time, *value);
+                        self.common().record_counter(statistic, time, *value);
                     }
                     Source::Gauge => {
                         let _ = self.metrics().record_gauge(statistic, time, *value);
@@ -251,8 +258,7 @@ impl Memory {
         for stat in &self.statistics {
             if let Some(value) = result.get(stat) {
                 if stat.source() == Source::Counter {
-                    let _ = self
-                        .metrics()
+                    self.common()
                         .record_counter(stat, time, *value * stat.multiplier());
                 } else {
                     let _ = self
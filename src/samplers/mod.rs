@@ -2,58 +2,168 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::convert::TryInto;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use rustcommon_metrics::*;
 use tokio::runtime::Runtime;
+use tokio::sync::Notify;
 use tokio::time::{interval, Interval};
 
+use crate::common::histograms::Histograms;
+use crate::common::labels;
 use crate::config::General as GeneralConfig;
-use crate::config::{Config, SamplerConfig};
+use crate::config::{Config, ConfigHandle, SamplerConfig};
 use crate::HardwareInfo;
 
+pub mod aer;
+pub mod amdgpu;
+pub mod bonding;
+pub mod btrfs;
+pub mod ceph;
+pub mod cgroups;
+pub mod conntrack;
 pub mod cpu;
+pub mod cpufreq;
+pub mod cpuidle;
 pub mod disk;
+pub mod dm;
+pub mod dns;
+pub mod ebpf;
+pub mod edac;
+pub mod entropy;
 pub mod ext4;
+pub mod fd;
+pub mod filesystem;
+pub mod fsync;
+pub mod futex;
 pub mod http;
+pub mod hugepages;
+pub mod intel_gpu;
 pub mod interrupt;
+pub mod io_uring;
+pub mod ipmi;
 pub mod krb5kdc;
+pub mod mce;
+pub mod mdraid;
 pub mod memcache;
 pub mod memory;
 pub mod network;
+pub mod nfs;
 pub mod ntp;
+pub mod numa;
 pub mod nvidia;
+pub mod nvme;
+pub mod oom;
 pub mod page_cache;
+pub mod power;
+pub mod process;
+pub mod psi;
+pub mod quic;
+pub mod rdma;
+pub mod reclaim;
+pub mod resctrl;
 pub mod rezolus;
 pub mod scheduler;
+pub mod slab;
+pub mod smart;
+pub mod softirq;
 pub mod softnet;
+pub mod sriov;
+pub mod swap;
+pub mod syscall;
 pub mod tcp;
+pub mod thermal;
+pub mod tls;
 pub mod udp;
+pub mod uncore;
+pub mod uprobe;
+pub mod usdt;
 pub mod usercall;
+pub mod vfs;
+pub mod writeback;
 pub mod xfs;
+pub mod zfs;
 
+pub use aer::Aer;
+pub use amdgpu::Amdgpu;
+pub use bonding::Bonding;
+pub use btrfs::Btrfs;
+pub use ceph::Ceph;
+pub use cgroups::Cgroups;
+pub use conntrack::Conntrack;
 pub use cpu::Cpu;
+pub use cpufreq::Cpufreq;
+pub use cpuidle::Cpuidle;
 pub use disk::Disk;
+pub use dm::Dm;
+pub use dns::Dns;
+pub use ebpf::Ebpf;
+pub use edac::Edac;
+pub use entropy::Entropy;
 pub use ext4::Ext4;
+pub use fd::Fd;
+pub use filesystem::Filesystem;
+pub use fsync::Fsync;
+pub use futex::Futex;
 pub use http::Http;
+pub use hugepages::Hugepages;
+pub use intel_gpu::IntelGpu;
 pub use interrupt::Interrupt;
+pub use io_uring::IoUring;
+pub use ipmi::Ipmi;
 pub use krb5kdc::Krb5kdc;
+pub use mce::Mce;
+pub use mdraid::Mdraid;
 pub use memcache::Memcache;
 pub use memory::Memory;
 pub use network::Network;
+pub use nfs::Nfs;
 pub use ntp::Ntp;
+pub use numa::Numa;
 pub use nvidia::Nvidia;
+pub use nvme::Nvme;
+pub use oom::Oom;
 pub use page_cache::PageCache;
+pub use power::Power;
+pub use process::Process;
+pub use psi::Psi;
+pub use quic::Quic;
+pub use rdma::Rdma;
+pub use reclaim::Reclaim;
+pub use resctrl::Resctrl;
 pub use rezolus::Rezolus;
 pub use scheduler::Scheduler;
+pub use slab::Slab;
+pub use smart::Smart;
+pub use softirq::Softirq;
 pub use softnet::Softnet;
+pub use sriov::Sriov;
+pub use swap::Swap;
+pub use syscall::Syscall;
 pub use tcp::Tcp;
+pub use thermal::Thermal;
+pub use tls::Tls;
 pub use udp::Udp;
+pub use uncore::Uncore;
+pub use uprobe::Uprobe;
+pub use usdt::Usdt;
 pub use usercall::Usercall;
+pub use vfs::Vfs;
+pub use writeback::Writeback;
 pub use xfs::Xfs;
+pub use zfs::Zfs;
+
+mod counters;
+mod overhead;
+
+pub use counters::Counters;
+pub use overhead::{Overhead, SamplerOverhead};
 
 #[async_trait]
 pub trait Sampler: Sized + Send {
@@ -78,12 +188,28 @@ pub trait Sampler: Sized + Send {
             .unwrap_or_else(|| self.general_config().interval())
     }
 
+    /// Percentage of the interval by which each sample may be delayed
+    fn jitter(&self) -> usize {
+        self.sampler_config()
+            .jitter()
+            .unwrap_or_else(|| self.general_config().jitter())
+    }
+
     /// Wait until the next time to sample
-    fn delay(&mut self) -> &mut Option<Interval> {
+    fn delay(&mut self) -> &mut Option<Delay> {
+        if self.common_mut().refresh_config() {
+            debug!("configuration reloaded");
+            self.common_mut().set_interval(None);
+            self.reconfigure();
+        }
         if self.common_mut().interval().is_none() {
             let millis = self.interval() as u64;
+            let jitter = self.jitter() as u32;
             self.common_mut()
-                .set_interval(Some(interval(Duration::from_millis(millis))));
+                .set_interval(Some(Duration::from_millis(millis)));
+            if let Some(delay) = self.common_mut().interval() {
+                delay.set_jitter(jitter);
+            }
         }
         self.common_mut().interval()
     }
@@ -95,11 +221,25 @@ pub trait Sampler: Sized + Send {
         self.sampler_config().enabled()
     }
 
+    /// Returns whether the sampler has been replaced by the watchdog, in which
+    /// case it should stop sampling
+    fn retired(&self) -> bool {
+        self.common().retired()
+    }
+
     /// Access the general config
     fn general_config(&self) -> &GeneralConfig {
         self.common().config().general()
     }
 
+    /// Apply a reloaded configuration. Samplers which keep their own list of
+    /// statistics should refresh it here before registering.
+    fn reconfigure(&mut self) {
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     /// Register all the statistics
     fn register(&self) {
         for statistic in self.sampler_config().statistics() {
@@ -109,23 +249,9 @@ pub trait Sampler: Sized + Send {
             let percentiles = self.sampler_config().percentiles();
             if !percentiles.is_empty() {
                 if statistic.source() == Source::Distribution {
-                    self.common().metrics().add_summary(
-                        &statistic,
-                        Summary::heatmap(
-                            1_000_000_000,
-                            2,
-                            Duration::new(
-                                self.common()
-                                    .config()
-                                    .general()
-                                    .window()
-                                    .try_into()
-                                    .unwrap(),
-                                0,
-                            ),
-                            Duration::new(1, 0),
-                        ),
-                    );
+                    self.common()
+                        .metrics()
+                        .add_summary(&statistic, self.heatmap(1_000_000_000));
                 } else {
                     self.common()
                         .metrics()
@@ -141,13 +267,42 @@ pub trait Sampler: Sized + Send {
     }
 
     fn samples(&self) -> usize {
-        ((1000.0 / self.interval() as f64) * self.general_config().window() as f64).ceil() as usize
+        ((1000.0 / self.interval() as f64) * self.window() as f64).ceil() as usize
+    }
+
+    /// Seconds of samples which percentiles are calculated over
+    fn window(&self) -> usize {
+        self.sampler_config()
+            .histogram()
+            .window()
+            .unwrap_or_else(|| self.general_config().window())
+    }
+
+    /// A heatmap for a distribution of values up to `max`, unless the sampler
+    /// config sets its own histogram parameters
+    fn heatmap(&self, max: u64) -> Summary<AtomicU64, AtomicU32> {
+        let histogram = self.sampler_config().histogram();
+        Summary::heatmap(
+            histogram.max().unwrap_or(max),
+            histogram.precision().unwrap_or(2),
+            Duration::new(self.window() as u64, 0),
+            Duration::new(1, 0),
+        )
     }
 
     fn metrics(&self) -> &Metrics<AtomicU64, AtomicU32> {
         self.common().metrics()
     }
 
+    /// Runs a read of the sampler's BPF maps, recording the time taken as part
+    /// of the sampler's overhead
+    fn time_bpf<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let start = Instant::now();
+        let result = f(self);
+        self.common().record_bpf_read(start.elapsed());
+        result
+    }
+
     /// Used to map errors according to fault tolerance
     /// WouldBlock is returned as-is so that async/await behaves as expected
     /// All other errors are handled per fault tolerance setting
@@ -156,6 +311,7 @@ pub trait Sampler: Sized + Send {
             if e.kind() == std::io::ErrorKind::WouldBlock {
                 return Err(e);
             }
+            self.common().record_error();
             if self.common().config().general().fault_tolerant() {
                 debug!("error: {}", e);
             } else {
@@ -166,41 +322,467 @@ pub trait Sampler: Sized + Send {
     }
 }
 
+/// Invokes `$m!(name, Type)` for each sampler, where `name` is the name of
+/// its config section
+macro_rules! for_each_sampler {
+    ($m:ident) => {
+        $m!(aer, Aer);
+        $m!(amdgpu, Amdgpu);
+        $m!(bonding, Bonding);
+        $m!(btrfs, Btrfs);
+        $m!(ceph, Ceph);
+        $m!(cgroups, Cgroups);
+        $m!(conntrack, Conntrack);
+        $m!(cpu, Cpu);
+        $m!(cpufreq, Cpufreq);
+        $m!(cpuidle, Cpuidle);
+        $m!(disk, Disk);
+        $m!(dm, Dm);
+        $m!(dns, Dns);
+        $m!(ebpf, Ebpf);
+        $m!(edac, Edac);
+        $m!(entropy, Entropy);
+        $m!(ext4, Ext4);
+        $m!(fd, Fd);
+        $m!(filesystem, Filesystem);
+        $m!(fsync, Fsync);
+        $m!(futex, Futex);
+        $m!(http, Http);
+        $m!(hugepages, Hugepages);
+        $m!(intel_gpu, IntelGpu);
+        $m!(interrupt, Interrupt);
+        $m!(io_uring, IoUring);
+        $m!(ipmi, Ipmi);
+        $m!(krb5kdc, Krb5kdc);
+        $m!(mce, Mce);
+        $m!(mdraid, Mdraid);
+        $m!(memcache, Memcache);
+        $m!(memory, Memory);
+        $m!(network, Network);
+        $m!(nfs, Nfs);
+        $m!(ntp, Ntp);
+        $m!(numa, Numa);
+        $m!(nvidia, Nvidia);
+        $m!(nvme, Nvme);
+        $m!(oom, Oom);
+        $m!(page_cache, PageCache);
+        $m!(power, Power);
+        $m!(process, Process);
+        $m!(psi, Psi);
+        $m!(quic, Quic);
+        $m!(rdma, Rdma);
+        $m!(reclaim, Reclaim);
+        $m!(resctrl, Resctrl);
+        $m!(rezolus, Rezolus);
+        $m!(scheduler, Scheduler);
+        $m!(slab, Slab);
+        $m!(smart, Smart);
+        $m!(softirq, Softirq);
+        $m!(softnet, Softnet);
+        $m!(sriov, Sriov);
+        $m!(swap, Swap);
+        $m!(syscall, Syscall);
+        $m!(tcp, Tcp);
+        $m!(thermal, Thermal);
+        $m!(tls, Tls);
+        $m!(udp, Udp);
+        $m!(uncore, Uncore);
+        $m!(uprobe, Uprobe);
+        $m!(usdt, Usdt);
+        $m!(usercall, Usercall);
+        $m!(vfs, Vfs);
+        $m!(writeback, Writeback);
+        $m!(xfs, Xfs);
+        $m!(zfs, Zfs);
+    };
+}
+
+/// Names of all samplers
+pub fn sampler_names() -> Vec<&'static str> {
+    let mut names = Vec::new();
+    macro_rules! name {
+        ($name:ident, $sampler:ident) => {
+            names.push(stringify!($name));
+        };
+    }
+    for_each_sampler!(name);
+    names
+}
+
+/// Returns whether the named sampler is enabled in the config, or `None` if
+/// there is no such sampler
+pub fn sampler_enabled(config: &Config, name: &str) -> Option<bool> {
+    macro_rules! enabled {
+        ($name:ident, $sampler:ident) => {
+            if name == stringify!($name) {
+                return Some(config.samplers().$name().enabled());
+            }
+        };
+    }
+    for_each_sampler!(enabled);
+    None
+}
+
+/// Returns the problems with each sampler's config which deserializing it
+/// does not catch
+pub fn check_samplers(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+    macro_rules! check {
+        ($name:ident, $sampler:ident) => {
+            let sampler = config.samplers().$name();
+            if sampler.interval() == Some(0) {
+                errors.push(format!(
+                    "samplers.{}.interval: must be greater than 0",
+                    stringify!($name)
+                ));
+            }
+            if sampler.jitter().unwrap_or(0) > 100 {
+                errors.push(format!(
+                    "samplers.{}.jitter: must be at most 100 percent",
+                    stringify!($name)
+                ));
+            }
+            let histogram = sampler.histogram();
+            if histogram.max() == Some(0) || histogram.window() == Some(0) {
+                errors.push(format!(
+                    "samplers.{}.histogram: max and window must be greater than 0",
+                    stringify!($name)
+                ));
+            }
+            if let Some(precision) = histogram.precision() {
+                if !(1..=5).contains(&precision) {
+                    errors.push(format!(
+                        "samplers.{}.histogram.precision: {} is not between 1 and 5",
+                        stringify!($name),
+                        precision
+                    ));
+                }
+            }
+            for percentile in sampler.percentiles() {
+                if !(0.0..=100.0).contains(percentile) {
+                    errors.push(format!(
+                        "samplers.{}.percentiles: {} is not between 0 and 100",
+                        stringify!($name),
+                        percentile
+                    ));
+                }
+            }
+            #[cfg(feature = "bpf_core")]
+            {
+                let core = env!("BPF_CORE_SAMPLERS")
+                    .split(',')
+                    .any(|name| name == stringify!($name));
+                if sampler.enabled() && sampler.bpf() && !core {
+                    errors.push(format!(
+                        "samplers.{}.bpf: there is no CO-RE program for this sampler, set bpf = false or build with the bpf feature",
+                        stringify!($name)
+                    ));
+                }
+                if sampler.enabled() && sampler.perf_events() {
+                    errors.push(format!(
+                        "samplers.{}.perf_events: perf counters require the bpf feature",
+                        stringify!($name)
+                    ));
+                }
+            }
+        };
+    }
+    for_each_sampler!(check);
+    errors
+}
+
+/// Spawn each enabled sampler which has not already been spawned. This is
+/// called at startup and again after the configuration changes, so that
+/// samplers which were disabled at startup may be enabled later.
+pub fn spawn_samplers(common: &Common, registry: &Registry) {
+    macro_rules! spawn {
+        ($name:ident, $sampler:ident) => {
+            if common.config().samplers().$name().enabled() {
+                if let Some(spawned) = registry.insert(stringify!($name)) {
+                    $sampler::spawn(common.for_sampler(stringify!($name), spawned));
+                }
+            }
+        };
+    }
+    for_each_sampler!(spawn);
+}
+
+/// Restarts samplers which have stopped sampling, such as after a panic or
+/// while blocked on a lock, and counts the restarts in their overhead. This
+/// runs on its own thread so that it is not held up by a sampler which blocks
+/// the runtime, though a restarted sampler can only make progress if there is
+/// another worker thread to run it.
+pub fn watchdog(mut common: Common, registry: Registry) {
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+        common.refresh_config();
+        let timeout = common.config().general().watchdog();
+        if timeout == 0 {
+            continue;
+        }
+        let stalled = registry.stalled(Duration::from_secs(timeout as u64));
+        for name in &stalled {
+            // the stalled sampler exits if it ever resumes
+            registry.remove(name);
+            if sampler_enabled(common.config(), name) == Some(true) {
+                warn!("{} sampler has stopped sampling, restarting it", name);
+                common.overhead().record_restart(name);
+            }
+        }
+        if !stalled.is_empty() {
+            spawn_samplers(&common, &registry);
+        }
+    }
+}
+
+/// Tracks the samplers which have been spawned, allowing an immediate sample
+/// to be requested from each of them
+#[derive(Clone, Default)]
+pub struct Registry {
+    spawned: Arc<Mutex<HashMap<&'static str, Spawned>>>,
+}
+
+impl Registry {
+    /// Record that a sampler is being spawned, returning the state which it
+    /// shares with the registry, or `None` if it has already been spawned
+    fn insert(&self, name: &'static str) -> Option<Spawned> {
+        let mut spawned = self.spawned.lock().unwrap();
+        if spawned.contains_key(name) {
+            return None;
+        }
+        let sampler = Spawned::new();
+        spawned.insert(name, sampler.clone());
+        Some(sampler)
+    }
+
+    /// Forget the named sampler so that it may be spawned again, retiring the
+    /// running instance
+    fn remove(&self, name: &str) {
+        if let Some(sampler) = self.spawned.lock().unwrap().remove(name) {
+            sampler.heartbeat.retired.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Retire every sampler, waking them so that they stop after finishing
+    /// their current sample. Samplers detach their BPF probes as they stop.
+    pub fn stop(&self) {
+        for sampler in self.spawned.lock().unwrap().values() {
+            sampler.heartbeat.retired.store(true, Ordering::Relaxed);
+            sampler.trigger.notify_one();
+        }
+    }
+
+    /// Waits for the samplers to stop, giving up at the deadline. Returns the
+    /// samplers which are still running.
+    pub fn wait(&self, deadline: Instant) -> Vec<&'static str> {
+        loop {
+            // the registry holds the only reference to the heartbeat of a
+            // sampler which has stopped
+            let running: Vec<&'static str> = self
+                .spawned
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, sampler)| Arc::strong_count(&sampler.heartbeat) > 1)
+                .map(|(name, _)| *name)
+                .collect();
+            if running.is_empty() || Instant::now() >= deadline {
+                return running;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Returns the samplers which have gone longer than their interval plus
+    /// the timeout without waiting to sample
+    pub fn stalled(&self, timeout: Duration) -> Vec<&'static str> {
+        self.spawned
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, sampler)| !sampler.heartbeat.retired.load(Ordering::Relaxed))
+            .filter(|(_, sampler)| sampler.heartbeat.is_stalled(timeout))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Returns whether the named sampler has been spawned
+    pub fn is_spawned(&self, name: &str) -> bool {
+        self.spawned.lock().unwrap().contains_key(name)
+    }
+
+    /// Wake the named sampler so that it samples immediately. Returns `false`
+    /// if the sampler has not been spawned.
+    pub fn trigger(&self, name: &str) -> bool {
+        match self.spawned.lock().unwrap().get(name) {
+            Some(sampler) => {
+                sampler.trigger.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The state shared between the registry and a spawned sampler
+#[derive(Clone)]
+struct Spawned {
+    trigger: Arc<Notify>,
+    heartbeat: Arc<Heartbeat>,
+}
+
+impl Spawned {
+    fn new() -> Self {
+        Self {
+            trigger: Arc::new(Notify::new()),
+            heartbeat: Arc::new(Heartbeat::default()),
+        }
+    }
+}
+
+/// Records each time a sampler begins waiting to sample, so that the watchdog
+/// can tell when it has stopped
+#[derive(Default)]
+struct Heartbeat {
+    /// when the sampler last began waiting, and its interval. This is `None`
+    /// until the sampler first waits, so samplers which fail to initialize
+    /// are never restarted.
+    last: Mutex<Option<(Instant, Duration)>>,
+    /// set when the sampler has been replaced by a new instance
+    retired: AtomicBool,
+}
+
+impl Heartbeat {
+    fn beat(&self, interval: Duration) {
+        *self.last.lock().unwrap() = Some((Instant::now(), interval));
+    }
+
+    fn is_stalled(&self, timeout: Duration) -> bool {
+        match *self.last.lock().unwrap() {
+            Some((last, interval)) => last.elapsed() > interval + timeout,
+            None => false,
+        }
+    }
+}
+
+/// Waits for the sampling interval to elapse, or for an immediate sample to be
+/// requested through the `Registry`. The time between returning from one tick
+/// and starting the next is recorded as the duration of a sample.
+///
+/// With jitter, each sample is delayed by a random fraction of the jitter after
+/// the interval elapses, so that samplers, and hosts, which share an interval
+/// don't all sample at once. The mean interval is unchanged.
+pub struct Delay {
+    interval: Interval,
+    period: Duration,
+    jitter: Duration,
+    rng: u64,
+    trigger: Arc<Notify>,
+    heartbeat: Arc<Heartbeat>,
+    name: &'static str,
+    overhead: Arc<Overhead>,
+    sampling: Option<Instant>,
+}
+
+impl Delay {
+    pub async fn tick(&mut self) {
+        if let Some(start) = self.sampling.take() {
+            self.overhead.record_sample(self.name, start.elapsed());
+        }
+        self.heartbeat.beat(self.period + self.jitter);
+        let triggered = tokio::select! {
+            _ = self.interval.tick() => false,
+            _ = self.trigger.notified() => true,
+        };
+        if !triggered && self.jitter > Duration::from_secs(0) {
+            let delay = self.next_jitter();
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.trigger.notified() => {}
+            }
+        }
+        self.sampling = Some(Instant::now());
+    }
+
+    /// Delay each sample by up to the percentage of the interval
+    pub fn set_jitter(&mut self, percent: u32) {
+        self.jitter = self.period * percent.min(100) / 100;
+    }
+
+    /// Returns a random delay of less than the jitter, using xorshift so that
+    /// each sampler has its own sequence
+    fn next_jitter(&mut self) -> Duration {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let jitter = self.jitter.as_nanos() as u64;
+        Duration::from_nanos(self.rng % jitter.max(1))
+    }
+}
+
 pub struct Common {
     config: Arc<Config>,
+    counters: Arc<Counters>,
+    handle: ConfigHandle,
     runtime: Arc<Runtime>,
     hardware_info: Arc<HardwareInfo>,
-    interval: Option<Interval>,
+    histograms: Arc<Histograms>,
+    interval: Option<Delay>,
     metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
+    name: &'static str,
+    overhead: Arc<Overhead>,
+    spawned: Spawned,
 }
 
 impl Clone for Common {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
+            counters: self.counters.clone(),
+            handle: self.handle.clone(),
             runtime: self.runtime.clone(),
             hardware_info: self.hardware_info.clone(),
+            histograms: self.histograms.clone(),
             interval: None,
             metrics: self.metrics.clone(),
+            name: self.name,
+            overhead: self.overhead.clone(),
+            spawned: self.spawned.clone(),
         }
     }
 }
 
 impl Common {
     pub fn new(
-        config: Arc<Config>,
+        handle: ConfigHandle,
         metrics: Arc<Metrics<AtomicU64, AtomicU32>>,
         runtime: Arc<Runtime>,
+        histograms: Arc<Histograms>,
     ) -> Self {
         Self {
-            config,
+            config: handle.current(),
+            counters: Arc::new(Counters::default()),
+            handle,
             hardware_info: Arc::new(HardwareInfo::new()),
+            histograms,
             interval: None,
             metrics,
+            name: "",
+            overhead: Arc::new(Overhead::default()),
             runtime,
+            spawned: Spawned::new(),
         }
     }
 
+    /// Returns a copy for the named sampler, which wakes on its trigger as
+    /// well as its interval, and reports its heartbeat to the registry
+    fn for_sampler(&self, name: &'static str, spawned: Spawned) -> Self {
+        let mut common = self.clone();
+        common.name = name;
+        common.spawned = spawned;
+        common
+    }
+
     pub fn runtime(&self) -> &Runtime {
         &self.runtime
     }
@@ -209,19 +791,154 @@ impl Common {
         &self.config
     }
 
+    /// Switch to the active config if it has been reloaded since it was last
+    /// checked. Returns `true` if the config changed.
+    pub fn refresh_config(&mut self) -> bool {
+        let current = self.handle.current();
+        if Arc::ptr_eq(&current, &self.config) {
+            false
+        } else {
+            self.config = current;
+            true
+        }
+    }
+
     pub fn hardware_info(&self) -> &HardwareInfo {
         &self.hardware_info
     }
 
-    pub fn interval(&mut self) -> &mut Option<Interval> {
+    pub fn interval(&mut self) -> &mut Option<Delay> {
         &mut self.interval
     }
 
-    pub fn set_interval(&mut self, interval: Option<Interval>) {
-        self.interval = interval
+    pub fn set_interval(&mut self, period: Option<Duration>) {
+        let trigger = self.spawned.trigger.clone();
+        let heartbeat = self.spawned.heartbeat.clone();
+        let name = self.name;
+        let overhead = self.overhead.clone();
+        // seeded from the randomly keyed hasher, so that the sequence differs
+        // between samplers and between hosts
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write(name.as_bytes());
+        let rng = hasher.finish() | 1;
+        self.interval = period.map(|period| Delay {
+            interval: interval(period),
+            period,
+            jitter: Duration::from_secs(0),
+            rng,
+            trigger,
+            heartbeat,
+            name,
+            overhead,
+            sampling: None,
+        })
     }
 
     pub fn metrics(&self) -> &Metrics<AtomicU64, AtomicU32> {
         &self.metrics
     }
+
+    /// Records a reading of a counter as a total which only increases, so
+    /// that wraps and resets of the counter don't distort its rate. Readings
+    /// of other statistics are recorded as they are.
+    pub fn record_counter<S: Statistic<AtomicU64, AtomicU32>>(
+        &self,
+        statistic: &S,
+        time: Instant,
+        value: u64,
+    ) {
+        let value = if statistic.source() == Source::Counter {
+            self.counters.record(statistic.name(), value)
+        } else {
+            value
+        };
+        let _ = self.metrics.record_counter(statistic, time, value);
+    }
+
+    /// Records `count` occurrences of a value of a distribution, which is
+    /// also added to its histogram if it is exported as one
+    pub fn record_bucket<S: Statistic<AtomicU64, AtomicU32>>(
+        &self,
+        statistic: &S,
+        time: Instant,
+        value: u64,
+        count: u32,
+    ) {
+        let _ = self.metrics.record_bucket(statistic, time, value, count);
+        self.histograms.record(statistic.name(), value, count);
+    }
+
+    /// Stops exporting a statistic whose dimension has gone away, such as a
+    /// process or cgroup, and forgets its labels and last reading
+    pub fn deregister<S: Statistic<AtomicU64, AtomicU32>>(&self, statistic: &S) {
+        self.metrics.deregister(statistic);
+        self.counters.remove(statistic.name());
+        labels::remove(statistic.name());
+    }
+
+    /// Returns whether the sampler has been replaced by the watchdog
+    pub fn retired(&self) -> bool {
+        self.spawned.heartbeat.retired.load(Ordering::Relaxed)
+    }
+
+    /// The counters which have been recorded, which are shared between the
+    /// samplers
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// The overhead of running each sampler, which is shared between them
+    pub fn overhead(&self) -> &Overhead {
+        &self.overhead
+    }
+
+    pub fn record_error(&self) {
+        self.overhead.record_error(self.name);
+    }
+
+    pub fn record_bpf_read(&self, elapsed: Duration) {
+        self.overhead.record_bpf_read(self.name, elapsed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registry_stalled() {
+        let registry = Registry::default();
+        let sampler = registry.insert("disk").unwrap();
+        assert!(registry.insert("disk").is_none());
+
+        // samplers which have never waited to sample are not stalled
+        assert!(registry.stalled(Duration::from_secs(0)).is_empty());
+
+        sampler.heartbeat.beat(Duration::from_millis(0));
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(registry.stalled(Duration::from_secs(0)), vec!["disk"]);
+        assert!(registry.stalled(Duration::from_secs(60)).is_empty());
+
+        registry.remove("disk");
+        assert!(sampler.heartbeat.retired.load(Ordering::Relaxed));
+        assert!(!registry.is_spawned("disk"));
+        assert!(registry.insert("disk").is_some());
+    }
+
+    #[test]
+    fn test_registry_stop() {
+        let registry = Registry::default();
+        let sampler = registry.insert("disk").unwrap();
+        registry.stop();
+        assert!(sampler.heartbeat.retired.load(Ordering::Relaxed));
+        // a stopped sampler is never restarted
+        sampler.heartbeat.beat(Duration::from_millis(0));
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(registry.stalled(Duration::from_secs(0)).is_empty());
+
+        // the sampler is running until its state is dropped
+        assert_eq!(registry.wait(Instant::now()), vec!["disk"]);
+        drop(sampler);
+        assert!(registry.wait(Instant::now()).is_empty());
+    }
 }
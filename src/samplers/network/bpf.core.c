@@ -0,0 +1,30 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// CO-RE version of bpf.c, built with the bpf_core feature
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_core_read.h>
+
+#include "histogram.h"
+
+HISTOGRAM(rx_size);
+HISTOGRAM(tx_size);
+
+SEC("tracepoint/net/net_dev_queue")
+int trace_transmit(struct trace_event_raw_net_dev_template *args)
+{
+    histogram_add(&tx_size, BPF_CORE_READ(args, len));
+    return 0;
+}
+
+SEC("tracepoint/net/netif_rx")
+int trace_receive(struct trace_event_raw_net_dev_template *args)
+{
+    histogram_add(&rx_size, BPF_CORE_READ(args, len));
+    return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -17,10 +17,21 @@ pub struct NetworkConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    interfaces: Vec<String>,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(default)]
+    queues: bool,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<NetworkStatistic>,
 }
 
@@ -29,8 +40,12 @@ impl Default for NetworkConfig {
         Self {
             bpf: Default::default(),
             enabled: Default::default(),
+            interfaces: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
+            queues: Default::default(),
             statistics: default_statistics(),
         }
     }
@@ -40,6 +55,33 @@ fn default_statistics() -> Vec<NetworkStatistic> {
     NetworkStatistic::iter().collect()
 }
 
+impl NetworkConfig {
+    /// Whether per-queue and per-interface driver statistics are collected
+    pub fn queues(&self) -> bool {
+        self.queues
+    }
+
+    /// The interfaces for which driver statistics are collected, all
+    /// interfaces other than loopback if none are configured
+    pub fn interfaces(&self) -> Vec<String> {
+        if !self.interfaces.is_empty() {
+            return self.interfaces.clone();
+        }
+        let mut interfaces = Vec::new();
+        if let Ok(entries) = std::fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                if let Ok(name) = entry.file_name().into_string() {
+                    if name != "lo" {
+                        interfaces.push(name);
+                    }
+                }
+            }
+        }
+        interfaces.sort();
+        interfaces
+    }
+}
+
 impl SamplerConfig for NetworkConfig {
     type Statistic = NetworkStatistic;
 
@@ -51,10 +93,18 @@ impl SamplerConfig for NetworkConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
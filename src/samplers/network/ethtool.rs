@@ -0,0 +1,215 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! Reads driver statistics of a network interface with the `SIOCETHTOOL`
+//! ioctl, see `linux/ethtool.h`
+
+use std::convert::TryInto;
+
+const SIOCETHTOOL: u64 = 0x8946;
+const ETHTOOL_GSTRINGS: u32 = 0x1b;
+const ETHTOOL_GSTATS: u32 = 0x1d;
+const ETHTOOL_GSSET_INFO: u32 = 0x37;
+const ETH_SS_STATS: u32 = 1;
+const ETH_GSTRING_LEN: usize = 32;
+const IFNAMSIZ: usize = 16;
+
+/// Mirrors `struct ifreq` with the `ifr_data` member of its union
+#[repr(C)]
+struct IfReq {
+    name: [u8; IFNAMSIZ],
+    data: *mut libc::c_void,
+    // pads the union to its full size
+    _pad: [u8; 16],
+}
+
+/// Mirrors `struct ethtool_sset_info` with room for a single string set
+#[repr(C)]
+#[derive(Default)]
+struct EthtoolSsetInfo {
+    cmd: u32,
+    reserved: u32,
+    sset_mask: u64,
+    data: u32,
+}
+
+/// An `AF_INET` socket which is used to issue ioctls and is closed on drop
+struct Socket {
+    fd: libc::c_int,
+}
+
+impl Socket {
+    fn new() -> Result<Self, std::io::Error> {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { fd })
+    }
+
+    fn ethtool(&self, interface: &str, data: *mut libc::c_void) -> Result<(), std::io::Error> {
+        if interface.len() >= IFNAMSIZ {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("interface name too long: {}", interface),
+            ));
+        }
+        let mut request = IfReq {
+            name: [0; IFNAMSIZ],
+            data,
+            _pad: [0; 16],
+        };
+        request.name[..interface.len()].copy_from_slice(interface.as_bytes());
+        let result = unsafe { libc::ioctl(self.fd, SIOCETHTOOL as _, &mut request) };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// Returns the names of the driver statistics of an interface, in the order
+/// their values are returned by `stats()`
+pub fn names(interface: &str) -> Result<Vec<String>, std::io::Error> {
+    let socket = Socket::new()?;
+
+    let mut info = EthtoolSsetInfo {
+        cmd: ETHTOOL_GSSET_INFO,
+        sset_mask: 1 << ETH_SS_STATS,
+        ..Default::default()
+    };
+    socket.ethtool(interface, &mut info as *mut _ as *mut libc::c_void)?;
+    if info.sset_mask == 0 {
+        // the driver does not provide statistics
+        return Ok(Vec::new());
+    }
+    let count = info.data as usize;
+
+    // struct ethtool_gstrings is a header of cmd, string_set, and len followed
+    // by the strings
+    let mut buffer = vec![0_u8; 12 + count * ETH_GSTRING_LEN];
+    buffer[0..4].copy_from_slice(&ETHTOOL_GSTRINGS.to_ne_bytes());
+    buffer[4..8].copy_from_slice(&ETH_SS_STATS.to_ne_bytes());
+    buffer[8..12].copy_from_slice(&(count as u32).to_ne_bytes());
+    socket.ethtool(interface, buffer.as_mut_ptr() as *mut libc::c_void)?;
+
+    Ok(buffer[12..]
+        .chunks(ETH_GSTRING_LEN)
+        .map(|name| {
+            let end = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+            String::from_utf8_lossy(&name[..end]).into_owned()
+        })
+        .collect())
+}
+
+/// Returns the values of the driver statistics of an interface
+pub fn stats(interface: &str, count: usize) -> Result<Vec<u64>, std::io::Error> {
+    let socket = Socket::new()?;
+
+    // struct ethtool_stats is a header of cmd and n_stats followed by the
+    // values, the buffer is of u64 so that the values are aligned
+    let mut buffer = vec![0_u64; 1 + count];
+    let header = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u32, 2) };
+    header[0] = ETHTOOL_GSTATS;
+    header[1] = count.try_into().unwrap_or(u32::MAX);
+    socket.ethtool(interface, buffer.as_mut_ptr() as *mut libc::c_void)?;
+
+    Ok(buffer.split_off(1))
+}
+
+/// The direction of traffic through a queue
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Direction {
+    Receive,
+    Transmit,
+}
+
+/// A normalized per-queue statistic
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum QueueField {
+    Bytes,
+    Drops,
+    Packets,
+}
+
+/// Parses a driver statistic name into the queue and field it counts. Each
+/// driver names these differently, the known forms are:
+/// * `rx_queue_0_packets` - ixgbe, i40e, ice, virtio_net
+/// * `rx0_packets` - mlx4, mlx5
+/// * `queue_0_rx_cnt` - ena
+/// * `[0]: rx_ucast_packets` - bnxt
+pub fn parse_queue_stat(name: &str) -> Option<(Direction, usize, QueueField)> {
+    let (direction, queue, field) = if let Some(rest) = name.strip_prefix('[') {
+        let (queue, rest) = rest.split_once("]: ")?;
+        let (direction, field) = rest.split_once('_')?;
+        (direction, queue, field)
+    } else if let Some(rest) = name.strip_prefix("queue_") {
+        let (queue, rest) = rest.split_once('_')?;
+        let (direction, field) = rest.split_once('_')?;
+        (direction, queue, field)
+    } else {
+        let (direction, rest) = if let Some(rest) = name.strip_prefix("rx") {
+            ("rx", rest)
+        } else {
+            ("tx", name.strip_prefix("tx")?)
+        };
+        let rest = rest.strip_prefix("_queue_").unwrap_or(rest);
+        let (queue, field) = rest.split_once('_')?;
+        (direction, queue, field)
+    };
+
+    let direction = match direction {
+        "rx" => Direction::Receive,
+        "tx" => Direction::Transmit,
+        _ => return None,
+    };
+    let queue = queue.parse().ok()?;
+    let field = match field {
+        "packets" | "pkts" | "cnt" | "ucast_packets" => QueueField::Packets,
+        "bytes" | "ucast_bytes" => QueueField::Bytes,
+        "dropped" | "drops" | "drop" | "discards" => QueueField::Drops,
+        _ => return None,
+    };
+    Some((direction, queue, field))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_queue_stat() {
+        assert_eq!(
+            parse_queue_stat("rx_queue_3_packets"),
+            Some((Direction::Receive, 3, QueueField::Packets))
+        );
+        assert_eq!(
+            parse_queue_stat("tx_queue_0_bytes"),
+            Some((Direction::Transmit, 0, QueueField::Bytes))
+        );
+        assert_eq!(
+            parse_queue_stat("rx12_dropped"),
+            Some((Direction::Receive, 12, QueueField::Drops))
+        );
+        assert_eq!(
+            parse_queue_stat("queue_1_tx_cnt"),
+            Some((Direction::Transmit, 1, QueueField::Packets))
+        );
+        assert_eq!(
+            parse_queue_stat("[7]: rx_discards"),
+            Some((Direction::Receive, 7, QueueField::Drops))
+        );
+        assert_eq!(parse_queue_stat("rx_packets"), None);
+        assert_eq!(parse_queue_stat("rx0_csum_none"), None);
+        assert_eq!(parse_queue_stat("tx_timeout"), None);
+    }
+}
@@ -2,12 +2,13 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::time::*;
 use tokio::io::SeekFrom;
 
 use async_trait::async_trait;
+use rustcommon_metrics::{Output, Summary};
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
 
@@ -17,6 +18,7 @@ use crate::samplers::Common;
 use crate::Sampler;
 
 mod config;
+mod ethtool;
 mod stat;
 
 pub use config::*;
@@ -27,6 +29,9 @@ pub struct Network {
     bpf: Option<Arc<Mutex<BPF>>>,
     bpf_last: Arc<Mutex<Instant>>,
     common: Common,
+    /// names of the driver statistics of each interface
+    ethtool_names: HashMap<String, Vec<String>>,
+    interface_statistics: HashSet<InterfaceStatistic>,
     proc_net_dev: Option<File>,
     statistics: Vec<NetworkStatistic>,
 }
@@ -44,6 +49,8 @@ impl Sampler for Network {
             bpf: None,
             bpf_last: Arc::new(Mutex::new(Instant::now())),
             common,
+            ethtool_names: HashMap::new(),
+            interface_statistics: HashSet::new(),
             proc_net_dev: None,
             statistics,
         };
@@ -66,7 +73,7 @@ impl Sampler for Network {
         if common.config().samplers().network().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -90,6 +97,13 @@ impl Sampler for Network {
         self.common.config().samplers().network()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -104,8 +118,16 @@ impl Sampler for Network {
         let result = self.sample_proc_net_dev().await;
         self.map_result(result)?;
 
-        #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+        if self.common.config().samplers().network().queues() {
+            let result = self.sample_interfaces().await;
+            self.map_result(result)?;
+        }
+
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
@@ -113,7 +135,7 @@ impl Sampler for Network {
 
 impl Network {
     // checks that bpf is enabled in config and one or more bpf stats enabled
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn bpf_enabled(&self) -> bool {
         if self.sampler_config().bpf() {
             for statistic in &self.statistics {
@@ -149,6 +171,16 @@ impl Network {
             }
         }
 
+        #[cfg(feature = "bpf_core")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let object = include_bytes!(concat!(env!("OUT_DIR"), "/network.bpf.o"));
+                let bpf = BPF::load("network", object, &[])?;
+                self.bpf = Some(Arc::new(Mutex::new(bpf)));
+            }
+        }
+
         Ok(())
     }
 
@@ -189,25 +221,84 @@ impl Network {
         let time = Instant::now();
         for statistic in &self.statistics {
             if let Some(value) = result.get(statistic) {
-                let _ = self.metrics().record_counter(statistic, time, *value);
+                self.common().record_counter(statistic, time, *value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples per-queue driver statistics with ethtool and the missed frames
+    /// of each interface
+    async fn sample_interfaces(&mut self) -> Result<(), std::io::Error> {
+        let time = Instant::now();
+        for interface in self.common.config().samplers().network().interfaces() {
+            let mut values = HashMap::new();
+
+            if !self.ethtool_names.contains_key(&interface) {
+                // not all drivers provide statistics, those which fail are
+                // remembered as having none
+                let names = ethtool::names(&interface).unwrap_or_else(|e| {
+                    debug!("no ethtool statistics for {}: {}", interface, e);
+                    Vec::new()
+                });
+                self.ethtool_names.insert(interface.clone(), names);
+            }
+            let names = &self.ethtool_names[&interface];
+            if !names.is_empty() {
+                let stats = ethtool::stats(&interface, names.len())?;
+                for (name, value) in names.iter().zip(stats) {
+                    if let Some((direction, queue, field)) = ethtool::parse_queue_stat(name) {
+                        let statistic =
+                            InterfaceStatistic::queue(&interface, direction, queue, field);
+                        *values.entry(statistic).or_insert(0) += value;
+                    }
+                }
+            }
+
+            let path = format!("/sys/class/net/{}/statistics/rx_missed_errors", interface);
+            if let Ok(Ok(value)) = tokio::fs::read_to_string(path)
+                .await
+                .map(|v| v.trim().parse::<u64>())
+            {
+                values.insert(InterfaceStatistic::missed(&interface), value);
+            }
+
+            for (statistic, value) in values {
+                if !self.interface_statistics.contains(&statistic) {
+                    self.register_interface_statistic(&statistic);
+                    self.interface_statistics.insert(statistic.clone());
+                }
+                self.common().record_counter(&statistic, time, value);
             }
         }
         Ok(())
     }
 
-    #[cfg(feature = "bpf")]
+    fn register_interface_statistic(&self, statistic: &InterfaceStatistic) {
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            self.metrics()
+                .add_summary(statistic, Summary::stream(self.samples()));
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+    }
+
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn sample_bpf(&self) -> Result<(), std::io::Error> {
-        if self.bpf_last.lock().unwrap().elapsed()
-            >= Duration::new(self.general_config().window() as u64, 0)
-        {
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
             let time = Instant::now();
             if let Some(ref bpf) = self.bpf {
                 let bpf = bpf.lock().unwrap();
                 for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
-                    if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
-                        for (&value, &count) in &map_from_table(&mut table) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
                             if count > 0 {
-                                let _ = self.metrics().record_bucket(statistic, time, value, count);
+                                self.common().record_bucket(statistic, time, value, count);
                             }
                         }
                     }
@@ -10,6 +10,9 @@ use serde_derive::{Deserialize, Serialize};
 use strum::ParseError;
 use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 
+use super::ethtool::{Direction, QueueField};
+use crate::common::labels::labeled;
+
 #[derive(
     Clone,
     Copy,
@@ -116,3 +119,60 @@ impl TryFrom<&str> for NetworkStatistic {
         NetworkStatistic::from_str(s)
     }
 }
+
+/// A statistic of a single network interface or one of its queues, which are
+/// discovered at runtime
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct InterfaceStatistic {
+    name: String,
+}
+
+impl InterfaceStatistic {
+    /// A per-queue counter, named `network/<interface>/queue<n>/<direction>/<field>`
+    pub fn queue(interface: &str, direction: Direction, queue: usize, field: QueueField) -> Self {
+        let direction = match direction {
+            Direction::Receive => "receive",
+            Direction::Transmit => "transmit",
+        };
+        let field = match field {
+            QueueField::Bytes => "bytes",
+            QueueField::Drops => "drops",
+            QueueField::Packets => "packets",
+        };
+        Self {
+            name: labeled(
+                format!(
+                    "network/{}/queue{}/{}/{}",
+                    interface, queue, direction, field
+                ),
+                format!("network/queue/{}", field),
+                &[
+                    ("interface", interface),
+                    ("queue", &queue.to_string()),
+                    ("direction", direction),
+                ],
+            ),
+        }
+    }
+
+    /// Frames the interface missed because its receive buffers were full
+    pub fn missed(interface: &str) -> Self {
+        Self {
+            name: labeled(
+                format!("network/{}/receive/missed", interface),
+                "network/receive/missed".to_string(),
+                &[("interface", interface)],
+            ),
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for InterfaceStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Counter
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const MOUNTSTATS: &str = "/proc/self/mountstats";
+const PROC_NET_RPC_NFS: &str = "/proc/net/rpc/nfs";
+
+pub struct Nfs {
+    common: Common,
+    /// counters of each (mountpoint, operation) from the previous sample, used
+    /// to find the mean latency of the operations completed since then
+    previous: HashMap<(String, String), Operation>,
+    /// statistics which have been registered, as mounts are discovered at
+    /// runtime
+    registered: HashSet<NfsStatistic>,
+    statistics: Vec<NfsConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Nfs {
+    type Statistic = NfsStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().nfs().statistics.clone();
+        Ok(Self {
+            common,
+            previous: HashMap::new(),
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().nfs().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize nfs sampler {}", e);
+                    } else {
+                        error!("failed to initialize nfs sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().nfs()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().nfs().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_rpc().await;
+        self.map_result(r)?;
+
+        let r = self.sample_mounts().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Nfs {
+    fn record(&mut self, statistic: NfsStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples the host-wide RPC counters of the NFS client
+    async fn sample_rpc(&mut self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| !s.operation()) {
+            return Ok(());
+        }
+        let content = match tokio::fs::read_to_string(PROC_NET_RPC_NFS).await {
+            Ok(content) => content,
+            // the nfs client module is not loaded
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let rpc = match parse_rpc(&content) {
+            Some(rpc) => rpc,
+            None => return Ok(()),
+        };
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            let value = match statistic {
+                NfsConfigStatistic::RpcAuthRefresh => rpc.authrefresh,
+                NfsConfigStatistic::RpcCalls => rpc.calls,
+                NfsConfigStatistic::RpcRetransmits => rpc.retransmits,
+                _ => continue,
+            };
+            self.record(NfsStatistic::host(statistic), time, value);
+        }
+        Ok(())
+    }
+
+    /// Samples the per-operation counters of each NFS mount
+    async fn sample_mounts(&mut self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| s.operation()) {
+            return Ok(());
+        }
+        let content = tokio::fs::read_to_string(MOUNTSTATS).await?;
+        let operations = self.common.config().samplers().nfs().operations().to_vec();
+        let time = Instant::now();
+        let mut current = HashMap::new();
+
+        for mount in parse_mountstats(&content) {
+            for (name, operation) in mount.operations {
+                let name = name.to_lowercase();
+                if !operations.contains(&name) {
+                    continue;
+                }
+                let key = (mount.mountpoint.clone(), name);
+                let previous = self.previous.get(&key).copied();
+                for statistic in self.statistics.clone() {
+                    let value = match statistic {
+                        NfsConfigStatistic::Operations => Some(operation.operations),
+                        NfsConfigStatistic::Retransmits => Some(operation.retransmits()),
+                        NfsConfigStatistic::Timeouts => Some(operation.timeouts),
+                        NfsConfigStatistic::Rtt => previous.and_then(|p| operation.mean_rtt(&p)),
+                        NfsConfigStatistic::Execute => {
+                            previous.and_then(|p| operation.mean_execute(&p))
+                        }
+                        _ => None,
+                    };
+                    if let Some(value) = value {
+                        let metric = NfsStatistic::operation(&key.0, &key.1, statistic);
+                        self.record(metric, time, value);
+                    }
+                }
+                current.insert(key, operation);
+            }
+        }
+
+        // mounts which have gone away are dropped along with their counters
+        self.previous = current;
+        Ok(())
+    }
+}
+
+/// The host-wide counters from the `rpc` line of `/proc/net/rpc/nfs`
+#[derive(Debug, PartialEq)]
+struct Rpc {
+    calls: u64,
+    retransmits: u64,
+    authrefresh: u64,
+}
+
+fn parse_rpc(content: &str) -> Option<Rpc> {
+    let line = content.lines().find(|l| l.starts_with("rpc "))?;
+    let mut values = line.split_whitespace().skip(1).map(|v| v.parse().ok());
+    Some(Rpc {
+        calls: values.next()??,
+        retransmits: values.next()??,
+        authrefresh: values.next()??,
+    })
+}
+
+/// The counters of an operation from the per-op statistics of a mount
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Operation {
+    operations: u64,
+    transmissions: u64,
+    timeouts: u64,
+    /// cumulative round trip time in milliseconds
+    rtt: u64,
+    /// cumulative time from submission to completion in milliseconds
+    execute: u64,
+}
+
+impl Operation {
+    fn retransmits(&self) -> u64 {
+        self.transmissions.saturating_sub(self.operations)
+    }
+
+    /// The mean round trip time, in nanoseconds, of the operations which
+    /// completed since the previous counters were read
+    fn mean_rtt(&self, previous: &Operation) -> Option<u64> {
+        self.mean(previous, self.rtt.wrapping_sub(previous.rtt))
+    }
+
+    /// The mean execution time, in nanoseconds, which includes time spent
+    /// queued in the client, of the operations which completed since the
+    /// previous counters were read
+    fn mean_execute(&self, previous: &Operation) -> Option<u64> {
+        self.mean(previous, self.execute.wrapping_sub(previous.execute))
+    }
+
+    fn mean(&self, previous: &Operation, milliseconds: u64) -> Option<u64> {
+        let operations = self.operations.checked_sub(previous.operations)?;
+        if operations == 0 {
+            return None;
+        }
+        Some(milliseconds.saturating_mul(crate::MILLISECOND) / operations)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Mount {
+    mountpoint: String,
+    operations: HashMap<String, Operation>,
+}
+
+/// Parses the NFS mounts from `/proc/self/mountstats`. Each mount begins with a
+/// `device <export> mounted on <mountpoint> with fstype <type>` line, and for
+/// NFS mounts is followed by lines of the form `<OP>: <counters>` after the
+/// `per-op statistics` line.
+fn parse_mountstats(content: &str) -> Vec<Mount> {
+    let mut mounts = Vec::new();
+    let mut current: Option<Mount> = None;
+    let mut per_op = false;
+    for line in content.lines() {
+        if line.starts_with("device ") {
+            mounts.extend(current.take());
+            per_op = false;
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 8
+                && parts[2] == "mounted"
+                && parts[5] == "with"
+                && (parts[7] == "nfs" || parts[7] == "nfs4")
+            {
+                current = Some(Mount {
+                    mountpoint: parts[4].to_string(),
+                    ..Default::default()
+                });
+            }
+            continue;
+        }
+        let mount = match current.as_mut() {
+            Some(mount) => mount,
+            None => continue,
+        };
+        let line = line.trim();
+        if line == "per-op statistics" {
+            per_op = true;
+            continue;
+        }
+        if !per_op {
+            continue;
+        }
+        // fields are: operations, transmissions, major timeouts, bytes sent,
+        // bytes received, queue time, rtt, execute time, and on newer kernels
+        // errors
+        if let Some((name, values)) = line.split_once(':') {
+            let values: Vec<u64> = values
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            if values.len() >= 8 {
+                mount.operations.insert(
+                    name.to_string(),
+                    Operation {
+                        operations: values[0],
+                        transmissions: values[1],
+                        timeouts: values[2],
+                        rtt: values[6],
+                        execute: values[7],
+                    },
+                );
+            }
+        }
+    }
+    mounts.extend(current);
+    mounts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rpc() {
+        let rpc = parse_rpc(
+            "net 0 0 0 0\n\
+             rpc 123456 78 9\n\
+             proc3 22 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21\n",
+        );
+        assert_eq!(
+            rpc,
+            Some(Rpc {
+                calls: 123456,
+                retransmits: 78,
+                authrefresh: 9,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_mountstats() {
+        let mounts = parse_mountstats(
+            "device rootfs mounted on / with fstype rootfs\n\
+             device proc mounted on /proc with fstype proc\n\
+             device nfs01:/export/data mounted on /mnt/data with fstype nfs4 statvers=1.1\n\
+             \topts:\trw,vers=4.1,rsize=1048576,wsize=1048576\n\
+             \tage:\t3600\n\
+             \tevents:\t1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27\n\
+             \tbytes:\t1000 2000 0 0 1000 2000 10 20\n\
+             \tRPC iostats version: 1.1  p/v: 100003/4 (nfs)\n\
+             \txprt:\ttcp 0 1 2 0 0 1234 1234 0 5678 0 2 0 0\n\
+             \tper-op statistics\n\
+             \t        NULL: 0 0 0 0 0 0 0 0\n\
+             \t        READ: 100 103 1 12000 800000 50 300 400 0\n\
+             \t       WRITE: 20 20 0 900000 2400 10 80 95 0\n\
+             \n\
+             device tmpfs mounted on /tmp with fstype tmpfs\n",
+        );
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].mountpoint, "/mnt/data");
+        assert_eq!(mounts[0].operations.len(), 3);
+        let read = mounts[0].operations["READ"];
+        assert_eq!(read.operations, 100);
+        assert_eq!(read.retransmits(), 3);
+        assert_eq!(read.timeouts, 1);
+        assert_eq!(read.rtt, 300);
+        assert_eq!(read.execute, 400);
+
+        let previous = Operation {
+            operations: 90,
+            rtt: 250,
+            ..read
+        };
+        assert_eq!(read.mean_rtt(&previous), Some(5 * crate::MILLISECOND));
+        assert_eq!(read.mean_rtt(&read), None);
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum NfsConfigStatistic {
+    #[strum(serialize = "rpc/authrefresh")]
+    RpcAuthRefresh,
+    #[strum(serialize = "rpc/calls")]
+    RpcCalls,
+    #[strum(serialize = "rpc/retransmits")]
+    RpcRetransmits,
+    #[strum(serialize = "execute")]
+    Execute,
+    #[strum(serialize = "operations")]
+    Operations,
+    #[strum(serialize = "retransmits")]
+    Retransmits,
+    #[strum(serialize = "rtt")]
+    Rtt,
+    #[strum(serialize = "timeouts")]
+    Timeouts,
+}
+
+impl NfsConfigStatistic {
+    /// Whether this statistic is reported for each operation of each mount,
+    /// rather than for the host
+    pub fn operation(self) -> bool {
+        !matches!(
+            self,
+            Self::RpcAuthRefresh | Self::RpcCalls | Self::RpcRetransmits
+        )
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::Execute | Self::Rtt => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for NfsConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        NfsConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct NfsStatistic {
+    name: String,
+    statistic: NfsConfigStatistic,
+}
+
+impl NfsStatistic {
+    /// A host-wide statistic, which is named `nfs/rpc/...`
+    pub fn host(statistic: NfsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("nfs/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of an operation on a mount, which is named
+    /// `nfs/<mountpoint>/<operation>/...` with the leading `/` of the
+    /// mountpoint removed
+    pub fn operation(mountpoint: &str, operation: &str, statistic: NfsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let mountpoint = match mountpoint.trim_start_matches('/') {
+            "" => "root",
+            mountpoint => mountpoint,
+        };
+        Self {
+            name: labeled(
+                format!("nfs/{}/{}/{}", mountpoint, operation.to_lowercase(), suffix),
+                format!("nfs/{}", suffix),
+                &[
+                    ("mountpoint", mountpoint),
+                    ("operation", &operation.to_lowercase()),
+                ],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> NfsConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for NfsStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
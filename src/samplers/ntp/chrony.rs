@@ -0,0 +1,172 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+//! A minimal client for the chronyd command protocol, which is used to read
+//! the tracking report over the unix domain socket that `chronyc` uses
+
+use std::convert::TryInto;
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::net::UnixDatagram;
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+const PROTOCOL_VERSION: u8 = 6;
+const PKT_TYPE_CMD_REQUEST: u8 = 1;
+const PKT_TYPE_CMD_REPLY: u8 = 2;
+const REQ_TRACKING: u16 = 33;
+const RPY_TRACKING: u16 = 5;
+const STT_SUCCESS: u16 = 0;
+
+/// The length of the reply header, which precedes the data of the reply
+const REPLY_HEADER: usize = 28;
+
+/// The length of the tracking reply, which the request is padded to, as
+/// chronyd does not send replies which are longer than their request
+const TRACKING_REPLY: usize = REPLY_HEADER + 80;
+
+/// The fields of the tracking report which are exported, with times in
+/// seconds
+#[derive(Debug, PartialEq)]
+pub struct Tracking {
+    pub stratum: u16,
+    pub last_offset: f64,
+    pub rms_offset: f64,
+    pub root_delay: f64,
+    pub root_dispersion: f64,
+}
+
+/// Requests the tracking report from chronyd. The reply is sent to the
+/// address of our socket, so it is bound to a path alongside the socket of
+/// chronyd, which requires the same privileges as `chronyc`.
+pub async fn tracking(socket: &Path) -> Result<Tracking, std::io::Error> {
+    let directory = socket.parent().unwrap_or_else(|| Path::new("/"));
+    let local = directory.join(format!("rezolus.{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&local);
+
+    let query = async {
+        let client = UnixDatagram::bind(&local)?;
+        client.connect(socket)?;
+        client.send(&request(REQ_TRACKING)).await?;
+        let mut buffer = [0; 1024];
+        let length = client.recv(&mut buffer).await?;
+        parse_tracking(&buffer[..length]).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "bad tracking reply")
+        })
+    };
+    let result = tokio::time::timeout(SOCKET_TIMEOUT, query)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "chronyd timeout"));
+    let _ = std::fs::remove_file(&local);
+    result?
+}
+
+/// Builds a request for a command which takes no arguments, which is only the
+/// 20 byte header followed by padding
+fn request(command: u16) -> Vec<u8> {
+    let mut request = vec![0; TRACKING_REPLY];
+    request[0] = PROTOCOL_VERSION;
+    request[1] = PKT_TYPE_CMD_REQUEST;
+    request[4..6].copy_from_slice(&command.to_be_bytes());
+    // the sequence number, which is echoed in the reply
+    request[8..12].copy_from_slice(&std::process::id().to_be_bytes());
+    request
+}
+
+/// Parses a tracking reply, returning `None` if it is not a successful reply
+/// to a tracking request
+fn parse_tracking(reply: &[u8]) -> Option<Tracking> {
+    if reply.len() < TRACKING_REPLY
+        || reply[0] != PROTOCOL_VERSION
+        || reply[1] != PKT_TYPE_CMD_REPLY
+        || u16_at(reply, 4)? != REQ_TRACKING
+        || u16_at(reply, 6)? != RPY_TRACKING
+        || u16_at(reply, 8)? != STT_SUCCESS
+    {
+        return None;
+    }
+    let data = &reply[REPLY_HEADER..];
+    Some(Tracking {
+        stratum: u16_at(data, 24)?,
+        last_offset: float_at(data, 44)?,
+        rms_offset: float_at(data, 48)?,
+        root_delay: float_at(data, 64)?,
+        root_dispersion: float_at(data, 68)?,
+    })
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn float_at(data: &[u8], offset: usize) -> Option<f64> {
+    Some(decode_float(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    )))
+}
+
+/// Decodes the floating point format of the chronyd protocol, which has a 7
+/// bit signed exponent and a 25 bit signed coefficient
+fn decode_float(value: u32) -> f64 {
+    const EXP_BITS: u32 = 7;
+    const COEF_BITS: u32 = 25;
+
+    let mut exp = (value >> COEF_BITS) as i32;
+    if exp >= 1 << (EXP_BITS - 1) {
+        exp -= 1 << EXP_BITS;
+    }
+    exp -= COEF_BITS as i32;
+
+    let mut coef = (value % (1 << COEF_BITS)) as i32;
+    if coef >= 1 << (COEF_BITS - 1) {
+        coef -= 1 << COEF_BITS;
+    }
+
+    coef as f64 * 2.0_f64.powi(exp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_float() {
+        assert_eq!(decode_float(0), 0.0);
+        // an exponent of 1 and coefficient of 2^23 is 2^23 * 2^(1 - 25)
+        assert_eq!(decode_float(1 << 25 | 1 << 23), 0.5);
+        // a coefficient with the sign bit set is negative
+        assert_eq!(decode_float(1 << 25 | 0x1800000), -0.5);
+        // an exponent of -10 scales the coefficient down further
+        assert_eq!(decode_float(0x76 << 25 | 1 << 23), 2.0_f64.powi(-12));
+    }
+
+    #[test]
+    fn test_parse_tracking() {
+        let mut reply = vec![0; TRACKING_REPLY];
+        reply[0] = PROTOCOL_VERSION;
+        reply[1] = PKT_TYPE_CMD_REPLY;
+        reply[4..6].copy_from_slice(&REQ_TRACKING.to_be_bytes());
+        reply[6..8].copy_from_slice(&RPY_TRACKING.to_be_bytes());
+        let data = REPLY_HEADER;
+        reply[data + 24..data + 26].copy_from_slice(&3_u16.to_be_bytes());
+        reply[data + 44..data + 48].copy_from_slice(&(1_u32 << 25 | 1 << 23).to_be_bytes());
+        assert_eq!(
+            parse_tracking(&reply),
+            Some(Tracking {
+                stratum: 3,
+                last_offset: 0.5,
+                rms_offset: 0.0,
+                root_delay: 0.0,
+                root_dispersion: 0.0,
+            })
+        );
+
+        reply[8..10].copy_from_slice(&1_u16.to_be_bytes());
+        assert_eq!(parse_tracking(&reply), None);
+        assert_eq!(parse_tracking(&reply[..REPLY_HEADER]), None);
+    }
+}
@@ -5,48 +5,79 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct NtpConfig {
+    #[serde(default = "default_chrony_socket")]
+    chrony_socket: String,
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<NtpStatistic>,
 }
 
 impl Default for NtpConfig {
     fn default() -> Self {
         Self {
+            chrony_socket: default_chrony_socket(),
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             statistics: default_statistics(),
         }
     }
 }
 
+fn default_chrony_socket() -> String {
+    "/var/run/chrony/chronyd.sock".to_string()
+}
+
 fn default_statistics() -> Vec<NtpStatistic> {
     NtpStatistic::iter().collect()
 }
 
+impl NtpConfig {
+    /// The command socket of chronyd, which is skipped if it does not exist
+    pub fn chrony_socket(&self) -> &str {
+        &self.chrony_socket
+    }
+}
+
 impl SamplerConfig for NtpConfig {
     type Statistic = NtpStatistic;
     fn enabled(&self) -> bool {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
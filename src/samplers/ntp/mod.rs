@@ -2,6 +2,7 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+use std::path::Path;
 use std::time::*;
 
 use async_trait::async_trait;
@@ -11,14 +12,20 @@ use crate::config::SamplerConfig;
 use crate::samplers::Common;
 use crate::Sampler;
 
+mod chrony;
 mod config;
 mod stat;
 
 pub use config::*;
 pub use stat::*;
 
+const CURRENT_CLOCKSOURCE: &str =
+    "/sys/devices/system/clocksource/clocksource0/current_clocksource";
+
 #[allow(dead_code)]
 pub struct Ntp {
+    clocksource: Option<String>,
+    clocksource_changes: u64,
     common: Common,
     statistics: Vec<NtpStatistic>,
 }
@@ -30,7 +37,12 @@ impl Sampler for Ntp {
     fn new(common: Common) -> Result<Self, anyhow::Error> {
         let statistics = common.config().samplers().ntp().statistics();
         #[allow(unused_mut)]
-        let mut sampler = Self { common, statistics };
+        let mut sampler = Self {
+            clocksource: None,
+            clocksource_changes: 0,
+            common,
+            statistics,
+        };
 
         if sampler.sampler_config().enabled() {
             sampler.register();
@@ -45,7 +57,7 @@ impl Sampler for Ntp {
             debug!("sampler is enabled");
             if let Ok(mut ntp) = Ntp::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !ntp.retired() {
                         let _ = ntp.sample().await;
                     }
                 });
@@ -69,6 +81,13 @@ impl Sampler for Ntp {
         self.common.config().samplers().ntp()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -83,6 +102,21 @@ impl Sampler for Ntp {
         let r = self.sample_ntp_adjtime().await;
         self.map_result(r)?;
 
+        if self.statistics.iter().any(|s| s.chrony()) {
+            self.sample_chrony().await;
+        }
+
+        if self.statistics.contains(&NtpStatistic::ClocksourceChanges) {
+            let r = self.sample_clocksource().await;
+            self.map_result(r)?;
+        }
+
+        #[cfg(all(target_os = "linux", not(target_env = "musl")))]
+        if self.statistics.contains(&NtpStatistic::PtpOffset) {
+            let r = self.sample_ptp().await;
+            self.map_result(r)?;
+        }
+
         Ok(())
     }
 }
@@ -115,6 +149,121 @@ impl Ntp {
         // TODO: implement NTP sampling for musl
         Ok(())
     }
+
+    /// Reads the tracking report of chronyd. Hosts which do not run chronyd
+    /// are skipped, and failures are not fatal, as chronyd may be restarted.
+    async fn sample_chrony(&mut self) {
+        let socket = Path::new(self.common.config().samplers().ntp().chrony_socket());
+        if !socket.exists() {
+            return;
+        }
+        let tracking = match chrony::tracking(socket).await {
+            Ok(tracking) => tracking,
+            Err(e) => {
+                debug!("failed to query chronyd: {}", e);
+                return;
+            }
+        };
+        let nanos = |seconds: f64| (seconds.abs() * SECOND as f64).round() as u64;
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            let value = match statistic {
+                NtpStatistic::ChronyJitter => nanos(tracking.rms_offset),
+                NtpStatistic::ChronyOffset => nanos(tracking.last_offset),
+                NtpStatistic::ChronyRootDelay => nanos(tracking.root_delay),
+                NtpStatistic::ChronyRootDispersion => nanos(tracking.root_dispersion),
+                NtpStatistic::ChronyStratum => tracking.stratum as u64,
+                _ => continue,
+            };
+            let _ = self.metrics().record_gauge(statistic, time, value);
+        }
+    }
+
+    /// Counts the changes of the current clocksource, such as when the kernel
+    /// marks the TSC as unstable and falls back to a slower clocksource
+    async fn sample_clocksource(&mut self) -> Result<(), std::io::Error> {
+        let current = tokio::fs::read_to_string(CURRENT_CLOCKSOURCE).await?;
+        let current = current.trim();
+        if let Some(previous) = &self.clocksource {
+            if previous != current {
+                info!("clocksource changed from {} to {}", previous, current);
+                self.clocksource_changes += 1;
+            }
+        }
+        self.clocksource = Some(current.to_string());
+        self.common().record_counter(
+            &NtpStatistic::ClocksourceChanges,
+            Instant::now(),
+            self.clocksource_changes,
+        );
+        Ok(())
+    }
+
+    /// Compares each PTP hardware clock with the system clock, and records
+    /// the largest absolute offset. PHCs are expected to be kept in TAI, as
+    /// they are by ptp4l, so the kernel's TAI offset is applied.
+    #[cfg(all(target_os = "linux", not(target_env = "musl")))]
+    async fn sample_ptp(&mut self) -> Result<(), std::io::Error> {
+        let mut timeval = default_ntptimeval();
+        let tai = if unsafe { libc::ntp_gettime(&mut timeval) } >= 0 {
+            timeval.tai as i64
+        } else {
+            0
+        };
+
+        let mut offset = None;
+        let mut entries = tokio::fs::read_dir("/dev").await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_name().to_string_lossy().starts_with("ptp") {
+                continue;
+            }
+            let file = match std::fs::File::open(entry.path()) {
+                Ok(file) => file,
+                Err(e) => {
+                    debug!("failed to open {}: {}", entry.path().display(), e);
+                    continue;
+                }
+            };
+            if let Some(value) = phc_offset(&file, tai) {
+                offset = Some(offset.unwrap_or(0).max(value));
+            }
+        }
+
+        if let Some(offset) = offset {
+            let _ = self
+                .metrics()
+                .record_gauge(&NtpStatistic::PtpOffset, Instant::now(), offset);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the absolute offset, in nanoseconds, between a PTP hardware clock
+/// and the system clock. The PHC is read between two reads of the system
+/// clock, and compared with their midpoint.
+#[cfg(all(target_os = "linux", not(target_env = "musl")))]
+fn phc_offset(file: &std::fs::File, tai: i64) -> Option<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    // the dynamic clock id of an open PHC device, as FD_TO_CLOCKID()
+    let clock = (!file.as_raw_fd() << 3) | 3;
+    let mut before = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let mut phc = before;
+    let mut after = before;
+    unsafe {
+        if libc::clock_gettime(libc::CLOCK_REALTIME, &mut before) != 0
+            || libc::clock_gettime(clock, &mut phc) != 0
+            || libc::clock_gettime(libc::CLOCK_REALTIME, &mut after) != 0
+        {
+            return None;
+        }
+    }
+    let nanos = |t: &libc::timespec| t.tv_sec as i128 * SECOND as i128 + t.tv_nsec as i128;
+    let system = (nanos(&before) + nanos(&after)) / 2 + tai as i128 * SECOND as i128;
+    Some((nanos(&phc) - system).unsigned_abs() as u64)
 }
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
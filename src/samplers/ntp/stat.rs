@@ -25,10 +25,38 @@ use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 )]
 #[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
 pub enum NtpStatistic {
+    #[strum(serialize = "ntp/chrony/jitter")]
+    ChronyJitter,
+    #[strum(serialize = "ntp/chrony/offset")]
+    ChronyOffset,
+    #[strum(serialize = "ntp/chrony/root_delay")]
+    ChronyRootDelay,
+    #[strum(serialize = "ntp/chrony/root_dispersion")]
+    ChronyRootDispersion,
+    #[strum(serialize = "ntp/chrony/stratum")]
+    ChronyStratum,
+    #[strum(serialize = "ntp/clocksource/changes")]
+    ClocksourceChanges,
     #[strum(serialize = "ntp/estimated_error")]
     EstimatedError,
     #[strum(serialize = "ntp/maximum_error")]
     MaximumError,
+    #[strum(serialize = "ntp/ptp/offset")]
+    PtpOffset,
+}
+
+impl NtpStatistic {
+    /// Whether this statistic is read from chronyd
+    pub fn chrony(self) -> bool {
+        matches!(
+            self,
+            Self::ChronyJitter
+                | Self::ChronyOffset
+                | Self::ChronyRootDelay
+                | Self::ChronyRootDispersion
+                | Self::ChronyStratum
+        )
+    }
 }
 
 impl TryFrom<&str> for NtpStatistic {
@@ -45,6 +73,9 @@ impl Statistic<AtomicU64, AtomicU32> for NtpStatistic {
     }
 
     fn source(&self) -> Source {
-        Source::Gauge
+        match self {
+            Self::ClocksourceChanges => Source::Counter,
+            _ => Source::Gauge,
+        }
     }
 }
@@ -0,0 +1,232 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const NODE_PATH: &str = "/sys/devices/system/node";
+
+pub struct Numa {
+    common: Common,
+    nodes: Vec<u64>,
+    statistics: Vec<NumaConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Numa {
+    type Statistic = NumaStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().numa().statistics.clone();
+        let nodes = discover_nodes()?;
+        if nodes.is_empty() {
+            return Err(format_err!("no numa nodes found"));
+        }
+        let sampler = Self {
+            common,
+            nodes,
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register_nodes();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().numa().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize numa sampler {}", e);
+                    } else {
+                        error!("failed to initialize numa sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().numa()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().numa().statistics.clone();
+        if self.enabled() {
+            self.register_nodes();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_nodes().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Numa {
+    fn register_nodes(&self) {
+        let percentiles = self.sampler_config().percentiles();
+        for node in &self.nodes {
+            for statistic in &self.statistics {
+                let statistic = NumaStatistic::new(*node, *statistic);
+                self.metrics().register(&statistic);
+                self.metrics().add_output(&statistic, Output::Reading);
+                if !percentiles.is_empty() {
+                    self.metrics()
+                        .add_summary(&statistic, Summary::stream(self.samples()));
+                }
+                for percentile in percentiles {
+                    self.metrics()
+                        .add_output(&statistic, Output::Percentile(*percentile));
+                }
+            }
+        }
+    }
+
+    async fn sample_nodes(&self) -> Result<(), std::io::Error> {
+        let files: HashSet<&'static str> = self.statistics.iter().map(|s| s.keys().0).collect();
+
+        for node in &self.nodes {
+            let mut result = HashMap::<&'static str, HashMap<String, u64>>::new();
+            for file in &files {
+                let path = format!("{}/node{}/{}", NODE_PATH, node, file);
+                let content = tokio::fs::read_to_string(path).await?;
+                let parsed = match *file {
+                    "meminfo" => parse_meminfo(&content),
+                    _ => parse_numastat(&content),
+                };
+                result.insert(file, parsed);
+            }
+
+            let time = Instant::now();
+            for statistic in &self.statistics {
+                let (file, key) = statistic.keys();
+                if let Some(value) = result.get(file).and_then(|v| v.get(key)) {
+                    let metric = NumaStatistic::new(*node, *statistic);
+                    match statistic.source() {
+                        Source::Counter => {
+                            self.common().record_counter(&metric, time, *value);
+                        }
+                        Source::Gauge => {
+                            let _ = self.metrics().record_gauge(&metric, time, *value);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn discover_nodes() -> Result<Vec<u64>, std::io::Error> {
+    let mut nodes = Vec::new();
+    for entry in std::fs::read_dir(NODE_PATH)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(Ok(node)) = name.strip_prefix("node").map(|v| v.parse()) {
+            nodes.push(node);
+        }
+    }
+    nodes.sort_unstable();
+    Ok(nodes)
+}
+
+/// Parses `numastat`, which has one `key value` pair per line
+fn parse_numastat(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(key), Some(Ok(value))) = (parts.next(), parts.next().map(|v| v.parse())) {
+            result.insert(key.to_string(), value);
+        }
+    }
+    result
+}
+
+/// Parses the per-node `meminfo`, which has lines of the form
+/// `Node 0 MemFree:  1234 kB`. Values are converted to bytes.
+fn parse_meminfo(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 4 {
+            continue;
+        }
+        let key = parts[2].trim_end_matches(':');
+        if let Ok(value) = parts[3].parse::<u64>() {
+            let multiplier = if parts.get(4) == Some(&"kB") { 1024 } else { 1 };
+            result.insert(key.to_string(), value * multiplier);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_numastat() {
+        let result = parse_numastat(
+            "numa_hit 1234\nnuma_miss 5\nnuma_foreign 6\ninterleave_hit 7\n\
+             local_node 1200\nother_node 34\n",
+        );
+        assert_eq!(result.len(), 6);
+        assert_eq!(result.get("numa_hit"), Some(&1234));
+        assert_eq!(result.get("other_node"), Some(&34));
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let result = parse_meminfo(
+            "Node 0 MemTotal:       32768 kB\nNode 0 MemFree:        1024 kB\n\
+             Node 0 HugePages_Total:     0\n",
+        );
+        assert_eq!(result.get("MemTotal"), Some(&(32768 * 1024)));
+        assert_eq!(result.get("MemFree"), Some(&(1024 * 1024)));
+        assert_eq!(result.get("HugePages_Total"), Some(&0));
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum NumaConfigStatistic {
+    #[strum(serialize = "hit")]
+    Hit,
+    #[strum(serialize = "miss")]
+    Miss,
+    #[strum(serialize = "foreign")]
+    Foreign,
+    #[strum(serialize = "interleave/hit")]
+    InterleaveHit,
+    #[strum(serialize = "local")]
+    Local,
+    #[strum(serialize = "other")]
+    Other,
+    #[strum(serialize = "memory/total")]
+    MemoryTotal,
+    #[strum(serialize = "memory/free")]
+    MemoryFree,
+    #[strum(serialize = "memory/used")]
+    MemoryUsed,
+    #[strum(serialize = "memory/file")]
+    MemoryFile,
+    #[strum(serialize = "memory/anon")]
+    MemoryAnon,
+}
+
+impl NumaConfigStatistic {
+    /// The file within the node directory and the key within that file which
+    /// provide the value for this statistic
+    pub fn keys(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Hit => ("numastat", "numa_hit"),
+            Self::Miss => ("numastat", "numa_miss"),
+            Self::Foreign => ("numastat", "numa_foreign"),
+            Self::InterleaveHit => ("numastat", "interleave_hit"),
+            Self::Local => ("numastat", "local_node"),
+            Self::Other => ("numastat", "other_node"),
+            Self::MemoryTotal => ("meminfo", "MemTotal"),
+            Self::MemoryFree => ("meminfo", "MemFree"),
+            Self::MemoryUsed => ("meminfo", "MemUsed"),
+            Self::MemoryFile => ("meminfo", "FilePages"),
+            Self::MemoryAnon => ("meminfo", "AnonPages"),
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self.keys().0 {
+            "meminfo" => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for NumaConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        NumaConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct NumaStatistic {
+    name: String,
+    statistic: NumaConfigStatistic,
+}
+
+impl NumaStatistic {
+    pub fn new(node: u64, statistic: NumaConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("numa/node{}/{}", node, suffix),
+                format!("numa/{}", suffix),
+                &[("node", &node.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> NumaConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for NumaStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
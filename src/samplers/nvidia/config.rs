@@ -6,7 +6,7 @@ use nvml_wrapper::NVML;
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -16,20 +16,32 @@ pub struct NvidiaConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     pub(crate) statistics: Vec<NvidiaConfigStatistic>,
+    #[serde(default = "default_top_processes")]
+    top_processes: usize,
 }
 
 impl Default for NvidiaConfig {
     fn default() -> Self {
         Self {
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             statistics: default_statistics(),
+            top_processes: default_top_processes(),
         }
     }
 }
@@ -38,6 +50,18 @@ fn default_statistics() -> Vec<NvidiaConfigStatistic> {
     NvidiaConfigStatistic::iter().collect()
 }
 
+fn default_top_processes() -> usize {
+    10
+}
+
+impl NvidiaConfig {
+    /// Number of processes on each GPU, ranked by GPU memory usage, which are
+    /// reported each interval
+    pub fn top_processes(&self) -> usize {
+        self.top_processes
+    }
+}
+
 impl SamplerConfig for NvidiaConfig {
     type Statistic = NvidiaStatistic;
 
@@ -45,10 +69,18 @@ impl SamplerConfig for NvidiaConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
@@ -129,6 +161,21 @@ impl SamplerConfig for NvidiaConfig {
                         NvidiaConfigStatistic::ProcessesCompute => {
                             enabled.push(NvidiaStatistic::ProcessesCompute(id));
                         }
+                        NvidiaConfigStatistic::NvlinkRxBytes => {
+                            enabled.push(NvidiaStatistic::NvlinkRxBytes(id));
+                        }
+                        NvidiaConfigStatistic::NvlinkTxBytes => {
+                            enabled.push(NvidiaStatistic::NvlinkTxBytes(id));
+                        }
+                        NvidiaConfigStatistic::XidErrors => {
+                            enabled.push(NvidiaStatistic::XidErrors(id));
+                        }
+                        // MIG instances and processes are discovered at
+                        // runtime, they are registered as they are found
+                        NvidiaConfigStatistic::MigGpuUtilization
+                        | NvidiaConfigStatistic::MigMemoryFbTotal
+                        | NvidiaConfigStatistic::MigMemoryFbUsed
+                        | NvidiaConfigStatistic::ProcessesMemoryUsed => {}
                     }
                 }
             }
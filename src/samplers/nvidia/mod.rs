@@ -2,12 +2,19 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::os::unix::fs::OpenOptionsExt;
 use std::time::*;
 
 use async_trait::async_trait;
 use nvml_wrapper::enum_wrappers::device::*;
-use nvml_wrapper::NVML;
+use nvml_wrapper::enums::device::{SampleValue, UsedGpuMemory};
+use nvml_wrapper::structs::device::FieldId;
+use nvml_wrapper::{Device, NVML};
+use rustcommon_metrics::*;
 
 use crate::config::SamplerConfig;
 use crate::samplers::Common;
@@ -19,11 +26,25 @@ mod stat;
 pub use config::*;
 pub use stat::*;
 
+const DEV_KMSG: &str = "/dev/kmsg";
+
+// NVML field ids of the NVLink data throughput counters, in KiB, which are
+// summed across all links of a GPU
+const NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_TX: u32 = 138;
+const NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_RX: u32 = 139;
+
 #[allow(dead_code)]
 pub struct Nvidia {
     common: Common,
+    kmsg: Option<File>,
     nvml: NVML,
+    /// statistics of MIG instances and processes which have been registered,
+    /// as they are discovered at runtime
+    registered: HashSet<NvidiaStatistic>,
     statistics: Vec<NvidiaStatistic>,
+    /// XID errors which have been logged by the driver, by the PCI domain,
+    /// bus, and device of the GPU
+    xid_errors: HashMap<(u32, u32, u32), u64>,
 }
 
 #[async_trait]
@@ -37,8 +58,11 @@ impl Sampler for Nvidia {
                 #[allow(unused_mut)]
                 let mut sampler = Self {
                     common,
+                    kmsg: None,
                     nvml,
+                    registered: HashSet::new(),
                     statistics,
+                    xid_errors: HashMap::new(),
                 };
 
                 if sampler.sampler_config().enabled() {
@@ -57,7 +81,7 @@ impl Sampler for Nvidia {
             debug!("sampler is enabled");
             if let Ok(mut sampler) = Nvidia::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -81,6 +105,15 @@ impl Sampler for Nvidia {
         self.common.config().samplers().nvidia()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        // register MIG instances and processes on the next sample
+        self.registered.clear();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -92,6 +125,18 @@ impl Sampler for Nvidia {
 
         debug!("sampling");
 
+        if self
+            .common
+            .config()
+            .samplers()
+            .nvidia()
+            .statistics
+            .contains(&NvidiaConfigStatistic::XidErrors)
+        {
+            let r = self.sample_xid();
+            self.map_result(r)?;
+        }
+
         let r = self.sample_nvml().await;
         self.map_result(r)?;
 
@@ -100,10 +145,75 @@ impl Sampler for Nvidia {
 }
 
 impl Nvidia {
+    fn record(&mut self, statistic: NvidiaStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Counts the XID errors which the driver has written to the kernel log
+    /// since the last sample, starting from the end of the log when it is
+    /// first opened. XID errors are not exposed through NVML without holding
+    /// an event set for the lifetime of the sampler.
+    fn sample_xid(&mut self) -> Result<(), std::io::Error> {
+        if self.kmsg.is_none() {
+            // the kernel log may not be readable, which should not prevent
+            // the remaining GPU statistics from being sampled
+            let mut kmsg = match std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(DEV_KMSG)
+            {
+                Ok(kmsg) => kmsg,
+                Err(e) => {
+                    debug!("failed to open {}: {}", DEV_KMSG, e);
+                    return Ok(());
+                }
+            };
+            kmsg.seek(SeekFrom::End(0))?;
+            self.kmsg = Some(kmsg);
+        }
+        let mut buffer = [0_u8; 8192];
+        loop {
+            let length = match self.kmsg.as_mut().unwrap().read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => length,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // records were overwritten before they were read, and the
+                // next read continues from the oldest remaining record
+                Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+                Err(e) => return Err(e),
+            };
+            let record = String::from_utf8_lossy(&buffer[..length]);
+            if let Some((_, message)) = record.split_once(';') {
+                if let Some(bus) = parse_xid(message.lines().next().unwrap_or("")) {
+                    *self.xid_errors.entry(bus).or_default() += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
     async fn sample_nvml(&mut self) -> Result<(), std::io::Error> {
         let time = Instant::now();
         let devices = self.nvml.device_count().unwrap_or(0);
         let statistics = &self.common.config().samplers().nvidia().statistics;
+        let top_processes = self.common.config().samplers().nvidia().top_processes();
+        // readings of MIG instances and processes, which are recorded once the
+        // devices are released
+        let mut scoped = Vec::new();
         for id in 0..devices {
             if let Ok(device) = self.nvml.device_by_index(id) {
                 for statistic in statistics {
@@ -125,7 +235,7 @@ impl Nvidia {
                                     0_u32
                                 }
                             }) {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::MemoryEccEnabled(id),
                                     time,
                                     value.into(),
@@ -136,7 +246,7 @@ impl Nvidia {
                             if let Ok(value) = device
                                 .total_ecc_errors(MemoryError::Corrected, EccCounter::Aggregate)
                             {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::MemoryEccSbe(id),
                                     time,
                                     value.into(),
@@ -147,7 +257,7 @@ impl Nvidia {
                             if let Ok(value) = device
                                 .total_ecc_errors(MemoryError::Uncorrected, EccCounter::Aggregate)
                             {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::MemoryEccDbe(id),
                                     time,
                                     value.into(),
@@ -204,7 +314,7 @@ impl Nvidia {
                         }
                         NvidiaConfigStatistic::PcieReplay => {
                             if let Ok(value) = device.pcie_replay_counter() {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::PcieReplay(id),
                                     time,
                                     value.into(),
@@ -213,7 +323,7 @@ impl Nvidia {
                         }
                         NvidiaConfigStatistic::PcieRxThroughput => {
                             if let Ok(value) = device.pcie_throughput(PcieUtilCounter::Receive) {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::PcieRxThroughput(id),
                                     time,
                                     value.into(),
@@ -222,7 +332,7 @@ impl Nvidia {
                         }
                         NvidiaConfigStatistic::PcieTxThroughput => {
                             if let Ok(value) = device.pcie_throughput(PcieUtilCounter::Send) {
-                                let _ = self.metrics().record_counter(
+                                self.common().record_counter(
                                     &NvidiaStatistic::PcieTxThroughput(id),
                                     time,
                                     value.into(),
@@ -340,11 +450,195 @@ impl Nvidia {
                                 );
                             }
                         }
+                        NvidiaConfigStatistic::ProcessesMemoryUsed => {
+                            if let Ok(processes) = device.running_compute_processes() {
+                                let processes = processes
+                                    .iter()
+                                    .filter_map(|p| match p.used_gpu_memory {
+                                        UsedGpuMemory::Used(bytes) => {
+                                            Some((process_name(p.pid), bytes))
+                                        }
+                                        UsedGpuMemory::Unavailable => None,
+                                    })
+                                    .collect();
+                                for (name, bytes) in rank_processes(processes, top_processes) {
+                                    scoped.push((
+                                        NvidiaStatistic::process(id, &name, *statistic),
+                                        bytes,
+                                    ));
+                                }
+                            }
+                        }
+                        NvidiaConfigStatistic::MigGpuUtilization
+                        | NvidiaConfigStatistic::MigMemoryFbTotal
+                        | NvidiaConfigStatistic::MigMemoryFbUsed => {
+                            // MIG instances are only present when MIG mode is
+                            // enabled, and are sampled below
+                        }
+                        NvidiaConfigStatistic::NvlinkRxBytes => {
+                            if let Some(value) =
+                                field_value(&device, NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_RX)
+                            {
+                                self.common().record_counter(
+                                    &NvidiaStatistic::NvlinkRxBytes(id),
+                                    time,
+                                    value * 1024,
+                                );
+                            }
+                        }
+                        NvidiaConfigStatistic::NvlinkTxBytes => {
+                            if let Some(value) =
+                                field_value(&device, NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_TX)
+                            {
+                                self.common().record_counter(
+                                    &NvidiaStatistic::NvlinkTxBytes(id),
+                                    time,
+                                    value * 1024,
+                                );
+                            }
+                        }
+                        NvidiaConfigStatistic::XidErrors => {
+                            if let Some(bus) =
+                                device.pci_info().ok().and_then(|p| parse_bus_id(&p.bus_id))
+                            {
+                                let value = self.xid_errors.get(&bus).copied().unwrap_or(0);
+                                self.common().record_counter(
+                                    &NvidiaStatistic::XidErrors(id),
+                                    time,
+                                    value,
+                                );
+                            }
+                        }
                     }
                 }
+                scoped.extend(sample_mig(id, &device, statistics));
             }
         }
 
+        for (statistic, value) in scoped {
+            self.record(statistic, time, value);
+        }
+
         Ok(())
     }
 }
+
+/// Samples the utilization and memory of each MIG instance of a GPU. GPUs
+/// without MIG support or with MIG disabled have no instances.
+fn sample_mig(
+    id: u32,
+    device: &Device,
+    statistics: &[NvidiaConfigStatistic],
+) -> Vec<(NvidiaStatistic, u64)> {
+    let mut readings = Vec::new();
+    if !statistics.iter().any(|s| s.mig()) {
+        return readings;
+    }
+    let count = device.max_mig_device_count().unwrap_or(0);
+    for instance in 0..count {
+        let mig = match device.mig_device_by_index(instance) {
+            Ok(mig) => mig,
+            Err(_) => continue,
+        };
+        for statistic in statistics {
+            let value = match statistic {
+                NvidiaConfigStatistic::MigGpuUtilization => {
+                    mig.utilization_rates().ok().map(|v| v.gpu.into())
+                }
+                NvidiaConfigStatistic::MigMemoryFbTotal => mig.memory_info().ok().map(|v| v.total),
+                NvidiaConfigStatistic::MigMemoryFbUsed => mig.memory_info().ok().map(|v| v.used),
+                _ => None,
+            };
+            if let Some(value) = value {
+                readings.push((NvidiaStatistic::mig(id, instance, *statistic), value));
+            }
+        }
+    }
+    readings
+}
+
+/// Reads a field value of a device, such as an NVLink throughput counter
+fn field_value(device: &Device, field: u32) -> Option<u64> {
+    let samples = device.field_values_for(&[FieldId(field)]).ok()?;
+    match samples.into_iter().next()?.ok()?.value.ok()? {
+        SampleValue::U64(value) => Some(value),
+        SampleValue::U32(value) => Some(value.into()),
+        _ => None,
+    }
+}
+
+/// The name of a process, which processes on the GPU are grouped by
+fn process_name(pid: u32) -> String {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Sums the GPU memory used by processes with the same name, and returns the
+/// names using the most memory
+fn rank_processes(processes: Vec<(String, u64)>, top: usize) -> Vec<(String, u64)> {
+    let mut totals = HashMap::<String, u64>::new();
+    for (name, bytes) in processes {
+        *totals.entry(name).or_default() += bytes;
+    }
+    let mut ranked: Vec<(String, u64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top);
+    ranked
+}
+
+/// Parses the PCI domain, bus, and device of a GPU from its bus id, such as
+/// `00000000:3B:00.0` as reported by NVML or `0000:3b:00` as logged by the
+/// driver
+fn parse_bus_id(bus_id: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = bus_id.trim().split(':');
+    let domain = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let bus = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let device = parts.next()?.split('.').next()?;
+    let device = u32::from_str_radix(device, 16).ok()?;
+    Some((domain, bus, device))
+}
+
+/// Parses an XID error logged by the driver, such as
+/// `NVRM: Xid (PCI:0000:3b:00): 79, pid=1234, GPU has fallen off the bus.`,
+/// returning the PCI location of the GPU
+fn parse_xid(message: &str) -> Option<(u32, u32, u32)> {
+    let location = message.strip_prefix("NVRM: Xid (PCI:")?;
+    let (bus_id, _) = location.split_once(')')?;
+    parse_bus_id(bus_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_xid() {
+        assert_eq!(
+            parse_xid("NVRM: Xid (PCI:0000:3b:00): 79, pid=1234, GPU has fallen off the bus."),
+            Some((0, 0x3b, 0))
+        );
+        assert_eq!(
+            parse_xid("NVRM: loading NVIDIA UNIX x86_64 Kernel Module"),
+            None
+        );
+        assert_eq!(parse_bus_id("00000000:3B:00.0"), Some((0, 0x3b, 0)));
+    }
+
+    #[test]
+    fn test_rank_processes() {
+        let processes = vec![
+            ("python".to_string(), 4 << 30),
+            ("trainer".to_string(), 6 << 30),
+            ("python".to_string(), 3 << 30),
+            ("nvidia-smi".to_string(), 1 << 20),
+        ];
+        assert_eq!(
+            rank_processes(processes, 2),
+            vec![
+                ("python".to_string(), 7 << 30),
+                ("trainer".to_string(), 6 << 30)
+            ]
+        );
+    }
+}
@@ -10,6 +10,8 @@ use serde_derive::{Deserialize, Serialize};
 use strum::ParseError;
 use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 
+use crate::common::labels::labeled;
+
 #[derive(
     Clone,
     Copy,
@@ -71,6 +73,31 @@ pub enum NvidiaConfigStatistic {
     MemoryRetiredPending,
     #[strum(serialize = "processes/compute")]
     ProcessesCompute,
+    #[strum(serialize = "processes/memory/used")]
+    ProcessesMemoryUsed,
+    #[strum(serialize = "mig/gpu/utilization")]
+    MigGpuUtilization,
+    #[strum(serialize = "mig/memory/fb/total")]
+    MigMemoryFbTotal,
+    #[strum(serialize = "mig/memory/fb/used")]
+    MigMemoryFbUsed,
+    #[strum(serialize = "nvlink/rx/bytes")]
+    NvlinkRxBytes,
+    #[strum(serialize = "nvlink/tx/bytes")]
+    NvlinkTxBytes,
+    #[strum(serialize = "xid/errors")]
+    XidErrors,
+}
+
+impl NvidiaConfigStatistic {
+    /// Statistics of MIG instances, which are reported for each instance of
+    /// a GPU with MIG mode enabled
+    pub fn mig(self) -> bool {
+        matches!(
+            self,
+            Self::MigGpuUtilization | Self::MigMemoryFbTotal | Self::MigMemoryFbUsed
+        )
+    }
 }
 
 impl TryFrom<&str> for NvidiaConfigStatistic {
@@ -81,7 +108,7 @@ impl TryFrom<&str> for NvidiaConfigStatistic {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum NvidiaStatistic {
     GpuTemperature(u32),
     MemoryEccSbe(u32),
@@ -106,6 +133,57 @@ pub enum NvidiaStatistic {
     MemoryRetiredDbe(u32),
     MemoryRetiredPending(u32),
     ProcessesCompute(u32),
+    NvlinkRxBytes(u32),
+    NvlinkTxBytes(u32),
+    XidErrors(u32),
+    /// A statistic of a MIG instance or process of a GPU
+    Scoped {
+        name: String,
+        statistic: NvidiaConfigStatistic,
+    },
+}
+
+impl NvidiaStatistic {
+    /// A statistic of a MIG instance, which is named
+    /// `nvidia/gpu_<id>/mig_<instance>/...`
+    pub fn mig(id: u32, instance: u32, statistic: NvidiaConfigStatistic) -> Self {
+        let name: &str = statistic.into();
+        Self::Scoped {
+            name: labeled(
+                format!(
+                    "nvidia/gpu_{}/mig_{}/{}",
+                    id,
+                    instance,
+                    name.trim_start_matches("mig/")
+                ),
+                format!("nvidia/{}", name),
+                &[
+                    ("gpu", &id.to_string()),
+                    ("instance", &instance.to_string()),
+                ],
+            ),
+            statistic,
+        }
+    }
+
+    /// A statistic of the processes with a name, which is named
+    /// `nvidia/gpu_<id>/process/<name>/...`
+    pub fn process(id: u32, process: &str, statistic: NvidiaConfigStatistic) -> Self {
+        let name: &str = statistic.into();
+        Self::Scoped {
+            name: labeled(
+                format!(
+                    "nvidia/gpu_{}/process/{}/{}",
+                    id,
+                    process,
+                    name.trim_start_matches("processes/")
+                ),
+                format!("nvidia/{}", name),
+                &[("gpu", &id.to_string()), ("process", process)],
+            ),
+            statistic,
+        }
+    }
 }
 
 impl Statistic<AtomicU64, AtomicU32> for NvidiaStatistic {
@@ -227,6 +305,22 @@ impl Statistic<AtomicU64, AtomicU32> for NvidiaStatistic {
                 1 => "nvidia/gpu_1/processes/compute",
                 _ => "nvidia/gpu_unknown/processes/compute",
             },
+            NvidiaStatistic::NvlinkRxBytes(id) => match id {
+                0 => "nvidia/gpu_0/nvlink/rx/bytes",
+                1 => "nvidia/gpu_1/nvlink/rx/bytes",
+                _ => "nvidia/gpu_unknown/nvlink/rx/bytes",
+            },
+            NvidiaStatistic::NvlinkTxBytes(id) => match id {
+                0 => "nvidia/gpu_0/nvlink/tx/bytes",
+                1 => "nvidia/gpu_1/nvlink/tx/bytes",
+                _ => "nvidia/gpu_unknown/nvlink/tx/bytes",
+            },
+            NvidiaStatistic::XidErrors(id) => match id {
+                0 => "nvidia/gpu_0/xid/errors",
+                1 => "nvidia/gpu_1/xid/errors",
+                _ => "nvidia/gpu_unknown/xid/errors",
+            },
+            NvidiaStatistic::Scoped { name, .. } => name,
         }
     }
 
@@ -237,7 +331,10 @@ impl Statistic<AtomicU64, AtomicU32> for NvidiaStatistic {
             | Self::EnergyConsumption(_)
             | Self::MemoryRetiredDbe(_)
             | Self::MemoryRetiredSbe(_)
-            | Self::PcieReplay(_) => Source::Counter,
+            | Self::PcieReplay(_)
+            | Self::NvlinkRxBytes(_)
+            | Self::NvlinkTxBytes(_)
+            | Self::XidErrors(_) => Source::Counter,
             _ => Source::Gauge,
         }
     }
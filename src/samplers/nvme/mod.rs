@@ -0,0 +1,381 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::*;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+/// `_IOWR('N', 0x41, struct nvme_admin_cmd)`
+const NVME_IOCTL_ADMIN_CMD: u64 = 0xC048_4E41;
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+const NVME_LOG_SMART: u32 = 0x02;
+const NVME_NSID_ALL: u32 = 0xFFFF_FFFF;
+const SMART_LOG_LEN: usize = 512;
+
+/// Mirrors `struct nvme_passthru_cmd` from `linux/nvme_ioctl.h`
+#[repr(C)]
+#[derive(Default)]
+struct NvmePassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+pub struct Nvme {
+    common: Common,
+    controllers: Vec<String>,
+    namespaces: Vec<String>,
+    statistics: Vec<NvmeConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Nvme {
+    type Statistic = NvmeStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().nvme().statistics.clone();
+        let (controllers, namespaces) = discover_devices()?;
+        if controllers.is_empty() {
+            return Err(format_err!("no nvme controllers found"));
+        }
+        let sampler = Self {
+            common,
+            controllers,
+            namespaces,
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register_devices();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().nvme().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize nvme sampler {}", e);
+                    } else {
+                        error!("failed to initialize nvme sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().nvme()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().nvme().statistics.clone();
+        if self.enabled() {
+            self.register_devices();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_smart_log();
+        self.map_result(r)?;
+
+        let r = self.sample_namespaces().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Nvme {
+    fn register_devices(&self) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in &self.statistics {
+            let devices = if statistic.namespace() {
+                &self.namespaces
+            } else {
+                &self.controllers
+            };
+            for device in devices {
+                let statistic = NvmeStatistic::new(device, *statistic);
+                self.metrics().register(&statistic);
+                self.metrics().add_output(&statistic, Output::Reading);
+                if !percentiles.is_empty() {
+                    self.metrics()
+                        .add_summary(&statistic, Summary::stream(self.samples()));
+                }
+                for percentile in percentiles {
+                    self.metrics()
+                        .add_output(&statistic, Output::Percentile(*percentile));
+                }
+            }
+        }
+    }
+
+    fn record(&self, device: &str, statistic: NvmeConfigStatistic, time: Instant, value: u64) {
+        let metric = NvmeStatistic::new(device, statistic);
+        match statistic.source() {
+            Source::Counter => {
+                self.common().record_counter(&metric, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&metric, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads the SMART / Health Information log page from each controller.
+    /// This requires `CAP_SYS_ADMIN`. A controller whose log page can't be
+    /// read, such as a fabrics controller, is skipped so that the others are
+    /// still reported.
+    fn sample_smart_log(&self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| !s.namespace()) {
+            return Ok(());
+        }
+        for controller in &self.controllers {
+            let log = match read_smart_log(controller) {
+                Ok(log) => log,
+                Err(e) => {
+                    debug!("failed to read smart log of {}: {}", controller, e);
+                    continue;
+                }
+            };
+            let time = Instant::now();
+            for statistic in &self.statistics {
+                if let Some(value) = log.value(*statistic) {
+                    self.record(controller, *statistic, time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn sample_namespaces(&self) -> Result<(), std::io::Error> {
+        if !self
+            .statistics
+            .contains(&NvmeConfigStatistic::NamespaceCapacity)
+        {
+            return Ok(());
+        }
+        for namespace in &self.namespaces {
+            let path = format!("/sys/block/{}/size", namespace);
+            let sectors: u64 = tokio::fs::read_to_string(path)
+                .await?
+                .trim()
+                .parse()
+                .map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, "bad namespace size")
+                })?;
+            // sysfs reports the size in 512 byte sectors regardless of the
+            // logical block size of the namespace
+            self.record(
+                namespace,
+                NvmeConfigStatistic::NamespaceCapacity,
+                Instant::now(),
+                sectors * 512,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Finds controllers, such as `nvme0`, and their namespaces, such as
+/// `nvme0n1`, using sysfs
+fn discover_devices() -> Result<(Vec<String>, Vec<String>), std::io::Error> {
+    let mut controllers = Vec::new();
+    let mut namespaces = Vec::new();
+    for entry in std::fs::read_dir("/sys/class/nvme")? {
+        let controller = entry?.file_name().to_string_lossy().to_string();
+        for entry in std::fs::read_dir(format!("/sys/class/nvme/{}", controller))? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            // hidden per-path namespaces for multipath devices, such as
+            // `nvme0c0n1`, are skipped in favor of the shared namespace
+            if is_namespace(&controller, &name) {
+                namespaces.push(name);
+            }
+        }
+        controllers.push(controller);
+    }
+    controllers.sort();
+    namespaces.sort();
+    Ok((controllers, namespaces))
+}
+
+fn is_namespace(controller: &str, name: &str) -> bool {
+    name.strip_prefix(controller)
+        .and_then(|v| v.strip_prefix('n'))
+        .map(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+fn read_smart_log(controller: &str) -> Result<SmartLog, std::io::Error> {
+    let file = File::open(format!("/dev/{}", controller))?;
+    let mut buffer = [0_u8; SMART_LOG_LEN];
+    let numd = (SMART_LOG_LEN / 4 - 1) as u32;
+    let mut cmd = NvmePassthruCmd {
+        opcode: NVME_ADMIN_GET_LOG_PAGE,
+        nsid: NVME_NSID_ALL,
+        addr: buffer.as_mut_ptr() as u64,
+        data_len: SMART_LOG_LEN as u32,
+        cdw10: (numd << 16) | NVME_LOG_SMART,
+        ..Default::default()
+    };
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut cmd) };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    } else if result > 0 {
+        // a positive result is the nvme status code of a failed command
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!(
+                "get log page failed for {}: status {:#x}",
+                controller, result
+            ),
+        ));
+    }
+    Ok(SmartLog::new(buffer))
+}
+
+/// The SMART / Health Information log page, as defined in the NVMe base
+/// specification
+struct SmartLog {
+    data: [u8; SMART_LOG_LEN],
+}
+
+impl SmartLog {
+    fn new(data: [u8; SMART_LOG_LEN]) -> Self {
+        Self { data }
+    }
+
+    fn byte(&self, offset: usize) -> u64 {
+        self.data[offset].into()
+    }
+
+    fn word(&self, offset: usize) -> u64 {
+        u16::from_le_bytes(self.data[offset..(offset + 2)].try_into().unwrap()).into()
+    }
+
+    /// 128-bit counters, saturated to fit a u64
+    fn counter(&self, offset: usize) -> u64 {
+        let value = u128::from_le_bytes(self.data[offset..(offset + 16)].try_into().unwrap());
+        value.min(u64::MAX as u128) as u64
+    }
+
+    /// Returns the value for the statistic, converted into the exported unit
+    fn value(&self, statistic: NvmeConfigStatistic) -> Option<u64> {
+        let value = match statistic {
+            NvmeConfigStatistic::CriticalWarning => self.byte(0),
+            // reported in kelvin, exported in degrees celsius
+            NvmeConfigStatistic::Temperature => self.word(1).saturating_sub(273),
+            NvmeConfigStatistic::SpareAvailable => self.byte(3),
+            NvmeConfigStatistic::SpareThreshold => self.byte(4),
+            NvmeConfigStatistic::EnduranceUsed => self.byte(5),
+            // data units are thousands of 512 byte units
+            NvmeConfigStatistic::DataRead => self.counter(32).saturating_mul(512_000),
+            NvmeConfigStatistic::DataWritten => self.counter(48).saturating_mul(512_000),
+            NvmeConfigStatistic::CommandsRead => self.counter(64),
+            NvmeConfigStatistic::CommandsWrite => self.counter(80),
+            // reported in minutes
+            NvmeConfigStatistic::BusyTime => self.counter(96).saturating_mul(60 * SECOND),
+            NvmeConfigStatistic::PowerCycles => self.counter(112),
+            // reported in hours
+            NvmeConfigStatistic::PowerOnTime => self.counter(128).saturating_mul(3600 * SECOND),
+            NvmeConfigStatistic::UnsafeShutdowns => self.counter(144),
+            NvmeConfigStatistic::ErrorsMedia => self.counter(160),
+            NvmeConfigStatistic::ErrorsLogEntries => self.counter(176),
+            NvmeConfigStatistic::NamespaceCapacity => {
+                return None;
+            }
+        };
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_namespace() {
+        assert!(is_namespace("nvme0", "nvme0n1"));
+        assert!(is_namespace("nvme1", "nvme1n12"));
+        assert!(!is_namespace("nvme0", "nvme0c0n1"));
+        assert!(!is_namespace("nvme0", "device"));
+        assert!(!is_namespace("nvme1", "nvme10n1"));
+    }
+
+    #[test]
+    fn test_smart_log() {
+        let mut data = [0_u8; SMART_LOG_LEN];
+        data[1..3].copy_from_slice(&310_u16.to_le_bytes());
+        data[3] = 100;
+        data[5] = 7;
+        data[32..48].copy_from_slice(&2_u128.to_le_bytes());
+        data[96..112].copy_from_slice(&3_u128.to_le_bytes());
+        data[160..176].copy_from_slice(&(u128::MAX).to_le_bytes());
+        let log = SmartLog::new(data);
+        assert_eq!(log.value(NvmeConfigStatistic::Temperature), Some(37));
+        assert_eq!(log.value(NvmeConfigStatistic::SpareAvailable), Some(100));
+        assert_eq!(log.value(NvmeConfigStatistic::EnduranceUsed), Some(7));
+        assert_eq!(log.value(NvmeConfigStatistic::DataRead), Some(1_024_000));
+        assert_eq!(log.value(NvmeConfigStatistic::BusyTime), Some(180 * SECOND));
+        assert_eq!(log.value(NvmeConfigStatistic::ErrorsMedia), Some(u64::MAX));
+        assert_eq!(log.value(NvmeConfigStatistic::NamespaceCapacity), None);
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum NvmeConfigStatistic {
+    #[strum(serialize = "critical_warning")]
+    CriticalWarning,
+    #[strum(serialize = "temperature")]
+    Temperature,
+    #[strum(serialize = "spare/available")]
+    SpareAvailable,
+    #[strum(serialize = "spare/threshold")]
+    SpareThreshold,
+    #[strum(serialize = "endurance/used")]
+    EnduranceUsed,
+    #[strum(serialize = "data/read")]
+    DataRead,
+    #[strum(serialize = "data/written")]
+    DataWritten,
+    #[strum(serialize = "commands/read")]
+    CommandsRead,
+    #[strum(serialize = "commands/write")]
+    CommandsWrite,
+    #[strum(serialize = "busy_time")]
+    BusyTime,
+    #[strum(serialize = "power/cycles")]
+    PowerCycles,
+    #[strum(serialize = "power/on_time")]
+    PowerOnTime,
+    #[strum(serialize = "unsafe_shutdowns")]
+    UnsafeShutdowns,
+    #[strum(serialize = "errors/media")]
+    ErrorsMedia,
+    #[strum(serialize = "errors/log_entries")]
+    ErrorsLogEntries,
+    #[strum(serialize = "namespace/capacity")]
+    NamespaceCapacity,
+}
+
+impl NvmeConfigStatistic {
+    /// Whether this statistic is reported for each namespace, rather than for
+    /// each controller
+    pub fn namespace(self) -> bool {
+        matches!(self, Self::NamespaceCapacity)
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::CriticalWarning
+            | Self::Temperature
+            | Self::SpareAvailable
+            | Self::SpareThreshold
+            | Self::EnduranceUsed
+            | Self::NamespaceCapacity => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for NvmeConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        NvmeConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct NvmeStatistic {
+    name: String,
+    statistic: NvmeConfigStatistic,
+}
+
+impl NvmeStatistic {
+    pub fn new(device: &str, statistic: NvmeConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("nvme/{}/{}", device, suffix),
+                format!("nvme/{}", suffix),
+                &[("device", device)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> NvmeConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for NvmeStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const DEV_KMSG: &str = "/dev/kmsg";
+
+/// Mount point of the cgroup hierarchy, which the paths of cgroups in the
+/// kernel log are relative to
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// The largest RSS, in bytes, which is recorded in the distribution
+const MAX_RSS: u64 = 1 << 40;
+
+pub struct Oom {
+    /// kill counts of each cgroup, and the time of its last kill, by the path
+    /// of the cgroup
+    cgroup_kills: HashMap<String, (u64, Instant)>,
+    common: Common,
+    kills: u64,
+    kmsg: Option<File>,
+    /// the cgroup of the task chosen by the most recent `oom-kill` message,
+    /// which is followed by a `Killed process` message for the same task
+    pending_cgroup: Option<String>,
+    /// statistics which have been registered, as cgroups are discovered at
+    /// runtime
+    registered: HashSet<OomStatistic>,
+    statistics: Vec<OomConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Oom {
+    type Statistic = OomStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().oom().statistics.clone();
+        let mut sampler = Self {
+            cgroup_kills: HashMap::new(),
+            common,
+            kills: 0,
+            kmsg: None,
+            pending_cgroup: None,
+            registered: HashSet::new(),
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.open_kmsg()?;
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().oom().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize oom sampler {}", e);
+                    } else {
+                        error!("failed to initialize oom sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().oom()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().oom().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_kmsg();
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Oom {
+    /// Opens the kernel log, starting from its end so that kills which
+    /// happened before rezolus started are not counted
+    fn open_kmsg(&mut self) -> Result<(), std::io::Error> {
+        let mut kmsg = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(DEV_KMSG)?;
+        kmsg.seek(SeekFrom::End(0))?;
+        self.kmsg = Some(kmsg);
+        Ok(())
+    }
+
+    fn register(&mut self, statistic: &OomStatistic) {
+        if self.registered.contains(statistic) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            if statistic.source() == Source::Distribution {
+                self.metrics().add_summary(statistic, self.heatmap(MAX_RSS));
+            } else {
+                self.metrics()
+                    .add_summary(statistic, Summary::stream(self.samples()));
+            }
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.registered.insert(statistic.clone());
+    }
+
+    /// Reads the records which have been added to the kernel log since the
+    /// last sample. Each read returns a single record, and fails with
+    /// `WouldBlock` once there are none left.
+    fn sample_kmsg(&mut self) -> Result<(), std::io::Error> {
+        if self.kmsg.is_none() {
+            self.open_kmsg()?;
+        }
+        let mut buffer = [0_u8; 8192];
+        let mut killed = Vec::new();
+        loop {
+            let length = match self.kmsg.as_mut().unwrap().read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => length,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // records were overwritten before they were read, and the
+                // next read continues from the oldest remaining record
+                Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+                Err(e) => return Err(e),
+            };
+            let record = String::from_utf8_lossy(&buffer[..length]);
+            let message = match record.split_once(';') {
+                Some((_, message)) => message.lines().next().unwrap_or(""),
+                None => continue,
+            };
+            if let Some(cgroup) = parse_oom_kill(message) {
+                self.pending_cgroup = Some(cgroup);
+            } else if let Some(rss) = parse_killed_process(message) {
+                self.kills += 1;
+                if let Some(cgroup) = self.pending_cgroup.take() {
+                    let kills = self
+                        .cgroup_kills
+                        .entry(cgroup)
+                        .or_insert((0, Instant::now()));
+                    kills.0 += 1;
+                    kills.1 = Instant::now();
+                }
+                killed.push(rss);
+            }
+        }
+
+        self.remove_cgroups();
+
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            match statistic {
+                OomConfigStatistic::Kills => {
+                    let metric = OomStatistic::host(statistic);
+                    self.register(&metric);
+                    self.common().record_counter(&metric, time, self.kills);
+                }
+                OomConfigStatistic::KilledRss => {
+                    let metric = OomStatistic::host(statistic);
+                    self.register(&metric);
+                    for rss in &killed {
+                        self.common().record_bucket(&metric, time, *rss, 1);
+                    }
+                }
+                OomConfigStatistic::CgroupKills => {
+                    for (cgroup, (kills, _)) in self.cgroup_kills.clone() {
+                        let metric = OomStatistic::cgroup(&cgroup, statistic);
+                        self.register(&metric);
+                        self.common().record_counter(&metric, time, kills);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets the cgroups which no longer exist, such as those of pods which
+    /// have been deleted, and deregisters their kill counts. A cgroup is kept
+    /// for a window after its last kill, so that the kills of a cgroup which
+    /// is removed right after them are still exported.
+    fn remove_cgroups(&mut self) {
+        let window = Duration::from_secs(self.window() as u64);
+        let removed: Vec<String> = self
+            .cgroup_kills
+            .iter()
+            .filter(|(cgroup, (_, last))| last.elapsed() >= window && !cgroup_exists(cgroup))
+            .map(|(cgroup, _)| cgroup.clone())
+            .collect();
+        for cgroup in removed {
+            self.cgroup_kills.remove(&cgroup);
+            let metric = OomStatistic::cgroup(&cgroup, OomConfigStatistic::CgroupKills);
+            if self.registered.remove(&metric) {
+                self.common().deregister(&metric);
+            }
+        }
+    }
+}
+
+/// Whether the cgroup exists in either the cgroup v2 hierarchy or the v1
+/// memory controller hierarchy
+fn cgroup_exists(cgroup: &str) -> bool {
+    let relative = cgroup.trim_start_matches('/');
+    Path::new(CGROUP_ROOT).join(relative).exists()
+        || Path::new(CGROUP_ROOT)
+            .join("memory")
+            .join(relative)
+            .exists()
+}
+
+/// Parses the cgroup of the chosen task from an `oom-kill:` message, which is
+/// logged before the kill from Linux 5.0, such as
+/// `oom-kill:constraint=CONSTRAINT_MEMCG,...,task_memcg=/app,task=app,pid=1`
+fn parse_oom_kill(message: &str) -> Option<String> {
+    let fields = message.strip_prefix("oom-kill:")?;
+    fields
+        .split(',')
+        .find_map(|f| f.strip_prefix("task_memcg="))
+        .map(|v| v.to_string())
+}
+
+/// Parses the RSS, in bytes, of the task from a `Killed process` message,
+/// such as `Killed process 1 (app) total-vm:1024kB, anon-rss:512kB,
+/// file-rss:4kB, shmem-rss:0kB`, which may be prefixed with the reason for
+/// the kill
+fn parse_killed_process(message: &str) -> Option<u64> {
+    let (_, details) = message.split_once("Killed process ")?;
+    let mut rss = 0;
+    for field in details.split(|c| c == ',' || c == ' ') {
+        if let Some((name, value)) = field.split_once(':') {
+            if name.ends_with("-rss") {
+                rss += value.trim_end_matches("kB").parse::<u64>().ok()? * 1024;
+            }
+        }
+    }
+    Some(rss)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_oom_kill() {
+        assert_eq!(
+            parse_oom_kill(
+                "oom-kill:constraint=CONSTRAINT_MEMCG,nodemask=(null),cpuset=/,mems_allowed=0,\
+                 oom_memcg=/system.slice/app.service,task_memcg=/system.slice/app.service,\
+                 task=app,pid=1234,uid=0"
+            ),
+            Some("/system.slice/app.service".to_string())
+        );
+        assert_eq!(parse_oom_kill("Out of memory: Killed process 1234"), None);
+    }
+
+    #[test]
+    fn test_parse_killed_process() {
+        assert_eq!(
+            parse_killed_process(
+                "Memory cgroup out of memory: Killed process 1234 (app) total-vm:2097152kB, \
+                 anon-rss:1048576kB, file-rss:2048kB, shmem-rss:0kB, UID:0 pgtables:2100kB \
+                 oom_score_adj:0"
+            ),
+            Some((1048576 + 2048) * 1024)
+        );
+        assert_eq!(
+            parse_killed_process(
+                "Killed process 42 (java) total-vm:100kB, anon-rss:10kB, file-rss:1kB"
+            ),
+            Some(11 * 1024)
+        );
+        assert_eq!(
+            parse_killed_process("oom_reaper: reaped process 42 (java)"),
+            None
+        );
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum OomConfigStatistic {
+    #[strum(serialize = "cgroup/kills")]
+    CgroupKills,
+    #[strum(serialize = "kills")]
+    Kills,
+    #[strum(serialize = "killed/rss")]
+    KilledRss,
+}
+
+impl OomConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::KilledRss => Source::Distribution,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for OomConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        OomConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct OomStatistic {
+    name: String,
+    statistic: OomConfigStatistic,
+}
+
+impl OomStatistic {
+    /// A host-wide statistic, which is named `oom/...`
+    pub fn host(statistic: OomConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("oom/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of a cgroup, which is named `oom/cgroup/<path>/...` with
+    /// the leading `/` of the path removed, and with the root cgroup named
+    /// `root`
+    pub fn cgroup(path: &str, statistic: OomConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let path = match path.trim_start_matches('/') {
+            "" => "root",
+            path => path,
+        };
+        Self {
+            name: labeled(
+                format!(
+                    "oom/cgroup/{}/{}",
+                    path,
+                    suffix.trim_start_matches("cgroup/")
+                ),
+                format!("oom/{}", suffix),
+                &[("cgroup", path)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> OomConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for OomStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// bounds the sample times held for a sampler, should they not be read
+const MAX_SAMPLE_TIMES: usize = 1024;
+
+/// Tracks the cost of running each sampler so that it can be reported by the
+/// rezolus sampler. Nothing is recorded while it is disabled.
+#[derive(Default)]
+pub struct Overhead {
+    enabled: AtomicBool,
+    samplers: Mutex<HashMap<&'static str, SamplerOverhead>>,
+}
+
+/// The cost of running a single sampler
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SamplerOverhead {
+    /// time taken by each sample since the overhead was last taken, in
+    /// nanoseconds
+    pub sample_times: Vec<u64>,
+    /// number of errors returned while sampling
+    pub errors: u64,
+    /// total time spent reading BPF maps, in nanoseconds
+    pub bpf_read_time: u64,
+    /// number of times the sampler has been restarted by the watchdog
+    pub restarts: u64,
+}
+
+impl Overhead {
+    /// Start or stop recording. Stopping discards everything recorded so far.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.samplers.lock().unwrap().clear();
+        }
+    }
+
+    /// Records the time taken by a single sample of the named sampler
+    pub fn record_sample(&self, sampler: &'static str, elapsed: Duration) {
+        self.update(sampler, |overhead| {
+            if overhead.sample_times.len() < MAX_SAMPLE_TIMES {
+                overhead.sample_times.push(elapsed.as_nanos() as u64);
+            }
+        });
+    }
+
+    /// Records an error returned while sampling the named sampler
+    pub fn record_error(&self, sampler: &'static str) {
+        self.update(sampler, |overhead| overhead.errors += 1);
+    }
+
+    /// Records the time taken by a read of the named sampler's BPF maps
+    pub fn record_bpf_read(&self, sampler: &'static str, elapsed: Duration) {
+        self.update(sampler, |overhead| {
+            overhead.bpf_read_time += elapsed.as_nanos() as u64
+        });
+    }
+
+    /// Records that the watchdog has restarted the named sampler
+    pub fn record_restart(&self, sampler: &'static str) {
+        self.update(sampler, |overhead| overhead.restarts += 1);
+    }
+
+    /// Returns the overhead of each sampler, clearing the sample times which
+    /// have been recorded. The other measurements are totals.
+    pub fn take(&self) -> Vec<(&'static str, SamplerOverhead)> {
+        let mut samplers = self.samplers.lock().unwrap();
+        samplers
+            .iter_mut()
+            .map(|(sampler, overhead)| {
+                let taken = overhead.clone();
+                overhead.sample_times.clear();
+                (*sampler, taken)
+            })
+            .collect()
+    }
+
+    fn update(&self, sampler: &'static str, f: impl FnOnce(&mut SamplerOverhead)) {
+        // samplers which are not spawned through the registry have no name
+        if sampler.is_empty() || !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        f(self.samplers.lock().unwrap().entry(sampler).or_default());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overhead() {
+        let overhead = Overhead::default();
+        overhead.record_error("disk");
+        assert!(overhead.take().is_empty());
+
+        overhead.set_enabled(true);
+        overhead.record_sample("disk", Duration::from_micros(5));
+        overhead.record_error("disk");
+        overhead.record_bpf_read("disk", Duration::from_micros(2));
+        overhead.record_bpf_read("disk", Duration::from_micros(3));
+        overhead.record_restart("disk");
+        let expected = SamplerOverhead {
+            sample_times: vec![5_000],
+            errors: 1,
+            bpf_read_time: 5_000,
+            restarts: 1,
+        };
+        assert_eq!(overhead.take(), vec![("disk", expected)]);
+
+        // sample times are cleared, while the totals are kept
+        let expected = SamplerOverhead {
+            sample_times: Vec::new(),
+            errors: 1,
+            bpf_read_time: 5_000,
+            restarts: 1,
+        };
+        assert_eq!(overhead.take(), vec![("disk", expected)]);
+
+        overhead.set_enabled(false);
+        assert!(overhead.take().is_empty());
+    }
+}
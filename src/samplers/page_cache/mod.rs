@@ -60,7 +60,7 @@ impl Sampler for PageCache {
         if common.config().samplers().page_cache().enabled() {
             if let Ok(mut interrupt) = PageCache::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !interrupt.retired() {
                         let _ = interrupt.sample().await;
                     }
                 });
@@ -84,6 +84,13 @@ impl Sampler for PageCache {
         self.common.config().samplers().page_cache()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -97,7 +104,7 @@ impl Sampler for PageCache {
 
         #[cfg(feature = "bpf")]
         {
-            let result = self.sample_bpf_counters();
+            let result = self.time_bpf(|s| s.sample_bpf_counters());
             self.map_result(result)?;
         }
 
@@ -127,22 +134,38 @@ impl PageCache {
                 let code = include_str!("bpf.c");
                 let mut bpf = bcc::BPF::new(code)?;
 
-                bcc::Kprobe::new()
-                    .handler("trace_mark_page_accessed")
-                    .function("mark_page_accessed")
-                    .attach(&mut bpf)?;
-                bcc::Kprobe::new()
-                    .handler("trace_mark_buffer_dirty")
-                    .function("mark_buffer_dirty")
-                    .attach(&mut bpf)?;
-                bcc::Kprobe::new()
-                    .handler("trace_add_to_page_cache_lru")
-                    .function("add_to_page_cache_lru")
-                    .attach(&mut bpf)?;
-                bcc::Kprobe::new()
-                    .handler("trace_account_page_dirtied")
-                    .function("account_page_dirtied")
-                    .attach(&mut bpf)?;
+                // kernels from 5.16 account for page cache usage by folio, and
+                // the page based functions are either removed or are wrappers
+                // which miss some callers, so the folio functions are preferred
+                for (handler, functions) in &[
+                    (
+                        "trace_mark_page_accessed",
+                        &["folio_mark_accessed", "mark_page_accessed"][..],
+                    ),
+                    ("trace_mark_buffer_dirty", &["mark_buffer_dirty"][..]),
+                    (
+                        "trace_add_to_page_cache_lru",
+                        &["filemap_add_folio", "add_to_page_cache_lru"][..],
+                    ),
+                    (
+                        "trace_account_page_dirtied",
+                        &["folio_account_dirtied", "account_page_dirtied"][..],
+                    ),
+                ] {
+                    let attached = functions.iter().any(|function| {
+                        bcc::Kprobe::new()
+                            .handler(handler)
+                            .function(function)
+                            .attach(&mut bpf)
+                            .is_ok()
+                    });
+                    if !attached {
+                        return Err(format_err!(
+                            "failed to attach kprobe to any of: {}",
+                            functions.join(", ")
+                        ));
+                    }
+                }
 
                 self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })))
             }
@@ -202,16 +225,28 @@ impl PageCache {
                 self.counters.insert(PageCacheStatistic::Miss, misses);
             }
 
-            let _ = self.metrics().record_counter(
+            if let Some(count) = self.counters.get_mut(&PageCacheStatistic::Dirty) {
+                *count += page_dirtied;
+            } else {
+                self.counters
+                    .insert(PageCacheStatistic::Dirty, page_dirtied);
+            }
+
+            self.common().record_counter(
                 &PageCacheStatistic::Hit,
                 time,
                 *self.counters.get(&PageCacheStatistic::Hit).unwrap_or(&0),
             );
-            let _ = self.metrics().record_counter(
+            self.common().record_counter(
                 &PageCacheStatistic::Miss,
                 time,
                 *self.counters.get(&PageCacheStatistic::Miss).unwrap_or(&0),
             );
+            self.common().record_counter(
+                &PageCacheStatistic::Dirty,
+                time,
+                *self.counters.get(&PageCacheStatistic::Dirty).unwrap_or(&0),
+            );
         }
         Ok(())
     }
@@ -25,6 +25,8 @@ use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 )]
 #[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
 pub enum PageCacheStatistic {
+    #[strum(serialize = "page_cache/dirty")]
+    Dirty,
     #[strum(serialize = "page_cache/hit")]
     Hit,
     #[strum(serialize = "page_cache/miss")]
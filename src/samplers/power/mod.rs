@@ -0,0 +1,312 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::path::{Path, PathBuf};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const POWERCAP_PATH: &str = "/sys/class/powercap";
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+/// An energy counter for one power domain, such as a CPU package or the DRAM
+/// attached to it
+struct Domain {
+    name: String,
+    path: PathBuf,
+    /// value at which the raw counter wraps around, in microjoules
+    max: u64,
+    /// previous raw reading and the time it was taken
+    last: Option<(u64, Instant)>,
+    /// microjoules consumed since the sampler started
+    total: u64,
+}
+
+impl Domain {
+    fn new(name: String, path: PathBuf, max: u64) -> Self {
+        Self {
+            name,
+            path,
+            max,
+            last: None,
+            total: 0,
+        }
+    }
+}
+
+pub struct Power {
+    common: Common,
+    domains: Vec<Domain>,
+    statistics: Vec<PowerConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Power {
+    type Statistic = PowerStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().power().statistics.clone();
+        let mut domains = discover_rapl();
+        if domains.is_empty() {
+            domains = discover_amd_energy();
+        }
+        if domains.is_empty() {
+            return Err(format_err!("no energy counters found"));
+        }
+        let sampler = Self {
+            common,
+            domains,
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register_domains();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().power().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize power sampler {}", e);
+                    } else {
+                        error!("failed to initialize power sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().power()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().power().statistics.clone();
+        if self.enabled() {
+            self.register_domains();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_energy().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Power {
+    fn register_domains(&self) {
+        let percentiles = self.sampler_config().percentiles();
+        for domain in &self.domains {
+            for statistic in &self.statistics {
+                let statistic = PowerStatistic::new(&domain.name, *statistic);
+                self.metrics().register(&statistic);
+                self.metrics().add_output(&statistic, Output::Reading);
+                if !percentiles.is_empty() {
+                    self.metrics()
+                        .add_summary(&statistic, Summary::stream(self.samples()));
+                }
+                for percentile in percentiles {
+                    self.metrics()
+                        .add_output(&statistic, Output::Percentile(*percentile));
+                }
+            }
+        }
+    }
+
+    async fn sample_energy(&mut self) -> Result<(), std::io::Error> {
+        for index in 0..self.domains.len() {
+            // reading RAPL energy counters requires root on newer kernels
+            let raw: u64 = tokio::fs::read_to_string(&self.domains[index].path)
+                .await?
+                .trim()
+                .parse()
+                .map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, "bad energy counter")
+                })?;
+            let time = Instant::now();
+
+            let domain = &mut self.domains[index];
+            let mut power = None;
+            if let Some((last, last_time)) = domain.last {
+                let delta = energy_delta(last, raw, domain.max);
+                domain.total += delta;
+                let elapsed = time.duration_since(last_time).as_secs_f64();
+                if elapsed > 0.0 {
+                    power = Some((delta as f64 / 1_000_000.0 / elapsed).round() as u64);
+                }
+            }
+            domain.last = Some((raw, time));
+            let total = domain.total;
+
+            let domain = &self.domains[index];
+            for statistic in &self.statistics {
+                let metric = PowerStatistic::new(&domain.name, *statistic);
+                match statistic {
+                    PowerConfigStatistic::Energy => {
+                        self.common()
+                            .record_counter(&metric, time, total / 1_000_000);
+                    }
+                    PowerConfigStatistic::Power => {
+                        if let Some(power) = power {
+                            let _ = self.metrics().record_gauge(&metric, time, power);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Microjoules consumed between two readings of a counter which wraps around
+/// at `max`
+fn energy_delta(last: u64, current: u64, max: u64) -> u64 {
+    if current >= last {
+        current - last
+    } else {
+        max.saturating_sub(last) + current
+    }
+}
+
+/// Converts a powercap zone name, such as `package-0`, into the form used in
+/// metric names, such as `package0`
+fn domain_name(zone: &str) -> String {
+    zone.replace('-', "")
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|v| v.trim().to_string())
+}
+
+/// Finds RAPL domains exposed through the powercap framework. This is used by
+/// both Intel and, on recent kernels, AMD processors. Subzones are named
+/// relative to their parent zone, such as `package0/dram`.
+fn discover_rapl() -> Vec<Domain> {
+    let mut domains = Vec::new();
+    let entries = match std::fs::read_dir(POWERCAP_PATH) {
+        Ok(entries) => entries,
+        Err(_) => return domains,
+    };
+    let mut zones: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("intel-rapl:"))
+        .collect();
+    zones.sort();
+
+    for zone in &zones {
+        let path = Path::new(POWERCAP_PATH).join(zone);
+        let name = match read_trimmed(&path.join("name")) {
+            Some(name) => domain_name(&name),
+            None => continue,
+        };
+        // subzones have ids of the form `intel-rapl:0:1`
+        let name = match zone.rfind(':') {
+            Some(index) if index > "intel-rapl".len() => {
+                let parent = Path::new(POWERCAP_PATH).join(&zone[..index]);
+                match read_trimmed(&parent.join("name")) {
+                    Some(parent) => format!("{}/{}", domain_name(&parent), name),
+                    None => continue,
+                }
+            }
+            _ => name,
+        };
+        let max = read_trimmed(&path.join("max_energy_range_uj"))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(u64::MAX);
+        domains.push(Domain::new(name, path.join("energy_uj"), max));
+    }
+    domains
+}
+
+/// Finds energy counters provided by the `amd_energy` hwmon driver, which is
+/// used on AMD processors with kernels that lack RAPL powercap support
+fn discover_amd_energy() -> Vec<Domain> {
+    let mut domains = Vec::new();
+    let entries = match std::fs::read_dir(HWMON_PATH) {
+        Ok(entries) => entries,
+        Err(_) => return domains,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if read_trimmed(&path.join("name")).as_deref() != Some("amd_energy") {
+            continue;
+        }
+        for id in 1.. {
+            // labels have the form `Esocket0` or `Ecore000`
+            let label = match read_trimmed(&path.join(format!("energy{}_label", id))) {
+                Some(label) => label,
+                None => break,
+            };
+            let name = label.trim_start_matches('E').to_string();
+            domains.push(Domain::new(
+                name,
+                path.join(format!("energy{}_input", id)),
+                u64::MAX,
+            ));
+        }
+    }
+    domains
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_energy_delta() {
+        assert_eq!(energy_delta(100, 250, 1000), 150);
+        assert_eq!(energy_delta(900, 50, 1000), 150);
+        assert_eq!(energy_delta(100, 100, 1000), 0);
+    }
+
+    #[test]
+    fn test_domain_name() {
+        assert_eq!(domain_name("package-0"), "package0");
+        assert_eq!(domain_name("dram"), "dram");
+    }
+}
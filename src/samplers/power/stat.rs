@@ -0,0 +1,85 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum PowerConfigStatistic {
+    #[strum(serialize = "energy")]
+    Energy,
+    #[strum(serialize = "power")]
+    Power,
+}
+
+impl PowerConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::Energy => Source::Counter,
+            Self::Power => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for PowerConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        PowerConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PowerStatistic {
+    name: String,
+    statistic: PowerConfigStatistic,
+}
+
+impl PowerStatistic {
+    pub fn new(domain: &str, statistic: PowerConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("power/{}/{}", domain, suffix),
+                format!("power/{}", suffix),
+                &[("domain", domain)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> PowerConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for PowerStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<ProcessConfigStatistic>,
+    #[serde(default = "default_top_n")]
+    top_n: usize,
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    cgroups: Vec<String>,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+            top_n: default_top_n(),
+            names: Default::default(),
+            cgroups: Default::default(),
+        }
+    }
+}
+
+fn default_statistics() -> Vec<ProcessConfigStatistic> {
+    ProcessConfigStatistic::iter().collect()
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+impl ProcessConfig {
+    /// Number of processes, ranked by CPU usage during the last interval,
+    /// which are reported each interval
+    pub fn top_n(&self) -> usize {
+        self.top_n
+    }
+
+    /// Only consider processes with one of these names
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Only consider processes within one of these cgroup v2 paths, such as
+    /// `/system.slice/sshd.service`
+    pub fn cgroups(&self) -> &[String] {
+        &self.cgroups
+    }
+}
+
+impl SamplerConfig for ProcessConfig {
+    type Statistic = ProcessStatistic;
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // processes are ranked at runtime, register them as they are found
+        Vec::new()
+    }
+}
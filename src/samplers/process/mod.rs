@@ -0,0 +1,398 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::cpu::nanos_per_tick;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Process {
+    common: Common,
+    nanos_per_tick: u64,
+    page_size: u64,
+    /// the name and last reading of each process, so that only the increase
+    /// since the last sample is added to the totals
+    pids: HashMap<u32, (String, ProcessStats)>,
+    /// names of the processes which are being exported
+    processes: HashSet<String>,
+    statistics: Vec<ProcessConfigStatistic>,
+    /// totals for each name, which keep increasing as processes with the name
+    /// exit, along with the resident memory of those which are running
+    totals: HashMap<String, ProcessStats>,
+}
+
+#[async_trait]
+impl Sampler for Process {
+    type Statistic = ProcessStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().process().statistics.clone();
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size <= 0 {
+            return Err(format_err!("failed to get page size"));
+        }
+        Ok(Self {
+            common,
+            nanos_per_tick: nanos_per_tick(),
+            page_size: page_size as u64,
+            pids: HashMap::new(),
+            processes: HashSet::new(),
+            statistics,
+            totals: HashMap::new(),
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().process().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize process sampler {}", e);
+                    } else {
+                        error!("failed to initialize process sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().process()
+    }
+
+    fn reconfigure(&mut self) {
+        // remove known processes so they are registered again with the new
+        // statistics when they are next reported
+        for name in std::mem::take(&mut self.processes) {
+            self.deregister_process(&name);
+        }
+        self.statistics = self.common.config().samplers().process().statistics.clone();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_processes().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+/// Values read from `/proc/[pid]/stat`, with cpu time in clock ticks and
+/// resident memory in pages
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ProcessStats {
+    cpu_user: u64,
+    cpu_system: u64,
+    memory_resident: u64,
+    faults_minor: u64,
+    faults_major: u64,
+}
+
+impl ProcessStats {
+    fn add(&mut self, other: &ProcessStats) {
+        self.cpu_user += other.cpu_user;
+        self.cpu_system += other.cpu_system;
+        self.memory_resident += other.memory_resident;
+        self.faults_minor += other.faults_minor;
+        self.faults_major += other.faults_major;
+    }
+
+    /// The increase of the counters since a previous reading, along with the
+    /// current resident memory
+    fn since(&self, previous: &ProcessStats) -> ProcessStats {
+        ProcessStats {
+            cpu_user: self.cpu_user.saturating_sub(previous.cpu_user),
+            cpu_system: self.cpu_system.saturating_sub(previous.cpu_system),
+            memory_resident: self.memory_resident,
+            faults_minor: self.faults_minor.saturating_sub(previous.faults_minor),
+            faults_major: self.faults_major.saturating_sub(previous.faults_major),
+        }
+    }
+
+    fn cpu(&self) -> u64 {
+        self.cpu_user + self.cpu_system
+    }
+}
+
+impl Process {
+    /// Reads all processes and reports the ones which used the most CPU time
+    /// during the last interval. Processes are grouped by name, so that a
+    /// daemon with many worker processes is reported as a single entry.
+    async fn sample_processes(&mut self) -> Result<(), std::io::Error> {
+        // the usage of each name during the last interval, summed from the
+        // increase of each of its processes so that processes which exit
+        // don't take their usage with them
+        let mut processes = HashMap::<String, ProcessStats>::new();
+        let mut pids = HashMap::new();
+
+        let mut entries = tokio::fs::read_dir("/proc").await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let pid = match entry.file_name().to_str().map(|v| v.parse::<u32>()) {
+                Some(Ok(pid)) => pid,
+                _ => continue,
+            };
+            // the process may exit at any point while we read it
+            let (name, stats) = match tokio::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .await
+                .ok()
+                .and_then(|content| parse_stat(&content))
+            {
+                Some(v) => v,
+                None => continue,
+            };
+            if !self.matches(pid, &name).await {
+                continue;
+            }
+            // a process seen for the first time, or a pid which has been
+            // reused, contributes all of its usage
+            let increase = match self.pids.get(&pid) {
+                Some((previous_name, previous)) if *previous_name == name => stats.since(previous),
+                _ => stats.clone(),
+            };
+            processes.entry(name.clone()).or_default().add(&increase);
+            pids.insert(pid, (name, stats));
+        }
+        self.pids = pids;
+
+        // names which no longer have any processes are forgotten
+        self.totals.retain(|name, _| processes.contains_key(name));
+        for (name, increase) in &processes {
+            let total = self.totals.entry(name.clone()).or_default();
+            let memory_resident = increase.memory_resident;
+            total.add(increase);
+            total.memory_resident = memory_resident;
+        }
+
+        // rank by cpu time used since the previous sample
+        let mut ranked: Vec<(u64, &String)> = processes
+            .iter()
+            .map(|(name, increase)| (increase.cpu(), name))
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        let top_n = self.common.config().samplers().process().top_n();
+        let top: HashSet<String> = ranked
+            .into_iter()
+            .take(top_n)
+            .map(|(_, name)| name.clone())
+            .collect();
+
+        // names which are no longer among the top are not exported
+        let removed: Vec<String> = self.processes.difference(&top).cloned().collect();
+        for name in removed {
+            self.deregister_process(&name);
+            self.processes.remove(&name);
+        }
+
+        let time = Instant::now();
+        for name in &top {
+            if !self.processes.contains(name) {
+                self.register_process(name);
+                self.processes.insert(name.clone());
+            }
+            let stats = &self.totals[name];
+            for statistic in &self.statistics {
+                let metric = ProcessStatistic::new(name, *statistic);
+                let value = match statistic {
+                    ProcessConfigStatistic::CpuUser => stats.cpu_user * self.nanos_per_tick,
+                    ProcessConfigStatistic::CpuSystem => stats.cpu_system * self.nanos_per_tick,
+                    ProcessConfigStatistic::MemoryResident => {
+                        stats.memory_resident * self.page_size
+                    }
+                    ProcessConfigStatistic::FaultsMinor => stats.faults_minor,
+                    ProcessConfigStatistic::FaultsMajor => stats.faults_major,
+                };
+                match statistic.source() {
+                    Source::Counter => {
+                        self.common().record_counter(&metric, time, value);
+                    }
+                    Source::Gauge => {
+                        let _ = self.metrics().record_gauge(&metric, time, value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a process against the configured names and cgroups. With
+    /// neither configured, all processes match.
+    async fn matches(&self, pid: u32, name: &str) -> bool {
+        let names = self.common.config().samplers().process().names();
+        let cgroups = self.common.config().samplers().process().cgroups();
+        if names.is_empty() && cgroups.is_empty() {
+            return true;
+        }
+        if names.iter().any(|n| n == name) {
+            return true;
+        }
+        if !cgroups.is_empty() {
+            if let Ok(content) = tokio::fs::read_to_string(format!("/proc/{}/cgroup", pid)).await {
+                if let Some(path) = parse_cgroup(&content) {
+                    return cgroups.iter().any(|cgroup| {
+                        let cgroup = cgroup.trim_end_matches('/');
+                        path == cgroup || path.starts_with(&format!("{}/", cgroup))
+                    });
+                }
+            }
+        }
+        false
+    }
+
+    fn deregister_process(&self, name: &str) {
+        for statistic in &self.statistics {
+            self.common
+                .deregister(&ProcessStatistic::new(name, *statistic));
+        }
+    }
+
+    fn register_process(&self, name: &str) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in &self.statistics {
+            let statistic = ProcessStatistic::new(name, *statistic);
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+        }
+    }
+}
+
+/// Parses `/proc/[pid]/stat`. The name is enclosed in parentheses and may
+/// itself contain spaces or parentheses, so fields are located relative to the
+/// final closing parenthesis.
+fn parse_stat(content: &str) -> Option<(String, ProcessStats)> {
+    let start = content.find('(')?;
+    let end = content.rfind(')')?;
+    let name = content.get((start + 1)..end)?.to_string();
+    // fields following the name, starting with the process state
+    let fields: Vec<&str> = content.get((end + 1)..)?.split_whitespace().collect();
+    let field = |index: usize| -> Option<u64> { fields.get(index)?.parse().ok() };
+    Some((
+        name,
+        ProcessStats {
+            faults_minor: field(7)?,
+            faults_major: field(9)?,
+            cpu_user: field(11)?,
+            cpu_system: field(12)?,
+            memory_resident: field(21)?,
+        },
+    ))
+}
+
+/// Returns the cgroup v2 path from `/proc/[pid]/cgroup`
+fn parse_cgroup(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find(|line| line.starts_with("0::"))
+        .map(|line| &line[3..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let (name, stats) = parse_stat(
+            "1234 (my (odd) daemon) S 1 1234 1234 0 -1 4194560 5012 0 17 0 250 120 0 0 20 0 \
+             4 0 1812 234881024 2048 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 3 0 0 \
+             0 0 0\n",
+        )
+        .unwrap();
+        assert_eq!(name, "my (odd) daemon");
+        assert_eq!(
+            stats,
+            ProcessStats {
+                cpu_user: 250,
+                cpu_system: 120,
+                memory_resident: 2048,
+                faults_minor: 5012,
+                faults_major: 17,
+            }
+        );
+    }
+
+    #[test]
+    fn test_since() {
+        let previous = ProcessStats {
+            cpu_user: 250,
+            cpu_system: 120,
+            memory_resident: 2048,
+            faults_minor: 5012,
+            faults_major: 17,
+        };
+        let current = ProcessStats {
+            cpu_user: 300,
+            cpu_system: 120,
+            memory_resident: 1024,
+            faults_minor: 5112,
+            faults_major: 18,
+        };
+        assert_eq!(
+            current.since(&previous),
+            ProcessStats {
+                cpu_user: 50,
+                cpu_system: 0,
+                memory_resident: 1024,
+                faults_minor: 100,
+                faults_major: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_cgroup() {
+        assert_eq!(
+            parse_cgroup("0::/system.slice/sshd.service\n"),
+            Some("/system.slice/sshd.service")
+        );
+        assert_eq!(parse_cgroup("1:name=systemd:/user.slice\n"), None);
+    }
+}
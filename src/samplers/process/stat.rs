@@ -0,0 +1,103 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ProcessConfigStatistic {
+    #[strum(serialize = "cpu/user")]
+    CpuUser,
+    #[strum(serialize = "cpu/system")]
+    CpuSystem,
+    #[strum(serialize = "memory/resident")]
+    MemoryResident,
+    #[strum(serialize = "faults/minor")]
+    FaultsMinor,
+    #[strum(serialize = "faults/major")]
+    FaultsMajor,
+}
+
+impl ProcessConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::MemoryResident => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for ProcessConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ProcessConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ProcessStatistic {
+    name: String,
+    statistic: ProcessConfigStatistic,
+}
+
+impl ProcessStatistic {
+    pub fn new(process: &str, statistic: ProcessConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        // process names may contain characters which would be ambiguous in
+        // the metric name, such as `kworker/0:1`
+        let process: String = process
+            .chars()
+            .map(|c| {
+                if c == '/' || c.is_whitespace() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        Self {
+            name: labeled(
+                format!("process/{}/{}", process, suffix),
+                format!("process/{}", suffix),
+                &[("process", &process)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> ProcessConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ProcessStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
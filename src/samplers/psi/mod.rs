@@ -0,0 +1,188 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::common::MICROSECOND;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Psi {
+    common: Common,
+    pressure_files: HashMap<&'static str, File>,
+    statistics: Vec<PsiStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Psi {
+    type Statistic = PsiStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().psi().statistics();
+        let sampler = Self {
+            common,
+            pressure_files: HashMap::new(),
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().psi().enabled() {
+            if let Ok(mut sampler) = Self::new(common.clone()) {
+                common.runtime().spawn(async move {
+                    while !sampler.retired() {
+                        let _ = sampler.sample().await;
+                    }
+                });
+            } else if !common.config.fault_tolerant() {
+                fatal!("failed to initialize psi sampler");
+            } else {
+                error!("failed to initialize psi sampler");
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().psi()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        for resource in &["cpu", "memory", "io"] {
+            if self.statistics.iter().any(|s| s.keys().0 == *resource) {
+                let r = self.sample_pressure(resource).await;
+                self.map_result(r)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Psi {
+    async fn sample_pressure(&mut self, resource: &'static str) -> Result<(), std::io::Error> {
+        if !self.pressure_files.contains_key(resource) {
+            let file = File::open(format!("/proc/pressure/{}", resource)).await?;
+            self.pressure_files.insert(resource, file);
+        }
+
+        if let Some(file) = self.pressure_files.get_mut(resource) {
+            file.seek(SeekFrom::Start(0)).await?;
+            let mut content = String::new();
+            file.read_to_string(&mut content).await?;
+            let result = parse_pressure(&content);
+
+            let time = Instant::now();
+            for statistic in self.statistics.iter().filter(|s| s.keys().0 == resource) {
+                let (_, kind, field) = statistic.keys();
+                if let Some(value) = result.get(&(kind.to_string(), field.to_string())) {
+                    match statistic.source() {
+                        Source::Counter => {
+                            // total stall time is reported in microseconds
+                            self.common().record_counter(
+                                statistic,
+                                time,
+                                *value as u64 * MICROSECOND,
+                            );
+                        }
+                        Source::Gauge => {
+                            // averages are percentages with two decimal places,
+                            // store them as hundredths of a percent
+                            let _ = self.metrics().record_gauge(
+                                statistic,
+                                time,
+                                (*value * 100.0).round() as u64,
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the content of a pressure file into a map of (kind, field) to value,
+/// where kind is either `some` or `full`
+fn parse_pressure(content: &str) -> HashMap<(String, String), f64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        if let Some(kind) = parts.next() {
+            for part in parts {
+                let mut kv = part.split('=');
+                if let (Some(field), Some(Ok(value))) = (kv.next(), kv.next().map(|v| v.parse())) {
+                    result.insert((kind.to_string(), field.to_string()), value);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_pressure() {
+        let result = parse_pressure(
+            "some avg10=1.53 avg60=0.87 avg300=0.00 total=41522\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=1024\n",
+        );
+        assert_eq!(result.len(), 8);
+        assert_eq!(
+            result.get(&("some".to_string(), "avg10".to_string())),
+            Some(&1.53)
+        );
+        assert_eq!(
+            result.get(&("full".to_string(), "total".to_string())),
+            Some(&1024.0)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum PsiStatistic {
+    #[strum(serialize = "psi/cpu/some/avg10")]
+    CpuSomeAvg10,
+    #[strum(serialize = "psi/cpu/some/avg60")]
+    CpuSomeAvg60,
+    #[strum(serialize = "psi/cpu/some/avg300")]
+    CpuSomeAvg300,
+    #[strum(serialize = "psi/cpu/some/total")]
+    CpuSomeTotal,
+    #[strum(serialize = "psi/cpu/full/avg10")]
+    CpuFullAvg10,
+    #[strum(serialize = "psi/cpu/full/avg60")]
+    CpuFullAvg60,
+    #[strum(serialize = "psi/cpu/full/avg300")]
+    CpuFullAvg300,
+    #[strum(serialize = "psi/cpu/full/total")]
+    CpuFullTotal,
+    #[strum(serialize = "psi/memory/some/avg10")]
+    MemorySomeAvg10,
+    #[strum(serialize = "psi/memory/some/avg60")]
+    MemorySomeAvg60,
+    #[strum(serialize = "psi/memory/some/avg300")]
+    MemorySomeAvg300,
+    #[strum(serialize = "psi/memory/some/total")]
+    MemorySomeTotal,
+    #[strum(serialize = "psi/memory/full/avg10")]
+    MemoryFullAvg10,
+    #[strum(serialize = "psi/memory/full/avg60")]
+    MemoryFullAvg60,
+    #[strum(serialize = "psi/memory/full/avg300")]
+    MemoryFullAvg300,
+    #[strum(serialize = "psi/memory/full/total")]
+    MemoryFullTotal,
+    #[strum(serialize = "psi/io/some/avg10")]
+    IoSomeAvg10,
+    #[strum(serialize = "psi/io/some/avg60")]
+    IoSomeAvg60,
+    #[strum(serialize = "psi/io/some/avg300")]
+    IoSomeAvg300,
+    #[strum(serialize = "psi/io/some/total")]
+    IoSomeTotal,
+    #[strum(serialize = "psi/io/full/avg10")]
+    IoFullAvg10,
+    #[strum(serialize = "psi/io/full/avg60")]
+    IoFullAvg60,
+    #[strum(serialize = "psi/io/full/avg300")]
+    IoFullAvg300,
+    #[strum(serialize = "psi/io/full/total")]
+    IoFullTotal,
+}
+
+impl PsiStatistic {
+    /// The pressure file, line prefix, and field which provide the value for
+    /// this statistic
+    pub fn keys(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::CpuSomeAvg10 => ("cpu", "some", "avg10"),
+            Self::CpuSomeAvg60 => ("cpu", "some", "avg60"),
+            Self::CpuSomeAvg300 => ("cpu", "some", "avg300"),
+            Self::CpuSomeTotal => ("cpu", "some", "total"),
+            Self::CpuFullAvg10 => ("cpu", "full", "avg10"),
+            Self::CpuFullAvg60 => ("cpu", "full", "avg60"),
+            Self::CpuFullAvg300 => ("cpu", "full", "avg300"),
+            Self::CpuFullTotal => ("cpu", "full", "total"),
+            Self::MemorySomeAvg10 => ("memory", "some", "avg10"),
+            Self::MemorySomeAvg60 => ("memory", "some", "avg60"),
+            Self::MemorySomeAvg300 => ("memory", "some", "avg300"),
+            Self::MemorySomeTotal => ("memory", "some", "total"),
+            Self::MemoryFullAvg10 => ("memory", "full", "avg10"),
+            Self::MemoryFullAvg60 => ("memory", "full", "avg60"),
+            Self::MemoryFullAvg300 => ("memory", "full", "avg300"),
+            Self::MemoryFullTotal => ("memory", "full", "total"),
+            Self::IoSomeAvg10 => ("io", "some", "avg10"),
+            Self::IoSomeAvg60 => ("io", "some", "avg60"),
+            Self::IoSomeAvg300 => ("io", "some", "avg300"),
+            Self::IoSomeTotal => ("io", "some", "total"),
+            Self::IoFullAvg10 => ("io", "full", "avg10"),
+            Self::IoFullAvg60 => ("io", "full", "avg60"),
+            Self::IoFullAvg300 => ("io", "full", "avg300"),
+            Self::IoFullTotal => ("io", "full", "total"),
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for PsiStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        match self.keys().2 {
+            "total" => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for PsiStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        PsiStatistic::from_str(s)
+    }
+}
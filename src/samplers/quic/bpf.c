@@ -0,0 +1,88 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <bcc/proto.h>
+#include <uapi/linux/if_packet.h>
+
+// counters, which index the counts array
+#define RECEIVE_PACKETS 0
+#define RECEIVE_BYTES 1
+#define RECEIVE_INITIAL 2
+#define TRANSMIT_PACKETS 3
+#define TRANSMIT_BYTES 4
+#define TRANSMIT_INITIAL 5
+
+BPF_ARRAY(counts, u64, 6);
+
+static void add(int index, u64 value)
+{
+    u64 *count = counts.lookup(&index);
+    if (count) {
+        lock_xadd(count, value);
+    }
+}
+
+// PORT_MATCH is replaced by userspace with a test of the configured ports
+static int quic_port(u16 port)
+{
+    return PORT_MATCH;
+}
+
+// Socket filter attached to a raw socket on each interface, which sees the
+// packets in both directions. Packets are classified as QUIC by UDP port. The
+// return value of zero means the packet is not queued on the raw socket.
+int quic_filter(struct __sk_buff *skb)
+{
+    u32 udp;
+    u16 proto = load_half(skb, offsetof(struct ethernet_t, type));
+    if (proto == ETH_P_IP) {
+        // skip fragments after the first, which have no udp header
+        if ((load_half(skb, ETH_HLEN + 6) & 0x1fff) != 0) {
+            return 0;
+        }
+        if (load_byte(skb, ETH_HLEN + 9) != IPPROTO_UDP) {
+            return 0;
+        }
+        udp = ETH_HLEN + ((load_byte(skb, ETH_HLEN) & 0xf) << 2);
+    } else if (proto == ETH_P_IPV6) {
+        // extension headers are not followed
+        if (load_byte(skb, ETH_HLEN + 6) != IPPROTO_UDP) {
+            return 0;
+        }
+        udp = ETH_HLEN + 40;
+    } else {
+        return 0;
+    }
+
+    u16 sport = load_half(skb, udp);
+    u16 dport = load_half(skb, udp + 2);
+    if (!quic_port(sport) && !quic_port(dport)) {
+        return 0;
+    }
+
+    // the udp length includes its 8 byte header
+    u16 length = load_half(skb, udp + 4);
+    u64 bytes = length > 8 ? length - 8 : 0;
+
+    // a long header with packet type 0 is an Initial packet, which opens the
+    // handshake in QUIC version 1
+    u8 flags = load_byte(skb, udp + 8);
+    int initial = (flags & 0xb0) == 0x80;
+
+    if (skb->pkt_type == PACKET_OUTGOING) {
+        add(TRANSMIT_PACKETS, 1);
+        add(TRANSMIT_BYTES, bytes);
+        if (initial) {
+            add(TRANSMIT_INITIAL, 1);
+        }
+    } else {
+        add(RECEIVE_PACKETS, 1);
+        add(RECEIVE_BYTES, bytes);
+        if (initial) {
+            add(RECEIVE_INITIAL, 1);
+        }
+    }
+
+    return 0;
+}
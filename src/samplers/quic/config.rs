@@ -0,0 +1,119 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuicConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    interfaces: Vec<String>,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(default = "default_ports")]
+    ports: Vec<u16>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    statistics: Vec<QuicStatistic>,
+}
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            interfaces: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            ports: default_ports(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+fn default_ports() -> Vec<u16> {
+    vec![443]
+}
+
+fn default_statistics() -> Vec<QuicStatistic> {
+    QuicStatistic::iter().collect()
+}
+
+impl QuicConfig {
+    /// The network interfaces to watch, all interfaces other than loopback
+    /// are watched if none are configured
+    pub fn interfaces(&self) -> Vec<String> {
+        if !self.interfaces.is_empty() {
+            return self.interfaces.clone();
+        }
+        let mut interfaces = Vec::new();
+        if let Ok(entries) = std::fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                if let Ok(name) = entry.file_name().into_string() {
+                    if name != "lo" {
+                        interfaces.push(name);
+                    }
+                }
+            }
+        }
+        interfaces.sort();
+        interfaces
+    }
+
+    /// The UDP ports which carry QUIC traffic
+    pub fn ports(&self) -> &[u16] {
+        &self.ports
+    }
+}
+
+impl SamplerConfig for QuicConfig {
+    type Statistic = QuicStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        self.statistics.clone()
+    }
+}
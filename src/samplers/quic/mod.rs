@@ -0,0 +1,179 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use async_trait::async_trait;
+
+use std::sync::{Arc, Mutex};
+
+use crate::common::bpf::BPF;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+#[cfg(feature = "bpf")]
+use std::time::Instant;
+
+mod config;
+mod stat;
+
+pub use config::QuicConfig;
+pub use stat::QuicStatistic;
+
+#[allow(dead_code)]
+pub struct Quic {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    common: Common,
+    /// raw sockets which the filter is attached to, closed when dropped
+    #[cfg(feature = "bpf")]
+    sockets: Vec<bcc::RawSocket>,
+    statistics: Vec<QuicStatistic>,
+}
+
+impl Quic {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && !self.sampler_config().statistics().is_empty()
+    }
+
+    fn init_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let config = self.common.config().samplers().quic();
+
+                let ports: Vec<String> = config
+                    .ports()
+                    .iter()
+                    .map(|port| format!("port == {}", port))
+                    .collect();
+                let port_match = if ports.is_empty() {
+                    "0".to_string()
+                } else {
+                    ports.join(" || ")
+                };
+                let code = include_str!("bpf.c").replace("PORT_MATCH", &port_match);
+                let mut bpf = bcc::BPF::new(&code)?;
+
+                let interfaces = config.interfaces();
+                debug!("attaching quic socket filter to: {:?}", interfaces);
+                self.sockets = bcc::SocketBuilder::new()
+                    .handler("quic_filter")
+                    .add_interfaces(&interfaces)
+                    .attach(&mut bpf)?;
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Some(counts) = bpf.counters("counts") {
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    let value = counts.get(&statistic.index()).copied().unwrap_or(0);
+                    self.common().record_counter(statistic, time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sampler for Quic {
+    type Statistic = QuicStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().quic().statistics();
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            common,
+            #[cfg(feature = "bpf")]
+            sockets: Vec::new(),
+            statistics,
+        };
+
+        if let Err(e) = sampler.init_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().quic().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize quic sampler {}", e);
+                    } else {
+                        error!("failed to initialize quic sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().quic()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
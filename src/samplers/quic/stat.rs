@@ -0,0 +1,72 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum QuicStatistic {
+    #[strum(serialize = "quic/receive/bytes")]
+    ReceiveBytes,
+    #[strum(serialize = "quic/receive/initial")]
+    ReceiveInitial,
+    #[strum(serialize = "quic/receive/packets")]
+    ReceivePackets,
+    #[strum(serialize = "quic/transmit/bytes")]
+    TransmitBytes,
+    #[strum(serialize = "quic/transmit/initial")]
+    TransmitInitial,
+    #[strum(serialize = "quic/transmit/packets")]
+    TransmitPackets,
+}
+
+impl QuicStatistic {
+    /// The index of the counter in the `counts` BPF array
+    pub fn index(self) -> u32 {
+        match self {
+            Self::ReceivePackets => 0,
+            Self::ReceiveBytes => 1,
+            Self::ReceiveInitial => 2,
+            Self::TransmitPackets => 3,
+            Self::TransmitBytes => 4,
+            Self::TransmitInitial => 5,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for QuicStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for QuicStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        QuicStatistic::from_str(s)
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const INFINIBAND: &str = "/sys/class/infiniband";
+
+/// A port of an RDMA device
+struct Port {
+    device: String,
+    port: String,
+}
+
+impl Port {
+    fn path(&self, file: &str) -> String {
+        format!(
+            "{}/{}/ports/{}/{}",
+            INFINIBAND, self.device, self.port, file
+        )
+    }
+}
+
+pub struct Rdma {
+    common: Common,
+    ports: Vec<Port>,
+    statistics: Vec<RdmaConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Rdma {
+    type Statistic = RdmaStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().rdma().statistics.clone();
+        let ports = discover_ports()?;
+        if ports.is_empty() {
+            return Err(format_err!("no rdma devices found"));
+        }
+        let sampler = Self {
+            common,
+            ports,
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register_ports();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().rdma().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize rdma sampler {}", e);
+                    } else {
+                        error!("failed to initialize rdma sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().rdma()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().rdma().statistics.clone();
+        if self.enabled() {
+            self.register_ports();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_counters().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Rdma {
+    fn register_ports(&self) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in &self.statistics {
+            for port in &self.ports {
+                let statistic = RdmaStatistic::new(&port.device, &port.port, *statistic);
+                self.metrics().register(&statistic);
+                self.metrics().add_output(&statistic, Output::Reading);
+                if !percentiles.is_empty() {
+                    self.metrics()
+                        .add_summary(&statistic, Summary::stream(self.samples()));
+                }
+                for percentile in percentiles {
+                    self.metrics()
+                        .add_output(&statistic, Output::Percentile(*percentile));
+                }
+            }
+        }
+    }
+
+    async fn sample_counters(&self) -> Result<(), std::io::Error> {
+        for port in &self.ports {
+            let time = Instant::now();
+            for statistic in &self.statistics {
+                // hardware counters are driver specific, and missing counters
+                // are skipped
+                if let Ok(Ok(value)) = tokio::fs::read_to_string(port.path(statistic.file()))
+                    .await
+                    .map(|v| v.trim().parse::<u64>())
+                {
+                    let metric = RdmaStatistic::new(&port.device, &port.port, *statistic);
+                    self.common().record_counter(
+                        &metric,
+                        time,
+                        value.wrapping_mul(statistic.multiplier()),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds each port of each RDMA device
+fn discover_ports() -> Result<Vec<Port>, std::io::Error> {
+    let mut ports = Vec::new();
+    for device in std::fs::read_dir(INFINIBAND)? {
+        let device = match device?.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        for port in std::fs::read_dir(format!("{}/{}/ports", INFINIBAND, device))? {
+            if let Ok(port) = port?.file_name().into_string() {
+                ports.push(Port {
+                    device: device.clone(),
+                    port,
+                });
+            }
+        }
+    }
+    ports.sort_by(|a, b| (&a.device, &a.port).cmp(&(&b.device, &b.port)));
+    Ok(ports)
+}
@@ -0,0 +1,131 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum RdmaConfigStatistic {
+    #[strum(serialize = "congestion/cnp_handled")]
+    CongestionCnpHandled,
+    #[strum(serialize = "congestion/cnp_sent")]
+    CongestionCnpSent,
+    #[strum(serialize = "congestion/ecn_marked")]
+    CongestionEcnMarked,
+    #[strum(serialize = "errors/symbol")]
+    ErrorsSymbol,
+    #[strum(serialize = "link/downed")]
+    LinkDowned,
+    #[strum(serialize = "link/error_recovery")]
+    LinkErrorRecovery,
+    #[strum(serialize = "receive/bytes")]
+    ReceiveBytes,
+    #[strum(serialize = "receive/errors")]
+    ReceiveErrors,
+    #[strum(serialize = "receive/out_of_sequence")]
+    ReceiveOutOfSequence,
+    #[strum(serialize = "receive/packets")]
+    ReceivePackets,
+    #[strum(serialize = "transmit/bytes")]
+    TransmitBytes,
+    #[strum(serialize = "transmit/discards")]
+    TransmitDiscards,
+    #[strum(serialize = "transmit/packets")]
+    TransmitPackets,
+    #[strum(serialize = "transmit/wait")]
+    TransmitWait,
+}
+
+impl RdmaConfigStatistic {
+    /// The path of the counter relative to the port directory
+    pub fn file(self) -> &'static str {
+        match self {
+            Self::CongestionCnpHandled => "hw_counters/rp_cnp_handled",
+            Self::CongestionCnpSent => "hw_counters/np_cnp_sent",
+            Self::CongestionEcnMarked => "hw_counters/np_ecn_marked_roce_packets",
+            Self::ErrorsSymbol => "counters/symbol_error",
+            Self::LinkDowned => "counters/link_downed",
+            Self::LinkErrorRecovery => "counters/link_error_recovery",
+            Self::ReceiveBytes => "counters/port_rcv_data",
+            Self::ReceiveErrors => "counters/port_rcv_errors",
+            Self::ReceiveOutOfSequence => "hw_counters/out_of_sequence",
+            Self::ReceivePackets => "counters/port_rcv_packets",
+            Self::TransmitBytes => "counters/port_xmit_data",
+            Self::TransmitDiscards => "counters/port_xmit_discards",
+            Self::TransmitPackets => "counters/port_xmit_packets",
+            Self::TransmitWait => "counters/port_xmit_wait",
+        }
+    }
+
+    /// The factor which converts the counter to the unit of the statistic
+    pub fn multiplier(self) -> u64 {
+        match self {
+            // data counters are in units of 4 byte words
+            Self::ReceiveBytes | Self::TransmitBytes => 4,
+            _ => 1,
+        }
+    }
+}
+
+impl TryFrom<&str> for RdmaConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        RdmaConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RdmaStatistic {
+    name: String,
+    statistic: RdmaConfigStatistic,
+}
+
+impl RdmaStatistic {
+    pub fn new(device: &str, port: &str, statistic: RdmaConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("rdma/{}/port{}/{}", device, port, suffix),
+                format!("rdma/{}", suffix),
+                &[("device", device), ("port", port)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> RdmaConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for RdmaStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Counter
+    }
+}
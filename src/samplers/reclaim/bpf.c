@@ -0,0 +1,119 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+
+// events, which index the counters array
+#define DIRECT_EVENTS 0
+#define DIRECT_PAGES 1
+#define KSWAPD_WAKEUPS 2
+#define MEMCG_EVENTS 3
+
+BPF_HASH(direct_start, u32, u64);
+BPF_HASH(memcg_start, u32, u64);
+BPF_ARRAY(counters, u64, 4);
+BPF_HISTOGRAM(direct_latency, int, 461);
+BPF_HISTOGRAM(memcg_latency, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+int trace_direct_begin(struct tracepoint__vmscan__mm_vmscan_direct_reclaim_begin *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    direct_start.update(&pid, &ts);
+    add(DIRECT_EVENTS, 1);
+    return 0;
+}
+
+int trace_direct_end(struct tracepoint__vmscan__mm_vmscan_direct_reclaim_end *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = direct_start.lookup(&pid);
+
+    add(DIRECT_PAGES, args->nr_reclaimed);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    direct_latency.increment(value_to_index2(delta));
+    direct_start.delete(&pid);
+    return 0;
+}
+
+int trace_kswapd_wake(struct tracepoint__vmscan__mm_vmscan_kswapd_wake *args)
+{
+    add(KSWAPD_WAKEUPS, 1);
+    return 0;
+}
+
+// memcg reclaim tracepoints are only present in kernels built with memory
+// cgroup support, MEMCG is defined when they are available
+#ifdef MEMCG
+int trace_memcg_begin(struct tracepoint__vmscan__mm_vmscan_memcg_reclaim_begin *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    memcg_start.update(&pid, &ts);
+    add(MEMCG_EVENTS, 1);
+    return 0;
+}
+
+int trace_memcg_end(struct tracepoint__vmscan__mm_vmscan_memcg_reclaim_end *args)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 *tsp = memcg_start.lookup(&pid);
+
+    // skip events with unknown start
+    if (tsp == 0) {
+        return 0;
+    }
+
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    memcg_latency.increment(value_to_index2(delta));
+    memcg_start.delete(&pid);
+    return 0;
+}
+#endif
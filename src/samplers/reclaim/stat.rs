@@ -0,0 +1,83 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ReclaimStatistic {
+    #[strum(serialize = "reclaim/direct/events")]
+    DirectEvents,
+    #[strum(serialize = "reclaim/direct/latency")]
+    DirectLatency,
+    #[strum(serialize = "reclaim/direct/pages")]
+    DirectPages,
+    #[strum(serialize = "reclaim/kswapd/wakeups")]
+    KswapdWakeups,
+    #[strum(serialize = "reclaim/memcg/events")]
+    MemcgEvents,
+    #[strum(serialize = "reclaim/memcg/latency")]
+    MemcgLatency,
+}
+
+impl ReclaimStatistic {
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::DirectEvents => Some(0),
+            Self::DirectPages => Some(1),
+            Self::KswapdWakeups => Some(2),
+            Self::MemcgEvents => Some(3),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::DirectLatency => Some("direct_latency"),
+            Self::MemcgLatency => Some("memcg_latency"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ReclaimStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.bpf_table().is_some() {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for ReclaimStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ReclaimStatistic::from_str(s)
+    }
+}
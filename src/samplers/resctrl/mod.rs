@@ -0,0 +1,186 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const SYS_FS_RESCTRL: &str = "/sys/fs/resctrl";
+
+pub struct Resctrl {
+    common: Common,
+    /// statistics which have been registered, as resctrl groups are
+    /// discovered at runtime
+    registered: HashSet<ResctrlStatistic>,
+    statistics: Vec<ResctrlConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Resctrl {
+    type Statistic = ResctrlStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().resctrl().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().resctrl().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize resctrl sampler {}", e);
+                    } else {
+                        error!("failed to initialize resctrl sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().resctrl()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().resctrl().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_groups().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Resctrl {
+    fn record(&mut self, statistic: ResctrlStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    async fn sample_groups(&mut self) -> Result<(), std::io::Error> {
+        for (name, path) in groups(Path::new(SYS_FS_RESCTRL)).await? {
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if let Some(value) = read_mon_data(&path, statistic.file()).await {
+                    self.record(ResctrlStatistic::new(&name, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists the groups with monitoring data. These are the default group, which
+/// is named `root`, each control group, and each monitoring group, which is
+/// named `<control group>/<monitoring group>`.
+async fn groups(root: &Path) -> Result<Vec<(String, PathBuf)>, std::io::Error> {
+    let mut control = vec![("root".to_string(), root.to_path_buf())];
+    let mut entries = tokio::fs::read_dir(root).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().join("mon_data").is_dir() {
+            control.push((name, entry.path()));
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (name, path) in control {
+        if let Ok(mut entries) = tokio::fs::read_dir(path.join("mon_groups")).await {
+            while let Some(entry) = entries.next_entry().await? {
+                let monitor = entry.file_name().to_string_lossy().to_string();
+                groups.push((format!("{}/{}", name, monitor), entry.path()));
+            }
+        }
+        groups.push((name, path));
+    }
+    Ok(groups)
+}
+
+/// Reads a monitoring file of a group, summed across the L3 cache domains.
+/// Returns `None` if it is not supported, or is `Unavailable` in any domain.
+async fn read_mon_data(group: &Path, file: &str) -> Option<u64> {
+    let mut domains = tokio::fs::read_dir(group.join("mon_data")).await.ok()?;
+    let mut total = 0;
+    let mut found = false;
+    while let Ok(Some(domain)) = domains.next_entry().await {
+        let content = tokio::fs::read_to_string(domain.path().join(file))
+            .await
+            .ok()?;
+        total += content.trim().parse::<u64>().ok()?;
+        found = true;
+    }
+    if found {
+        Some(total)
+    } else {
+        None
+    }
+}
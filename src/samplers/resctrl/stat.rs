@@ -0,0 +1,97 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ResctrlConfigStatistic {
+    #[strum(serialize = "llc/occupancy")]
+    LlcOccupancy,
+    #[strum(serialize = "memory_bandwidth/local")]
+    MemoryBandwidthLocal,
+    #[strum(serialize = "memory_bandwidth/total")]
+    MemoryBandwidthTotal,
+}
+
+impl ResctrlConfigStatistic {
+    /// The file in each `mon_data/mon_L3_*` directory of a group
+    pub fn file(self) -> &'static str {
+        match self {
+            Self::LlcOccupancy => "llc_occupancy",
+            Self::MemoryBandwidthLocal => "mbm_local_bytes",
+            Self::MemoryBandwidthTotal => "mbm_total_bytes",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::LlcOccupancy => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for ResctrlConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ResctrlConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ResctrlStatistic {
+    name: String,
+    statistic: ResctrlConfigStatistic,
+}
+
+impl ResctrlStatistic {
+    /// A statistic of a resctrl group, which is named `resctrl/<group>/...`
+    pub fn new(group: &str, statistic: ResctrlConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("resctrl/{}/{}", group, suffix),
+                format!("resctrl/{}", suffix),
+                &[("group", group)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> ResctrlConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ResctrlStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -15,10 +15,19 @@ pub struct RezolusConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "default_overhead")]
+    overhead: bool,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<RezolusStatistic>,
 }
 
@@ -26,17 +35,31 @@ impl Default for RezolusConfig {
     fn default() -> Self {
         Self {
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
+            overhead: default_overhead(),
             percentiles: crate::common::default_percentiles(),
             statistics: default_statistics(),
         }
     }
 }
 
+fn default_overhead() -> bool {
+    true
+}
+
 fn default_statistics() -> Vec<RezolusStatistic> {
     RezolusStatistic::iter().collect()
 }
 
+impl RezolusConfig {
+    /// Whether to report the overhead of each sampler
+    pub fn overhead(&self) -> bool {
+        self.overhead
+    }
+}
+
 impl SamplerConfig for RezolusConfig {
     type Statistic = RezolusStatistic;
 
@@ -44,10 +67,18 @@ impl SamplerConfig for RezolusConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
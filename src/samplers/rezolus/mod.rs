@@ -2,10 +2,11 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::SeekFrom;
 
 use async_trait::async_trait;
+use rustcommon_metrics::*;
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
 
@@ -32,6 +33,9 @@ pub struct Rezolus {
     nanos_per_tick: u64,
     proc_stat: Option<File>,
     proc_statm: Option<File>,
+    /// names of the overhead and counter reset statistics which have been
+    /// registered, as they are only known once samplers are running
+    registered: HashSet<String>,
     statistics: Vec<RezolusStatistic>,
 }
 
@@ -46,11 +50,13 @@ impl Sampler for Rezolus {
             nanos_per_tick: nanos_per_tick() as u64,
             proc_stat: None,
             proc_statm: None,
+            registered: HashSet::new(),
             statistics,
         };
         if sampler.sampler_config().enabled() {
             sampler.register();
         }
+        sampler.enable_overhead();
         Ok(sampler)
     }
 
@@ -58,7 +64,7 @@ impl Sampler for Rezolus {
         if common.config().samplers().rezolus().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -82,6 +88,15 @@ impl Sampler for Rezolus {
         self.common.config().samplers().rezolus()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+        self.registered.clear();
+        self.enable_overhead();
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -98,11 +113,84 @@ impl Sampler for Rezolus {
         let r = self.sample_cpu().await;
         self.map_result(r)?;
 
+        self.sample_overhead();
+        self.sample_resets();
+
         Ok(())
     }
 }
 
 impl Rezolus {
+    /// Overhead is only recorded while it is being reported
+    fn enable_overhead(&self) {
+        let config = self.common.config().samplers().rezolus();
+        self.common
+            .overhead()
+            .set_enabled(config.enabled() && config.overhead());
+    }
+
+    fn register_statistic<S: Statistic<AtomicU64, AtomicU32>>(&mut self, statistic: &S) {
+        if self.registered.contains(statistic.name()) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            if statistic.source() == Source::Distribution {
+                self.metrics().add_summary(statistic, self.heatmap(SECOND));
+            } else {
+                self.metrics()
+                    .add_summary(statistic, Summary::stream(self.samples()));
+            }
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.registered.insert(statistic.name().to_string());
+    }
+
+    /// Records the overhead which each sampler has reported since the last
+    /// sample
+    fn sample_overhead(&mut self) {
+        let time = Instant::now();
+        for (sampler, overhead) in self.common.overhead().take() {
+            let statistic = SamplerStatistic::new(sampler, OverheadStatistic::SampleTime);
+            self.register_statistic(&statistic);
+            for value in overhead.sample_times {
+                self.common().record_bucket(&statistic, time, value, 1);
+            }
+
+            let statistic = SamplerStatistic::new(sampler, OverheadStatistic::Errors);
+            self.register_statistic(&statistic);
+            self.common()
+                .record_counter(&statistic, time, overhead.errors);
+
+            let statistic = SamplerStatistic::new(sampler, OverheadStatistic::Restarts);
+            self.register_statistic(&statistic);
+            self.common()
+                .record_counter(&statistic, time, overhead.restarts);
+
+            if overhead.bpf_read_time > 0 {
+                let statistic = SamplerStatistic::new(sampler, OverheadStatistic::BpfReadTime);
+                self.register_statistic(&statistic);
+                self.common()
+                    .record_counter(&statistic, time, overhead.bpf_read_time);
+            }
+        }
+    }
+
+    /// Records the number of resets of each counter which has been reset
+    fn sample_resets(&mut self) {
+        let time = Instant::now();
+        for (name, resets) in self.common.counters().resets() {
+            let statistic = CounterResetStatistic::new(&name);
+            self.register_statistic(&statistic);
+            self.common().record_counter(&statistic, time, resets);
+        }
+    }
+
     async fn sample_cpu(&mut self) -> Result<(), std::io::Error> {
         if self.proc_stat.is_none() {
             let pid: u32 = std::process::id();
@@ -130,7 +218,7 @@ impl Rezolus {
             let time = Instant::now();
             for statistic in &self.statistics {
                 if let Some(value) = result.get(statistic) {
-                    let _ = self.metrics().record_counter(statistic, time, *value);
+                    self.common().record_counter(statistic, time, *value);
                 }
             }
         }
@@ -10,6 +10,8 @@ use serde_derive::{Deserialize, Serialize};
 use strum::ParseError;
 use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 
+use crate::common::labels::labeled;
+
 #[derive(
     Clone,
     Copy,
@@ -55,3 +57,86 @@ impl TryFrom<&str> for RezolusStatistic {
         RezolusStatistic::from_str(s)
     }
 }
+
+/// The overhead of running a sampler
+#[derive(Clone, Copy, Debug, Eq, IntoStaticStr, PartialEq, Hash)]
+pub enum OverheadStatistic {
+    #[strum(serialize = "bpf/read_time")]
+    BpfReadTime,
+    #[strum(serialize = "errors")]
+    Errors,
+    #[strum(serialize = "restarts")]
+    Restarts,
+    #[strum(serialize = "sample_time")]
+    SampleTime,
+}
+
+impl OverheadStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::SampleTime => Source::Distribution,
+            _ => Source::Counter,
+        }
+    }
+}
+
+/// The overhead of a single sampler, which is named
+/// `rezolus/sampler/<sampler>/...`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SamplerStatistic {
+    name: String,
+    statistic: OverheadStatistic,
+}
+
+impl SamplerStatistic {
+    pub fn new(sampler: &str, statistic: OverheadStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("rezolus/sampler/{}/{}", sampler, suffix),
+                format!("rezolus/sampler/{}", suffix),
+                &[("sampler", sampler)],
+            ),
+            statistic,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SamplerStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
+
+/// The number of times the source of a counter has been reset, which is named
+/// `rezolus/counter/<statistic>/resets`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CounterResetStatistic {
+    name: String,
+}
+
+impl CounterResetStatistic {
+    pub fn new(statistic: &str) -> Self {
+        Self {
+            name: labeled(
+                format!("rezolus/counter/{}/resets", statistic),
+                "rezolus/counter/resets".to_string(),
+                &[("statistic", statistic)],
+            ),
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for CounterResetStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Counter
+    }
+}
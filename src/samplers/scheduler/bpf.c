@@ -1,67 +1,52 @@
 // Based on: https://github.com/iovisor/bcc/blob/master/tools/runqlat.py
 
 #include <uapi/linux/ptrace.h>
-#include <linux/sched.h>
-#include <linux/nsproxy.h>
-#include <linux/pid_namespace.h>
 
-typedef struct pid_key {
-    u64 id;
-    u64 slot;
-} pid_key_t;
+// the state reported by sched_switch for a task which was preempted, from
+// Linux 4.14
+#define TASK_REPORT_MAX 0x100
 
-typedef struct pidns_key {
-    u64 id;
-    u64 slot;
-} pidns_key_t;
+// indices into the counters array
+#define VOLUNTARY 0
+#define INVOLUNTARY 1
+#define FORKS 2
 
 BPF_TABLE("hash", u32, u64, start, 65536);
+BPF_ARRAY(counters, u64, 3);
+
+// migrations keyed by the source cpu in the upper 16 bits and the destination
+// cpu in the lower 16 bits, so that userspace can count those between nodes
+BPF_HASH(migrations, u32, u64);
 
 // value_to_index() gives us from 0-460 as the index
 BPF_HISTOGRAM(runqueue_latency, int, 461);
 
-struct rq;
-
-// from /sys/kernel/debug/tracing/events/sched/sched_wakeup/format
-struct sched_wakeup_arg {
-    u64 __unused__;
-    char comm[16];
-    pid_t pid;
-    int prio;
-    int success;
-    int target_cpu;
-};
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
 
-static int trace_enqueue(u32 tgid, u32 pid)
+static int trace_enqueue(u32 pid)
 {
+    if (!pid) {
+        return 0;
+    }
     u64 ts = bpf_ktime_get_ns();
     start.update(&pid, &ts);
     return 0;
 }
 
-int trace_wake_up_new_task(struct pt_regs *ctx, struct task_struct *p)
+int trace_wakeup(struct tracepoint__sched__sched_wakeup *args)
 {
-    return trace_enqueue(p->tgid, p->pid);
+    return trace_enqueue(args->pid);
 }
 
-int trace_ttwu_do_wakeup(struct pt_regs *ctx, struct rq *rq, struct task_struct *p,
-    int wake_flags)
+int trace_wakeup_new(struct tracepoint__sched__sched_wakeup_new *args)
 {
-    return trace_enqueue(p->tgid, p->pid);
+    return trace_enqueue(args->pid);
 }
 
-// from /sys/kernel/debug/tracing/events/sched/sched_switch/format
-struct sched_switch_arg {
-    u64 __unused__;
-    char prev_comm[16];
-    pid_t prev_pid;
-    int prev_prio;
-    long prev_state;
-    char next_comm[16];
-    pid_t next_pid;
-    int next_prio;
-};
-
 // histogram indexing
 static unsigned int value_to_index2(unsigned int value) {
     unsigned int index = 460;
@@ -96,19 +81,18 @@ static unsigned int value_to_index2(unsigned int value) {
     return index;
 }
 
-int trace_run(struct pt_regs *ctx, struct task_struct *prev)
+int trace_switch(struct tracepoint__sched__sched_switch *args)
 {
-    // handle involuntary context switch
-    if (prev->state == TASK_RUNNING) {
-        u32 tgid = prev->tgid;
-        u32 pid = prev->pid;
-        u64 ts = bpf_ktime_get_ns();
-        start.update(&pid, &ts);
+    // handle involuntary context switch, where the task remains runnable
+    long state = args->prev_state;
+    if (state == 0 || state == TASK_REPORT_MAX) {
+        add(INVOLUNTARY, 1);
+        trace_enqueue(args->prev_pid);
+    } else {
+        add(VOLUNTARY, 1);
     }
 
-    // get tgid and pid
-    u32 tgid = bpf_get_current_pid_tgid() >> 32;
-    u32 pid = bpf_get_current_pid_tgid();
+    u32 pid = args->next_pid;
 
     // lookup start time
     u64 *tsp = start.lookup(&pid);
@@ -129,3 +113,18 @@ int trace_run(struct pt_regs *ctx, struct task_struct *prev)
     start.delete(&pid);
     return 0;
 }
+
+int trace_migrate(struct tracepoint__sched__sched_migrate_task *args)
+{
+    u32 key = (args->orig_cpu & 0xFFFF) << 16 | (args->dest_cpu & 0xFFFF);
+    u64 zero = 0;
+    u64 *count = migrations.lookup_or_try_init(&key, &zero);
+    if (count) lock_xadd(count, 1);
+    return 0;
+}
+
+int trace_fork(struct tracepoint__sched__sched_process_fork *args)
+{
+    add(FORKS, 1);
+    return 0;
+}
@@ -0,0 +1,116 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// CO-RE version of bpf.c, built with the bpf_core feature. Perf counters
+// require the bpf feature.
+// Based on: https://github.com/iovisor/bcc/blob/master/libbpf-tools/runqlat.bpf.c
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+#include <bpf/bpf_core_read.h>
+
+#include "histogram.h"
+
+#define TASK_RUNNING 0
+
+// indices into the counters array
+#define VOLUNTARY 0
+#define INVOLUNTARY 1
+#define FORKS 2
+
+HASH(start, u32, u64);
+COUNTERS(counters, 3);
+
+// migrations keyed by the source cpu in the upper 16 bits and the destination
+// cpu in the lower 16 bits, so that userspace can count those between nodes
+HASH(migrations, u32, u64);
+
+HISTOGRAM(runqueue_latency);
+
+// task_struct->state was renamed to __state in 5.14
+struct task_struct___old {
+    long state;
+} __attribute__((preserve_access_index));
+
+static __always_inline long task_state(struct task_struct *task)
+{
+    if (bpf_core_field_exists(task->__state)) {
+        return BPF_CORE_READ(task, __state);
+    }
+    return BPF_CORE_READ((struct task_struct___old *)task, state);
+}
+
+static __always_inline int trace_enqueue(u32 pid)
+{
+    if (!pid) {
+        return 0;
+    }
+    u64 ts = bpf_ktime_get_ns();
+    bpf_map_update_elem(&start, &pid, &ts, BPF_ANY);
+    return 0;
+}
+
+SEC("tp_btf/sched_wakeup")
+int BPF_PROG(sched_wakeup, struct task_struct *p)
+{
+    return trace_enqueue(p->pid);
+}
+
+SEC("tp_btf/sched_wakeup_new")
+int BPF_PROG(sched_wakeup_new, struct task_struct *p)
+{
+    return trace_enqueue(p->pid);
+}
+
+SEC("tp_btf/sched_switch")
+int BPF_PROG(sched_switch, bool preempt, struct task_struct *prev, struct task_struct *next)
+{
+    // handle involuntary context switch
+    if (task_state(prev) == TASK_RUNNING) {
+        increment(&counters, INVOLUNTARY);
+        trace_enqueue(prev->pid);
+    } else {
+        increment(&counters, VOLUNTARY);
+    }
+
+    u32 pid = next->pid;
+
+    // skip events with unknown start
+    u64 *tsp = bpf_map_lookup_elem(&start, &pid);
+    if (!tsp) {
+        return 0;
+    }
+
+    // record latency in microseconds
+    histogram_add(&runqueue_latency, (bpf_ktime_get_ns() - *tsp) / 1000);
+
+    bpf_map_delete_elem(&start, &pid);
+    return 0;
+}
+
+SEC("tracepoint/sched/sched_migrate_task")
+int sched_migrate_task(struct trace_event_raw_sched_migrate_task *args)
+{
+    u32 key = (args->orig_cpu & 0xFFFF) << 16 | (args->dest_cpu & 0xFFFF);
+    u64 *count = bpf_map_lookup_elem(&migrations, &key);
+    if (!count) {
+        u64 zero = 0;
+        bpf_map_update_elem(&migrations, &key, &zero, BPF_NOEXIST);
+        count = bpf_map_lookup_elem(&migrations, &key);
+    }
+    if (count) {
+        __sync_fetch_and_add(count, 1);
+    }
+    return 0;
+}
+
+SEC("tracepoint/sched/sched_process_fork")
+int sched_process_fork(void *args)
+{
+    increment(&counters, FORKS);
+    return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
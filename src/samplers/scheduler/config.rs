@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use strum::IntoEnumIterator;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::*;
 
@@ -17,12 +17,19 @@ pub struct SchedulerConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
     #[serde(default = "crate::common::default_percentiles")]
     percentiles: Vec<f64>,
     #[serde(default)]
     perf_events: bool,
-    #[serde(default = "default_statistics")]
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
     statistics: Vec<SchedulerStatistic>,
 }
 
@@ -31,7 +38,9 @@ impl Default for SchedulerConfig {
         Self {
             bpf: Default::default(),
             enabled: Default::default(),
+            histogram: Default::default(),
             interval: Default::default(),
+            jitter: Default::default(),
             percentiles: crate::common::default_percentiles(),
             perf_events: Default::default(),
             statistics: default_statistics(),
@@ -54,10 +63,18 @@ impl SamplerConfig for SchedulerConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
@@ -73,7 +90,7 @@ impl SamplerConfig for SchedulerConfig {
                 if self.perf_events() {
                     enabled.push(*statistic);
                 }
-            } else if statistic.bpf_table().is_some() {
+            } else if statistic.bpf() {
                 if self.bpf() {
                     enabled.push(*statistic);
                 }
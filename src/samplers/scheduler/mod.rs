@@ -91,7 +91,7 @@ impl Sampler for Scheduler {
         if common.config().samplers().scheduler().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -115,6 +115,13 @@ impl Sampler for Scheduler {
         self.common.config().samplers().scheduler()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -129,12 +136,29 @@ impl Sampler for Scheduler {
         // we do perf sampling first, since it is time critical to keep it
         // between underlying counter updates
         #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf_perf_counters())?;
+        {
+            let r = self.time_bpf(|s| s.sample_bpf_perf_counters());
+            self.map_result(r)?;
+        }
 
         let r = self.sample_proc_stat().await;
         self.map_result(r)?;
-        #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+
+        if self.statistics.iter().any(|s| s.loadavg().is_some()) {
+            let r = self.sample_loadavg().await;
+            self.map_result(r)?;
+        }
+
+        if self.statistics.iter().any(|s| s.task()) {
+            let r = self.sample_tasks().await;
+            self.map_result(r)?;
+        }
+
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
@@ -231,7 +255,7 @@ impl Scheduler {
                 if let Some(value) = result.get(statistic) {
                     match statistic.source() {
                         Source::Counter => {
-                            let _ = self.metrics().record_counter(statistic, time, *value);
+                            self.common().record_counter(statistic, time, *value);
                         }
                         Source::Gauge => {
                             let _ = self.metrics().record_gauge(statistic, time, *value);
@@ -245,23 +269,108 @@ impl Scheduler {
         Ok(())
     }
 
-    #[cfg(feature = "bpf")]
+    /// Reads the 1, 5, and 15 minute load averages, which are recorded in
+    /// hundredths so that they can be stored as integers
+    async fn sample_loadavg(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string("/proc/loadavg").await?;
+        let loadavg = parse_loadavg(&content);
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            if let Some(value) = statistic.loadavg().and_then(|i| loadavg.get(i)) {
+                let _ =
+                    self.metrics()
+                        .record_gauge(statistic, time, (*value * 100.0).round() as u64);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts processes, threads, and the processes in uninterruptible sleep
+    /// or which are zombies, from the state of each process in /proc
+    async fn sample_tasks(&mut self) -> Result<(), std::io::Error> {
+        let mut processes = 0;
+        let mut threads = 0;
+        let mut uninterruptible = 0;
+        let mut zombie = 0;
+
+        let mut entries = tokio::fs::read_dir("/proc").await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name().to_str().map(|v| v.parse::<u32>().is_ok()) != Some(true) {
+                continue;
+            }
+            // the process may exit at any point while we read it
+            let (state, count) = match tokio::fs::read_to_string(entry.path().join("stat"))
+                .await
+                .ok()
+                .and_then(|content| parse_task_stat(&content))
+            {
+                Some(v) => v,
+                None => continue,
+            };
+            processes += 1;
+            threads += count;
+            match state {
+                'D' => uninterruptible += 1,
+                'Z' => zombie += 1,
+                _ => {}
+            }
+        }
+
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            let value = match statistic {
+                SchedulerStatistic::ProcessesTotal => processes,
+                SchedulerStatistic::ProcessesUninterruptible => uninterruptible,
+                SchedulerStatistic::ProcessesZombie => zombie,
+                SchedulerStatistic::ThreadsTotal => threads,
+                _ => continue,
+            };
+            let _ = self.metrics().record_gauge(statistic, time, value);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn sample_bpf(&self) -> Result<(), std::io::Error> {
         use crate::common::MICROSECOND;
 
-        // sample bpf
+        // counters are sampled every interval
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let time = Instant::now();
+            if let Some(counters) = bpf.counters("counters") {
+                for statistic in &self.statistics {
+                    if let Some(index) = statistic.index() {
+                        let value = counters.get(&index).copied().unwrap_or(0);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+            if let Some(migrations) = bpf.counters("migrations") {
+                let (cpu, numa) = self.count_migrations(&migrations);
+                for statistic in &self.statistics {
+                    let value = match statistic {
+                        SchedulerStatistic::MigrationsCpu => cpu,
+                        SchedulerStatistic::MigrationsNuma => numa,
+                        _ => continue,
+                    };
+                    self.common().record_counter(statistic, time, value);
+                }
+            }
+        }
+
+        // histograms are sampled once per window
         {
-            if self.bpf_last.lock().unwrap().elapsed()
-                >= Duration::new(self.general_config().window() as u64, 0)
-            {
+            if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
                 if let Some(ref bpf) = self.bpf {
                     let bpf = bpf.lock().unwrap();
                     let time = Instant::now();
                     for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
-                        if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
-                            for (&value, &count) in &map_from_table(&mut table) {
+                        if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                            for (&value, &count) in &histogram {
                                 if count > 0 {
-                                    let _ = self.metrics().record_bucket(
+                                    self.common().record_bucket(
                                         statistic,
                                         time,
                                         value * MICROSECOND,
@@ -279,6 +388,24 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Sums the migrations between each pair of cpus, returning the total and
+    /// those where the cpus are on different NUMA nodes
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+    fn count_migrations(&self, migrations: &HashMap<u32, u64>) -> (u64, u64) {
+        let hardware_info = self.common.hardware_info();
+        let mut cpu = 0;
+        let mut numa = 0;
+        for (key, count) in migrations {
+            cpu += count;
+            let source = hardware_info.get_numa((key >> 16) as u64);
+            let destination = hardware_info.get_numa((key & 0xFFFF) as u64);
+            if source != destination {
+                numa += count;
+            }
+        }
+        (cpu, numa)
+    }
+
     #[cfg(feature = "bpf")]
     fn sample_bpf_perf_counters(&self) -> Result<(), std::io::Error> {
         if let Some(ref bpf) = self.perf {
@@ -291,7 +418,7 @@ impl Scheduler {
                     for (_cpu, count) in map.iter() {
                         total += count;
                     }
-                    let _ = self.metrics().record_counter(stat, time, total);
+                    self.common().record_counter(stat, time, total);
                 }
             }
         }
@@ -299,19 +426,9 @@ impl Scheduler {
     }
 
     // checks that bpf is enabled in config and one or more bpf stats enabled
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn bpf_enabled(&self) -> bool {
-        if self.sampler_config().bpf() {
-            for statistic in &self.statistics {
-                match statistic {
-                    SchedulerStatistic::RunqueueLatency => {
-                        return true;
-                    }
-                    _ => {}
-                }
-            }
-        }
-        false
+        self.sampler_config().bpf() && self.statistics.iter().any(|s| s.bpf())
     }
 
     fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
@@ -323,24 +440,81 @@ impl Scheduler {
                 let code = include_str!("bpf.c");
                 let mut bpf = bcc::BPF::new(code)?;
 
-                // load + attach kprobes!
-                bcc::Kprobe::new()
-                    .handler("trace_run")
-                    .function("finish_task_switch")
-                    .attach(&mut bpf)?;
-                bcc::Kprobe::new()
-                    .handler("trace_ttwu_do_wakeup")
-                    .function("ttwu_do_wakeup")
-                    .attach(&mut bpf)?;
-                bcc::Kprobe::new()
-                    .handler("trace_wake_up_new_task")
-                    .function("wake_up_new_task")
-                    .attach(&mut bpf)?;
+                for (handler, tracepoint) in &[
+                    ("trace_fork", "sched_process_fork"),
+                    ("trace_migrate", "sched_migrate_task"),
+                    ("trace_switch", "sched_switch"),
+                    ("trace_wakeup", "sched_wakeup"),
+                    ("trace_wakeup_new", "sched_wakeup_new"),
+                ] {
+                    bcc::Tracepoint::new()
+                        .handler(handler)
+                        .subsystem("sched")
+                        .tracepoint(tracepoint)
+                        .attach(&mut bpf)?;
+                }
 
                 self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
             }
         }
 
+        #[cfg(feature = "bpf_core")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let object = include_bytes!(concat!(env!("OUT_DIR"), "/scheduler.bpf.o"));
+                let bpf = BPF::load("scheduler", object, &[])?;
+                self.bpf = Some(Arc::new(Mutex::new(bpf)));
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Parses the load averages from `/proc/loadavg`, such as
+/// `0.52 0.58 0.59 2/1024 12345`
+fn parse_loadavg(content: &str) -> Vec<f64> {
+    content
+        .split_whitespace()
+        .take(3)
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+/// Parses the state and number of threads from `/proc/[pid]/stat`. The name is
+/// enclosed in parentheses and may itself contain spaces or parentheses, so
+/// fields are located relative to the final closing parenthesis.
+fn parse_task_stat(content: &str) -> Option<(char, u64)> {
+    let end = content.rfind(')')?;
+    let mut fields = content.get((end + 1)..)?.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let threads = fields.nth(16)?.parse().ok()?;
+    Some((state, threads))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_loadavg() {
+        assert_eq!(
+            parse_loadavg("0.52 12.58 0.00 2/1024 12345\n"),
+            vec![0.52, 12.58, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_parse_task_stat() {
+        assert_eq!(
+            parse_task_stat(
+                "1234 (my (odd) daemon) D 1 1234 1234 0 -1 4194560 5012 0 17 0 250 120 0 0 \
+                 20 0 4 0 1812 234881024 2048 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 \
+                 17 3 0 0 0 0 0\n"
+            ),
+            Some(('D', 4))
+        );
+        assert_eq!(parse_task_stat("42 (defunct) Z 1"), None);
+    }
+}
@@ -35,15 +35,78 @@ pub enum SchedulerStatistic {
     RunqueueLatency,
     #[strum(serialize = "scheduler/context_switches")]
     ContextSwitches,
+    #[strum(serialize = "scheduler/context_switches/involuntary")]
+    ContextSwitchesInvoluntary,
+    #[strum(serialize = "scheduler/context_switches/voluntary")]
+    ContextSwitchesVoluntary,
+    #[strum(serialize = "scheduler/forks")]
+    Forks,
+    #[strum(serialize = "scheduler/load/1")]
+    Load1,
+    #[strum(serialize = "scheduler/load/5")]
+    Load5,
+    #[strum(serialize = "scheduler/load/15")]
+    Load15,
+    #[strum(serialize = "scheduler/migrations/cpu")]
+    MigrationsCpu,
+    #[strum(serialize = "scheduler/migrations/numa")]
+    MigrationsNuma,
     #[strum(serialize = "scheduler/processes/created")]
     ProcessesCreated,
     #[strum(serialize = "scheduler/processes/running")]
     ProcessesRunning,
     #[strum(serialize = "scheduler/processes/blocked")]
     ProcessesBlocked,
+    #[strum(serialize = "scheduler/processes/total")]
+    ProcessesTotal,
+    #[strum(serialize = "scheduler/processes/uninterruptible")]
+    ProcessesUninterruptible,
+    #[strum(serialize = "scheduler/processes/zombie")]
+    ProcessesZombie,
+    #[strum(serialize = "scheduler/threads/total")]
+    ThreadsTotal,
 }
 
 impl SchedulerStatistic {
+    /// Whether this statistic requires BPF
+    pub fn bpf(self) -> bool {
+        self.bpf_table().is_some()
+            || self.index().is_some()
+            || matches!(self, Self::MigrationsCpu | Self::MigrationsNuma)
+    }
+
+    /// The index of the statistic in the BPF counters array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::ContextSwitchesVoluntary => Some(0),
+            Self::ContextSwitchesInvoluntary => Some(1),
+            Self::Forks => Some(2),
+            _ => None,
+        }
+    }
+
+    /// The index of the load average in `/proc/loadavg`
+    pub fn loadavg(self) -> Option<usize> {
+        match self {
+            Self::Load1 => Some(0),
+            Self::Load5 => Some(1),
+            Self::Load15 => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Whether this statistic is counted from the state of each task in
+    /// `/proc/[pid]/stat`
+    pub fn task(self) -> bool {
+        matches!(
+            self,
+            Self::ProcessesTotal
+                | Self::ProcessesUninterruptible
+                | Self::ProcessesZombie
+                | Self::ThreadsTotal
+        )
+    }
+
     #[allow(dead_code)]
     pub fn bpf_table(self) -> Option<&'static str> {
         match self {
@@ -84,7 +147,15 @@ impl Statistic<AtomicU64, AtomicU32> for SchedulerStatistic {
     fn source(&self) -> Source {
         match *self {
             Self::RunqueueLatency => Source::Distribution,
-            Self::ProcessesRunning | Self::ProcessesBlocked => Source::Gauge,
+            Self::Load1
+            | Self::Load5
+            | Self::Load15
+            | Self::ProcessesRunning
+            | Self::ProcessesBlocked
+            | Self::ProcessesTotal
+            | Self::ProcessesUninterruptible
+            | Self::ProcessesZombie
+            | Self::ThreadsTotal => Source::Gauge,
             _ => Source::Counter,
         }
     }
@@ -0,0 +1,98 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlabConfig {
+    #[serde(default)]
+    caches: Vec<String>,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<SlabConfigStatistic>,
+    #[serde(default = "default_top")]
+    top: usize,
+}
+
+impl Default for SlabConfig {
+    fn default() -> Self {
+        Self {
+            caches: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+            top: default_top(),
+        }
+    }
+}
+
+fn default_statistics() -> Vec<SlabConfigStatistic> {
+    SlabConfigStatistic::iter().collect()
+}
+
+fn default_top() -> usize {
+    10
+}
+
+impl SlabConfig {
+    /// Slab caches which are always reported, in addition to the largest
+    pub fn caches(&self) -> &[String] {
+        &self.caches
+    }
+
+    /// The number of the largest slab caches, by total bytes, to report
+    pub fn top(&self) -> usize {
+        self.top
+    }
+}
+
+impl SamplerConfig for SlabConfig {
+    type Statistic = SlabStatistic;
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // slab caches are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
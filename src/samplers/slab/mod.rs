@@ -0,0 +1,221 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_SLABINFO: &str = "/proc/slabinfo";
+
+pub struct Slab {
+    common: Common,
+    page_size: u64,
+    /// statistics which have been registered, as slab caches are discovered
+    /// at runtime
+    registered: HashSet<SlabStatistic>,
+    statistics: Vec<SlabConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Slab {
+    type Statistic = SlabStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().slab().statistics.clone();
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size <= 0 {
+            return Err(format_err!("failed to get page size"));
+        }
+        Ok(Self {
+            common,
+            page_size: page_size as u64,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().slab().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize slab sampler {}", e);
+                    } else {
+                        error!("failed to initialize slab sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().slab()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().slab().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_slabinfo().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Slab {
+    fn record(&mut self, statistic: SlabStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Samples `/proc/slabinfo`, which is only readable by root, reporting the
+    /// largest caches and those in the configured allowlist
+    async fn sample_slabinfo(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_SLABINFO).await?;
+        let mut caches = parse_slabinfo(&content, self.page_size);
+        caches.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+        let config = self.common.config().samplers().slab();
+        let top = config.top();
+        let allowlist = config.caches().to_vec();
+
+        let time = Instant::now();
+        for (rank, cache) in caches.iter().enumerate() {
+            if rank >= top && !allowlist.contains(&cache.name) {
+                continue;
+            }
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    SlabConfigStatistic::Bytes => cache.bytes,
+                    SlabConfigStatistic::ObjectsActive => cache.active_objects,
+                    SlabConfigStatistic::ObjectsTotal => cache.objects,
+                };
+                self.record(SlabStatistic::new(&cache.name, statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SlabCache {
+    name: String,
+    active_objects: u64,
+    objects: u64,
+    /// memory used by the slabs of the cache in bytes
+    bytes: u64,
+}
+
+/// Parses `/proc/slabinfo` version 2.1, where each cache is a line of the
+/// form `<name> <active_objs> <num_objs> <objsize> <objperslab>
+/// <pagesperslab> : tunables ... : slabdata <active_slabs> <num_slabs> ...`
+fn parse_slabinfo(content: &str, page_size: u64) -> Vec<SlabCache> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with("slabinfo") && !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let slabdata = fields.iter().position(|f| *f == "slabdata")?;
+            let pages_per_slab: u64 = fields.get(5)?.parse().ok()?;
+            let slabs: u64 = fields.get(slabdata + 2)?.parse().ok()?;
+            Some(SlabCache {
+                name: fields.first()?.to_string(),
+                active_objects: fields.get(1)?.parse().ok()?,
+                objects: fields.get(2)?.parse().ok()?,
+                bytes: slabs * pages_per_slab * page_size,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_slabinfo() {
+        let caches = parse_slabinfo(
+            "slabinfo - version: 2.1\n\
+             # name            <active_objs> <num_objs> <objsize> <objperslab> \
+             <pagesperslab> : tunables <limit> <batchcount> <sharedfactor> : slabdata \
+             <active_slabs> <num_slabs> <sharedavail>\n\
+             nf_conntrack        1200   1326    320   51    4 : tunables    0    0    0 \
+             : slabdata     26     26      0\n\
+             dentry            501234 504252    192   42    2 : tunables    0    0    0 \
+             : slabdata  12006  12006      0\n",
+            4096,
+        );
+        assert_eq!(
+            caches,
+            vec![
+                SlabCache {
+                    name: "nf_conntrack".to_string(),
+                    active_objects: 1200,
+                    objects: 1326,
+                    bytes: 26 * 4 * 4096,
+                },
+                SlabCache {
+                    name: "dentry".to_string(),
+                    active_objects: 501234,
+                    objects: 504252,
+                    bytes: 12006 * 2 * 4096,
+                },
+            ]
+        );
+    }
+}
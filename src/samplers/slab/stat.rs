@@ -0,0 +1,85 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SlabConfigStatistic {
+    #[strum(serialize = "bytes")]
+    Bytes,
+    #[strum(serialize = "objects/active")]
+    ObjectsActive,
+    #[strum(serialize = "objects/total")]
+    ObjectsTotal,
+}
+
+impl SlabConfigStatistic {
+    pub fn source(self) -> Source {
+        Source::Gauge
+    }
+}
+
+impl TryFrom<&str> for SlabConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SlabConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SlabStatistic {
+    name: String,
+    statistic: SlabConfigStatistic,
+}
+
+impl SlabStatistic {
+    /// A statistic of a slab cache, which is named `slab/<cache>/...`
+    pub fn new(cache: &str, statistic: SlabConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("slab/{}/{}", cache, suffix),
+                format!("slab/{}", suffix),
+                &[("cache", cache)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SlabConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SlabStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmartConfig {
+    #[serde(default)]
+    devices: Vec<String>,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default = "default_interval")]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<SmartConfigStatistic>,
+}
+
+impl Default for SmartConfig {
+    fn default() -> Self {
+        Self {
+            devices: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: default_interval(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+        }
+    }
+}
+
+// SMART queries are handled by the drive firmware and may stall IO, so they
+// are issued infrequently unless configured otherwise
+fn default_interval() -> Option<usize> {
+    Some(600_000)
+}
+
+fn default_statistics() -> Vec<SmartConfigStatistic> {
+    SmartConfigStatistic::iter().collect()
+}
+
+impl SmartConfig {
+    /// The disks, such as `sda`, which are queried. If empty, all SATA and SAS
+    /// disks are queried.
+    pub fn devices(&self) -> &[String] {
+        &self.devices
+    }
+}
+
+impl SamplerConfig for SmartConfig {
+    type Statistic = SmartStatistic;
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // disks are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::command_output;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Smart {
+    common: Common,
+    /// statistics which have been registered, as disks are discovered at
+    /// runtime
+    registered: HashSet<SmartStatistic>,
+    statistics: Vec<SmartConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Smart {
+    type Statistic = SmartStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().smart().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().smart().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize smart sampler {}", e);
+                    } else {
+                        error!("failed to initialize smart sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().smart()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().smart().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_disks().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Smart {
+    fn record(&mut self, statistic: SmartStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        let _ = self.metrics().record_gauge(&statistic, time, value);
+    }
+
+    /// Queries each disk with `smartctl`, which handles the differences
+    /// between ATA and SCSI devices and the quirks of individual drives
+    async fn sample_disks(&mut self) -> Result<(), std::io::Error> {
+        let mut disks = self.common.config().samplers().smart().devices().to_vec();
+        if disks.is_empty() {
+            disks = discover_disks().await?;
+        }
+        for disk in disks {
+            // disks in standby are skipped, rather than spun up
+            let output = match command_output(
+                tokio::process::Command::new("smartctl")
+                    .args(&["--json", "--attributes", "--nocheck=standby"])
+                    .arg(format!("/dev/{}", disk)),
+            )
+            .await
+            {
+                Ok(output) => output,
+                // a failing disk may not respond, which shouldn't hold up the
+                // others
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    warn!("failed to read SMART attributes of {}: {}", disk, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let attributes = match json::parse(&String::from_utf8_lossy(&output.stdout)) {
+                Ok(json) => Attributes::from_json(&json),
+                Err(e) => {
+                    debug!("failed to parse smartctl output for {}: {}", disk, e);
+                    continue;
+                }
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    SmartConfigStatistic::PendingSectors => attributes.pending_sectors,
+                    SmartConfigStatistic::PowerOnHours => attributes.power_on_hours,
+                    SmartConfigStatistic::ReallocatedSectors => attributes.reallocated_sectors,
+                    SmartConfigStatistic::Temperature => attributes.temperature,
+                };
+                if let Some(value) = value {
+                    self.record(SmartStatistic::new(&disk, statistic), time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the SATA and SAS disks, which are all handled by the SCSI disk driver
+async fn discover_disks() -> Result<Vec<String>, std::io::Error> {
+    let mut disks = Vec::new();
+    let mut entries = tokio::fs::read_dir("/sys/block").await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if let Ok(name) = entry.file_name().into_string() {
+            if name.starts_with("sd") {
+                disks.push(name);
+            }
+        }
+    }
+    disks.sort();
+    Ok(disks)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Attributes {
+    pending_sectors: Option<u64>,
+    power_on_hours: Option<u64>,
+    reallocated_sectors: Option<u64>,
+    /// temperature in degrees Celsius
+    temperature: Option<u64>,
+}
+
+impl Attributes {
+    /// Reads the attributes from the JSON output of `smartctl`. ATA disks
+    /// report sector counts as vendor attributes, while SCSI disks report
+    /// reallocations as the size of the grown defect list and have no
+    /// equivalent of pending sectors.
+    fn from_json(json: &json::JsonValue) -> Self {
+        let ata_attribute = |id| {
+            json["ata_smart_attributes"]["table"]
+                .members()
+                .find(|a| a["id"].as_u64() == Some(id))
+                .and_then(|a| a["raw"]["value"].as_u64())
+        };
+        Self {
+            pending_sectors: SmartConfigStatistic::PendingSectors
+                .ata_attribute()
+                .and_then(ata_attribute),
+            power_on_hours: json["power_on_time"]["hours"].as_u64(),
+            reallocated_sectors: SmartConfigStatistic::ReallocatedSectors
+                .ata_attribute()
+                .and_then(ata_attribute)
+                .or_else(|| json["scsi_grown_defect_list"].as_u64()),
+            temperature: json["temperature"]["current"].as_u64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ata_attributes() {
+        let json = json::parse(
+            r#"{
+                "device": { "name": "/dev/sda", "protocol": "ATA" },
+                "ata_smart_attributes": {
+                    "revision": 16,
+                    "table": [
+                        { "id": 5, "name": "Reallocated_Sector_Ct", "raw": { "value": 8 } },
+                        { "id": 9, "name": "Power_On_Hours", "raw": { "value": 20000 } },
+                        { "id": 197, "name": "Current_Pending_Sector", "raw": { "value": 2 } }
+                    ]
+                },
+                "power_on_time": { "hours": 20000 },
+                "temperature": { "current": 34 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Attributes::from_json(&json),
+            Attributes {
+                pending_sectors: Some(2),
+                power_on_hours: Some(20000),
+                reallocated_sectors: Some(8),
+                temperature: Some(34),
+            }
+        );
+    }
+
+    #[test]
+    fn test_scsi_attributes() {
+        let json = json::parse(
+            r#"{
+                "device": { "name": "/dev/sdb", "protocol": "SCSI" },
+                "scsi_grown_defect_list": 3,
+                "power_on_time": { "hours": 1500, "minutes": 12 },
+                "temperature": { "current": 41, "drive_trip": 65 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Attributes::from_json(&json),
+            Attributes {
+                pending_sectors: None,
+                power_on_hours: Some(1500),
+                reallocated_sectors: Some(3),
+                temperature: Some(41),
+            }
+        );
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SmartConfigStatistic {
+    #[strum(serialize = "pending_sectors")]
+    PendingSectors,
+    #[strum(serialize = "power_on_hours")]
+    PowerOnHours,
+    #[strum(serialize = "reallocated_sectors")]
+    ReallocatedSectors,
+    #[strum(serialize = "temperature")]
+    Temperature,
+}
+
+impl SmartConfigStatistic {
+    /// The id of the ATA SMART attribute this statistic is read from
+    pub fn ata_attribute(self) -> Option<u64> {
+        match self {
+            Self::PendingSectors => Some(197),
+            Self::ReallocatedSectors => Some(5),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for SmartConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SmartConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SmartStatistic {
+    name: String,
+    statistic: SmartConfigStatistic,
+}
+
+impl SmartStatistic {
+    /// A statistic of a disk, which is named `smart/<disk>/...`
+    pub fn new(disk: &str, statistic: SmartConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("smart/{}/{}", disk, suffix),
+                format!("smart/{}", suffix),
+                &[("disk", disk)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SmartConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SmartStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Gauge
+    }
+}
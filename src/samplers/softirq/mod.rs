@@ -0,0 +1,196 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_SOFTIRQS: &str = "/proc/softirqs";
+
+pub struct Softirq {
+    common: Common,
+    /// statistics which have been registered, as cpus are discovered at
+    /// runtime
+    registered: HashSet<SoftirqStatistic>,
+    statistics: Vec<SoftirqConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Softirq {
+    type Statistic = SoftirqStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().softirq().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().softirq().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize softirq sampler {}", e);
+                    } else {
+                        error!("failed to initialize softirq sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().softirq()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().softirq().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_softirqs().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Softirq {
+    fn record(&mut self, statistic: SoftirqStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    async fn sample_softirqs(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_SOFTIRQS).await?;
+        let per_cpu = self.common.config().samplers().softirq().per_cpu();
+        let time = Instant::now();
+        for (statistic, counts) in parse_softirqs(&content) {
+            if !self.statistics.contains(&statistic) {
+                continue;
+            }
+            let total = counts.iter().map(|(_, count)| count).sum();
+            self.record(SoftirqStatistic::host(statistic), time, total);
+            if per_cpu {
+                for (cpu, count) in counts {
+                    self.record(SoftirqStatistic::cpu(cpu, statistic), time, count);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `/proc/softirqs`, which has a header of the online CPUs followed by
+/// a row of counts, one for each CPU, for each type of softirq. Returns the
+/// counts of each type keyed by CPU id.
+fn parse_softirqs(content: &str) -> Vec<(SoftirqConfigStatistic, Vec<(u64, u64)>)> {
+    let mut lines = content.lines();
+    let cpus: Vec<u64> = match lines.next() {
+        Some(header) => header
+            .split_whitespace()
+            .filter_map(|cpu| cpu.strip_prefix("CPU")?.parse().ok())
+            .collect(),
+        None => return Vec::new(),
+    };
+    lines
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let label = fields.next()?.trim_end_matches(':');
+            let statistic = SoftirqConfigStatistic::from_label(label)?;
+            let counts = cpus
+                .iter()
+                .zip(fields)
+                .filter_map(|(cpu, count)| Some((*cpu, count.parse().ok()?)))
+                .collect();
+            Some((statistic, counts))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_softirqs() {
+        let softirqs = parse_softirqs(
+            "                    CPU0       CPU2\n\
+             \x20         HI:          1          0\n\
+             \x20      TIMER:     123456     654321\n\
+             \x20     NET_TX:         12         34\n\
+             \x20     NET_RX:    9876543         21\n\
+             \x20      BLOCK:          0          0\n\
+             \x20   IRQ_POLL:          0          0\n\
+             \x20    TASKLET:          5          6\n\
+             \x20      SCHED:       1000       2000\n\
+             \x20    HRTIMER:          0          1\n\
+             \x20        RCU:      44444      55555\n",
+        );
+        assert_eq!(softirqs.len(), 10);
+        assert_eq!(
+            softirqs[3],
+            (SoftirqConfigStatistic::NetRx, vec![(0, 9876543), (2, 21)])
+        );
+        assert_eq!(
+            softirqs[9],
+            (SoftirqConfigStatistic::Rcu, vec![(0, 44444), (2, 55555)])
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SoftirqConfigStatistic {
+    #[strum(serialize = "block")]
+    Block,
+    #[strum(serialize = "hi")]
+    Hi,
+    #[strum(serialize = "hr_timer")]
+    HrTimer,
+    #[strum(serialize = "irq_poll")]
+    IrqPoll,
+    #[strum(serialize = "net_rx")]
+    NetRx,
+    #[strum(serialize = "net_tx")]
+    NetTx,
+    #[strum(serialize = "rcu")]
+    Rcu,
+    #[strum(serialize = "sched")]
+    Sched,
+    #[strum(serialize = "tasklet")]
+    Tasklet,
+    #[strum(serialize = "timer")]
+    Timer,
+}
+
+impl SoftirqConfigStatistic {
+    /// Maps the label of a row of `/proc/softirqs` to its statistic
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "BLOCK" => Some(Self::Block),
+            "HI" => Some(Self::Hi),
+            "HRTIMER" => Some(Self::HrTimer),
+            "IRQ_POLL" | "BLOCK_IOPOLL" => Some(Self::IrqPoll),
+            "NET_RX" => Some(Self::NetRx),
+            "NET_TX" => Some(Self::NetTx),
+            "RCU" => Some(Self::Rcu),
+            "SCHED" => Some(Self::Sched),
+            "TASKLET" => Some(Self::Tasklet),
+            "TIMER" => Some(Self::Timer),
+            _ => None,
+        }
+    }
+
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for SoftirqConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SoftirqConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SoftirqStatistic {
+    name: String,
+    statistic: SoftirqConfigStatistic,
+}
+
+impl SoftirqStatistic {
+    /// A host-wide statistic, which is named `softirq/...`
+    pub fn host(statistic: SoftirqConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("softirq/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of a CPU, which is named `softirq/cpu<id>/...`
+    pub fn cpu(cpu: u64, statistic: SoftirqConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("softirq/cpu{}/{}", cpu, suffix),
+                format!("softirq/{}", suffix),
+                &[("cpu", &cpu.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SoftirqConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SoftirqStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -47,7 +47,7 @@ impl Sampler for Softnet {
         if common.config().samplers().softnet().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -71,6 +71,13 @@ impl Sampler for Softnet {
         self.common.config().samplers().softnet()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -116,7 +123,7 @@ impl Softnet {
             let time = Instant::now();
             for statistic in &self.statistics {
                 if let Some(value) = result.get(statistic) {
-                    let _ = self.metrics().record_counter(statistic, time, *value);
+                    self.common().record_counter(statistic, time, *value);
                 }
             }
         }
@@ -0,0 +1,237 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+pub struct Sriov {
+    common: Common,
+    /// (physical function interface, virtual function index) of each virtual
+    /// function whose statistics have been registered
+    registered: HashSet<(String, usize)>,
+    statistics: Vec<SriovConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Sriov {
+    type Statistic = SriovStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().sriov().statistics.clone();
+        Ok(Self {
+            common,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().sriov().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize sriov sampler {}", e);
+                    } else {
+                        error!("failed to initialize sriov sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().sriov()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().sriov().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_virtual_functions().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Sriov {
+    fn register_virtual_function(&self, interface: &str, vf: usize) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in &self.statistics {
+            let statistic = SriovStatistic::new(interface, vf, *statistic);
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+        }
+    }
+
+    /// Samples each virtual function of each physical function. Virtual
+    /// functions may be created or removed at any time, so they are discovered
+    /// on each sample.
+    async fn sample_virtual_functions(&mut self) -> Result<(), std::io::Error> {
+        for (interface, count) in discover_physical_functions().await? {
+            for vf in 0..count {
+                let counters = match read_counters(&interface, vf).await {
+                    Some(counters) => counters,
+                    None => continue,
+                };
+                let key = (interface.clone(), vf);
+                if !self.registered.contains(&key) {
+                    self.register_virtual_function(&interface, vf);
+                    self.registered.insert(key);
+                }
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    if let Some(value) = counters.get(statistic.field()) {
+                        let metric = SriovStatistic::new(&interface, vf, *statistic);
+                        self.common().record_counter(&metric, time, *value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds each network interface which is an SR-IOV physical function with
+/// virtual functions enabled, and returns it with the number of them
+async fn discover_physical_functions() -> Result<Vec<(String, usize)>, std::io::Error> {
+    let mut result = Vec::new();
+    let mut entries = tokio::fs::read_dir("/sys/class/net").await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let interface = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let path = format!("/sys/class/net/{}/device/sriov_numvfs", interface);
+        if let Ok(Ok(count)) = tokio::fs::read_to_string(path)
+            .await
+            .map(|v| v.trim().parse::<usize>())
+        {
+            if count > 0 {
+                result.push((interface, count));
+            }
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+/// Reads the counters of a virtual function. Some drivers, such as mlx5,
+/// provide them in the physical function's `sriov/<vf>/stats` file. Otherwise
+/// they are read from the netdev of the virtual function, which only exists
+/// while it is bound to a driver on the host rather than passed to a guest.
+async fn read_counters(interface: &str, vf: usize) -> Option<HashMap<String, u64>> {
+    let device = format!("/sys/class/net/{}/device", interface);
+
+    if let Ok(content) = tokio::fs::read_to_string(format!("{}/sriov/{}/stats", device, vf)).await {
+        return Some(parse_stats(&content));
+    }
+
+    let mut netdevs = tokio::fs::read_dir(format!("{}/virtfn{}/net", device, vf))
+        .await
+        .ok()?;
+    let netdev = netdevs.next_entry().await.ok()??.path();
+    let mut counters = HashMap::new();
+    for field in &[
+        "rx_bytes",
+        "rx_dropped",
+        "rx_packets",
+        "tx_bytes",
+        "tx_dropped",
+        "tx_packets",
+    ] {
+        if let Ok(Ok(value)) = tokio::fs::read_to_string(netdev.join("statistics").join(field))
+            .await
+            .map(|v| v.trim().parse::<u64>())
+        {
+            counters.insert(field.to_string(), value);
+        }
+    }
+    Some(counters)
+}
+
+/// Parses a VF stats file, which has a line of the form `name : value` for
+/// each counter
+fn parse_stats(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines() {
+        if let Some((name, value)) = line.split_once(':') {
+            if let Ok(value) = value.trim().parse() {
+                result.insert(name.trim().to_string(), value);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_stats() {
+        let result = parse_stats(
+            "tx_packets    : 1024\n\
+             tx_bytes      : 65536\n\
+             tx_dropped    : 0\n\
+             rx_packets    : 2048\n\
+             rx_bytes      : 131072\n\
+             rx_broadcast  : 4\n\
+             rx_dropped    : 7\n",
+        );
+        assert_eq!(result.len(), 7);
+        assert_eq!(result.get("tx_packets"), Some(&1024));
+        assert_eq!(result.get("rx_bytes"), Some(&131072));
+        assert_eq!(result.get("rx_dropped"), Some(&7));
+    }
+}
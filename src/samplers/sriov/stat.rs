@@ -0,0 +1,101 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SriovConfigStatistic {
+    #[strum(serialize = "receive/bytes")]
+    ReceiveBytes,
+    #[strum(serialize = "receive/drops")]
+    ReceiveDrops,
+    #[strum(serialize = "receive/packets")]
+    ReceivePackets,
+    #[strum(serialize = "transmit/bytes")]
+    TransmitBytes,
+    #[strum(serialize = "transmit/drops")]
+    TransmitDrops,
+    #[strum(serialize = "transmit/packets")]
+    TransmitPackets,
+}
+
+impl SriovConfigStatistic {
+    /// The name of the counter in the VF stats file, which matches the name of
+    /// the netdev statistic
+    pub fn field(self) -> &'static str {
+        match self {
+            Self::ReceiveBytes => "rx_bytes",
+            Self::ReceiveDrops => "rx_dropped",
+            Self::ReceivePackets => "rx_packets",
+            Self::TransmitBytes => "tx_bytes",
+            Self::TransmitDrops => "tx_dropped",
+            Self::TransmitPackets => "tx_packets",
+        }
+    }
+}
+
+impl TryFrom<&str> for SriovConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SriovConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SriovStatistic {
+    name: String,
+    statistic: SriovConfigStatistic,
+}
+
+impl SriovStatistic {
+    /// A statistic of a virtual function, identified by the name of its
+    /// physical function's interface and its index
+    pub fn new(interface: &str, vf: usize, statistic: SriovConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("sriov/{}/vf{}/{}", interface, vf, suffix),
+                format!("sriov/{}", suffix),
+                &[("interface", interface), ("vf", &vf.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SriovConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SriovStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        Source::Counter
+    }
+}
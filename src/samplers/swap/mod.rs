@@ -0,0 +1,356 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_SWAPS: &str = "/proc/swaps";
+const PROC_VMSTAT: &str = "/proc/vmstat";
+const SYS_BLOCK: &str = "/sys/block";
+const SYS_ZSWAP: &str = "/sys/kernel/debug/zswap";
+
+pub struct Swap {
+    common: Common,
+    page_size: u64,
+    /// statistics which have been registered, as swap and zram devices are
+    /// discovered at runtime
+    registered: HashSet<SwapStatistic>,
+    statistics: Vec<SwapConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Swap {
+    type Statistic = SwapStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().swap().statistics.clone();
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size <= 0 {
+            return Err(format_err!("failed to get page size"));
+        }
+        Ok(Self {
+            common,
+            page_size: page_size as u64,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().swap().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize swap sampler {}", e);
+                    } else {
+                        error!("failed to initialize swap sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().swap()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().swap().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_vmstat().await;
+        self.map_result(r)?;
+
+        let r = self.sample_devices().await;
+        self.map_result(r)?;
+
+        let r = self.sample_zram().await;
+        self.map_result(r)?;
+
+        // zswap statistics are only available with debugfs mounted
+        self.sample_zswap().await;
+
+        Ok(())
+    }
+}
+
+impl Swap {
+    fn record(&mut self, statistic: SwapStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples the pages swapped in and out from `/proc/vmstat`
+    async fn sample_vmstat(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_VMSTAT).await?;
+        let time = Instant::now();
+        for line in content.lines() {
+            let statistic = match line.split_once(' ') {
+                Some(("pswpin", value)) => (SwapConfigStatistic::In, value),
+                Some(("pswpout", value)) => (SwapConfigStatistic::Out, value),
+                _ => continue,
+            };
+            if !self.statistics.contains(&statistic.0) {
+                continue;
+            }
+            if let Ok(value) = statistic.1.trim().parse() {
+                self.record(SwapStatistic::host(statistic.0), time, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the size and utilization of each swap device in `/proc/swaps`
+    async fn sample_devices(&mut self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_SWAPS).await?;
+        let time = Instant::now();
+        for device in parse_swaps(&content) {
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    SwapConfigStatistic::DeviceSize => device.size,
+                    SwapConfigStatistic::DeviceUsed => device.used,
+                    _ => continue,
+                };
+                self.record(SwapStatistic::device(&device.name, statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the memory usage of each zram device from its `mm_stat`
+    async fn sample_zram(&mut self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| s.zram()) {
+            return Ok(());
+        }
+        let mut entries = tokio::fs::read_dir(SYS_BLOCK).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = match entry.file_name().into_string() {
+                Ok(name) if name.starts_with("zram") => name,
+                _ => continue,
+            };
+            let stat = match tokio::fs::read_to_string(entry.path().join("mm_stat")).await {
+                Ok(content) => match parse_mm_stat(&content) {
+                    Some(stat) => stat,
+                    None => continue,
+                },
+                // the device may have been removed
+                Err(_) => continue,
+            };
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                let value = match statistic {
+                    SwapConfigStatistic::ZramCompressed => stat.compressed,
+                    SwapConfigStatistic::ZramCompressionRatio => {
+                        match compression_ratio(stat.original, stat.compressed) {
+                            Some(ratio) => ratio,
+                            None => continue,
+                        }
+                    }
+                    SwapConfigStatistic::ZramOriginal => stat.original,
+                    SwapConfigStatistic::ZramUsed => stat.used,
+                    _ => continue,
+                };
+                self.record(SwapStatistic::device(&name, statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the zswap compressed cache from debugfs
+    async fn sample_zswap(&mut self) {
+        let pool_size = read_value(&format!("{}/pool_total_size", SYS_ZSWAP)).await;
+        let stored = read_value(&format!("{}/stored_pages", SYS_ZSWAP))
+            .await
+            .map(|pages| pages * self.page_size);
+        let written_back = read_value(&format!("{}/written_back_pages", SYS_ZSWAP)).await;
+        let time = Instant::now();
+        for statistic in self.statistics.clone() {
+            let value = match statistic {
+                SwapConfigStatistic::ZswapCompressionRatio => match (stored, pool_size) {
+                    (Some(stored), Some(pool_size)) => compression_ratio(stored, pool_size),
+                    _ => None,
+                },
+                SwapConfigStatistic::ZswapPoolSize => pool_size,
+                SwapConfigStatistic::ZswapStored => stored,
+                SwapConfigStatistic::ZswapWrittenBack => written_back,
+                _ => continue,
+            };
+            if let Some(value) = value {
+                self.record(SwapStatistic::host(statistic), time, value);
+            }
+        }
+    }
+}
+
+async fn read_value(path: &str) -> Option<u64> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The ratio of the original size of data to its compressed size, in
+/// hundredths, so that data compressed to a third of its size is 300
+fn compression_ratio(original: u64, compressed: u64) -> Option<u64> {
+    if compressed == 0 {
+        None
+    } else {
+        Some(original * 100 / compressed)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SwapDevice {
+    name: String,
+    /// size in bytes
+    size: u64,
+    /// used space in bytes
+    used: u64,
+}
+
+/// Parses `/proc/swaps`, which reports sizes in KiB. Devices are named by
+/// their path with the leading `/dev/`, or for swap files the leading `/`,
+/// removed.
+fn parse_swaps(content: &str) -> Vec<SwapDevice> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let path = fields.first()?;
+            let name = path
+                .strip_prefix("/dev/")
+                .unwrap_or_else(|| path.trim_start_matches('/'));
+            Some(SwapDevice {
+                name: name.to_string(),
+                size: fields.get(2)?.parse::<u64>().ok()? * 1024,
+                used: fields.get(3)?.parse::<u64>().ok()? * 1024,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct MmStat {
+    /// uncompressed size of the stored data in bytes
+    original: u64,
+    /// compressed size of the stored data in bytes
+    compressed: u64,
+    /// memory used by the device, including overhead, in bytes
+    used: u64,
+}
+
+/// Parses the `mm_stat` of a zram device, whose first fields are the
+/// original, compressed, and total memory used sizes
+fn parse_mm_stat(content: &str) -> Option<MmStat> {
+    let mut fields = content.split_whitespace().map(|v| v.parse().ok());
+    Some(MmStat {
+        original: fields.next()??,
+        compressed: fields.next()??,
+        used: fields.next()??,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_swaps() {
+        let devices = parse_swaps(
+            "Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority\n\
+             /dev/zram0                              partition\t8388604\t\t1048576\t\t100\n\
+             /swapfile                               file\t\t2097148\t\t0\t\t-2\n",
+        );
+        assert_eq!(
+            devices,
+            vec![
+                SwapDevice {
+                    name: "zram0".to_string(),
+                    size: 8388604 * 1024,
+                    used: 1048576 * 1024,
+                },
+                SwapDevice {
+                    name: "swapfile".to_string(),
+                    size: 2097148 * 1024,
+                    used: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mm_stat() {
+        let stat = parse_mm_stat("1073741824 268435456 285212672 0 285212672 1024 0 12\n").unwrap();
+        assert_eq!(stat.original, 1073741824);
+        assert_eq!(stat.compressed, 268435456);
+        assert_eq!(stat.used, 285212672);
+        assert_eq!(compression_ratio(stat.original, stat.compressed), Some(400));
+        assert_eq!(compression_ratio(0, 0), None);
+    }
+}
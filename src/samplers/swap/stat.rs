@@ -0,0 +1,130 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SwapConfigStatistic {
+    #[strum(serialize = "in")]
+    In,
+    #[strum(serialize = "out")]
+    Out,
+    #[strum(serialize = "device/size")]
+    DeviceSize,
+    #[strum(serialize = "device/used")]
+    DeviceUsed,
+    #[strum(serialize = "zram/compressed")]
+    ZramCompressed,
+    #[strum(serialize = "zram/compression_ratio")]
+    ZramCompressionRatio,
+    #[strum(serialize = "zram/original")]
+    ZramOriginal,
+    #[strum(serialize = "zram/used")]
+    ZramUsed,
+    #[strum(serialize = "zswap/compression_ratio")]
+    ZswapCompressionRatio,
+    #[strum(serialize = "zswap/pool_size")]
+    ZswapPoolSize,
+    #[strum(serialize = "zswap/stored")]
+    ZswapStored,
+    #[strum(serialize = "zswap/written_back")]
+    ZswapWrittenBack,
+}
+
+impl SwapConfigStatistic {
+    /// Whether this statistic is reported for each swap device
+    pub fn device(self) -> bool {
+        matches!(self, Self::DeviceSize | Self::DeviceUsed)
+    }
+
+    /// Whether this statistic is reported for each zram device
+    pub fn zram(self) -> bool {
+        matches!(
+            self,
+            Self::ZramCompressed | Self::ZramCompressionRatio | Self::ZramOriginal | Self::ZramUsed
+        )
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::In | Self::Out | Self::ZswapWrittenBack => Source::Counter,
+            _ => Source::Gauge,
+        }
+    }
+}
+
+impl TryFrom<&str> for SwapConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SwapConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SwapStatistic {
+    name: String,
+    statistic: SwapConfigStatistic,
+}
+
+impl SwapStatistic {
+    /// A host-wide statistic, which is named `swap/...`
+    pub fn host(statistic: SwapConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("swap/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of a swap or zram device, which is named
+    /// `swap/device/<device>/...` or `swap/zram/<device>/...`
+    pub fn device(device: &str, statistic: SwapConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        let (kind, suffix) = suffix.split_once('/').unwrap();
+        Self {
+            name: labeled(
+                format!("swap/{}/{}/{}", kind, device, suffix),
+                format!("swap/{}/{}", kind, suffix),
+                &[("device", device)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SwapConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SwapStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// The latency histograms, along with latency_enabled() and record_latency()
+// which select them by syscall number, are generated for the configured
+// syscalls and prepended to this file.
+
+#include <uapi/linux/ptrace.h>
+
+// the syscall which each thread entered, for those whose latency is recorded
+struct entry_t {
+    u64 ts;
+    u32 nr;
+};
+
+BPF_HASH(start, u64, struct entry_t);
+
+// the number of calls of each syscall, keyed by syscall number
+BPF_HASH(counts, u32, u64);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+// the arguments of sys_enter are the registers and the syscall number
+int trace_sys_enter(struct bpf_raw_tracepoint_args *ctx)
+{
+    u32 nr = ctx->args[1];
+    u64 zero = 0;
+    u64 *count = counts.lookup_or_try_init(&nr, &zero);
+    if (count) lock_xadd(count, 1);
+
+    if (latency_enabled(nr)) {
+        u64 id = bpf_get_current_pid_tgid();
+        struct entry_t entry = {};
+        entry.ts = bpf_ktime_get_ns();
+        entry.nr = nr;
+        start.update(&id, &entry);
+    }
+    return 0;
+}
+
+int trace_sys_exit(struct bpf_raw_tracepoint_args *ctx)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    struct entry_t *entry = start.lookup(&id);
+    if (entry == 0) {
+        return 0;   // missed entry, or latency is not recorded
+    }
+
+    u64 delta_us = (bpf_ktime_get_ns() - entry->ts) / 1000ul;
+    record_latency(entry->nr, value_to_index2(delta_us));
+    start.delete(&id);
+    return 0;
+}
@@ -0,0 +1,123 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::*;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyscallConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "default_latency")]
+    latency: Vec<String>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(
+        default = "default_statistics",
+        deserialize_with = "crate::config::deserialize_statistics"
+    )]
+    pub(crate) statistics: Vec<SyscallConfigStatistic>,
+    #[serde(default)]
+    syscalls: Vec<String>,
+    #[serde(default = "default_top_n")]
+    top_n: usize,
+}
+
+impl Default for SyscallConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            latency: default_latency(),
+            percentiles: crate::common::default_percentiles(),
+            statistics: default_statistics(),
+            syscalls: Default::default(),
+            top_n: default_top_n(),
+        }
+    }
+}
+
+fn default_latency() -> Vec<String> {
+    vec![
+        "fsync".to_string(),
+        "futex".to_string(),
+        "openat".to_string(),
+    ]
+}
+
+fn default_statistics() -> Vec<SyscallConfigStatistic> {
+    SyscallConfigStatistic::iter().collect()
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+impl SyscallConfig {
+    /// Syscalls, by name, whose latency distribution is recorded
+    pub fn latency(&self) -> &[String] {
+        &self.latency
+    }
+
+    /// Syscalls, by name, whose counts are reported. When empty, the `top_n`
+    /// syscalls are reported instead.
+    pub fn syscalls(&self) -> &[String] {
+        &self.syscalls
+    }
+
+    /// Number of syscalls, ranked by the number of calls during the last
+    /// interval, which are reported each interval
+    pub fn top_n(&self) -> usize {
+        self.top_n
+    }
+}
+
+impl SamplerConfig for SyscallConfig {
+    type Statistic = SyscallStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        // syscalls are discovered at runtime, register them as they are found
+        Vec::new()
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+mod config;
+mod stat;
+mod syscalls;
+
+pub use config::*;
+pub use stat::*;
+
+#[allow(dead_code)]
+pub struct Syscall {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    /// syscalls whose latency is recorded, by name and number, in the order
+    /// of their histogram tables
+    latency: Vec<(String, u32)>,
+    /// the number of calls of each syscall at the previous sample, which is
+    /// used to rank them
+    previous: HashMap<u32, u64>,
+    /// statistics which have been registered, as syscalls are discovered at
+    /// runtime
+    registered: HashSet<SyscallStatistic>,
+    statistics: Vec<SyscallConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Syscall {
+    type Statistic = SyscallStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().syscall().statistics.clone();
+
+        let latency = latency_syscalls(common.config().samplers().syscall().latency());
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            latency,
+            previous: HashMap::new(),
+            registered: HashSet::new(),
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().syscall().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize syscall sampler {}", e);
+                    } else {
+                        error!("failed to initialize syscall sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().syscall()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().syscall().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Syscall {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf() && !self.statistics.is_empty()
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let code = format!("{}\n{}", latency_code(&self.latency), include_str!("bpf.c"));
+                let mut bpf = bcc::BPF::new(&code)?;
+
+                for (handler, tracepoint) in &[
+                    ("trace_sys_enter", "sys_enter"),
+                    ("trace_sys_exit", "sys_exit"),
+                ] {
+                    bcc::RawTracepoint::new()
+                        .handler(handler)
+                        .tracepoint(tracepoint)
+                        .attach(&mut bpf)?;
+                }
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn register(&mut self, statistic: &SyscallStatistic) {
+        if self.registered.contains(statistic) {
+            return;
+        }
+        let percentiles = self.sampler_config().percentiles();
+        self.metrics().register(statistic);
+        self.metrics().add_output(statistic, Output::Reading);
+        if !percentiles.is_empty() {
+            if statistic.source() == Source::Distribution {
+                self.metrics()
+                    .add_summary(statistic, self.heatmap(crate::common::SECOND));
+            } else {
+                self.metrics()
+                    .add_summary(statistic, Summary::stream(self.samples()));
+            }
+        }
+        for percentile in percentiles {
+            self.metrics()
+                .add_output(statistic, Output::Percentile(*percentile));
+        }
+        self.registered.insert(statistic.clone());
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&mut self) -> Result<(), std::io::Error> {
+        let counts = match self.bpf {
+            Some(ref bpf) => bpf.lock().unwrap().counters("counts"),
+            None => None,
+        };
+        if let Some(counts) = counts {
+            if self.statistics.contains(&SyscallConfigStatistic::Total) {
+                let time = Instant::now();
+                for (name, count) in self.select(&counts) {
+                    let statistic = SyscallStatistic::new(&name, SyscallConfigStatistic::Total);
+                    self.register(&statistic);
+                    self.common().record_counter(&statistic, time, count);
+                }
+            }
+            self.previous = counts;
+        }
+
+        // latency histograms are transferred once per window
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
+            if self.statistics.contains(&SyscallConfigStatistic::Latency) {
+                let mut histograms = Vec::new();
+                if let Some(ref bpf) = self.bpf {
+                    let bpf = bpf.lock().unwrap();
+                    for (index, (name, _)) in self.latency.iter().enumerate() {
+                        if let Some(histogram) = bpf.histogram(&format!("latency_{}", index)) {
+                            histograms.push((name.clone(), histogram));
+                        }
+                    }
+                }
+                let time = Instant::now();
+                for (name, histogram) in histograms {
+                    let statistic = SyscallStatistic::new(&name, SyscallConfigStatistic::Latency);
+                    self.register(&statistic);
+                    for (&value, &count) in &histogram {
+                        if count > 0 {
+                            self.common().record_bucket(
+                                &statistic,
+                                time,
+                                value * crate::MICROSECOND,
+                                count,
+                            );
+                        }
+                    }
+                }
+            }
+            *self.bpf_last.lock().unwrap() = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Returns the syscalls whose counts are reported, which are either those
+    /// configured or the most called since the previous sample
+    #[cfg(feature = "bpf")]
+    fn select(&self, counts: &HashMap<u32, u64>) -> Vec<(String, u64)> {
+        let config = self.common.config().samplers().syscall();
+        if !config.syscalls().is_empty() {
+            return config
+                .syscalls()
+                .iter()
+                .filter_map(|name| {
+                    let nr = syscalls::number(name)?;
+                    Some((name.clone(), counts.get(&nr).copied().unwrap_or(0)))
+                })
+                .collect();
+        }
+        rank(counts, &self.previous, config.top_n())
+            .into_iter()
+            .map(|nr| (name(nr), counts[&nr]))
+            .collect()
+    }
+}
+
+/// The name of a syscall, or its number for those which are not known
+#[allow(dead_code)]
+fn name(nr: u32) -> String {
+    match syscalls::name(nr) {
+        Some(name) => name.to_string(),
+        None => format!("nr_{}", nr),
+    }
+}
+
+/// Ranks syscalls by the number of calls since the previous sample, returning
+/// the `top` syscall numbers in descending order
+#[allow(dead_code)]
+fn rank(counts: &HashMap<u32, u64>, previous: &HashMap<u32, u64>, top: usize) -> Vec<u32> {
+    let mut ranked: Vec<(u64, u32)> = counts
+        .iter()
+        .map(|(nr, count)| {
+            let previous = previous.get(nr).copied().unwrap_or(0);
+            (count.saturating_sub(previous), *nr)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    ranked.into_iter().take(top).map(|(_, nr)| nr).collect()
+}
+
+/// Resolves the syscalls whose latency is recorded to their numbers, skipping
+/// unknown and repeated syscalls, as each number may only appear once in the
+/// generated switch statements
+fn latency_syscalls(names: &[String]) -> Vec<(String, u32)> {
+    let mut latency: Vec<(String, u32)> = Vec::new();
+    for name in names {
+        match syscalls::number(name) {
+            Some(nr) if latency.iter().any(|(_, known)| *known == nr) => {
+                warn!("duplicate syscall: {}", name)
+            }
+            Some(nr) => latency.push((name.clone(), nr)),
+            None => warn!("unknown syscall: {}", name),
+        }
+    }
+    latency
+}
+
+/// Generates a latency histogram for each syscall, along with the functions
+/// which select them by syscall number
+#[allow(dead_code)]
+fn latency_code(latency: &[(String, u32)]) -> String {
+    let mut code = String::new();
+    for index in 0..latency.len() {
+        code.push_str(&format!("BPF_HISTOGRAM(latency_{}, int, 461);\n", index));
+    }
+
+    code.push_str("\nstatic int latency_enabled(u32 nr)\n{\n    switch (nr) {\n");
+    for (name, nr) in latency {
+        code.push_str(&format!("    case {}: // {}\n", nr, name));
+    }
+    code.push_str("        return 1;\n    }\n    return 0;\n}\n");
+
+    code.push_str(
+        "\nstatic void record_latency(u32 nr, unsigned int index)\n{\n    switch (nr) {\n",
+    );
+    for (index, (_, nr)) in latency.iter().enumerate() {
+        code.push_str(&format!(
+            "    case {}:\n        latency_{}.increment(index);\n        break;\n",
+            nr, index
+        ));
+    }
+    code.push_str("    }\n}\n");
+    code
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let counts: HashMap<u32, u64> = vec![(0, 1000), (1, 500), (2, 50), (3, 300)]
+            .into_iter()
+            .collect();
+        let previous: HashMap<u32, u64> = vec![(0, 950), (1, 100)].into_iter().collect();
+        assert_eq!(rank(&counts, &previous, 3), vec![1, 3, 0]);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_latency_syscalls() {
+        let names: Vec<String> = vec!["fsync", "unknown", "futex", "fsync"]
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+        let latency = latency_syscalls(&names);
+        assert_eq!(latency.len(), 2);
+        assert_eq!(latency[0].0, "fsync");
+        assert_eq!(latency[1].0, "futex");
+    }
+
+    #[test]
+    fn test_latency_code() {
+        let code = latency_code(&[("fsync".to_string(), 74), ("futex".to_string(), 202)]);
+        assert!(code.contains("BPF_HISTOGRAM(latency_0, int, 461);"));
+        assert!(code.contains("BPF_HISTOGRAM(latency_1, int, 461);"));
+        assert!(code.contains("    case 202:\n        latency_1.increment(index);"));
+        assert!(latency_code(&[]).contains("return 0;"));
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum SyscallConfigStatistic {
+    #[strum(serialize = "latency")]
+    Latency,
+    #[strum(serialize = "total")]
+    Total,
+}
+
+impl SyscallConfigStatistic {
+    pub fn source(self) -> Source {
+        match self {
+            Self::Latency => Source::Distribution,
+            Self::Total => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for SyscallConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        SyscallConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SyscallStatistic {
+    name: String,
+    statistic: SyscallConfigStatistic,
+}
+
+impl SyscallStatistic {
+    /// A statistic of a syscall, which is named `syscall/<syscall>/...`
+    pub fn new(syscall: &str, statistic: SyscallConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("syscall/{}/{}", syscall, suffix),
+                format!("syscall/{}", suffix),
+                &[("syscall", syscall)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> SyscallConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for SyscallStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
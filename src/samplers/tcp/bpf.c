@@ -19,6 +19,35 @@ BPF_HASH(start, struct sock *, struct info_t);
 
 BPF_HISTOGRAM(connlat, int, 461);
 
+// time each established connection was added to its listener's accept queue,
+// an lru so that connections which are never accepted are evicted
+BPF_TABLE("lru_hash", struct sock *, u64, accept_start, 10240);
+
+BPF_HISTOGRAM(acceptlat, int, 461);
+
+// retransmit reasons, which index the retransmits array
+#define RETRANSMIT_OTHER 0
+#define RETRANSMIT_TIMEOUT 1
+#define RETRANSMIT_RECOVERY 2
+#define RETRANSMIT_TAIL_LOSS_PROBE 3
+#define RETRANSMIT_SYN 4
+
+// events, which index the events array
+#define EVENT_RTO 0
+#define EVENT_DROP 1
+
+BPF_ARRAY(retransmits, u64, 5);
+BPF_ARRAY(events, u64, 2);
+
+// the reason for any retransmits on this cpu, set while inside one of the
+// functions which retransmit segments
+BPF_PERCPU_ARRAY(retransmit_reason, u32, 1);
+
+// sockets entering and leaving each state, indexed by state, from which the
+// number of sockets in each state is calculated
+BPF_ARRAY(state_entered, u64, TCP_MAX_STATES);
+BPF_ARRAY(state_exited, u64, TCP_MAX_STATES);
+
 // histogram indexing
 static unsigned int value_to_index2(unsigned int value) {
     unsigned int index = 460;
@@ -85,4 +114,107 @@ int trace_tcp_rcv_state_process(struct pt_regs *ctx, struct sock *skp)
 
     start.delete(&skp);
     return 0;
-}
\ No newline at end of file
+}
+
+// the handshake has completed and the child socket is queued for accept()
+int trace_reqsk_queue_add(struct pt_regs *ctx, struct sock *sk, struct request_sock *req,
+    struct sock *child)
+{
+    u64 ts = bpf_ktime_get_ns();
+    accept_start.update(&child, &ts);
+    return 0;
+}
+
+int trace_accept_return(struct pt_regs *ctx)
+{
+    struct sock *child = (struct sock *)PT_REGS_RC(ctx);
+    if (child == 0) {
+        return 0;
+    }
+    u64 *tsp = accept_start.lookup(&child);
+    if (tsp == 0) {
+        return 0;   // missed entry
+    }
+    u64 delta_us = (bpf_ktime_get_ns() - *tsp) / 1000ul;
+    u64 index = value_to_index2(delta_us);
+    acceptlat.increment(index);
+
+    accept_start.delete(&child);
+    return 0;
+}
+
+static void set_reason(u32 reason)
+{
+    int zero = 0;
+    u32 *current = retransmit_reason.lookup(&zero);
+    if (current) {
+        *current = reason;
+    }
+}
+
+// retransmission timeout
+int trace_retransmit_timer(struct pt_regs *ctx)
+{
+    events.increment(EVENT_RTO);
+    set_reason(RETRANSMIT_TIMEOUT);
+    return 0;
+}
+
+// fast retransmit and loss recovery
+int trace_xmit_retransmit_queue(struct pt_regs *ctx)
+{
+    set_reason(RETRANSMIT_RECOVERY);
+    return 0;
+}
+
+int trace_send_loss_probe(struct pt_regs *ctx)
+{
+    set_reason(RETRANSMIT_TAIL_LOSS_PROBE);
+    return 0;
+}
+
+// shared return probe for the functions above
+int trace_retransmit_return(struct pt_regs *ctx)
+{
+    set_reason(RETRANSMIT_OTHER);
+    return 0;
+}
+
+// attached to __tcp_retransmit_skb(), which does the work for
+// tcp_retransmit_skb() and is called directly for tail loss probes
+int trace_retransmit_skb(struct pt_regs *ctx, struct sock *skp)
+{
+    u32 reason = RETRANSMIT_OTHER;
+    u8 state = skp->__sk_common.skc_state;
+    if (state == TCP_SYN_SENT || state == TCP_SYN_RECV) {
+        reason = RETRANSMIT_SYN;
+    } else {
+        int zero = 0;
+        u32 *current = retransmit_reason.lookup(&zero);
+        if (current) {
+            reason = *current;
+        }
+    }
+    retransmits.increment(reason);
+    return 0;
+}
+
+int trace_tcp_drop(struct pt_regs *ctx)
+{
+    events.increment(EVENT_DROP);
+    return 0;
+}
+
+int trace_inet_sock_set_state(struct tracepoint__sock__inet_sock_set_state *args)
+{
+    if (args->protocol != IPPROTO_TCP)
+        return 0;
+    int oldstate = args->oldstate;
+    int newstate = args->newstate;
+    // a socket accepted from a listener is cloned from it, so it appears to
+    // leave the listening state when it is created
+    if (oldstate != TCP_LISTEN || newstate != TCP_SYN_RECV)
+        state_exited.increment(oldstate);
+    state_entered.increment(newstate);
+    return 0;
+}
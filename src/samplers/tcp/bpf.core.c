@@ -0,0 +1,220 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// CO-RE version of bpf.c, built with the bpf_core feature
+// Based on: https://github.com/iovisor/bcc/blob/master/libbpf-tools/tcpconnlat.bpf.c
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+#include <bpf/bpf_core_read.h>
+
+#include "histogram.h"
+
+// from include/net/tcp_states.h, which is not part of the BTF
+#define TCP_SYN_SENT 2
+#define TCP_SYN_RECV 3
+#define TCP_LISTEN 10
+#define TCP_MAX_STATES 13
+
+// retransmit reasons and events, see bpf.c
+#define RETRANSMIT_OTHER 0
+#define RETRANSMIT_TIMEOUT 1
+#define RETRANSMIT_RECOVERY 2
+#define RETRANSMIT_TAIL_LOSS_PROBE 3
+#define RETRANSMIT_SYN 4
+
+#define EVENT_RTO 0
+#define EVENT_DROP 1
+
+HASH(start, struct sock *, u64);
+
+HISTOGRAM(connlat);
+
+// time each established connection was added to its listener's accept queue
+struct {
+    __uint(type, BPF_MAP_TYPE_LRU_HASH);
+    __uint(max_entries, 10240);
+    __type(key, struct sock *);
+    __type(value, u64);
+} accept_start SEC(".maps");
+
+HISTOGRAM(acceptlat);
+
+COUNTERS(retransmits, 5);
+COUNTERS(events, 2);
+
+struct {
+    __uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+    __uint(max_entries, 1);
+    __type(key, u32);
+    __type(value, u32);
+} retransmit_reason SEC(".maps");
+
+// sockets entering and leaving each state, see bpf.c
+COUNTERS(state_entered, TCP_MAX_STATES);
+COUNTERS(state_exited, TCP_MAX_STATES);
+
+static __always_inline int trace_connect(struct sock *sk)
+{
+    u64 ts = bpf_ktime_get_ns();
+    bpf_map_update_elem(&start, &sk, &ts, BPF_ANY);
+    return 0;
+}
+
+SEC("kprobe/tcp_v4_connect")
+int BPF_KPROBE(tcp_v4_connect, struct sock *sk)
+{
+    return trace_connect(sk);
+}
+
+SEC("kprobe/tcp_v6_connect")
+int BPF_KPROBE(tcp_v6_connect, struct sock *sk)
+{
+    return trace_connect(sk);
+}
+
+// handshake completion is processed by tcp_rcv_state_process() while the
+// socket is still in TCP_SYN_SENT, see bpf.c
+SEC("kprobe/tcp_rcv_state_process")
+int BPF_KPROBE(tcp_rcv_state_process, struct sock *sk)
+{
+    if (BPF_CORE_READ(sk, __sk_common.skc_state) != TCP_SYN_SENT) {
+        return 0;
+    }
+
+    // missed entry or filtered
+    u64 *tsp = bpf_map_lookup_elem(&start, &sk);
+    if (!tsp) {
+        return 0;
+    }
+
+    histogram_add(&connlat, (bpf_ktime_get_ns() - *tsp) / 1000);
+
+    bpf_map_delete_elem(&start, &sk);
+    return 0;
+}
+
+// the handshake has completed and the child socket is queued for accept()
+SEC("kprobe/inet_csk_reqsk_queue_add")
+int BPF_KPROBE(inet_csk_reqsk_queue_add, struct sock *sk, struct request_sock *req,
+    struct sock *child)
+{
+    u64 ts = bpf_ktime_get_ns();
+    bpf_map_update_elem(&accept_start, &child, &ts, BPF_ANY);
+    return 0;
+}
+
+SEC("kretprobe/inet_csk_accept")
+int BPF_KRETPROBE(inet_csk_accept, struct sock *child)
+{
+    if (!child) {
+        return 0;
+    }
+
+    // missed entry
+    u64 *tsp = bpf_map_lookup_elem(&accept_start, &child);
+    if (!tsp) {
+        return 0;
+    }
+
+    histogram_add(&acceptlat, (bpf_ktime_get_ns() - *tsp) / 1000);
+
+    bpf_map_delete_elem(&accept_start, &child);
+    return 0;
+}
+
+static __always_inline int set_reason(u32 reason)
+{
+    u32 zero = 0;
+    u32 *current = bpf_map_lookup_elem(&retransmit_reason, &zero);
+    if (current) {
+        *current = reason;
+    }
+    return 0;
+}
+
+SEC("kprobe/tcp_retransmit_timer")
+int BPF_KPROBE(tcp_retransmit_timer)
+{
+    increment(&events, EVENT_RTO);
+    return set_reason(RETRANSMIT_TIMEOUT);
+}
+
+SEC("kretprobe/tcp_retransmit_timer")
+int BPF_KRETPROBE(tcp_retransmit_timer_return)
+{
+    return set_reason(RETRANSMIT_OTHER);
+}
+
+SEC("kprobe/tcp_xmit_retransmit_queue")
+int BPF_KPROBE(tcp_xmit_retransmit_queue)
+{
+    return set_reason(RETRANSMIT_RECOVERY);
+}
+
+SEC("kretprobe/tcp_xmit_retransmit_queue")
+int BPF_KRETPROBE(tcp_xmit_retransmit_queue_return)
+{
+    return set_reason(RETRANSMIT_OTHER);
+}
+
+SEC("kprobe/tcp_send_loss_probe")
+int BPF_KPROBE(tcp_send_loss_probe)
+{
+    return set_reason(RETRANSMIT_TAIL_LOSS_PROBE);
+}
+
+SEC("kretprobe/tcp_send_loss_probe")
+int BPF_KRETPROBE(tcp_send_loss_probe_return)
+{
+    return set_reason(RETRANSMIT_OTHER);
+}
+
+// __tcp_retransmit_skb() does the work for tcp_retransmit_skb() and is called
+// directly for tail loss probes
+SEC("kprobe/__tcp_retransmit_skb")
+int BPF_KPROBE(tcp_retransmit_skb, struct sock *sk)
+{
+    u32 reason = RETRANSMIT_OTHER;
+    u8 state = BPF_CORE_READ(sk, __sk_common.skc_state);
+    if (state == TCP_SYN_SENT || state == TCP_SYN_RECV) {
+        reason = RETRANSMIT_SYN;
+    } else {
+        u32 zero = 0;
+        u32 *current = bpf_map_lookup_elem(&retransmit_reason, &zero);
+        if (current) {
+            reason = *current;
+        }
+    }
+    increment(&retransmits, reason);
+    return 0;
+}
+
+// removed in 5.17, skipped by userspace when the symbol is missing
+SEC("kprobe/tcp_drop")
+int BPF_KPROBE(tcp_drop)
+{
+    increment(&events, EVENT_DROP);
+    return 0;
+}
+
+// added in 4.16, skipped by userspace when the tracepoint is missing
+SEC("tracepoint/sock/inet_sock_set_state")
+int inet_sock_set_state(struct trace_event_raw_inet_sock_set_state *args)
+{
+    if (BPF_CORE_READ(args, protocol) != IPPROTO_TCP) {
+        return 0;
+    }
+    int oldstate = BPF_CORE_READ(args, oldstate);
+    int newstate = BPF_CORE_READ(args, newstate);
+    // an accepted socket is cloned from its listener, see bpf.c
+    if (oldstate != TCP_LISTEN || newstate != TCP_SYN_RECV) {
+        increment(&state_exited, oldstate);
+    }
+    increment(&state_entered, newstate);
+    return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
@@ -2,9 +2,12 @@
 // Licensed under the Apache License, Version 2.0
 // http://www.apache.org/licenses/LICENSE-2.0
 
+use std::collections::HashMap;
+use std::io::SeekFrom;
 use std::sync::{Arc, Mutex};
 use std::time::*;
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 use async_trait::async_trait;
 
@@ -25,6 +28,10 @@ pub struct Tcp {
     common: Common,
     proc_net_snmp: Option<File>,
     proc_net_netstat: Option<File>,
+    proc_net_tcp: HashMap<&'static str, File>,
+    /// when the connection states are counted by BPF, the number of sockets
+    /// in each state less the transitions which BPF had already counted
+    state_baseline: Option<HashMap<u8, i64>>,
     statistics: Vec<TcpStatistic>,
 }
 
@@ -42,6 +49,8 @@ impl Sampler for Tcp {
             common,
             proc_net_snmp: None,
             proc_net_netstat: None,
+            proc_net_tcp: HashMap::new(),
+            state_baseline: None,
             statistics,
         };
 
@@ -63,7 +72,7 @@ impl Sampler for Tcp {
         if common.config().samplers().tcp().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -87,6 +96,13 @@ impl Sampler for Tcp {
         self.common.config().samplers().tcp()
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -104,9 +120,15 @@ impl Sampler for Tcp {
         let r = self.sample_netstat().await;
         self.map_result(r)?;
 
+        let r = self.sample_connections().await;
+        self.map_result(r)?;
+
         // sample bpf
-        #[cfg(feature = "bpf")]
-        self.map_result(self.sample_bpf())?;
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
 
         Ok(())
     }
@@ -114,11 +136,14 @@ impl Sampler for Tcp {
 
 impl Tcp {
     // checks that bpf is enabled in config and one or more bpf stats enabled
-    #[cfg(feature = "bpf")]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
     fn bpf_enabled(&self) -> bool {
         if self.sampler_config().bpf() {
             for statistic in self.sampler_config().statistics() {
-                if statistic.bpf_table().is_some() {
+                if statistic.bpf_table().is_some()
+                    || statistic.bpf_counter().is_some()
+                    || statistic.connection_state().is_some()
+                {
                     return true;
                 }
             }
@@ -127,6 +152,10 @@ impl Tcp {
     }
 
     fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        // connection states are counted from the tracepoint, added in 4.16
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        let states = tracepoint_exists("sock", "inet_sock_set_state");
+
         #[cfg(feature = "bpf")]
         {
             if self.enabled() && self.bpf_enabled() {
@@ -149,13 +178,127 @@ impl Tcp {
                     .function("tcp_rcv_state_process")
                     .attach(&mut bpf)?;
 
+                // time spent by established connections in the accept queue
+                bcc::Kprobe::new()
+                    .handler("trace_reqsk_queue_add")
+                    .function("inet_csk_reqsk_queue_add")
+                    .attach(&mut bpf)?;
+                bcc::Kretprobe::new()
+                    .handler("trace_accept_return")
+                    .function("inet_csk_accept")
+                    .attach(&mut bpf)?;
+
+                // retransmits are classified by the function they happen within
+                bcc::Kprobe::new()
+                    .handler("trace_retransmit_skb")
+                    .function("__tcp_retransmit_skb")
+                    .attach(&mut bpf)?;
+                for (handler, function) in &[
+                    ("trace_retransmit_timer", "tcp_retransmit_timer"),
+                    ("trace_xmit_retransmit_queue", "tcp_xmit_retransmit_queue"),
+                    ("trace_send_loss_probe", "tcp_send_loss_probe"),
+                ] {
+                    bcc::Kprobe::new()
+                        .handler(handler)
+                        .function(function)
+                        .attach(&mut bpf)?;
+                    bcc::Kretprobe::new()
+                        .handler("trace_retransmit_return")
+                        .function(function)
+                        .attach(&mut bpf)?;
+                }
+
+                // tcp_drop() was removed in 5.17
+                if let Err(e) = bcc::Kprobe::new()
+                    .handler("trace_tcp_drop")
+                    .function("tcp_drop")
+                    .attach(&mut bpf)
+                {
+                    warn!(
+                        "unable to trace tcp_drop, tcp/receive/drop will be zero: {}",
+                        e
+                    );
+                }
+
+                if states {
+                    bcc::Tracepoint::new()
+                        .handler("trace_inet_sock_set_state")
+                        .subsystem("sock")
+                        .tracepoint("inet_sock_set_state")
+                        .attach(&mut bpf)?;
+                }
+
                 self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })))
             }
         }
 
+        #[cfg(feature = "bpf_core")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let object = include_bytes!(concat!(env!("OUT_DIR"), "/tcp.bpf.o"));
+                // tcp_drop() was removed in 5.17
+                let mut skip = Vec::new();
+                if symbol_lookup("tcp_drop").is_none() {
+                    warn!("unable to trace tcp_drop, tcp/receive/drop will be zero");
+                    skip.push("tcp_drop");
+                }
+                if !states {
+                    skip.push("inet_sock_set_state");
+                }
+                let bpf = BPF::load("tcp", object, &skip)?;
+                self.bpf = Some(Arc::new(Mutex::new(bpf)));
+            }
+        }
+
+        #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+        {
+            if states
+                && self.bpf.is_some()
+                && self
+                    .statistics
+                    .iter()
+                    .any(|s| s.connection_state().is_some())
+            {
+                self.state_baseline = Some(self.state_baseline()?);
+            }
+        }
+
         Ok(())
     }
 
+    /// The number of sockets in each state, from procfs, less the transitions
+    /// which BPF has already counted, so that adding the transitions which it
+    /// counts from now on gives the current number of sockets in each state
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+    fn state_baseline(&self) -> Result<HashMap<u8, i64>, std::io::Error> {
+        let mut sockets = HashMap::new();
+        for path in &["/proc/net/tcp", "/proc/net/tcp6"] {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                // ipv6 may be disabled
+                Err(_) if *path == "/proc/net/tcp6" => continue,
+                Err(e) => return Err(e),
+            };
+            for (state, count) in parse_connection_states(&content) {
+                *sockets.entry(state).or_insert(0) += count;
+            }
+        }
+
+        let mut baseline = HashMap::new();
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let entered = bpf.counters("state_entered").unwrap_or_default();
+            let exited = bpf.counters("state_exited").unwrap_or_default();
+            for state in self.statistics.iter().filter_map(|s| s.connection_state()) {
+                let transitions = connection_count(&HashMap::new(), &entered, &exited, state);
+                let sockets = sockets.get(&state).copied().unwrap_or(0) as i64;
+                baseline.insert(state, sockets - transitions);
+            }
+        }
+        Ok(baseline)
+    }
+
     async fn sample_snmp(&mut self) -> Result<(), std::io::Error> {
         if self.proc_net_snmp.is_none() {
             let file = File::open("/proc/net/snmp").await?;
@@ -168,7 +311,7 @@ impl Tcp {
                 if let Some((pkey, lkey)) = statistic.keys() {
                     if let Some(inner) = parsed.get(pkey) {
                         if let Some(value) = inner.get(lkey) {
-                            let _ = self.metrics().record_counter(statistic, time, *value);
+                            self.common().record_counter(statistic, time, *value);
                         }
                     }
                 }
@@ -190,7 +333,7 @@ impl Tcp {
                 if let Some((pkey, lkey)) = statistic.keys() {
                     if let Some(inner) = parsed.get(pkey) {
                         if let Some(value) = inner.get(lkey) {
-                            let _ = self.metrics().record_counter(statistic, time, *value);
+                            self.common().record_counter(statistic, time, *value);
                         }
                     }
                 }
@@ -199,24 +342,128 @@ impl Tcp {
         Ok(())
     }
 
-    #[cfg(feature = "bpf")]
-    fn sample_bpf(&self) -> Result<(), std::io::Error> {
-        if self.bpf_last.lock().unwrap().elapsed()
-            >= Duration::new(self.general_config().window() as u64, 0)
+    /// Counts the sockets in each state from procfs, unless they are counted
+    /// by BPF, along with the accept queues of the listening sockets
+    async fn sample_connections(&mut self) -> Result<(), std::io::Error> {
+        // sockets in TIME_WAIT aren't full sockets and don't pass through the
+        // tracepoint, so they are counted by the kernel's socket summary
+        if self.state_baseline.is_some()
+            && self.statistics.contains(&TcpStatistic::ConnectionsTimeWait)
         {
+            let content = tokio::fs::read_to_string("/proc/net/sockstat").await?;
+            if let Some(value) = parse_time_wait(&content) {
+                let _ = self.metrics().record_gauge(
+                    &TcpStatistic::ConnectionsTimeWait,
+                    Instant::now(),
+                    value,
+                );
+            }
+        }
+
+        let count_states = self.state_baseline.is_none()
+            && self
+                .statistics
+                .iter()
+                .any(|s| s.connection_state().is_some());
+        if !count_states && !self.statistics.iter().any(|s| s.listen_backlog()) {
+            return Ok(());
+        }
+
+        let mut states = HashMap::new();
+        let mut backlogs = Vec::new();
+        for path in &["/proc/net/tcp", "/proc/net/tcp6"] {
+            if !self.proc_net_tcp.contains_key(path) {
+                match File::open(path).await {
+                    Ok(file) => {
+                        self.proc_net_tcp.insert(*path, file);
+                    }
+                    // ipv6 may be disabled
+                    Err(_) if *path == "/proc/net/tcp6" => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Some(file) = self.proc_net_tcp.get_mut(path) {
+                file.seek(SeekFrom::Start(0)).await?;
+                let mut content = String::new();
+                file.read_to_string(&mut content).await?;
+                for (state, count) in parse_connection_states(&content) {
+                    *states.entry(state).or_insert(0) += count;
+                }
+                backlogs.extend(parse_listen_backlogs(&content));
+            }
+        }
+
+        let time = Instant::now();
+        if count_states {
+            for statistic in &self.statistics {
+                if let Some(state) = statistic.connection_state() {
+                    let value = states.get(&state).copied().unwrap_or(0);
+                    let _ = self.metrics().record_gauge(statistic, time, value);
+                }
+            }
+        }
+        for statistic in &self.statistics {
+            let value = match statistic {
+                TcpStatistic::ListenBacklog => backlogs.iter().map(|(queued, _)| queued).sum(),
+                TcpStatistic::ListenUtilization => backlogs
+                    .iter()
+                    .filter(|(_, max)| *max > 0)
+                    .map(|(queued, max)| queued * 100 / max)
+                    .max()
+                    .unwrap_or(0),
+                _ => continue,
+            };
+            let _ = self.metrics().record_gauge(statistic, time, value);
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let time = Instant::now();
+            let mut tables = HashMap::new();
+            for statistic in &self.statistics {
+                if let Some((table, index)) = statistic.bpf_counter() {
+                    if !tables.contains_key(table) {
+                        if let Some(counters) = bpf.counters(table) {
+                            tables.insert(table, counters);
+                        }
+                    }
+                    if let Some(value) = tables.get(table).and_then(|t| t.get(&index)) {
+                        self.common().record_counter(statistic, time, *value);
+                    }
+                }
+            }
+
+            if let Some(ref baseline) = self.state_baseline {
+                let entered = bpf.counters("state_entered").unwrap_or_default();
+                let exited = bpf.counters("state_exited").unwrap_or_default();
+                for statistic in &self.statistics {
+                    if *statistic == TcpStatistic::ConnectionsTimeWait {
+                        continue;
+                    }
+                    if let Some(state) = statistic.connection_state() {
+                        let value = connection_count(baseline, &entered, &exited, state);
+                        let _ = self
+                            .metrics()
+                            .record_gauge(statistic, time, value.max(0) as u64);
+                    }
+                }
+            }
+        }
+
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
             if let Some(ref bpf) = self.bpf {
                 let bpf = bpf.lock().unwrap();
                 let time = Instant::now();
                 for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
-                    if let Ok(mut table) = (*bpf).inner.table(statistic.bpf_table().unwrap()) {
-                        for (&value, &count) in &map_from_table(&mut table) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
                             if count > 0 {
-                                let _ = self.metrics().record_bucket(
-                                    statistic,
-                                    time,
-                                    value * 1000,
-                                    count,
-                                );
+                                self.common()
+                                    .record_bucket(statistic, time, value * 1000, count);
                             }
                         }
                     }
@@ -227,3 +474,128 @@ impl Tcp {
         Ok(())
     }
 }
+
+/// Counts the sockets in each state from the content of `/proc/net/tcp` or
+/// `/proc/net/tcp6`, where the state is the hex encoded fourth column
+fn parse_connection_states(content: &str) -> HashMap<u8, u64> {
+    let mut states = HashMap::new();
+    // skip the header
+    for line in content.lines().skip(1) {
+        if let Some(Ok(state)) = line
+            .split_whitespace()
+            .nth(3)
+            .map(|s| u8::from_str_radix(s, 16))
+        {
+            *states.entry(state).or_insert(0) += 1;
+        }
+    }
+    states
+}
+
+/// The number of sockets in a state, from the baseline for the state and the
+/// number of sockets which BPF has counted entering and leaving it since
+#[cfg(any(feature = "bpf", feature = "bpf_core"))]
+fn connection_count(
+    baseline: &HashMap<u8, i64>,
+    entered: &HashMap<u32, u64>,
+    exited: &HashMap<u32, u64>,
+    state: u8,
+) -> i64 {
+    let transitions =
+        |counts: &HashMap<u32, u64>| counts.get(&(state as u32)).copied().unwrap_or(0) as i64;
+    baseline.get(&state).copied().unwrap_or(0) + transitions(entered) - transitions(exited)
+}
+
+/// Parses the number of sockets in TIME_WAIT from the content of
+/// `/proc/net/sockstat`, which has a line such as
+/// `TCP: inuse 5 orphan 0 tw 3 alloc 8 mem 1`
+fn parse_time_wait(content: &str) -> Option<u64> {
+    let line = content.lines().find_map(|line| line.strip_prefix("TCP:"))?;
+    let mut fields = line.split_whitespace();
+    fields.find(|field| *field == "tw")?;
+    fields.next()?.parse().ok()
+}
+
+/// Returns the number of connections waiting to be accepted and the maximum
+/// backlog of each listening socket from the content of `/proc/net/tcp` or
+/// `/proc/net/tcp6`. For listening sockets, the fifth column holds the maximum
+/// backlog and the current length of the accept queue as `tx_queue:rx_queue`.
+fn parse_listen_backlogs(content: &str) -> Vec<(u64, u64)> {
+    let mut backlogs = Vec::new();
+    // skip the header
+    for line in content.lines().skip(1) {
+        let mut parts = line.split_whitespace().skip(3);
+        if parts.next() != Some("0A") {
+            continue;
+        }
+        if let Some((max, queued)) = parts.next().and_then(|s| s.split_once(':')) {
+            if let (Ok(max), Ok(queued)) = (
+                u64::from_str_radix(max, 16),
+                u64::from_str_radix(queued, 16),
+            ) {
+                backlogs.push((queued, max));
+            }
+        }
+    }
+    backlogs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_states() {
+        let states = parse_connection_states(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   \
+             uid  timeout inode\n   \
+             0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     \
+             0        0 21345 1 0000000000000000 100 0 0 10 0\n   \
+             1: 0100007F:1F90 0100007F:D2C4 01 00000000:00000000 00:00000000 00000000  \
+             1000        0 88231 1 0000000000000000 20 4 30 10 -1\n   \
+             2: 0100007F:D2C4 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  \
+             1000        0 88230 1 0000000000000000 20 4 30 10 -1\n   \
+             3: 0100007F:D2C6 0100007F:1F90 06 00000000:00000000 03:00001770 00000000     \
+             0        0 0 3 0000000000000000\n",
+        );
+        assert_eq!(states.len(), 3);
+        assert_eq!(states.get(&0x01), Some(&2));
+        assert_eq!(states.get(&0x06), Some(&1));
+        assert_eq!(states.get(&0x0A), Some(&1));
+    }
+
+    #[test]
+    fn test_parse_time_wait() {
+        let content = "sockets: used 130\n\
+                       TCP: inuse 5 orphan 0 tw 3 alloc 8 mem 1\n\
+                       UDP: inuse 2 mem 1\n";
+        assert_eq!(parse_time_wait(content), Some(3));
+        assert_eq!(parse_time_wait("UDP: inuse 2 mem 1\n"), None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "bpf", feature = "bpf_core"))]
+    fn test_connection_count() {
+        let baseline: HashMap<u8, i64> = vec![(0x01, 10), (0x0A, -2)].into_iter().collect();
+        let entered: HashMap<u32, u64> = vec![(0x01, 5), (0x0A, 4)].into_iter().collect();
+        let exited: HashMap<u32, u64> = vec![(0x01, 7)].into_iter().collect();
+        assert_eq!(connection_count(&baseline, &entered, &exited, 0x01), 8);
+        assert_eq!(connection_count(&baseline, &entered, &exited, 0x0A), 2);
+        assert_eq!(connection_count(&baseline, &entered, &exited, 0x02), 0);
+    }
+
+    #[test]
+    fn test_parse_listen_backlogs() {
+        let backlogs = parse_listen_backlogs(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   \
+             uid  timeout inode\n   \
+             0: 00000000:0016 00000000:0000 0A 00000080:00000003 00:00000000 00000000     \
+             0        0 21345 1 0000000000000000 100 0 0 10 0\n   \
+             1: 0100007F:1F90 0100007F:D2C4 01 00000000:00000000 00:00000000 00000000  \
+             1000        0 88231 1 0000000000000000 20 4 30 10 -1\n   \
+             2: 00000000:1F90 00000000:0000 0A 00001000:00000000 00:00000000 00000000  \
+             1000        0 88229 1 0000000000000000 100 0 0 10 0\n",
+        );
+        assert_eq!(backlogs, vec![(3, 128), (0, 4096)]);
+    }
+}
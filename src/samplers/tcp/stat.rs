@@ -25,6 +25,8 @@ use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 )]
 #[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
 pub enum TcpStatistic {
+    #[strum(serialize = "tcp/accept/latency")]
+    AcceptLatency,
     #[strum(serialize = "tcp/connect/latency")]
     ConnectLatency,
     #[strum(serialize = "tcp/receive/segment")]
@@ -59,6 +61,12 @@ pub enum TcpStatistic {
     ReceiveListenOverflows,
     #[strum(serialize = "tcp/receive/listen_drops")]
     ReceiveListenDrops,
+    #[strum(serialize = "tcp/receive/syn_queue_drops")]
+    ReceiveSynQueueDrops,
+    #[strum(serialize = "tcp/listen/backlog")]
+    ListenBacklog,
+    #[strum(serialize = "tcp/listen/utilization")]
+    ListenUtilization,
     #[strum(serialize = "tcp/abort/failed")]
     AbortFailed,
     #[strum(serialize = "tcp/abort/on_close")]
@@ -71,6 +79,40 @@ pub enum TcpStatistic {
     AbortOnMemory,
     #[strum(serialize = "tcp/abort/on_timeout")]
     AbortOnTimeout,
+    #[strum(serialize = "tcp/retransmit/timeout")]
+    RetransmitTimeout,
+    #[strum(serialize = "tcp/retransmit/recovery")]
+    RetransmitRecovery,
+    #[strum(serialize = "tcp/retransmit/tail_loss_probe")]
+    RetransmitTailLossProbe,
+    #[strum(serialize = "tcp/retransmit/syn")]
+    RetransmitSyn,
+    #[strum(serialize = "tcp/retransmit/other")]
+    RetransmitOther,
+    #[strum(serialize = "tcp/timeout/rto")]
+    TimeoutRto,
+    #[strum(serialize = "tcp/receive/drop")]
+    ReceiveDrop,
+    #[strum(serialize = "tcp/connections/established")]
+    ConnectionsEstablished,
+    #[strum(serialize = "tcp/connections/syn_sent")]
+    ConnectionsSynSent,
+    #[strum(serialize = "tcp/connections/syn_recv")]
+    ConnectionsSynRecv,
+    #[strum(serialize = "tcp/connections/fin_wait1")]
+    ConnectionsFinWait1,
+    #[strum(serialize = "tcp/connections/fin_wait2")]
+    ConnectionsFinWait2,
+    #[strum(serialize = "tcp/connections/time_wait")]
+    ConnectionsTimeWait,
+    #[strum(serialize = "tcp/connections/close_wait")]
+    ConnectionsCloseWait,
+    #[strum(serialize = "tcp/connections/last_ack")]
+    ConnectionsLastAck,
+    #[strum(serialize = "tcp/connections/listen")]
+    ConnectionsListen,
+    #[strum(serialize = "tcp/connections/closing")]
+    ConnectionsClosing,
 }
 
 impl TcpStatistic {
@@ -98,16 +140,56 @@ impl TcpStatistic {
             Self::TransmitDelayedAcks => Some(("TcpExt:", "DelayedACKs")),
             Self::ReceiveListenOverflows => Some(("TcpExt:", "ListenOverflows")),
             Self::ReceiveListenDrops => Some(("TcpExt:", "ListenDrops")),
+            Self::ReceiveSynQueueDrops => Some(("TcpExt:", "TCPReqQFullDrop")),
             _ => None,
         }
     }
 
     pub fn bpf_table(self) -> Option<&'static str> {
         match self {
+            Self::AcceptLatency => Some("acceptlat"),
             Self::ConnectLatency => Some("connlat"),
             _ => None,
         }
     }
+
+    /// The BPF array and index within it which hold the value of a counter
+    pub fn bpf_counter(self) -> Option<(&'static str, u32)> {
+        match self {
+            Self::RetransmitOther => Some(("retransmits", 0)),
+            Self::RetransmitTimeout => Some(("retransmits", 1)),
+            Self::RetransmitRecovery => Some(("retransmits", 2)),
+            Self::RetransmitTailLossProbe => Some(("retransmits", 3)),
+            Self::RetransmitSyn => Some(("retransmits", 4)),
+            Self::TimeoutRto => Some(("events", 0)),
+            Self::ReceiveDrop => Some(("events", 1)),
+            _ => None,
+        }
+    }
+
+    /// The socket state, as shown in `/proc/net/tcp`, which is counted by
+    /// this statistic
+    pub fn connection_state(self) -> Option<u8> {
+        match self {
+            Self::ConnectionsEstablished => Some(0x01),
+            Self::ConnectionsSynSent => Some(0x02),
+            Self::ConnectionsSynRecv => Some(0x03),
+            Self::ConnectionsFinWait1 => Some(0x04),
+            Self::ConnectionsFinWait2 => Some(0x05),
+            Self::ConnectionsTimeWait => Some(0x06),
+            Self::ConnectionsCloseWait => Some(0x08),
+            Self::ConnectionsLastAck => Some(0x09),
+            Self::ConnectionsListen => Some(0x0A),
+            Self::ConnectionsClosing => Some(0x0B),
+            _ => None,
+        }
+    }
+
+    /// Whether this statistic is calculated from the accept queues of the
+    /// listening sockets
+    pub fn listen_backlog(self) -> bool {
+        matches!(self, Self::ListenBacklog | Self::ListenUtilization)
+    }
 }
 
 impl Statistic<AtomicU64, AtomicU32> for TcpStatistic {
@@ -118,6 +200,8 @@ impl Statistic<AtomicU64, AtomicU32> for TcpStatistic {
     fn source(&self) -> Source {
         if self.bpf_table().is_some() {
             Source::Distribution
+        } else if self.connection_state().is_some() || self.listen_backlog() {
+            Source::Gauge
         } else {
             Source::Counter
         }
@@ -0,0 +1,261 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::*;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const THERMAL_PATH: &str = "/sys/class/thermal";
+const CPU_PATH: &str = "/sys/devices/system/cpu";
+
+pub struct Thermal {
+    common: Common,
+    /// thermal zone names, such as `zone0`, and their temperature files
+    zones: Vec<(String, PathBuf)>,
+    /// `thermal_throttle` directories for every cpu
+    cpus: Vec<PathBuf>,
+    /// `thermal_throttle` directories for one cpu in each package
+    packages: Vec<PathBuf>,
+    statistics: Vec<ThermalConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Thermal {
+    type Statistic = ThermalStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().thermal().statistics.clone();
+        let zones = discover_zones();
+        let (cpus, packages) = discover_throttle();
+        if zones.is_empty() && cpus.is_empty() {
+            return Err(format_err!("no thermal zones or throttle counters found"));
+        }
+        let sampler = Self {
+            common,
+            zones,
+            cpus,
+            packages,
+            statistics,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.register_statistics();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().thermal().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize thermal sampler {}", e);
+                    } else {
+                        error!("failed to initialize thermal sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().thermal()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().thermal().statistics.clone();
+        if self.enabled() {
+            self.register_statistics();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_temperature().await;
+        self.map_result(r)?;
+
+        let r = self.sample_throttle().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Thermal {
+    /// Returns the statistics which will be reported, expanding temperature
+    /// into one statistic per thermal zone
+    fn expanded_statistics(&self) -> Vec<ThermalStatistic> {
+        let mut statistics = Vec::new();
+        for statistic in &self.statistics {
+            if *statistic == ThermalConfigStatistic::Temperature {
+                for (zone, _) in &self.zones {
+                    statistics.push(ThermalStatistic::zone(zone, *statistic));
+                }
+            } else if !self.cpus.is_empty() {
+                statistics.push(ThermalStatistic::new(*statistic));
+            }
+        }
+        statistics
+    }
+
+    fn register_statistics(&self) {
+        let percentiles = self.sampler_config().percentiles();
+        for statistic in self.expanded_statistics() {
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+        }
+    }
+
+    async fn sample_temperature(&self) -> Result<(), std::io::Error> {
+        if !self
+            .statistics
+            .contains(&ThermalConfigStatistic::Temperature)
+        {
+            return Ok(());
+        }
+        for (zone, path) in &self.zones {
+            // some zones return an error while their sensor is unavailable
+            if let Some(millidegrees) = read_value::<i64>(path).await {
+                let celsius = (millidegrees as f64 / 1000.0).round().max(0.0) as u64;
+                let _ = self.metrics().record_gauge(
+                    &ThermalStatistic::zone(zone, ThermalConfigStatistic::Temperature),
+                    Instant::now(),
+                    celsius,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn sample_throttle(&self) -> Result<(), std::io::Error> {
+        for statistic in &self.statistics {
+            let file = match statistic.throttle_file() {
+                Some(file) => file,
+                None => continue,
+            };
+            let directories = if statistic.per_package() {
+                &self.packages
+            } else {
+                &self.cpus
+            };
+            let mut total = 0;
+            for directory in directories {
+                total += read_value::<u64>(&directory.join(file)).await.unwrap_or(0);
+            }
+            if *statistic == ThermalConfigStatistic::ThrottleCoreTime
+                || *statistic == ThermalConfigStatistic::ThrottlePackageTime
+            {
+                total *= MILLISECOND;
+            }
+            let statistic = ThermalStatistic::new(*statistic);
+            self.common()
+                .record_counter(&statistic, Instant::now(), total);
+        }
+        Ok(())
+    }
+}
+
+async fn read_value<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    tokio::fs::read_to_string(path)
+        .await
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Finds thermal zones, such as `thermal_zone0`, which are exported with
+/// names of the form `zone0`
+fn discover_zones() -> Vec<(String, PathBuf)> {
+    let mut zones = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(THERMAL_PATH) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(id) = name.strip_prefix("thermal_zone") {
+                zones.push((format!("zone{}", id), entry.path().join("temp")));
+            }
+        }
+    }
+    zones.sort();
+    zones
+}
+
+/// Finds the `thermal_throttle` directory for each cpu, along with one cpu
+/// from each physical package
+fn discover_throttle() -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut cpus = Vec::new();
+    let mut packages = Vec::new();
+    let mut seen = HashSet::new();
+    if let Ok(entries) = std::fs::read_dir(CPU_PATH) {
+        let mut entries: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.file_name()
+                    .to_str()
+                    .and_then(|v| v.strip_prefix("cpu"))
+                    .map(|v| v.parse::<u64>().is_ok())
+                    .unwrap_or(false)
+            })
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+        for cpu in entries {
+            let directory = cpu.join("thermal_throttle");
+            if !directory.exists() {
+                continue;
+            }
+            let package = std::fs::read_to_string(cpu.join("topology/physical_package_id"))
+                .map(|v| v.trim().to_string())
+                .unwrap_or_default();
+            if seen.insert(package) {
+                packages.push(directory.clone());
+            }
+            cpus.push(directory);
+        }
+    }
+    (cpus, packages)
+}
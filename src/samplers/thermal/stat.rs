@@ -0,0 +1,119 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ThermalConfigStatistic {
+    #[strum(serialize = "temperature")]
+    Temperature,
+    #[strum(serialize = "throttle/core/count")]
+    ThrottleCoreCount,
+    #[strum(serialize = "throttle/core/time")]
+    ThrottleCoreTime,
+    #[strum(serialize = "throttle/package/count")]
+    ThrottlePackageCount,
+    #[strum(serialize = "throttle/package/time")]
+    ThrottlePackageTime,
+}
+
+impl ThermalConfigStatistic {
+    /// The file within a cpu's `thermal_throttle` directory which provides the
+    /// value for this statistic. Temperatures are read from thermal zones
+    /// instead.
+    pub fn throttle_file(self) -> Option<&'static str> {
+        match self {
+            Self::Temperature => None,
+            Self::ThrottleCoreCount => Some("core_throttle_count"),
+            Self::ThrottleCoreTime => Some("core_throttle_total_time_ms"),
+            Self::ThrottlePackageCount => Some("package_throttle_count"),
+            Self::ThrottlePackageTime => Some("package_throttle_total_time_ms"),
+        }
+    }
+
+    /// Package throttling is reported identically by every cpu in the package
+    pub fn per_package(self) -> bool {
+        matches!(self, Self::ThrottlePackageCount | Self::ThrottlePackageTime)
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::Temperature => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for ThermalConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ThermalConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ThermalStatistic {
+    name: String,
+    statistic: ThermalConfigStatistic,
+}
+
+impl ThermalStatistic {
+    /// A statistic which is reported for the system as a whole
+    pub fn new(statistic: ThermalConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("thermal/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic which is reported for each thermal zone
+    pub fn zone(zone: &str, statistic: ThermalConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("thermal/{}/{}", zone, suffix),
+                format!("thermal/{}", suffix),
+                &[("zone", zone)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> ThermalConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ThermalStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+
+// events, which index the counters array
+#define HANDSHAKE_TOTAL 0
+#define HANDSHAKE_ERROR 1
+#define READ_BYTES 2
+#define WRITE_BYTES 3
+
+// the time of the first attempt of a handshake, keyed by the SSL object. A
+// handshake on a non-blocking socket is retried until it completes, and one
+// which fails with an error is never removed, so entries are evicted as the
+// table fills.
+BPF_TABLE("lru_hash", u64, u64, start, 65536);
+
+// the SSL object of the handshake in progress on each thread
+BPF_HASH(handshakes, u64, u64);
+
+BPF_ARRAY(counters, u64, 4);
+BPF_HISTOGRAM(latency, int, 461);
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+int trace_handshake_entry(struct pt_regs *ctx)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    u64 ssl = PT_REGS_PARM1(ctx);
+    u64 ts = bpf_ktime_get_ns();
+    // keep the time of the first attempt when a handshake is retried
+    start.insert(&ssl, &ts);
+    handshakes.update(&id, &ssl);
+    return 0;
+}
+
+int trace_handshake_return(struct pt_regs *ctx)
+{
+    u64 id = bpf_get_current_pid_tgid();
+    u64 *ssl = handshakes.lookup(&id);
+    if (ssl == 0) {
+        return 0;   // missed entry
+    }
+    u64 key = *ssl;
+    handshakes.delete(&id);
+
+    // a negative return is either a fatal error, or a non-blocking handshake
+    // which needs to be retried once the socket is ready, which can only be
+    // told apart with SSL_get_error()
+    int ret = PT_REGS_RC(ctx);
+    if (ret < 0) {
+        return 0;
+    }
+
+    add(HANDSHAKE_TOTAL, 1);
+    if (ret == 0) {
+        add(HANDSHAKE_ERROR, 1);
+    } else {
+        u64 *tsp = start.lookup(&key);
+        if (tsp != 0) {
+            u64 delta_us = (bpf_ktime_get_ns() - *tsp) / 1000ul;
+            latency.increment(value_to_index2(delta_us));
+        }
+    }
+    start.delete(&key);
+    return 0;
+}
+
+int trace_read_return(struct pt_regs *ctx)
+{
+    int ret = PT_REGS_RC(ctx);
+    if (ret > 0) {
+        add(READ_BYTES, ret);
+    }
+    return 0;
+}
+
+int trace_write_return(struct pt_regs *ctx)
+{
+    int ret = PT_REGS_RC(ctx);
+    if (ret > 0) {
+        add(WRITE_BYTES, ret);
+    }
+    return 0;
+}
@@ -0,0 +1,80 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum TlsStatistic {
+    #[strum(serialize = "tls/handshake/error")]
+    HandshakeError,
+    #[strum(serialize = "tls/handshake/latency")]
+    HandshakeLatency,
+    #[strum(serialize = "tls/handshake/total")]
+    HandshakeTotal,
+    #[strum(serialize = "tls/read/bytes")]
+    ReadBytes,
+    #[strum(serialize = "tls/write/bytes")]
+    WriteBytes,
+}
+
+impl TlsStatistic {
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::HandshakeTotal => Some(0),
+            Self::HandshakeError => Some(1),
+            Self::ReadBytes => Some(2),
+            Self::WriteBytes => Some(3),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::HandshakeLatency => Some("latency"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for TlsStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        if self.bpf_table().is_some() {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
+
+impl TryFrom<&str> for TlsStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        TlsStatistic::from_str(s)
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <uapi/linux/errno.h>
+
+// drop reasons, which index the drops array
+#define DROP_RECEIVE_BUFFER 0
+#define DROP_MEMORY_LIMIT 1
+
+BPF_ARRAY(drops, u64, 2);
+
+// __udp_enqueue_schedule_skb() queues a datagram on the receiving socket and
+// fails if the socket receive buffer is full or the protocol-wide memory limit
+// in net.ipv4.udp_mem is reached
+int trace_enqueue_return(struct pt_regs *ctx)
+{
+    int ret = PT_REGS_RC(ctx);
+    if (ret == -ENOMEM) {
+        drops.increment(DROP_RECEIVE_BUFFER);
+    } else if (ret == -ENOBUFS) {
+        drops.increment(DROP_MEMORY_LIMIT);
+    }
+    return 0;
+}
@@ -3,9 +3,11 @@
 // http://www.apache.org/licenses/LICENSE-2.0
 
 use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tokio::fs::File;
 
+use crate::common::bpf::BPF;
 use crate::config::SamplerConfig;
 use crate::samplers::Common;
 use crate::Sampler;
@@ -18,6 +20,7 @@ pub use stat::UdpStatistic;
 
 #[allow(dead_code)]
 pub struct Udp {
+    bpf: Option<Arc<Mutex<BPF>>>,
     common: Common,
     proc_net_snmp: Option<File>,
     proc_net_netstat: Option<File>,
@@ -29,14 +32,25 @@ impl Sampler for Udp {
     type Statistic = UdpStatistic;
 
     fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
         let statistics = common.config().samplers().udp().statistics();
 
-        let sampler = Self {
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
             common,
             proc_net_snmp: None,
             proc_net_netstat: None,
             statistics,
         };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
         if sampler.sampler_config().enabled() {
             sampler.register();
         }
@@ -47,7 +61,7 @@ impl Sampler for Udp {
         if common.config().samplers().udp().enabled() {
             if let Ok(mut sampler) = Self::new(common.clone()) {
                 common.runtime().spawn(async move {
-                    loop {
+                    while !sampler.retired() {
                         let _ = sampler.sample().await;
                     }
                 });
@@ -67,6 +81,13 @@ impl Sampler for Udp {
         &mut self.common
     }
 
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
     async fn sample(&mut self) -> Result<(), std::io::Error> {
         if let Some(ref mut delay) = self.delay() {
             delay.tick().await;
@@ -84,6 +105,12 @@ impl Sampler for Udp {
         let r = self.sample_netstat().await;
         self.map_result(r)?;
 
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
         Ok(())
     }
 
@@ -93,6 +120,40 @@ impl Sampler for Udp {
 }
 
 impl Udp {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        if self.sampler_config().bpf() {
+            for statistic in self.sampler_config().statistics() {
+                if statistic.bpf_drop_reason().is_some() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let code = include_str!("bpf.c");
+                let mut bpf = bcc::BPF::new(code)?;
+
+                // shared by ipv4 and ipv6
+                bcc::Kretprobe::new()
+                    .handler("trace_enqueue_return")
+                    .function("__udp_enqueue_schedule_skb")
+                    .attach(&mut bpf)?;
+
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })))
+            }
+        }
+
+        Ok(())
+    }
+
     async fn sample_snmp(&mut self) -> Result<(), std::io::Error> {
         if self.proc_net_snmp.is_none() {
             let file = File::open("/proc/net/snmp").await?;
@@ -105,7 +166,7 @@ impl Udp {
                 if let Some((pkey, lkey)) = statistic.keys() {
                     if let Some(inner) = parsed.get(pkey) {
                         if let Some(value) = inner.get(lkey) {
-                            let _ = self.metrics().record_counter(statistic, time, *value);
+                            self.common().record_counter(statistic, time, *value);
                         }
                     }
                 }
@@ -127,7 +188,7 @@ impl Udp {
                 if let Some((pkey, lkey)) = statistic.keys() {
                     if let Some(inner) = parsed.get(pkey) {
                         if let Some(value) = inner.get(lkey) {
-                            let _ = self.metrics().record_counter(statistic, time, *value);
+                            self.common().record_counter(statistic, time, *value);
                         }
                     }
                 }
@@ -135,4 +196,21 @@ impl Udp {
         }
         Ok(())
     }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Some(drops) = bpf.counters("drops") {
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    if let Some(index) = statistic.bpf_drop_reason() {
+                        let value = drops.get(&index).copied().unwrap_or(0);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
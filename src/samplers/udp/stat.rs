@@ -25,10 +25,22 @@ use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 )]
 #[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
 pub enum UdpStatistic {
+    #[strum(serialize = "udp/drop/memory_limit")]
+    DropMemoryLimit,
+    #[strum(serialize = "udp/drop/receive_buffer")]
+    DropReceiveBuffer,
+    #[strum(serialize = "udp/receive/buffer_errors")]
+    RcvbufErrors,
+    #[strum(serialize = "udp/receive/checksum_errors")]
+    InCsumErrors,
     #[strum(serialize = "udp/receive/datagrams")]
     InDatagrams,
     #[strum(serialize = "udp/receive/errors")]
     InErrors,
+    #[strum(serialize = "udp/receive/no_port")]
+    NoPorts,
+    #[strum(serialize = "udp/transmit/buffer_errors")]
+    SndbufErrors,
     #[strum(serialize = "udp/transmit/datagrams")]
     OutDatagrams,
 }
@@ -36,9 +48,23 @@ pub enum UdpStatistic {
 impl UdpStatistic {
     pub fn keys(self) -> Option<(&'static str, &'static str)> {
         match self {
+            Self::InCsumErrors => Some(("Udp:", "InCsumErrors")),
             Self::InDatagrams => Some(("Udp:", "InDatagrams")),
             Self::InErrors => Some(("Udp:", "InErrors")),
+            Self::NoPorts => Some(("Udp:", "NoPorts")),
             Self::OutDatagrams => Some(("Udp:", "OutDatagrams")),
+            Self::RcvbufErrors => Some(("Udp:", "RcvbufErrors")),
+            Self::SndbufErrors => Some(("Udp:", "SndbufErrors")),
+            _ => None,
+        }
+    }
+
+    /// The index of the counter in the `drops` BPF array
+    pub fn bpf_drop_reason(self) -> Option<u32> {
+        match self {
+            Self::DropReceiveBuffer => Some(0),
+            Self::DropMemoryLimit => Some(1),
+            _ => None,
         }
     }
 }
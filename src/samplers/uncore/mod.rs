@@ -0,0 +1,180 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::perf::{self, Counter};
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const EVENT_SOURCE_DEVICES: &str = "/sys/bus/event_source/devices";
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+
+pub struct Uncore {
+    common: Common,
+    /// open counters, one for each memory controller channel and statistic,
+    /// which are opened on the first sample
+    counters: Option<Vec<(u64, UncoreConfigStatistic, Counter)>>,
+    /// statistics which have been registered, as sockets are discovered at
+    /// runtime
+    registered: HashSet<UncoreStatistic>,
+    statistics: Vec<UncoreConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Uncore {
+    type Statistic = UncoreStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().uncore().statistics.clone();
+        Ok(Self {
+            common,
+            counters: None,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().uncore().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize uncore sampler {}", e);
+                    } else {
+                        error!("failed to initialize uncore sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().uncore()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().uncore().statistics.clone();
+        // open counters and register the new set of statistics on the next
+        // sample
+        self.counters = None;
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_imc();
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Uncore {
+    fn record(&mut self, statistic: UncoreStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        self.common().record_counter(&statistic, time, value);
+    }
+
+    /// Opens the CAS count events of each integrated memory controller (IMC)
+    /// channel, which are exposed as `uncore_imc_<N>` PMUs on Intel server
+    /// processors. Each CAS operation transfers a 64 byte cache line.
+    fn open_imc(&self) -> Result<Vec<(u64, UncoreConfigStatistic, Counter)>, std::io::Error> {
+        let mut counters = Vec::new();
+        for entry in std::fs::read_dir(EVENT_SOURCE_DEVICES)? {
+            let pmu = entry?.path();
+            let name = pmu.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !name.starts_with("uncore_imc") {
+                continue;
+            }
+            for cpu in perf::cpus(&pmu)? {
+                let socket = std::fs::read_to_string(format!(
+                    "{}/cpu{}/topology/physical_package_id",
+                    SYSFS_CPU, cpu
+                ))
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(0);
+                for statistic in &self.statistics {
+                    counters.push((
+                        socket,
+                        *statistic,
+                        Counter::open(&pmu, statistic.event(), cpu)?,
+                    ));
+                }
+            }
+        }
+        if counters.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no uncore memory controller PMUs found",
+            ));
+        }
+        Ok(counters)
+    }
+
+    fn sample_imc(&mut self) -> Result<(), std::io::Error> {
+        if self.counters.is_none() {
+            self.counters = Some(self.open_imc()?);
+        }
+        let time = Instant::now();
+        let mut totals: HashMap<(u64, UncoreConfigStatistic), u64> = HashMap::new();
+        for (socket, statistic, counter) in self.counters.as_mut().unwrap().iter_mut() {
+            let bytes = counter.read()?;
+            *totals.entry((*socket, *statistic)).or_insert(0) += bytes;
+        }
+        for ((socket, statistic), bytes) in totals {
+            self.record(UncoreStatistic::new(socket, statistic), time, bytes);
+        }
+        Ok(())
+    }
+}
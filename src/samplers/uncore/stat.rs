@@ -0,0 +1,91 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum UncoreConfigStatistic {
+    #[strum(serialize = "memory/read")]
+    MemoryRead,
+    #[strum(serialize = "memory/write")]
+    MemoryWrite,
+}
+
+impl UncoreConfigStatistic {
+    /// The event of the `uncore_imc` PMUs which counts this statistic
+    pub fn event(self) -> &'static str {
+        match self {
+            Self::MemoryRead => "cas_count_read",
+            Self::MemoryWrite => "cas_count_write",
+        }
+    }
+
+    pub fn source(self) -> Source {
+        Source::Counter
+    }
+}
+
+impl TryFrom<&str> for UncoreConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        UncoreConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UncoreStatistic {
+    name: String,
+    statistic: UncoreConfigStatistic,
+}
+
+impl UncoreStatistic {
+    /// A statistic of a socket, which is named `uncore/socket<id>/...`
+    pub fn new(socket: u64, statistic: UncoreConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("uncore/socket{}/{}", socket, suffix),
+                format!("uncore/{}", suffix),
+                &[("socket", &socket.to_string())],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> UncoreConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for UncoreStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::UprobeStatistic;
+
+pub const NAMESPACE: &str = "uprobe";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeType {
+    /// Counts calls to the function
+    Uprobe,
+    /// Counts returns from the function
+    Uretprobe,
+}
+
+impl Default for ProbeType {
+    fn default() -> Self {
+        Self::Uprobe
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeConfig {
+    /// Path to the binary or library containing the symbol
+    pub binary: String,
+    pub symbol: String,
+    #[serde(default, rename = "type")]
+    pub probe_type: ProbeType,
+    /// Name used for the metric, `uprobe/<name>`. Defaults to the file name of
+    /// the binary followed by the symbol.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl ProbeConfig {
+    pub fn stat_path(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("{}/{}", NAMESPACE, name),
+            None => {
+                let binary = std::path::Path::new(&self.binary)
+                    .file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_else(|| self.binary.clone());
+                let suffix = match self.probe_type {
+                    ProbeType::Uprobe => "",
+                    ProbeType::Uretprobe => "/return",
+                };
+                format!("{}/{}/{}{}", NAMESPACE, binary, self.symbol, suffix)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct UprobeConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default)]
+    percentiles: Vec<f64>,
+    #[serde(default)]
+    probes: Vec<ProbeConfig>,
+}
+
+impl UprobeConfig {
+    /// The configured probes, skipping any which would duplicate the metric
+    /// of an earlier probe
+    pub fn probes(&self) -> Vec<ProbeConfig> {
+        let mut probes: Vec<ProbeConfig> = Vec::new();
+        for probe in &self.probes {
+            if probes.iter().any(|p| p.stat_path() == probe.stat_path()) {
+                warn!("Skipping duplicate uprobe: {}", probe.stat_path());
+                continue;
+            }
+            probes.push(probe.clone());
+        }
+        probes
+    }
+}
+
+impl SamplerConfig for UprobeConfig {
+    type Statistic = UprobeStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        self.probes()
+            .iter()
+            .map(|probe| UprobeStatistic {
+                stat_path: probe.stat_path(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(binary: &str, symbol: &str, probe_type: ProbeType, name: Option<&str>) -> ProbeConfig {
+        ProbeConfig {
+            binary: binary.into(),
+            symbol: symbol.into(),
+            probe_type,
+            name: name.map(|n| n.into()),
+        }
+    }
+
+    #[test]
+    fn stat_path() {
+        assert_eq!(
+            probe(
+                "/usr/sbin/nginx",
+                "ngx_event_accept",
+                ProbeType::Uprobe,
+                None
+            )
+            .stat_path(),
+            "uprobe/nginx/ngx_event_accept"
+        );
+        assert_eq!(
+            probe(
+                "/usr/sbin/nginx",
+                "ngx_event_accept",
+                ProbeType::Uretprobe,
+                None
+            )
+            .stat_path(),
+            "uprobe/nginx/ngx_event_accept/return"
+        );
+        assert_eq!(
+            probe(
+                "/usr/sbin/nginx",
+                "ngx_event_accept",
+                ProbeType::Uprobe,
+                Some("accepts")
+            )
+            .stat_path(),
+            "uprobe/accepts"
+        );
+    }
+
+    #[test]
+    fn dedup() {
+        let config = UprobeConfig {
+            probes: vec![
+                probe(
+                    "/usr/sbin/nginx",
+                    "ngx_event_accept",
+                    ProbeType::Uprobe,
+                    None,
+                ),
+                probe(
+                    "/usr/sbin/nginx",
+                    "ngx_event_accept",
+                    ProbeType::Uprobe,
+                    None,
+                ),
+                probe(
+                    "/usr/sbin/nginx",
+                    "ngx_event_accept",
+                    ProbeType::Uretprobe,
+                    None,
+                ),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.probes().len(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use async_trait::async_trait;
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::common::bpf::BPF;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+#[cfg(feature = "bpf")]
+use crate::common::bpf::perf_table_to_map;
+
+mod config;
+mod stat;
+
+pub use config::{ProbeConfig, ProbeType, UprobeConfig, NAMESPACE};
+pub use stat::UprobeStatistic;
+
+#[allow(dead_code)]
+pub struct Uprobe {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    statistics: Vec<UprobeStatistic>,
+    /// the probes, where the index of each is its key in the counts table
+    probes: Vec<ProbeConfig>,
+}
+
+#[cfg(feature = "bpf")]
+const PROBE_PRELUDE: &str = r#"
+#include <uapi/linux/ptrace.h>
+BPF_ARRAY(counts, u64, NUM_PROBES);
+
+"#;
+
+#[cfg(feature = "bpf")]
+macro_rules! probe_template {
+    () => {
+        r#"
+
+int probe_{}(void *ctx) {{
+    int key = {};
+    u64 *val = counts.lookup(&key);
+    if (val) {{
+        lock_xadd(val, 1);
+    }}
+    return 0;
+}}
+"#
+    };
+}
+
+impl Uprobe {
+    fn init_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.probes.is_empty() {
+                return Ok(());
+            }
+
+            // generate a handler for each probe which counts into its own
+            // index of the counts table
+            let mut code = PROBE_PRELUDE.replace("NUM_PROBES", &self.probes.len().to_string());
+            for i in 0..self.probes.len() {
+                code.push_str(&format!(probe_template!(), i, i));
+            }
+
+            debug!("Registering probes: {:?}", self.probes);
+            let mut bpf = bcc::BPF::new(&code)?;
+            for (i, probe) in self.probes.iter().enumerate() {
+                let result = match probe.probe_type {
+                    ProbeType::Uprobe => bcc::Uprobe::new()
+                        .handler(&format!("probe_{}", i))
+                        .binary(&probe.binary)
+                        .symbol(&probe.symbol)
+                        .attach(&mut bpf),
+                    ProbeType::Uretprobe => bcc::Uretprobe::new()
+                        .handler(&format!("probe_{}", i))
+                        .binary(&probe.binary)
+                        .symbol(&probe.symbol)
+                        .attach(&mut bpf),
+                };
+                if let Err(e) = result {
+                    let err = std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("problem probing {:?}: {}", probe, e),
+                    );
+                    if self.common.config().fault_tolerant() {
+                        let stat_path = probe.stat_path();
+                        self.statistics.retain(|s| s.stat_path != stat_path);
+                        warn!("{}", err);
+                    } else {
+                        Err(err)?
+                    }
+                }
+            }
+
+            self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sampler for Uprobe {
+    type Statistic = UprobeStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().uprobe().statistics();
+        let probes = common.config().samplers().uprobe().probes();
+
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            statistics,
+            probes,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.init_bpf()?;
+            sampler.register();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().uprobe().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize uprobe sampler {}", e);
+                    } else {
+                        error!("failed to initialize uprobe sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().uprobe()
+    }
+
+    fn reconfigure(&mut self) {
+        // probes are attached at startup, so only the probes which were
+        // attached are kept
+        let statistics = self.sampler_config().statistics();
+        self.statistics.retain(|s| statistics.contains(s));
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let table = (*bpf)
+                .inner
+                .table("counts")
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            let counts = perf_table_to_map(&table);
+            for (i, probe) in self.probes.iter().enumerate() {
+                let stat_path = probe.stat_path();
+                if let Some(stat) = self.statistics.iter().find(|s| s.stat_path == stat_path) {
+                    let val = counts.get(&(i as u32)).unwrap_or(&0);
+                    self.common().record_counter(stat, Instant::now(), *val);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use serde_derive::Deserialize;
+
+use crate::config::{Histogram, SamplerConfig};
+
+use super::stat::UsdtStatistic;
+
+pub const NAMESPACE: &str = "usdt";
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeConfig {
+    /// Path to the binary or library which declares the probe
+    #[serde(default)]
+    pub binary: Option<String>,
+    /// Process to attach to, instead of a binary
+    #[serde(default)]
+    pub pid: Option<u32>,
+    pub provider: String,
+    pub probe: String,
+    /// Argument of the probe, starting from 1, to record as a histogram
+    #[serde(default)]
+    pub argument: Option<u8>,
+    /// Name used for the metrics, `usdt/<name>`. Defaults to the provider
+    /// followed by the probe name.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl ProbeConfig {
+    /// Name of the hit count for this probe
+    pub fn stat_path(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("{}/{}", NAMESPACE, name),
+            None => format!("{}/{}/{}", NAMESPACE, self.provider, self.probe),
+        }
+    }
+
+    /// Name of the argument histogram for this probe, if one is recorded
+    pub fn argument_stat_path(&self) -> Option<String> {
+        self.argument
+            .map(|argument| format!("{}/arg{}", self.stat_path(), argument))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UsdtConfig {
+    #[serde(default)]
+    bpf: bool,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
+    interval: Option<usize>,
+    #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default = "crate::common::default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(default)]
+    probes: Vec<ProbeConfig>,
+}
+
+impl Default for UsdtConfig {
+    fn default() -> Self {
+        Self {
+            bpf: Default::default(),
+            enabled: Default::default(),
+            histogram: Default::default(),
+            interval: Default::default(),
+            jitter: Default::default(),
+            percentiles: crate::common::default_percentiles(),
+            probes: Default::default(),
+        }
+    }
+}
+
+impl UsdtConfig {
+    /// The valid probes, which have exactly one of a binary or pid and which
+    /// do not duplicate the metrics of an earlier probe
+    pub fn probes(&self) -> Vec<ProbeConfig> {
+        let mut probes: Vec<ProbeConfig> = Vec::new();
+        for probe in &self.probes {
+            if probe.binary.is_some() == probe.pid.is_some() {
+                warn!(
+                    "Skipping usdt probe without one of binary or pid: {:?}",
+                    probe
+                );
+                continue;
+            }
+            if !(1..=12).contains(&probe.argument.unwrap_or(1)) {
+                warn!("Skipping usdt probe with invalid argument: {:?}", probe);
+                continue;
+            }
+            if probes.iter().any(|p| p.stat_path() == probe.stat_path()) {
+                warn!("Skipping duplicate usdt probe: {}", probe.stat_path());
+                continue;
+            }
+            probes.push(probe.clone());
+        }
+        probes
+    }
+}
+
+impl SamplerConfig for UsdtConfig {
+    type Statistic = UsdtStatistic;
+
+    fn bpf(&self) -> bool {
+        self.bpf
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn interval(&self) -> Option<usize> {
+        self.interval
+    }
+
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
+    fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    fn statistics(&self) -> Vec<<Self as SamplerConfig>::Statistic> {
+        let mut stats = Vec::new();
+        for probe in self.probes() {
+            stats.push(UsdtStatistic {
+                stat_path: probe.stat_path(),
+                histogram: false,
+            });
+            if let Some(stat_path) = probe.argument_stat_path() {
+                stats.push(UsdtStatistic {
+                    stat_path,
+                    histogram: true,
+                });
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(binary: Option<&str>, pid: Option<u32>, argument: Option<u8>) -> ProbeConfig {
+        ProbeConfig {
+            binary: binary.map(|b| b.into()),
+            pid,
+            provider: "postgresql".into(),
+            probe: "query__done".into(),
+            argument,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn statistics() {
+        let config = UsdtConfig {
+            probes: vec![probe(Some("/usr/bin/postgres"), None, Some(2))],
+            ..Default::default()
+        };
+        let names: Vec<String> = config
+            .statistics()
+            .into_iter()
+            .map(|s| s.stat_path)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "usdt/postgresql/query__done",
+                "usdt/postgresql/query__done/arg2"
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let config = UsdtConfig {
+            probes: vec![
+                probe(None, None, None),
+                probe(Some("/usr/bin/postgres"), Some(1), None),
+                probe(Some("/usr/bin/postgres"), None, Some(0)),
+                probe(None, Some(1), None),
+                probe(Some("/usr/bin/postgres"), None, None),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.probes(), vec![probe(None, Some(1), None)]);
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use async_trait::async_trait;
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::common::bpf::BPF;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+#[cfg(feature = "bpf")]
+use crate::common::bpf::perf_table_to_map;
+#[cfg(feature = "bpf")]
+use std::time::Duration;
+
+mod config;
+mod stat;
+
+pub use config::{ProbeConfig, UsdtConfig, NAMESPACE};
+pub use stat::UsdtStatistic;
+
+#[allow(dead_code)]
+pub struct Usdt {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    statistics: Vec<UsdtStatistic>,
+    /// the probes, where the index of each is its key in the counts table
+    probes: Vec<ProbeConfig>,
+}
+
+#[cfg(feature = "bpf")]
+const PROBE_PRELUDE: &str = r#"
+#include <uapi/linux/ptrace.h>
+BPF_ARRAY(counts, u64, NUM_PROBES);
+
+// histogram indexing
+static unsigned int value_to_index2(u64 value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+"#;
+
+#[cfg(feature = "bpf")]
+macro_rules! probe_template {
+    () => {
+        r#"
+
+int usdt_{0}(struct pt_regs *ctx) {{
+    int key = {0};
+    u64 *val = counts.lookup(&key);
+    if (val) {{
+        lock_xadd(val, 1);
+    }}
+    {1}
+    return 0;
+}}
+"#
+    };
+}
+
+#[cfg(feature = "bpf")]
+macro_rules! argument_template {
+    () => {
+        r#"u64 arg = 0;
+    bpf_usdt_readarg({1}, ctx, &arg);
+    arg_{0}.increment(value_to_index2(arg));"#
+    };
+}
+
+impl Usdt {
+    fn init_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.probes.is_empty() {
+                return Ok(());
+            }
+
+            // generate a handler for each probe which counts into its own
+            // index of the counts table, and records the argument into its
+            // own histogram if requested
+            let mut code = PROBE_PRELUDE.replace("NUM_PROBES", &self.probes.len().to_string());
+            for (i, probe) in self.probes.iter().enumerate() {
+                let argument = match probe.argument {
+                    Some(argument) => {
+                        code.push_str(&format!("BPF_HISTOGRAM(arg_{}, int, 461);\n", i));
+                        format!(argument_template!(), i, argument)
+                    }
+                    None => String::new(),
+                };
+                code.push_str(&format!(probe_template!(), i, argument));
+            }
+
+            // probes in the same binary or process share a context
+            let mut contexts: Vec<(ProbeConfig, bcc::USDTContext)> = Vec::new();
+            debug!("Registering probes: {:?}", self.probes);
+            for (i, probe) in self.probes.iter().enumerate() {
+                let index = match contexts
+                    .iter()
+                    .position(|(p, _)| p.binary == probe.binary && p.pid == probe.pid)
+                {
+                    Some(index) => Ok(index),
+                    None => match (&probe.binary, probe.pid) {
+                        (Some(binary), _) => bcc::USDTContext::from_binary_path(binary),
+                        (None, Some(pid)) => bcc::USDTContext::from_pid(pid as i32),
+                        (None, None) => unreachable!(),
+                    }
+                    .map(|context| {
+                        contexts.push((probe.clone(), context));
+                        contexts.len() - 1
+                    }),
+                };
+                let result = index.and_then(|index| {
+                    contexts[index]
+                        .1
+                        .enable_probe(&probe.probe, &format!("usdt_{}", i))
+                });
+
+                if let Err(e) = result {
+                    let err = std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("problem probing {:?}: {}", probe, e),
+                    );
+                    if self.common.config().fault_tolerant() {
+                        let stat_path = probe.stat_path();
+                        let argument_stat_path = probe.argument_stat_path();
+                        self.statistics.retain(|s| {
+                            s.stat_path != stat_path
+                                && Some(&s.stat_path) != argument_stat_path.as_ref()
+                        });
+                        warn!("{}", err);
+                    } else {
+                        Err(err)?
+                    }
+                }
+            }
+
+            let mut builder = bcc::BPFBuilder::new(&code)?;
+            for (_, context) in contexts {
+                builder = builder.add_usdt_context(context)?;
+            }
+            let bpf = builder.build()?;
+
+            self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            let table = (*bpf)
+                .inner
+                .table("counts")
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            let counts = perf_table_to_map(&table);
+            let time = Instant::now();
+
+            // histograms are transferred once per window
+            let window =
+                self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0);
+
+            for (i, probe) in self.probes.iter().enumerate() {
+                let stat_path = probe.stat_path();
+                if let Some(stat) = self.statistics.iter().find(|s| s.stat_path == stat_path) {
+                    let val = counts.get(&(i as u32)).unwrap_or(&0);
+                    self.common().record_counter(stat, time, *val);
+                }
+
+                if !window {
+                    continue;
+                }
+                if let Some(stat_path) = probe.argument_stat_path() {
+                    if let Some(stat) = self.statistics.iter().find(|s| s.stat_path == stat_path) {
+                        if let Some(histogram) = bpf.histogram(&format!("arg_{}", i)) {
+                            for (&value, &count) in &histogram {
+                                if count > 0 {
+                                    self.common().record_bucket(stat, time, value, count);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if window {
+                *self.bpf_last.lock().unwrap() = Instant::now();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sampler for Usdt {
+    type Statistic = UsdtStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().usdt().statistics();
+        let probes = common.config().samplers().usdt().probes();
+
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            statistics,
+            probes,
+        };
+        if sampler.sampler_config().enabled() {
+            sampler.init_bpf()?;
+            sampler.register();
+        }
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().usdt().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize usdt sampler {}", e);
+                    } else {
+                        error!("failed to initialize usdt sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().usdt()
+    }
+
+    fn reconfigure(&mut self) {
+        // probes are attached at startup, so only the probes which were
+        // attached are kept
+        let statistics = self.sampler_config().statistics();
+        self.statistics.retain(|s| statistics.contains(s));
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
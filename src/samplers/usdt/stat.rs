@@ -0,0 +1,26 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use rustcommon_metrics::{AtomicU32, AtomicU64, Source, Statistic};
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UsdtStatistic {
+    pub stat_path: String,
+    /// whether this is a histogram of a probe argument rather than a count
+    pub histogram: bool,
+}
+
+impl Statistic<AtomicU64, AtomicU32> for UsdtStatistic {
+    fn name(&self) -> &str {
+        &self.stat_path
+    }
+
+    fn source(&self) -> Source {
+        if self.histogram {
+            Source::Distribution
+        } else {
+            Source::Counter
+        }
+    }
+}
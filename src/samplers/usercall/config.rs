@@ -5,7 +5,7 @@
 use serde_derive::Deserialize;
 use std::collections::BTreeMap;
 
-use crate::config::SamplerConfig;
+use crate::config::{Histogram, SamplerConfig};
 
 use super::stat::UsercallStatistic;
 
@@ -26,8 +26,12 @@ pub struct UsercallConfig {
     #[serde(default)]
     enabled: bool,
     #[serde(default)]
+    histogram: Histogram,
+    #[serde(default)]
     interval: Option<usize>,
     #[serde(default)]
+    jitter: Option<usize>,
+    #[serde(default)]
     percentiles: Vec<f64>,
     #[serde(default)]
     libraries: Vec<LibraryProbeConfig>,
@@ -85,10 +89,18 @@ impl SamplerConfig for UsercallConfig {
         self.enabled
     }
 
+    fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
     fn interval(&self) -> Option<usize> {
         self.interval
     }
 
+    fn jitter(&self) -> Option<usize> {
+        self.jitter
+    }
+
     fn percentiles(&self) -> &[f64] {
         &self.percentiles
     }
@@ -245,7 +245,7 @@ impl Sampler for Usercall {
             match Self::new(common.clone()) {
                 Ok(mut sampler) => {
                     common.runtime().spawn(async move {
-                        loop {
+                        while !sampler.retired() {
                             let _ = sampler.sample().await;
                         }
                     });
@@ -292,9 +292,7 @@ impl Sampler for Usercall {
             let stat_map = bpf_hash_char_to_map(&table);
             for stat in self.statistics.iter() {
                 let val = stat_map.get(&stat.stat_path).unwrap_or(&0);
-                self.metrics()
-                    .record_counter(stat, Instant::now(), *val)
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                self.common().record_counter(stat, Instant::now(), *val);
             }
         }
 
@@ -0,0 +1,154 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <linux/sched.h>
+
+// operations, which select the histogram
+#define OP_READ 0
+#define OP_WRITE 1
+#define OP_OPEN 2
+#define OP_CREATE 3
+#define OP_UNLINK 4
+
+// start times are keyed by operation as well as thread, as the operations
+// may nest, such as a read by a filesystem while opening a file
+struct start_key_t {
+    u32 pid;
+    u32 op;
+};
+
+BPF_HASH(start, struct start_key_t, u64);
+
+// value_to_index2() gives us from 0-460 as the index
+BPF_HISTOGRAM(read, int, 461);
+BPF_HISTOGRAM(write, int, 461);
+BPF_HISTOGRAM(open, int, 461);
+BPF_HISTOGRAM(create, int, 461);
+BPF_HISTOGRAM(unlink, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+static int trace_entry(struct pt_regs *ctx, u32 op)
+{
+    struct start_key_t key = {};
+    key.pid = bpf_get_current_pid_tgid();
+    key.op = op;
+    u64 ts = bpf_ktime_get_ns();
+    start.update(&key, &ts);
+    return 0;
+}
+
+static int trace_return(struct pt_regs *ctx, u32 op)
+{
+    struct start_key_t key = {};
+    key.pid = bpf_get_current_pid_tgid();
+    key.op = op;
+
+    // skip events with unknown start
+    u64 *tsp = start.lookup(&key);
+    if (tsp == 0) {
+        return 0;
+    }
+
+    // calculate latency and store as histogram
+    u64 delta = (bpf_ktime_get_ns() - *tsp) / 1000;
+    unsigned int index = value_to_index2(delta);
+    if (op == OP_READ) {
+        read.increment(index);
+    } else if (op == OP_WRITE) {
+        write.increment(index);
+    } else if (op == OP_OPEN) {
+        open.increment(index);
+    } else if (op == OP_CREATE) {
+        create.increment(index);
+    } else if (op == OP_UNLINK) {
+        unlink.increment(index);
+    }
+
+    start.delete(&key);
+    return 0;
+}
+
+int trace_read_entry(struct pt_regs *ctx)
+{
+    return trace_entry(ctx, OP_READ);
+}
+
+int trace_read_return(struct pt_regs *ctx)
+{
+    return trace_return(ctx, OP_READ);
+}
+
+int trace_write_entry(struct pt_regs *ctx)
+{
+    return trace_entry(ctx, OP_WRITE);
+}
+
+int trace_write_return(struct pt_regs *ctx)
+{
+    return trace_return(ctx, OP_WRITE);
+}
+
+int trace_open_entry(struct pt_regs *ctx)
+{
+    return trace_entry(ctx, OP_OPEN);
+}
+
+int trace_open_return(struct pt_regs *ctx)
+{
+    return trace_return(ctx, OP_OPEN);
+}
+
+int trace_create_entry(struct pt_regs *ctx)
+{
+    return trace_entry(ctx, OP_CREATE);
+}
+
+int trace_create_return(struct pt_regs *ctx)
+{
+    return trace_return(ctx, OP_CREATE);
+}
+
+int trace_unlink_entry(struct pt_regs *ctx)
+{
+    return trace_entry(ctx, OP_UNLINK);
+}
+
+int trace_unlink_return(struct pt_regs *ctx)
+{
+    return trace_return(ctx, OP_UNLINK);
+}
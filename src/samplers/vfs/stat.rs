@@ -0,0 +1,69 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum VfsStatistic {
+    #[strum(serialize = "vfs/create/latency")]
+    CreateLatency,
+    #[strum(serialize = "vfs/open/latency")]
+    OpenLatency,
+    #[strum(serialize = "vfs/read/latency")]
+    ReadLatency,
+    #[strum(serialize = "vfs/unlink/latency")]
+    UnlinkLatency,
+    #[strum(serialize = "vfs/write/latency")]
+    WriteLatency,
+}
+
+impl VfsStatistic {
+    #[allow(dead_code)]
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::CreateLatency => Some("create"),
+            Self::OpenLatency => Some("open"),
+            Self::ReadLatency => Some("read"),
+            Self::UnlinkLatency => Some("unlink"),
+            Self::WriteLatency => Some("write"),
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for VfsStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        Source::Distribution
+    }
+}
+
+impl TryFrom<&str> for VfsStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        VfsStatistic::from_str(s)
+    }
+}
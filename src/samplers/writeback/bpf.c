@@ -0,0 +1,68 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+#include <uapi/linux/ptrace.h>
+#include <linux/jiffies.h>
+
+// events, which index the counters array
+#define THROTTLE_EVENTS 0
+#define THROTTLE_TIME 1
+
+BPF_ARRAY(counters, u64, 2);
+BPF_HISTOGRAM(throttle_latency, int, 461);
+
+// histogram indexing
+static unsigned int value_to_index2(unsigned int value) {
+    unsigned int index = 460;
+    if (value < 100) {
+        // 0-99 => [0..100)
+        // 0 => 0
+        // 99 => 99
+        index = value;
+    } else if (value < 1000) {
+        // 100-999 => [100..190)
+        // 100 => 100
+        // 999 => 189
+        index = 90 + value / 10;
+    } else if (value < 10000) {
+        // 1_000-9_999 => [190..280)
+        // 1000 => 190
+        // 9999 => 279
+        index = 180 + value / 100;
+    } else if (value < 100000) {
+        // 10_000-99_999 => [280..370)
+        // 10000 => 280
+        // 99999 => 369
+        index = 270 + value / 1000;
+    } else if (value < 1000000) {
+        // 100_000-999_999 => [370..460)
+        // 100000 => 370
+        // 999999 => 459
+        index = 360 + value / 10000;
+    } else {
+        index = 460;
+    }
+    return index;
+}
+
+static void add(int index, u64 value)
+{
+    u64 *count = counters.lookup(&index);
+    if (count) lock_xadd(count, value);
+}
+
+int trace_balance_dirty_pages(struct tracepoint__writeback__balance_dirty_pages *args)
+{
+    // the pause, in jiffies, is only positive when the task is put to sleep
+    // because it has dirtied pages faster than they can be written back
+    if (args->pause <= 0) {
+        return 0;
+    }
+
+    u64 delta = (u64)args->pause * 1000000 / HZ;
+    add(THROTTLE_EVENTS, 1);
+    add(THROTTLE_TIME, delta * 1000);
+    throttle_latency.increment(value_to_index2(delta));
+    return 0;
+}
@@ -0,0 +1,251 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::bpf::*;
+use crate::config::SamplerConfig;
+use crate::samplers::{Common, Sampler};
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const PROC_VMSTAT: &str = "/proc/vmstat";
+
+#[allow(dead_code)]
+pub struct Writeback {
+    bpf: Option<Arc<Mutex<BPF>>>,
+    bpf_last: Arc<Mutex<Instant>>,
+    common: Common,
+    page_size: u64,
+    statistics: Vec<WritebackStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Writeback {
+    type Statistic = WritebackStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let fault_tolerant = common.config.general().fault_tolerant();
+        let statistics = common.config().samplers().writeback().statistics();
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size <= 0 {
+            return Err(format_err!("failed to get page size"));
+        }
+
+        #[allow(unused_mut)]
+        let mut sampler = Self {
+            bpf: None,
+            bpf_last: Arc::new(Mutex::new(Instant::now())),
+            common,
+            page_size: page_size as u64,
+            statistics,
+        };
+
+        if let Err(e) = sampler.initialize_bpf() {
+            error!("{}", e);
+            if !fault_tolerant {
+                return Err(e);
+            }
+        }
+
+        if sampler.sampler_config().enabled() {
+            sampler.register();
+        }
+
+        Ok(sampler)
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().writeback().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize writeback sampler {}", e);
+                    } else {
+                        error!("failed to initialize writeback sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().writeback()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.sampler_config().statistics();
+        if self.enabled() {
+            self.register();
+        }
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_vmstat().await;
+        self.map_result(r)?;
+
+        #[cfg(feature = "bpf")]
+        {
+            let r = self.time_bpf(|s| s.sample_bpf());
+            self.map_result(r)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Writeback {
+    // checks that bpf is enabled in config and one or more bpf stats enabled
+    #[cfg(feature = "bpf")]
+    fn bpf_enabled(&self) -> bool {
+        self.sampler_config().bpf()
+            && self
+                .sampler_config()
+                .statistics()
+                .iter()
+                .any(|s| s.index().is_some() || s.bpf_table().is_some())
+    }
+
+    fn initialize_bpf(&mut self) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "bpf")]
+        {
+            if self.enabled() && self.bpf_enabled() {
+                debug!("initializing bpf");
+                let mut bpf = bcc::BPF::new(include_str!("bpf.c"))?;
+                bcc::Tracepoint::new()
+                    .handler("trace_balance_dirty_pages")
+                    .subsystem("writeback")
+                    .tracepoint("balance_dirty_pages")
+                    .attach(&mut bpf)?;
+                self.bpf = Some(Arc::new(Mutex::new(BPF { inner: bpf })));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Samples the dirty and writeback page counts, the dirty limits, and the
+    /// pages dirtied and written from `/proc/vmstat`
+    async fn sample_vmstat(&self) -> Result<(), std::io::Error> {
+        let content = tokio::fs::read_to_string(PROC_VMSTAT).await?;
+        let vmstat = parse_vmstat(&content);
+        let time = Instant::now();
+        for statistic in &self.statistics {
+            if let Some(value) = statistic.vmstat().and_then(|key| vmstat.get(key)) {
+                match statistic {
+                    WritebackStatistic::DirtiedBytes | WritebackStatistic::WrittenBytes => {
+                        self.common()
+                            .record_counter(statistic, time, value * self.page_size);
+                    }
+                    _ => {
+                        let _ = self.metrics().record_gauge(statistic, time, *value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bpf")]
+    fn sample_bpf(&self) -> Result<(), std::io::Error> {
+        if let Some(ref bpf) = self.bpf {
+            let bpf = bpf.lock().unwrap();
+            if let Some(counters) = bpf.counters("counters") {
+                let time = Instant::now();
+                for statistic in &self.statistics {
+                    if let Some(index) = statistic.index() {
+                        let value = counters.get(&index).copied().unwrap_or(0);
+                        self.common().record_counter(statistic, time, value);
+                    }
+                }
+            }
+        }
+
+        // latency histograms are transferred once per window
+        if self.bpf_last.lock().unwrap().elapsed() >= Duration::new(self.window() as u64, 0) {
+            if let Some(ref bpf) = self.bpf {
+                let bpf = bpf.lock().unwrap();
+                let time = Instant::now();
+                for statistic in self.statistics.iter().filter(|s| s.bpf_table().is_some()) {
+                    if let Some(histogram) = bpf.histogram(statistic.bpf_table().unwrap()) {
+                        for (&value, &count) in &histogram {
+                            if count > 0 {
+                                self.common().record_bucket(
+                                    statistic,
+                                    time,
+                                    value * crate::MICROSECOND,
+                                    count,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            *self.bpf_last.lock().unwrap() = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// Parses the contents of `/proc/vmstat` into its values by key
+fn parse_vmstat(content: &str) -> HashMap<&str, u64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let key = parts.next()?;
+            let value = parts.next()?.parse().ok()?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_vmstat() {
+        let content = "nr_dirty 1234\nnr_writeback 0\nnr_dirty_threshold 1592186\nbogus\n";
+        let vmstat = parse_vmstat(content);
+        assert_eq!(vmstat.get("nr_dirty"), Some(&1234));
+        assert_eq!(vmstat.get("nr_writeback"), Some(&0));
+        assert_eq!(vmstat.get("nr_dirty_threshold"), Some(&1592186));
+        assert_eq!(vmstat.get("bogus"), None);
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum WritebackStatistic {
+    #[strum(serialize = "writeback/dirtied/bytes")]
+    DirtiedBytes,
+    #[strum(serialize = "writeback/pages/dirty")]
+    PagesDirty,
+    #[strum(serialize = "writeback/pages/writeback")]
+    PagesWriteback,
+    #[strum(serialize = "writeback/threshold/background")]
+    ThresholdBackground,
+    #[strum(serialize = "writeback/threshold/dirty")]
+    ThresholdDirty,
+    #[strum(serialize = "writeback/throttle/events")]
+    ThrottleEvents,
+    #[strum(serialize = "writeback/throttle/latency")]
+    ThrottleLatency,
+    #[strum(serialize = "writeback/throttle/time")]
+    ThrottleTime,
+    #[strum(serialize = "writeback/written/bytes")]
+    WrittenBytes,
+}
+
+impl WritebackStatistic {
+    /// The key of the statistic in `/proc/vmstat`
+    pub fn vmstat(self) -> Option<&'static str> {
+        match self {
+            Self::DirtiedBytes => Some("nr_dirtied"),
+            Self::PagesDirty => Some("nr_dirty"),
+            Self::PagesWriteback => Some("nr_writeback"),
+            Self::ThresholdBackground => Some("nr_dirty_background_threshold"),
+            Self::ThresholdDirty => Some("nr_dirty_threshold"),
+            Self::WrittenBytes => Some("nr_written"),
+            _ => None,
+        }
+    }
+
+    /// The index of the counter in the `counters` BPF array
+    pub fn index(self) -> Option<u32> {
+        match self {
+            Self::ThrottleEvents => Some(0),
+            Self::ThrottleTime => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn bpf_table(self) -> Option<&'static str> {
+        match self {
+            Self::ThrottleLatency => Some("throttle_latency"),
+            _ => None,
+        }
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for WritebackStatistic {
+    fn name(&self) -> &str {
+        (*self).into()
+    }
+
+    fn source(&self) -> Source {
+        match self {
+            Self::ThrottleLatency => Source::Distribution,
+            Self::PagesDirty
+            | Self::PagesWriteback
+            | Self::ThresholdBackground
+            | Self::ThresholdDirty => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for WritebackStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        WritebackStatistic::from_str(s)
+    }
+}
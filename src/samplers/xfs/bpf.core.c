@@ -0,0 +1,94 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// CO-RE version of bpf.c, built with the bpf_core feature
+// Based on: https://github.com/iovisor/bcc/blob/master/libbpf-tools/xfsdist.bpf.c
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+#include "histogram.h"
+
+HASH(start, u32, u64);
+
+HISTOGRAM(read);
+HISTOGRAM(write);
+HISTOGRAM(open);
+HISTOGRAM(fsync);
+
+static __always_inline int trace_entry(void)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+    u64 ts = bpf_ktime_get_ns();
+    bpf_map_update_elem(&start, &pid, &ts, BPF_ANY);
+    return 0;
+}
+
+static __always_inline int trace_return(void *histogram)
+{
+    u32 pid = bpf_get_current_pid_tgid();
+
+    // skip events with unknown start
+    u64 *tsp = bpf_map_lookup_elem(&start, &pid);
+    if (!tsp) {
+        return 0;
+    }
+
+    // record latency in microseconds
+    histogram_add(histogram, (bpf_ktime_get_ns() - *tsp) / 1000);
+
+    bpf_map_delete_elem(&start, &pid);
+    return 0;
+}
+
+SEC("kprobe/xfs_file_read_iter")
+int BPF_KPROBE(read_entry)
+{
+    return trace_entry();
+}
+
+SEC("kprobe/xfs_file_write_iter")
+int BPF_KPROBE(write_entry)
+{
+    return trace_entry();
+}
+
+SEC("kprobe/xfs_file_open")
+int BPF_KPROBE(open_entry)
+{
+    return trace_entry();
+}
+
+SEC("kprobe/xfs_file_fsync")
+int BPF_KPROBE(fsync_entry)
+{
+    return trace_entry();
+}
+
+SEC("kretprobe/xfs_file_read_iter")
+int BPF_KRETPROBE(read_return)
+{
+    return trace_return(&read);
+}
+
+SEC("kretprobe/xfs_file_write_iter")
+int BPF_KRETPROBE(write_return)
+{
+    return trace_return(&write);
+}
+
+SEC("kretprobe/xfs_file_open")
+int BPF_KRETPROBE(open_return)
+{
+    return trace_return(&open);
+}
+
+SEC("kretprobe/xfs_file_fsync")
+int BPF_KRETPROBE(fsync_return)
+{
+    return trace_return(&fsync);
+}
+
+char LICENSE[] SEC("license") = "GPL";
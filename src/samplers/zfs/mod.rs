@@ -0,0 +1,393 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use std::collections::{HashMap, HashSet};
+use std::time::*;
+
+use async_trait::async_trait;
+use rustcommon_metrics::*;
+
+use crate::common::command_output;
+use crate::config::SamplerConfig;
+use crate::samplers::Common;
+use crate::Sampler;
+
+mod config;
+mod stat;
+
+pub use config::*;
+pub use stat::*;
+
+const KSTAT_ZFS: &str = "/proc/spl/kstat/zfs";
+
+pub struct Zfs {
+    common: Common,
+    /// ARC hits and misses from the previous sample, used to find the hit
+    /// ratio of the last interval
+    previous: Option<(u64, u64)>,
+    /// statistics which have been registered, as pools are discovered at
+    /// runtime
+    registered: HashSet<ZfsStatistic>,
+    statistics: Vec<ZfsConfigStatistic>,
+}
+
+#[async_trait]
+impl Sampler for Zfs {
+    type Statistic = ZfsStatistic;
+
+    fn new(common: Common) -> Result<Self, anyhow::Error> {
+        let statistics = common.config().samplers().zfs().statistics.clone();
+        Ok(Self {
+            common,
+            previous: None,
+            registered: HashSet::new(),
+            statistics,
+        })
+    }
+
+    fn spawn(common: Common) {
+        if common.config().samplers().zfs().enabled() {
+            match Self::new(common.clone()) {
+                Ok(mut sampler) => {
+                    common.runtime().spawn(async move {
+                        while !sampler.retired() {
+                            let _ = sampler.sample().await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if !common.config.fault_tolerant() {
+                        fatal!("failed to initialize zfs sampler {}", e);
+                    } else {
+                        error!("failed to initialize zfs sampler {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn common(&self) -> &Common {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut Common {
+        &mut self.common
+    }
+
+    fn sampler_config(&self) -> &dyn SamplerConfig<Statistic = Self::Statistic> {
+        self.common.config().samplers().zfs()
+    }
+
+    fn reconfigure(&mut self) {
+        self.statistics = self.common.config().samplers().zfs().statistics.clone();
+        // register the new set of statistics on the next sample
+        self.registered.clear();
+    }
+
+    async fn sample(&mut self) -> Result<(), std::io::Error> {
+        if let Some(ref mut delay) = self.delay() {
+            delay.tick().await;
+        }
+
+        if !self.sampler_config().enabled() {
+            return Ok(());
+        }
+
+        debug!("sampling");
+
+        let r = self.sample_arc().await;
+        self.map_result(r)?;
+
+        let r = self.sample_pool_io().await;
+        self.map_result(r)?;
+
+        let r = self.sample_pool_errors().await;
+        self.map_result(r)?;
+
+        Ok(())
+    }
+}
+
+impl Zfs {
+    fn record(&mut self, statistic: ZfsStatistic, time: Instant, value: u64) {
+        if !self.registered.contains(&statistic) {
+            let percentiles = self.sampler_config().percentiles();
+            self.metrics().register(&statistic);
+            self.metrics().add_output(&statistic, Output::Reading);
+            if !percentiles.is_empty() {
+                self.metrics()
+                    .add_summary(&statistic, Summary::stream(self.samples()));
+            }
+            for percentile in percentiles {
+                self.metrics()
+                    .add_output(&statistic, Output::Percentile(*percentile));
+            }
+            self.registered.insert(statistic.clone());
+        }
+        match statistic.statistic().source() {
+            Source::Counter => {
+                self.common().record_counter(&statistic, time, value);
+            }
+            Source::Gauge => {
+                let _ = self.metrics().record_gauge(&statistic, time, value);
+            }
+            _ => {}
+        }
+    }
+
+    async fn sample_arc(&mut self) -> Result<(), std::io::Error> {
+        if self.statistics.iter().all(|s| s.pool()) {
+            return Ok(());
+        }
+        let content = tokio::fs::read_to_string(format!("{}/arcstats", KSTAT_ZFS)).await?;
+        let arcstats = parse_named_kstat(&content);
+        let time = Instant::now();
+
+        let current = match (arcstats.get("hits"), arcstats.get("misses")) {
+            (Some(hits), Some(misses)) => Some((*hits, *misses)),
+            _ => None,
+        };
+        let previous = self.previous;
+        self.previous = current;
+
+        for statistic in self.statistics.clone() {
+            let value = if statistic == ZfsConfigStatistic::ArcHitRatio {
+                match (current, previous) {
+                    (Some(current), Some(previous)) => hit_ratio(current, previous),
+                    _ => None,
+                }
+            } else {
+                statistic.arcstat().and_then(|f| arcstats.get(f).copied())
+            };
+            if let Some(value) = value {
+                self.record(ZfsStatistic::arc(statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the `io` kstat of each pool, which was removed in OpenZFS 2.0,
+    /// so pools without it are skipped
+    async fn sample_pool_io(&mut self) -> Result<(), std::io::Error> {
+        if !self.statistics.iter().any(|s| s.pool_io().is_some()) {
+            return Ok(());
+        }
+        let mut entries = tokio::fs::read_dir(KSTAT_ZFS).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let pool = match entry.file_name().into_string() {
+                Ok(pool) => pool,
+                Err(_) => continue,
+            };
+            let content = match tokio::fs::read_to_string(entry.path().join("io")).await {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let io = parse_io_kstat(&content);
+            let time = Instant::now();
+            for statistic in self.statistics.clone() {
+                if let Some(value) = statistic.pool_io().and_then(|f| io.get(f)) {
+                    self.record(ZfsStatistic::pool(&pool, statistic), time, *value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the error counters of each pool. These are not exposed through
+    /// kstats, so they are read from the output of `zpool status`.
+    async fn sample_pool_errors(&mut self) -> Result<(), std::io::Error> {
+        let statistics: Vec<ZfsConfigStatistic> = self
+            .statistics
+            .iter()
+            .filter(|s| s.pool() && s.pool_io().is_none())
+            .copied()
+            .collect();
+        if statistics.is_empty() {
+            return Ok(());
+        }
+        let output =
+            command_output(tokio::process::Command::new("zpool").args(&["status", "-p"])).await?;
+        if !output.status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("zpool status failed: {}", output.status),
+            ));
+        }
+        let time = Instant::now();
+        for (pool, errors) in parse_zpool_status(&String::from_utf8_lossy(&output.stdout)) {
+            for statistic in &statistics {
+                let value = match statistic {
+                    ZfsConfigStatistic::PoolErrorsChecksum => errors.checksum,
+                    ZfsConfigStatistic::PoolErrorsRead => errors.read,
+                    ZfsConfigStatistic::PoolErrorsWrite => errors.write,
+                    _ => continue,
+                };
+                self.record(ZfsStatistic::pool(&pool, *statistic), time, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The ARC hit ratio, in hundredths of a percent, between two readings of the
+/// (hits, misses) counters
+fn hit_ratio(current: (u64, u64), previous: (u64, u64)) -> Option<u64> {
+    let hits = current.0.checked_sub(previous.0)?;
+    let misses = current.1.checked_sub(previous.1)?;
+    let total = hits + misses;
+    if total == 0 {
+        return None;
+    }
+    Some(hits * 10_000 / total)
+}
+
+/// Parses a named kstat, such as `arcstats`, which has a header line and a
+/// column header, followed by a `name type data` line for each value
+fn parse_named_kstat(content: &str) -> HashMap<String, u64> {
+    let mut result = HashMap::new();
+    for line in content.lines().skip(2) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() == 3 {
+            if let Ok(value) = parts[2].parse() {
+                result.insert(parts[0].to_string(), value);
+            }
+        }
+    }
+    result
+}
+
+/// Parses an io kstat, which has a header line, followed by a line of field
+/// names and a line of their values
+fn parse_io_kstat(content: &str) -> HashMap<String, u64> {
+    let mut lines = content.lines().skip(1);
+    let (names, values) = match (lines.next(), lines.next()) {
+        (Some(names), Some(values)) => (names, values),
+        _ => return HashMap::new(),
+    };
+    names
+        .split_whitespace()
+        .zip(values.split_whitespace())
+        .filter_map(|(name, value)| value.parse().ok().map(|v| (name.to_string(), v)))
+        .collect()
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct PoolErrors {
+    read: u64,
+    write: u64,
+    checksum: u64,
+}
+
+/// Parses the output of `zpool status -p`, summing the error counters of each
+/// vdev in the config of each pool
+fn parse_zpool_status(content: &str) -> HashMap<String, PoolErrors> {
+    let mut result = HashMap::new();
+    let mut pool: Option<String> = None;
+    let mut config = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix("pool:") {
+            pool = Some(name.trim().to_string());
+            config = false;
+            continue;
+        }
+        if trimmed.starts_with("NAME") {
+            config = true;
+            continue;
+        }
+        if trimmed.starts_with("errors:") {
+            config = false;
+            continue;
+        }
+        let pool = match (&pool, config) {
+            (Some(pool), true) => pool,
+            _ => continue,
+        };
+        // rows are `name state read write cksum`, and may be followed by a
+        // note, while section headers such as `logs` and spares have no
+        // counters
+        let parts: Vec<&str> = trimmed.split_whitespace().collect();
+        if parts.len() < 5 {
+            continue;
+        }
+        if let (Ok(read), Ok(write), Ok(checksum)) = (
+            parts[2].parse::<u64>(),
+            parts[3].parse::<u64>(),
+            parts[4].parse::<u64>(),
+        ) {
+            let errors: &mut PoolErrors = result.entry(pool.clone()).or_default();
+            errors.read += read;
+            errors.write += write;
+            errors.checksum += checksum;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_arcstats() {
+        let arcstats = parse_named_kstat(
+            "13 1 0x01 123 33456 1234567 7654321\n\
+             name                            type data\n\
+             hits                            4    9000\n\
+             misses                          4    1000\n\
+             c                               4    4294967296\n\
+             size                            4    2147483648\n",
+        );
+        assert_eq!(arcstats.get("hits"), Some(&9000));
+        assert_eq!(arcstats.get("c"), Some(&4294967296));
+        assert_eq!(arcstats.len(), 4);
+        assert_eq!(hit_ratio((9000, 1000), (8000, 900)), Some(9090));
+        assert_eq!(hit_ratio((9000, 1000), (9000, 1000)), None);
+    }
+
+    #[test]
+    fn test_parse_io_kstat() {
+        let io = parse_io_kstat(
+            "12 3 0x00 1 80 2376546423 3895629542945\n\
+             nread    nwritten reads    writes   wtime    wlentime wupdate  \
+             rtime    rlentime rupdate  wcnt     rcnt\n\
+             4096     8192     1        2        0        0        0        \
+             0        0        0        0        0\n",
+        );
+        assert_eq!(io.get("nread"), Some(&4096));
+        assert_eq!(io.get("writes"), Some(&2));
+    }
+
+    #[test]
+    fn test_parse_zpool_status() {
+        let pools = parse_zpool_status(
+            "  pool: tank\n\
+             \x20state: ONLINE\n\
+             config:\n\
+             \n\
+             \tNAME        STATE     READ WRITE CKSUM\n\
+             \ttank        ONLINE       0     0     0\n\
+             \t  mirror-0  ONLINE       0     0     0\n\
+             \t    sda     ONLINE       0     0     3\n\
+             \t    sdb     ONLINE       1     0     0  (repairing)\n\
+             \tlogs\n\
+             \t  nvme0n1   ONLINE       0     2     0\n\
+             \tspares\n\
+             \t  sdc       AVAIL\n\
+             \n\
+             errors: No known data errors\n",
+        );
+        assert_eq!(
+            pools.get("tank"),
+            Some(&PoolErrors {
+                read: 1,
+                write: 2,
+                checksum: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2021 Twitter, Inc.
+// Licensed under the Apache License, Version 2.0
+// http://www.apache.org/licenses/LICENSE-2.0
+
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use rustcommon_metrics::*;
+use serde_derive::{Deserialize, Serialize};
+use strum::ParseError;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use crate::common::labels::labeled;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    IntoStaticStr,
+    PartialEq,
+    Hash,
+    Serialize,
+)]
+#[serde(deny_unknown_fields, try_from = "&str", into = "&str")]
+pub enum ZfsConfigStatistic {
+    #[strum(serialize = "arc/evictions")]
+    ArcEvictions,
+    #[strum(serialize = "arc/hit_ratio")]
+    ArcHitRatio,
+    #[strum(serialize = "arc/hits")]
+    ArcHits,
+    #[strum(serialize = "arc/l2/hits")]
+    ArcL2Hits,
+    #[strum(serialize = "arc/l2/misses")]
+    ArcL2Misses,
+    #[strum(serialize = "arc/l2/size")]
+    ArcL2Size,
+    #[strum(serialize = "arc/max_size")]
+    ArcMaxSize,
+    #[strum(serialize = "arc/misses")]
+    ArcMisses,
+    #[strum(serialize = "arc/size")]
+    ArcSize,
+    #[strum(serialize = "arc/target_size")]
+    ArcTargetSize,
+    #[strum(serialize = "pool/errors/checksum")]
+    PoolErrorsChecksum,
+    #[strum(serialize = "pool/errors/read")]
+    PoolErrorsRead,
+    #[strum(serialize = "pool/errors/write")]
+    PoolErrorsWrite,
+    #[strum(serialize = "pool/read/bytes")]
+    PoolReadBytes,
+    #[strum(serialize = "pool/read/operations")]
+    PoolReadOperations,
+    #[strum(serialize = "pool/write/bytes")]
+    PoolWriteBytes,
+    #[strum(serialize = "pool/write/operations")]
+    PoolWriteOperations,
+}
+
+impl ZfsConfigStatistic {
+    /// The field of `arcstats` this statistic is read from
+    pub fn arcstat(self) -> Option<&'static str> {
+        match self {
+            Self::ArcEvictions => Some("deleted"),
+            Self::ArcHits => Some("hits"),
+            Self::ArcL2Hits => Some("l2_hits"),
+            Self::ArcL2Misses => Some("l2_misses"),
+            Self::ArcL2Size => Some("l2_size"),
+            Self::ArcMaxSize => Some("c_max"),
+            Self::ArcMisses => Some("misses"),
+            Self::ArcSize => Some("size"),
+            Self::ArcTargetSize => Some("c"),
+            _ => None,
+        }
+    }
+
+    /// The field of the pool `io` kstat this statistic is read from
+    pub fn pool_io(self) -> Option<&'static str> {
+        match self {
+            Self::PoolReadBytes => Some("nread"),
+            Self::PoolReadOperations => Some("reads"),
+            Self::PoolWriteBytes => Some("nwritten"),
+            Self::PoolWriteOperations => Some("writes"),
+            _ => None,
+        }
+    }
+
+    /// Whether this statistic is reported for each pool, rather than for the
+    /// ARC, which is shared by all pools
+    pub fn pool(self) -> bool {
+        let name: &str = self.into();
+        name.starts_with("pool/")
+    }
+
+    pub fn source(self) -> Source {
+        match self {
+            Self::ArcHitRatio
+            | Self::ArcL2Size
+            | Self::ArcMaxSize
+            | Self::ArcSize
+            | Self::ArcTargetSize => Source::Gauge,
+            _ => Source::Counter,
+        }
+    }
+}
+
+impl TryFrom<&str> for ZfsConfigStatistic {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ZfsConfigStatistic::from_str(s)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ZfsStatistic {
+    name: String,
+    statistic: ZfsConfigStatistic,
+}
+
+impl ZfsStatistic {
+    /// A statistic of the ARC, which is named `zfs/arc/...`
+    pub fn arc(statistic: ZfsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: format!("zfs/{}", suffix),
+            statistic,
+        }
+    }
+
+    /// A statistic of a pool, which is named `zfs/<pool>/...`
+    pub fn pool(pool: &str, statistic: ZfsConfigStatistic) -> Self {
+        let suffix: &str = statistic.into();
+        Self {
+            name: labeled(
+                format!("zfs/{}/{}", pool, suffix.trim_start_matches("pool/")),
+                format!("zfs/{}", suffix),
+                &[("pool", pool)],
+            ),
+            statistic,
+        }
+    }
+
+    pub fn statistic(&self) -> ZfsConfigStatistic {
+        self.statistic
+    }
+}
+
+impl Statistic<AtomicU64, AtomicU32> for ZfsStatistic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Source {
+        self.statistic.source()
+    }
+}